@@ -0,0 +1,62 @@
+// Hot-path arithmetic benchmark: price parsing and fixed-point helpers.
+//
+//   cargo bench --bench price
+//   cargo bench --bench price --features unchecked-arith
+//
+// Plain timing loop (no harness dependency). Results are checked against the
+// expected values first, so both arithmetic modes are known to agree on
+// in-range inputs before their speed is compared.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use binance_futures_writer::{fixed, price};
+
+/// Typical bookTicker prices: (input, 1e8-scaled value)
+const PRICES: [(&str, i64); 8] = [
+    ("65000.10", 6_500_010_000_000),
+    ("3450.27", 345_027_000_000),
+    ("0.00001234", 1_234),
+    ("1.23450000", 123_450_000),
+    ("0.5123", 51_230_000),
+    ("27.389", 2_738_900_000),
+    ("0.000000005", 1),
+    ("105432.9", 10_543_290_000_000),
+];
+
+const ROUNDS: u32 = 1_000_000;
+
+/// ns per call of `f` over `ROUNDS` rounds of `n` calls
+fn time(n: usize, mut f: impl FnMut()) -> f64 {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    start.elapsed().as_nanos() as f64 / (ROUNDS as f64 * n as f64)
+}
+
+fn main() {
+    for (s, expected) in PRICES {
+        assert_eq!(price::parse_price_i64_1e8(s).unwrap(), expected, "{}", s);
+    }
+    assert_eq!(fixed::bps_diff(6_500_000_000_000, 6_510_000_000_000), Some(15));
+    assert_eq!(fixed::percent_change(200_000_000, 203_000_000), Some(150_000_000));
+
+    println!("arithmetic: {}", fixed::arith_mode());
+
+    let parse = time(PRICES.len(), || {
+        for (s, _) in PRICES {
+            black_box(price::parse_price_i64_1e8(black_box(s)).ok());
+        }
+    });
+    println!("parse_price_i64_1e8: {:.2} ns/op", parse);
+
+    let values: Vec<i64> = PRICES.iter().map(|&(_, v)| v).collect();
+    let derived = time(values.len() - 1, || {
+        for pair in values.windows(2) {
+            let (a, b) = (black_box(pair[0]), black_box(pair[1]));
+            black_box((fixed::mid(a, b), fixed::spread(a, b), fixed::bps_diff(a, b), fixed::percent_change(a, b)));
+        }
+    });
+    println!("mid+spread+bps+percent: {:.2} ns/op", derived);
+}
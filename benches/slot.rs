@@ -0,0 +1,58 @@
+// Hot-path slot write benchmark: resolve a slot, parse bid/ask, write the quote.
+//
+//   cargo bench --bench slot
+//   cargo bench --bench slot --features prefetch
+//
+// Plain timing loop (no harness dependency). Symbols are drawn at random from
+// a universe much larger than the caches, so the slot's line is usually cold:
+// the case the `prefetch` feature targets. A small universe that stays cached
+// is timed too, to show what the prefetch costs when the line is already there.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use std::sync::Arc;
+
+use binance_futures_writer::{price, shm::ShmManager, writer::WriterToken};
+
+/// Typical bookTicker prices
+const PRICES: [(&str, &str); 4] = [("65000.10", "65000.20"), ("3450.27", "3450.28"), ("0.5123", "0.5124"), ("27.389", "27.390")];
+
+const ROUNDS: u64 = 2_000_000;
+
+/// ns per quote written over `ROUNDS` random slots of an `n_symbols` universe
+fn time(token: &WriterToken, n_symbols: u64) -> f64 {
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let start = Instant::now();
+    for i in 0..ROUNDS {
+        // xorshift: cheap and not predictable by the hardware prefetcher
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let slot = token.writer(state % n_symbols).unwrap();
+        slot.prefetch();
+
+        let (bid, ask) = PRICES[(i % PRICES.len() as u64) as usize];
+        let bid = price::parse_price_i64_1e8(black_box(bid)).unwrap();
+        let ask = price::parse_price_i64_1e8(black_box(ask)).unwrap();
+        slot.write(bid, ask, i as i64);
+    }
+    start.elapsed().as_nanos() as f64 / ROUNDS as f64
+}
+
+fn main() {
+    println!("prefetch: {}", if cfg!(feature = "prefetch") { "on" } else { "off" });
+
+    for n_symbols in [64, 1 << 20] {
+        let shm = Arc::new(ShmManager::anonymous(1, n_symbols).unwrap());
+        let ids: Vec<u64> = (0..n_symbols).collect();
+        for &symbol_id in &ids {
+            shm.init_slot(0, symbol_id).unwrap();
+        }
+        let token = WriterToken::claim(shm, "bench", 0, &ids).unwrap();
+        // Warm-up pass faults the mapping in, so page faults aren't timed
+        time(&token, n_symbols);
+        let ns = time(&token, n_symbols);
+        println!("{} slots ({} KiB): {:.2} ns/quote", n_symbols, n_symbols * 64 / 1024, ns);
+    }
+}
@@ -0,0 +1,170 @@
+/* quotes_shm.h - layout of the quotes SHM file for non-Rust readers
+ *
+ * Generated by `cargo run --bin shm_header` from src/shm.rs; do not edit.
+ * All fields are little-endian. A slot is read with the seqlock protocol in
+ * qshm_read_quote(): never trust a Quote64 copied without it.
+ */
+#ifndef QUOTES_SHM_H
+#define QUOTES_SHM_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+#define QSHM_STATIC_ASSERT(cond, msg) static_assert(cond, msg)
+extern "C" {
+#else
+#define QSHM_STATIC_ASSERT(cond, msg) _Static_assert(cond, msg)
+#endif
+
+#define QSHM_MAGIC "QSHM1\0\0\0"
+#define QSHM_HEADER_SIZE 4096ULL
+#define QSHM_RECORD_SIZE 64ULL
+#define QSHM_RECORDS_OFFSET 4096ULL
+#define QSHM_PRICE_SCALE 100000000ULL
+#define QSHM_TS_SCALE 1000000ULL
+#define QSHM_CHECKSUMMED_HEADER_BYTES 88ULL
+#define QSHM_SOURCE_STATE_OFFSET 1024ULL
+#define QSHM_SOURCE_STATE_SIZE 128ULL
+#define QSHM_MAX_HEADER_SOURCES 24ULL
+#define QSHM_SOURCE_FLAG_WRITER_FAULT 1ULL
+
+/* Quote64.status bits */
+#define QSHM_SLOT_STATUS_INIT 1u
+#define QSHM_SLOT_STATUS_STALE 2u
+#define QSHM_SLOT_STATUS_CROSSED 4u
+#define QSHM_SLOT_STATUS_ZERO_PRICE 8u
+#define QSHM_SLOT_STATUS_HALTED 16u
+
+/* First bytes of the 4096-byte header; header_crc32 covers bytes 0..88 (0 = no checksum) */
+typedef struct qshm_header {
+    uint8_t magic[8];               /* offset 0 */
+    uint64_t version;               /* offset 8 */
+    uint64_t header_size;           /* offset 16 */
+    uint64_t record_size;           /* offset 24 */
+    uint64_t records_offset;        /* offset 32 */
+    uint64_t price_scale;           /* offset 40 */
+    uint64_t ts_scale;              /* offset 48 */
+    uint64_t n_sources;             /* offset 56 */
+    uint64_t n_symbols;             /* offset 64 */
+    uint64_t n_records;             /* offset 72 */
+    uint64_t shm_total_size;        /* offset 80 */
+    uint64_t header_crc32;          /* offset 88 */
+} qshm_header;
+
+QSHM_STATIC_ASSERT(sizeof(qshm_header) == 96, "qshm_header size");
+QSHM_STATIC_ASSERT(offsetof(qshm_header, magic) == 0, "qshm_header.magic");
+QSHM_STATIC_ASSERT(offsetof(qshm_header, version) == 8, "qshm_header.version");
+QSHM_STATIC_ASSERT(offsetof(qshm_header, header_size) == 16, "qshm_header.header_size");
+QSHM_STATIC_ASSERT(offsetof(qshm_header, record_size) == 24, "qshm_header.record_size");
+QSHM_STATIC_ASSERT(offsetof(qshm_header, records_offset) == 32, "qshm_header.records_offset");
+QSHM_STATIC_ASSERT(offsetof(qshm_header, price_scale) == 40, "qshm_header.price_scale");
+QSHM_STATIC_ASSERT(offsetof(qshm_header, ts_scale) == 48, "qshm_header.ts_scale");
+QSHM_STATIC_ASSERT(offsetof(qshm_header, n_sources) == 56, "qshm_header.n_sources");
+QSHM_STATIC_ASSERT(offsetof(qshm_header, n_symbols) == 64, "qshm_header.n_symbols");
+QSHM_STATIC_ASSERT(offsetof(qshm_header, n_records) == 72, "qshm_header.n_records");
+QSHM_STATIC_ASSERT(offsetof(qshm_header, shm_total_size) == 80, "qshm_header.shm_total_size");
+QSHM_STATIC_ASSERT(offsetof(qshm_header, header_crc32) == 88, "qshm_header.header_crc32");
+
+/* Writer state of source s at QSHM_SOURCE_STATE_OFFSET + s * QSHM_SOURCE_STATE_SIZE */
+typedef struct qshm_source_state {
+    uint64_t flags;                 /* offset 0 */
+    uint64_t started_at_unix_us;    /* offset 8 */
+    char writer_version[16];        /* offset 16 */
+    char git_sha[16];               /* offset 32 */
+    char source_name[32];           /* offset 48 */
+    uint64_t heartbeat_seq;         /* offset 80 */
+    uint64_t heartbeat_mono_us;     /* offset 88 */
+    uint64_t heartbeat_unix_us;     /* offset 96 */
+    uint64_t reserved[3];           /* offset 104 */
+} qshm_source_state;
+
+QSHM_STATIC_ASSERT(sizeof(qshm_source_state) == 128, "qshm_source_state size");
+QSHM_STATIC_ASSERT(offsetof(qshm_source_state, flags) == 0, "qshm_source_state.flags");
+QSHM_STATIC_ASSERT(offsetof(qshm_source_state, started_at_unix_us) == 8, "qshm_source_state.started_at_unix_us");
+QSHM_STATIC_ASSERT(offsetof(qshm_source_state, writer_version) == 16, "qshm_source_state.writer_version");
+QSHM_STATIC_ASSERT(offsetof(qshm_source_state, git_sha) == 32, "qshm_source_state.git_sha");
+QSHM_STATIC_ASSERT(offsetof(qshm_source_state, source_name) == 48, "qshm_source_state.source_name");
+QSHM_STATIC_ASSERT(offsetof(qshm_source_state, heartbeat_seq) == 80, "qshm_source_state.heartbeat_seq");
+QSHM_STATIC_ASSERT(offsetof(qshm_source_state, heartbeat_mono_us) == 88, "qshm_source_state.heartbeat_mono_us");
+QSHM_STATIC_ASSERT(offsetof(qshm_source_state, heartbeat_unix_us) == 96, "qshm_source_state.heartbeat_unix_us");
+QSHM_STATIC_ASSERT(offsetof(qshm_source_state, reserved) == 104, "qshm_source_state.reserved");
+
+/* Slot of (source_id, symbol_id) at QSHM_RECORDS_OFFSET + (source_id * n_symbols + symbol_id) * 64 */
+typedef struct qshm_quote64 {
+    uint64_t seq;                   /* offset 0 */
+    uint64_t source_id;             /* offset 8 */
+    uint64_t symbol_id;             /* offset 16 */
+    int64_t bid;                    /* offset 24 */
+    int64_t ask;                    /* offset 32 */
+    int64_t ts;                     /* offset 40 */
+    uint32_t price_scale;           /* offset 48 */
+    uint32_t status;                /* offset 52 */
+    uint64_t owner;                 /* offset 56 */
+} qshm_quote64;
+
+QSHM_STATIC_ASSERT(sizeof(qshm_quote64) == 64, "qshm_quote64 size");
+QSHM_STATIC_ASSERT(offsetof(qshm_quote64, seq) == 0, "qshm_quote64.seq");
+QSHM_STATIC_ASSERT(offsetof(qshm_quote64, source_id) == 8, "qshm_quote64.source_id");
+QSHM_STATIC_ASSERT(offsetof(qshm_quote64, symbol_id) == 16, "qshm_quote64.symbol_id");
+QSHM_STATIC_ASSERT(offsetof(qshm_quote64, bid) == 24, "qshm_quote64.bid");
+QSHM_STATIC_ASSERT(offsetof(qshm_quote64, ask) == 32, "qshm_quote64.ask");
+QSHM_STATIC_ASSERT(offsetof(qshm_quote64, ts) == 40, "qshm_quote64.ts");
+QSHM_STATIC_ASSERT(offsetof(qshm_quote64, price_scale) == 48, "qshm_quote64.price_scale");
+QSHM_STATIC_ASSERT(offsetof(qshm_quote64, status) == 52, "qshm_quote64.status");
+QSHM_STATIC_ASSERT(offsetof(qshm_quote64, owner) == 56, "qshm_quote64.owner");
+
+/* Consistent copy of a slot */
+typedef struct qshm_quote {
+    uint64_t source_id;
+    uint64_t symbol_id;
+    int64_t bid;        /* * price_scale (0 = QSHM_PRICE_SCALE) */
+    int64_t ask;
+    int64_t ts;         /* writer monotonic µs */
+    uint32_t price_scale;
+    uint32_t status;
+} qshm_quote;
+
+static inline const qshm_quote64 *qshm_slot(const void *base, uint64_t source_id, uint64_t symbol_id) {
+    const qshm_header *header = (const qshm_header *)base;
+    if (source_id >= header->n_sources || symbol_id >= header->n_symbols) {
+        return NULL;
+    }
+    return (const qshm_quote64 *)((const char *)base + QSHM_RECORDS_OFFSET) + source_id * header->n_symbols + symbol_id;
+}
+
+/*
+ * Seqlock read (the writer makes seq odd, stores the fields, makes it even):
+ *   1. s1 = seq (acquire); odd means a write is in progress: retry
+ *   2. copy the fields
+ *   3. acquire fence, then re-read seq: unchanged means the copy is consistent
+ * seq 0 means the slot was never written. Returns 1 on success, 0 if the slot
+ * stayed busy for `max_retries` attempts or was never written.
+ */
+static inline int qshm_read_quote(const qshm_quote64 *slot, qshm_quote *out, int max_retries) {
+    for (int i = 0; i < max_retries; i++) {
+        uint64_t s1 = __atomic_load_n(&slot->seq, __ATOMIC_ACQUIRE);
+        if (s1 & 1) {
+            continue;
+        }
+        const volatile qshm_quote64 *v = slot;
+        out->source_id = v->source_id;
+        out->symbol_id = v->symbol_id;
+        out->bid = v->bid;
+        out->ask = v->ask;
+        out->ts = v->ts;
+        out->price_scale = v->price_scale;
+        out->status = __atomic_load_n(&slot->status, __ATOMIC_RELAXED);
+        __atomic_thread_fence(__ATOMIC_ACQUIRE);
+        if (__atomic_load_n(&slot->seq, __ATOMIC_RELAXED) == s1) {
+            return s1 != 0;
+        }
+    }
+    return 0;
+}
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* QUOTES_SHM_H */
@@ -0,0 +1,421 @@
+//! Admin socket: a line-based text protocol for runtime control
+//!
+//! Meant for an operator with `nc 127.0.0.1 <port>`. Without a token only a
+//! loopback address is accepted (see `AdminConfig::exposure_refusal`); with
+//! one (ADMIN_TOKEN), a connection's first line must be `auth <token>` and
+//! anything else closes it. Commands, one per line:
+//! - `flags` - list feature flags
+//! - `enable <flag>` / `disable <flag>` - flip a feature flag
+//! - `connections` - list WebSocket connections
+//! - `states` - each connection's lifecycle state, and entries/time per state
+//! - `restart <connection>` - reconnect one connection, by tag (`WS-3`, `WS-P0`),
+//!   main feed index (`3`) or a symbol it carries; the others keep running
+//! - `conflation` - per-symbol updates suppressed by conflation/dedup
+//! - `readers` - registered SHM readers and their health
+//! - `reload` - re-read the subscribe file and resubscribe the difference
+//! - `help`
+
+use std::sync::{Arc, Mutex, OnceLock};
+
+use anyhow::{bail, Context, Result};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::conflation::ConflationStats;
+use crate::flags::{FeatureFlags, Flag};
+use crate::readers::{ReaderHealth, ReaderMonitor};
+use crate::reconnect::ConnState;
+use crate::reload::Reloader;
+use crate::ws::RestartHandle;
+
+/// Longest command line accepted
+const MAX_LINE_BYTES: usize = 1024;
+
+/// Symbols shown per connection by `connections`
+const LIST_SYMBOLS: usize = 5;
+
+const HELP: &str =
+    "commands: flags | enable <flag> | disable <flag> | connections | traffic | states | restart <tag|index|symbol> | conflation | readers | reload | help";
+
+pub struct Admin {
+    flags: Arc<FeatureFlags>,
+    /// Restartable connections, registered as the feeds start
+    connections: Mutex<Vec<RestartHandle>>,
+    conflation: Option<Arc<ConflationStats>>,
+    readers: Option<Arc<ReaderMonitor>>,
+    /// Set once the main feed is running
+    reloader: OnceLock<Arc<Reloader>>,
+    /// Required as `auth <token>` on each connection's first line
+    token: Option<String>,
+    /// Flags whose feature isn't configured, with the setting it needs
+    unconfigured: Vec<(Flag, &'static str)>,
+}
+
+impl Admin {
+    pub fn new(flags: Arc<FeatureFlags>) -> Self {
+        Self {
+            flags,
+            connections: Mutex::new(Vec::new()),
+            conflation: None,
+            readers: None,
+            reloader: OnceLock::new(),
+            token: None,
+            unconfigured: Vec::new(),
+        }
+    }
+
+    /// Refuse `enable <flag>`: its feature isn't configured (`setting` is unset)
+    pub fn with_unconfigured(mut self, flag: Flag, setting: &'static str) -> Self {
+        self.unconfigured.push((flag, setting));
+        self
+    }
+
+    /// Require `auth <token>` before any command (None = no authentication)
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// Whether `line` authenticates a connection
+    fn authenticates(&self, line: &str) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        let mut words = line.split_whitespace();
+        let given = match (words.next(), words.next(), words.next()) {
+            (Some("auth"), Some(given), None) => given.as_bytes(),
+            _ => return false,
+        };
+        // Compare every byte so the reply time doesn't tell how much matched
+        given.len() == token.len() && given.iter().zip(token.as_bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+
+    /// Serve `conflation` from these counters (None = no conflation or dedup configured)
+    pub fn with_conflation(mut self, stats: Option<Arc<ConflationStats>>) -> Self {
+        self.conflation = stats;
+        self
+    }
+
+    /// Make these connections restartable
+    pub fn add_connections(&self, handles: Vec<RestartHandle>) {
+        self.connections.lock().unwrap_or_else(|e| e.into_inner()).extend(handles);
+    }
+
+    /// Serve `reload` with this reloader
+    pub fn set_reloader(&self, reloader: Arc<Reloader>) {
+        let _ = self.reloader.set(reloader);
+    }
+
+    /// Connection for a tag, main feed index or symbol
+    fn find_connection(&self, target: &str) -> Option<RestartHandle> {
+        let connections = self.connections.lock().unwrap_or_else(|e| e.into_inner());
+        let tag = match target.parse::<usize>() {
+            Ok(i) => format!("WS-{}", i),
+            Err(_) => target.to_uppercase(),
+        };
+        connections
+            .iter()
+            .find(|c| c.tag == tag)
+            .or_else(|| connections.iter().find(|c| c.symbols().contains(&tag)))
+            .cloned()
+    }
+
+    /// Serve `readers` from this monitor (None = no reader region configured)
+    pub fn with_readers(mut self, monitor: Option<Arc<ReaderMonitor>>) -> Self {
+        self.readers = monitor;
+        self
+    }
+
+    /// Run one command line; returns the reply (without the trailing newline)
+    pub fn execute(&self, line: &str) -> String {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (Some("flags"), None, _) => self
+                .flags
+                .list()
+                .iter()
+                .map(|(name, on)| format!("{} {}", name, if *on { "on" } else { "off" }))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            (Some(command @ ("enable" | "disable")), Some(name), None) => match name.parse::<Flag>() {
+                Ok(flag) => {
+                    let on = command == "enable";
+                    if let Some((_, setting)) = self.unconfigured.iter().find(|&&(f, _)| on && f == flag) {
+                        return format!("error: {} is not configured (set {} and restart)", flag.name(), setting);
+                    }
+                    if self.flags.set(flag, on) != on {
+                        eprintln!("[ADMIN] Feature flag {} {}", flag.name(), if on { "enabled" } else { "disabled" });
+                    }
+                    format!("ok {} {}", flag.name(), if on { "on" } else { "off" })
+                }
+                Err(e) => format!("error: {}", e),
+            },
+            (Some("connections"), None, _) => {
+                let connections = self.connections.lock().unwrap_or_else(|e| e.into_inner());
+                if connections.is_empty() {
+                    return "no connections".to_string();
+                }
+                connections
+                    .iter()
+                    .map(|c| {
+                        let symbols = c.symbols();
+                        let more = if symbols.len() > LIST_SYMBOLS { " ..." } else { "" };
+                        format!("{} {} streams: {}{}", c.tag, symbols.len(),
+                                symbols.iter().take(LIST_SYMBOLS).cloned().collect::<Vec<_>>().join(" "), more)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            (Some("traffic"), None, _) => {
+                let connections = self.connections.lock().unwrap_or_else(|e| e.into_inner());
+                if connections.is_empty() {
+                    return "no connections".to_string();
+                }
+                connections
+                    .iter()
+                    .map(|c| {
+                        let (frames, bytes) = c.traffic();
+                        format!("{} {} frames {} bytes avg {} B", c.tag, frames, bytes, bytes.checked_div(frames).unwrap_or(0))
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            (Some("states"), None, _) => {
+                let connections = self.connections.lock().unwrap_or_else(|e| e.into_inner());
+                if connections.is_empty() {
+                    return "no connections".to_string();
+                }
+                let now_us = crate::platform::monotonic_us();
+                connections
+                    .iter()
+                    .map(|c| {
+                        let ((state, in_state_us), times) = c.state_times(now_us);
+                        let per_state: Vec<String> = ConnState::ALL
+                            .iter()
+                            .zip(times)
+                            .map(|(s, (entries, us))| format!("{:?} {}x {:.3}s", s, entries, us as f64 / 1e6))
+                            .collect();
+                        format!("{} {:?} for {:.3}s | {}", c.tag, state, in_state_us as f64 / 1e6, per_state.join(" "))
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            (Some("restart"), Some(target), None) => match self.find_connection(target) {
+                Some(connection) => {
+                    eprintln!("[ADMIN] Restarting {}", connection.tag);
+                    connection.restart();
+                    format!("ok restarting {} ({} streams)", connection.tag, connection.symbols().len())
+                }
+                None => format!("error: no connection {:?}", target),
+            },
+            (Some("conflation"), None, _) => match &self.conflation {
+                None => "conflation and dedup are off".to_string(),
+                Some(stats) => {
+                    let symbols = stats.snapshot();
+                    if symbols.is_empty() {
+                        return "no suppressed updates".to_string();
+                    }
+                    symbols
+                        .iter()
+                        .map(|s| format!("{} suppressed {} max_interval_us {}", s.symbol, s.suppressed, s.max_interval_us))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }
+            },
+            (Some("readers"), None, _) => match &self.readers {
+                None => "no reader region (SHM_READERS_PATH)".to_string(),
+                Some(monitor) => {
+                    let statuses = monitor.status(crate::persist::unix_now_us());
+                    if statuses.is_empty() {
+                        return "no registered readers".to_string();
+                    }
+                    statuses
+                        .iter()
+                        .map(|s| {
+                            let health = match s.health {
+                                ReaderHealth::Ok => "ok".to_string(),
+                                ReaderHealth::Lagging { slots, max_missed } => {
+                                    format!("lagging {} slots, max {} updates behind", slots, max_missed)
+                                }
+                                ReaderHealth::Dead => "dead".to_string(),
+                            };
+                            format!("#{} {} pid {} heartbeat {} ms ago: {}",
+                                    s.reader.entry, s.reader.name, s.reader.pid, s.heartbeat_age_us / 1000, health)
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }
+            },
+            (Some("reload"), None, _) => match self.reloader.get() {
+                None => "error: the main feed isn't running".to_string(),
+                Some(reloader) => {
+                    eprintln!("[ADMIN] Reloading the subscribe list");
+                    match reloader.reload() {
+                        Ok(diff) => format!("ok {}", diff),
+                        Err(e) => format!("error: {:#}", e),
+                    }
+                }
+            },
+            (Some("help"), None, _) => HELP.to_string(),
+            (None, _, _) => String::new(),
+            _ => format!("error: unknown command {:?}; {}", line.trim(), HELP),
+        }
+    }
+
+    async fn handle_connection(&self, stream: TcpStream) -> Result<()> {
+        let mut stream = BufReader::new(stream);
+        let mut line = String::new();
+        let mut authenticated = self.token.is_none();
+        loop {
+            line.clear();
+            let n = (&mut stream).take(MAX_LINE_BYTES as u64).read_line(&mut line).await?;
+            if n == 0 {
+                return Ok(());
+            }
+            if !line.ends_with('\n') && n == MAX_LINE_BYTES {
+                stream.get_mut().write_all(b"error: line too long\n").await?;
+                return Ok(());
+            }
+            if !authenticated {
+                if !self.authenticates(&line) {
+                    stream.get_mut().write_all(b"error: not authenticated\n").await?;
+                    bail!("not authenticated");
+                }
+                authenticated = true;
+                stream.get_mut().write_all(b"ok\n").await?;
+                continue;
+            }
+            let reply = self.execute(&line);
+            if !reply.is_empty() {
+                stream.get_mut().write_all(format!("{}\n", reply).as_bytes()).await?;
+            }
+        }
+    }
+
+    /// Serve until the listener fails
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, peer) = listener.accept().await.context("Admin accept failed")?;
+            let admin = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = admin.handle_connection(stream).await {
+                    crate::eprintln_limited!("[ADMIN] Connection {} failed: {:#}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_execute_commands() {
+        let flags = Arc::new(FeatureFlags::default());
+        let admin = Admin::new(flags.clone());
+
+        assert_eq!(admin.execute("disable recorder\n"), "ok recorder off");
+        assert!(!flags.enabled(Flag::Recorder));
+        assert_eq!(admin.execute("flags"), "recorder off\nbroadcast on\nanomaly on\ntick_check on");
+        assert_eq!(admin.execute("enable recorder"), "ok recorder on");
+        assert!(admin.execute("enable nope").starts_with("error:"));
+        assert!(admin.execute("reboot now").starts_with("error: unknown command"));
+        assert_eq!(admin.execute("  "), "");
+        assert_eq!(admin.execute("conflation"), "conflation and dedup are off");
+        assert_eq!(admin.execute("readers"), "no reader region (SHM_READERS_PATH)");
+
+        // Nothing to resume without the feature's own configuration
+        let admin = Admin::new(flags.clone()).with_unconfigured(Flag::Broadcast, "BROADCAST_ADDR");
+        assert_eq!(admin.execute("enable broadcast"), "error: broadcast is not configured (set BROADCAST_ADDR and restart)");
+        assert_eq!(admin.execute("disable broadcast"), "ok broadcast off");
+    }
+
+    #[test]
+    fn test_conflation_command() {
+        let map = std::collections::HashMap::from([("BTCUSDT".to_string(), 0), ("ETHUSDT".to_string(), 1)]);
+        let stats = Arc::new(ConflationStats::new(&map));
+        let admin = Admin::new(Arc::new(FeatureFlags::default())).with_conflation(Some(stats.clone()));
+        assert_eq!(admin.execute("conflation"), "no suppressed updates");
+
+        stats.record_suppressed(1);
+        stats.record_interval(1, 2_500);
+        assert_eq!(admin.execute("conflation"), "ETHUSDT suppressed 1 max_interval_us 2500");
+    }
+
+    #[test]
+    fn test_restart_targets() {
+        let admin = Admin::new(Arc::new(FeatureFlags::default()));
+        assert_eq!(admin.execute("connections"), "no connections");
+
+        let symbols: Vec<String> = (0..7).map(|i| format!("SYM{}USDT", i)).collect();
+        let handler: crate::ws::MessageHandler = Arc::new(|_| {});
+        let stats = Arc::new(crate::ws::PerfStats::new());
+        let manager = crate::ws::WsManager::new(symbols, handler, Default::default(), stats);
+        admin.add_connections(manager.restart_handles());
+
+        assert_eq!(admin.execute("connections"), "WS-0 7 streams: SYM0USDT SYM1USDT SYM2USDT SYM3USDT SYM4USDT ...");
+        manager.conn_stats()[0].2.record_bytes(250);
+        manager.conn_stats()[0].2.record_bytes(150);
+        assert_eq!(admin.execute("traffic"), "WS-0 2 frames 400 bytes avg 200 B");
+        let states = admin.execute("states");
+        assert!(states.starts_with("WS-0 Backoff for "), "{}", states);
+        assert!(states.contains("| Connecting 0x 0.000s Subscribing 0x 0.000s Streaming 0x 0.000s Draining 0x 0.000s Backoff 1x "), "{}", states);
+        assert_eq!(admin.execute("restart 0"), "ok restarting WS-0 (7 streams)");
+        assert_eq!(admin.execute("restart ws-0"), "ok restarting WS-0 (7 streams)");
+        assert_eq!(admin.execute("restart sym6usdt"), "ok restarting WS-0 (7 streams)");
+        assert!(admin.execute("restart 1").starts_with("error: no connection"));
+    }
+
+    #[tokio::test]
+    async fn test_serve_over_tcp() {
+        let flags = Arc::new(FeatureFlags::default());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(Arc::new(Admin::new(flags.clone())).serve(listener));
+
+        let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+        stream.get_mut().write_all(b"disable anomaly\nhelp\n").await.unwrap();
+        let mut reply = String::new();
+        stream.read_line(&mut reply).await.unwrap();
+        assert_eq!(reply, "ok anomaly off\n");
+        reply.clear();
+        stream.read_line(&mut reply).await.unwrap();
+        assert_eq!(reply.trim_end(), HELP);
+        assert!(!flags.enabled(Flag::Anomaly));
+    }
+
+    #[tokio::test]
+    async fn test_token_required_first() {
+        let flags = Arc::new(FeatureFlags::default());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(Arc::new(Admin::new(flags.clone()).with_token(Some("s3cret".to_string()))).serve(listener));
+
+        // A command before authenticating closes the connection unrun
+        let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+        stream.get_mut().write_all(b"disable anomaly\n").await.unwrap();
+        let mut reply = String::new();
+        stream.read_line(&mut reply).await.unwrap();
+        assert_eq!(reply, "error: not authenticated\n");
+        reply.clear();
+        assert_eq!(stream.read_line(&mut reply).await.unwrap(), 0);
+        assert!(flags.enabled(Flag::Anomaly));
+
+        let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+        stream.get_mut().write_all(b"auth s3cre\n").await.unwrap();
+        reply.clear();
+        stream.read_line(&mut reply).await.unwrap();
+        assert_eq!(reply, "error: not authenticated\n");
+
+        let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+        stream.get_mut().write_all(b"auth s3cret\ndisable anomaly\n").await.unwrap();
+        reply.clear();
+        stream.read_line(&mut reply).await.unwrap();
+        assert_eq!(reply, "ok\n");
+        reply.clear();
+        stream.read_line(&mut reply).await.unwrap();
+        assert_eq!(reply, "ok anomaly off\n");
+        assert!(!flags.enabled(Flag::Anomaly));
+    }
+}
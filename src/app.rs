@@ -0,0 +1,1398 @@
+//! The feed writer as a library: `App` owns the SHM, dictionaries and sinks,
+//! `App::run` drives the WebSocket feed on the caller's tokio runtime
+//!
+//! The binary is `run(Config::default())` plus CPU pinning and exit codes.
+//! An embedding service builds a `Config` (paths, source id) and usually sets
+//! `standalone: false`, keeping its own Ctrl+C and panic handling. Everything
+//! else is configured from the environment, as for the binary. `run` only
+//! returns on a fatal error (e.g. a feed connection giving up after too many
+//! consecutive errors, `reconnect::TooManyErrors`), never exiting the process
+//! for it; background tasks live on the caller's runtime.
+//!
+//! Fatal data errors (unknown symbol, invalid slot) still exit the process with
+//! the documented codes: a writer that can't trust its slots must not go on.
+
+use std::collections::HashMap;
+use std::process;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+
+use crate::flags::Flag;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+use crate::uring;
+use crate::{
+    admin, broadcast, canary, config, conflation, depth, dispatch, drift, eprintln_limited, fault, flags, gaps, hooks, http, inject, liquidation, metrics, monitor, oi, ownership,
+    persist, pipeline, platform, preflight, price, readers, reconnect, recorder, reload, rest, sequence, shed, shm, status, symbols, synthetic, trace, trading_status,
+    watchdog, writer, ws,
+};
+
+/// What differs between deployments of the writer; the rest comes from the environment
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub subscribe_file: String,
+    pub symbols_tsv: String,
+    pub source_id: u64,
+    /// Source name recorded in the SHM header writer info
+    pub source_name: String,
+    /// Writer info source name of the open interest row
+    pub oi_source_name: String,
+    /// Own the process: install the Ctrl+C handler (stats report, exit) and
+    /// the panic hook (mark the source faulted, abort)
+    pub standalone: bool,
+}
+
+impl Default for Config {
+    /// The spec deployment
+    fn default() -> Self {
+        let paths = config::DictionaryPaths::default();
+        Self {
+            subscribe_file: paths.subscribe_file,
+            symbols_tsv: paths.symbols_tsv,
+            source_id: 1,
+            source_name: "binance_futures".to_string(),
+            oi_source_name: "binance_futures_oi".to_string(),
+            standalone: true,
+        }
+    }
+}
+
+impl Config {
+    /// The defaults with dictionary paths resolved from the environment
+    /// (SUBSCRIBE_FILE / SYMBOLS_TSV, SIRO_HOME, XDG dirs; see `config::DictionaryPaths`)
+    pub fn from_env() -> Self {
+        let paths = config::DictionaryPaths::from_env();
+        Self { subscribe_file: paths.subscribe_file, symbols_tsv: paths.symbols_tsv, ..Self::default() }
+    }
+}
+
+/// Initialize and run the writer until a fatal error
+pub async fn run(config: Config) -> Result<()> {
+    let app = App::new(config).context("Initialization failed")?;
+    app.run().await
+}
+
+/// Application state
+pub struct App {
+    config: Config,
+    /// Environment settings, read once at startup
+    settings: config::Settings,
+    /// The subscribe file as loaded at startup (a reload only adds symbols, see `added`)
+    universe: symbols::Universe,
+    /// The feed source's mapping (`shm_registry.get(config.source_id)`)
+    shm: Arc<shm::ShmManager>,
+    shm_path: String,
+    /// SHM mapping of every source written by this process (see SHM_ROUTES)
+    shm_registry: shm::ShmRegistry,
+    symbol_id_map: Arc<HashMap<String, u64>>,
+    perf_stats: Arc<ws::PerfStats>,
+    /// Separate latency stats for the priority feed
+    priority_stats: Arc<ws::PerfStats>,
+    anomaly_monitor: Arc<monitor::AnomalyMonitor>,
+    /// Data regression checks (None = CANARY_CHECKS unset)
+    canary: Option<Arc<canary::Canary>>,
+    tick_validator: Arc<monitor::TickValidator>,
+    /// Decimals kept per symbol_id (8 unless overridden)
+    price_decimals: Arc<Vec<u32>>,
+    /// Local re-broadcast of written quotes (None = disabled)
+    publisher: Option<broadcast::Publisher>,
+    recorder: Option<Arc<recorder::Recorder>>,
+    gaps: Arc<gaps::GapTracker>,
+    /// Synthetic cross rates recomputed on leg updates (None = none configured)
+    crosses: Option<Arc<synthetic::CrossEngine>>,
+    /// Slot ownership stamps, checked for a second writer
+    ownership: Arc<ownership::SlotOwnership>,
+    /// This process's right to write the subscribed slots
+    feed_writer: Arc<writer::WriterToken>,
+    /// Stream name -> (symbol_id, slot) of the startup feed symbols
+    dispatch: Arc<dispatch::StreamTable>,
+    /// Main feed symbols subscribed by a reload (see reload)
+    added: Arc<reload::AddedSymbols>,
+    /// Normalization hooks (none in the binary; set by embedders with `with_hooks`)
+    hooks: Arc<hooks::Hooks>,
+    /// Optional write-path work, switchable at runtime via the admin socket
+    flags: Arc<flags::FeatureFlags>,
+    /// Admin commands (flags, connection restarts); served when ADMIN_ADDR is set
+    admin: Arc<admin::Admin>,
+    /// Latency budget enforcement (None = LATENCY_BUDGET_US unset)
+    shed: Option<Arc<shed::ShedController>>,
+    /// Per-symbol suppressed updates (None = no conflation or dedup configured)
+    conflation: Option<Arc<conflation::ConflationStats>>,
+    /// Registered SHM readers' lag and liveness (None = SHM_READERS_PATH unset)
+    readers: Option<Arc<readers::ReaderMonitor>>,
+    /// Exchange-to-local clock offset (None = both CLOCK_DRIFT_* thresholds 0)
+    drift: Option<Arc<drift::ClockDrift>>,
+    /// Reconnect attempts shared by all feeds (None = RECONNECT_PER_MIN=0)
+    reconnect_bucket: Option<Arc<reconnect::ReconnectBucket>>,
+    /// Order books of `streams=depth` symbols (None = DEPTH_SHM_PATH unset or no such symbols)
+    depth: Option<Arc<depth::DepthFeed>>,
+    /// Each slot's bookTicker updateId (None = SHM_SEQUENCE_PATH unset)
+    sequences: Option<Arc<sequence::SequenceRegion>>,
+    /// Test mode delaying every message (None = INJECT_LATENCY unset)
+    latency: Option<Arc<inject::LatencyInjector>>,
+    /// Feed and connection stats exported to the METRICS_BACKEND
+    metrics: Arc<metrics::MetricsExporter>,
+    /// Startup, reconnect and REST bootstrap spans (disabled = OTEL_EXPORTER_OTLP_ENDPOINT unset)
+    tracer: Arc<trace::Tracer>,
+    /// Ended once `run` has started the feeds
+    startup: std::sync::Mutex<Option<trace::Span>>,
+    /// symbols.tsv status of subscribed symbols, where listed
+    listed_status: HashMap<String, String>,
+}
+
+impl App {
+    /// Load dictionaries, open the SHM and set up everything `run` needs,
+    /// with settings read from the environment
+    pub fn new(config: Config) -> Result<Self> {
+        Self::with_settings(config, config::Settings::from_env()?)
+    }
+
+    /// `new` with a settings snapshot taken by the caller
+    pub fn with_settings(config: Config, mut settings: config::Settings) -> Result<Self> {
+        let tracer = Arc::new(trace::Tracer::from_config(&settings.trace).unwrap_or_else(|e| {
+            eprintln!("[WARN] Tracing disabled: {:#}", e);
+            trace::Tracer::disabled()
+        }));
+        let mut startup = tracer.start("startup");
+        startup.attr("source_id", config.source_id);
+        let symbols_span = tracer.child(&startup, "startup.symbols");
+
+        eprintln!("[INIT] Loading symbols...");
+
+        // Load symbols.tsv (with tick sizes where listed)
+        let symbol_info = symbols::load_symbols_info(&config.symbols_tsv)
+            .context("Failed to load symbols.tsv")?;
+        let symbol_map: symbols::SymbolMap =
+            symbol_info.iter().map(|(symbol, info)| (symbol.clone(), info.symbol_id)).collect();
+
+        // Load subscribe list with per-symbol options (the only read of it until a reload)
+        let universe = symbols::Universe::load(&config.subscribe_file)
+            .context("Failed to load subscribe list")?;
+        let (subscribe_entries, subscribe_list) = (universe.entries(), universe.book_ticker());
+        let depth_config = &settings.depth;
+        let depth_list = universe.with_stream(symbols::StreamKind::Depth);
+        let other_streams: Vec<&str> = subscribe_entries.iter()
+            .filter(|e| e.options.streams.iter().any(|&s| match s {
+                symbols::StreamKind::BookTicker => false,
+                symbols::StreamKind::Depth => depth_config.path.is_none(),
+                _ => true,
+            }))
+            .map(|e| e.symbol.as_str())
+            .collect();
+        if !other_streams.is_empty() {
+            eprintln!("[WARN] aggTrade streams (and depth without DEPTH_SHM_PATH) are not captured by this writer (requested for {})",
+                      other_streams.join(", "));
+        }
+
+        // Validate all symbols exist
+        symbols::validate_symbols(subscribe_list, &symbol_map)
+            .context("Symbol validation failed")?;
+
+        eprintln!("[INIT] All {} symbols validated", subscribe_list.len());
+
+        // Create symbol_id lookup map
+        let symbol_id_map = symbols::create_symbol_id_map(subscribe_list, &symbol_map)
+            .context("Failed to create symbol_id map")?;
+        // Per-symbol tables are indexed by symbol_id, up to the largest subscribed one
+        let n_slots = symbol_id_map.values().max().map_or(0, |&id| id as usize + 1);
+        symbols_span.end();
+        startup.attr("symbols", symbol_id_map.len() as u64);
+
+        let depth_ids = match depth_config.path {
+            Some(_) => symbols::create_symbol_id_map(&depth_list, &symbol_map).context("Depth symbol validation failed")?,
+            None => HashMap::new(),
+        };
+
+        // Synthetic crosses get slots of their own, so size a new SHM for them too
+        let synthetic_config = &settings.synthetic;
+        let synthetic_ids = synthetic_config.pairs.iter().filter_map(|p| symbol_map.get(&p.symbol));
+
+        // Latency accounting assumes an NTP-disciplined clock
+        preflight::check_clock_sync(&settings.clock_sync)?;
+
+        // Open and validate the SHM file of every source we write (routed per
+        // source, see SHM_ROUTES): the feed (and its synthetic crosses) and OI
+        let shm_config = &settings.shm;
+        let shm_path = shm_config.path_for(config.source_id).to_string();
+        let mut shm_span = tracer.child(&startup, "startup.shm");
+        shm_span.attr("path", shm_path.as_str());
+        let n_symbols = symbol_id_map.values().chain(synthetic_ids).max().map_or(0, |&id| id + 1);
+        let oi_source = (settings.oi.interval_ms > 0).then_some(settings.oi.source_id);
+        let shm_sources: Vec<u64> = std::iter::once(config.source_id).chain(oi_source).collect();
+        let shm_registry = open_shm_routes(shm_config, &shm_sources, n_symbols)?;
+        let shm = shm_registry.get(config.source_id)?.clone();
+
+        // Stamp our slots (feed and synthetic crosses) before touching any of
+        // them, so a second writer on this source is noticed by either side
+        let cross_ids = synthetic_config.pairs.iter().filter_map(|p| symbol_map.get(&p.symbol).copied());
+        let mut owned_ids: Vec<u64> = symbol_id_map.values().copied().chain(cross_ids).collect();
+        owned_ids.sort_unstable();
+        owned_ids.dedup();
+        let ownership = ownership::SlotOwnership::claim(shm.clone(), config.source_id, owned_ids, ownership::writer_token())
+            .context("Slot ownership claim failed")?;
+
+        // Initialize slots for all subscribed symbols
+        eprintln!("[INIT] Initializing SHM slots...");
+        for (symbol, &symbol_id) in &symbol_id_map {
+            shm.init_slot(config.source_id, symbol_id)
+                .with_context(|| format!("Failed to init slot for {}", symbol))?;
+        }
+        shm_span.end();
+
+        // Symbols the exchange lists as not trading (symbols.tsv status column)
+        for (symbol, &symbol_id) in &symbol_id_map {
+            let status = symbol_info.get(symbol).and_then(|info| info.status.as_deref());
+            if status.is_some_and(trading_status::is_halted) {
+                shm.get_slot(config.source_id, symbol_id)?.update_status(shm::SLOT_STATUS_HALTED, shm::SLOT_STATUS_HALTED);
+                eprintln!("[INIT] {} marked halted (status {})", symbol, status.unwrap_or_default());
+            }
+        }
+
+        let feed_ids: Vec<u64> = symbol_id_map.values().copied().collect();
+        let feed_writer = Arc::new(writer::WriterToken::claim(shm.clone(), "feed", config.source_id, &feed_ids)?);
+        let dispatch = Arc::new(dispatch::StreamTable::new(feed_writer.clone(), &symbol_id_map)?);
+        let added = Arc::new(reload::AddedSymbols::new(shm.clone(), config.source_id));
+
+        let listed_status: HashMap<String, String> = symbol_id_map.keys()
+            .filter_map(|symbol| Some((symbol.clone(), symbol_info.get(symbol)?.status.clone()?)))
+            .collect();
+
+        // Subscribe file scales first so PRICE_SCALE_OVERRIDES wins
+        let file_scales = subscribe_entries.iter().filter_map(|e| {
+            e.options.scale_decimals.map(|decimals| config::ScaleOverride { symbol: e.symbol.clone(), decimals })
+        });
+        settings.price.scale_overrides = file_scales.chain(std::mem::take(&mut settings.price.scale_overrides)).collect();
+        let price_config = &settings.price;
+        eprintln!("[INIT] Price parsing: {:?}, rounding {:?}",
+                  price_config.options.mode, price_config.options.rounding);
+
+        // Per-symbol scale overrides, recorded in the slots for consumers
+        let mut price_decimals = vec![price::PRICE_DECIMALS; n_slots];
+        let mut price_scales = HashMap::new();
+        for scale in &price_config.scale_overrides {
+            let Some(&symbol_id) = symbol_id_map.get(&scale.symbol) else {
+                eprintln!("[WARN] Price scale override for unsubscribed {} ignored", scale.symbol);
+                continue;
+            };
+            shm.set_price_scale(config.source_id, symbol_id, 10u64.pow(scale.decimals))?;
+            price_scales.insert(symbol_id, 10u64.pow(scale.decimals));
+            price_decimals[symbol_id as usize] = scale.decimals;
+            eprintln!("[INIT] {} prices at scale 1e{}", scale.symbol, scale.decimals);
+        }
+
+        // Off-tick prices point at a parsing or normalization bug
+        let mut ticks = vec![0; n_slots];
+        for (symbol, &symbol_id) in &symbol_id_map {
+            if let Some(tick) = symbol_info.get(symbol).and_then(|info| info.tick_size) {
+                ticks[symbol_id as usize] = monitor::TickValidator::tick_in_scale(tick, price_decimals[symbol_id as usize]);
+            }
+        }
+        let tick_validator = monitor::TickValidator::new(ticks);
+        eprintln!("[INIT] Tick-size validation for {} symbols", tick_validator.checked_symbols());
+
+        let crosses = if synthetic_config.pairs.is_empty() {
+            None
+        } else {
+            // Crosses are written in the feed's source row
+            let cross_shm = shm_registry.get(config.source_id)?.clone();
+            match synthetic::CrossEngine::new(cross_shm, config.source_id, &synthetic_config.pairs, &symbol_map, &symbol_id_map) {
+                Ok(engine) => {
+                    let names: Vec<&str> = engine.symbols().map(|(symbol, _)| symbol).collect();
+                    eprintln!("[INIT] Synthetic crosses: {}", names.join(", "));
+                    Some(Arc::new(engine))
+                }
+                Err(e) => {
+                    eprintln!("[WARN] Synthetic crosses disabled: {:#}", e);
+                    None
+                }
+            }
+        };
+
+        // Rate-of-change monitor sized to cover every subscribed symbol_id
+        let anomaly_config = &settings.anomaly;
+        let anomaly_monitor = monitor::AnomalyMonitor::new(anomaly_config, n_slots);
+        eprintln!("[INIT] Anomaly alerts: >= {} bps within {} ms",
+                  anomaly_config.move_bps, anomaly_config.window_ms);
+
+        let canary_config = &settings.canary;
+        let canary = (!canary_config.checks.is_empty()).then(|| {
+            let checks: Vec<&str> = canary_config.checks.iter().map(|c| c.name()).collect();
+            eprintln!("[INIT] Canary checks: {} ({})", checks.join(", "),
+                      if canary_config.exit { "exit on violation" } else { "log only" });
+            Arc::new(canary::Canary::new(canary_config, n_slots))
+        });
+
+        // Clear a writer-fault flag left by a previous crashed run and
+        // record which build now owns this source
+        match shm.source_state(config.source_id) {
+            Ok(state) => {
+                state.clear_flags(shm::SOURCE_FLAG_WRITER_FAULT);
+                state.set_writer_info(&shm::WriterInfo {
+                    version: status::VERSION.to_string(),
+                    git_sha: status::GIT_SHA.to_string(),
+                    source_name: config.source_name.clone(),
+                    started_at_unix_us: persist::unix_now_us(),
+                });
+            }
+            Err(e) => eprintln!("[WARN] No header state block for source {}: {:#}", config.source_id, e),
+        }
+
+        let shed_config = &settings.shed;
+        let perf_config = &settings.perf;
+        eprintln!("[INIT] Slow-message buckets: {:?} µs, warn over {} µs",
+                  perf_config.slow_thresholds_us, perf_config.slow_warn_us);
+
+        let broadcast_config = &settings.broadcast;
+        let symbol_conflate: HashMap<u64, std::time::Duration> = subscribe_entries.iter()
+            .filter_map(|e| Some((*symbol_id_map.get(&e.symbol)?, std::time::Duration::from_millis(e.options.conflate_ms?))))
+            .collect();
+        // Suppressed-update accounting wherever quotes get conflated or deduped
+        let conflating = broadcast_config.addr.is_some()
+            && (broadcast_config.conflate_ms > 0 || symbol_conflate.values().any(|interval| !interval.is_zero()));
+        let conflation = (conflating || shed_config.budget_us > 0)
+            .then(|| Arc::new(conflation::ConflationStats::new(&symbol_id_map)));
+        let publisher = broadcast_config.addr.as_ref().map(|_| {
+            let publisher = broadcast::Publisher::new(broadcast_config.capacity)
+                .with_symbol_conflation(symbol_conflate)
+                .with_price_scales(price_scales.clone());
+            match &conflation {
+                Some(stats) => publisher.with_conflation_stats(stats.clone()),
+                None => publisher,
+            }
+        });
+
+        let recorder = match &settings.recorder.path {
+            Some(path) => match recorder::Recorder::create(path, config.source_id, &price_scales) {
+                Ok(recorder) => {
+                    eprintln!("[INIT] Recording quotes to {}", path);
+                    Some(Arc::new(recorder))
+                }
+                Err(e) => {
+                    eprintln!("[WARN] Recording disabled: {:#}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let gaps = Arc::new(gaps::GapTracker::new(n_slots, shm::monotonic_us()));
+
+        let flags = Arc::new(flags::FeatureFlags::from_spec(&settings.admin.feature_flags));
+        let disabled: Vec<&str> = flags.list().into_iter().filter(|&(_, on)| !on).map(|(name, _)| name).collect();
+        if !disabled.is_empty() {
+            eprintln!("[INIT] Feature flags off: {}", disabled.join(", "));
+        }
+
+        // Per-symbol latency over the startup symbols' ids
+        let n_latency_slots = if perf_config.symbol_latency_top > 0 {
+            eprintln!("[INIT] Tracking latency per symbol ({} slowest reported)", perf_config.symbol_latency_top);
+            n_slots
+        } else {
+            0
+        };
+        let perf_stats = Arc::new(ws::PerfStats::with_thresholds(
+            perf_config.slow_thresholds_us.clone(),
+            perf_config.slow_warn_us,
+        ).with_symbol_latency(n_latency_slots).with_budget(shed_config.budget_us));
+        let shed = (shed_config.budget_us > 0).then(|| {
+            let names: Vec<&str> = shed_config.flags.iter().map(|flag| flag.name()).collect();
+            eprintln!("[INIT] Latency budget: p99 {} µs over {} s sheds [{}] and dedups unchanged quotes",
+                      shed_config.budget_us, shed_config.breach_secs, names.join(", "));
+            Arc::new(shed::ShedController::new(perf_stats.clone(), flags.clone(), shed_config))
+        });
+
+        let drift_config = &settings.clock_drift;
+        let drift = drift_config.enabled().then(|| {
+            eprintln!("[INIT] Clock drift alerts: offset > {} ms or jump >= {} ms (0 = off)",
+                      drift_config.max_offset_ms, drift_config.jump_ms);
+            Arc::new(drift::ClockDrift::new(drift_config))
+        });
+
+        let reconnect_config = &settings.reconnect_budget;
+        let reconnect_bucket = (reconnect_config.per_min > 0).then(|| {
+            eprintln!("[INIT] Reconnect budget: {}/min, burst {}", reconnect_config.per_min, reconnect_config.burst);
+            Arc::new(reconnect::ReconnectBucket::new(reconnect_config.per_min, reconnect_config.burst))
+        });
+
+        // Consumer registration region, checked for lagging and dead readers
+        let readers_config = &settings.readers;
+        let readers = readers_config.path.as_ref().and_then(|path| {
+            match readers::ReaderRegistry::open_or_create(path, readers_config.max_readers, shm.n_sources(), shm.n_symbols(),
+                                                          shm_config.create_mode, shm_config.create_group.as_deref()) {
+                Ok(registry) => {
+                    eprintln!("[INIT] Reader registration region {} ({} entries)", path, registry.max_readers());
+                    Some(Arc::new(readers::ReaderMonitor::new(Arc::new(registry), shm.clone(), readers_config)))
+                }
+                Err(e) => {
+                    eprintln!("[WARN] Reader monitoring disabled: {:#}", e);
+                    None
+                }
+            }
+        });
+
+        // Order books of `streams=depth` symbols into their own region
+        let depth = depth_config.path.as_ref().filter(|_| !depth_ids.is_empty()).and_then(|path| {
+            let feed_symbols = shm_registry.get(config.source_id).map_or(0, |shm| shm.n_symbols());
+            let n_symbols = depth_ids.values().max().map_or(0, |&id| id + 1).max(feed_symbols);
+            let symbols: Vec<(String, u64)> = depth_ids.iter().map(|(s, &id)| (s.clone(), id)).collect();
+            let feed = depth::DepthRegion::open_or_create(path, depth_config.levels, n_symbols,
+                                                          shm_config.create_mode, shm_config.create_group.as_deref())
+                .and_then(|region| depth::DepthFeed::new(Arc::new(region), &symbols, price_config.options));
+            match feed {
+                Ok(feed) => {
+                    eprintln!("[INIT] Order books of {} symbols into {}", symbols.len(), path);
+                    Some(Arc::new(feed))
+                }
+                Err(e) => {
+                    eprintln!("[WARN] Depth capture disabled: {:#}", e);
+                    None
+                }
+            }
+        });
+
+        // Exchange sequence of each slot's quote, beside the quotes file
+        let sequences = settings.sequence.path.as_ref().and_then(|path| {
+            match sequence::SequenceRegion::open_or_create(path, shm.n_sources(), shm.n_symbols(),
+                                                           shm_config.create_mode, shm_config.create_group.as_deref()) {
+                Ok(region) => {
+                    eprintln!("[INIT] Slot updateIds into {}", path);
+                    Some(Arc::new(region))
+                }
+                Err(e) => {
+                    eprintln!("[WARN] Sequence region disabled: {:#}", e);
+                    None
+                }
+            }
+        });
+
+        let inject_config = &settings.inject;
+        let latency = inject_config.latency.map(|distribution| {
+            eprintln!("[WARN] Latency injection test mode: every message delayed by {:?} µs (seed {})",
+                      distribution, inject_config.seed);
+            Arc::new(inject::LatencyInjector::new(distribution, inject_config.seed))
+        });
+
+        let metrics = Arc::new(metrics::MetricsExporter::from_config(&settings.metrics).unwrap_or_else(|e| {
+            eprintln!("[WARN] Metrics disabled: {:#}", e);
+            metrics::MetricsExporter::disabled()
+        }));
+
+        let priority_stats = Arc::new(ws::PerfStats::with_thresholds(
+            perf_config.slow_thresholds_us.clone(),
+            perf_config.slow_warn_us,
+        ).with_symbol_latency(n_latency_slots));
+
+        let mut admin = admin::Admin::new(flags.clone())
+            .with_conflation(conflation.clone())
+            .with_readers(readers.clone())
+            .with_token(settings.admin.token.clone());
+        if recorder.is_none() {
+            admin = admin.with_unconfigured(Flag::Recorder, "RECORD_FILE");
+        }
+        if publisher.is_none() {
+            admin = admin.with_unconfigured(Flag::Broadcast, "BROADCAST_ADDR");
+        }
+        let admin = Arc::new(admin);
+
+        eprintln!("[INIT] Initialization complete!");
+
+        Ok(Self {
+            config,
+            settings,
+            universe,
+            shm,
+            shm_path,
+            shm_registry,
+            symbol_id_map: Arc::new(symbol_id_map),
+            perf_stats,
+            priority_stats,
+            anomaly_monitor: Arc::new(anomaly_monitor),
+            canary,
+            tick_validator: Arc::new(tick_validator),
+            price_decimals: Arc::new(price_decimals),
+            publisher,
+            recorder,
+            gaps,
+            crosses,
+            ownership: Arc::new(ownership),
+            feed_writer,
+            dispatch,
+            added,
+            hooks: Arc::new(hooks::Hooks::new()),
+            admin,
+            flags,
+            shed,
+            conflation,
+            readers,
+            reconnect_bucket,
+            depth,
+            sequences,
+            latency,
+            metrics,
+            tracer,
+            startup: std::sync::Mutex::new(Some(startup)),
+            drift,
+            listed_status,
+        })
+    }
+
+    /// Use these normalization hooks on every feed started by `run`
+    pub fn with_hooks(mut self, hooks: hooks::Hooks) -> Self {
+        self.hooks = Arc::new(hooks);
+        self
+    }
+
+    /// Feature flags, e.g. for an embedder's own control plane
+    pub fn flags(&self) -> &Arc<flags::FeatureFlags> {
+        &self.flags
+    }
+
+    /// Settings the writer runs with (price scales and slow-message buckets
+    /// as adjusted at startup)
+    pub fn settings(&self) -> &config::Settings {
+        &self.settings
+    }
+
+    /// The subscribe file as loaded at startup
+    pub fn universe(&self) -> &symbols::Universe {
+        &self.universe
+    }
+
+    /// symbol -> symbol_id of the startup feed symbols
+    pub fn symbol_ids(&self) -> &HashMap<String, u64> {
+        &self.symbol_id_map
+    }
+
+    /// Create message handler recording latency into `perf_stats`
+    fn create_handler(&self, perf_stats: Arc<ws::PerfStats>) -> Arc<dyn Fn(ws::BookTickerData) + Send + Sync> {
+        let feed_writer = self.feed_writer.clone();
+        let dispatch = self.dispatch.clone();
+        let added = self.added.clone();
+        let symbol_id_map = self.symbol_id_map.clone();
+        let anomaly_monitor = self.anomaly_monitor.clone();
+        let canary = self.canary.clone();
+        let tick_validator = self.tick_validator.clone();
+        let parse_opts = self.settings.price.options;
+        let price_decimals = self.price_decimals.clone();
+        let publisher = self.publisher.clone();
+        let recorder = self.recorder.clone();
+        let gaps = self.gaps.clone();
+        let hooks = self.hooks.clone();
+        let crosses = self.crosses.clone();
+        let flags = self.flags.clone();
+        let shed = self.shed.clone();
+        let conflation = self.conflation.clone();
+        let drift = self.drift.clone();
+        let sequences = self.sequences.clone();
+        let source_id = self.config.source_id;
+
+        Arc::new(move |data: ws::BookTickerData| {
+            let t_start = shm::monotonic_us();
+            if data.received_us > 0 {
+                perf_stats.record_parse(t_start.saturating_sub(data.received_us) as u64);
+            }
+
+            // Resolve symbol_id and slot from the stream name; the symbol map covers the
+            // all-market stream and hook-rewritten frames, then symbols added by a reload
+            let (symbol_id, slot) = match dispatch.get(&data.stream) {
+                Some(hit) => hit,
+                None => {
+                    let (symbol_id, added_slot) = match symbol_id_map.get(&data.symbol) {
+                        Some(&id) => (id, None),
+                        None => match added.get(&data.symbol) {
+                            Some((id, slot)) => (id, Some(slot)),
+                            None => {
+                                eprintln!("[ERROR] Unknown symbol: {}", data.symbol);
+                                process::exit(10);
+                            }
+                        },
+                    };
+                    match added_slot.map_or_else(|| feed_writer.slot(symbol_id), Ok) {
+                        Ok(s) => (symbol_id, s),
+                        Err(e) => {
+                            eprintln!("[ERROR] Failed to get slot for symbol_id {}: {}", symbol_id, e);
+                            process::exit(11);
+                        }
+                    }
+                }
+            };
+            // Prefetch the slot's line while the prices are parsed
+            slot.prefetch();
+
+            // Parse prices (no float!) at the symbol's scale
+            let decimals = price_decimals.get(symbol_id as usize).copied().unwrap_or(price::PRICE_DECIMALS);
+            let bid = match price::parse_price_scaled(&data.bid_price, decimals, &parse_opts) {
+                Ok(v) => v,
+                Err(e) => {
+                    perf_stats.record_price_error();
+                    eprintln_limited!("[ERROR] Failed to parse bid price '{}': {}", data.bid_price, e);
+                    return;
+                }
+            };
+
+            let ask = match price::parse_price_scaled(&data.ask_price, decimals, &parse_opts) {
+                Ok(v) => v,
+                Err(e) => {
+                    perf_stats.record_price_error();
+                    eprintln_limited!("[ERROR] Failed to parse ask price '{}': {}", data.ask_price, e);
+                    return;
+                }
+            };
+
+            if flags.enabled(Flag::TickCheck) && !tick_validator.observe(symbol_id, bid, ask) {
+                eprintln_limited!("[WARN] {} price off tick: bid '{}' ask '{}'", data.symbol, data.bid_price, data.ask_price);
+            }
+
+            // Get timestamp (monotonic microseconds)
+            let ts = shm::monotonic_us();
+            if let Some(drift) = &drift {
+                drift.observe(data.event_time_ms, ts);
+            }
+
+            let quote = shm::QuoteSnapshot { source_id, symbol_id, bid, ask, ts };
+            if !hooks.validate(&quote) {
+                perf_stats.record_hook_dropped();
+                return;
+            }
+
+            // Shedding: a quote the slot already holds isn't rewritten or fanned out
+            if let Some(shed) = shed.as_ref().filter(|shed| shed.active()) {
+                if let Some(current) = slot.snapshot().filter(|current| current.bid == bid && current.ask == ask) {
+                    // Still an update of the symbol, which isn't gapping
+                    gaps.observe(symbol_id, ts);
+                    shed.record_deduped();
+                    perf_stats.record_deduped();
+                    if let Some(conflation) = &conflation {
+                        conflation.record_suppressed(symbol_id);
+                        conflation.record_interval(symbol_id, ts.saturating_sub(current.ts) as u64);
+                    }
+                    return;
+                }
+            }
+
+            if let Some(canary) = &canary {
+                if let Some(violation) = canary.check_quote(symbol_id, bid, ask, data.update_id) {
+                    canary.fail(&data.symbol, violation);
+                }
+            }
+
+            // Write to SHM using seqlock, with the quote's crossed/zero-price status
+            slot.write_with_status(bid, ask, ts, shm::SLOT_STATUS_QUOTE_MASK, shm::quote_status(bid, ask));
+            if let Some(sequences) = &sequences {
+                if let Err(e) = sequences.write(source_id, symbol_id, data.update_id, data.event_time_ms, ts) {
+                    eprintln_limited!("[ERROR] {}: {:#}", data.symbol, e);
+                }
+            }
+            gaps.observe(symbol_id, ts);
+
+            // Crosses using this symbol as a leg
+            if let Some(crosses) = &crosses {
+                crosses.on_update(symbol_id, ts);
+            }
+
+            // Local re-broadcast (no-op without connected clients)
+            if let Some(publisher) = publisher.as_ref().filter(|_| flags.enabled(Flag::Broadcast)) {
+                publisher.publish(quote);
+            }
+
+            // Capture for replay (queued to the recorder thread)
+            if let Some(recorder) = recorder.as_ref().filter(|_| flags.enabled(Flag::Recorder)) {
+                recorder.record(quote);
+            }
+
+            // Rate-of-change check (alerts are rare, so logging here is fine)
+            let alert = flags.enabled(Flag::Anomaly).then(|| anomaly_monitor.observe(symbol_id, bid, ask, ts)).flatten();
+            if let Some(bps) = alert {
+                eprintln!("[ALERT] {} mid moved {} bps in a single update (bid {} ask {})", data.symbol, bps,
+                          price::Price::new(bid, decimals), price::Price::new(ask, decimals));
+            }
+
+            // Record performance
+            let t_end = shm::monotonic_us();
+            let proc_us = (t_end - t_start) as u64;
+            perf_stats.record(proc_us);
+            perf_stats.record_symbol(symbol_id, proc_us);
+            if let Some(canary) = &canary {
+                if let Some(violation) = canary.check_latency(proc_us) {
+                    canary.fail(&data.symbol, violation);
+                }
+            }
+        })
+    }
+
+    /// Write captured frames (combined-stream JSON text, as read off the
+    /// socket) through the main feed's decode and write path, without
+    /// connecting or starting any background task; returns the stats after
+    pub fn replay<S: Into<String>>(&self, frames: impl IntoIterator<Item = S>) -> ws::PerfSnapshot {
+        let mut symbols: Vec<String> = self.symbol_id_map.keys().cloned().collect();
+        symbols.sort();
+        let handler = self.create_handler(self.perf_stats.clone());
+        let ws_manager = ws::WsManager::new(symbols, handler, self.settings.ws.clone(), self.perf_stats.clone())
+            .with_hooks(self.hooks.clone());
+        ws_manager.replay(frames);
+        self.perf_stats.snapshot()
+    }
+
+    /// Run the feed until a fatal error
+    pub async fn run(&self) -> Result<()> {
+        // Periodic stats persistence for post-mortem analysis
+        let stats_config = &self.settings.stats_file;
+        let persister = stats_config.path.clone().map(|path| {
+            match persist::load(&path) {
+                Ok(Some(prev)) => eprintln!("[STATS] Previous run (pid {}): {:?}", prev.pid, prev.perf),
+                Ok(None) => {}
+                Err(e) => eprintln!("[WARN] Ignoring unreadable stats file: {:#}", e),
+            }
+            eprintln!("[INIT] Persisting stats to {} every {} ms", path, stats_config.interval_ms);
+            let persister = Arc::new(persist::StatsPersister::new(path, self.perf_stats.clone()));
+            persister.clone().spawn(std::time::Duration::from_millis(stats_config.interval_ms));
+            persister
+        });
+
+        // Panic hook: mark SHM faulted, flush stats, abort
+        if self.config.standalone {
+            fault::install_panic_hook(
+                self.shm.clone(),
+                self.config.source_id,
+                self.perf_stats.clone(),
+                persister.clone(),
+            );
+        }
+
+        // Background slow-message reporter (keeps logging off the hot path)
+        let perf_stats = self.perf_stats.clone();
+        let priority_stats = self.priority_stats.clone();
+        let report_interval = std::time::Duration::from_millis(self.settings.perf.report_interval_ms);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(report_interval);
+            loop {
+                ticker.tick().await;
+                perf_stats.report_interval();
+                priority_stats.report_interval();
+            }
+        });
+        self.metrics.add_feed("main", self.perf_stats.clone());
+        self.metrics.clone()
+            .start(&self.settings.metrics.addr, std::time::Duration::from_millis(self.settings.metrics.interval_ms))
+            .await?;
+        self.tracer.clone().spawn_export();
+
+        // Second-writer detection on our slots
+        if self.settings.shm.owner_check_interval_ms > 0 {
+            let names = self.symbol_id_map.iter().map(|(s, &id)| (id, s.clone())).collect();
+            self.ownership.clone().spawn(std::time::Duration::from_millis(self.settings.shm.owner_check_interval_ms), names);
+        }
+
+        // Per-tier update SLA watchdog
+        let sla_config = &self.settings.sla;
+        let sla_watchdog = (!sla_config.tiers.is_empty()).then(|| {
+            let watchdog = Arc::new(watchdog::SlaWatchdog::new(
+                self.shm.clone(),
+                self.config.source_id,
+                &sla_config.tiers,
+                &self.symbol_id_map,
+            ));
+            for tier in watchdog.status() {
+                eprintln!("[INIT] SLA tier {}: {} symbols within {} ms", tier.name, tier.symbols, tier.max_age_ms);
+            }
+            watchdog.clone().spawn(std::time::Duration::from_millis(sla_config.check_interval_ms));
+            watchdog
+        });
+
+        // Catch another process corrupting or truncating the file
+        if self.settings.shm.header_check_interval_ms > 0 {
+            let interval = std::time::Duration::from_millis(self.settings.shm.header_check_interval_ms);
+            for shm in self.shm_registry.files() {
+                shm.clone().spawn_header_check(interval)?;
+            }
+        }
+
+        // Wall-clock reference for quote ages in other processes
+        if self.settings.shm.heartbeat_ms > 0 {
+            let interval = std::time::Duration::from_millis(self.settings.shm.heartbeat_ms);
+            self.shm.clone().spawn_heartbeat(self.config.source_id, interval)?;
+            eprintln!("[INIT] SHM heartbeat every {:?}", interval);
+        }
+
+        // Report consumers that fall behind or stop heartbeating
+        if let Some(readers) = &self.readers {
+            readers.clone().spawn(std::time::Duration::from_millis(self.settings.readers.check_interval_ms));
+        }
+
+        if self.settings.clock_sync.check_secs > 0 {
+            preflight::spawn_clock_sync_check(&self.settings.clock_sync,
+                                              std::time::Duration::from_secs(self.settings.clock_sync.check_secs));
+        }
+
+        // Exchange vs local clock, checked once a second
+        if let Some(drift) = &self.drift {
+            drift.clone().spawn(std::time::Duration::from_secs(1));
+        }
+
+        // Shed non-critical work while p99 latency is over budget
+        if let Some(shed) = &self.shed {
+            shed.clone().spawn(std::time::Duration::from_secs(1));
+        }
+
+        // Durability of a file-backed SHM (nothing to do on tmpfs)
+        let msync = self.settings.shm.msync;
+        if msync == shm::MsyncPolicy::Periodic {
+            let interval = std::time::Duration::from_millis(self.settings.shm.msync_interval_ms);
+            for shm in self.shm_registry.files() {
+                shm.clone().spawn_periodic_flush(interval)?;
+            }
+            eprintln!("[INIT] SHM msync every {:?}", interval);
+        }
+
+        // Set up signal handler for graceful shutdown
+        if self.config.standalone {
+            let shutdown_shm: Vec<_> = self.shm_registry.files().cloned().collect();
+            let perf_stats = self.perf_stats.clone();
+            let priority_stats = self.priority_stats.clone();
+            let (anomaly_monitor, canary) = (self.anomaly_monitor.clone(), self.canary.clone());
+            let tick_validator = self.tick_validator.clone();
+            let (shed, conflation, drift) = (self.shed.clone(), self.conflation.clone(), self.drift.clone());
+            let (reconnect_bucket, depth) = (self.reconnect_bucket.clone(), self.depth.clone());
+            let shutdown_watchdog = sla_watchdog.clone();
+            let recorder = self.recorder.clone();
+            let (gaps, symbol_id_map) = (self.gaps.clone(), self.symbol_id_map.clone());
+            let (gap_report_top, symbol_latency_top) = (self.settings.perf.gap_report_top, self.settings.perf.symbol_latency_top);
+            tokio::spawn(async move {
+                tokio::signal::ctrl_c().await.ok();
+                eprintln!("\n[SHUTDOWN] Received Ctrl+C, printing stats...");
+                perf_stats.report();
+                perf_stats.report_symbols(&symbol_id_map, symbol_latency_top);
+                if priority_stats.snapshot().total_messages > 0 {
+                    eprintln!("\n[STATS] --- Priority feed ---");
+                    priority_stats.report();
+                    priority_stats.report_symbols(&symbol_id_map, symbol_latency_top);
+                }
+                anomaly_monitor.report();
+                if let Some(canary) = &canary {
+                    canary.report();
+                }
+                tick_validator.report();
+                if let Some(shed) = &shed {
+                    shed.report();
+                }
+                if let Some(conflation) = &conflation {
+                    conflation.report(gap_report_top);
+                }
+                if let Some(drift) = &drift {
+                    drift.report();
+                }
+                if let Some(bucket) = &reconnect_bucket {
+                    bucket.report();
+                }
+                if let Some(depth) = &depth {
+                    depth.report();
+                }
+                if let Some(watchdog) = &shutdown_watchdog {
+                    watchdog.report();
+                }
+                gaps.report(&symbol_id_map, gap_report_top, shm::monotonic_us());
+                if let Some(persister) = &persister {
+                    if let Err(e) = persister.flush() {
+                        eprintln!("[WARN] Failed to persist final stats: {:#}", e);
+                    }
+                }
+                // Quotes still queued to the recorder thread go to the file first
+                if let Some(recorder) = &recorder {
+                    recorder.finish();
+                    eprintln!("[SHUTDOWN] Recording flushed");
+                }
+                if msync != shm::MsyncPolicy::Never {
+                    for shm in &shutdown_shm {
+                        match shm.flush() {
+                            Ok(()) => eprintln!("[SHUTDOWN] SHM synced to its file"),
+                            Err(e) => eprintln!("[WARN] {:#}", e),
+                        }
+                    }
+                }
+                process::exit(0);
+            });
+        }
+
+        // Runtime control (feature flags) over a line-based admin socket
+        if let Some(addr) = &self.settings.admin.addr {
+            if let Some(reason) = self.settings.admin.exposure_refusal() {
+                anyhow::bail!("Refusing to serve the admin socket: {}", reason);
+            }
+            let listener = tokio::net::TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Failed to bind admin socket {}", addr))?;
+            eprintln!("[INIT] Admin socket on {}", addr);
+            let admin = self.admin.clone();
+            tokio::spawn(async move {
+                if let Err(e) = admin.serve(listener).await {
+                    eprintln!("[WARN] Admin socket stopped: {:#}", e);
+                }
+            });
+        }
+
+        // Optional HTTP JSON endpoint for current quotes
+        if let Some(addr) = &self.settings.http.addr {
+            let listener = tokio::net::TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Failed to bind HTTP endpoint {}", addr))?;
+            eprintln!("[INIT] HTTP quotes endpoint on http://{}/quotes", addr);
+            let query = Arc::new(http::QuoteQuery::new(self.shm.clone(), self.config.source_id, self.symbol_id_map.clone()));
+            tokio::spawn(async move {
+                if let Err(e) = query.serve(listener).await {
+                    eprintln!("[WARN] HTTP endpoint stopped: {:#}", e);
+                }
+            });
+        }
+
+        // Optional local WebSocket re-broadcast of the feed
+        if let (Some(publisher), Some(addr)) = (&self.publisher, &self.settings.broadcast.addr) {
+            let listener = tokio::net::TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Failed to bind broadcast server {}", addr))?;
+            let conflate = (self.settings.broadcast.conflate_ms > 0)
+                .then(|| std::time::Duration::from_millis(self.settings.broadcast.conflate_ms));
+            eprintln!("[INIT] Re-broadcasting feed on ws://{} (conflation {:?})", addr, conflate);
+            let (publisher, symbol_id_map) = (publisher.clone(), self.symbol_id_map.clone());
+            tokio::spawn(async move {
+                if let Err(e) = publisher.serve(listener, &symbol_id_map, conflate).await {
+                    eprintln!("[WARN] Broadcast server stopped: {:#}", e);
+                }
+            });
+        }
+
+        let ws_config = self.settings.ws.clone();
+        if ws_config.io_uring && !cfg!(all(feature = "io-uring", target_os = "linux")) {
+            eprintln!("[WARN] WS_IO_URING is set but this build has no io-uring support; using the default socket path");
+        }
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = &ws_config.chaos {
+            eprintln!("[WARN] Chaos test mode: {:?}", chaos.config());
+        }
+        if !ws_config.headers.is_empty() {
+            // Names only: values may be API keys
+            let names: Vec<&str> = ws_config.headers.iter().map(|(name, _)| name.as_str()).collect();
+            eprintln!("[INIT] WS handshake headers: {}", names.join(", "));
+        }
+
+        // Liquidation events into their own SHM ring
+        let liquidation_config = &self.settings.liquidation;
+        if let Some(path) = &liquidation_config.path {
+            match liquidation::EventRing::open_or_create(
+                path,
+                liquidation_config.capacity,
+                self.settings.shm.create_mode,
+                self.settings.shm.create_group.as_deref(),
+            ) {
+                Ok(ring) => {
+                    eprintln!("[INIT] Capturing liquidations to {} ({} events)", path, ring.capacity());
+                    // Its own stats, and an outage there never takes the quotes down
+                    let stats = Arc::new(ws::PerfStats::new());
+                    self.metrics.add_feed("liquidation", stats.clone());
+                    let manager = liquidation::manager(
+                        Arc::new(ring),
+                        self.symbol_id_map.clone(),
+                        self.settings.price.options,
+                        self.settings.liquidation_ws.clone(),
+                        stats,
+                    )
+                    .retry_forever()
+                    .with_metrics(self.metrics.metrics())
+                    .with_tracer(self.tracer.clone());
+                    let manager = match &self.reconnect_bucket {
+                        Some(bucket) => manager.with_reconnect_bucket(bucket.clone(), false),
+                        None => manager,
+                    };
+                    self.metrics.add_connections(manager.conn_stats());
+                    self.gaps.add_connections(manager.conn_stats());
+                    self.admin.add_connections(manager.restart_handles());
+                    tokio::spawn(async move {
+                        let _ = manager.run_all().await;
+                    });
+                }
+                Err(e) => eprintln!("[WARN] Liquidation capture disabled: {:#}", e),
+            }
+        }
+
+        if let Some(feed) = &self.depth {
+            let timeout = std::time::Duration::from_millis(self.settings.depth.timeout_ms);
+            match rest::RestClient::new(&self.settings.depth.rest_base_url, timeout) {
+                Ok(client) => {
+                    tokio::spawn(feed.clone().run_snapshots(client, self.tracer.clone()));
+                    // Its own stats, and an outage there never takes the quotes down
+                    let stats = Arc::new(ws::PerfStats::new());
+                    self.metrics.add_feed("depth", stats.clone());
+                    let manager = depth::manager(feed.clone(), self.settings.depth_ws.clone(), stats)
+                        .retry_forever()
+                        .with_metrics(self.metrics.metrics())
+                        .with_tracer(self.tracer.clone());
+                    let manager = match &self.reconnect_bucket {
+                        Some(bucket) => manager.with_reconnect_bucket(bucket.clone(), false),
+                        None => manager,
+                    };
+                    self.metrics.add_connections(manager.conn_stats());
+                    self.gaps.add_connections(manager.conn_stats());
+                    self.admin.add_connections(manager.restart_handles());
+                    tokio::spawn(async move {
+                        let _ = manager.run_all().await;
+                    });
+                }
+                Err(e) => eprintln!("[WARN] Depth capture disabled: {:#}", e),
+            }
+        }
+
+        // Open interest polled over REST into its own source row
+        let oi_config = &self.settings.oi;
+        if oi_config.interval_ms > 0 {
+            if let Err(e) = self.spawn_oi_poller(oi_config) {
+                eprintln!("[WARN] Open interest polling disabled: {:#}", e);
+            }
+        }
+
+        // Exchange trading status (halts, breaks, delistings) into the slot status bits
+        let status_config = &self.settings.trading_status;
+        if status_config.interval_ms > 0 {
+            if let Err(e) = self.spawn_status_poller(status_config) {
+                eprintln!("[WARN] Trading status polling disabled: {:#}", e);
+            }
+        }
+
+        // Priority symbols get their own connection(s) on a dedicated pinned thread
+        let mut priority_config = self.settings.priority.clone();
+        for symbol in self.universe.priority() {
+            if !priority_config.symbols.contains(&symbol) {
+                priority_config.symbols.push(symbol);
+            }
+        }
+        let (priority_list, subscribe_list, missing) =
+            symbols::partition_priority(self.universe.book_ticker(), &priority_config.symbols);
+        for symbol in &missing {
+            eprintln!("[WARN] Priority symbol {} is not in the subscribe list", symbol);
+        }
+
+        // Status file for fleet inventory
+        let status_config = &self.settings.status_file;
+        if let Some(path) = &status_config.path {
+            let base = status::WriterStatus {
+                version: status::VERSION.to_string(),
+                git_sha: status::GIT_SHA.to_string(),
+                pid: process::id(),
+                started_at_unix: persist::unix_now(),
+                updated_at_unix: 0,
+                config_digest: status::config_digest(&[
+                    &self.settings.perf,
+                    &self.settings.price,
+                    &self.settings.anomaly,
+                    &ws_config,
+                    &priority_config,
+                ]),
+                shm_path: self.shm_path.clone(),
+                source_id: self.config.source_id,
+                subscribed_symbols: priority_list.len() + subscribe_list.len(),
+                connections: ws::WsManager::connection_count(&priority_list, &config::WsConfig { all_market: false, ..ws_config.clone() })
+                    + ws::WsManager::connection_count(&subscribe_list, &ws_config),
+                total_messages: 0,
+                reconnects: 0,
+                sla_tiers: Vec::new(),
+            };
+            eprintln!("[INIT] Writing status to {} (config digest {})", path, base.config_digest);
+            let mut writer = status::StatusWriter::new(
+                path.clone(),
+                base,
+                vec![self.perf_stats.clone(), self.priority_stats.clone()],
+            );
+            if let Some(watchdog) = &sla_watchdog {
+                writer = writer.with_watchdog(watchdog.clone());
+            }
+            let writer = Arc::new(writer);
+            writer.spawn(std::time::Duration::from_millis(status_config.interval_ms));
+        }
+
+        let priority_failure = if !priority_list.is_empty() {
+            // Priority symbols keep their own per-symbol streams
+            let priority_ws_config = config::WsConfig { all_market: false, ..ws_config.clone() };
+            Some(self.spawn_priority_feed(priority_list, &priority_config, priority_ws_config)?)
+        } else {
+            None
+        };
+        let priority_failure = fatal_error(priority_failure);
+        tokio::pin!(priority_failure);
+        if subscribe_list.is_empty() {
+            // Everything is on the priority thread; keep the main loop alive for signals
+            self.end_startup();
+            return Err(priority_failure.await);
+        }
+
+        // Create message handler
+        let handler = self.create_handler(self.perf_stats.clone());
+
+        // Create WebSocket manager
+        let main_list = subscribe_list.clone();
+        let ws_manager = ws::WsManager::new(
+            subscribe_list,
+            handler,
+            ws_config,
+            self.perf_stats.clone(),
+        )
+        .with_hooks(self.hooks.clone())
+        .with_metrics(self.metrics.metrics())
+        .with_tracer(self.tracer.clone());
+        let ws_manager = match &self.latency {
+            Some(latency) => ws_manager.with_latency(latency.clone()),
+            None => ws_manager,
+        };
+        let ws_manager = match &self.reconnect_bucket {
+            Some(bucket) => ws_manager.with_reconnect_bucket(bucket.clone(), false),
+            None => ws_manager,
+        };
+
+        // Optionally move parsing + SHM writes off the I/O thread
+        let pipeline_config = &self.settings.pipeline;
+        let ws_manager = if pipeline_config.enabled {
+            let (ws_manager, decoder) =
+                pipeline::split(ws_manager, pipeline_config.capacity, self.perf_stats.clone())?;
+            decoder.with_deadline(std::time::Duration::from_micros(pipeline_config.deadline_us))
+                .spawn(pipeline_config.cpu_core)?;
+            eprintln!("[INIT] Decode pipeline on core {:?} ({} frames per connection, deadline {} µs)",
+                      pipeline_config.cpu_core, pipeline_config.capacity, pipeline_config.deadline_us);
+            ws_manager
+        } else {
+            ws_manager
+        };
+
+        self.metrics.add_connections(ws_manager.conn_stats());
+        self.gaps.add_connections(ws_manager.conn_stats());
+        self.admin.add_connections(ws_manager.restart_handles());
+
+        // Subscribe list changes on SIGHUP or the admin `reload` command
+        let ws_manager = Arc::new(ws_manager);
+        let reloader = Arc::new(reload::Reloader::new(
+            &self.config.subscribe_file,
+            &self.config.symbols_tsv,
+            self.symbol_id_map.clone(),
+            self.added.clone(),
+            priority_config.symbols.clone(),
+            ws_manager.clone(),
+            main_list,
+        ));
+        self.admin.set_reloader(reloader.clone());
+        #[cfg(unix)]
+        reloader.spawn_on_sighup()?;
+
+        // Per-connection load report
+        let conn_report_ms = self.settings.perf.conn_report_interval_ms;
+        if conn_report_ms > 0 {
+            ws_manager.spawn_conn_reporter(std::time::Duration::from_millis(conn_report_ms));
+        }
+
+        // Run all connections
+        self.end_startup();
+        eprintln!("[MAIN] Starting WebSocket connections...");
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        if ws_manager.io_uring() {
+            // io_uring runs its own runtime; give it a blocking thread off the main loop
+            let feed = tokio::task::spawn_blocking(move || uring::start(ws_manager.run_all_uring()));
+            return tokio::select! {
+                result = feed => result.context("io_uring feed thread failed")?,
+                e = &mut priority_failure => Err(e),
+            };
+        }
+        tokio::select! {
+            result = ws_manager.run_all() => result,
+            e = &mut priority_failure => Err(e),
+        }
+    }
+}
+
+impl App {
+    /// End the `startup` span: everything is set up, the feeds are connecting
+    fn end_startup(&self) {
+        if let Some(startup) = self.startup.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            startup.end();
+        }
+    }
+
+    /// Poll exchangeInfo for the trading status of every subscribed symbol
+    fn spawn_status_poller(&self, status_config: &config::TradingStatusConfig) -> Result<()> {
+        let client = rest::RestClient::new(&status_config.rest_base_url, std::time::Duration::from_millis(status_config.timeout_ms))?;
+        let symbols = self.symbol_id_map.iter()
+            .map(|(s, &id)| (s.clone(), id, self.listed_status.get(s).cloned()))
+            .collect();
+        let mut poller = trading_status::TradingStatusPoller::new(client, self.shm.clone(), self.config.source_id, symbols);
+        if let Some(path) = &status_config.event_log {
+            poller = poller.with_event_log(path)?;
+        }
+        eprintln!("[INIT] Polling trading status of {} symbols every {} ms",
+                  self.symbol_id_map.len(), status_config.interval_ms);
+        tokio::spawn(poller.run(std::time::Duration::from_millis(status_config.interval_ms)));
+        Ok(())
+    }
+
+    /// Poll open interest for every subscribed symbol into the OI source row
+    /// of the file that source is routed to
+    fn spawn_oi_poller(&self, oi_config: &config::OiConfig) -> Result<()> {
+        let shm = oi::check_source(&self.shm_registry, oi_config.source_id, self.config.source_id)?;
+        let client = rest::RestClient::new(&oi_config.rest_base_url, std::time::Duration::from_millis(oi_config.timeout_ms))?;
+        let symbols = self.symbol_id_map.iter().map(|(s, &id)| (s.clone(), id)).collect();
+        let poller = oi::OiPoller::new(client, shm.clone(), oi_config.source_id, symbols, self.settings.price.options)?;
+
+        let state = shm.source_state(oi_config.source_id)?;
+        state.clear_flags(shm::SOURCE_FLAG_WRITER_FAULT);
+        state.set_writer_info(&shm::WriterInfo {
+            version: status::VERSION.to_string(),
+            git_sha: status::GIT_SHA.to_string(),
+            source_name: self.config.oi_source_name.clone(),
+            started_at_unix_us: persist::unix_now_us(),
+        });
+
+        eprintln!("[INIT] Polling open interest of {} symbols every {} ms into source {}",
+                  self.symbol_id_map.len(), oi_config.interval_ms, oi_config.source_id);
+        tokio::spawn(poller.run(std::time::Duration::from_millis(oi_config.interval_ms)));
+        Ok(())
+    }
+
+    /// Run the priority feed on its own OS thread with a dedicated runtime,
+    /// so heavy alt-coin traffic on the main loop can't delay it; its fatal
+    /// error, if it ever stops, arrives on the returned channel
+    fn spawn_priority_feed(
+        &self,
+        symbols: Vec<String>,
+        priority_config: &config::PriorityConfig,
+        ws_config: config::WsConfig,
+    ) -> Result<tokio::sync::oneshot::Receiver<anyhow::Error>> {
+        let cpu_core = priority_config.cpu_core;
+        eprintln!("[MAIN] Priority feed: {} symbols on core {:?}", symbols.len(), cpu_core);
+
+        // Busy-poll burns a whole core: only on a dedicated, isolated one
+        let mut busy_poll = false;
+        if priority_config.busy_poll {
+            let main_core = self.settings.cpu_core;
+            match priority_config.busy_poll_refusal(main_core, platform::isolated_cpus().as_deref()) {
+                Some(reason) => eprintln!("[WARN] PRIORITY_BUSY_POLL ignored: {}", reason),
+                None => busy_poll = true,
+            }
+        }
+
+        let handler = self.create_handler(self.priority_stats.clone());
+        let stats = self.priority_stats.clone();
+        let conn_report_ms = self.settings.perf.conn_report_interval_ms;
+        let (gaps, hooks, admin) = (self.gaps.clone(), self.hooks.clone(), self.admin.clone());
+        self.metrics.add_feed("priority", stats.clone());
+        let metrics = self.metrics.clone();
+        let tracer = self.tracer.clone();
+        let latency = self.latency.clone();
+        let reconnect_bucket = self.reconnect_bucket.clone();
+        let (failed, failure) = tokio::sync::oneshot::channel();
+
+        std::thread::Builder::new()
+            .name("priority-feed".to_string())
+            .spawn(move || {
+                if let Some(cpu) = cpu_core {
+                    if let Err(e) = platform::set_cpu_affinity(cpu) {
+                        eprintln!("[WARN] Failed to pin priority feed: {}", e);
+                    }
+                }
+
+                let ws_manager = ws::WsManager::new(symbols, handler, ws_config, stats)
+                    .with_label("P")
+                    .with_hooks(hooks)
+                    .with_metrics(metrics.metrics())
+                    .with_tracer(tracer);
+                let ws_manager = match latency {
+                    Some(latency) => ws_manager.with_latency(latency),
+                    None => ws_manager,
+                };
+                // Priority symbols reconnect first after a blip
+                let ws_manager = match reconnect_bucket {
+                    Some(bucket) => ws_manager.with_reconnect_bucket(bucket, true),
+                    None => ws_manager,
+                };
+                metrics.add_connections(ws_manager.conn_stats());
+                gaps.add_connections(ws_manager.conn_stats());
+                admin.add_connections(ws_manager.restart_handles());
+
+                #[cfg(all(feature = "io-uring", target_os = "linux"))]
+                if ws_manager.io_uring() {
+                    if busy_poll {
+                        eprintln!("[WARN] PRIORITY_BUSY_POLL is not supported with WS_IO_URING, not busy-polling");
+                    }
+                    let result = uring::start(async {
+                        if conn_report_ms > 0 {
+                            ws_manager.spawn_conn_reporter(std::time::Duration::from_millis(conn_report_ms));
+                        }
+                        ws_manager.run_all_uring().await
+                    });
+                    if let Err(e) = result {
+                        let _ = failed.send(e.context("Priority feed failed"));
+                    }
+                    return;
+                }
+
+                let mut builder = tokio::runtime::Builder::new_current_thread();
+                if busy_poll {
+                    // Check for socket events after every task poll
+                    builder.event_interval(1);
+                }
+                let runtime = match builder.enable_all().build() {
+                    Ok(rt) => rt,
+                    Err(e) => {
+                        let _ = failed.send(anyhow::Error::new(e).context("Failed to build priority runtime"));
+                        return;
+                    }
+                };
+
+                let result = runtime.block_on(async {
+                    if busy_poll {
+                        // An always-ready task keeps the runtime polling epoll
+                        // with a zero timeout instead of sleeping in it
+                        eprintln!("[MAIN] Priority feed busy-polling on core {:?}", cpu_core);
+                        tokio::spawn(async {
+                            loop {
+                                tokio::task::yield_now().await;
+                            }
+                        });
+                    }
+                    if conn_report_ms > 0 {
+                        ws_manager.spawn_conn_reporter(std::time::Duration::from_millis(conn_report_ms));
+                    }
+                    ws_manager.run_all().await
+                });
+                if let Err(e) = result {
+                    let _ = failed.send(e.context("Priority feed failed"));
+                }
+            })
+            .context("Failed to spawn priority feed thread")?;
+
+        Ok(failure)
+    }
+}
+
+/// The error arriving on `failure`; never resolves without one
+async fn fatal_error(failure: Option<tokio::sync::oneshot::Receiver<anyhow::Error>>) -> anyhow::Error {
+    if let Some(failure) = failure {
+        if let Ok(e) = failure.await {
+            return e;
+        }
+    }
+    std::future::pending().await
+}
+
+/// Open the SHM file each of `source_ids` is routed to (SHM_ROUTES), creating
+/// missing files with room for every source routed there when SHM_CREATE is set
+fn open_shm_routes(shm_config: &config::ShmConfig, source_ids: &[u64], n_symbols: u64) -> Result<shm::ShmRegistry> {
+    let mut registry = shm::ShmRegistry::new();
+    for &source_id in source_ids {
+        let path = shm_config.path_for(source_id);
+        if shm_config.create && !std::path::Path::new(path).exists() {
+            let max_id = source_ids.iter().copied().filter(|&id| shm_config.path_for(id) == path).max().unwrap_or(source_id);
+            let n_sources = shm_config.create_sources.max(max_id + 1);
+            shm::ShmManager::create(path, n_sources, n_symbols,
+                                    shm_config.create_mode, shm_config.create_group.as_deref())
+                .with_context(|| format!("Failed to create SHM {}", path))?;
+        }
+        eprintln!("[INIT] Opening SHM for source {}: {}", source_id, path);
+        preflight::check_shm_file(path)?;
+        registry.register(source_id, path)
+            .with_context(|| format!("Failed to open SHM for source {}", source_id))?;
+    }
+    Ok(registry)
+}
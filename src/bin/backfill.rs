@@ -0,0 +1,35 @@
+// Backfill the quote history ring from recorder captures, so readers starting
+// up can warm their indicators on recent history (see src/history.rs)
+//
+//   HISTORY_SHM_PATH=/dev/shm/quotes_history.dat BACKFILL_SINCE_SECS=3600 \
+//       cargo run --release --bin backfill -- capture1.rec capture2.rec
+//
+// Only quotes newer than the ring's newest are appended, so running it again
+// after more captures were written adds just those.
+
+use anyhow::{bail, Context, Result};
+use binance_futures_writer::{config, history, persist};
+
+fn main() -> Result<()> {
+    let captures: Vec<String> = std::env::args().skip(1).collect();
+    if captures.is_empty() {
+        bail!("usage: backfill <capture file>... (ring from HISTORY_SHM_PATH)");
+    }
+    let history_config = config::HistoryConfig::from_env();
+    let Some(path) = &history_config.path else {
+        bail!("HISTORY_SHM_PATH is not set");
+    };
+    let shm_config = config::ShmConfig::from_env()?;
+    let ring = history::QuoteRing::open_or_create(path, history_config.capacity, shm_config.create_mode,
+                                                  shm_config.create_group.as_deref())?;
+
+    let since_unix_us = match history_config.since_secs {
+        0 => 0,
+        secs => persist::unix_now_us().saturating_sub(secs * 1_000_000) as i64,
+    };
+    let quotes = history::load_captures(&captures, since_unix_us).context("Failed to read captures")?;
+    let written = history::backfill(&ring, &quotes);
+    eprintln!("[HISTORY] {} quotes in {} captures, {} appended to {} ({} written, capacity {})",
+              quotes.len(), captures.len(), written, path, ring.write_idx(), ring.capacity());
+    Ok(())
+}
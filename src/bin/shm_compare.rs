@@ -0,0 +1,78 @@
+// Compare two sources of the quotes SHM: per-symbol mid differences, which
+// feed shows a move first and by how much, and divergence alerts (see
+// src/compare.rs)
+//
+//   COMPARE_SOURCE_A=1 COMPARE_SOURCE_B=2 COMPARE_CSV=/tmp/compare.csv \
+//       cargo run --release --bin shm_compare
+//
+// Each source is read from its SHM_ROUTES file (or SHM_PATH), read-only.
+// Symbol names come from symbols.tsv (see DictionaryPaths); slots of ids it
+// doesn't list are compared too, by id.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use binance_futures_writer::compare::{self, AlertChange, SymbolCompare, Thresholds};
+use binance_futures_writer::{config, platform, shm, symbols};
+
+fn main() -> Result<()> {
+    let compare_config = config::CompareConfig::from_env();
+    let (a, b) = (compare_config.source_a, compare_config.source_b);
+    if a == b {
+        bail!("COMPARE_SOURCE_A and COMPARE_SOURCE_B are both {}", a);
+    }
+    let shm_config = config::ShmConfig::from_env()?;
+    let open = |source_id: u64| {
+        let path = shm_config.path_for(source_id);
+        shm::ShmManager::open_readonly(path).with_context(|| format!("Failed to open source {} ({})", source_id, path))
+    };
+    let (reader_a, reader_b) = (open(a)?, open(b)?);
+    let n_symbols = reader_a.n_symbols().min(reader_b.n_symbols());
+
+    let names: HashMap<u64, String> = symbols::load_symbols_tsv(&config::DictionaryPaths::from_env().symbols_tsv)
+        .map(|map| map.into_iter().map(|(symbol, id)| (id, symbol)).collect())
+        .unwrap_or_else(|e| {
+            eprintln!("[WARN] Symbols by id only: {:#}", e);
+            HashMap::new()
+        });
+    let mut symbols: Vec<SymbolCompare> = (0..n_symbols)
+        .map(|id| SymbolCompare::new(id, names.get(&id).cloned().unwrap_or_else(|| format!("#{}", id))))
+        .collect();
+    let thresholds = Thresholds {
+        alert_bps: compare_config.alert_bps,
+        alert_us: (compare_config.alert_ms * 1_000) as i64,
+    };
+    eprintln!("[COMPARE] Source {} vs {} over {} symbols, polling every {}µs", a, b, n_symbols, compare_config.poll_us);
+
+    let started = Instant::now();
+    let report_every = Duration::from_secs(compare_config.report_secs);
+    let mut next_report = started + report_every;
+    loop {
+        let now_us = platform::monotonic_us();
+        for s in symbols.iter_mut() {
+            let quote_a = reader_a.slot(a, s.symbol_id).ok().and_then(compare::slot_mid);
+            let quote_b = reader_b.slot(b, s.symbol_id).ok().and_then(compare::slot_mid);
+            match s.sample(quote_a, quote_b, now_us, &thresholds) {
+                Some(AlertChange::Diverged(bps)) => {
+                    eprintln!("[ALERT] {} source {} is {} bps off source {} for over {}ms", s.name, b, bps, a, compare_config.alert_ms);
+                }
+                Some(AlertChange::Recovered) => eprintln!("[RECOVERED] {} sources {} and {} agree again", s.name, a, b),
+                None => {}
+            }
+        }
+
+        let done = compare_config.duration_secs > 0 && started.elapsed() >= Duration::from_secs(compare_config.duration_secs);
+        if Instant::now() >= next_report || done {
+            next_report += report_every;
+            print!("{}", compare::report(&symbols, compare_config.report_top));
+            if let Some(path) = &compare_config.csv_path {
+                std::fs::write(path, compare::csv(&symbols)).with_context(|| format!("Failed to write {}", path))?;
+            }
+        }
+        if done {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_micros(compare_config.poll_us));
+    }
+}
@@ -0,0 +1,9 @@
+// Print the C header describing the quotes SHM layout (see shm::c_header)
+//
+//   cargo run --bin shm_header > include/quotes_shm.h
+//
+// The shm tests fail while the checked-in header is out of date.
+
+fn main() {
+    print!("{}", binance_futures_writer::shm::c_header());
+}
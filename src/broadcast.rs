@@ -0,0 +1,436 @@
+//! Optional local WebSocket re-broadcast of the normalized feed
+//!
+//! Internal clients (dashboards, notebooks) connect here instead of each
+//! opening their own Binance connections. Every quote written to SHM is
+//! published on an in-process channel; each client gets either every update
+//! or, with conflation, the latest quote per symbol every N ms. Symbols may
+//! carry their own conflation interval (subscribe file `conflate_ms=`).
+//! Quotes replaced before being sent are counted per symbol (summed over
+//! clients) when a `ConflationStats` is attached. Every message carries its
+//! symbol's price scale, as per-symbol scale overrides make it vary.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use futures_util::SinkExt;
+use serde::Serialize;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::conflation::ConflationStats;
+use crate::shm::QuoteSnapshot;
+
+/// Default price scale (1e8), for symbols without an override
+const PRICE_SCALE: u64 = 100_000_000;
+
+#[derive(Debug, Serialize)]
+struct QuoteJson<'a> {
+    symbol: &'a str,
+    symbol_id: u64,
+    /// Prices scaled by `price_scale`
+    bid: i64,
+    ask: i64,
+    /// monotonic_us at write
+    ts: i64,
+    /// 1e8 unless the symbol has a scale override
+    price_scale: u64,
+}
+
+/// Hot-path side: publish quotes to connected clients
+#[derive(Clone)]
+pub struct Publisher {
+    tx: broadcast::Sender<QuoteSnapshot>,
+    /// Per-symbol_id conflation overriding the server-wide one (zero = every update)
+    symbol_conflate: Arc<HashMap<u64, Duration>>,
+    /// Per-symbol counts of quotes conflation replaced
+    conflation: Option<Arc<ConflationStats>>,
+    /// Price scale per symbol_id where it isn't the default 1e8
+    price_scales: Arc<HashMap<u64, u64>>,
+}
+
+impl Publisher {
+    /// `capacity` updates are buffered per client before it starts missing some
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity.max(1));
+        Self { tx, symbol_conflate: Arc::new(HashMap::new()), conflation: None, price_scales: Arc::new(HashMap::new()) }
+    }
+
+    /// Price scales of the symbol_ids with a scale override
+    pub fn with_price_scales(mut self, scales: HashMap<u64, u64>) -> Self {
+        self.price_scales = Arc::new(scales);
+        self
+    }
+
+    /// Conflate these symbol_ids at their own interval (zero = every update)
+    pub fn with_symbol_conflation(mut self, intervals: HashMap<u64, Duration>) -> Self {
+        self.symbol_conflate = Arc::new(intervals);
+        self
+    }
+
+    /// Count quotes conflation replaced in `stats`
+    pub fn with_conflation_stats(mut self, stats: Arc<ConflationStats>) -> Self {
+        self.conflation = Some(stats);
+        self
+    }
+
+    /// Publish a written quote (no-op without clients)
+    #[inline(always)]
+    pub fn publish(&self, quote: QuoteSnapshot) {
+        if self.tx.receiver_count() > 0 {
+            let _ = self.tx.send(quote);
+        }
+    }
+
+    /// Accept clients; only returns if the task is dropped
+    pub async fn serve(
+        self,
+        listener: TcpListener,
+        symbol_id_map: &HashMap<String, u64>,
+        conflate: Option<Duration>,
+    ) -> Result<()> {
+        let symbols: Arc<HashMap<u64, (String, u64)>> = Arc::new(
+            symbol_id_map
+                .iter()
+                .map(|(s, &id)| (id, (s.clone(), self.price_scales.get(&id).copied().unwrap_or(PRICE_SCALE))))
+                .collect(),
+        );
+
+        loop {
+            // Accept errors (EMFILE, aborted handshakes) are transient: log, back off, go on
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    crate::eprintln_limited!("[BCAST] Accept failed: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            let rx = self.tx.subscribe();
+            let (symbols, symbol_conflate) = (symbols.clone(), self.symbol_conflate.clone());
+            let stats = self.conflation.clone();
+            tokio::spawn(async move {
+                eprintln!("[BCAST] Client {} connected", peer);
+                match serve_client(stream, rx, &symbols, conflate, &symbol_conflate, stats.as_deref()).await {
+                    Ok(()) => eprintln!("[BCAST] Client {} disconnected", peer),
+                    Err(e) => eprintln!("[BCAST] Client {} dropped: {:#}", peer, e),
+                }
+            });
+        }
+    }
+}
+
+/// `symbols`: name and price scale per symbol_id
+fn to_message(quote: &QuoteSnapshot, symbols: &HashMap<u64, (String, u64)>) -> Option<Message> {
+    let (symbol, price_scale) = symbols.get(&quote.symbol_id)?;
+    let json = QuoteJson {
+        symbol,
+        symbol_id: quote.symbol_id,
+        bid: quote.bid,
+        ask: quote.ask,
+        ts: quote.ts,
+        price_scale: *price_scale,
+    };
+    serde_json::to_string(&json).ok().map(Message::Text)
+}
+
+/// A conflated symbol's latest unsent quote: (quote, when the symbol's first
+/// unsent quote arrived, whether an unsent quote was replaced)
+type Pending = HashMap<u64, (QuoteSnapshot, tokio::time::Instant, bool)>;
+
+/// Hold `quote` until its symbol's next send, replacing (and counting) an unsent one
+fn hold(pending: &mut Pending, quote: QuoteSnapshot, stats: Option<&ConflationStats>) {
+    match pending.entry(quote.symbol_id) {
+        Entry::Occupied(mut held) => {
+            *held.get_mut() = (quote, held.get().1, true);
+            if let Some(stats) = stats {
+                stats.record_suppressed(quote.symbol_id);
+            }
+        }
+        Entry::Vacant(slot) => {
+            slot.insert((quote, tokio::time::Instant::now(), false));
+        }
+    }
+}
+
+/// Quotes to send on a conflation tick at `now`: whatever is already queued
+/// is taken first (unconflated quotes in arrival order, conflated ones held)
+/// so the flush carries the latest quotes, then the held quote of every
+/// symbol whose interval has passed since its previous send
+fn flush_due(
+    rx: &mut broadcast::Receiver<QuoteSnapshot>,
+    pending: &mut Pending,
+    last_sent: &mut HashMap<u64, tokio::time::Instant>,
+    now: tokio::time::Instant,
+    interval_for: impl Fn(u64) -> Option<Duration>,
+    stats: Option<&ConflationStats>,
+) -> Vec<QuoteSnapshot> {
+    let mut out = Vec::new();
+    while let Ok(quote) = rx.try_recv() {
+        if interval_for(quote.symbol_id).is_some() {
+            hold(pending, quote, stats);
+        } else {
+            out.push(quote);
+        }
+    }
+    let due: Vec<u64> = pending
+        .keys()
+        .filter(|&&id| {
+            let interval = interval_for(id).unwrap_or_default();
+            last_sent.get(&id).is_none_or(|&last| now >= last + interval)
+        })
+        .copied()
+        .collect();
+    for id in due {
+        let (quote, since, replaced) = pending.remove(&id).expect("due ids come from pending");
+        last_sent.insert(id, now);
+        if let Some(stats) = stats.filter(|_| replaced) {
+            stats.record_interval(id, since.elapsed().as_micros() as u64);
+        }
+        out.push(quote);
+    }
+    out
+}
+
+/// Send each quote as it comes, or for conflated symbols the latest one on the
+/// first tick at least their interval after the previous send
+async fn serve_client(
+    stream: TcpStream,
+    mut rx: broadcast::Receiver<QuoteSnapshot>,
+    symbols: &HashMap<u64, (String, u64)>,
+    conflate: Option<Duration>,
+    symbol_conflate: &HashMap<u64, Duration>,
+    stats: Option<&ConflationStats>,
+) -> Result<()> {
+    let mut ws = tokio_tungstenite::accept_async(stream).await.context("WebSocket handshake failed")?;
+    let interval_for = |symbol_id: u64| {
+        symbol_conflate.get(&symbol_id).copied().or(conflate).filter(|interval| !interval.is_zero())
+    };
+    let tick = symbol_conflate.values().copied().chain(conflate).filter(|interval| !interval.is_zero()).min();
+    let mut pending = Pending::new();
+    // Tick deadlines (not wall time) so equal intervals line up exactly
+    let mut last_sent: HashMap<u64, tokio::time::Instant> = HashMap::new();
+    let mut ticker = tokio::time::interval(tick.unwrap_or(Duration::from_secs(3600)));
+
+    loop {
+        tokio::select! {
+            received = rx.recv() => match received {
+                Ok(quote) if interval_for(quote.symbol_id).is_some() => hold(&mut pending, quote, stats),
+                Ok(quote) => {
+                    if let Some(msg) = to_message(&quote, symbols) {
+                        ws.send(msg).await?;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    crate::eprintln_limited!("[BCAST] Slow client skipped {} updates", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            now = ticker.tick(), if tick.is_some() => {
+                let mut fed = false;
+                for quote in flush_due(&mut rx, &mut pending, &mut last_sent, now, interval_for, stats) {
+                    if let Some(msg) = to_message(&quote, symbols) {
+                        ws.feed(msg).await?;
+                        fed = true;
+                    }
+                }
+                if fed {
+                    ws.flush().await?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    fn quote(symbol_id: u64, bid: i64) -> QuoteSnapshot {
+        QuoteSnapshot { source_id: 1, symbol_id, bid, ask: bid + 1, ts: bid }
+    }
+
+    async fn start(conflate: Option<Duration>) -> (Publisher, std::net::SocketAddr) {
+        start_with(Publisher::new(1024).with_price_scales(HashMap::from([(2, 10_000)])), conflate).await
+    }
+
+    async fn start_with(publisher: Publisher, conflate: Option<Duration>) -> (Publisher, std::net::SocketAddr) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = publisher.clone();
+        tokio::spawn(async move {
+            let map = HashMap::from([("BTCUSDT".to_string(), 1), ("ETHUSDT".to_string(), 2)]);
+            server.serve(listener, &map, conflate).await
+        });
+        (publisher, addr)
+    }
+
+    async fn next_json(
+        client: &mut tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>,
+    ) -> serde_json::Value {
+        let msg = tokio::time::timeout(Duration::from_secs(5), client.next()).await.unwrap().unwrap().unwrap();
+        serde_json::from_str(msg.to_text().unwrap()).unwrap()
+    }
+
+    async fn wait_for_client(publisher: &Publisher) {
+        while publisher.tx.receiver_count() == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rebroadcast_every_update() {
+        let (publisher, addr) = start(None).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+        wait_for_client(&publisher).await;
+
+        publisher.publish(quote(1, 100));
+        publisher.publish(quote(1, 101));
+        assert_eq!(next_json(&mut client).await["bid"], 100);
+        let second = next_json(&mut client).await;
+        assert_eq!(second["bid"], 101);
+        assert_eq!(second["symbol"], "BTCUSDT");
+        assert_eq!(second["price_scale"], 100_000_000);
+        publisher.publish(quote(2, 7));
+        assert_eq!(next_json(&mut client).await["price_scale"], 10_000);
+    }
+
+    #[tokio::test]
+    async fn test_rebroadcast_conflated() {
+        let (publisher, addr) = start(Some(Duration::from_millis(50))).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+        wait_for_client(&publisher).await;
+
+        for bid in 100..110 {
+            publisher.publish(quote(2, bid));
+        }
+        let json = next_json(&mut client).await;
+        assert_eq!(json["symbol"], "ETHUSDT");
+        assert_eq!(json["bid"], 109);
+    }
+
+    #[tokio::test]
+    async fn test_conflation_stats() {
+        let map = HashMap::from([("BTCUSDT".to_string(), 1), ("ETHUSDT".to_string(), 2)]);
+        let stats = Arc::new(ConflationStats::new(&map));
+        let publisher = Publisher::new(1024).with_conflation_stats(stats.clone());
+        let (publisher, addr) = start_with(publisher, Some(Duration::from_millis(50))).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+        wait_for_client(&publisher).await;
+
+        for bid in 100..110 {
+            publisher.publish(quote(2, bid));
+        }
+        assert_eq!(next_json(&mut client).await["bid"], 109);
+        let snap = stats.snapshot();
+        assert_eq!(snap.len(), 1);
+        assert_eq!((snap[0].symbol.as_str(), snap[0].suppressed), ("ETHUSDT", 9));
+        assert!(snap[0].max_interval_us > 0);
+    }
+
+    #[tokio::test]
+    async fn test_rebroadcast_per_symbol_conflation() {
+        // BTCUSDT every update despite the server-wide conflation, ETHUSDT conflated
+        let publisher = Publisher::new(1024).with_symbol_conflation(HashMap::from([(1, Duration::ZERO)]));
+        let (publisher, addr) = start_with(publisher, Some(Duration::from_millis(50))).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+        wait_for_client(&publisher).await;
+
+        for bid in 100..110 {
+            publisher.publish(quote(2, bid));
+        }
+        publisher.publish(quote(1, 500));
+        publisher.publish(quote(1, 501));
+        // Unconflated quotes go out as received, before the latest ETHUSDT is flushed
+        let mut btc = Vec::new();
+        loop {
+            let json = next_json(&mut client).await;
+            match json["symbol"].as_str().unwrap() {
+                "BTCUSDT" => btc.push(json["bid"].as_i64().unwrap()),
+                _ if json["bid"] == 109 => break,
+                _ => {}
+            }
+        }
+        assert_eq!(btc, vec![500, 501]);
+    }
+
+    #[test]
+    fn test_conflation_keeps_latest_per_symbol() {
+        let (tx, mut rx) = broadcast::channel(16);
+        let (mut pending, mut last_sent) = (Pending::new(), HashMap::new());
+        let every_50ms = |_| Some(Duration::from_millis(50));
+        for quote in [quote(1, 100), quote(2, 200), quote(1, 101), quote(2, 201), quote(1, 102)] {
+            tx.send(quote).unwrap();
+        }
+
+        let now = tokio::time::Instant::now();
+        let mut sent = flush_due(&mut rx, &mut pending, &mut last_sent, now, every_50ms, None);
+        sent.sort_by_key(|q| q.symbol_id);
+        assert_eq!(sent, vec![quote(1, 102), quote(2, 201)]);
+
+        // Within the interval a new quote is held, not sent
+        tx.send(quote(1, 103)).unwrap();
+        assert!(flush_due(&mut rx, &mut pending, &mut last_sent, now + Duration::from_millis(10), every_50ms, None).is_empty());
+        let later = flush_due(&mut rx, &mut pending, &mut last_sent, now + Duration::from_millis(50), every_50ms, None);
+        assert_eq!(later, vec![quote(1, 103)]);
+    }
+
+    #[test]
+    fn test_flush_drains_queue_first() {
+        // A quote held since the last tick is superseded by one still queued at the tick
+        let (tx, mut rx) = broadcast::channel(16);
+        let (mut pending, mut last_sent) = (Pending::new(), HashMap::new());
+        let conflated = |id| (id == 2).then_some(Duration::from_millis(50));
+        hold(&mut pending, quote(2, 200), None);
+        tx.send(quote(2, 201)).unwrap();
+        tx.send(quote(1, 100)).unwrap();
+
+        let sent = flush_due(&mut rx, &mut pending, &mut last_sent, tokio::time::Instant::now(), conflated, None);
+        assert_eq!(sent, vec![quote(1, 100), quote(2, 201)]);
+        assert!(pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_slow_client_skips_to_latest() {
+        // Capacity 4: a client that falls further behind loses the oldest updates
+        let (publisher, addr) = start_with(Publisher::new(4), None).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+        wait_for_client(&publisher).await;
+
+        // No await in between: the client task can't keep up on this runtime
+        for bid in 0..100 {
+            publisher.publish(quote(1, bid));
+        }
+        let mut received = Vec::new();
+        for _ in 0..4 {
+            received.push(next_json(&mut client).await["bid"].as_i64().unwrap());
+        }
+        assert_eq!(received, vec![96, 97, 98, 99]);
+
+        // Still connected after lagging
+        publisher.publish(quote(1, 100));
+        assert_eq!(next_json(&mut client).await["bid"], 100);
+    }
+
+    #[tokio::test]
+    async fn test_disconnected_client_dropped() {
+        let (publisher, addr) = start(None).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+        wait_for_client(&publisher).await;
+        client.close(None).await.unwrap();
+        drop(client);
+
+        // The client's task ends on the next failed send and releases its receiver
+        let dropped = tokio::time::timeout(Duration::from_secs(5), async {
+            while publisher.tx.receiver_count() > 0 {
+                publisher.publish(quote(1, 100));
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        });
+        assert!(dropped.await.is_ok());
+    }
+}
@@ -0,0 +1,190 @@
+//! Canary invariants: data regressions that fail a canary loudly
+//!
+//! Canary instances (CI, staging) run the production binary but should stop
+//! at the first sign of a data regression instead of carrying on. With
+//! CANARY_CHECKS set, every quote is checked against the listed invariants
+//! before it is written; a violation is counted and logged, and with
+//! CANARY_EXIT=1 the process exits with the invariant's own code so the
+//! harness can tell which one broke. Production leaves CANARY_EXIT off and
+//! only gets the log lines.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::bail;
+
+use crate::config::CanaryConfig;
+use crate::shm;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Invariant {
+    /// bid > ask
+    Crossed,
+    /// A symbol's updateId went backwards
+    UpdateId,
+    /// Processing time over CANARY_MAX_LATENCY_US
+    Latency,
+}
+
+impl Invariant {
+    pub const ALL: [Invariant; 3] = [Invariant::Crossed, Invariant::UpdateId, Invariant::Latency];
+
+    /// Name used in CANARY_CHECKS
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Crossed => "crossed",
+            Self::UpdateId => "update_id",
+            Self::Latency => "latency",
+        }
+    }
+
+    /// Process exit code for a violation with CANARY_EXIT=1
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Crossed => 30,
+            Self::UpdateId => 31,
+            Self::Latency => 32,
+        }
+    }
+}
+
+impl FromStr for Invariant {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        match Invariant::ALL.into_iter().find(|invariant| invariant.name() == s) {
+            Some(invariant) => Ok(invariant),
+            None => bail!("Unknown canary check {:?}", s),
+        }
+    }
+}
+
+/// A failed check and what was seen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub invariant: Invariant,
+    pub detail: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} invariant violated: {}", self.invariant.name(), self.detail)
+    }
+}
+
+/// Checks quotes on the write path against the configured invariants
+pub struct Canary {
+    crossed: bool,
+    update_id: bool,
+    /// 0 = latency not checked
+    max_latency_us: u64,
+    exit: bool,
+    /// Highest updateId seen per symbol_id
+    last_update_id: Vec<AtomicU64>,
+    violations: [AtomicU64; 3],
+}
+
+impl Canary {
+    /// Checker with updateId state for symbol_ids in 0..n_symbols
+    pub fn new(config: &CanaryConfig, n_symbols: usize) -> Self {
+        let enabled = |invariant| config.checks.contains(&invariant);
+        Self {
+            crossed: enabled(Invariant::Crossed),
+            update_id: enabled(Invariant::UpdateId),
+            max_latency_us: if enabled(Invariant::Latency) { config.max_latency_us } else { 0 },
+            exit: config.exit,
+            last_update_id: (0..n_symbols).map(|_| AtomicU64::new(0)).collect(),
+            violations: Default::default(),
+        }
+    }
+
+    /// Check a quote before it is written (updateId 0 = not carried)
+    #[inline]
+    pub fn check_quote(&self, symbol_id: u64, bid: i64, ask: i64, update_id: u64) -> Option<Violation> {
+        if self.crossed && shm::quote_status(bid, ask) & shm::SLOT_STATUS_CROSSED != 0 {
+            return Some(Violation { invariant: Invariant::Crossed, detail: format!("bid {} > ask {}", bid, ask) });
+        }
+        if self.update_id && update_id != 0 {
+            if let Some(last) = self.last_update_id.get(symbol_id as usize) {
+                let prev = last.fetch_max(update_id, Ordering::Relaxed);
+                if update_id < prev {
+                    return Some(Violation {
+                        invariant: Invariant::UpdateId,
+                        detail: format!("updateId {} after {}", update_id, prev),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Check one message's processing time
+    #[inline]
+    pub fn check_latency(&self, proc_us: u64) -> Option<Violation> {
+        (self.max_latency_us > 0 && proc_us > self.max_latency_us).then(|| Violation {
+            invariant: Invariant::Latency,
+            detail: format!("processing took {} µs (limit {} µs)", proc_us, self.max_latency_us),
+        })
+    }
+
+    /// Count and log `violation` on `symbol`; with CANARY_EXIT, exit with its code
+    #[cold]
+    pub fn fail(&self, symbol: &str, violation: Violation) {
+        let invariant = violation.invariant;
+        self.violations[invariant as usize].fetch_add(1, Ordering::Relaxed);
+        if self.exit {
+            eprintln!("[CANARY] {} {}: exiting with code {}", symbol, violation, invariant.exit_code());
+            std::process::exit(invariant.exit_code());
+        }
+        crate::eprintln_limited!("[CANARY] {} {}", symbol, violation);
+    }
+
+    /// Violations of `invariant` so far
+    pub fn violations(&self, invariant: Invariant) -> u64 {
+        self.violations[invariant as usize].load(Ordering::Relaxed)
+    }
+
+    pub fn report(&self) {
+        let counts: Vec<String> = Invariant::ALL
+            .iter()
+            .map(|&invariant| format!("{} {}", invariant.name(), self.violations(invariant)))
+            .collect();
+        eprintln!("[STATS] Canary violations: {}", counts.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invariants() {
+        let config = CanaryConfig {
+            checks: "crossed, UPDATE_ID".split(',').map(|s| s.parse().unwrap()).collect(),
+            max_latency_us: 100,
+            exit: false,
+        };
+        assert!("sorted".parse::<Invariant>().is_err());
+        let canary = Canary::new(&config, 2);
+
+        assert_eq!(canary.check_quote(0, 100, 101, 5), None);
+        assert_eq!(canary.check_quote(0, 100, 100, 0), None);
+        let crossed = canary.check_quote(0, 102, 101, 6).unwrap();
+        assert_eq!(crossed.invariant, Invariant::Crossed);
+
+        // Ids regress per symbol; repeats and missing ids are fine
+        assert_eq!(canary.check_quote(1, 100, 101, 3), None);
+        assert_eq!(canary.check_quote(0, 100, 101, 6), None);
+        let regressed = canary.check_quote(0, 100, 101, 4).unwrap();
+        assert_eq!(regressed.to_string(), "update_id invariant violated: updateId 4 after 6");
+        canary.fail("BTCUSDT", regressed);
+        assert_eq!(canary.violations(Invariant::UpdateId), 1);
+
+        // Latency isn't in the list
+        assert_eq!(canary.check_latency(1_000), None);
+        let config = CanaryConfig { checks: vec![Invariant::Latency], ..config };
+        assert_eq!(Canary::new(&config, 0).check_latency(101).unwrap().invariant.exit_code(), 32);
+    }
+}
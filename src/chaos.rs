@@ -0,0 +1,124 @@
+//! Chaos test mode (`chaos` feature): inject connection faults
+//!
+//! For validating supervision and data-quality handling against the mock
+//! server (or a staging feed) without waiting for real outages:
+//! - sessions are dropped after a random lifetime (exponential, mean
+//!   CHAOS_CLOSE_MEAN_MS) as a read error would drop them (`Reset`);
+//! - each reconnect waits up to CHAOS_RECONNECT_DELAY_MS longer than planned;
+//! - CHAOS_CORRUPT_PCT percent of feed frames are corrupted before decoding,
+//!   either truncated or with one byte overwritten by JSON/number syntax, so
+//!   both parse failures and plausible-but-wrong prices come out.
+//!
+//! Draws come from one seeded PRNG (CHAOS_SEED) shared by all connections.
+
+use std::time::Duration;
+
+use crate::config::ChaosConfig;
+use crate::inject::XorShift;
+
+/// Bytes a corrupted frame gets one of its bytes overwritten with
+const GARBAGE: &[u8] = b"0123456789.-,:\"{}[]e";
+
+/// Fault injector for the WebSocket connections
+#[derive(Debug)]
+pub struct Chaos {
+    config: ChaosConfig,
+    rng: XorShift,
+}
+
+impl Chaos {
+    pub fn new(config: ChaosConfig) -> Self {
+        Self { rng: XorShift::new(config.seed), config }
+    }
+
+    /// From the CHAOS_* variables; None when no fault is configured
+    pub fn from_env() -> Option<Self> {
+        let config = ChaosConfig::from_env();
+        config.enabled().then(|| Self::new(config))
+    }
+
+    pub fn config(&self) -> &ChaosConfig {
+        &self.config
+    }
+
+    /// How long a new session lives before it is dropped (None = not dropped)
+    pub fn session_lifetime(&self) -> Option<Duration> {
+        let mean = self.config.close_mean_ms as f64;
+        (mean > 0.0).then(|| Duration::from_micros((-mean * 1000.0 * (1.0 - self.rng.next_f64()).ln()) as u64))
+    }
+
+    /// Delay added to a reconnect
+    pub fn reconnect_delay(&self) -> Duration {
+        let max = self.config.reconnect_delay_ms;
+        if max == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_millis(self.rng.next_u64() % (max + 1))
+    }
+
+    /// Corrupt the frame with probability CHAOS_CORRUPT_PCT; true if it was
+    pub fn corrupt(&self, text: &mut String) -> bool {
+        if text.is_empty() || self.rng.next_f64() * 100.0 >= self.config.corrupt_pct {
+            return false;
+        }
+        // A byte position on a char boundary (frames are ASCII in practice)
+        let mut at = (self.rng.next_u64() % text.len() as u64) as usize;
+        while !text.is_char_boundary(at) {
+            at -= 1;
+        }
+        let draw = self.rng.next_u64();
+        if draw & 1 == 0 {
+            text.truncate(at);
+        } else {
+            let len = text[at..].chars().next().map_or(1, char::len_utf8);
+            let mut garbage = GARBAGE[(draw >> 1) as usize % GARBAGE.len()];
+            if text.as_bytes()[at] == garbage {
+                garbage = GARBAGE[((draw >> 1) as usize + 1) % GARBAGE.len()];
+            }
+            text.replace_range(at..at + len, std::str::from_utf8(&[garbage]).unwrap_or("?"));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_faults() {
+        let frame = r#"{"stream":"btcusdt@bookTicker","data":{"u":1,"s":"BTCUSDT","b":"1.0","a":"1.1"}}"#;
+        let off = Chaos::new(ChaosConfig { seed: 3, ..Default::default() });
+        let mut text = frame.to_string();
+        assert!(!off.corrupt(&mut text));
+        assert_eq!((off.session_lifetime(), off.reconnect_delay()), (None, Duration::ZERO));
+
+        // Every frame corrupted, differently
+        let chaos = Chaos::new(ChaosConfig { close_mean_ms: 500, reconnect_delay_ms: 200, corrupt_pct: 100.0, seed: 3 });
+        let corrupted: Vec<String> = (0..200)
+            .map(|_| {
+                let mut text = frame.to_string();
+                assert!(chaos.corrupt(&mut text));
+                text
+            })
+            .collect();
+        assert!(corrupted.iter().all(|text| text != frame));
+        assert!(corrupted.iter().any(|text| text.len() < frame.len()));
+        assert!(corrupted.iter().any(|text| text.len() == frame.len()));
+        let mut multibyte = "é".repeat(8);
+        for _ in 0..20 {
+            chaos.corrupt(&mut multibyte);
+        }
+
+        // Around the configured means
+        let mean_ms = (0..10_000).map(|_| chaos.session_lifetime().unwrap().as_millis()).sum::<u128>() / 10_000;
+        assert!((450..550).contains(&mean_ms), "{}", mean_ms);
+        let delays: Vec<Duration> = (0..1_000).map(|_| chaos.reconnect_delay()).collect();
+        assert!(delays.iter().all(|d| *d <= Duration::from_millis(200)));
+        assert!(delays.contains(&Duration::ZERO) && delays.contains(&Duration::from_millis(200)));
+
+        let partial = Chaos::new(ChaosConfig { corrupt_pct: 10.0, seed: 5, ..Default::default() });
+        let hits = (0..10_000).filter(|_| partial.corrupt(&mut frame.to_string())).count();
+        assert!((800..1200).contains(&hits), "{}", hits);
+    }
+}
@@ -0,0 +1,241 @@
+//! Feed comparison between two sources of the quotes SHM (`shm_compare`)
+//!
+//! Both sources' slots of each symbol are polled; per symbol this keeps:
+//! - the mid difference B vs A in bps of A's mid (mean and max magnitude);
+//! - update lag: when a source shows a mid the other showed first, the
+//!   difference of their write times (`ts`, one monotonic clock per host, so
+//!   both writers must run on the same host) counts for the source that led;
+//! - divergence alerts: the difference at or over the threshold for longer
+//!   than the alert window logs one `[ALERT]`, and `[RECOVERED]` once under.
+//!
+//! Polling sees only the latest quote: a mid the poll missed on one side
+//! can't be matched, so poll faster than the feeds move to catch most.
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use crate::fixed;
+use crate::shm::SlotView;
+
+/// Recent mids remembered per source for matching
+const RECENT_MIDS: usize = 64;
+
+/// A slot's mid (1e8) and write time, when it has been written
+pub fn slot_mid(slot: SlotView) -> Option<(i64, i64)> {
+    let (bid, ask, ts) = slot.quote()?;
+    let mid = fixed::mid(bid.to_1e8()?, ask.to_1e8()?);
+    ts.is_written().then_some((mid, ts.as_micros()))
+}
+
+/// Alert thresholds
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    /// Mid difference that counts as divergent (bps)
+    pub alert_bps: i64,
+    /// How long it has to last before an alert (µs)
+    pub alert_us: i64,
+}
+
+/// What a sample changed in a symbol's alert state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertChange {
+    /// Divergent for longer than the window: difference in bps
+    Diverged(i64),
+    /// Back under the threshold after an alert
+    Recovered,
+}
+
+/// Lag of the source that followed, when it was the one that followed
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LagStats {
+    pub count: u64,
+    pub total_us: u64,
+    pub max_us: u64,
+}
+
+impl LagStats {
+    fn record(&mut self, lag_us: u64) {
+        self.count += 1;
+        self.total_us += lag_us;
+        self.max_us = self.max_us.max(lag_us);
+    }
+
+    pub fn mean_us(&self) -> u64 {
+        self.total_us.checked_div(self.count).unwrap_or(0)
+    }
+}
+
+/// One source's side of a symbol
+#[derive(Debug, Default)]
+struct Side {
+    last_ts: i64,
+    updates: u64,
+    /// (mid, ts when first shown), newest last
+    recent: VecDeque<(i64, i64)>,
+}
+
+impl Side {
+    /// Take the current quote; when it moved the mid, when the previous mid
+    /// was first shown (i64::MIN for the first one)
+    fn observe(&mut self, mid: i64, ts: i64) -> Option<i64> {
+        if ts == self.last_ts {
+            return None;
+        }
+        self.last_ts = ts;
+        self.updates += 1;
+        let previous = match self.recent.back() {
+            Some(&(last, _)) if last == mid => return None,
+            Some(&(_, shown)) => shown,
+            None => i64::MIN,
+        };
+        if self.recent.len() == RECENT_MIDS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((mid, ts));
+        Some(previous)
+    }
+
+    /// When this side first showed `mid`, if it still remembers it
+    fn shown_at(&self, mid: i64) -> Option<i64> {
+        self.recent.iter().rev().find(|&&(m, _)| m == mid).map(|&(_, ts)| ts)
+    }
+}
+
+/// Comparison state of one symbol
+#[derive(Debug, Default)]
+pub struct SymbolCompare {
+    pub symbol_id: u64,
+    pub name: String,
+    sides: [Side; 2],
+    /// Polls where both sides had a quote
+    pub samples: u64,
+    diff_bps_total: i64,
+    pub max_abs_diff_bps: i64,
+    /// Lag of B where A showed the mid first, and of A where B did
+    pub b_behind: LagStats,
+    pub a_behind: LagStats,
+    divergent_since: Option<i64>,
+    alerted: bool,
+    pub alerts: u64,
+}
+
+impl SymbolCompare {
+    pub fn new(symbol_id: u64, name: String) -> Self {
+        Self { symbol_id, name, ..Default::default() }
+    }
+
+    /// Updates seen from A and B
+    pub fn updates(&self) -> (u64, u64) {
+        (self.sides[0].updates, self.sides[1].updates)
+    }
+
+    pub fn mean_diff_bps(&self) -> i64 {
+        self.diff_bps_total.checked_div(self.samples as i64).unwrap_or(0)
+    }
+
+    /// Take one poll of both sides (mid 1e8, ts) at `now_us`
+    pub fn sample(&mut self, a: Option<(i64, i64)>, b: Option<(i64, i64)>, now_us: i64, thresholds: &Thresholds) -> Option<AlertChange> {
+        for (i, quote) in [a, b].into_iter().enumerate() {
+            let Some((mid, ts)) = quote else {
+                continue;
+            };
+            if let Some(previous) = self.sides[i].observe(mid, ts) {
+                // A mid the other side showed first (since this side's previous
+                // move, so a revisited old level doesn't match): this side followed
+                if let Some(first) = self.sides[1 - i].shown_at(mid).filter(|&first| first > previous && first <= ts) {
+                    let lag = if i == 0 { &mut self.a_behind } else { &mut self.b_behind };
+                    lag.record((ts - first) as u64);
+                }
+            }
+        }
+
+        let (Some((mid_a, _)), Some((mid_b, _))) = (a, b) else {
+            return None;
+        };
+        let diff = fixed::bps_diff(mid_a, mid_b)?;
+        self.samples += 1;
+        self.diff_bps_total += diff;
+        self.max_abs_diff_bps = self.max_abs_diff_bps.max(diff.abs());
+
+        if diff.abs() < thresholds.alert_bps {
+            self.divergent_since = None;
+            return std::mem::take(&mut self.alerted).then_some(AlertChange::Recovered);
+        }
+        let since = *self.divergent_since.get_or_insert(now_us);
+        if !self.alerted && now_us - since >= thresholds.alert_us {
+            self.alerted = true;
+            self.alerts += 1;
+            return Some(AlertChange::Diverged(diff));
+        }
+        None
+    }
+}
+
+/// CSV of the per-symbol statistics
+pub fn csv(symbols: &[SymbolCompare]) -> String {
+    let mut out = String::from(
+        "symbol_id,symbol,samples,updates_a,updates_b,mean_diff_bps,max_abs_diff_bps,\
+         a_first,b_lag_mean_us,b_lag_max_us,b_first,a_lag_mean_us,a_lag_max_us,alerts\n");
+    for s in symbols {
+        let (updates_a, updates_b) = s.updates();
+        let _ = writeln!(out, "{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                         s.symbol_id, s.name, s.samples, updates_a, updates_b, s.mean_diff_bps(), s.max_abs_diff_bps,
+                         s.b_behind.count, s.b_behind.mean_us(), s.b_behind.max_us,
+                         s.a_behind.count, s.a_behind.mean_us(), s.a_behind.max_us, s.alerts);
+    }
+    out
+}
+
+/// Table of the symbols with samples, largest difference first
+pub fn report(symbols: &[SymbolCompare], top: usize) -> String {
+    let mut rows: Vec<&SymbolCompare> = symbols.iter().filter(|s| s.samples > 0).collect();
+    rows.sort_by_key(|s| std::cmp::Reverse(s.max_abs_diff_bps));
+    let mut out = format!("{:<16} {:>8} {:>9} {:>9} {:>8} {:>8} {:>14} {:>14} {:>6}\n",
+                          "symbol", "samples", "upd A", "upd B", "mean bp", "max bp", "A first/lag", "B first/lag", "alerts");
+    for s in rows.iter().take(if top == 0 { usize::MAX } else { top }) {
+        let (updates_a, updates_b) = s.updates();
+        let _ = writeln!(out, "{:<16} {:>8} {:>9} {:>9} {:>8} {:>8} {:>14} {:>14} {:>6}",
+                         s.name, s.samples, updates_a, updates_b, s.mean_diff_bps(), s.max_abs_diff_bps,
+                         format!("{}/{}us", s.b_behind.count, s.b_behind.mean_us()),
+                         format!("{}/{}us", s.a_behind.count, s.a_behind.mean_us()), s.alerts);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lag_and_divergence() {
+        let thresholds = Thresholds { alert_bps: 50, alert_us: 1_000 };
+        let mut s = SymbolCompare::new(1, "BTCUSDT".to_string());
+
+        // A moves to 100.00 first, B follows 300µs later
+        assert_eq!(s.sample(Some((10_000_000_000, 1_000)), None, 1_000, &thresholds), None);
+        assert_eq!(s.sample(Some((10_000_000_000, 1_000)), Some((10_000_000_000, 1_300)), 1_400, &thresholds), None);
+        // B leads the next move by 100µs; an unchanged poll isn't an update
+        s.sample(Some((10_000_000_000, 1_000)), Some((10_001_000_000, 2_000)), 2_050, &thresholds);
+        s.sample(Some((10_001_000_000, 2_100)), Some((10_001_000_000, 2_000)), 2_150, &thresholds);
+        assert_eq!(s.updates(), (2, 2));
+        assert_eq!((s.b_behind.count, s.b_behind.mean_us()), (1, 300));
+        assert_eq!((s.a_behind.count, s.a_behind.max_us), (1, 100));
+        assert_eq!((s.samples, s.max_abs_diff_bps), (3, 1));
+
+        // 1% apart: alerted once after the window, then recovered
+        let (a, b) = (Some((10_000_000_000, 3_000)), Some((10_100_000_000, 3_000)));
+        assert_eq!(s.sample(a, b, 3_000, &thresholds), None);
+        assert_eq!(s.sample(a, b, 3_999, &thresholds), None);
+        assert_eq!(s.sample(a, b, 4_000, &thresholds), Some(AlertChange::Diverged(100)));
+        assert_eq!(s.sample(a, b, 9_000, &thresholds), None);
+        // A back at an old level doesn't count as B following it
+        let b = Some((10_000_000_000, 9_050));
+        assert_eq!(s.sample(a, b, 9_100, &thresholds), Some(AlertChange::Recovered));
+        assert_eq!(s.sample(a, b, 9_200, &thresholds), None);
+        assert_eq!((s.alerts, s.b_behind.count, s.a_behind.count), (1, 1, 1));
+
+        let csv = csv(&[s]);
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.lines().nth(1).unwrap().starts_with("1,BTCUSDT,9,3,4,"), "{}", csv);
+    }
+}
@@ -0,0 +1,1576 @@
+use std::str::FromStr;
+
+use crate::flags::Flag;
+use crate::price::{ParseMode, PriceParseOptions, RoundingMode};
+use crate::shm::MsyncPolicy;
+
+/// Read an environment variable and parse it, falling back to `default`
+/// when the variable is unset or cannot be parsed
+pub fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// Read a comma-separated list of numbers from an environment variable,
+/// falling back to `default` when unset or if any element fails to parse
+pub fn env_list_or<T: FromStr>(name: &str, default: Vec<T>) -> Vec<T> {
+    match std::env::var(name) {
+        Ok(s) => s
+            .split(',')
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty())
+            .map(|part| part.parse())
+            .collect::<Result<Vec<T>, _>>()
+            .unwrap_or(default),
+        Err(_) => default,
+    }
+}
+
+/// Read an environment variable and parse it, `default` when unset; unlike
+/// `env_or`, a value that fails to parse is an error
+pub fn env_strict<T: FromStr>(name: &str, default: T) -> anyhow::Result<T>
+where
+    T::Err: std::fmt::Display,
+{
+    match std::env::var(name) {
+        Ok(s) => s.trim().parse().map_err(|e| anyhow::anyhow!("{}: {}", name, e)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Read a comma-separated list from an environment variable, `default` when
+/// unset; unlike `env_list_or`, an element that fails to parse is an error
+pub fn env_list_strict<T: FromStr>(name: &str, default: Vec<T>) -> anyhow::Result<Vec<T>>
+where
+    T::Err: std::fmt::Display,
+{
+    let Ok(s) = std::env::var(name) else {
+        return Ok(default);
+    };
+    s.split(',')
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().map_err(|e| anyhow::anyhow!("{}: {}", name, e)))
+        .collect()
+}
+
+/// Slow-message accounting settings
+#[derive(Debug, Clone)]
+pub struct PerfConfig {
+    /// Bucket thresholds in µs
+    pub slow_thresholds_us: Vec<u64>,
+    /// Processing time above which the background reporter warns
+    pub slow_warn_us: u64,
+    /// How often the background reporter checks for slow messages
+    pub report_interval_ms: u64,
+    /// How often per-connection load is logged (0 = disabled)
+    pub conn_report_interval_ms: u64,
+    /// Symbols listed in the shutdown gap report (0 = all)
+    pub gap_report_top: usize,
+    /// Latency tracked per symbol and this many slowest listed at shutdown (0 = not tracked)
+    pub symbol_latency_top: usize,
+}
+
+impl PerfConfig {
+    /// Load from SLOW_THRESHOLDS_US / SLOW_WARN_US / SLOW_REPORT_INTERVAL_MS / CONN_REPORT_INTERVAL_MS /
+    /// GAP_REPORT_TOP / SYMBOL_LATENCY_TOP
+    pub fn from_env() -> Self {
+        Self {
+            slow_thresholds_us: env_list_or("SLOW_THRESHOLDS_US", vec![1_000, 5_000, 20_000]),
+            slow_warn_us: env_or("SLOW_WARN_US", 5_000),
+            report_interval_ms: env_or("SLOW_REPORT_INTERVAL_MS", 10_000),
+            conn_report_interval_ms: env_or("CONN_REPORT_INTERVAL_MS", 60_000),
+            gap_report_top: env_or("GAP_REPORT_TOP", 20),
+            symbol_latency_top: env_or("SYMBOL_LATENCY_TOP", 0),
+        }
+    }
+}
+
+/// Error-log rate limiting (see ratelog)
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    /// Lines allowed per call site per interval (0 = unlimited)
+    pub burst: u64,
+    pub interval_ms: u64,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self { burst: 10, interval_ms: 10_000 }
+    }
+}
+
+impl LogConfig {
+    /// Load from LOG_RATE_BURST / LOG_RATE_INTERVAL_MS
+    pub fn from_env() -> Self {
+        let d = Self::default();
+        Self {
+            burst: env_or("LOG_RATE_BURST", d.burst),
+            interval_ms: env_or("LOG_RATE_INTERVAL_MS", d.interval_ms),
+        }
+    }
+}
+
+/// Rate-of-change anomaly alert settings
+#[derive(Debug, Clone)]
+pub struct AnomalyConfig {
+    /// Alert when a single update moves the mid by at least this many basis points (0 = disabled)
+    pub move_bps: u64,
+    /// Only compare against the previous update if it arrived within this window
+    pub window_ms: u64,
+}
+
+impl AnomalyConfig {
+    /// Load from ANOMALY_MOVE_BPS / ANOMALY_WINDOW_MS
+    pub fn from_env() -> Self {
+        Self {
+            move_bps: env_or("ANOMALY_MOVE_BPS", 500),
+            window_ms: env_or("ANOMALY_WINDOW_MS", 1000),
+        }
+    }
+}
+
+/// One update-SLA tier: its symbols must tick at least every `max_age_ms`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlaTier {
+    pub name: String,
+    pub max_age_ms: u64,
+    /// Uppercase symbols; empty = every symbol not assigned to another tier
+    pub symbols: Vec<String>,
+}
+
+impl FromStr for SlaTier {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let malformed = || anyhow::anyhow!("Expected name:max_age_ms:SYM1|SYM2 or name:max_age_ms:*, got {:?}", s.trim());
+        let mut parts = s.trim().splitn(3, ':');
+        let (name, max_age_ms, symbols) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(name), Some(max_age_ms), Some(symbols)) => (name.trim(), max_age_ms.trim(), symbols.trim()),
+            _ => return Err(malformed()),
+        };
+        let max_age_ms = match max_age_ms.parse() {
+            Ok(ms) if ms > 0 && !name.is_empty() => ms,
+            _ => return Err(malformed()),
+        };
+        let symbols = match symbols {
+            "*" => Vec::new(),
+            list => {
+                let list: Vec<String> = list.split('|').map(|s| s.trim().to_uppercase()).filter(|s| !s.is_empty()).collect();
+                // Empty would silently mean every other symbol, like `*`
+                if list.is_empty() {
+                    return Err(malformed());
+                }
+                list
+            }
+        };
+        Ok(Self { name: name.to_string(), max_age_ms, symbols })
+    }
+}
+
+/// Staleness watchdog settings
+#[derive(Debug, Clone, Default)]
+pub struct SlaConfig {
+    /// Empty = watchdog disabled
+    pub tiers: Vec<SlaTier>,
+    pub check_interval_ms: u64,
+}
+
+impl SlaConfig {
+    /// Parse "name:max_age_ms:SYM1|SYM2;name:max_age_ms:*"; fails on any invalid tier
+    pub fn parse_tiers(spec: &str) -> anyhow::Result<Vec<SlaTier>> {
+        spec.split(';').filter(|tier| !tier.trim().is_empty()).map(str::parse).collect()
+    }
+
+    /// Load from SLA_TIERS / SLA_CHECK_INTERVAL_MS; fails on an invalid tier
+    pub fn from_env() -> anyhow::Result<Self> {
+        let tiers = Self::parse_tiers(&std::env::var("SLA_TIERS").unwrap_or_default())
+            .map_err(|e| anyhow::anyhow!("SLA_TIERS: {}", e))?;
+        Ok(Self {
+            tiers,
+            check_interval_ms: env_or("SLA_CHECK_INTERVAL_MS", 1000),
+        })
+    }
+}
+
+/// Synthetic cross pair: `symbol` = `base` / `quote` (e.g. ETHBTC = ETHUSDT / BTCUSDT)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntheticPair {
+    pub symbol: String,
+    pub base: String,
+    pub quote: String,
+}
+
+/// Synthetic cross-rate settings
+#[derive(Debug, Clone, Default)]
+pub struct SyntheticConfig {
+    /// Empty = no synthetic quotes
+    pub pairs: Vec<SyntheticPair>,
+}
+
+impl SyntheticConfig {
+    /// Parse "ETHBTC=ETHUSDT/BTCUSDT;SOLETH=SOLUSDT/ETHUSDT" (invalid pairs are skipped)
+    pub fn parse_pairs(spec: &str) -> Vec<SyntheticPair> {
+        spec.split(';')
+            .filter_map(|pair| {
+                let (symbol, legs) = pair.split_once('=')?;
+                let (base, quote) = legs.split_once('/')?;
+                let [symbol, base, quote] = [symbol, base, quote].map(|s| s.trim().to_uppercase());
+                if symbol.is_empty() || base.is_empty() || quote.is_empty() {
+                    return None;
+                }
+                Some(SyntheticPair { symbol, base, quote })
+            })
+            .collect()
+    }
+
+    /// Load from SYNTHETIC_PAIRS
+    pub fn from_env() -> Self {
+        Self { pairs: Self::parse_pairs(&std::env::var("SYNTHETIC_PAIRS").unwrap_or_default()) }
+    }
+}
+
+/// Crash-safe stats persistence settings
+#[derive(Debug, Clone)]
+pub struct StatsFileConfig {
+    /// State file path (None = persistence disabled)
+    pub path: Option<String>,
+    pub interval_ms: u64,
+}
+
+impl StatsFileConfig {
+    /// Load from STATS_FILE (empty string disables) / STATS_FILE_INTERVAL_MS
+    pub fn from_env() -> Self {
+        let default_path = std::env::temp_dir()
+            .join("binance_futures_writer.stats.json")
+            .to_string_lossy()
+            .into_owned();
+        let path = env_or("STATS_FILE", default_path);
+        Self {
+            path: if path.is_empty() { None } else { Some(path) },
+            interval_ms: env_or("STATS_FILE_INTERVAL_MS", 5000),
+        }
+    }
+}
+
+/// Root of the spec deployment's dictionaries (the last resort)
+pub const LEGACY_SIRO_HOME: &str = "/root/siro";
+const SUBSCRIBE_FILE_REL: &str = "dictionaries/subscribe/binance/binance_futures.txt";
+const SYMBOLS_TSV_REL: &str = "dictionaries/configs/symbols.tsv";
+
+/// Where the subscribe list and symbols.tsv are read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DictionaryPaths {
+    pub subscribe_file: String,
+    pub symbols_tsv: String,
+}
+
+impl Default for DictionaryPaths {
+    /// The spec deployment, under LEGACY_SIRO_HOME
+    fn default() -> Self {
+        Self::under(std::path::Path::new(LEGACY_SIRO_HOME))
+    }
+}
+
+impl DictionaryPaths {
+    fn under(home: &std::path::Path) -> Self {
+        Self {
+            subscribe_file: home.join(SUBSCRIBE_FILE_REL).to_string_lossy().into_owned(),
+            symbols_tsv: home.join(SYMBOLS_TSV_REL).to_string_lossy().into_owned(),
+        }
+    }
+
+    /// Resolve each file from the environment, first match wins:
+    /// 1. SUBSCRIBE_FILE / SYMBOLS_TSV;
+    /// 2. `$SIRO_HOME/dictionaries/...`;
+    /// 3. `<dir>/siro/dictionaries/...` for XDG_CONFIG_HOME (default ~/.config), then
+    ///    each of XDG_CONFIG_DIRS (default /etc/xdg), where the file exists;
+    /// 4. `/root/siro/dictionaries/...` (LEGACY_SIRO_HOME).
+    ///
+    /// Relative paths are taken from the working directory and made absolute.
+    pub fn from_env() -> Self {
+        Self::resolve(|name| std::env::var(name).ok(), |path| path.is_file())
+    }
+
+    /// `from_env` with the variable lookup and the existence check supplied
+    pub fn resolve(var: impl Fn(&str) -> Option<String>, exists: impl Fn(&std::path::Path) -> bool) -> Self {
+        use std::path::{Path, PathBuf};
+
+        let var = |name: &str| var(name).map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+        let absolute = |path: PathBuf| std::path::absolute(&path).unwrap_or(path).to_string_lossy().into_owned();
+
+        let mut xdg_dirs: Vec<PathBuf> = var("XDG_CONFIG_HOME").map(PathBuf::from)
+            .or_else(|| var("HOME").map(|home| Path::new(&home).join(".config")))
+            .into_iter()
+            .collect();
+        let config_dirs = var("XDG_CONFIG_DIRS").unwrap_or_else(|| "/etc/xdg".to_string());
+        xdg_dirs.extend(config_dirs.split(':').filter(|dir| !dir.is_empty()).map(PathBuf::from));
+
+        let resolve_one = |explicit: &str, rel: &str| -> String {
+            if let Some(path) = var(explicit) {
+                return absolute(PathBuf::from(path));
+            }
+            if let Some(home) = var("SIRO_HOME") {
+                return absolute(Path::new(&home).join(rel));
+            }
+            xdg_dirs.iter()
+                .map(|dir| dir.join("siro").join(rel))
+                .find(|path| exists(path))
+                .map_or_else(|| Path::new(LEGACY_SIRO_HOME).join(rel).to_string_lossy().into_owned(), absolute)
+        };
+        Self {
+            subscribe_file: resolve_one("SUBSCRIBE_FILE", SUBSCRIBE_FILE_REL),
+            symbols_tsv: resolve_one("SYMBOLS_TSV", SYMBOLS_TSV_REL),
+        }
+    }
+}
+
+/// SHM file routing: which file each source writes to
+#[derive(Debug, Clone)]
+pub struct ShmConfig {
+    /// File for sources without an explicit route
+    pub default_path: String,
+    /// (source_id, path) overrides
+    pub routes: Vec<(u64, String)>,
+    /// Create the file if it doesn't exist (otherwise it must be pre-created)
+    pub create: bool,
+    /// Number of sources when creating
+    pub create_sources: u64,
+    /// Permission bits applied when creating
+    pub create_mode: u32,
+    /// Group applied when creating (None = creator's primary group)
+    pub create_group: Option<String>,
+    /// Slot ownership check interval (0 = no duplicate writer checks)
+    pub owner_check_interval_ms: u64,
+    /// When the mapping is msync'ed to the file
+    pub msync: MsyncPolicy,
+    /// Interval of `MsyncPolicy::Periodic`
+    pub msync_interval_ms: u64,
+    /// Per-source heartbeat interval (0 = no heartbeat)
+    pub heartbeat_ms: u64,
+    /// Header re-verification interval (0 = only checked on open)
+    pub header_check_interval_ms: u64,
+}
+
+impl ShmConfig {
+    /// Load from SHM_PATH and SHM_ROUTES ("source_id=path,...", invalid entries ignored),
+    /// plus SHM_CREATE / SHM_CREATE_SOURCES / SHM_MODE (octal) / SHM_GROUP / SHM_OWNER_CHECK_INTERVAL_MS
+    /// and SHM_MSYNC (never | periodic | shutdown, an unknown policy is an error) /
+    /// SHM_MSYNC_INTERVAL_MS / SHM_HEARTBEAT_MS / SHM_HEADER_CHECK_INTERVAL_MS
+    pub fn from_env() -> anyhow::Result<Self> {
+        let routes = std::env::var("SHM_ROUTES")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|entry| {
+                let (id, path) = entry.split_once('=')?;
+                Some((id.trim().parse().ok()?, path.trim().to_string()))
+            })
+            .collect();
+        Ok(Self {
+            default_path: env_or("SHM_PATH", crate::platform::default_shm_path()),
+            routes,
+            create: env_or("SHM_CREATE", 0u8) != 0,
+            create_sources: env_or("SHM_CREATE_SOURCES", 4),
+            create_mode: std::env::var("SHM_MODE")
+                .ok()
+                .and_then(|s| u32::from_str_radix(s.trim().trim_start_matches("0o"), 8).ok())
+                .unwrap_or(0o640),
+            create_group: std::env::var("SHM_GROUP").ok().filter(|g| !g.trim().is_empty()),
+            owner_check_interval_ms: env_or("SHM_OWNER_CHECK_INTERVAL_MS", 5_000),
+            msync: env_strict("SHM_MSYNC", MsyncPolicy::Never)?,
+            msync_interval_ms: env_or("SHM_MSYNC_INTERVAL_MS", 1_000).max(1),
+            heartbeat_ms: env_or("SHM_HEARTBEAT_MS", 0),
+            header_check_interval_ms: env_or("SHM_HEADER_CHECK_INTERVAL_MS", 1_000),
+        })
+    }
+
+    /// SHM file for a source
+    pub fn path_for(&self, source_id: u64) -> &str {
+        self.routes
+            .iter()
+            .find(|(id, _)| *id == source_id)
+            .map_or(self.default_path.as_str(), |(_, path)| path.as_str())
+    }
+}
+
+/// HTTP quote endpoint settings
+#[derive(Debug, Clone, Default)]
+pub struct HttpConfig {
+    /// Listen address, e.g. 127.0.0.1:8080 (None = disabled)
+    pub addr: Option<String>,
+}
+
+impl HttpConfig {
+    /// Load from HTTP_ADDR (unset or empty disables)
+    pub fn from_env() -> Self {
+        Self {
+            addr: std::env::var("HTTP_ADDR").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+        }
+    }
+}
+
+/// Admin socket and runtime feature flag settings
+#[derive(Debug, Clone, Default)]
+pub struct AdminConfig {
+    /// Listen address, e.g. 127.0.0.1:9100 (None = disabled)
+    pub addr: Option<String>,
+    /// Shared secret a client sends as `auth <token>` before any command
+    /// (None = no authentication, loopback addresses only)
+    pub token: Option<String>,
+    /// Initial flags, e.g. "-recorder,-anomaly" (see `flags::FeatureFlags::from_spec`)
+    pub feature_flags: String,
+}
+
+impl AdminConfig {
+    /// Load from ADMIN_ADDR (unset or empty disables), ADMIN_TOKEN and
+    /// FEATURE_FLAGS; fails on an address off loopback without a token
+    pub fn from_env() -> anyhow::Result<Self> {
+        let config = Self {
+            addr: std::env::var("ADMIN_ADDR").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+            token: std::env::var("ADMIN_TOKEN").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+            feature_flags: std::env::var("FEATURE_FLAGS").unwrap_or_default(),
+        };
+        match config.exposure_refusal() {
+            Some(reason) => anyhow::bail!("ADMIN_ADDR: {}", reason),
+            None => Ok(config),
+        }
+    }
+
+    /// Why the admin socket must not be served with this config, if it mustn't:
+    /// anyone who can reach it can pause sinks and restart connections, so
+    /// without a token it stays on loopback
+    pub fn exposure_refusal(&self) -> Option<String> {
+        let addr = self.addr.as_deref()?;
+        if self.token.is_some() {
+            return None;
+        }
+        let loopback = match addr.parse::<std::net::SocketAddr>() {
+            Ok(addr) => addr.ip().is_loopback(),
+            Err(_) => addr.rsplit_once(':').is_some_and(|(host, _)| host.eq_ignore_ascii_case("localhost")),
+        };
+        (!loopback).then(|| format!("{} is not a loopback address; set ADMIN_TOKEN to serve it there", addr))
+    }
+}
+
+/// Latency budget / shed mode settings (see shed)
+#[derive(Debug, Clone)]
+pub struct ShedConfig {
+    /// p99 processing time allowed, µs (0 = shed mode disabled)
+    pub budget_us: u64,
+    /// Consecutive seconds over (or back under) the budget before switching
+    pub breach_secs: u32,
+    /// Feature flags turned off while shedding
+    pub flags: Vec<Flag>,
+}
+
+impl ShedConfig {
+    /// Load from LATENCY_BUDGET_US / LATENCY_BUDGET_SECS / SHED_FLAGS
+    pub fn from_env() -> Self {
+        Self {
+            budget_us: env_or("LATENCY_BUDGET_US", 0),
+            breach_secs: env_or("LATENCY_BUDGET_SECS", 5).max(1),
+            flags: env_list_or("SHED_FLAGS", vec![Flag::Recorder]),
+        }
+    }
+}
+
+/// Exchange-to-local clock drift alerts (see drift)
+#[derive(Debug, Clone)]
+pub struct ClockDriftConfig {
+    /// Alert when |local - exchange event time| exceeds this, ms (0 = no offset alert)
+    pub max_offset_ms: u64,
+    /// Alert when the offset moves this far from the window median, ms (0 = no jump alert)
+    pub jump_ms: u64,
+    /// Seconds of per-second offsets the median is taken over
+    pub window_secs: u32,
+}
+
+impl Default for ClockDriftConfig {
+    fn default() -> Self {
+        Self { max_offset_ms: 1_000, jump_ms: 250, window_secs: 60 }
+    }
+}
+
+impl ClockDriftConfig {
+    /// Load from CLOCK_DRIFT_MAX_MS / CLOCK_DRIFT_JUMP_MS / CLOCK_DRIFT_WINDOW_SECS
+    pub fn from_env() -> Self {
+        let d = Self::default();
+        Self {
+            max_offset_ms: env_or("CLOCK_DRIFT_MAX_MS", d.max_offset_ms),
+            jump_ms: env_or("CLOCK_DRIFT_JUMP_MS", d.jump_ms),
+            window_secs: env_or("CLOCK_DRIFT_WINDOW_SECS", d.window_secs),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.max_offset_ms > 0 || self.jump_ms > 0
+    }
+}
+
+/// Canary invariant checks (see canary)
+#[derive(Debug, Clone)]
+pub struct CanaryConfig {
+    /// Invariants checked on every quote (empty = canary checks off)
+    pub checks: Vec<crate::canary::Invariant>,
+    /// Processing time limit for the `latency` check, µs
+    pub max_latency_us: u64,
+    /// Exit with the invariant's code on the first violation (otherwise log)
+    pub exit: bool,
+}
+
+impl Default for CanaryConfig {
+    fn default() -> Self {
+        Self { checks: Vec::new(), max_latency_us: 5_000, exit: false }
+    }
+}
+
+impl CanaryConfig {
+    /// Load from CANARY_CHECKS / CANARY_MAX_LATENCY_US / CANARY_EXIT; an
+    /// unknown check name is an error (a typo must not turn a check off)
+    pub fn from_env() -> anyhow::Result<Self> {
+        let d = Self::default();
+        Ok(Self {
+            checks: env_list_strict("CANARY_CHECKS", d.checks)?,
+            max_latency_us: env_or("CANARY_MAX_LATENCY_US", d.max_latency_us),
+            exit: env_or("CANARY_EXIT", d.exit as u8) != 0,
+        })
+    }
+}
+
+/// System clock synchronization check (see preflight::check_clock_sync)
+#[derive(Debug, Clone)]
+pub struct ClockSyncConfig {
+    /// Refuse to start while the clock is unsynchronized (otherwise warn)
+    pub require: bool,
+    /// Also treat a kernel max error bound above this as unsynchronized, ms (0 = off)
+    pub max_error_ms: u64,
+    /// Recheck every N seconds while running, logging changes (0 = startup only)
+    pub check_secs: u64,
+}
+
+impl Default for ClockSyncConfig {
+    fn default() -> Self {
+        Self { require: false, max_error_ms: 0, check_secs: 60 }
+    }
+}
+
+impl ClockSyncConfig {
+    /// Load from CLOCK_SYNC_REQUIRE / CLOCK_SYNC_MAX_ERROR_MS / CLOCK_SYNC_CHECK_SECS
+    pub fn from_env() -> Self {
+        let d = Self::default();
+        Self {
+            require: env_or("CLOCK_SYNC_REQUIRE", d.require as u8) != 0,
+            max_error_ms: env_or("CLOCK_SYNC_MAX_ERROR_MS", d.max_error_ms),
+            check_secs: env_or("CLOCK_SYNC_CHECK_SECS", d.check_secs),
+        }
+    }
+}
+
+/// Reconnect budget shared by every connection (see reconnect::ReconnectBucket)
+#[derive(Debug, Clone)]
+pub struct ReconnectBudgetConfig {
+    /// Reconnect attempts per minute across all connections (0 = unlimited)
+    pub per_min: u32,
+    /// Attempts allowed at once before the rate applies
+    pub burst: u32,
+}
+
+impl Default for ReconnectBudgetConfig {
+    fn default() -> Self {
+        Self { per_min: 60, burst: 10 }
+    }
+}
+
+impl ReconnectBudgetConfig {
+    /// Load from RECONNECT_PER_MIN / RECONNECT_BURST
+    pub fn from_env() -> Self {
+        let d = Self::default();
+        Self {
+            per_min: env_or("RECONNECT_PER_MIN", d.per_min),
+            burst: env_or("RECONNECT_BURST", d.burst),
+        }
+    }
+}
+
+/// Local WebSocket re-broadcast settings
+#[derive(Debug, Clone, Default)]
+pub struct BroadcastConfig {
+    /// Listen address, e.g. 127.0.0.1:9001 (None = disabled)
+    pub addr: Option<String>,
+    /// Send the latest quote per symbol every N ms (0 = every update)
+    pub conflate_ms: u64,
+    /// Updates buffered per client before a slow client misses some
+    pub capacity: usize,
+}
+
+impl BroadcastConfig {
+    /// Load from BROADCAST_ADDR (unset or empty disables) / BROADCAST_CONFLATE_MS / BROADCAST_CAPACITY
+    pub fn from_env() -> Self {
+        Self {
+            addr: std::env::var("BROADCAST_ADDR").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+            conflate_ms: env_or("BROADCAST_CONFLATE_MS", 0),
+            capacity: env_or("BROADCAST_CAPACITY", 65_536),
+        }
+    }
+}
+
+/// Reader registration region settings (see crate::readers)
+#[derive(Debug, Clone)]
+pub struct ReadersConfig {
+    /// Region file, created if missing (None = disabled)
+    pub path: Option<String>,
+    /// Reader entries in a newly created region
+    pub max_readers: u64,
+    /// How often registered readers are checked
+    pub check_interval_ms: u64,
+    /// A reader without a heartbeat for this long is reported dead
+    pub dead_after_ms: u64,
+    /// A reader this many updates behind on a slot it reads is reported lagging
+    pub lag_updates: u64,
+}
+
+impl Default for ReadersConfig {
+    fn default() -> Self {
+        Self { path: None, max_readers: 64, check_interval_ms: 1_000, dead_after_ms: 5_000, lag_updates: 1_000 }
+    }
+}
+
+impl ReadersConfig {
+    /// Load from SHM_READERS_PATH (unset or empty disables) / SHM_READERS_MAX /
+    /// SHM_READERS_CHECK_MS / SHM_READERS_DEAD_MS / SHM_READERS_LAG_UPDATES
+    pub fn from_env() -> Self {
+        let d = Self::default();
+        Self {
+            path: std::env::var("SHM_READERS_PATH").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+            max_readers: env_or("SHM_READERS_MAX", d.max_readers),
+            check_interval_ms: env_or("SHM_READERS_CHECK_MS", d.check_interval_ms).max(1),
+            dead_after_ms: env_or("SHM_READERS_DEAD_MS", d.dead_after_ms),
+            lag_updates: env_or("SHM_READERS_LAG_UPDATES", d.lag_updates),
+        }
+    }
+}
+
+/// Liquidation (forceOrder) capture settings
+#[derive(Debug, Clone)]
+pub struct LiquidationConfig {
+    /// Event ring file, created if missing (None = disabled)
+    pub path: Option<String>,
+    /// Events kept in a newly created ring
+    pub capacity: u64,
+}
+
+impl LiquidationConfig {
+    /// Load from LIQUIDATION_SHM_PATH (unset or empty disables) / LIQUIDATION_RING_CAPACITY
+    pub fn from_env() -> Self {
+        Self {
+            path: std::env::var("LIQUIDATION_SHM_PATH").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+            capacity: env_or("LIQUIDATION_RING_CAPACITY", 65_536),
+        }
+    }
+}
+
+/// Quote history ring filled by the `backfill` binary
+#[derive(Debug, Clone)]
+pub struct HistoryConfig {
+    /// History ring file, created if missing (None = no ring)
+    pub path: Option<String>,
+    /// Quotes kept in a newly created ring
+    pub capacity: u64,
+    /// Backfill only quotes from the last N seconds (0 = everything captured)
+    pub since_secs: u64,
+}
+
+impl HistoryConfig {
+    /// Load from HISTORY_SHM_PATH (unset or empty disables) / HISTORY_RING_CAPACITY / BACKFILL_SINCE_SECS
+    pub fn from_env() -> Self {
+        Self {
+            path: std::env::var("HISTORY_SHM_PATH").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+            capacity: env_or("HISTORY_RING_CAPACITY", 1 << 20),
+            since_secs: env_or("BACKFILL_SINCE_SECS", 0),
+        }
+    }
+}
+
+/// Order books from the depth diff stream (see depth)
+#[derive(Debug, Clone)]
+pub struct DepthConfig {
+    /// Depth region file, created if missing (None = depth streams not captured)
+    pub path: Option<String>,
+    /// Levels a side kept in a newly created region
+    pub levels: u64,
+    /// REST API base URL for snapshots
+    pub rest_base_url: String,
+    /// Per-request timeout
+    pub timeout_ms: u64,
+}
+
+impl DepthConfig {
+    /// Load from DEPTH_SHM_PATH (unset or empty disables) / DEPTH_LEVELS / REST_BASE_URL / REST_TIMEOUT_MS
+    pub fn from_env() -> Self {
+        Self {
+            path: std::env::var("DEPTH_SHM_PATH").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+            levels: env_or("DEPTH_LEVELS", 10),
+            rest_base_url: env_or("REST_BASE_URL", "https://fapi.binance.com".to_string()),
+            timeout_ms: env_or("REST_TIMEOUT_MS", 5_000),
+        }
+    }
+}
+
+/// Metrics backend and export settings (see metrics)
+#[derive(Debug, Clone)]
+pub struct MetricsConfig {
+    pub backend: crate::metrics::Backend,
+    /// Prometheus scrape address
+    pub addr: String,
+    /// Prepended to every metric name
+    pub prefix: String,
+    /// Stats exported this often
+    pub interval_ms: u64,
+    /// StatsD agent address (host:port)
+    pub statsd_addr: String,
+    /// Send labels as DogStatsD tags (else appended to the metric name)
+    pub statsd_tags: bool,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            backend: crate::metrics::Backend::None,
+            addr: "127.0.0.1:9464".to_string(),
+            prefix: "binance_writer".to_string(),
+            interval_ms: 10_000,
+            statsd_addr: "127.0.0.1:8125".to_string(),
+            statsd_tags: true,
+        }
+    }
+}
+
+impl MetricsConfig {
+    /// Load from METRICS_BACKEND (invalid is warned about and off) / METRICS_ADDR / METRICS_PREFIX / METRICS_INTERVAL_MS /
+    /// STATSD_ADDR / STATSD_TAGS
+    pub fn from_env() -> Self {
+        let d = Self::default();
+        let backend = std::env::var("METRICS_BACKEND").ok().map_or(Ok(d.backend), |s| s.parse()).unwrap_or_else(|e| {
+            eprintln!("[WARN] METRICS_BACKEND ignored: {:#}", e);
+            d.backend
+        });
+        Self {
+            backend,
+            addr: env_or("METRICS_ADDR", d.addr),
+            prefix: env_or("METRICS_PREFIX", d.prefix),
+            interval_ms: env_or("METRICS_INTERVAL_MS", d.interval_ms).max(1),
+            statsd_addr: env_or("STATSD_ADDR", d.statsd_addr),
+            statsd_tags: env_or("STATSD_TAGS", d.statsd_tags as u8) != 0,
+        }
+    }
+}
+
+/// OTLP span export (see trace); the variable names are the OpenTelemetry SDK ones
+#[derive(Debug, Clone)]
+pub struct TraceConfig {
+    /// Collector base URL, spans POSTed to `<endpoint>/v1/traces` (None = off)
+    pub endpoint: Option<String>,
+    /// `service.name` resource attribute
+    pub service_name: String,
+    /// Finished spans are exported this often
+    pub interval_ms: u64,
+    /// Finished spans buffered for export; more are dropped
+    pub queue: usize,
+}
+
+impl Default for TraceConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            service_name: "binance-futures-writer".to_string(),
+            interval_ms: 5_000,
+            queue: 2_048,
+        }
+    }
+}
+
+impl TraceConfig {
+    /// Load from OTEL_EXPORTER_OTLP_ENDPOINT / OTEL_SERVICE_NAME / OTEL_BSP_SCHEDULE_DELAY /
+    /// OTEL_BSP_MAX_QUEUE_SIZE
+    pub fn from_env() -> Self {
+        let d = Self::default();
+        Self {
+            endpoint: std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok()
+                .map(|s| s.trim().trim_end_matches('/').to_string())
+                .filter(|s| !s.is_empty()),
+            service_name: env_or("OTEL_SERVICE_NAME", d.service_name),
+            interval_ms: env_or("OTEL_BSP_SCHEDULE_DELAY", d.interval_ms).max(1),
+            queue: env_or("OTEL_BSP_MAX_QUEUE_SIZE", d.queue).max(1),
+        }
+    }
+}
+
+/// Latency injection test mode (see inject)
+#[derive(Debug, Clone)]
+pub struct InjectConfig {
+    /// Delay drawn per message (None = off)
+    pub latency: Option<crate::inject::Distribution>,
+    /// PRNG seed, so a run's delays can be replayed
+    pub seed: u64,
+}
+
+impl InjectConfig {
+    /// Load from INJECT_LATENCY (unset or empty = off; invalid is warned about and off) / INJECT_SEED
+    pub fn from_env() -> Self {
+        let latency = std::env::var("INJECT_LATENCY").ok().filter(|s| !s.trim().is_empty()).and_then(|s| {
+            s.parse()
+                .map_err(|e| eprintln!("[WARN] INJECT_LATENCY ignored: {:#}", e))
+                .ok()
+        });
+        Self { latency, seed: env_or("INJECT_SEED", 1) }
+    }
+}
+
+/// Chaos test mode (see chaos; only with the `chaos` feature)
+#[cfg(feature = "chaos")]
+#[derive(Debug, Clone, Default)]
+pub struct ChaosConfig {
+    /// Mean time before a session is dropped (exponential; 0 = never)
+    pub close_mean_ms: u64,
+    /// Extra reconnect delay, uniform in 0..=this
+    pub reconnect_delay_ms: u64,
+    /// Percent of feed frames corrupted before decoding
+    pub corrupt_pct: f64,
+    /// PRNG seed, so a run's faults can be replayed
+    pub seed: u64,
+}
+
+#[cfg(feature = "chaos")]
+impl ChaosConfig {
+    /// Load from CHAOS_CLOSE_MEAN_MS / CHAOS_RECONNECT_DELAY_MS / CHAOS_CORRUPT_PCT / CHAOS_SEED
+    pub fn from_env() -> Self {
+        Self {
+            close_mean_ms: env_or("CHAOS_CLOSE_MEAN_MS", 0),
+            reconnect_delay_ms: env_or("CHAOS_RECONNECT_DELAY_MS", 0),
+            corrupt_pct: env_or("CHAOS_CORRUPT_PCT", 0.0f64).clamp(0.0, 100.0),
+            seed: env_or("CHAOS_SEED", 1),
+        }
+    }
+
+    /// Any fault configured
+    pub fn enabled(&self) -> bool {
+        self.close_mean_ms > 0 || self.reconnect_delay_ms > 0 || self.corrupt_pct > 0.0
+    }
+}
+
+/// `shm_compare` settings (see compare)
+#[derive(Debug, Clone)]
+pub struct CompareConfig {
+    /// Source ids compared: B's mid against A's
+    pub source_a: u64,
+    pub source_b: u64,
+    /// Poll interval of both sources' slots
+    pub poll_us: u64,
+    /// Table printed (and CSV rewritten) every N seconds
+    pub report_secs: u64,
+    /// Rows of the printed table (0 = all)
+    pub report_top: usize,
+    /// Mid difference that counts as divergent, bps
+    pub alert_bps: i64,
+    /// How long a divergence lasts before it is alerted
+    pub alert_ms: u64,
+    /// CSV export of the per-symbol statistics (None = none)
+    pub csv_path: Option<String>,
+    /// Stop after N seconds (0 = run until killed)
+    pub duration_secs: u64,
+}
+
+impl Default for CompareConfig {
+    fn default() -> Self {
+        Self {
+            source_a: 1,
+            source_b: 2,
+            poll_us: 1_000,
+            report_secs: 10,
+            report_top: 20,
+            alert_bps: 50,
+            alert_ms: 1_000,
+            csv_path: None,
+            duration_secs: 0,
+        }
+    }
+}
+
+impl CompareConfig {
+    /// Load from COMPARE_SOURCE_A / COMPARE_SOURCE_B / COMPARE_POLL_US / COMPARE_REPORT_SECS /
+    /// COMPARE_REPORT_TOP / COMPARE_ALERT_BPS / COMPARE_ALERT_MS / COMPARE_CSV / COMPARE_DURATION_SECS
+    pub fn from_env() -> Self {
+        let d = Self::default();
+        Self {
+            source_a: env_or("COMPARE_SOURCE_A", d.source_a),
+            source_b: env_or("COMPARE_SOURCE_B", d.source_b),
+            poll_us: env_or("COMPARE_POLL_US", d.poll_us).max(1),
+            report_secs: env_or("COMPARE_REPORT_SECS", d.report_secs).max(1),
+            report_top: env_or("COMPARE_REPORT_TOP", d.report_top),
+            alert_bps: env_or("COMPARE_ALERT_BPS", d.alert_bps),
+            alert_ms: env_or("COMPARE_ALERT_MS", d.alert_ms),
+            csv_path: std::env::var("COMPARE_CSV").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+            duration_secs: env_or("COMPARE_DURATION_SECS", d.duration_secs),
+        }
+    }
+}
+
+/// Per-slot exchange sequence sidecar (see sequence)
+#[derive(Debug, Clone)]
+pub struct SequenceConfig {
+    /// Sequence region file, created if missing (None = updateIds not kept)
+    pub path: Option<String>,
+}
+
+impl SequenceConfig {
+    /// Load from SHM_SEQUENCE_PATH (unset or empty disables)
+    pub fn from_env() -> Self {
+        Self {
+            path: std::env::var("SHM_SEQUENCE_PATH").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+        }
+    }
+}
+
+/// Open interest poller settings
+#[derive(Debug, Clone)]
+pub struct OiConfig {
+    /// Poll interval (0 = disabled)
+    pub interval_ms: u64,
+    /// Source row of the quotes SHM the values go to
+    pub source_id: u64,
+    /// REST API base URL
+    pub rest_base_url: String,
+    /// Per-request timeout
+    pub timeout_ms: u64,
+}
+
+impl OiConfig {
+    /// Load from OI_POLL_INTERVAL_MS / OI_SOURCE_ID / REST_BASE_URL / REST_TIMEOUT_MS
+    pub fn from_env() -> Self {
+        Self {
+            interval_ms: env_or("OI_POLL_INTERVAL_MS", 0),
+            source_id: env_or("OI_SOURCE_ID", 2),
+            rest_base_url: env_or("REST_BASE_URL", "https://fapi.binance.com".to_string()),
+            timeout_ms: env_or("REST_TIMEOUT_MS", 5_000),
+        }
+    }
+}
+
+/// Instrument trading status polling (see trading_status)
+#[derive(Debug, Clone)]
+pub struct TradingStatusConfig {
+    /// exchangeInfo poll interval (0 = disabled)
+    pub interval_ms: u64,
+    /// Append status changes here as JSON lines (None = log only)
+    pub event_log: Option<String>,
+    /// REST API base URL
+    pub rest_base_url: String,
+    /// Per-request timeout
+    pub timeout_ms: u64,
+}
+
+impl TradingStatusConfig {
+    /// Load from TRADING_STATUS_POLL_MS / TRADING_STATUS_LOG / REST_BASE_URL / REST_TIMEOUT_MS
+    pub fn from_env() -> Self {
+        Self {
+            interval_ms: env_or("TRADING_STATUS_POLL_MS", 0),
+            event_log: std::env::var("TRADING_STATUS_LOG").ok().filter(|s| !s.is_empty()),
+            rest_base_url: env_or("REST_BASE_URL", "https://fapi.binance.com".to_string()),
+            timeout_ms: env_or("REST_TIMEOUT_MS", 5_000),
+        }
+    }
+}
+
+/// Quote recorder settings
+#[derive(Debug, Clone, Default)]
+pub struct RecorderConfig {
+    /// Recording file, must not exist yet (None = disabled)
+    pub path: Option<String>,
+}
+
+impl RecorderConfig {
+    /// Load from RECORD_FILE (unset or empty disables)
+    pub fn from_env() -> Self {
+        Self {
+            path: std::env::var("RECORD_FILE").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+        }
+    }
+}
+
+/// Status file settings (inventory info for fleet tooling)
+#[derive(Debug, Clone)]
+pub struct StatusFileConfig {
+    /// Status file path (None = disabled)
+    pub path: Option<String>,
+    pub interval_ms: u64,
+}
+
+impl StatusFileConfig {
+    /// Load from STATUS_FILE (empty string disables) / STATUS_FILE_INTERVAL_MS
+    pub fn from_env() -> Self {
+        let default_path = std::env::temp_dir()
+            .join("binance_futures_writer.status.json")
+            .to_string_lossy()
+            .into_owned();
+        let path = env_or("STATUS_FILE", default_path);
+        Self {
+            path: if path.is_empty() { None } else { Some(path) },
+            interval_ms: env_or("STATUS_FILE_INTERVAL_MS", 5000),
+        }
+    }
+}
+
+/// Price parser settings
+#[derive(Debug, Clone)]
+pub struct PriceConfig {
+    pub options: PriceParseOptions,
+    /// Symbols stored at a scale other than 1e8
+    pub scale_overrides: Vec<ScaleOverride>,
+}
+
+/// Per-symbol price scale: "SYMBOL:1e4" or "SYMBOL:10000" (a power of ten up to 1e8)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScaleOverride {
+    pub symbol: String,
+    /// Decimals kept: the scale is 10^decimals
+    pub decimals: u32,
+}
+
+impl FromStr for ScaleOverride {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (symbol, scale) = s.split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("Expected SYMBOL:SCALE, got {:?}", s))?;
+        let decimals = crate::price::parse_scale(scale)?;
+        Ok(Self { symbol: symbol.trim().to_uppercase(), decimals })
+    }
+}
+
+impl PriceConfig {
+    /// Load from PRICE_ROUNDING (half_up | half_even | truncate),
+    /// PRICE_PARSE_MODE (lenient | strict) and PRICE_SCALE_OVERRIDES
+    pub fn from_env() -> Self {
+        Self {
+            options: PriceParseOptions {
+                rounding: env_or("PRICE_ROUNDING", RoundingMode::HalfUp),
+                mode: env_or("PRICE_PARSE_MODE", ParseMode::Lenient),
+            },
+            scale_overrides: env_list_or("PRICE_SCALE_OVERRIDES", Vec::new()),
+        }
+    }
+}
+
+/// WebSocket connection settings
+#[derive(Debug, Clone)]
+pub struct WsConfig {
+    /// Endpoint base URL
+    pub base_url: String,
+    /// Alternate endpoints, rotated to on TLS failures
+    pub fallback_urls: Vec<String>,
+    /// DNS + TCP connect timeout
+    pub connect_timeout_ms: u64,
+    /// Start a connect to the next resolved address if the previous one hasn't
+    /// succeeded within this long (happy eyeballs; 0 = all addresses at once)
+    pub connect_race_delay_ms: u64,
+    /// TLS + WebSocket upgrade handshake timeout
+    pub handshake_timeout_ms: u64,
+    /// Reconnect when nothing (data, ping or pong) is read for this long (0 = disabled)
+    pub idle_timeout_ms: u64,
+    /// Frames sent to keep the connection alive (see ws::Keepalive)
+    pub keepalive: crate::ws::Keepalive,
+    /// Use the experimental io_uring socket path (needs the `io-uring` feature)
+    pub io_uring: bool,
+    /// Subscribe to the all-market !bookTicker stream and filter locally
+    pub all_market: bool,
+    /// Assign symbols to connections by a hash of their name instead of file order
+    pub stable_chunks: bool,
+    /// Require every bookTicker field (u, s, b, B, a, A, T, E) well-formed; drop violating frames
+    pub strict_schema: bool,
+    /// Keep an idle duplicate connection per chunk, promoted when the primary drops
+    pub warm_spares: bool,
+    /// Read every chunk on two connections (the second starting on the next
+    /// endpoint) and keep the first copy of each update id
+    pub arbitrate: bool,
+    /// Extra handshake request headers (User-Agent, API key headers)
+    pub headers: Vec<(String, String)>,
+    /// Extra query parameters appended to every stream URL (sent as given, not encoded)
+    pub query: Vec<(String, String)>,
+    /// Unless `warmup_min_pct` of a new session's symbols deliver a message
+    /// within this long, resubscribe the silent ones (or reconnect if none
+    /// delivered; 0 = disabled)
+    pub warmup_timeout_ms: u64,
+    /// Percent of a connection's symbols the warm-up needs
+    pub warmup_min_pct: u64,
+    /// Check LIST_SUBSCRIPTIONS against the intended streams this often,
+    /// resubscribing missing ones (0 = disabled)
+    pub subscription_check_ms: u64,
+    /// Injected connection faults (CHAOS_*; None = off)
+    #[cfg(feature = "chaos")]
+    pub chaos: Option<std::sync::Arc<crate::chaos::Chaos>>,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        Self {
+            base_url: crate::ws::WS_BASE.to_string(),
+            fallback_urls: Vec::new(),
+            connect_timeout_ms: 5_000,
+            connect_race_delay_ms: 250,
+            handshake_timeout_ms: 10_000,
+            idle_timeout_ms: crate::ws::Keepalive::default().default_idle_timeout_ms(),
+            keepalive: crate::ws::Keepalive::default(),
+            io_uring: false,
+            all_market: false,
+            stable_chunks: false,
+            strict_schema: false,
+            warm_spares: false,
+            arbitrate: false,
+            headers: Vec::new(),
+            query: Vec::new(),
+            warmup_timeout_ms: 0,
+            warmup_min_pct: 90,
+            subscription_check_ms: 60_000,
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+}
+
+impl WsConfig {
+    /// All endpoints in rotation order (base_url first)
+    pub fn endpoints(&self) -> Vec<String> {
+        std::iter::once(self.base_url.clone())
+            .chain(self.fallback_urls.iter().cloned())
+            .collect()
+    }
+
+    /// Load from WS_BASE_URL / WS_FALLBACK_URLS / WS_CONNECT_TIMEOUT_MS / WS_CONNECT_RACE_DELAY_MS /
+    /// WS_HANDSHAKE_TIMEOUT_MS / WS_KEEPALIVE + WS_IDLE_TIMEOUT_MS (see `with_keepalive_env`) / WS_IO_URING / WS_ALL_MARKET / WS_STABLE_CHUNKS / WS_STRICT_SCHEMA /
+    /// WS_WARM_SPARES / WS_ARBITRATE / WS_HEADERS / WS_QUERY / WS_WARMUP_TIMEOUT_MS / WS_WARMUP_MIN_PCT /
+    /// WS_SUBSCRIPTION_CHECK_MS; fails on WS_ARBITRATE with WS_WARM_SPARES
+    pub fn from_env() -> anyhow::Result<Self> {
+        let d = Self::default();
+        let config = Self {
+            base_url: env_or("WS_BASE_URL", d.base_url),
+            fallback_urls: env_list_or("WS_FALLBACK_URLS", d.fallback_urls),
+            connect_timeout_ms: env_or("WS_CONNECT_TIMEOUT_MS", d.connect_timeout_ms),
+            connect_race_delay_ms: env_or("WS_CONNECT_RACE_DELAY_MS", d.connect_race_delay_ms),
+            handshake_timeout_ms: env_or("WS_HANDSHAKE_TIMEOUT_MS", d.handshake_timeout_ms),
+            idle_timeout_ms: d.idle_timeout_ms,
+            keepalive: d.keepalive,
+            io_uring: env_or("WS_IO_URING", d.io_uring as u8) != 0,
+            all_market: env_or("WS_ALL_MARKET", d.all_market as u8) != 0,
+            stable_chunks: env_or("WS_STABLE_CHUNKS", d.stable_chunks as u8) != 0,
+            strict_schema: env_or("WS_STRICT_SCHEMA", d.strict_schema as u8) != 0,
+            warm_spares: env_or("WS_WARM_SPARES", d.warm_spares as u8) != 0,
+            arbitrate: env_or("WS_ARBITRATE", d.arbitrate as u8) != 0,
+            headers: Self::parse_headers(&std::env::var("WS_HEADERS").unwrap_or_default()),
+            query: Self::parse_query(&std::env::var("WS_QUERY").unwrap_or_default()),
+            warmup_timeout_ms: env_or("WS_WARMUP_TIMEOUT_MS", d.warmup_timeout_ms),
+            warmup_min_pct: env_or("WS_WARMUP_MIN_PCT", d.warmup_min_pct),
+            subscription_check_ms: env_or("WS_SUBSCRIPTION_CHECK_MS", d.subscription_check_ms),
+            #[cfg(feature = "chaos")]
+            chaos: crate::chaos::Chaos::from_env().map(std::sync::Arc::new),
+        }
+        .with_keepalive_env("WS_");
+        match config.pairing_refusal() {
+            Some(reason) => anyhow::bail!("WS_ARBITRATE: {}", reason),
+            None => Ok(config),
+        }
+    }
+
+    /// Why the connections can't be paired as configured, if they can't:
+    /// an A/B pair and a warm spare pair are two uses of the same second
+    /// connection, and with both set the spares would silently not exist
+    pub fn pairing_refusal(&self) -> Option<String> {
+        (self.arbitrate && self.warm_spares)
+            .then(|| "A/B arbitration already reads every chunk on two connections; unset WS_WARM_SPARES".to_string())
+    }
+
+    /// This config with `{prefix}KEEPALIVE` / `{prefix}IDLE_TIMEOUT_MS` applied,
+    /// so a feed can keep alive the way its endpoint wants. A keepalive set
+    /// without an idle timeout brings its mode's default timeout; an invalid
+    /// keepalive is warned about and the current one kept
+    pub fn with_keepalive_env(self, prefix: &str) -> Self {
+        let name = format!("{}KEEPALIVE", prefix);
+        let keepalive = match std::env::var(&name).map(|s| s.parse::<crate::ws::Keepalive>()) {
+            Ok(Ok(keepalive)) => keepalive,
+            Ok(Err(e)) => {
+                eprintln!("[WARN] {} ignored: {:#}", name, e);
+                self.keepalive
+            }
+            Err(_) => self.keepalive,
+        };
+        let idle_default = if keepalive == self.keepalive { self.idle_timeout_ms } else { keepalive.default_idle_timeout_ms() };
+        Self {
+            idle_timeout_ms: env_or(&format!("{}IDLE_TIMEOUT_MS", prefix), idle_default),
+            keepalive,
+            ..self
+        }
+    }
+
+    /// Parse "Name: value; Name2: value2"; entries that aren't a valid header
+    /// (token name, printable value) are dropped with a warning
+    pub fn parse_headers(spec: &str) -> Vec<(String, String)> {
+        let is_token = |b: u8| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b);
+        spec.split(';')
+            .filter(|entry| !entry.trim().is_empty())
+            .filter_map(|entry| {
+                let parsed = entry.split_once(':').map(|(name, value)| (name.trim(), value.trim())).filter(|(name, value)| {
+                    !name.is_empty() && name.bytes().all(is_token) && value.bytes().all(|b| b == b'\t' || (b' '..=b'~').contains(&b))
+                });
+                if parsed.is_none() {
+                    eprintln!("[WARN] WS_HEADERS entry {:?} ignored (expected \"Name: value\")", entry.trim());
+                }
+                parsed.map(|(name, value)| (name.to_string(), value.to_string()))
+            })
+            .collect()
+    }
+
+    /// Parse "key=value&key2=value2" (a key alone has an empty value)
+    pub fn parse_query(spec: &str) -> Vec<(String, String)> {
+        spec.split('&')
+            .filter_map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                let key = key.trim();
+                (!key.is_empty()).then(|| (key.to_string(), value.trim().to_string()))
+            })
+            .collect()
+    }
+}
+
+/// Decode pipeline settings (main feed): read loops hand raw frames to a decoder thread
+#[derive(Debug, Clone)]
+pub struct PipelineConfig {
+    pub enabled: bool,
+    /// Core the decoder thread is pinned to (None = not pinned)
+    pub cpu_core: Option<usize>,
+    /// Frames buffered per connection; more are dropped until the decoder catches up
+    pub capacity: usize,
+    /// Skip frames queued longer than this when a newer one for the symbol is pending (0 = never)
+    pub deadline_us: u64,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self { enabled: false, cpu_core: None, capacity: 16_384, deadline_us: 0 }
+    }
+}
+
+impl PipelineConfig {
+    /// Load from PIPELINE_DECODE / PIPELINE_CPU_CORE / PIPELINE_CAPACITY / PIPELINE_DEADLINE_US
+    pub fn from_env() -> Self {
+        let d = Self::default();
+        Self {
+            enabled: env_or("PIPELINE_DECODE", 0u8) != 0,
+            cpu_core: std::env::var("PIPELINE_CPU_CORE").ok().and_then(|s| s.trim().parse().ok()),
+            capacity: env_or("PIPELINE_CAPACITY", d.capacity),
+            deadline_us: env_or("PIPELINE_DEADLINE_US", d.deadline_us),
+        }
+    }
+}
+
+/// Priority feed settings: symbols that get their own connection(s) and thread
+#[derive(Debug, Clone, Default)]
+pub struct PriorityConfig {
+    /// Uppercase symbols (empty = no priority feed)
+    pub symbols: Vec<String>,
+    /// Core the priority thread is pinned to (None = not pinned)
+    pub cpu_core: Option<usize>,
+    /// Busy-poll the priority runtime instead of sleeping in epoll (burns the whole core)
+    pub busy_poll: bool,
+    /// Allow busy-poll on a core the kernel doesn't isolate (isolcpus/nohz_full)
+    pub busy_poll_force: bool,
+}
+
+impl PriorityConfig {
+    /// Why busy-poll must not run with this config, if it mustn't
+    ///
+    /// Spinning is only allowed on a dedicated pinned core: not the main loop's
+    /// core and, unless forced, one the kernel keeps other tasks off.
+    pub fn busy_poll_refusal(&self, main_core: usize, isolated: Option<&[usize]>) -> Option<String> {
+        let Some(core) = self.cpu_core else {
+            return Some("PRIORITY_CPU_CORE is not set".to_string());
+        };
+        if core == main_core {
+            return Some(format!("core {} is also the main loop's CPU_CORE", core));
+        }
+        if self.busy_poll_force {
+            return None;
+        }
+        match isolated {
+            Some(cores) if cores.contains(&core) => None,
+            Some(_) => Some(format!("core {} is not isolated (isolcpus); set PRIORITY_BUSY_POLL_FORCE=1 to override", core)),
+            None => Some("core isolation is unknown on this platform; set PRIORITY_BUSY_POLL_FORCE=1 to override".to_string()),
+        }
+    }
+
+    /// Load from PRIORITY_SYMBOLS (comma-separated) / PRIORITY_CPU_CORE /
+    /// PRIORITY_BUSY_POLL / PRIORITY_BUSY_POLL_FORCE
+    pub fn from_env() -> Self {
+        Self {
+            symbols: env_list_or::<String>("PRIORITY_SYMBOLS", Vec::new())
+                .into_iter()
+                .map(|s| s.to_uppercase())
+                .collect(),
+            cpu_core: std::env::var("PRIORITY_CPU_CORE").ok().and_then(|s| s.trim().parse().ok()),
+            busy_poll: env_or("PRIORITY_BUSY_POLL", 0u8) != 0,
+            busy_poll_force: env_or("PRIORITY_BUSY_POLL_FORCE", 0u8) != 0,
+        }
+    }
+}
+
+/// Snapshot of every environment setting the writer uses, read once
+///
+/// `App` takes it at startup and hands each component its part, so a
+/// variable changed while the process runs can't give two components
+/// different views of the deployment (the reload of the subscribe file is
+/// the one thing re-read at runtime).
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Core the main loop is pinned to (CPU_CORE, default 0)
+    pub cpu_core: usize,
+    pub log: LogConfig,
+    pub perf: PerfConfig,
+    pub anomaly: AnomalyConfig,
+    pub sla: SlaConfig,
+    pub synthetic: SyntheticConfig,
+    pub stats_file: StatsFileConfig,
+    pub shm: ShmConfig,
+    pub http: HttpConfig,
+    pub admin: AdminConfig,
+    pub shed: ShedConfig,
+    pub clock_drift: ClockDriftConfig,
+    pub canary: CanaryConfig,
+    pub clock_sync: ClockSyncConfig,
+    pub reconnect_budget: ReconnectBudgetConfig,
+    pub broadcast: BroadcastConfig,
+    pub readers: ReadersConfig,
+    pub liquidation: LiquidationConfig,
+    pub depth: DepthConfig,
+    pub metrics: MetricsConfig,
+    pub trace: TraceConfig,
+    pub inject: InjectConfig,
+    pub sequence: SequenceConfig,
+    pub oi: OiConfig,
+    pub trading_status: TradingStatusConfig,
+    pub recorder: RecorderConfig,
+    pub status_file: StatusFileConfig,
+    pub price: PriceConfig,
+    pub ws: WsConfig,
+    /// `ws` with the LIQUIDATION_WS_ keepalive overrides
+    pub liquidation_ws: WsConfig,
+    /// `ws` with the DEPTH_WS_ keepalive overrides
+    pub depth_ws: WsConfig,
+    pub pipeline: PipelineConfig,
+    pub priority: PriorityConfig,
+}
+
+impl Settings {
+    /// Read every section from the environment (see each section's `from_env`);
+    /// fails on the settings that are parsed strictly
+    pub fn from_env() -> anyhow::Result<Self> {
+        let ws = WsConfig::from_env()?;
+        Ok(Self {
+            cpu_core: env_or("CPU_CORE", 0),
+            log: LogConfig::from_env(),
+            perf: PerfConfig::from_env(),
+            anomaly: AnomalyConfig::from_env(),
+            sla: SlaConfig::from_env()?,
+            synthetic: SyntheticConfig::from_env(),
+            stats_file: StatsFileConfig::from_env(),
+            shm: ShmConfig::from_env()?,
+            http: HttpConfig::from_env(),
+            admin: AdminConfig::from_env()?,
+            shed: ShedConfig::from_env(),
+            clock_drift: ClockDriftConfig::from_env(),
+            canary: CanaryConfig::from_env()?,
+            clock_sync: ClockSyncConfig::from_env(),
+            reconnect_budget: ReconnectBudgetConfig::from_env(),
+            broadcast: BroadcastConfig::from_env(),
+            readers: ReadersConfig::from_env(),
+            liquidation: LiquidationConfig::from_env(),
+            depth: DepthConfig::from_env(),
+            metrics: MetricsConfig::from_env(),
+            trace: TraceConfig::from_env(),
+            inject: InjectConfig::from_env(),
+            sequence: SequenceConfig::from_env(),
+            oi: OiConfig::from_env(),
+            trading_status: TradingStatusConfig::from_env(),
+            recorder: RecorderConfig::from_env(),
+            status_file: StatusFileConfig::from_env(),
+            price: PriceConfig::from_env(),
+            liquidation_ws: ws.clone().with_keepalive_env("LIQUIDATION_WS_"),
+            depth_ws: ws.clone().with_keepalive_env("DEPTH_WS_"),
+            ws,
+            pipeline: PipelineConfig::from_env(),
+            priority: PriorityConfig::from_env(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_strict() {
+        use crate::canary::Invariant;
+
+        let name = "TEST_ENV_STRICT_MSYNC";
+        assert_eq!(env_strict(name, MsyncPolicy::Periodic).unwrap(), MsyncPolicy::Periodic);
+        std::env::set_var(name, "on-shutdown");
+        assert_eq!(env_strict(name, MsyncPolicy::Never).unwrap(), MsyncPolicy::OnShutdown);
+        std::env::set_var(name, "sometimes");
+        assert!(env_strict(name, MsyncPolicy::Never).is_err());
+        std::env::remove_var(name);
+
+        let name = "TEST_ENV_LIST_STRICT_CHECKS";
+        assert_eq!(env_list_strict::<Invariant>(name, vec![Invariant::Latency]).unwrap(), vec![Invariant::Latency]);
+        std::env::set_var(name, "crossed, Latency");
+        assert_eq!(env_list_strict::<Invariant>(name, Vec::new()).unwrap(), vec![Invariant::Crossed, Invariant::Latency]);
+        std::env::set_var(name, "crossed,latnecy");
+        let err = env_list_strict::<Invariant>(name, Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("latnecy"), "{}", err);
+        std::env::remove_var(name);
+    }
+
+    #[test]
+    fn test_admin_exposure() {
+        let config = |addr: &str, token: Option<&str>| AdminConfig {
+            addr: Some(addr.to_string()),
+            token: token.map(str::to_string),
+            ..Default::default()
+        };
+        assert_eq!(AdminConfig::default().exposure_refusal(), None);
+        assert_eq!(config("127.0.0.1:9100", None).exposure_refusal(), None);
+        assert_eq!(config("[::1]:9100", None).exposure_refusal(), None);
+        assert_eq!(config("localhost:9100", None).exposure_refusal(), None);
+        assert!(config("0.0.0.0:9100", None).exposure_refusal().is_some());
+        assert!(config("admin.internal:9100", None).exposure_refusal().is_some());
+        assert_eq!(config("0.0.0.0:9100", Some("s3cret")).exposure_refusal(), None);
+    }
+
+    #[test]
+    fn test_busy_poll_safeguards() {
+        let mut config = PriorityConfig { busy_poll: true, ..Default::default() };
+        assert!(config.busy_poll_refusal(0, Some(&[3])).is_some());
+
+        config.cpu_core = Some(0);
+        assert!(config.busy_poll_refusal(0, Some(&[0])).is_some());
+
+        config.cpu_core = Some(3);
+        assert_eq!(config.busy_poll_refusal(0, Some(&[2, 3])), None);
+        assert!(config.busy_poll_refusal(0, Some(&[2])).is_some());
+        assert!(config.busy_poll_refusal(0, None).is_some());
+
+        config.busy_poll_force = true;
+        assert_eq!(config.busy_poll_refusal(0, None), None);
+    }
+
+    #[test]
+    fn test_ws_headers_and_query_parse() {
+        let headers = WsConfig::parse_headers("User-Agent: siro/1.0 (linux); X-MBX-APIKEY: a=b:c ;bad header: x; nocolon;;");
+        assert_eq!(headers, vec![
+            ("User-Agent".to_string(), "siro/1.0 (linux)".to_string()),
+            ("X-MBX-APIKEY".to_string(), "a=b:c".to_string()),
+        ]);
+        assert!(WsConfig::parse_headers("X-Bad: line\nbreak").is_empty());
+
+        let query = WsConfig::parse_query("listenKey=abc&timeUnit=MICROSECOND&flag&=x");
+        assert_eq!(query, vec![
+            ("listenKey".to_string(), "abc".to_string()),
+            ("timeUnit".to_string(), "MICROSECOND".to_string()),
+            ("flag".to_string(), String::new()),
+        ]);
+    }
+
+    #[test]
+    fn test_arbitrate_excludes_warm_spares() {
+        assert_eq!(WsConfig { arbitrate: true, ..Default::default() }.pairing_refusal(), None);
+        assert_eq!(WsConfig { warm_spares: true, ..Default::default() }.pairing_refusal(), None);
+        assert!(WsConfig { arbitrate: true, warm_spares: true, ..Default::default() }.pairing_refusal().is_some());
+    }
+
+    #[test]
+    fn test_feed_keepalive_env() {
+        use crate::ws::Keepalive;
+
+        let base = WsConfig { idle_timeout_ms: 90_000, ..Default::default() };
+        let unset = base.clone().with_keepalive_env("TEST_FEED_A_WS_");
+        assert_eq!((unset.keepalive, unset.idle_timeout_ms), (Keepalive::default(), 90_000));
+
+        // A quieter mode brings a timeout that outlasts the server's pings
+        std::env::set_var("TEST_FEED_B_WS_KEEPALIVE", "none");
+        let quiet = base.clone().with_keepalive_env("TEST_FEED_B_WS_");
+        assert_eq!((quiet.keepalive, quiet.idle_timeout_ms), (Keepalive::None, Keepalive::None.default_idle_timeout_ms()));
+        std::env::set_var("TEST_FEED_B_WS_IDLE_TIMEOUT_MS", "0");
+        assert_eq!(base.clone().with_keepalive_env("TEST_FEED_B_WS_").idle_timeout_ms, 0);
+
+        std::env::set_var("TEST_FEED_C_WS_KEEPALIVE", "pong");
+        assert_eq!(base.with_keepalive_env("TEST_FEED_C_WS_").keepalive, Keepalive::default());
+        for name in ["TEST_FEED_B_WS_KEEPALIVE", "TEST_FEED_B_WS_IDLE_TIMEOUT_MS", "TEST_FEED_C_WS_KEEPALIVE"] {
+            std::env::remove_var(name);
+        }
+    }
+
+    #[test]
+    fn test_sla_tiers_parse() {
+        let tiers = SlaConfig::parse_tiers(" tier1:2000:btcusdt|ETHUSDT ;tier3:60000:*;").unwrap();
+        assert_eq!(tiers, vec![
+            SlaTier { name: "tier1".to_string(), max_age_ms: 2000, symbols: vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()] },
+            SlaTier { name: "tier3".to_string(), max_age_ms: 60000, symbols: Vec::new() },
+        ]);
+        assert!(SlaConfig::parse_tiers("").unwrap().is_empty());
+        for bad in ["tier1:2000", "tier1:2s:*", "tier1:0:*", ":2000:*", "tier1:2000:", "tier1:2000:|", "ok:1:*;bad"] {
+            assert!(SlaConfig::parse_tiers(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_scale_override_parse() {
+        let parsed: ScaleOverride = "shibusdt:1e4".parse().unwrap();
+        assert_eq!(parsed, ScaleOverride { symbol: "SHIBUSDT".to_string(), decimals: 4 });
+        assert_eq!("X:1000000".parse::<ScaleOverride>().unwrap().decimals, 6);
+        assert_eq!("X:1".parse::<ScaleOverride>().unwrap().decimals, 0);
+        for bad in ["X", "X:1e9", "X:5000", "X:0", "X:abc"] {
+            assert!(bad.parse::<ScaleOverride>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_dictionary_path_precedence() {
+        let resolve = |vars: &[(&str, &str)], existing: &[&str]| {
+            DictionaryPaths::resolve(
+                |name| vars.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string()),
+                |path| existing.iter().any(|e| std::path::Path::new(e) == path),
+            )
+        };
+        let cwd = std::env::current_dir().unwrap();
+
+        // Nothing set or found: the spec deployment
+        assert_eq!(resolve(&[("HOME", "/home/u")], &[]), DictionaryPaths::default());
+
+        // XDG: the config home before the config dirs, only where the file exists
+        let vars = [("HOME", "/home/u"), ("XDG_CONFIG_DIRS", "/opt/a:/opt/b")];
+        let paths = resolve(&vars, &["/opt/b/siro/dictionaries/configs/symbols.tsv",
+                                     "/home/u/.config/siro/dictionaries/configs/symbols.tsv",
+                                     "/opt/a/siro/dictionaries/subscribe/binance/binance_futures.txt"]);
+        assert_eq!(paths.symbols_tsv, "/home/u/.config/siro/dictionaries/configs/symbols.tsv");
+        assert_eq!(paths.subscribe_file, "/opt/a/siro/dictionaries/subscribe/binance/binance_futures.txt");
+
+        // SIRO_HOME beats XDG whether or not the file is there; explicit files beat both
+        let vars = [("HOME", "/home/u"), ("SIRO_HOME", "deploy"), ("SYMBOLS_TSV", " /srv/symbols.tsv ")];
+        let paths = resolve(&vars, &["/home/u/.config/siro/dictionaries/configs/symbols.tsv"]);
+        assert_eq!(paths.symbols_tsv, "/srv/symbols.tsv");
+        assert_eq!(std::path::Path::new(&paths.subscribe_file),
+                   cwd.join("deploy/dictionaries/subscribe/binance/binance_futures.txt"));
+        let paths = resolve(&[("SUBSCRIBE_FILE", "subscribe.txt"), ("SIRO_HOME", "")], &[]);
+        assert_eq!(std::path::Path::new(&paths.subscribe_file), cwd.join("subscribe.txt"));
+        assert_eq!(paths.symbols_tsv, DictionaryPaths::default().symbols_tsv);
+    }
+}
@@ -0,0 +1,115 @@
+//! Per-symbol accounting of suppressed updates
+//!
+//! Two places drop quotes on purpose: re-broadcast conflation (a newer quote
+//! replaces one still waiting for the symbol's next send) and shed-mode dedup
+//! (a quote equal to the slot's current one isn't rewritten). Both count here
+//! per symbol, with the longest suppression interval: for conflation, from the
+//! first replaced quote's arrival to the send that superseded it; for dedup,
+//! how long the slot's quote had stood when an update was skipped. Exported
+//! over the admin socket (`conflation`) and in the shutdown report, to check
+//! that conflation settings aren't hiding meaningful moves.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Default)]
+struct Counters {
+    suppressed: AtomicU64,
+    max_interval_us: AtomicU64,
+}
+
+/// One symbol's totals
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SymbolConflation {
+    pub symbol: String,
+    pub suppressed: u64,
+    pub max_interval_us: u64,
+}
+
+/// Suppressed update counters indexed by symbol_id
+pub struct ConflationStats {
+    counters: Vec<Counters>,
+    names: Vec<Option<String>>,
+}
+
+impl ConflationStats {
+    pub fn new(symbol_id_map: &HashMap<String, u64>) -> Self {
+        let n = symbol_id_map.values().max().map_or(0, |&id| id as usize + 1);
+        let mut names = vec![None; n];
+        for (symbol, &id) in symbol_id_map {
+            names[id as usize] = Some(symbol.clone());
+        }
+        Self { counters: (0..n).map(|_| Counters::default()).collect(), names }
+    }
+
+    /// Count one update of `symbol_id` that was dropped
+    #[inline]
+    pub fn record_suppressed(&self, symbol_id: u64) {
+        if let Some(counters) = self.counters.get(symbol_id as usize) {
+            counters.suppressed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Note a suppression interval of `symbol_id` (the max is kept)
+    #[inline]
+    pub fn record_interval(&self, symbol_id: u64, interval_us: u64) {
+        if let Some(counters) = self.counters.get(symbol_id as usize) {
+            counters.max_interval_us.fetch_max(interval_us, Ordering::Relaxed);
+        }
+    }
+
+    /// Symbols with suppressed updates, most suppressed first
+    pub fn snapshot(&self) -> Vec<SymbolConflation> {
+        let mut symbols: Vec<SymbolConflation> = self.counters
+            .iter()
+            .zip(&self.names)
+            .filter_map(|(counters, name)| {
+                let suppressed = counters.suppressed.load(Ordering::Relaxed);
+                let symbol = name.as_ref().filter(|_| suppressed > 0)?.clone();
+                Some(SymbolConflation { symbol, suppressed, max_interval_us: counters.max_interval_us.load(Ordering::Relaxed) })
+            })
+            .collect();
+        symbols.sort_by(|a, b| b.suppressed.cmp(&a.suppressed).then_with(|| a.symbol.cmp(&b.symbol)));
+        symbols
+    }
+
+    /// Log totals and the `top` most suppressed symbols
+    pub fn report(&self, top: usize) {
+        let symbols = self.snapshot();
+        if symbols.is_empty() {
+            return;
+        }
+        let total: u64 = symbols.iter().map(|s| s.suppressed).sum();
+        eprintln!("[STATS] Suppressed updates (conflation/dedup): {} across {} symbols", total, symbols.len());
+        for s in symbols.iter().take(top) {
+            eprintln!("[STATS]   {}: {} suppressed, longest {:.1} ms", s.symbol, s.suppressed, s.max_interval_us as f64 / 1000.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_symbol_counts() {
+        let map: HashMap<String, u64> = [("BTCUSDT", 0), ("ETHUSDT", 2), ("XRPUSDT", 3)].map(|(s, id)| (s.to_string(), id)).into();
+        let stats = ConflationStats::new(&map);
+        for _ in 0..3 {
+            stats.record_suppressed(2);
+        }
+        stats.record_suppressed(0);
+        stats.record_interval(2, 40_000);
+        stats.record_interval(2, 15_000);
+        // Unknown ids are ignored
+        stats.record_suppressed(9);
+        stats.record_interval(9, 1);
+
+        let snap = stats.snapshot();
+        assert_eq!(snap, vec![
+            SymbolConflation { symbol: "ETHUSDT".to_string(), suppressed: 3, max_interval_us: 40_000 },
+            SymbolConflation { symbol: "BTCUSDT".to_string(), suppressed: 1, max_interval_us: 0 },
+        ]);
+    }
+}
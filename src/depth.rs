@@ -0,0 +1,731 @@
+//! Order books from the `@depth` diff stream, best N levels into a depth SHM region
+//!
+//! For symbols subscribed with `streams=depth`, where bookTicker's top of book
+//! isn't enough. Each book is kept in sync per Binance's documented algorithm:
+//!
+//! 1. open `<symbol>@depth@100ms` and buffer its events;
+//! 2. GET `/fapi/v1/depth?symbol=X&limit=1000` for a snapshot;
+//! 3. drop buffered events with `u` < the snapshot's `lastUpdateId`;
+//! 4. the first event applied must have `U` <= `lastUpdateId` <= `u`;
+//! 5. every later event's `pu` must equal the previous event's `u`, else the
+//!    book is dropped and synced again from step 2 (this is also what happens
+//!    after a reconnect);
+//! 6. quantities are absolute, 0 removes the level.
+//!
+//! Region file layout (little-endian): 64-byte header (magic `QDEP1\0\0\0`,
+//! version (2), record_size, levels, n_symbols, price_scale (1e8)), then one
+//! record per symbol_id, written under its own seqlock: a 64-byte `DepthHeader`
+//! and `levels` bid then `levels` ask `Level`s, best first, padded to 64 bytes.
+//! The header also carries the top of book's imbalance and microprice (see
+//! `fixed`), computed at write time so readers don't each redo it.
+
+use anyhow::{bail, Context, Result};
+use memmap2::MmapMut;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+
+use crate::config::WsConfig;
+use crate::fixed;
+use crate::price::{self, PriceParseOptions};
+use crate::region::{self, RegionFormat};
+use crate::rest::RestClient;
+use crate::trace::Tracer;
+use crate::seqlock::{self, AtomicU64, Ordering};
+use crate::ws::{chunk_for, MessageHandler, PerfStats, WsConnection, WsManager};
+
+const MAGIC: &[u8; 8] = b"QDEP1\0\0\0";
+const VERSION: u64 = 2;
+const HEADER_SIZE: u64 = 64;
+const FORMAT: RegionFormat = RegionFormat { kind: "depth region", magic: MAGIC, header_size: HEADER_SIZE };
+const PRICE_SCALE: u64 = 100_000_000;
+/// Snapshot depth requested over REST (weight 20)
+const SNAPSHOT_LIMIT: u32 = 1000;
+/// Events buffered per symbol while its snapshot is fetched
+const MAX_BUFFERED: usize = 10_000;
+/// Pause after a 429/418 without Retry-After
+const RATE_LIMIT_PAUSE: Duration = Duration::from_secs(60);
+/// Pause before a failed snapshot may be requested again
+const RETRY_PAUSE: Duration = Duration::from_secs(1);
+
+/// Record header (64 bytes)
+#[repr(C)]
+pub struct DepthHeader {
+    pub seq: AtomicU64,
+    pub symbol_id: u64,
+    /// `u` of the last event applied (or the snapshot's lastUpdateId)
+    pub update_id: u64,
+    /// Exchange event time of that update (ms since epoch)
+    pub event_time_ms: i64,
+    /// Write time (monotonic µs)
+    pub ts: i64,
+    /// Levels filled on each side (the rest of the record is stale)
+    pub n_bids: u32,
+    pub n_asks: u32,
+    /// Best level `(bid_qty - ask_qty) / (bid_qty + ask_qty)` * 1e8 (0 if a side is empty)
+    pub imbalance: i64,
+    /// Best level quantity-weighted mid * 1e8 (0 if a side is empty)
+    pub microprice: i64,
+}
+
+#[cfg(not(feature = "loom"))]
+const _: () = assert!(std::mem::size_of::<DepthHeader>() == HEADER_SIZE as usize);
+
+/// One price level: price and quantity, both * 1e8
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Level {
+    pub price: i64,
+    pub qty: i64,
+}
+
+/// Copy of one symbol's record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepthSnapshot {
+    pub symbol_id: u64,
+    pub update_id: u64,
+    pub event_time_ms: i64,
+    pub ts: i64,
+    pub imbalance: i64,
+    pub microprice: i64,
+    /// Best (highest) first
+    pub bids: Vec<Level>,
+    /// Best (lowest) first
+    pub asks: Vec<Level>,
+}
+
+/// Per-symbol best-N levels in a memory-mapped file
+pub struct DepthRegion {
+    _mmap: MmapMut,
+    records: *mut u8,
+    record_size: usize,
+    levels: usize,
+    n_symbols: u64,
+}
+
+// SAFETY: the mapping lives as long as the region; records are seqlock-protected
+unsafe impl Send for DepthRegion {}
+unsafe impl Sync for DepthRegion {}
+
+/// Bytes per record for `levels` levels a side
+fn record_size(levels: u64) -> u64 {
+    (HEADER_SIZE + 2 * levels * std::mem::size_of::<Level>() as u64).next_multiple_of(64)
+}
+
+impl DepthRegion {
+    /// Open `path`, creating it for symbol_ids 0..n_symbols with `levels` levels a
+    /// side (mode/group as for the quotes file) if it doesn't exist
+    pub fn open_or_create(path: &str, levels: u64, n_symbols: u64, mode: u32, group: Option<&str>) -> Result<Self> {
+        if !std::path::Path::new(path).exists() {
+            Self::create(path, levels, n_symbols, mode, group)?;
+        }
+        Self::open(path)
+    }
+
+    fn create(path: &str, levels: u64, n_symbols: u64, mode: u32, group: Option<&str>) -> Result<()> {
+        if levels == 0 || n_symbols == 0 {
+            bail!("Depth region needs levels and symbols (got {} levels, {} symbols)", levels, n_symbols);
+        }
+        let fields = [VERSION, record_size(levels), levels, n_symbols, PRICE_SCALE];
+        FORMAT.create(path, &fields, HEADER_SIZE + n_symbols * record_size(levels), mode, group)?;
+        eprintln!("[DEPTH] Created depth region {} ({} symbols, {} levels)", path, n_symbols, levels);
+        Ok(())
+    }
+
+    /// Map an existing region read-write, validating its header
+    pub fn open(path: &str) -> Result<Self> {
+        let mut mmap = FORMAT.open(path)?;
+        let field = |i: usize| region::field(&mmap, i);
+        let (version, size, levels, n_symbols) = (field(0), field(1), field(2), field(3));
+        if version != VERSION || levels == 0 || size != record_size(levels) {
+            bail!("Unsupported depth region {}: version {}, record size {}, {} levels", path, version, size, levels);
+        }
+        if mmap.len() as u64 != HEADER_SIZE + n_symbols * size {
+            bail!("Depth region {} size {} doesn't match {} symbols", path, mmap.len(), n_symbols);
+        }
+
+        Ok(Self {
+            records: unsafe { mmap.as_mut_ptr().add(HEADER_SIZE as usize) },
+            record_size: size as usize,
+            levels: levels as usize,
+            n_symbols,
+            _mmap: mmap,
+        })
+    }
+
+    pub fn levels(&self) -> usize {
+        self.levels
+    }
+
+    pub fn n_symbols(&self) -> u64 {
+        self.n_symbols
+    }
+
+    /// Header and levels of `symbol_id`'s record
+    fn record(&self, symbol_id: u64) -> Result<(*mut DepthHeader, *mut Level)> {
+        if symbol_id >= self.n_symbols {
+            bail!("symbol_id {} outside the depth region ({} symbols)", symbol_id, self.n_symbols);
+        }
+        let record = unsafe { self.records.add(symbol_id as usize * self.record_size) };
+        Ok((record as *mut DepthHeader, unsafe { record.add(HEADER_SIZE as usize) } as *mut Level))
+    }
+
+    /// Check `symbol_id` has a record
+    pub fn check(&self, symbol_id: u64) -> Result<()> {
+        self.record(symbol_id).map(|_| ())
+    }
+
+    /// Write `book`'s best levels as `symbol_id`'s record (one writer per symbol)
+    pub fn write(&self, symbol_id: u64, book: &OrderBook, ts: i64) -> Result<()> {
+        let (header, levels) = self.record(symbol_id)?;
+        let bids = book.bids.iter().rev().take(self.levels);
+        let asks = book.asks.iter().take(self.levels);
+        let (imbalance, microprice) = match (book.bids.last_key_value(), book.asks.first_key_value()) {
+            (Some((&bid, &bid_qty)), Some((&ask, &ask_qty))) => (
+                fixed::imbalance(bid_qty, ask_qty).unwrap_or(0),
+                fixed::microprice(bid, bid_qty, ask, ask_qty).unwrap_or(0),
+            ),
+            _ => (0, 0),
+        };
+
+        // SAFETY: one writer per record; readers detect torn records via the seqlock
+        unsafe {
+            let seq0 = seqlock::write_begin(&(*header).seq);
+            std::ptr::write_volatile(&mut (*header).symbol_id, symbol_id);
+            std::ptr::write_volatile(&mut (*header).update_id, book.update_id);
+            std::ptr::write_volatile(&mut (*header).event_time_ms, book.event_time_ms);
+            std::ptr::write_volatile(&mut (*header).ts, ts);
+            let mut n_bids = 0;
+            for (i, (&price, &qty)) in bids.enumerate() {
+                std::ptr::write_volatile(levels.add(i), Level { price, qty });
+                n_bids += 1;
+            }
+            let mut n_asks = 0;
+            for (i, (&price, &qty)) in asks.enumerate() {
+                std::ptr::write_volatile(levels.add(self.levels + i), Level { price, qty });
+                n_asks += 1;
+            }
+            std::ptr::write_volatile(&mut (*header).n_bids, n_bids);
+            std::ptr::write_volatile(&mut (*header).n_asks, n_asks);
+            std::ptr::write_volatile(&mut (*header).imbalance, imbalance);
+            std::ptr::write_volatile(&mut (*header).microprice, microprice);
+            seqlock::write_end(&(*header).seq, seq0);
+        }
+        Ok(())
+    }
+
+    /// Read `symbol_id`'s record; None if never written or it stayed busy
+    pub fn read(&self, symbol_id: u64) -> Option<DepthSnapshot> {
+        let (header, levels) = self.record(symbol_id).ok()?;
+        for _ in 0..1000 {
+            let seq = unsafe { &(*header).seq };
+            let Some(s1) = seqlock::read_begin(seq) else {
+                continue;
+            };
+            // SAFETY: volatile loads so retries re-read the mapped memory
+            let snapshot = unsafe {
+                let n_bids = (std::ptr::read_volatile(&(*header).n_bids) as usize).min(self.levels);
+                let n_asks = (std::ptr::read_volatile(&(*header).n_asks) as usize).min(self.levels);
+                DepthSnapshot {
+                    symbol_id: std::ptr::read_volatile(&(*header).symbol_id),
+                    update_id: std::ptr::read_volatile(&(*header).update_id),
+                    event_time_ms: std::ptr::read_volatile(&(*header).event_time_ms),
+                    ts: std::ptr::read_volatile(&(*header).ts),
+                    imbalance: std::ptr::read_volatile(&(*header).imbalance),
+                    microprice: std::ptr::read_volatile(&(*header).microprice),
+                    bids: (0..n_bids).map(|i| std::ptr::read_volatile(levels.add(i))).collect(),
+                    asks: (0..n_asks).map(|i| std::ptr::read_volatile(levels.add(self.levels + i))).collect(),
+                }
+            };
+            if seqlock::read_end(seq, s1) {
+                return (s1 > 0).then_some(snapshot);
+            }
+        }
+        None
+    }
+}
+
+/// One depthUpdate event, prices and quantities * 1e8
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepthEvent {
+    /// `U`
+    pub first_update_id: u64,
+    /// `u`
+    pub final_update_id: u64,
+    /// `pu`: `u` of the previous event on the stream
+    pub prev_final_update_id: u64,
+    pub event_time_ms: i64,
+    pub bids: Vec<(i64, i64)>,
+    pub asks: Vec<(i64, i64)>,
+}
+
+/// REST depth snapshot, prices and quantities * 1e8
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub last_update_id: u64,
+    pub event_time_ms: i64,
+    pub bids: Vec<(i64, i64)>,
+    pub asks: Vec<(i64, i64)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DepthFrame {
+    data: DepthData,
+}
+
+#[derive(Debug, Deserialize)]
+struct DepthData {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "E", default)]
+    event_time_ms: i64,
+    #[serde(rename = "U")]
+    first_update_id: u64,
+    #[serde(rename = "u")]
+    final_update_id: u64,
+    #[serde(rename = "pu")]
+    prev_final_update_id: u64,
+    #[serde(rename = "b")]
+    bids: Vec<(String, String)>,
+    #[serde(rename = "a")]
+    asks: Vec<(String, String)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SnapshotBody {
+    #[serde(rename = "lastUpdateId")]
+    last_update_id: u64,
+    #[serde(rename = "E", default)]
+    event_time_ms: i64,
+    bids: Vec<(String, String)>,
+    asks: Vec<(String, String)>,
+}
+
+fn parse_levels(levels: &[(String, String)], opts: &PriceParseOptions) -> Result<Vec<(i64, i64)>> {
+    levels
+        .iter()
+        .map(|(price, qty)| Ok((price::parse_price_with(price, opts)?, price::parse_price_with(qty, opts)?)))
+        .collect()
+}
+
+/// Parse a combined-stream depthUpdate frame: (symbol, event)
+pub fn parse_event(text: &str, opts: &PriceParseOptions) -> Result<(String, DepthEvent)> {
+    let frame: DepthFrame = serde_json::from_str(text).context("Invalid depthUpdate message")?;
+    let data = frame.data;
+    Ok((data.symbol, DepthEvent {
+        first_update_id: data.first_update_id,
+        final_update_id: data.final_update_id,
+        prev_final_update_id: data.prev_final_update_id,
+        event_time_ms: data.event_time_ms,
+        bids: parse_levels(&data.bids, opts)?,
+        asks: parse_levels(&data.asks, opts)?,
+    }))
+}
+
+/// Parse a /fapi/v1/depth response
+pub fn parse_snapshot(body: &str, opts: &PriceParseOptions) -> Result<Snapshot> {
+    let body: SnapshotBody = serde_json::from_str(body).context("Invalid depth snapshot")?;
+    Ok(Snapshot {
+        last_update_id: body.last_update_id,
+        event_time_ms: body.event_time_ms,
+        bids: parse_levels(&body.bids, opts)?,
+        asks: parse_levels(&body.asks, opts)?,
+    })
+}
+
+/// Price -> quantity per side
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OrderBook {
+    pub bids: BTreeMap<i64, i64>,
+    pub asks: BTreeMap<i64, i64>,
+    pub update_id: u64,
+    pub event_time_ms: i64,
+}
+
+impl OrderBook {
+    fn from_snapshot(snapshot: Snapshot) -> Self {
+        let mut book = Self { update_id: snapshot.last_update_id, event_time_ms: snapshot.event_time_ms, ..Self::default() };
+        Self::set_levels(&mut book.bids, &snapshot.bids);
+        Self::set_levels(&mut book.asks, &snapshot.asks);
+        book
+    }
+
+    fn set_levels(side: &mut BTreeMap<i64, i64>, levels: &[(i64, i64)]) {
+        for &(price, qty) in levels {
+            if qty == 0 {
+                side.remove(&price);
+            } else {
+                side.insert(price, qty);
+            }
+        }
+    }
+
+    fn apply(&mut self, event: &DepthEvent) {
+        Self::set_levels(&mut self.bids, &event.bids);
+        Self::set_levels(&mut self.asks, &event.asks);
+        self.update_id = event.final_update_id;
+        self.event_time_ms = event.event_time_ms;
+    }
+}
+
+/// What an event or snapshot did to a book
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncStep {
+    /// No snapshot yet: kept for when it arrives
+    Buffered,
+    /// Older than the snapshot: dropped
+    Stale,
+    /// The book changed
+    Applied,
+    /// Sequence broken: the book was dropped and needs a new snapshot
+    Gap,
+}
+
+/// One symbol's book and where it is in the sync algorithm
+#[derive(Debug, Default)]
+pub struct BookSync {
+    book: Option<OrderBook>,
+    /// An event was applied on top of the snapshot (later ones chain on `pu`)
+    bridged: bool,
+    buffer: VecDeque<DepthEvent>,
+    /// A snapshot is on its way
+    requested: bool,
+}
+
+impl BookSync {
+    /// The synced book, if any
+    pub fn book(&self) -> Option<&OrderBook> {
+        self.book.as_ref()
+    }
+
+    /// Steps 3-5 for one event
+    pub fn on_event(&mut self, event: DepthEvent) -> SyncStep {
+        let Some(book) = &mut self.book else {
+            if self.buffer.len() == MAX_BUFFERED {
+                self.buffer.pop_front();
+            }
+            self.buffer.push_back(event);
+            return SyncStep::Buffered;
+        };
+        let in_sequence = if self.bridged {
+            event.prev_final_update_id == book.update_id
+        } else if event.final_update_id < book.update_id {
+            return SyncStep::Stale;
+        } else {
+            event.first_update_id <= book.update_id
+        };
+        if !in_sequence {
+            // The event may still bridge the next snapshot
+            self.book = None;
+            self.bridged = false;
+            self.buffer.clear();
+            self.buffer.push_back(event);
+            return SyncStep::Gap;
+        }
+        book.apply(&event);
+        self.bridged = true;
+        SyncStep::Applied
+    }
+
+    /// Start over from `snapshot`, replaying the buffered events on it
+    pub fn on_snapshot(&mut self, snapshot: Snapshot) -> SyncStep {
+        self.requested = false;
+        self.book = Some(OrderBook::from_snapshot(snapshot));
+        self.bridged = false;
+        let mut events = std::mem::take(&mut self.buffer).into_iter();
+        while let Some(event) = events.next() {
+            if self.on_event(event) == SyncStep::Gap {
+                self.buffer.extend(events);
+                return SyncStep::Gap;
+            }
+        }
+        SyncStep::Applied
+    }
+}
+
+/// Books of the depth symbols, written to a DepthRegion
+pub struct DepthFeed {
+    region: Arc<DepthRegion>,
+    /// symbol -> (symbol_id, book)
+    books: HashMap<String, (u64, Mutex<BookSync>)>,
+    snapshots: mpsc::UnboundedSender<String>,
+    /// Taken by `run_snapshots`
+    requests: Mutex<Option<mpsc::UnboundedReceiver<String>>>,
+    opts: PriceParseOptions,
+    applied: AtomicU64,
+    resyncs: AtomicU64,
+}
+
+impl DepthFeed {
+    /// Feed for `symbols` (symbol, symbol_id)
+    pub fn new(region: Arc<DepthRegion>, symbols: &[(String, u64)], opts: PriceParseOptions) -> Result<Self> {
+        for (symbol, symbol_id) in symbols {
+            region.check(*symbol_id).with_context(|| format!("No depth record for {}", symbol))?;
+        }
+        let (snapshots, requests) = mpsc::unbounded_channel();
+        let books = symbols.iter().map(|(symbol, id)| (symbol.clone(), (*id, Mutex::default()))).collect();
+        Ok(Self {
+            region,
+            books,
+            snapshots,
+            requests: Mutex::new(Some(requests)),
+            opts,
+            applied: AtomicU64::new(0),
+            resyncs: AtomicU64::new(0),
+        })
+    }
+
+    /// Combined stream names per connection, chunked like the bookTicker
+    /// connections, e.g. `btcusdt@depth@100ms/ethusdt@depth@100ms`
+    pub fn streams(&self, config: &WsConfig) -> Vec<String> {
+        let mut symbols: Vec<String> = self.books.keys().cloned().collect();
+        symbols.sort();
+        chunk_for(&symbols, config)
+            .iter()
+            .map(|chunk| chunk.iter().map(|s| format!("{}@depth@100ms", s.to_lowercase())).collect::<Vec<_>>().join("/"))
+            .collect()
+    }
+
+    /// Handle one stream frame
+    pub fn handle_frame(&self, text: &str) {
+        let (symbol, event) = match parse_event(text, &self.opts) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                crate::eprintln_limited!("[DEPTH] Failed to parse depth update: {:#}", e);
+                return;
+            }
+        };
+        let Some((symbol_id, sync)) = self.books.get(&symbol) else {
+            return;
+        };
+        let mut sync = sync.lock().unwrap_or_else(|e| e.into_inner());
+        let step = sync.on_event(event);
+        self.after(&symbol, *symbol_id, &mut sync, step);
+    }
+
+    /// Apply a fetched snapshot
+    fn handle_snapshot(&self, symbol: &str, snapshot: Snapshot) {
+        let Some((symbol_id, sync)) = self.books.get(symbol) else {
+            return;
+        };
+        let mut sync = sync.lock().unwrap_or_else(|e| e.into_inner());
+        let step = sync.on_snapshot(snapshot);
+        self.after(symbol, *symbol_id, &mut sync, step);
+    }
+
+    fn after(&self, symbol: &str, symbol_id: u64, sync: &mut BookSync, step: SyncStep) {
+        match step {
+            SyncStep::Applied => {
+                self.applied.fetch_add(1, Ordering::Relaxed);
+                if let Some(book) = sync.book() {
+                    if let Err(e) = self.region.write(symbol_id, book, crate::platform::monotonic_us()) {
+                        crate::eprintln_limited!("[DEPTH] {}: {:#}", symbol, e);
+                    }
+                }
+            }
+            SyncStep::Gap => {
+                self.resyncs.fetch_add(1, Ordering::Relaxed);
+                crate::eprintln_limited!("[DEPTH] {} update sequence broken, resyncing from a snapshot", symbol);
+            }
+            SyncStep::Buffered | SyncStep::Stale => {}
+        }
+        if sync.book().is_none() && !sync.requested {
+            sync.requested = true;
+            let _ = self.snapshots.send(symbol.to_string());
+        }
+    }
+
+    /// Fetch requested snapshots one at a time (once per feed), each traced
+    /// as a `rest.depth_snapshot` span
+    pub async fn run_snapshots(self: Arc<Self>, mut client: RestClient, tracer: Arc<Tracer>) {
+        let Some(mut requests) = self.requests.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+            return;
+        };
+        while let Some(symbol) = requests.recv().await {
+            let mut span = tracer.start("rest.depth_snapshot").client();
+            span.attr("symbol", symbol.as_str());
+            let path = format!("/fapi/v1/depth?symbol={}&limit={}", symbol, SNAPSHOT_LIMIT);
+            let result = match client.get(&path).await {
+                Ok(response) if response.status == 429 || response.status == 418 => {
+                    let pause = response.retry_after_secs.map_or(RATE_LIMIT_PAUSE, Duration::from_secs);
+                    eprintln!("[DEPTH] Rate limited (HTTP {}), pausing {:?}", response.status, pause);
+                    span.attr("http.status_code", response.status as u64);
+                    span.fail(format!("Rate limited, pausing {:?}", pause));
+                    span.end();
+                    tokio::time::sleep(pause).await;
+                    let _ = self.snapshots.send(symbol);
+                    continue;
+                }
+                Ok(response) => {
+                    span.attr("http.status_code", response.status as u64);
+                    match response.status {
+                        200 => parse_snapshot(&response.body, &self.opts),
+                        status => Err(anyhow::anyhow!("HTTP {}: {}", status, response.body.trim())),
+                    }
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(snapshot) => {
+                    span.attr("last_update_id", snapshot.last_update_id);
+                    span.end();
+                    self.handle_snapshot(&symbol, snapshot);
+                }
+                Err(e) => {
+                    span.fail(format!("{:#}", e));
+                    span.end();
+                    crate::eprintln_limited!("[DEPTH] {} snapshot failed: {:#}", symbol, e);
+                    // The next event asks again
+                    tokio::time::sleep(RETRY_PAUSE).await;
+                    if let Some((_, sync)) = self.books.get(&symbol) {
+                        sync.lock().unwrap_or_else(|e| e.into_inner()).requested = false;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn report(&self) {
+        eprintln!("[STATS] Depth: {} book updates, {} resyncs",
+                  self.applied.load(Ordering::Relaxed),
+                  self.resyncs.load(Ordering::Relaxed));
+    }
+}
+
+/// Manager streaming the feed's depth updates, one connection per chunk of symbols
+pub fn manager(feed: Arc<DepthFeed>, config: WsConfig, stats: Arc<PerfStats>) -> WsManager {
+    let config = WsConfig { all_market: false, ..config };
+    let unused: MessageHandler = Arc::new(|_| {});
+    let connections = feed
+        .streams(&config)
+        .iter()
+        .map(|streams| {
+            let feed = feed.clone();
+            WsConnection::new(Vec::new(), unused.clone(), config.clone(), stats.clone())
+                .with_streams(streams)
+                .with_raw_handler(Box::new(move |text: String| feed.handle_frame(&text)))
+        })
+        .collect();
+    WsManager::from_connections(connections, stats).with_label("D")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(first: u64, last: u64, prev: u64, bids: &[(i64, i64)], asks: &[(i64, i64)]) -> DepthEvent {
+        DepthEvent {
+            first_update_id: first,
+            final_update_id: last,
+            prev_final_update_id: prev,
+            event_time_ms: last as i64,
+            bids: bids.to_vec(),
+            asks: asks.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let opts = PriceParseOptions::default();
+        let frame = r#"{"stream":"btcusdt@depth@100ms","data":{"e":"depthUpdate","E":1571889248277,"T":1571889248276,"s":"BTCUSDT","U":390497796,"u":390497878,"pu":390497794,"b":[["7403.89","0.002"],["7403.90","0"]],"a":[["7405.96","3.340"]]}}"#;
+        let (symbol, event) = parse_event(frame, &opts).unwrap();
+        assert_eq!(symbol, "BTCUSDT");
+        assert_eq!((event.first_update_id, event.final_update_id, event.prev_final_update_id), (390497796, 390497878, 390497794));
+        assert_eq!(event.bids, vec![(740_389_000_000, 200_000), (740_390_000_000, 0)]);
+
+        let body = r#"{"lastUpdateId":1027024,"E":1589436922972,"T":1589436922959,"bids":[["4.00000000","431.00000000"]],"asks":[["4.00000200","12.00000000"]]}"#;
+        let snapshot = parse_snapshot(body, &opts).unwrap();
+        assert_eq!((snapshot.last_update_id, snapshot.asks[0]), (1027024, (400_000_200, 1_200_000_000)));
+        assert!(parse_event(body, &opts).is_err());
+    }
+
+    #[test]
+    fn test_sync_algorithm() {
+        let mut sync = BookSync::default();
+        let snapshot = Snapshot { last_update_id: 100, event_time_ms: 0, bids: vec![(10, 1), (9, 2)], asks: vec![(11, 1)] };
+
+        // Buffered until the snapshot; the stale one is dropped, the one
+        // spanning lastUpdateId bridges, the next chains on pu
+        assert_eq!(sync.on_event(event(90, 95, 89, &[(10, 5)], &[])), SyncStep::Buffered);
+        assert_eq!(sync.on_event(event(96, 105, 95, &[(9, 0)], &[(12, 3)])), SyncStep::Buffered);
+        assert_eq!(sync.on_event(event(106, 110, 105, &[(10, 7)], &[])), SyncStep::Buffered);
+        assert_eq!(sync.on_snapshot(snapshot.clone()), SyncStep::Applied);
+        let book = sync.book().unwrap();
+        assert_eq!(book.update_id, 110);
+        assert_eq!(book.bids, BTreeMap::from([(10, 7)]));
+        assert_eq!(book.asks, BTreeMap::from([(11, 1), (12, 3)]));
+
+        // A missed event drops the book until the next snapshot
+        assert_eq!(sync.on_event(event(115, 120, 112, &[], &[])), SyncStep::Gap);
+        assert!(sync.book().is_none());
+
+        // A snapshot older than the buffer doesn't bridge it either
+        assert_eq!(sync.on_snapshot(snapshot), SyncStep::Gap);
+        let snapshot = Snapshot { last_update_id: 118, event_time_ms: 0, bids: vec![(10, 1)], asks: vec![(11, 1)] };
+        assert_eq!(sync.on_snapshot(snapshot), SyncStep::Applied);
+        assert_eq!(sync.book().unwrap().update_id, 120);
+    }
+
+    #[test]
+    fn test_region_best_levels() {
+        let path = std::env::temp_dir().join(format!("test_depth_{}.dat", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let region = Arc::new(DepthRegion::open_or_create(path, 2, 4, 0o600, None).unwrap());
+        assert_eq!(region.read(1), None);
+        let feed = DepthFeed::new(region.clone(), &[("BTCUSDT".to_string(), 1)], PriceParseOptions::default()).unwrap();
+        let mut requests = feed.requests.lock().unwrap().take().unwrap();
+        assert_eq!(feed.streams(&WsConfig::default()), vec!["btcusdt@depth@100ms"]);
+        assert!(DepthFeed::new(region.clone(), &[("FARUSDT".to_string(), 4)], PriceParseOptions::default()).is_err());
+
+        // The first event asks for a snapshot, once
+        let frame = |first: u64, last: u64, bid: &str| format!(
+            r#"{{"stream":"btcusdt@depth@100ms","data":{{"e":"depthUpdate","E":5,"s":"BTCUSDT","U":{},"u":{},"pu":{},"b":[["{}","1"]],"a":[]}}}}"#,
+            first, last, first - 1, bid);
+        feed.handle_frame(&frame(10, 12, "99.5"));
+        feed.handle_frame(&frame(13, 14, "99.7"));
+        assert_eq!(requests.try_recv().unwrap(), "BTCUSDT");
+        assert!(requests.try_recv().is_err());
+
+        let levels = |pairs: &[(i64, i64)]| pairs.to_vec();
+        let snapshot = Snapshot {
+            last_update_id: 11,
+            event_time_ms: 1,
+            bids: levels(&[(9_900_000_000, 100_000_000), (9_800_000_000, 100_000_000)]),
+            asks: levels(&[(10_100_000_000, 100_000_000), (10_200_000_000, 100_000_000), (10_300_000_000, 100_000_000)]),
+        };
+        feed.handle_snapshot("BTCUSDT", snapshot);
+        let depth = region.read(1).unwrap();
+        assert_eq!((depth.symbol_id, depth.update_id, depth.event_time_ms), (1, 14, 5));
+        let prices = |side: &[Level]| side.iter().map(|level| level.price).collect::<Vec<_>>();
+        assert_eq!(prices(&depth.bids), vec![9_970_000_000, 9_950_000_000]);
+        assert_eq!(prices(&depth.asks), vec![10_100_000_000, 10_200_000_000]);
+        // 1 lot each side at 99.7 / 101
+        assert_eq!((depth.imbalance, depth.microprice), (0, 10_035_000_000));
+
+        drop((feed, region));
+        assert_eq!(DepthRegion::open(path).unwrap().read(1).unwrap().update_id, 14);
+        std::fs::write(path, b"garbage").unwrap();
+        assert!(DepthRegion::open(path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_streams_chunked_per_connection() {
+        let path = std::env::temp_dir().join(format!("test_depth_chunks_{}.dat", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let region = Arc::new(DepthRegion::open_or_create(path, 1, 150, 0o600, None).unwrap());
+        let symbols: Vec<(String, u64)> = (0..150).map(|i| (format!("S{:03}USDT", i), i)).collect();
+        let feed = Arc::new(DepthFeed::new(region, &symbols, PriceParseOptions::default()).unwrap());
+        let streams = feed.streams(&WsConfig::default());
+        assert_eq!(streams.iter().map(|s| s.split('/').count()).collect::<Vec<_>>(), vec![100, 50]);
+        assert!(streams[0].starts_with("s000usdt@depth@100ms/"));
+        assert_eq!(manager(feed, WsConfig::default(), Arc::new(PerfStats::new())).conn_stats().len(), 2);
+        std::fs::remove_file(path).unwrap();
+    }
+}
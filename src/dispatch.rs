@@ -0,0 +1,84 @@
+//! Pre-resolved stream -> slot dispatch table for the feed handler
+//!
+//! Combined-stream frames name their stream ("btcusdt@bookTicker"), so the
+//! handler can go from those bytes straight to (symbol_id, slot) without
+//! hashing the symbol or the per-message `WriterToken` claim check. The table
+//! is built once at startup from the slots the feed claimed. Streams it
+//! doesn't know fall back to the symbol lookup: the all-market `!bookTicker`
+//! stream, symbols added by a reload and frames a post-parse hook rewrote.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::shm::Quote64;
+use crate::writer::WriterToken;
+use crate::ws::stream_name;
+
+/// Stream name -> (symbol_id, slot), sorted by stream bytes
+pub struct StreamTable {
+    entries: Vec<(Box<str>, u64, *const Quote64)>,
+    /// Keeps the slots' mapping and their claims alive
+    _writer: Arc<WriterToken>,
+}
+
+// SAFETY: the pointers are slots of `_writer`'s mapping, which lives as long
+// as the table, and Quote64 is shared across threads by design (seqlock)
+unsafe impl Send for StreamTable {}
+unsafe impl Sync for StreamTable {}
+
+impl StreamTable {
+    /// Resolve the bookTicker stream of every `symbol -> symbol_id` to the
+    /// slot `writer` holds; errors if `writer` doesn't hold one of them
+    pub fn new(writer: Arc<WriterToken>, symbol_ids: &HashMap<String, u64>) -> Result<Self> {
+        let mut entries = symbol_ids
+            .iter()
+            .map(|(symbol, &id)| Ok((stream_name(symbol).into_boxed_str(), id, writer.slot(id)? as *const Quote64)))
+            .collect::<Result<Vec<_>>>()?;
+        entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        Ok(Self { entries, _writer: writer })
+    }
+
+    /// symbol_id and slot of `stream` (None = not a startup feed stream)
+    #[inline(always)]
+    pub fn get(&self, stream: &str) -> Option<(u64, &Quote64)> {
+        let idx = self.entries.binary_search_by(|(name, _, _)| name.as_bytes().cmp(stream.as_bytes())).ok()?;
+        let (_, symbol_id, slot) = self.entries[idx];
+        // SAFETY: see the Send/Sync impls
+        Some((symbol_id, unsafe { &*slot }))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shm::ShmManager;
+
+    #[test]
+    fn test_stream_table() {
+        let shm = Arc::new(ShmManager::anonymous(2, 8).unwrap());
+        let writer = Arc::new(WriterToken::claim(shm.clone(), "feed", 1, &[3, 5]).unwrap());
+        let ids = HashMap::from([("BTCUSDT".to_string(), 3), ("ETHUSDT".to_string(), 5)]);
+        let table = StreamTable::new(writer.clone(), &ids).unwrap();
+        assert_eq!(table.len(), 2);
+
+        let (symbol_id, slot) = table.get("ethusdt@bookTicker").unwrap();
+        assert_eq!(symbol_id, 5);
+        assert!(std::ptr::eq(slot, shm.get_slot(1, 5).unwrap()));
+        assert!(table.get("!bookTicker").is_none());
+        assert!(table.get("").is_none());
+        assert!(table.get("BTCUSDT").is_none());
+
+        // Only slots the writer holds can be dispatched to
+        let unclaimed = HashMap::from([("SOLUSDT".to_string(), 6)]);
+        assert!(StreamTable::new(writer, &unclaimed).is_err());
+    }
+}
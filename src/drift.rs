@@ -0,0 +1,192 @@
+//! Clock drift between exchange event time and the local wall clock
+//!
+//! Every bookTicker carries the exchange's event time `E` (ms). Its offset
+//! from our clock when the frame is handled is clock offset plus network and
+//! processing latency; the smallest offset over a second is the best estimate
+//! of the clock offset alone (the hot path keeps it against the monotonic
+//! timestamp it already took; the check maps it to wall time).
+//!
+//! Once a second that minimum is compared with a rolling window of previous
+//! ones: an offset beyond `max_offset_ms` means the clocks disagree (or
+//! latency is extreme), a jump of `jump_ms` against the window's median means
+//! one of the clocks stepped. Both usually mean NTP is broken here and
+//! downstream timestamps are suspect; each is logged once per episode and
+//! counted.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::config::ClockDriftConfig;
+
+/// Unset per-second minimum
+const NO_SAMPLE: i64 = i64::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriftAlert {
+    /// |offset| over `max_offset_ms`
+    Offset { offset_ms: i64 },
+    /// Offset moved `jump_ms` or more from the window's median
+    Jump { from_ms: i64, to_ms: i64 },
+}
+
+#[derive(Default)]
+struct DriftState {
+    /// Per-second minimum offsets, oldest first (µs)
+    window: VecDeque<i64>,
+    /// Whether the last check was alerting (alerts are logged on entry only)
+    alerting: bool,
+}
+
+/// Tracks the exchange-to-local offset and alerts on drift or jumps
+pub struct ClockDrift {
+    max_offset_us: i64,
+    jump_us: i64,
+    window_len: usize,
+    /// Smallest monotonic - exchange offset since the last check (µs)
+    second_min: AtomicI64,
+    /// Last estimated offset (µs), for status output
+    last_offset_us: AtomicI64,
+    alerts: AtomicU64,
+    state: Mutex<DriftState>,
+}
+
+impl ClockDrift {
+    pub fn new(config: &ClockDriftConfig) -> Self {
+        Self {
+            max_offset_us: (config.max_offset_ms as i64).saturating_mul(1_000),
+            jump_us: (config.jump_ms as i64).saturating_mul(1_000),
+            window_len: config.window_secs.max(1) as usize,
+            second_min: AtomicI64::new(NO_SAMPLE),
+            last_offset_us: AtomicI64::new(0),
+            alerts: AtomicU64::new(0),
+            state: Mutex::new(DriftState::default()),
+        }
+    }
+
+    /// Record a frame with event time `event_time_ms` handled at `monotonic_us`
+    #[inline]
+    pub fn observe(&self, event_time_ms: u64, monotonic_us: i64) {
+        if event_time_ms == 0 {
+            return;
+        }
+        let offset = monotonic_us.saturating_sub((event_time_ms as i64).saturating_mul(1_000));
+        self.second_min.fetch_min(offset, Ordering::Relaxed);
+    }
+
+    /// Close the current second; returns a newly raised alert
+    pub fn check(&self) -> Option<DriftAlert> {
+        let monotonic_to_unix = crate::persist::unix_now_us() as i64 - crate::platform::monotonic_us();
+        self.check_with(monotonic_to_unix)
+    }
+
+    /// `check` with the wall clock at monotonic + `monotonic_to_unix_us`
+    fn check_with(&self, monotonic_to_unix_us: i64) -> Option<DriftAlert> {
+        let offset = self.second_min.swap(NO_SAMPLE, Ordering::Relaxed);
+        if offset == NO_SAMPLE {
+            return None;
+        }
+        let offset = offset.saturating_add(monotonic_to_unix_us);
+        self.last_offset_us.store(offset, Ordering::Relaxed);
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let median = (!state.window.is_empty()).then(|| {
+            let mut sorted: Vec<i64> = state.window.iter().copied().collect();
+            sorted.sort_unstable();
+            sorted[sorted.len() / 2]
+        });
+        let alert = if self.max_offset_us > 0 && offset.abs() > self.max_offset_us {
+            Some(DriftAlert::Offset { offset_ms: offset / 1_000 })
+        } else {
+            median
+                .filter(|&median| self.jump_us > 0 && (offset - median).abs() >= self.jump_us)
+                .map(|median| DriftAlert::Jump { from_ms: median / 1_000, to_ms: offset / 1_000 })
+        };
+
+        // A jump becomes the new normal once the window has caught up
+        state.window.push_back(offset);
+        while state.window.len() > self.window_len {
+            state.window.pop_front();
+        }
+
+        let entering = alert.is_some() && !state.alerting;
+        if alert.is_none() && state.alerting {
+            eprintln!("[CLOCK] Exchange clock offset back to normal: {} ms", offset / 1_000);
+        }
+        state.alerting = alert.is_some();
+        if !entering {
+            return None;
+        }
+        self.alerts.fetch_add(1, Ordering::Relaxed);
+        match alert? {
+            DriftAlert::Offset { offset_ms } => eprintln!(
+                "[CLOCK] WARNING: local clock is {} ms {} exchange event time (limit {} ms) - check NTP; latency figures are suspect",
+                offset_ms.abs(), if offset_ms > 0 { "ahead of" } else { "behind" }, self.max_offset_us / 1_000),
+            DriftAlert::Jump { from_ms, to_ms } => eprintln!(
+                "[CLOCK] WARNING: exchange clock offset jumped from {} ms to {} ms - a clock stepped, check NTP",
+                from_ms, to_ms),
+        }
+        alert
+    }
+
+    /// Latest per-second offset estimate (local - exchange, µs)
+    pub fn offset_us(&self) -> i64 {
+        self.last_offset_us.load(Ordering::Relaxed)
+    }
+
+    pub fn alerts(&self) -> u64 {
+        self.alerts.load(Ordering::Relaxed)
+    }
+
+    /// Check every `interval` (a second, so the window counts seconds)
+    pub fn spawn(self: std::sync::Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.check();
+            }
+        })
+    }
+
+    pub fn report(&self) {
+        eprintln!("[STATS] Clock offset (local - exchange): {:.1} ms, {} drift alerts",
+                  self.offset_us() as f64 / 1000.0, self.alerts());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_and_jump_alerts() {
+        let config = ClockDriftConfig { max_offset_ms: 500, jump_ms: 100, window_secs: 3 };
+        let drift = ClockDrift::new(&config);
+        // Monotonic clock 1000 s behind the wall clock
+        let second = |samples: &[(u64, i64)]| {
+            for &(e, local) in samples {
+                drift.observe(e, local - 1_000_000_000);
+            }
+            drift.check_with(1_000_000_000)
+        };
+
+        // Idle second; then the minimum offset (5 ms) is the estimate
+        assert_eq!(second(&[]), None);
+        assert_eq!(second(&[(1_000, 1_020_000), (1_001, 1_006_000), (0, 1)]), None);
+        assert_eq!(drift.offset_us(), 5_000);
+        assert_eq!(second(&[(2_000, 2_004_000)]), None);
+
+        // Our clock steps 150 ms ahead: one jump alert, then the window absorbs it
+        assert_eq!(second(&[(3_000, 3_155_000)]), Some(DriftAlert::Jump { from_ms: 5, to_ms: 155 }));
+        assert_eq!(second(&[(4_000, 4_155_000)]), None);
+        assert_eq!(second(&[(5_000, 5_155_000)]), None);
+        assert_eq!(second(&[(6_000, 6_155_000)]), None);
+
+        // Far behind the exchange
+        assert_eq!(second(&[(7_000, 6_300_000)]), Some(DriftAlert::Offset { offset_ms: -700 }));
+        assert_eq!(second(&[(8_000, 7_300_000)]), None);
+        assert_eq!(drift.alerts(), 2);
+    }
+}
@@ -0,0 +1,100 @@
+use std::cell::Cell;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crate::persist::StatsPersister;
+use crate::shm::ShmManager;
+use crate::ws::PerfStats;
+
+/// Install a panic hook that fails loudly instead of leaving a frozen feed
+///
+/// On panic: logs the message, marks the source as faulted in the SHM header
+/// (readers stop trusting the data), reports and persists stats, then aborts.
+/// Panics inside an `isolate`d future only unwind into it instead.
+pub fn install_panic_hook(
+    shm: Arc<ShmManager>,
+    source_id: u64,
+    perf_stats: Arc<PerfStats>,
+    persister: Option<Arc<StatsPersister>>,
+) {
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if ISOLATED.with(Cell::get) > 0 {
+            return;
+        }
+
+        let message = panic_message(info);
+        eprintln!("[FATAL] Writer panicked: {}", message);
+
+        if let Err(e) = shm.mark_writer_fault(source_id) {
+            eprintln!("[FATAL] Failed to mark writer fault in SHM: {:#}", e);
+        }
+
+        perf_stats.report();
+        if let Some(persister) = &persister {
+            if let Err(e) = persister.flush_panic(&message) {
+                eprintln!("[FATAL] Failed to persist stats: {:#}", e);
+            }
+        }
+
+        std::process::abort();
+    }));
+}
+
+/// Render panic payload and location as a single line
+fn panic_message(info: &std::panic::PanicHookInfo<'_>) -> String {
+    let msg = payload_message(info.payload());
+
+    match info.location() {
+        Some(loc) => format!("{} at {}:{}", msg, loc.file(), loc.line()),
+        None => msg,
+    }
+}
+
+/// Panic payload as text
+fn payload_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
+
+thread_local! {
+    /// Isolated futures being polled on this thread
+    static ISOLATED: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Future that turns a panic while polling `F` into `Err(message)`
+pub struct Isolated<F> {
+    inner: Pin<Box<F>>,
+}
+
+/// Run `future` so a panic in it ends only it (the panic hook logs it but
+/// doesn't abort); the caller decides what to restart
+pub fn isolate<F: Future>(future: F) -> Isolated<F> {
+    Isolated { inner: Box::pin(future) }
+}
+
+impl<F: Future> Future for Isolated<F> {
+    type Output = Result<F::Output, String>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = &mut self.get_mut().inner;
+        ISOLATED.with(|depth| depth.set(depth.get() + 1));
+        // A panicked future is never polled again, so no broken state is observed
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| inner.as_mut().poll(cx)));
+        ISOLATED.with(|depth| depth.set(depth.get() - 1));
+        match result {
+            Ok(poll) => poll.map(Ok),
+            Err(payload) => Poll::Ready(Err(payload_message(payload.as_ref()))),
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Fixed-point arithmetic on scaled i64 prices
+//!
+//! Derived values (mid, spread, moves in bps or percent) computed without
+//! floats, in i128 so intermediate products can't overflow. Rounding:
+//!
+//! - `mid` and `microprice` round down (toward negative infinity), so they
+//!   are always within [bid, ask];
+//! - ratios (`bps_diff`, `spread_bps`, `percent_change`, `imbalance`) truncate toward
+//!   zero, so a move or a spread is never overstated against a threshold.
+//!
+//! Operands must share a scale; results that don't fit i64 are None.
+//!
+//! Overflow checks here and in the price parser go through `arith`: checked
+//! by default; built with the `unchecked-arith` feature they only run in
+//! debug builds, and release builds wrap (garbage in, garbage out).
+
+/// Overflow-checked i64 steps, or wrapping ones that debug-assert
+#[cfg(not(feature = "unchecked-arith"))]
+pub(crate) mod arith {
+    /// Which mode this build uses
+    pub const MODE: &str = "checked";
+
+    #[inline(always)]
+    pub fn add(a: i64, b: i64) -> Option<i64> {
+        a.checked_add(b)
+    }
+
+    #[inline(always)]
+    pub fn sub(a: i64, b: i64) -> Option<i64> {
+        a.checked_sub(b)
+    }
+
+    #[inline(always)]
+    pub fn mul(a: i64, b: i64) -> Option<i64> {
+        a.checked_mul(b)
+    }
+
+    #[inline(always)]
+    pub fn narrow(v: i128) -> Option<i64> {
+        i64::try_from(v).ok()
+    }
+}
+
+#[cfg(feature = "unchecked-arith")]
+pub(crate) mod arith {
+    pub const MODE: &str = "unchecked";
+
+    #[inline(always)]
+    pub fn add(a: i64, b: i64) -> Option<i64> {
+        debug_assert!(a.checked_add(b).is_some(), "i64 overflow: {} + {}", a, b);
+        Some(a.wrapping_add(b))
+    }
+
+    #[inline(always)]
+    pub fn sub(a: i64, b: i64) -> Option<i64> {
+        debug_assert!(a.checked_sub(b).is_some(), "i64 overflow: {} - {}", a, b);
+        Some(a.wrapping_sub(b))
+    }
+
+    #[inline(always)]
+    pub fn mul(a: i64, b: i64) -> Option<i64> {
+        debug_assert!(a.checked_mul(b).is_some(), "i64 overflow: {} * {}", a, b);
+        Some(a.wrapping_mul(b))
+    }
+
+    #[inline(always)]
+    pub fn narrow(v: i128) -> Option<i64> {
+        debug_assert!(i64::try_from(v).is_ok(), "{} doesn't fit i64", v);
+        Some(v as i64)
+    }
+}
+
+/// Overflow handling compiled in: "checked" or "unchecked" (`unchecked-arith`)
+pub fn arith_mode() -> &'static str {
+    arith::MODE
+}
+
+/// Basis points per unit
+const BPS: i128 = 10_000;
+
+/// Scale of `percent_change` results (1e8, like prices)
+pub const PERCENT_SCALE: i64 = 100_000_000;
+
+/// Midpoint of `bid` and `ask`, rounded down
+#[inline]
+pub fn mid(bid: i64, ask: i64) -> i64 {
+    // The mean of two i64 always fits
+    (bid as i128 + ask as i128).div_euclid(2) as i64
+}
+
+/// `ask - bid` (negative when crossed; None on overflow)
+#[inline]
+pub fn spread(bid: i64, ask: i64) -> Option<i64> {
+    arith::sub(ask, bid)
+}
+
+/// Move from `from` to `to` in whole basis points of `from`, truncated
+/// toward zero (None unless `from` > 0)
+#[inline]
+pub fn bps_diff(from: i64, to: i64) -> Option<i64> {
+    ratio(to as i128 - from as i128, from, BPS)
+}
+
+/// Spread in whole basis points of the mid, truncated toward zero (None
+/// unless the mid is > 0)
+#[inline]
+pub fn spread_bps(bid: i64, ask: i64) -> Option<i64> {
+    ratio(ask as i128 - bid as i128, mid(bid, ask), BPS)
+}
+
+/// Percent change from `from` to `to`, scaled by `PERCENT_SCALE` (1.5% ->
+/// 150_000_000) and truncated toward zero (None unless `from` > 0)
+#[inline]
+pub fn percent_change(from: i64, to: i64) -> Option<i64> {
+    ratio(to as i128 - from as i128, from, 100 * PERCENT_SCALE as i128)
+}
+
+/// Scale of `imbalance` results (1e8: -1.0 ..= 1.0 -> -1e8 ..= 1e8)
+pub const IMBALANCE_SCALE: i64 = 100_000_000;
+
+/// Book imbalance `(bid_qty - ask_qty) / (bid_qty + ask_qty)`, scaled by
+/// `IMBALANCE_SCALE` and truncated toward zero (None unless both are > 0)
+#[inline]
+pub fn imbalance(bid_qty: i64, ask_qty: i64) -> Option<i64> {
+    if bid_qty <= 0 || ask_qty <= 0 {
+        return None;
+    }
+    let (bid_qty, ask_qty) = (bid_qty as i128, ask_qty as i128);
+    arith::narrow((bid_qty - ask_qty) * IMBALANCE_SCALE as i128 / (bid_qty + ask_qty))
+}
+
+/// Quantity-weighted mid `(bid * ask_qty + ask * bid_qty) / (bid_qty + ask_qty)`,
+/// rounded down (None unless both quantities are > 0)
+#[inline]
+pub fn microprice(bid: i64, bid_qty: i64, ask: i64, ask_qty: i64) -> Option<i64> {
+    if bid_qty <= 0 || ask_qty <= 0 {
+        return None;
+    }
+    // Each product is below 2^126 in magnitude: the sum fits i128
+    let weighted = bid as i128 * ask_qty as i128 + ask as i128 * bid_qty as i128;
+    arith::narrow(weighted.div_euclid(bid_qty as i128 + ask_qty as i128))
+}
+
+/// `diff * unit / base`, truncated toward zero
+#[inline]
+fn ratio(diff: i128, base: i64, unit: i128) -> Option<i64> {
+    if base <= 0 {
+        return None;
+    }
+    // |diff| < 2^65 and unit <= 1e10 < 2^34: the product fits i128
+    arith::narrow(diff * unit / base as i128)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rounding_rules() {
+        assert_eq!(arith_mode(), if cfg!(feature = "unchecked-arith") { "unchecked" } else { "checked" });
+
+        assert_eq!(mid(100, 103), 101);
+        assert_eq!(mid(-3, 0), -2);
+        assert_eq!(mid(i64::MAX, i64::MAX), i64::MAX);
+        assert_eq!(mid(i64::MIN, i64::MAX), -1);
+
+        assert_eq!(spread(6_500_000_000_000, 6_500_010_000_000), Some(10_000_000));
+        assert_eq!(spread(101, 100), Some(-1));
+
+        // 65000 -> 65100 is 15.38 bps: truncated both ways
+        assert_eq!(bps_diff(6_500_000_000_000, 6_510_000_000_000), Some(15));
+        assert_eq!(bps_diff(6_510_000_000_000, 6_500_000_000_000), Some(-15));
+        assert_eq!(bps_diff(0, 1), None);
+
+        assert_eq!(spread_bps(9_990_000_000, 10_010_000_000), Some(20));
+        assert_eq!(spread_bps(0, 0), None);
+
+        assert_eq!(percent_change(200_000_000, 203_000_000), Some(150_000_000));
+        // 3 -> 2.99 is -0.333..%: -0.33333333, not -0.33333334
+        assert_eq!(percent_change(300_000_000, 299_000_000), Some(-33_333_333));
+        assert_eq!(percent_change(-1, 1), None);
+
+        // 3 vs 1 lots: +0.5; 1 vs 2: -0.333..., truncated to -0.33333333
+        assert_eq!(imbalance(300_000_000, 100_000_000), Some(50_000_000));
+        assert_eq!(imbalance(100_000_000, 200_000_000), Some(-33_333_333));
+        assert_eq!(imbalance(i64::MAX, 1), Some(IMBALANCE_SCALE - 1));
+        assert_eq!(imbalance(0, 100_000_000), None);
+
+        // Heavier bid pulls toward the ask; rounded down, inside [bid, ask]
+        assert_eq!(microprice(10_000, 300, 10_010, 100), Some(10_007));
+        assert_eq!(microprice(10_000, 100, 10_010, 200), Some(10_003));
+        assert_eq!(microprice(i64::MAX - 1, i64::MAX, i64::MAX, i64::MAX), Some(i64::MAX - 1));
+        assert_eq!(microprice(10_000, 100, 10_010, 0), None);
+    }
+
+    #[test]
+    #[cfg(not(feature = "unchecked-arith"))]
+    fn test_overflow_is_none() {
+        assert_eq!(spread(i64::MIN, 1), None);
+        assert_eq!(bps_diff(1, i64::MAX), None);
+    }
+}
@@ -0,0 +1,125 @@
+//! Runtime feature flags for optional work in the write path
+//!
+//! Capture, fan-out and diagnostics each check their flag per message (one
+//! relaxed load), so they can be switched on while investigating an incident
+//! and off again without a restart. Initial values come from FEATURE_FLAGS;
+//! the admin socket flips them at runtime.
+
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::bail;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    /// Quote capture to RECORD_FILE
+    Recorder,
+    /// Local WebSocket re-broadcast
+    Broadcast,
+    /// Rate-of-change alerts
+    Anomaly,
+    /// Tick-size validation
+    TickCheck,
+}
+
+impl Flag {
+    pub const ALL: [Flag; 4] = [Flag::Recorder, Flag::Broadcast, Flag::Anomaly, Flag::TickCheck];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Flag::Recorder => "recorder",
+            Flag::Broadcast => "broadcast",
+            Flag::Anomaly => "anomaly",
+            Flag::TickCheck => "tick_check",
+        }
+    }
+
+    #[inline(always)]
+    fn bit(self) -> u64 {
+        1 << self as u64
+    }
+}
+
+impl FromStr for Flag {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        match Flag::ALL.into_iter().find(|flag| flag.name() == s) {
+            Some(flag) => Ok(flag),
+            None => bail!("Unknown feature flag {:?}", s),
+        }
+    }
+}
+
+/// Current flag values, shared by the write path and the admin socket
+pub struct FeatureFlags {
+    bits: AtomicU64,
+}
+
+impl Default for FeatureFlags {
+    /// Everything on, as before flags existed
+    fn default() -> Self {
+        Self { bits: AtomicU64::new(Flag::ALL.iter().fold(0, |bits, flag| bits | flag.bit())) }
+    }
+}
+
+impl FeatureFlags {
+    /// Defaults changed by a spec like `-recorder,-anomaly` (`name` enables,
+    /// `-name` disables); unknown names are warned about and skipped
+    pub fn from_spec(spec: &str) -> Self {
+        let flags = Self::default();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, on) = match entry.strip_prefix('-') {
+                Some(name) => (name, false),
+                None => (entry.strip_prefix('+').unwrap_or(entry), true),
+            };
+            match name.parse() {
+                Ok(flag) => {
+                    flags.set(flag, on);
+                }
+                Err(e) => eprintln!("[WARN] FEATURE_FLAGS: {}", e),
+            }
+        }
+        flags
+    }
+
+    #[inline(always)]
+    pub fn enabled(&self, flag: Flag) -> bool {
+        self.bits.load(Ordering::Relaxed) & flag.bit() != 0
+    }
+
+    /// Set a flag; returns its previous value
+    pub fn set(&self, flag: Flag, on: bool) -> bool {
+        let previous = if on {
+            self.bits.fetch_or(flag.bit(), Ordering::Relaxed)
+        } else {
+            self.bits.fetch_and(!flag.bit(), Ordering::Relaxed)
+        };
+        previous & flag.bit() != 0
+    }
+
+    /// (name, enabled) of every flag
+    pub fn list(&self) -> Vec<(&'static str, bool)> {
+        Flag::ALL.iter().map(|&flag| (flag.name(), self.enabled(flag))).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_spec_and_toggle() {
+        assert!(Flag::ALL.iter().all(|&flag| FeatureFlags::default().enabled(flag)));
+
+        let flags = FeatureFlags::from_spec(" -recorder, tick_check,-ANOMALY, bogus");
+        assert_eq!(flags.list(), vec![("recorder", false), ("broadcast", true), ("anomaly", false), ("tick_check", true)]);
+
+        assert!(!flags.set(Flag::Recorder, true));
+        assert!(flags.enabled(Flag::Recorder));
+        assert!(flags.set(Flag::Broadcast, false));
+        assert!(!flags.enabled(Flag::Broadcast) && flags.enabled(Flag::TickCheck));
+        assert!("nope".parse::<Flag>().is_err());
+    }
+}
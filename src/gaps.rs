@@ -0,0 +1,124 @@
+//! Feed quality report for the session: per-symbol update gaps and
+//! per-connection outages, logged at shutdown
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::ws::ConnStats;
+
+/// Longest interval without updates per symbol_id
+pub struct GapTracker {
+    started_us: i64,
+    /// Monotonic µs of the last update (0 = none yet)
+    last_us: Vec<AtomicI64>,
+    longest_us: Vec<AtomicI64>,
+    /// (log tag, stats) of every connection, registered as feeds start
+    connections: Mutex<Vec<(String, Arc<ConnStats>)>>,
+}
+
+/// Per-symbol line of the report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolGap {
+    pub symbol_id: u64,
+    pub longest_us: i64,
+    /// No update at all this session
+    pub never_updated: bool,
+}
+
+impl GapTracker {
+    /// Track symbol_ids 0..n_symbols for a session starting at `started_us`
+    pub fn new(n_symbols: usize, started_us: i64) -> Self {
+        Self {
+            started_us,
+            last_us: (0..n_symbols).map(|_| AtomicI64::new(0)).collect(),
+            longest_us: (0..n_symbols).map(|_| AtomicI64::new(0)).collect(),
+            connections: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Record an update of `symbol_id` at `ts` (monotonic µs)
+    ///
+    /// The interval before a symbol's first update isn't a gap (connect time).
+    #[inline(always)]
+    pub fn observe(&self, symbol_id: u64, ts: i64) {
+        let Some(last) = self.last_us.get(symbol_id as usize) else {
+            return;
+        };
+        let prev = last.swap(ts, Ordering::Relaxed);
+        if prev > 0 {
+            self.longest_us[symbol_id as usize].fetch_max(ts - prev, Ordering::Relaxed);
+        }
+    }
+
+    /// Include these connections in the outage report
+    pub fn add_connections(&self, connections: Vec<(String, usize, Arc<ConnStats>)>) {
+        let mut tracked = self.connections.lock().unwrap_or_else(|e| e.into_inner());
+        tracked.extend(connections.into_iter().map(|(tag, _, stats)| (tag, stats)));
+    }
+
+    /// Gaps of `symbol_ids` up to `now_us` (the silence since the last update counts), longest first
+    pub fn gaps(&self, symbol_ids: impl IntoIterator<Item = u64>, now_us: i64) -> Vec<SymbolGap> {
+        let mut gaps: Vec<SymbolGap> = symbol_ids
+            .into_iter()
+            .filter(|&id| (id as usize) < self.last_us.len())
+            .map(|symbol_id| {
+                let last = self.last_us[symbol_id as usize].load(Ordering::Relaxed);
+                let longest = self.longest_us[symbol_id as usize].load(Ordering::Relaxed);
+                if last == 0 {
+                    SymbolGap { symbol_id, longest_us: now_us - self.started_us, never_updated: true }
+                } else {
+                    SymbolGap { symbol_id, longest_us: longest.max(now_us - last), never_updated: false }
+                }
+            })
+            .collect();
+        gaps.sort_by(|a, b| b.longest_us.cmp(&a.longest_us).then(a.symbol_id.cmp(&b.symbol_id)));
+        gaps
+    }
+
+    /// Log the session report: the `top` worst symbols (0 = all) and every connection's outages
+    pub fn report(&self, symbol_id_map: &HashMap<String, u64>, top: usize, now_us: i64) {
+        let names: HashMap<u64, &str> = symbol_id_map.iter().map(|(s, &id)| (id, s.as_str())).collect();
+        let gaps = self.gaps(names.keys().copied(), now_us);
+        let never: Vec<&str> = gaps.iter().filter(|g| g.never_updated).map(|g| names[&g.symbol_id]).collect();
+
+        eprintln!("\n[GAPS] Session {:.1} s, {} symbols", (now_us - self.started_us) as f64 / 1e6, gaps.len());
+        if !never.is_empty() {
+            eprintln!("[GAPS] Never updated ({}): {}", never.len(), never.join(", "));
+        }
+        let shown = if top == 0 { gaps.len() } else { top.min(gaps.len()) };
+        eprintln!("[GAPS] Longest intervals without updates (top {}):", shown);
+        for gap in gaps.iter().filter(|g| !g.never_updated).take(shown) {
+            eprintln!("[GAPS]   {:<16} {:.3} s", names[&gap.symbol_id], gap.longest_us as f64 / 1e6);
+        }
+
+        let connections = self.connections.lock().unwrap_or_else(|e| e.into_inner());
+        for (tag, stats) in connections.iter() {
+            let (outages, total_us, longest_us) = stats.downtime(now_us);
+            eprintln!("[GAPS] {}: {} outages, {:.3} s down (longest {:.3} s)",
+                      tag, outages, total_us as f64 / 1e6, longest_us as f64 / 1e6);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_gaps() {
+        let tracker = GapTracker::new(4, 1_000);
+        tracker.observe(1, 2_000);
+        tracker.observe(1, 2_500);
+        tracker.observe(1, 9_500);
+        tracker.observe(2, 5_000);
+        tracker.observe(9, 5_000); // out of range: ignored
+
+        let gaps = tracker.gaps([1, 2, 3], 10_000);
+        assert_eq!(gaps, vec![
+            SymbolGap { symbol_id: 3, longest_us: 9_000, never_updated: true },
+            SymbolGap { symbol_id: 1, longest_us: 7_000, never_updated: false },
+            SymbolGap { symbol_id: 2, longest_us: 5_000, never_updated: false },
+        ]);
+    }
+}
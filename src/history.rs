@@ -0,0 +1,270 @@
+//! Quote history ring: recent quotes for readers warming up on start
+//!
+//! The latest-value slots hold only each symbol's current quote; indicators
+//! that need a window (moving averages, volatility) read this ring instead.
+//! The `backfill` binary fills it from recorder captures (RECORD_FILE),
+//! oldest first, so a reader starting up finds recent history.
+//!
+//! Ring file layout (little-endian, 64-byte header then 64-byte records), as
+//! the liquidation event ring:
+//! - header: magic `QHIS1\0\0\0`, version (1), record_size (64), capacity,
+//!   price_scale (1e8), write_idx (atomic: quotes written so far)
+//! - record `write_idx % capacity` holds quote number `write_idx`, written under
+//!   its own seqlock; readers keep a cursor and compare `quote_idx` to detect
+//!   being lapped
+//!
+//! Timestamps are unix µs (captures store the monotonic clock of the process
+//! that recorded them, converted with the capture header). Prices are as
+//! captured: 1e8 unless the symbol has a PRICE_SCALE_OVERRIDES scale.
+
+use anyhow::{bail, Context, Result};
+use memmap2::MmapMut;
+
+use crate::recorder::RecordReader;
+use crate::region::{self, RegionFormat};
+use crate::seqlock::{self, AtomicU64, Ordering};
+
+const MAGIC: &[u8; 8] = b"QHIS1\0\0\0";
+const VERSION: u64 = 1;
+const HEADER_SIZE: u64 = 64;
+const FORMAT: RegionFormat = RegionFormat { kind: "history ring", magic: MAGIC, header_size: HEADER_SIZE };
+const RECORD_SIZE: u64 = 64;
+const PRICE_SCALE: u64 = 100_000_000;
+/// Byte offset of write_idx in the header
+const WRITE_IDX_OFFSET: usize = 40;
+
+/// History record (64 bytes, cache-line aligned)
+#[repr(C, align(64))]
+pub struct History64 {
+    pub seq: AtomicU64,
+    pub quote_idx: u64,
+    pub source_id: u64,
+    pub symbol_id: u64,
+    pub bid: i64,
+    pub ask: i64,
+    /// Write time of the quote (unix µs)
+    pub ts_unix_us: i64,
+    _reserved: u64,
+}
+
+#[cfg(not(feature = "loom"))]
+const _: () = assert!(std::mem::size_of::<History64>() == RECORD_SIZE as usize);
+
+/// Copy of one historical quote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryQuote {
+    pub quote_idx: u64,
+    pub source_id: u64,
+    pub symbol_id: u64,
+    pub bid: i64,
+    pub ask: i64,
+    pub ts_unix_us: i64,
+}
+
+/// Single-writer quote ring in a memory-mapped file
+pub struct QuoteRing {
+    _mmap: MmapMut,
+    write_idx: *const AtomicU64,
+    records: *mut History64,
+    capacity: u64,
+}
+
+// SAFETY: the mapping lives as long as the ring; records are seqlock-protected
+unsafe impl Send for QuoteRing {}
+unsafe impl Sync for QuoteRing {}
+
+impl QuoteRing {
+    /// Open `path`, creating it with room for `capacity` quotes (mode/group as for the
+    /// quotes file) if it doesn't exist
+    pub fn open_or_create(path: &str, capacity: u64, mode: u32, group: Option<&str>) -> Result<Self> {
+        if !std::path::Path::new(path).exists() {
+            Self::create(path, capacity, mode, group)?;
+        }
+        Self::open(path)
+    }
+
+    fn create(path: &str, capacity: u64, mode: u32, group: Option<&str>) -> Result<()> {
+        if capacity == 0 {
+            bail!("History ring capacity must be > 0");
+        }
+        let fields = [VERSION, RECORD_SIZE, capacity, PRICE_SCALE];
+        FORMAT.create(path, &fields, HEADER_SIZE + capacity * RECORD_SIZE, mode, group)?;
+        eprintln!("[HISTORY] Created history ring {} ({} quotes)", path, capacity);
+        Ok(())
+    }
+
+    /// Map an existing ring read-write, validating its header
+    pub fn open(path: &str) -> Result<Self> {
+        let mut mmap = FORMAT.open(path)?;
+        let field = |i: usize| region::field(&mmap, i);
+        let (version, record_size, capacity) = (field(0), field(1), field(2));
+        if version != VERSION || record_size != RECORD_SIZE {
+            bail!("Unsupported history ring {}: version {}, record size {}", path, version, record_size);
+        }
+        if capacity == 0 || mmap.len() as u64 != HEADER_SIZE + capacity * RECORD_SIZE {
+            bail!("History ring {} size {} doesn't match capacity {}", path, mmap.len(), capacity);
+        }
+
+        let base = mmap.as_mut_ptr();
+        Ok(Self {
+            write_idx: unsafe { base.add(WRITE_IDX_OFFSET) } as *const AtomicU64,
+            records: unsafe { base.add(HEADER_SIZE as usize) } as *mut History64,
+            capacity,
+            _mmap: mmap,
+        })
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Quotes written so far (the next quote's index)
+    pub fn write_idx(&self) -> u64 {
+        unsafe { &*self.write_idx }.load(Ordering::Acquire)
+    }
+
+    fn record(&self, idx: u64) -> *mut History64 {
+        unsafe { self.records.add((idx % self.capacity) as usize) }
+    }
+
+    /// Append a quote (single writer); returns its index
+    pub fn push(&self, quote: &HistoryQuote) -> u64 {
+        let write_idx = unsafe { &*self.write_idx };
+        let idx = write_idx.load(Ordering::Relaxed);
+        let record = self.record(idx);
+
+        // SAFETY: single writer; readers detect torn records via the seqlock
+        unsafe {
+            let seq0 = seqlock::write_begin(&(*record).seq);
+            std::ptr::write_volatile(&mut (*record).quote_idx, idx);
+            std::ptr::write_volatile(&mut (*record).source_id, quote.source_id);
+            std::ptr::write_volatile(&mut (*record).symbol_id, quote.symbol_id);
+            std::ptr::write_volatile(&mut (*record).bid, quote.bid);
+            std::ptr::write_volatile(&mut (*record).ask, quote.ask);
+            std::ptr::write_volatile(&mut (*record).ts_unix_us, quote.ts_unix_us);
+            seqlock::write_end(&(*record).seq, seq0);
+        }
+
+        write_idx.store(idx + 1, Ordering::Release);
+        idx
+    }
+
+    /// Read quote `idx`; None if it isn't written yet, was overwritten, or stayed busy
+    pub fn read(&self, idx: u64) -> Option<HistoryQuote> {
+        if idx >= self.write_idx() {
+            return None;
+        }
+        let record = self.record(idx);
+        for _ in 0..1000 {
+            let seq = unsafe { &(*record).seq };
+            let Some(s1) = seqlock::read_begin(seq) else {
+                continue;
+            };
+            // SAFETY: volatile loads so retries re-read the mapped memory
+            let quote = unsafe {
+                HistoryQuote {
+                    quote_idx: std::ptr::read_volatile(&(*record).quote_idx),
+                    source_id: std::ptr::read_volatile(&(*record).source_id),
+                    symbol_id: std::ptr::read_volatile(&(*record).symbol_id),
+                    bid: std::ptr::read_volatile(&(*record).bid),
+                    ask: std::ptr::read_volatile(&(*record).ask),
+                    ts_unix_us: std::ptr::read_volatile(&(*record).ts_unix_us),
+                }
+            };
+            if seqlock::read_end(seq, s1) {
+                return (quote.quote_idx == idx).then_some(quote);
+            }
+        }
+        None
+    }
+
+    /// Time of the newest quote in the ring (None when empty)
+    pub fn newest_ts(&self) -> Option<i64> {
+        self.write_idx().checked_sub(1).and_then(|idx| self.read(idx)).map(|quote| quote.ts_unix_us)
+    }
+}
+
+/// Quotes of the `captures` at or after `since_unix_us`, oldest first
+///
+/// Captures may overlap (several sources, restarts): the merge is by time,
+/// ties keep file order. A capture cut short at the end still counts.
+pub fn load_captures(captures: &[String], since_unix_us: i64) -> Result<Vec<HistoryQuote>> {
+    let mut quotes = Vec::new();
+    for path in captures {
+        let reader = RecordReader::open(path)?;
+        let offset = reader.started_at_unix_us as i64 - reader.started_at_mono_us;
+        for quote in reader {
+            let quote = quote.with_context(|| format!("Failed to read capture {}", path))?;
+            let ts_unix_us = quote.ts + offset;
+            if ts_unix_us >= since_unix_us {
+                quotes.push(HistoryQuote {
+                    quote_idx: 0,
+                    source_id: quote.source_id,
+                    symbol_id: quote.symbol_id,
+                    bid: quote.bid,
+                    ask: quote.ask,
+                    ts_unix_us,
+                });
+            }
+        }
+    }
+    quotes.sort_by_key(|quote| quote.ts_unix_us);
+    Ok(quotes)
+}
+
+/// Append the quotes newer than the ring's newest (so a re-run adds only
+/// what's new) and at most `capacity` of them; returns how many were written
+pub fn backfill(ring: &QuoteRing, quotes: &[HistoryQuote]) -> u64 {
+    let newest = ring.newest_ts();
+    let new = &quotes[quotes.partition_point(|quote| newest.is_some_and(|newest| quote.ts_unix_us <= newest))..];
+    let start = new.len().saturating_sub(ring.capacity() as usize);
+    for quote in &new[start..] {
+        ring.push(quote);
+    }
+    (new.len() - start) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::Recorder;
+    use crate::shm::QuoteSnapshot;
+
+    #[test]
+    fn test_backfill_from_captures() {
+        let dir = std::env::temp_dir().join(format!("test_history_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str| dir.join(name).to_str().unwrap().to_string();
+
+        // Two interleaved captures
+        let captures = vec![file("a.rec"), file("b.rec")];
+        let start = crate::platform::monotonic_us();
+        for (path, symbol_id) in captures.iter().zip([1, 2]) {
+            let recorder = Recorder::create(path, 1, &Default::default()).unwrap();
+            for i in 0..3 {
+                let ts = start + i * 1_000 + symbol_id as i64 * 100;
+                recorder.record(QuoteSnapshot { source_id: 1, symbol_id, bid: 100 + i, ask: 101 + i, ts });
+            }
+            recorder.finish();
+        }
+        let quotes = load_captures(&captures, 0).unwrap();
+        let symbols: Vec<u64> = quotes.iter().map(|quote| quote.symbol_id).collect();
+        assert_eq!(symbols, vec![1, 2, 1, 2, 1, 2]);
+        let since = quotes[4].ts_unix_us;
+        assert_eq!(load_captures(&captures, since).unwrap().len(), 2);
+
+        // A ring smaller than the capture keeps the newest; a re-run adds nothing
+        let ring = QuoteRing::open_or_create(&file("history.dat"), 4, 0o600, None).unwrap();
+        assert_eq!(ring.newest_ts(), None);
+        assert_eq!(backfill(&ring, &quotes), 4);
+        assert_eq!(ring.read(0).unwrap().bid, 101);
+        assert_eq!(ring.read(3).unwrap(), HistoryQuote { quote_idx: 3, ..quotes[5] });
+        assert_eq!(backfill(&ring, &quotes), 0);
+        assert_eq!(ring.write_idx(), 4);
+
+        drop(ring);
+        std::fs::write(file("history.dat"), b"garbage").unwrap();
+        assert!(QuoteRing::open(&file("history.dat")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
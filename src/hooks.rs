@@ -0,0 +1,119 @@
+//! User hooks around message normalization, for embedders of the library
+//!
+//! Three stages, each a list of callbacks run in order:
+//! - pre-parse filter: sees the raw text frame; `false` drops it unparsed
+//! - post-parse transform: may rewrite the parsed message (symbol remaps,
+//!   price adjustments); `false` drops it
+//! - pre-write validator: sees the fixed-point quote about to be written;
+//!   `false` rejects it
+//!
+//! The first two run in the WebSocket read path (`WsManager::with_hooks`, also
+//! honored by the decode pipeline). Writers call `validate` before the SHM write.
+//! A transform that renames a symbol must map it to a subscribed one; with
+//! transforms set, messages are dispatched by symbol rather than stream name.
+
+use crate::shm::QuoteSnapshot;
+use crate::ws::{decode_frame, BookTickerData, FrameError};
+
+pub type PreParseFilter = Box<dyn Fn(&str) -> bool + Send + Sync>;
+pub type PostParseTransform = Box<dyn Fn(&mut BookTickerData) -> bool + Send + Sync>;
+pub type PreWriteValidator = Box<dyn Fn(&QuoteSnapshot) -> bool + Send + Sync>;
+
+/// Hook set; the default has no hooks and costs nothing per message
+#[derive(Default)]
+pub struct Hooks {
+    pre_parse: Vec<PreParseFilter>,
+    post_parse: Vec<PostParseTransform>,
+    pre_write: Vec<PreWriteValidator>,
+}
+
+impl Hooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a pre-parse filter (return false to drop the frame)
+    pub fn pre_parse(mut self, filter: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.pre_parse.push(Box::new(filter));
+        self
+    }
+
+    /// Add a post-parse transform (return false to drop the message)
+    pub fn post_parse(mut self, transform: impl Fn(&mut BookTickerData) -> bool + Send + Sync + 'static) -> Self {
+        self.post_parse.push(Box::new(transform));
+        self
+    }
+
+    /// Add a pre-write validator (return false to reject the quote)
+    pub fn pre_write(mut self, validator: impl Fn(&QuoteSnapshot) -> bool + Send + Sync + 'static) -> Self {
+        self.pre_write.push(Box::new(validator));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pre_parse.is_empty() && self.post_parse.is_empty() && self.pre_write.is_empty()
+    }
+
+    /// Filter, parse (checking the full schema if `strict`) and transform a
+    /// text frame; None if a hook dropped it
+    #[inline]
+    pub fn decode(&self, text: &str, strict: bool) -> Option<Result<BookTickerData, FrameError>> {
+        if !self.pre_parse.iter().all(|filter| filter(text)) {
+            return None;
+        }
+        let mut data = match decode_frame(text, strict) {
+            Ok(data) => data,
+            Err(e) => return Some(Err(e)),
+        };
+        if !self.post_parse.is_empty() {
+            // A remapped symbol must not be written to its original stream's slot
+            data.stream.clear();
+        }
+        self.post_parse.iter().all(|transform| transform(&mut data)).then_some(Ok(data))
+    }
+
+    /// Run the pre-write validators; false = don't write `quote`
+    #[inline(always)]
+    pub fn validate(&self, quote: &QuoteSnapshot) -> bool {
+        self.pre_write.iter().all(|validator| validator(quote))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(symbol: &str, bid: &str) -> String {
+        format!(r#"{{"stream":"x@bookTicker","data":{{"s":"{}","b":"{}","a":"9.0"}}}}"#, symbol, bid)
+    }
+
+    #[test]
+    fn test_hook_stages() {
+        let hooks = Hooks::new()
+            .pre_parse(|text| !text.contains("DROPUSDT"))
+            .post_parse(|data| {
+                if data.symbol == "OLDUSDT" {
+                    data.symbol = "NEWUSDT".to_string();
+                }
+                true
+            })
+            .post_parse(|data| data.bid_price != "0.0")
+            .pre_write(|quote| quote.bid < quote.ask);
+        assert!(!hooks.is_empty());
+
+        assert!(hooks.decode(&frame("DROPUSDT", "1.0"), false).is_none());
+        assert!(hooks.decode(&frame("BTCUSDT", "0.0"), false).is_none());
+        assert!(hooks.decode("not json", false).unwrap().is_err());
+        let remapped = hooks.decode(&frame("OLDUSDT", "1.0"), false).unwrap().unwrap();
+        assert_eq!((remapped.symbol.as_str(), remapped.stream.as_str()), ("NEWUSDT", ""));
+
+        let quote = QuoteSnapshot { source_id: 1, symbol_id: 0, bid: 100, ask: 200, ts: 1 };
+        assert!(hooks.validate(&quote));
+        assert!(!hooks.validate(&QuoteSnapshot { bid: 300, ..quote }));
+
+        let none = Hooks::default();
+        assert!(none.is_empty() && none.validate(&quote));
+        let data = none.decode(&frame("DROPUSDT", "1.0"), false).unwrap().unwrap();
+        assert_eq!((data.symbol.as_str(), data.stream.as_str()), ("DROPUSDT", "x@bookTicker"));
+    }
+}
@@ -0,0 +1,241 @@
+//! Optional HTTP JSON endpoint for current quotes
+//!
+//! `GET /quotes?symbols=BTCUSDT,ETHUSDT` seqlock-reads the SHM and returns
+//! bid/ask/ts as JSON, so ops and scripts don't need the binary format.
+//! A tiny hand-rolled HTTP/1.1 responder: one request per connection, no keep-alive.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::price;
+use crate::shm::ShmManager;
+
+/// Default price scale of the SHM
+const PRICE_SCALE: u64 = 100_000_000;
+/// Largest request head we accept
+const MAX_REQUEST_BYTES: usize = 8192;
+
+#[derive(Debug, Serialize)]
+struct QuoteJson<'a> {
+    symbol: &'a str,
+    symbol_id: u64,
+    /// Prices scaled by price_scale
+    bid: i64,
+    ask: i64,
+    /// The same prices as decimal strings ("65000.1")
+    bid_price: String,
+    ask_price: String,
+    /// monotonic_us of the last update (0 = never written)
+    ts: i64,
+    /// This symbol's scale when it overrides the top-level price_scale
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price_scale: Option<u64>,
+    /// SLOT_STATUS_* bits (see shm), omitted when 0
+    #[serde(skip_serializing_if = "is_zero")]
+    status: u32,
+}
+
+fn is_zero(status: &u32) -> bool {
+    *status == 0
+}
+
+#[derive(Debug, Serialize)]
+struct QuotesResponse<'a> {
+    price_scale: u64,
+    quotes: Vec<QuoteJson<'a>>,
+    /// Requested symbols that aren't subscribed
+    unknown: Vec<&'a str>,
+}
+
+/// Answers quote queries from the SHM for one source
+pub struct QuoteQuery {
+    shm: Arc<ShmManager>,
+    source_id: u64,
+    symbol_id_map: Arc<HashMap<String, u64>>,
+}
+
+impl QuoteQuery {
+    pub fn new(shm: Arc<ShmManager>, source_id: u64, symbol_id_map: Arc<HashMap<String, u64>>) -> Self {
+        Self { shm, source_id, symbol_id_map }
+    }
+
+    /// Handle a request target (path + query); returns (status, JSON body)
+    pub fn handle(&self, target: &str) -> (u16, String) {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        if path != "/quotes" {
+            return (404, r#"{"error":"not found"}"#.to_string());
+        }
+
+        let requested: Vec<String> = query
+            .split('&')
+            .filter_map(|pair| pair.strip_prefix("symbols="))
+            .flat_map(|list| list.split(','))
+            .map(|s| s.trim().to_uppercase())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let mut known: Vec<(&str, u64)> = Vec::new();
+        let mut unknown = Vec::new();
+        if requested.is_empty() {
+            known = self.symbol_id_map.iter().map(|(s, &id)| (s.as_str(), id)).collect();
+            known.sort_unstable();
+        } else {
+            for symbol in &requested {
+                match self.symbol_id_map.get_key_value(symbol.as_str()) {
+                    Some((s, &id)) => known.push((s.as_str(), id)),
+                    None => unknown.push(symbol.as_str()),
+                }
+            }
+        }
+
+        let mut quotes = Vec::with_capacity(known.len());
+        for &(symbol, symbol_id) in &known {
+            let slot = match self.shm.get_slot(self.source_id, symbol_id) {
+                Ok(slot) => slot,
+                Err(e) => return (500, serde_json::json!({ "error": e.to_string() }).to_string()),
+            };
+            // Busy slots are omitted; the status comes from the quote's own seqlock window
+            let Some(((_, symbol_id, bid, ask, ts), status)) = slot.read_with_status() else {
+                continue;
+            };
+            let price_scale = Some(slot.price_scale()).filter(|&scale| scale != PRICE_SCALE);
+            let decimals = price_scale.unwrap_or(PRICE_SCALE).ilog10();
+            quotes.push(QuoteJson {
+                symbol,
+                symbol_id,
+                bid,
+                ask,
+                bid_price: price::format_price_scaled(bid, decimals),
+                ask_price: price::format_price_scaled(ask, decimals),
+                ts,
+                price_scale,
+                status,
+            });
+        }
+
+        let response = QuotesResponse { price_scale: PRICE_SCALE, quotes, unknown };
+        match serde_json::to_string(&response) {
+            Ok(body) => (200, body),
+            Err(e) => (500, serde_json::json!({ "error": e.to_string() }).to_string()),
+        }
+    }
+
+    async fn handle_connection(&self, mut stream: TcpStream) -> Result<()> {
+        let mut buf = Vec::with_capacity(1024);
+        let mut chunk = [0u8; 1024];
+        while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 || buf.len() + n > MAX_REQUEST_BYTES {
+                return Ok(());
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+
+        let head = String::from_utf8_lossy(&buf);
+        let mut parts = head.lines().next().unwrap_or("").split_whitespace();
+        let (status, body) = match (parts.next(), parts.next()) {
+            (Some("GET"), Some(target)) => self.handle(target),
+            _ => (405, r#"{"error":"method not allowed"}"#.to_string()),
+        };
+
+        let reason = match status {
+            200 => "OK",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        };
+        let response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, reason, body.len(), body
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+
+    /// Serve requests; only returns if the task is dropped
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> Result<()> {
+        loop {
+            // Accept errors (EMFILE, aborted handshakes) are transient: log, back off, go on
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    crate::eprintln_limited!("[HTTP] Accept failed: {}", e);
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            let query = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = query.handle_connection(stream).await {
+                    crate::eprintln_limited!("[HTTP] Request failed: {:#}", e);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_query() -> QuoteQuery {
+        let shm = ShmManager::anonymous(2, 4).unwrap();
+        shm.init_slot(1, 1).unwrap();
+        shm.init_slot(1, 2).unwrap();
+        shm.set_price_scale(1, 2, 10_000).unwrap();
+        shm.get_slot(1, 1).unwrap().write(6_500_000_000_000, 6_500_010_000_000, 42);
+
+        let map = HashMap::from([("BTCUSDT".to_string(), 1), ("ETHUSDT".to_string(), 2)]);
+        QuoteQuery::new(Arc::new(shm), 1, Arc::new(map))
+    }
+
+    #[test]
+    fn test_handle_quotes() {
+        let query = test_query();
+
+        let (status, body) = query.handle("/quotes?symbols=btcusdt,FOO");
+        assert_eq!(status, 200);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["quotes"][0]["symbol"], "BTCUSDT");
+        assert_eq!(json["quotes"][0]["bid"], 6_500_000_000_000i64);
+        assert_eq!(json["quotes"][0]["ask_price"], "65000.1");
+        assert_eq!(json["quotes"][0]["ts"], 42);
+        assert_eq!(json["unknown"][0], "FOO");
+
+        let (_, body) = query.handle("/quotes");
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["quotes"].as_array().unwrap().len(), 2);
+        assert_eq!(json["quotes"][1]["symbol"], "ETHUSDT");
+        assert_eq!(json["quotes"][1]["price_scale"], 10_000);
+        assert_eq!(json["quotes"][1]["bid_price"], "0");
+        assert!(json["quotes"][0].get("price_scale").is_none());
+        // ETHUSDT was initialized but never written
+        assert!(json["quotes"][0].get("status").is_none());
+        assert_eq!(json["quotes"][1]["status"], crate::shm::SLOT_STATUS_INIT);
+
+        assert_eq!(query.handle("/other").0, 404);
+    }
+
+    #[tokio::test]
+    async fn test_serve_over_tcp() {
+        let query = test_query();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(Arc::new(query).serve(listener));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET /quotes?symbols=ETHUSDT HTTP/1.1\r\nHost: x\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.ends_with(r#""unknown":[]}"#), "{}", response);
+        assert!(response.contains(r#""symbol":"ETHUSDT""#), "{}", response);
+    }
+}
@@ -0,0 +1,172 @@
+//! Latency injection test mode: delay each quote before it is handled
+//!
+//! For testing downstream systems against a degraded feed without touching
+//! the network. Each message handled waits for a delay drawn from the
+//! configured distribution before its quote is written. The wait is an async
+//! sleep in the connection's read loop: that connection's frames queue up
+//! behind it, so under load delays compound the way they do behind a slow
+//! consumer and quotes are never reordered, while the runtime keeps serving
+//! the other connections, pings and timers. Sleeps have the runtime timer's
+//! resolution (1 ms), so shorter draws wait about a millisecond.
+//!
+//! Distributions (µs): `fixed:500`, `uniform:100..2000`, `normal:1000,250`
+//! (mean, standard deviation; negative draws are 0), `exp:800` (mean).
+
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+
+/// Delay distribution, µs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    Fixed(u64),
+    Uniform { min: u64, max: u64 },
+    Normal { mean: f64, stddev: f64 },
+    Exponential { mean: f64 },
+}
+
+impl FromStr for Distribution {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, args) = s.trim().split_once(':').unwrap_or((s.trim(), ""));
+        let num = |v: &str| v.trim().parse::<u64>().with_context(|| format!("Invalid delay {:?} in {:?}", v, s));
+        match kind.to_ascii_lowercase().as_str() {
+            "fixed" => Ok(Self::Fixed(num(args)?)),
+            "uniform" => {
+                let Some((min, max)) = args.split_once("..") else {
+                    bail!("Expected uniform:MIN..MAX, got {:?}", s);
+                };
+                let (min, max) = (num(min)?, num(max)?);
+                if min > max {
+                    bail!("Uniform delay range {:?} is empty", s);
+                }
+                Ok(Self::Uniform { min, max })
+            }
+            "normal" => {
+                let Some((mean, stddev)) = args.split_once(',') else {
+                    bail!("Expected normal:MEAN,STDDEV, got {:?}", s);
+                };
+                Ok(Self::Normal { mean: num(mean)? as f64, stddev: num(stddev)? as f64 })
+            }
+            "exp" => Ok(Self::Exponential { mean: num(args)? as f64 }),
+            other => bail!("Unknown delay distribution {:?} (fixed, uniform, normal, exp)", other),
+        }
+    }
+}
+
+/// xorshift64* PRNG shareable across threads (a seed replays the same draws;
+/// concurrent draws may repeat a value, which test modes don't mind)
+#[derive(Debug)]
+pub(crate) struct XorShift(AtomicU64);
+
+impl XorShift {
+    pub(crate) fn new(seed: u64) -> Self {
+        // xorshift state must be non-zero
+        Self(AtomicU64::new(seed.max(1)))
+    }
+
+    pub(crate) fn next_u64(&self) -> u64 {
+        let mut x = self.0.load(Ordering::Relaxed);
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0.store(x, Ordering::Relaxed);
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in [0, 1)
+    pub(crate) fn next_f64(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Draws delays from a distribution (xorshift64*, so a seed replays the same delays)
+pub struct LatencyInjector {
+    distribution: Distribution,
+    rng: XorShift,
+}
+
+impl LatencyInjector {
+    pub fn new(distribution: Distribution, seed: u64) -> Self {
+        Self { distribution, rng: XorShift::new(seed) }
+    }
+
+    pub fn distribution(&self) -> Distribution {
+        self.distribution
+    }
+
+    fn next_f64(&self) -> f64 {
+        self.rng.next_f64()
+    }
+
+    /// Next delay, µs
+    pub fn next_delay_us(&self) -> u64 {
+        match self.distribution {
+            Distribution::Fixed(us) => us,
+            Distribution::Uniform { min, max } => min + ((max - min + 1) as f64 * self.next_f64()) as u64,
+            Distribution::Normal { mean, stddev } => {
+                // Box-Muller
+                let (u1, u2) = (1.0 - self.next_f64(), self.next_f64());
+                let z = (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+                (mean + stddev * z).max(0.0) as u64
+            }
+            Distribution::Exponential { mean } => (-mean * (1.0 - self.next_f64()).ln()) as u64,
+        }
+    }
+
+    /// Wait for the next delay without blocking the runtime; returns the time
+    /// actually waited (µs)
+    pub async fn delay(&self) -> u64 {
+        let us = self.next_delay_us();
+        if us == 0 {
+            return 0;
+        }
+        let start = tokio::time::Instant::now();
+        tokio::time::sleep(Duration::from_micros(us)).await;
+        start.elapsed().as_micros() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+
+    #[test]
+    fn test_distributions() {
+        assert_eq!("fixed:500".parse::<Distribution>().unwrap(), Distribution::Fixed(500));
+        assert_eq!("Uniform: 100..2000".parse::<Distribution>().unwrap(), Distribution::Uniform { min: 100, max: 2000 });
+        assert_eq!("normal:1000,250".parse::<Distribution>().unwrap(), Distribution::Normal { mean: 1000.0, stddev: 250.0 });
+        for bad in ["", "fixed", "uniform:5..1", "normal:1000", "exp:-1", "pareto:1"] {
+            assert!(bad.parse::<Distribution>().is_err(), "{}", bad);
+        }
+
+        let mean = |injector: &LatencyInjector| (0..10_000).map(|_| injector.next_delay_us()).sum::<u64>() / 10_000;
+        let uniform = LatencyInjector::new("uniform:100..200".parse().unwrap(), 7);
+        let draws: Vec<u64> = (0..1_000).map(|_| uniform.next_delay_us()).collect();
+        assert!(draws.iter().all(|d| (100..=200).contains(d)));
+        assert!(draws.contains(&100) && draws.contains(&200));
+        assert!((140..160).contains(&mean(&uniform)));
+        assert!((950..1050).contains(&mean(&LatencyInjector::new("normal:1000,250".parse().unwrap(), 7))));
+        assert!((760..840).contains(&mean(&LatencyInjector::new("exp:800".parse().unwrap(), 7))));
+
+        // Same seed, same delays
+        let (a, b) = (LatencyInjector::new(Distribution::Exponential { mean: 50.0 }, 42),
+                      LatencyInjector::new(Distribution::Exponential { mean: 50.0 }, 42));
+        assert!((0..100).all(|_| a.next_delay_us() == b.next_delay_us()));
+        assert_eq!(LatencyInjector::new(Distribution::Fixed(0), 1).delay().now_or_never(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_delay_does_not_block_runtime() {
+        // Two 50 ms waits on the current_thread runtime overlap instead of queueing
+        let injector = LatencyInjector::new(Distribution::Fixed(50_000), 1);
+        let start = std::time::Instant::now();
+        let (a, b) = tokio::join!(injector.delay(), injector.delay());
+        assert!(a >= 50_000 && b >= 50_000, "{} {}", a, b);
+        assert!(start.elapsed() < Duration::from_millis(90), "{:?}", start.elapsed());
+    }
+}
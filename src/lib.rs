@@ -1,5 +1,53 @@
 // Library interface for testing
+pub mod admin;
+pub mod app;
+pub mod broadcast;
+pub mod canary;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod compare;
+pub mod config;
+pub mod conflation;
+pub mod depth;
+pub mod dispatch;
+pub mod drift;
+pub mod fault;
+pub mod fixed;
+pub mod flags;
+pub mod gaps;
+pub mod history;
+pub mod hooks;
+pub mod http;
+pub mod inject;
+pub mod liquidation;
+pub mod metrics;
+pub mod monitor;
+pub mod oi;
+pub mod ownership;
+pub mod persist;
+pub mod pipeline;
+pub mod platform;
+pub mod preflight;
+pub mod ratelog;
+pub mod readers;
+pub mod recorder;
+pub mod region;
+pub mod reload;
+pub mod reconnect;
+pub mod rest;
+pub mod seqlock;
+pub mod sequence;
+pub mod shed;
 pub mod shm;
+pub mod spsc;
+pub mod status;
 pub mod symbols;
+pub mod synthetic;
+pub mod trace;
+pub mod trading_status;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub mod uring;
 pub mod price;
+pub mod watchdog;
+pub mod writer;
 pub mod ws;
@@ -0,0 +1,304 @@
+//! Liquidation capture: Binance `!forceOrder@arr` events into an SHM event ring
+//!
+//! Ring file layout (little-endian, 64-byte header then 64-byte records):
+//! - header: magic `QEVT1\0\0\0`, version (1), record_size (64), capacity,
+//!   price_scale (1e8), write_idx (atomic: events written so far)
+//! - record `write_idx % capacity` holds event number `write_idx`, written under
+//!   its own seqlock; readers keep a cursor and compare `event_idx` to detect
+//!   being lapped
+
+use anyhow::{bail, Context, Result};
+use memmap2::MmapMut;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::config::WsConfig;
+use crate::price::{self, PriceParseOptions};
+use crate::region::{self, RegionFormat};
+use crate::seqlock::{self, AtomicU64, Ordering};
+use crate::ws::{MessageHandler, PerfStats, WsConnection, WsManager};
+
+const MAGIC: &[u8; 8] = b"QEVT1\0\0\0";
+const VERSION: u64 = 1;
+const HEADER_SIZE: u64 = 64;
+const FORMAT: RegionFormat = RegionFormat { kind: "event ring", magic: MAGIC, header_size: HEADER_SIZE };
+const RECORD_SIZE: u64 = 64;
+const PRICE_SCALE: u64 = 100_000_000;
+/// Byte offset of write_idx in the header
+const WRITE_IDX_OFFSET: usize = 40;
+/// All-market liquidation stream
+const STREAM: &str = "!forceOrder@arr";
+
+/// Liquidation side, as stored in `Event64::side`
+pub const SIDE_BUY: i64 = 1;
+pub const SIDE_SELL: i64 = -1;
+
+/// Event record (64 bytes, cache-line aligned)
+#[repr(C, align(64))]
+pub struct Event64 {
+    pub seq: AtomicU64,
+    pub event_idx: u64,
+    pub symbol_id: u64,
+    /// SIDE_BUY (short liquidated) or SIDE_SELL (long liquidated)
+    pub side: i64,
+    /// Average fill price * 1e8
+    pub price: i64,
+    /// Filled quantity * 1e8
+    pub qty: i64,
+    /// Exchange trade time (ms since epoch)
+    pub trade_time_ms: i64,
+    /// Receive time (monotonic µs)
+    pub ts: i64,
+}
+
+#[cfg(not(feature = "loom"))]
+const _: () = assert!(std::mem::size_of::<Event64>() == RECORD_SIZE as usize);
+
+/// Copy of one event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LiquidationEvent {
+    pub event_idx: u64,
+    pub symbol_id: u64,
+    pub side: i64,
+    pub price: i64,
+    pub qty: i64,
+    pub trade_time_ms: i64,
+    pub ts: i64,
+}
+
+/// Single-writer event ring in a memory-mapped file
+pub struct EventRing {
+    _mmap: MmapMut,
+    write_idx: *const AtomicU64,
+    records: *mut Event64,
+    capacity: u64,
+}
+
+// SAFETY: the mapping lives as long as the ring; records are seqlock-protected
+unsafe impl Send for EventRing {}
+unsafe impl Sync for EventRing {}
+
+impl EventRing {
+    /// Open `path`, creating it with room for `capacity` events (mode/group as for the
+    /// quotes file) if it doesn't exist
+    pub fn open_or_create(path: &str, capacity: u64, mode: u32, group: Option<&str>) -> Result<Self> {
+        if !std::path::Path::new(path).exists() {
+            Self::create(path, capacity, mode, group)?;
+        }
+        Self::open(path)
+    }
+
+    fn create(path: &str, capacity: u64, mode: u32, group: Option<&str>) -> Result<()> {
+        if capacity == 0 {
+            bail!("Event ring capacity must be > 0");
+        }
+        let fields = [VERSION, RECORD_SIZE, capacity, PRICE_SCALE];
+        FORMAT.create(path, &fields, HEADER_SIZE + capacity * RECORD_SIZE, mode, group)?;
+        eprintln!("[LIQ] Created event ring {} ({} events)", path, capacity);
+        Ok(())
+    }
+
+    /// Map an existing ring read-write, validating its header
+    pub fn open(path: &str) -> Result<Self> {
+        let mut mmap = FORMAT.open(path)?;
+        let field = |i: usize| region::field(&mmap, i);
+        let (version, record_size, capacity) = (field(0), field(1), field(2));
+        if version != VERSION || record_size != RECORD_SIZE {
+            bail!("Unsupported event ring {}: version {}, record size {}", path, version, record_size);
+        }
+        if capacity == 0 || mmap.len() as u64 != HEADER_SIZE + capacity * RECORD_SIZE {
+            bail!("Event ring {} size {} doesn't match capacity {}", path, mmap.len(), capacity);
+        }
+
+        let base = mmap.as_mut_ptr();
+        Ok(Self {
+            write_idx: unsafe { base.add(WRITE_IDX_OFFSET) } as *const AtomicU64,
+            records: unsafe { base.add(HEADER_SIZE as usize) } as *mut Event64,
+            capacity,
+            _mmap: mmap,
+        })
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Events written so far (the next event's index)
+    pub fn write_idx(&self) -> u64 {
+        unsafe { &*self.write_idx }.load(Ordering::Acquire)
+    }
+
+    fn record(&self, idx: u64) -> *mut Event64 {
+        unsafe { self.records.add((idx % self.capacity) as usize) }
+    }
+
+    /// Append an event (single writer); returns its index
+    pub fn push(&self, event: &LiquidationEvent) -> u64 {
+        let write_idx = unsafe { &*self.write_idx };
+        let idx = write_idx.load(Ordering::Relaxed);
+        let record = self.record(idx);
+
+        // SAFETY: single writer; readers detect torn records via the seqlock
+        unsafe {
+            let seq0 = seqlock::write_begin(&(*record).seq);
+            std::ptr::write_volatile(&mut (*record).event_idx, idx);
+            std::ptr::write_volatile(&mut (*record).symbol_id, event.symbol_id);
+            std::ptr::write_volatile(&mut (*record).side, event.side);
+            std::ptr::write_volatile(&mut (*record).price, event.price);
+            std::ptr::write_volatile(&mut (*record).qty, event.qty);
+            std::ptr::write_volatile(&mut (*record).trade_time_ms, event.trade_time_ms);
+            std::ptr::write_volatile(&mut (*record).ts, event.ts);
+            seqlock::write_end(&(*record).seq, seq0);
+        }
+
+        write_idx.store(idx + 1, Ordering::Release);
+        idx
+    }
+
+    /// Read event `idx`; None if it isn't written yet, was overwritten, or stayed busy
+    pub fn read(&self, idx: u64) -> Option<LiquidationEvent> {
+        if idx >= self.write_idx() {
+            return None;
+        }
+        let record = self.record(idx);
+        for _ in 0..1000 {
+            let seq = unsafe { &(*record).seq };
+            let Some(s1) = seqlock::read_begin(seq) else {
+                continue;
+            };
+            // SAFETY: volatile loads so retries re-read the mapped memory
+            let event = unsafe {
+                LiquidationEvent {
+                    event_idx: std::ptr::read_volatile(&(*record).event_idx),
+                    symbol_id: std::ptr::read_volatile(&(*record).symbol_id),
+                    side: std::ptr::read_volatile(&(*record).side),
+                    price: std::ptr::read_volatile(&(*record).price),
+                    qty: std::ptr::read_volatile(&(*record).qty),
+                    trade_time_ms: std::ptr::read_volatile(&(*record).trade_time_ms),
+                    ts: std::ptr::read_volatile(&(*record).ts),
+                }
+            };
+            if seqlock::read_end(seq, s1) {
+                return (event.event_idx == idx).then_some(event);
+            }
+        }
+        None
+    }
+}
+
+/// forceOrder payload, combined-stream wrapped
+#[derive(Debug, Deserialize)]
+struct ForceOrderFrame {
+    data: ForceOrderData,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForceOrderData {
+    #[serde(rename = "o")]
+    order: ForceOrder,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForceOrder {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "S")]
+    side: String,
+    #[serde(rename = "ap")]
+    avg_price: String,
+    #[serde(rename = "z")]
+    filled_qty: String,
+    #[serde(rename = "T")]
+    trade_time_ms: i64,
+}
+
+/// Parse a forceOrder frame; Ok(None) for symbols outside `symbol_id_map`
+pub fn parse_event(text: &str, symbol_id_map: &HashMap<String, u64>, opts: &PriceParseOptions, ts: i64) -> Result<Option<LiquidationEvent>> {
+    let frame: ForceOrderFrame = serde_json::from_str(text).context("Invalid forceOrder message")?;
+    let order = frame.data.order;
+    let Some(&symbol_id) = symbol_id_map.get(&order.symbol) else {
+        return Ok(None);
+    };
+    let side = match order.side.as_str() {
+        "BUY" => SIDE_BUY,
+        "SELL" => SIDE_SELL,
+        other => bail!("Unknown liquidation side {:?}", other),
+    };
+    Ok(Some(LiquidationEvent {
+        event_idx: 0,
+        symbol_id,
+        side,
+        price: price::parse_price_with(&order.avg_price, opts)?,
+        qty: price::parse_price_with(&order.filled_qty, opts)?,
+        trade_time_ms: order.trade_time_ms,
+        ts,
+    }))
+}
+
+/// One-connection manager streaming liquidations of subscribed symbols into `ring`
+pub fn manager(
+    ring: Arc<EventRing>,
+    symbol_id_map: Arc<HashMap<String, u64>>,
+    opts: PriceParseOptions,
+    config: WsConfig,
+    stats: Arc<PerfStats>,
+) -> WsManager {
+    let config = WsConfig { all_market: false, ..config };
+    let unused: MessageHandler = Arc::new(|_| {});
+    let connection = WsConnection::new(Vec::new(), unused, config, stats.clone())
+        .with_streams(STREAM)
+        .with_raw_handler(Box::new(move |text: String| {
+            match parse_event(&text, &symbol_id_map, &opts, crate::platform::monotonic_us()) {
+                Ok(Some(event)) => {
+                    ring.push(&event);
+                }
+                Ok(None) => {}
+                Err(e) => crate::eprintln_limited!("[LIQ] Failed to parse liquidation: {:#}", e),
+            }
+        }));
+    WsManager::from_connections(vec![connection], stats).with_label("L")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: &str = r#"{"stream":"!forceOrder@arr","data":{"e":"forceOrder","E":1568014460893,"o":{"s":"BTCUSDT","S":"SELL","o":"LIMIT","f":"IOC","q":"0.014","p":"9910","ap":"9910.5","X":"FILLED","l":"0.014","z":"0.014","T":1568014460893}}}"#;
+
+    #[test]
+    fn test_parse_event() {
+        let map = HashMap::from([("BTCUSDT".to_string(), 1)]);
+        let opts = PriceParseOptions::default();
+        let event = parse_event(FRAME, &map, &opts, 42).unwrap().unwrap();
+        assert_eq!((event.symbol_id, event.side, event.price, event.qty), (1, SIDE_SELL, 991_050_000_000, 1_400_000));
+        assert_eq!((event.trade_time_ms, event.ts), (1_568_014_460_893, 42));
+
+        assert_eq!(parse_event(FRAME, &HashMap::new(), &opts, 42).unwrap(), None);
+    }
+
+    #[test]
+    fn test_ring_wraps() {
+        let path = std::env::temp_dir().join(format!("test_event_ring_{}.dat", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let ring = EventRing::open_or_create(path, 4, 0o600, None).unwrap();
+        let event = |i: i64| LiquidationEvent { event_idx: 0, symbol_id: 7, side: SIDE_BUY, price: i, qty: 1, trade_time_ms: i, ts: i };
+        for i in 0..6 {
+            assert_eq!(ring.push(&event(i)), i as u64);
+        }
+        assert_eq!(ring.write_idx(), 6);
+        assert_eq!(ring.read(1), None); // lapped
+        assert_eq!(ring.read(5).unwrap().price, 5);
+        assert_eq!(ring.read(5).unwrap().event_idx, 5);
+        assert_eq!(ring.read(6), None); // not written yet
+
+        // Reopen keeps the events; a mismatched file is rejected
+        drop(ring);
+        assert_eq!(EventRing::open(path).unwrap().read(4).unwrap().price, 4);
+        std::fs::write(path, b"garbage").unwrap();
+        assert!(EventRing::open(path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,696 @@
+//! Pluggable metrics backends
+//!
+//! The hot path keeps counting into PerfStats / ConnStats atomics and the
+//! process-wide `shm::COUNTERS` / `price::COUNTERS` (status changes, seqlock
+//! read retries; price parse errors and strict-mode rejects); a `MetricsExporter` turns those into counter deltas and gauges every
+//! METRICS_INTERVAL_MS, and connection events (disconnects, time spent per
+//! lifecycle state) are reported as they happen. Both go to one `Metrics`
+//! backend picked at runtime by METRICS_BACKEND:
+//! - `none` - discard (the default);
+//! - `stderr` - `[METRICS]` lines with what changed, every interval;
+//! - `prometheus` - text exposition on `http://METRICS_ADDR/metrics`;
+//! - `statsd` - UDP datagrams to STATSD_ADDR (Datadog agent, Telegraf),
+//!   non-blocking and best-effort.
+//!
+//! Names are prefixed with METRICS_PREFIX (`binance_writer_messages_total`,
+//! `binance_writer.messages` for StatsD).
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::UdpSocket;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::reconnect::ConnState;
+use crate::ws::{ConnStats, PerfSnapshot, PerfStats};
+
+/// Largest scrape request head read
+const MAX_REQUEST_BYTES: usize = 8192;
+/// StatsD datagram payload limit (fits a 1500-byte MTU with IP/UDP headers)
+const STATSD_PACKET_BYTES: usize = 1432;
+
+/// Metric labels: (name, value) pairs
+pub type Labels<'a> = &'a [(&'a str, &'a str)];
+
+/// Metrics sink; calls come from background tasks and connection events,
+/// never per message
+pub trait Metrics: Send + Sync {
+    /// Add `delta` to a counter
+    fn counter(&self, name: &str, labels: Labels, delta: u64);
+    /// Set a gauge
+    fn gauge(&self, name: &str, labels: Labels, value: f64);
+    /// Record one duration observation (µs)
+    fn timing(&self, name: &str, labels: Labels, us: u64);
+    /// End of an export round
+    fn flush(&self) {}
+}
+
+/// Discards everything
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {
+    fn counter(&self, _: &str, _: Labels, _: u64) {}
+    fn gauge(&self, _: &str, _: Labels, _: f64) {}
+    fn timing(&self, _: &str, _: Labels, _: u64) {}
+}
+
+/// Which backend METRICS_BACKEND selects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    None,
+    Stderr,
+    Prometheus,
+    Statsd,
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "none" => Ok(Self::None),
+            "stderr" => Ok(Self::Stderr),
+            "prometheus" => Ok(Self::Prometheus),
+            "statsd" => Ok(Self::Statsd),
+            other => bail!("Unknown metrics backend {:?} (none, stderr, prometheus, statsd)", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Counter(u64),
+    Gauge(f64),
+    Timing { count: u64, sum_us: u64, max_us: u64 },
+}
+
+/// Series by (name, rendered labels), with whether they changed since the last flush
+#[derive(Default)]
+struct Registry {
+    series: BTreeMap<(String, String), (Value, bool)>,
+}
+
+impl Registry {
+    /// Counters and timings accumulate, gauges are replaced
+    fn update(&mut self, name: &str, labels: Labels, new: Value) {
+        use std::collections::btree_map::Entry;
+
+        match self.series.entry((name.to_string(), render_labels(labels))) {
+            Entry::Vacant(entry) => {
+                entry.insert((new, true));
+            }
+            Entry::Occupied(mut entry) => {
+                let (value, changed) = entry.get_mut();
+                let updated = match (*value, new) {
+                    (Value::Counter(total), Value::Counter(delta)) => Value::Counter(total + delta),
+                    (Value::Timing { count, sum_us, max_us }, Value::Timing { count: n, sum_us: us, max_us: max }) => {
+                        Value::Timing { count: count + n, sum_us: sum_us + us, max_us: max_us.max(max) }
+                    }
+                    _ => new,
+                };
+                *changed |= updated != *value;
+                *value = updated;
+            }
+        }
+    }
+}
+
+/// `a="x",b="y"` with Prometheus escaping
+fn render_labels(labels: Labels) -> String {
+    let mut out = String::new();
+    for (i, (name, value)) in labels.iter().enumerate() {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+        let _ = write!(out, "{}{}=\"{}\"", if i > 0 { "," } else { "" }, name, value);
+    }
+    out
+}
+
+/// Logs the series that changed since the last flush, one `[METRICS]` line
+/// each; timings are per interval (count, avg, max)
+pub struct StderrMetrics {
+    prefix: String,
+    registry: Mutex<Registry>,
+}
+
+impl StderrMetrics {
+    pub fn new(prefix: &str) -> Self {
+        Self { prefix: prefix.to_string(), registry: Mutex::default() }
+    }
+}
+
+impl Metrics for StderrMetrics {
+    fn counter(&self, name: &str, labels: Labels, delta: u64) {
+        self.registry.lock().unwrap_or_else(|e| e.into_inner()).update(name, labels, Value::Counter(delta));
+    }
+
+    fn gauge(&self, name: &str, labels: Labels, value: f64) {
+        self.registry.lock().unwrap_or_else(|e| e.into_inner()).update(name, labels, Value::Gauge(value));
+    }
+
+    fn timing(&self, name: &str, labels: Labels, us: u64) {
+        let value = Value::Timing { count: 1, sum_us: us, max_us: us };
+        self.registry.lock().unwrap_or_else(|e| e.into_inner()).update(name, labels, value);
+    }
+
+    fn flush(&self) {
+        let mut registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        for ((name, labels), (value, changed)) in registry.series.iter_mut() {
+            if !std::mem::take(changed) {
+                continue;
+            }
+            let series = if labels.is_empty() { format!("{}_{}", self.prefix, name) } else { format!("{}_{}{{{}}}", self.prefix, name, labels) };
+            match value {
+                Value::Counter(total) => eprintln!("[METRICS] {} {}", series, total),
+                Value::Gauge(v) => eprintln!("[METRICS] {} {}", series, v),
+                Value::Timing { count, sum_us, max_us } => {
+                    eprintln!("[METRICS] {} count {} avg {} µs max {} µs", series, count, *sum_us / (*count).max(1), max_us);
+                    *value = Value::Timing { count: 0, sum_us: 0, max_us: 0 };
+                }
+            }
+        }
+    }
+}
+
+/// Keeps every series for scraping (Prometheus text exposition format 0.0.4):
+/// counters as `<name>_total`, timings as summaries `<name>_sum` / `<name>_count` (µs)
+pub struct PrometheusMetrics {
+    prefix: String,
+    registry: Mutex<Registry>,
+}
+
+impl PrometheusMetrics {
+    pub fn new(prefix: &str) -> Self {
+        Self { prefix: prefix.to_string(), registry: Mutex::default() }
+    }
+
+    /// The exposition text
+    pub fn render(&self) -> String {
+        let registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = String::new();
+        let mut last_name = "";
+        for ((name, labels), (value, _)) in registry.series.iter() {
+            let (kind, suffix) = match value {
+                Value::Counter(_) => ("counter", "_total"),
+                Value::Gauge(_) => ("gauge", ""),
+                Value::Timing { .. } => ("summary", ""),
+            };
+            let full = format!("{}_{}{}", self.prefix, name, suffix);
+            if name != last_name {
+                let _ = writeln!(out, "# TYPE {} {}", full, kind);
+                last_name = name;
+            }
+            let braced = if labels.is_empty() { String::new() } else { format!("{{{}}}", labels) };
+            match value {
+                Value::Counter(total) => { let _ = writeln!(out, "{}{} {}", full, braced, total); }
+                Value::Gauge(v) => { let _ = writeln!(out, "{}{} {}", full, braced, v); }
+                Value::Timing { count, sum_us, .. } => {
+                    let _ = writeln!(out, "{}_sum{} {}", full, braced, sum_us);
+                    let _ = writeln!(out, "{}_count{} {}", full, braced, count);
+                }
+            }
+        }
+        out
+    }
+
+    async fn handle_connection(&self, mut stream: TcpStream) -> Result<()> {
+        let mut buf = Vec::with_capacity(1024);
+        let mut chunk = [0u8; 1024];
+        while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 || buf.len() + n > MAX_REQUEST_BYTES {
+                return Ok(());
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+
+        let head = String::from_utf8_lossy(&buf);
+        let mut parts = head.lines().next().unwrap_or("").split_whitespace();
+        let (status, body) = match (parts.next(), parts.next()) {
+            (Some("GET"), Some("/metrics")) => ("200 OK", self.render()),
+            (Some("GET"), Some(_)) => ("404 Not Found", "not found\n".to_string()),
+            _ => ("405 Method Not Allowed", "method not allowed\n".to_string()),
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, body.len(), body
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+
+    /// Serve scrapes; only returns if the task is dropped
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> Result<()> {
+        loop {
+            // Accept errors (EMFILE, aborted handshakes) are transient: log, back off, go on
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    crate::eprintln_limited!("[METRICS] Accept failed: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            let metrics = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = metrics.handle_connection(stream).await {
+                    crate::eprintln_limited!("[METRICS] Scrape failed: {:#}", e);
+                }
+            });
+        }
+    }
+}
+
+impl Metrics for PrometheusMetrics {
+    fn counter(&self, name: &str, labels: Labels, delta: u64) {
+        self.registry.lock().unwrap_or_else(|e| e.into_inner()).update(name, labels, Value::Counter(delta));
+    }
+
+    fn gauge(&self, name: &str, labels: Labels, value: f64) {
+        self.registry.lock().unwrap_or_else(|e| e.into_inner()).update(name, labels, Value::Gauge(value));
+    }
+
+    fn timing(&self, name: &str, labels: Labels, us: u64) {
+        let value = Value::Timing { count: 1, sum_us: us, max_us: us };
+        self.registry.lock().unwrap_or_else(|e| e.into_inner()).update(name, labels, value);
+    }
+}
+
+/// Sends StatsD lines over UDP: counters `|c`, gauges `|g`, timings `|ms`.
+/// Labels become DogStatsD tags (`|#conn:WS-3,kind:reset`), or with tags off
+/// are appended to the name (`prefix.disconnects.WS-3.reset`). Lines are
+/// batched into datagrams, sent when one fills up and on flush; the socket
+/// is non-blocking and a datagram that can't be sent is dropped and counted.
+pub struct StatsdMetrics {
+    prefix: String,
+    tags: bool,
+    socket: UdpSocket,
+    packet: Mutex<Vec<u8>>,
+    sent: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl StatsdMetrics {
+    /// Send to `addr` (host:port, resolved once)
+    pub fn new(addr: &str, prefix: &str, tags: bool) -> Result<Self> {
+        let socket = UdpSocket::bind(if addr.starts_with('[') { "[::]:0" } else { "0.0.0.0:0" })
+            .context("Failed to open StatsD socket")?;
+        socket.connect(addr).with_context(|| format!("Failed to resolve StatsD address {}", addr))?;
+        socket.set_nonblocking(true).context("Failed to make StatsD socket non-blocking")?;
+        Ok(Self {
+            prefix: prefix.to_string(),
+            tags,
+            socket,
+            packet: Mutex::new(Vec::with_capacity(STATSD_PACKET_BYTES)),
+            sent: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        })
+    }
+
+    /// (datagrams sent, datagrams dropped)
+    pub fn sent_dropped(&self) -> (u64, u64) {
+        (self.sent.load(Ordering::Relaxed), self.dropped.load(Ordering::Relaxed))
+    }
+
+    /// One line, e.g. `prefix.name:1|c|#a:x`
+    fn line(&self, name: &str, labels: Labels, value: &str, kind: &str) -> String {
+        // StatsD separators can't appear in names or tags
+        let clean = |s: &str| s.replace([':', '|', '@', ',', '#', '\n'], "_");
+        let mut line = format!("{}.{}", self.prefix, name);
+        if !self.tags {
+            for (_, v) in labels {
+                let _ = write!(line, ".{}", clean(v));
+            }
+        }
+        let _ = write!(line, ":{}|{}", value, kind);
+        if self.tags && !labels.is_empty() {
+            line.push_str("|#");
+            for (i, (k, v)) in labels.iter().enumerate() {
+                let _ = write!(line, "{}{}:{}", if i > 0 { "," } else { "" }, k, clean(v));
+            }
+        }
+        line
+    }
+
+    fn push(&self, line: String) {
+        let mut packet = self.packet.lock().unwrap_or_else(|e| e.into_inner());
+        if !packet.is_empty() && packet.len() + 1 + line.len() > STATSD_PACKET_BYTES {
+            self.send(&mut packet);
+        }
+        if !packet.is_empty() {
+            packet.push(b'\n');
+        }
+        packet.extend_from_slice(line.as_bytes());
+    }
+
+    fn send(&self, packet: &mut Vec<u8>) {
+        match self.socket.send(packet) {
+            Ok(_) => self.sent.fetch_add(1, Ordering::Relaxed),
+            Err(e) => {
+                crate::eprintln_limited!("[METRICS] StatsD send failed: {}", e);
+                self.dropped.fetch_add(1, Ordering::Relaxed)
+            }
+        };
+        packet.clear();
+    }
+}
+
+impl Metrics for StatsdMetrics {
+    fn counter(&self, name: &str, labels: Labels, delta: u64) {
+        if delta > 0 {
+            self.push(self.line(name, labels, &delta.to_string(), "c"));
+        }
+    }
+
+    fn gauge(&self, name: &str, labels: Labels, value: f64) {
+        self.push(self.line(name, labels, &value.to_string(), "g"));
+    }
+
+    fn timing(&self, name: &str, labels: Labels, us: u64) {
+        self.push(self.line(name, labels, &(us as f64 / 1000.0).to_string(), "ms"));
+    }
+
+    fn flush(&self) {
+        let mut packet = self.packet.lock().unwrap_or_else(|e| e.into_inner());
+        if !packet.is_empty() {
+            self.send(&mut packet);
+        }
+    }
+}
+
+/// (connection tag, stats, (frames, bytes) at the previous export)
+type ExportedConnection = (String, Arc<ConnStats>, (u64, u64));
+
+/// Periodically turns feed and connection stats into metrics
+pub struct MetricsExporter {
+    metrics: Arc<dyn Metrics>,
+    /// False for the noop backend: nothing to export
+    enabled: bool,
+    /// The backend again when it serves scrapes
+    prometheus: Option<Arc<PrometheusMetrics>>,
+    /// (feed label, stats, snapshot at the previous export)
+    feeds: Mutex<Vec<(&'static str, Arc<PerfStats>, PerfSnapshot)>>,
+    connections: Mutex<Vec<ExportedConnection>>,
+    /// shm and price counters at the previous export
+    process: Mutex<[u64; 6]>,
+}
+
+impl MetricsExporter {
+    pub fn new(metrics: Arc<dyn Metrics>) -> Self {
+        Self {
+            metrics,
+            enabled: true,
+            prometheus: None,
+            feeds: Mutex::default(),
+            connections: Mutex::default(),
+            process: Mutex::default(),
+        }
+    }
+
+    /// With the noop backend: nothing exported
+    pub fn disabled() -> Self {
+        Self { enabled: false, ..Self::new(Arc::new(NoopMetrics)) }
+    }
+
+    /// With the backend METRICS_BACKEND asks for
+    pub fn from_config(config: &crate::config::MetricsConfig) -> Result<Self> {
+        Ok(match config.backend {
+            Backend::None => Self::disabled(),
+            Backend::Stderr => Self::new(Arc::new(StderrMetrics::new(&config.prefix))),
+            Backend::Prometheus => {
+                let prometheus = Arc::new(PrometheusMetrics::new(&config.prefix));
+                Self { prometheus: Some(prometheus.clone()), ..Self::new(prometheus) }
+            }
+            Backend::Statsd => {
+                eprintln!("[INIT] StatsD metrics to {} (tags {})", config.statsd_addr, if config.statsd_tags { "on" } else { "off" });
+                Self::new(Arc::new(StatsdMetrics::new(&config.statsd_addr, &config.prefix, config.statsd_tags)?))
+            }
+        })
+    }
+
+    pub fn metrics(&self) -> Arc<dyn Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Export `stats` with a `feed` label (e.g. "main", "priority")
+    pub fn add_feed(&self, feed: &'static str, stats: Arc<PerfStats>) {
+        self.feeds.lock().unwrap_or_else(|e| e.into_inner()).push((feed, stats, PerfSnapshot::default()));
+    }
+
+    /// Export these connections (as given by WsManager::conn_stats)
+    pub fn add_connections(&self, connections: Vec<(String, usize, Arc<ConnStats>)>) {
+        let mut all = self.connections.lock().unwrap_or_else(|e| e.into_inner());
+        all.extend(connections.into_iter().map(|(tag, _, stats)| (tag, stats, (0, 0))));
+    }
+
+    /// One export round, then flush the backend
+    pub fn export(&self) {
+        let m = &*self.metrics;
+        let mut writes = 0;
+        for (feed, stats, previous) in self.feeds.lock().unwrap_or_else(|e| e.into_inner()).iter_mut() {
+            let snap = stats.snapshot();
+            export_feed(m, feed, &snap, previous);
+            writes += snap.total_messages;
+            *previous = snap;
+        }
+        for (tag, stats, previous) in self.connections.lock().unwrap_or_else(|e| e.into_inner()).iter_mut() {
+            let labels = [("conn", tag.as_str())];
+            let (frames, bytes) = stats.traffic();
+            m.counter("conn_frames", &labels, frames.saturating_sub(previous.0));
+            m.counter("conn_bytes", &labels, bytes.saturating_sub(previous.1));
+            *previous = (frames, bytes);
+            let current = stats.state();
+            for state in ConnState::ALL {
+                let name = format!("{:?}", state);
+                m.gauge("conn_state", &[("conn", tag.as_str()), ("state", &name)], (state == current) as u8 as f64);
+            }
+        }
+        export_process(m, writes, &mut self.process.lock().unwrap_or_else(|e| e.into_inner()));
+        m.flush();
+    }
+
+    /// Serve Prometheus scrapes on `addr` (for that backend), and export every
+    /// `interval` on the current runtime; nothing for the noop backend
+    pub async fn start(self: Arc<Self>, addr: &str, interval: Duration) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if let Some(prometheus) = &self.prometheus {
+            let listener = TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Failed to bind metrics endpoint {}", addr))?;
+            eprintln!("[INIT] Prometheus metrics on http://{}/metrics", addr);
+            let server = prometheus.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.serve(listener).await {
+                    eprintln!("[WARN] Metrics endpoint stopped: {:#}", e);
+                }
+            });
+        }
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.export();
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Counter deltas of the shm and price counters since `previous`; `writes` is
+/// the feeds' total of quotes written
+fn export_process(m: &dyn Metrics, writes: u64, previous: &mut [u64; 6]) {
+    let (shm, price) = (&crate::shm::COUNTERS, &crate::price::COUNTERS);
+    let counters = [
+        ("shm_writes", writes),
+        ("shm_status_changes", shm.status_changes.load(Ordering::Relaxed)),
+        ("shm_read_retries", shm.read_retries.load(Ordering::Relaxed)),
+        ("shm_busy_reads", shm.busy_reads.load(Ordering::Relaxed)),
+        ("price_parse_errors", price.parse_errors.load(Ordering::Relaxed)),
+        ("price_strict_rejects", price.strict_rejects.load(Ordering::Relaxed)),
+    ];
+    for ((name, now), before) in counters.into_iter().zip(previous.iter_mut()) {
+        m.counter(name, &[], now.saturating_sub(*before));
+        *before = now;
+    }
+}
+
+/// Counter deltas and gauges of one feed since `previous`
+fn export_feed(m: &dyn Metrics, feed: &str, snap: &PerfSnapshot, previous: &PerfSnapshot) {
+    let labels = [("feed", feed)];
+    let counters = [
+        ("messages", snap.total_messages, previous.total_messages),
+        ("frames_received", snap.frames_received, previous.frames_received),
+        ("frames_parsed", snap.frames_parsed, previous.frames_parsed),
+        ("bytes_received", snap.bytes_received, previous.bytes_received),
+        ("parse_errors", snap.parse_errors, previous.parse_errors),
+        ("schema_violations", snap.schema_violations, previous.schema_violations),
+        ("price_errors", snap.price_errors, previous.price_errors),
+        ("hook_dropped", snap.hook_dropped, previous.hook_dropped),
+        ("filtered", snap.filtered_messages, previous.filtered_messages),
+        ("deduped", snap.deduped, previous.deduped),
+        ("deadline_skipped", snap.deadline_skipped, previous.deadline_skipped),
+        ("pipeline_full", snap.pipeline_full, previous.pipeline_full),
+        ("arbitrated", snap.arbitrated, previous.arbitrated),
+        ("reconnects", snap.reconnects, previous.reconnects),
+        ("failovers", snap.failovers, previous.failovers),
+        ("panics", snap.panics, previous.panics),
+        ("warmup_failures", snap.warmup_failures, previous.warmup_failures),
+        ("subscribe_errors", snap.subscribe_errors, previous.subscribe_errors),
+    ];
+    for (name, now, before) in counters {
+        m.counter(name, &labels, now.saturating_sub(before));
+    }
+    let timeouts = [
+        ("connect", snap.connect_timeouts, previous.connect_timeouts),
+        ("handshake", snap.handshake_timeouts, previous.handshake_timeouts),
+        ("idle", snap.idle_timeouts, previous.idle_timeouts),
+    ];
+    for (kind, now, before) in timeouts {
+        m.counter("timeouts", &[("feed", feed), ("kind", kind)], now.saturating_sub(before));
+    }
+    for &(code, count) in &snap.close_codes {
+        let before = previous.close_codes.iter().find(|&&(c, _)| c == code).map_or(0, |&(_, n)| n);
+        m.counter("closes", &[("feed", feed), ("code", &code.to_string())], count.saturating_sub(before));
+    }
+
+    // Averages over the interval; the max since startup
+    let messages = snap.total_messages.saturating_sub(previous.total_messages);
+    let parsed = snap.parse_timed.saturating_sub(previous.parse_timed);
+    if messages > 0 {
+        m.gauge("proc_us_avg", &labels, snap.proc_us_total.saturating_sub(previous.proc_us_total) as f64 / messages as f64);
+    }
+    if parsed > 0 {
+        m.gauge("parse_us_avg", &labels, snap.parse_us_total.saturating_sub(previous.parse_us_total) as f64 / parsed as f64);
+    }
+    m.gauge("proc_us_max", &labels, snap.max_proc_us as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prometheus_exposition() {
+        assert_eq!("Prometheus".parse::<Backend>().unwrap(), Backend::Prometheus);
+        assert_eq!("".parse::<Backend>().unwrap(), Backend::None);
+        assert!("graphite".parse::<Backend>().is_err());
+
+        let prometheus = Arc::new(PrometheusMetrics::new("bw"));
+        let exporter = MetricsExporter::new(prometheus.clone());
+        let stats = Arc::new(PerfStats::new());
+        exporter.add_feed("main", stats.clone());
+        let conn = Arc::new(ConnStats::default());
+        exporter.add_connections(vec![("WS-0".to_string(), 3, conn.clone())]);
+
+        stats.record(40);
+        stats.record(60);
+        stats.parse_errors.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        // Status changes and price failures are counted process-wide
+        let shm = crate::shm::ShmManager::anonymous(1, 1).unwrap();
+        shm.init_slot(0, 0).unwrap();
+        shm.get_slot(0, 0).unwrap().write(1, 2, 3);
+        let strict = crate::price::PriceParseOptions { mode: crate::price::ParseMode::Strict, ..Default::default() };
+        assert!(crate::price::parse_price_with("+1.5", &strict).is_err());
+        stats.record_close(1006);
+        conn.record_bytes(100);
+        exporter.export();
+        // Deltas accumulate into totals
+        stats.record(20);
+        stats.record_close(1006);
+        exporter.export();
+        prometheus.timing("state_us", &[("conn", "WS-0"), ("state", "Connecting")], 1_500);
+        prometheus.timing("state_us", &[("conn", "WS-0"), ("state", "Connecting")], 500);
+        prometheus.gauge("weird", &[("why", "a \"quoted\"\nvalue")], 1.5);
+
+        let text = prometheus.render();
+        for line in [
+            "# TYPE bw_messages_total counter",
+            "bw_messages_total{feed=\"main\"} 3",
+            "bw_parse_errors_total{feed=\"main\"} 1",
+            "bw_closes_total{feed=\"main\",code=\"1006\"} 2",
+            "bw_proc_us_avg{feed=\"main\"} 20",
+            "bw_conn_bytes_total{conn=\"WS-0\"} 100",
+            "bw_conn_state{conn=\"WS-0\",state=\"Backoff\"} 1",
+            "bw_conn_state{conn=\"WS-0\",state=\"Streaming\"} 0",
+            "# TYPE bw_state_us summary",
+            "bw_state_us_sum{conn=\"WS-0\",state=\"Connecting\"} 2000",
+            "bw_state_us_count{conn=\"WS-0\",state=\"Connecting\"} 2",
+            "bw_weird{why=\"a \\\"quoted\\\"\\nvalue\"} 1.5",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {:?} in\n{}", line, text);
+        }
+        assert_eq!(text.matches("# TYPE bw_conn_state gauge").count(), 1);
+        // Other tests count into the same process-wide counters
+        assert!(text.lines().any(|l| l == "bw_shm_writes_total 3"), "{}", text);
+        for name in ["bw_shm_status_changes_total", "bw_price_parse_errors_total", "bw_price_strict_rejects_total"] {
+            let value = text.lines().find_map(|l| l.strip_prefix(name)?.strip_prefix(' ')).unwrap_or_else(|| panic!("missing {} in\n{}", name, text));
+            assert!(value.parse::<u64>().unwrap() >= 1, "{} {}", name, value);
+        }
+    }
+
+    #[test]
+    fn test_statsd_lines() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let addr = receiver.local_addr().unwrap().to_string();
+        let recv = || {
+            let mut buf = [0u8; 2048];
+            let n = receiver.recv(&mut buf).unwrap();
+            String::from_utf8(buf[..n].to_vec()).unwrap()
+        };
+
+        let statsd = StatsdMetrics::new(&addr, "bw", true).unwrap();
+        statsd.counter("disconnects", &[("conn", "WS-3"), ("kind", "reset")], 1);
+        statsd.counter("messages", &[], 0);
+        statsd.gauge("proc_us_avg", &[("feed", "main")], 12.5);
+        statsd.timing("state_us", &[("conn", "WS|3")], 1_500);
+        statsd.flush();
+        assert_eq!(recv(), "bw.disconnects:1|c|#conn:WS-3,kind:reset\nbw.proc_us_avg:12.5|g|#feed:main\nbw.state_us:1.5|ms|#conn:WS_3");
+
+        // Without tags the label values extend the name
+        let plain = StatsdMetrics::new(&addr, "bw", false).unwrap();
+        plain.counter("disconnects", &[("conn", "WS-3"), ("kind", "reset")], 2);
+        plain.flush();
+        assert_eq!(recv(), "bw.disconnects.WS-3.reset:2|c");
+
+        // Batches split at the datagram limit, nothing sent when empty
+        for i in 0..100 {
+            plain.counter("conn_bytes", &[("conn", &format!("WS-{}", i))], 1_000);
+        }
+        plain.flush();
+        plain.flush();
+        let (first, second) = (recv(), recv());
+        assert!(first.len() <= STATSD_PACKET_BYTES && second.len() <= STATSD_PACKET_BYTES);
+        assert_eq!(first.lines().count() + second.lines().count(), 100);
+        assert_eq!(plain.sent_dropped(), (3, 0));
+    }
+
+    #[tokio::test]
+    async fn test_scrape() {
+        let metrics = Arc::new(PrometheusMetrics::new("bw"));
+        metrics.counter("reconnects", &[], 2);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(metrics.serve(listener));
+
+        let scrape = |path: &'static str| async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(format!("GET {} HTTP/1.1\r\nHost: x\r\n\r\n", path).as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+        let response = scrape("/metrics").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.ends_with("bw_reconnects_total 2\n"), "{}", response);
+        assert!(scrape("/").await.starts_with("HTTP/1.1 404"));
+    }
+}
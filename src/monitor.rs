@@ -0,0 +1,175 @@
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+use crate::config::AnomalyConfig;
+use crate::fixed;
+
+/// Rolling mid-price rate-of-change monitor
+///
+/// Keeps the last mid and timestamp per symbol_id and flags any single update
+/// that moves the mid by more than the configured threshold within the window.
+/// Catches both genuine market events and bad data from the venue.
+pub struct AnomalyMonitor {
+    move_bps: u64,
+    window_us: i64,
+    last_mid: Vec<AtomicI64>,
+    last_ts: Vec<AtomicI64>,
+    alerts: AtomicU64,
+}
+
+impl AnomalyMonitor {
+    /// Create monitor with state for symbol_ids in 0..n_symbols
+    pub fn new(config: &AnomalyConfig, n_symbols: usize) -> Self {
+        Self {
+            move_bps: config.move_bps,
+            window_us: (config.window_ms as i64).saturating_mul(1_000),
+            last_mid: (0..n_symbols).map(|_| AtomicI64::new(0)).collect(),
+            last_ts: (0..n_symbols).map(|_| AtomicI64::new(0)).collect(),
+            alerts: AtomicU64::new(0),
+        }
+    }
+
+    /// Record an update; returns the move in basis points if it crosses the threshold
+    #[inline(always)]
+    pub fn observe(&self, symbol_id: u64, bid: i64, ask: i64, ts: i64) -> Option<u64> {
+        if self.move_bps == 0 || bid <= 0 || ask <= 0 {
+            return None;
+        }
+        let idx = symbol_id as usize;
+        if idx >= self.last_mid.len() {
+            return None;
+        }
+
+        let mid = fixed::mid(bid, ask);
+        let prev_mid = self.last_mid[idx].swap(mid, Ordering::Relaxed);
+        let prev_ts = self.last_ts[idx].swap(ts, Ordering::Relaxed);
+
+        if prev_mid <= 0 || ts - prev_ts > self.window_us {
+            return None;
+        }
+
+        // prev_mid > 0: None only for a move too large for i64 bps
+        let bps = fixed::bps_diff(prev_mid, mid).map_or(u64::MAX, i64::unsigned_abs);
+        if bps >= self.move_bps {
+            self.alerts.fetch_add(1, Ordering::Relaxed);
+            return Some(bps);
+        }
+        None
+    }
+
+    /// Total alerts raised
+    pub fn alerts(&self) -> u64 {
+        self.alerts.load(Ordering::Relaxed)
+    }
+
+    pub fn report(&self) {
+        eprintln!("[STATS] Rate-of-change alerts: {}", self.alerts());
+    }
+}
+
+/// Flags prices that aren't a multiple of the symbol's tick size
+///
+/// An off-tick price from the venue almost always means a parsing or
+/// normalization bug on our side, so these are counted rather than dropped.
+pub struct TickValidator {
+    /// Tick per symbol_id in that symbol's price scale (0 = not checked)
+    ticks: Vec<i64>,
+    violations: AtomicU64,
+}
+
+impl TickValidator {
+    /// `ticks` per symbol_id, already in each symbol's scale (see `tick_in_scale`)
+    pub fn new(ticks: Vec<i64>) -> Self {
+        Self { ticks, violations: AtomicU64::new(0) }
+    }
+
+    /// Tick size * 1e8 converted to a 10^decimals scale; 0 if the tick is
+    /// finer than the scale (prices are rounded, nothing to check)
+    pub fn tick_in_scale(tick_1e8: i64, decimals: u32) -> i64 {
+        let divisor = 10i64.pow(crate::price::PRICE_DECIMALS - decimals.min(crate::price::PRICE_DECIMALS));
+        if tick_1e8 <= 0 || tick_1e8 % divisor != 0 {
+            return 0;
+        }
+        tick_1e8 / divisor
+    }
+
+    /// Symbols with a known tick
+    pub fn checked_symbols(&self) -> usize {
+        self.ticks.iter().filter(|&&tick| tick > 0).count()
+    }
+
+    /// Check an update; false (and counted) if either side is off tick
+    #[inline(always)]
+    pub fn observe(&self, symbol_id: u64, bid: i64, ask: i64) -> bool {
+        let tick = self.ticks.get(symbol_id as usize).copied().unwrap_or(0);
+        if tick <= 0 || (bid % tick == 0 && ask % tick == 0) {
+            return true;
+        }
+        self.violations.fetch_add(1, Ordering::Relaxed);
+        false
+    }
+
+    /// Total off-tick updates
+    pub fn violations(&self) -> u64 {
+        self.violations.load(Ordering::Relaxed)
+    }
+
+    pub fn report(&self) {
+        eprintln!("[STATS] Off-tick prices: {} ({} symbols checked)", self.violations(), self.checked_symbols());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(move_bps: u64, window_ms: u64) -> AnomalyMonitor {
+        AnomalyMonitor::new(&AnomalyConfig { move_bps, window_ms }, 4)
+    }
+
+    #[test]
+    fn test_anomaly_alert_within_window() {
+        let m = monitor(500, 1000);
+        assert_eq!(m.observe(1, 100_00000000, 100_00000000, 0), None);
+        // +1% is below the 5% threshold
+        assert_eq!(m.observe(1, 101_00000000, 101_00000000, 1_000), None);
+        // 101 -> 110 is ~891 bps
+        assert_eq!(m.observe(1, 110_00000000, 110_00000000, 2_000), Some(891));
+        assert_eq!(m.alerts(), 1);
+    }
+
+    #[test]
+    fn test_anomaly_outside_window_ignored() {
+        let m = monitor(500, 1);
+        m.observe(2, 100_00000000, 100_00000000, 0);
+        // Big move but 2ms later with a 1ms window
+        assert_eq!(m.observe(2, 200_00000000, 200_00000000, 2_000), None);
+        assert_eq!(m.alerts(), 0);
+    }
+
+    #[test]
+    fn test_anomaly_disabled_and_invalid() {
+        let m = monitor(0, 1000);
+        m.observe(1, 100, 100, 0);
+        assert_eq!(m.observe(1, 1_000, 1_000, 1), None);
+
+        let m = monitor(500, 1000);
+        assert_eq!(m.observe(1, 0, 100, 0), None);
+        assert_eq!(m.observe(99, 100, 100, 0), None);
+    }
+
+    #[test]
+    fn test_tick_validation() {
+        // 0.10 tick at the default scale and at 1e4; a 0.5e-8 tick can't be checked at 1e4
+        assert_eq!(TickValidator::tick_in_scale(10_000_000, 8), 10_000_000);
+        assert_eq!(TickValidator::tick_in_scale(10_000_000, 4), 1_000);
+        assert_eq!(TickValidator::tick_in_scale(50, 4), 0);
+
+        let validator = TickValidator::new(vec![0, 10_000_000, 1_000]);
+        assert!(validator.observe(0, 123, 456));
+        assert!(validator.observe(1, 6_000_010_000_000, 6_000_020_000_000));
+        assert!(!validator.observe(1, 6_000_010_000_000, 6_000_025_000_000));
+        assert!(!validator.observe(2, 1_500, 2_001));
+        assert!(validator.observe(9, 1, 2));
+        assert_eq!((validator.violations(), validator.checked_symbols()), (2, 2));
+    }
+}
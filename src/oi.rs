@@ -0,0 +1,211 @@
+//! Open interest poller: /fapi/v1/openInterest for every subscribed symbol,
+//! written to a dedicated source row of the quotes SHM
+//!
+//! Slot fields for the OI source: `bid` = open interest * 1e8 (contracts),
+//! `ask` = exchange time of the value (ms since epoch), `ts` = when it was
+//! written (monotonic µs).
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::time::Duration;
+
+use crate::price::{self, PriceParseOptions};
+use crate::rest::RestClient;
+use crate::shm::{ShmManager, ShmRegistry};
+use crate::writer::WriterToken;
+
+/// Pause after a 429/418 without Retry-After
+const RATE_LIMIT_PAUSE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize)]
+struct OpenInterest {
+    #[serde(rename = "openInterest")]
+    open_interest: String,
+    time: i64,
+}
+
+/// Parse an openInterest response: (open interest * 1e8, time ms)
+pub fn parse_open_interest(body: &str, opts: &PriceParseOptions) -> Result<(i64, i64)> {
+    let oi: OpenInterest = serde_json::from_str(body).context("Invalid openInterest response")?;
+    Ok((price::parse_price_with(&oi.open_interest, opts)?, oi.time))
+}
+
+pub struct OiPoller {
+    client: RestClient,
+    writer: WriterToken,
+    /// (symbol, symbol_id)
+    symbols: Vec<(String, u64)>,
+    opts: PriceParseOptions,
+}
+
+impl OiPoller {
+    /// Poller for `symbols`, initializing their slots in the `source_id` row
+    pub fn new(client: RestClient, shm: Arc<ShmManager>, source_id: u64, mut symbols: Vec<(String, u64)>, opts: PriceParseOptions) -> Result<Self> {
+        symbols.sort();
+        for (symbol, symbol_id) in &symbols {
+            shm.init_slot(source_id, *symbol_id)
+                .with_context(|| format!("Failed to init OI slot for {}", symbol))?;
+        }
+        let ids: Vec<u64> = symbols.iter().map(|&(_, id)| id).collect();
+        let writer = WriterToken::claim(shm, "open-interest", source_id, &ids)?;
+        Ok(Self { client, writer, symbols, opts })
+    }
+
+    /// Poll every symbol once; returns how many slots were updated
+    ///
+    /// Stops early on a rate-limit response, returning the pause it asked for.
+    pub async fn poll_round(&mut self) -> (usize, Option<Duration>) {
+        let mut updated = 0;
+        for (symbol, symbol_id) in &self.symbols {
+            let path = format!("/fapi/v1/openInterest?symbol={}", symbol);
+            let response = match self.client.get(&path).await {
+                Ok(response) => response,
+                Err(e) => {
+                    crate::eprintln_limited!("[OI] {} request failed: {:#}", symbol, e);
+                    continue;
+                }
+            };
+            if response.status == 429 || response.status == 418 {
+                let pause = response.retry_after_secs.map_or(RATE_LIMIT_PAUSE, Duration::from_secs);
+                eprintln!("[OI] Rate limited (HTTP {}), pausing {:?}", response.status, pause);
+                return (updated, Some(pause));
+            }
+            let result = if response.status == 200 {
+                parse_open_interest(&response.body, &self.opts)
+            } else {
+                Err(anyhow::anyhow!("HTTP {}: {}", response.status, response.body.trim()))
+            };
+            match result.and_then(|(oi, time_ms)| self.write(*symbol_id, oi, time_ms)) {
+                Ok(()) => updated += 1,
+                Err(e) => crate::eprintln_limited!("[OI] {}: {:#}", symbol, e),
+            }
+        }
+        (updated, None)
+    }
+
+    fn write(&self, symbol_id: u64, oi: i64, time_ms: i64) -> Result<()> {
+        let slot = self.writer.slot(symbol_id)?;
+        slot.write(oi, time_ms, crate::platform::monotonic_us());
+        Ok(())
+    }
+
+    /// Poll forever, starting a round every `interval`
+    pub async fn run(mut self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if let (_, Some(pause)) = self.poll_round().await {
+                tokio::time::sleep(pause).await;
+            }
+        }
+    }
+}
+
+/// Check the OI source is usable in the file it's routed to; returns that mapping
+pub fn check_source(shm: &ShmRegistry, source_id: u64, quotes_source_id: u64) -> Result<&Arc<ShmManager>> {
+    if source_id == quotes_source_id {
+        bail!("OI_SOURCE_ID {} is the quotes source", source_id);
+    }
+    let mapping = shm.get(source_id)?;
+    // Also bounds-checks the source against the file
+    mapping.source_state(source_id).with_context(|| format!("OI_SOURCE_ID {} not usable", source_id))?;
+    Ok(mapping)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    #[test]
+    fn test_parse_open_interest() {
+        let body = r#"{"openInterest":"10659.509","symbol":"BTCUSDT","time":1589437530011}"#;
+        assert_eq!(parse_open_interest(body, &PriceParseOptions::default()).unwrap(),
+                   (1_065_950_900_000, 1_589_437_530_011));
+        assert!(parse_open_interest(r#"{"code":-1121}"#, &PriceParseOptions::default()).is_err());
+    }
+
+    /// SHM file in the temp dir, mapped as `source_ids` in `registry`
+    fn routed_shm(registry: &mut ShmRegistry, name: &str, source_ids: &[u64]) -> String {
+        let path = crate::shm::create_test_shm(&format!("oi_{}", name), 3, 4);
+        let path = path.to_str().unwrap().to_string();
+        for &source_id in source_ids {
+            registry.register(source_id, &path).unwrap();
+        }
+        path
+    }
+
+    /// REST stub: BTCUSDT has open interest 1.5 at time 1000, other symbols are invalid
+    async fn serve_open_interest() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (sock, _) = listener.accept().await.unwrap();
+            let mut sock = BufReader::new(sock);
+            loop {
+                let mut request = String::new();
+                if sock.read_line(&mut request).await.unwrap() == 0 {
+                    return;
+                }
+                let mut line = String::new();
+                while line != "\r\n" {
+                    line.clear();
+                    sock.read_line(&mut line).await.unwrap();
+                }
+                let body = if request.contains("symbol=BTCUSDT") {
+                    r#"{"openInterest":"1.5","symbol":"BTCUSDT","time":1000}"#
+                } else {
+                    r#"{"code":-1121,"msg":"Invalid symbol."}"#
+                };
+                let status = if request.contains("symbol=BTCUSDT") { "200 OK" } else { "400 Bad Request" };
+                let response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n\r\n{}", status, body.len(), body);
+                sock.get_mut().write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_poll_round_writes_slots() {
+        let mut registry = ShmRegistry::new();
+        let path = routed_shm(&mut registry, "slots", &[1, 2]);
+        check_source(&registry, 2, 1).unwrap();
+        assert!(check_source(&registry, 1, 1).is_err());
+        assert!(check_source(&registry, 0, 1).is_err());
+
+        let client = RestClient::new(&serve_open_interest().await, Duration::from_secs(5)).unwrap();
+        let symbols = vec![("BTCUSDT".to_string(), 1), ("NOPEUSDT".to_string(), 2)];
+        let shm = check_source(&registry, 2, 1).unwrap().clone();
+        let mut poller = OiPoller::new(client, shm.clone(), 2, symbols, PriceParseOptions::default()).unwrap();
+        assert_eq!(poller.poll_round().await, (1, None));
+
+        let (_, symbol_id, oi, time_ms, _) = shm.get_slot(2, 1).unwrap().read().unwrap();
+        assert_eq!((symbol_id, oi, time_ms), (1, 150_000_000, 1000));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_routed_source_writes_its_file() {
+        // Quotes on source 1 in one file, OI on source 2 routed to another
+        let mut registry = ShmRegistry::new();
+        let quotes_path = routed_shm(&mut registry, "quotes", &[1]);
+        let oi_path = routed_shm(&mut registry, "routed", &[2]);
+
+        let client = RestClient::new(&serve_open_interest().await, Duration::from_secs(5)).unwrap();
+        let shm = check_source(&registry, 2, 1).unwrap().clone();
+        let mut poller = OiPoller::new(client, shm, 2, vec![("BTCUSDT".to_string(), 1)], PriceParseOptions::default()).unwrap();
+        assert_eq!(poller.poll_round().await, (1, None));
+
+        let oi_file = ShmManager::open(&oi_path).unwrap();
+        let (_, symbol_id, oi, _, _) = oi_file.get_slot(2, 1).unwrap().read().unwrap();
+        assert_eq!((symbol_id, oi), (1, 150_000_000));
+        // Nothing landed in the same row of the quotes file
+        let quotes_file = ShmManager::open(&quotes_path).unwrap();
+        assert_eq!(quotes_file.get_slot(2, 1).unwrap().read(), Some((0, 0, 0, 0, 0)));
+
+        std::fs::remove_file(quotes_path).unwrap();
+        std::fs::remove_file(oi_path).unwrap();
+    }
+}
@@ -0,0 +1,168 @@
+//! Duplicate writer detection via slot ownership stamps
+//!
+//! Each writer process stamps the slots it writes with a token (pid in the
+//! high 32 bits, process start time in the low 32). At startup a stamp left
+//! by another live process means two writers share the source; a live pid
+//! with a different start time is a reused pid, and its stamp is stale. While
+//! running, a stamp that changes under us means a second writer started on
+//! our slots.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+use crate::platform;
+use crate::shm::ShmManager;
+
+/// Ownership token of this process
+pub fn writer_token() -> u64 {
+    let pid = std::process::id();
+    // Without a process start time the token's own time stands in for it
+    let start = platform::process_start_time(pid).unwrap_or_else(crate::persist::unix_now_us);
+    ((pid as u64) << 32) | (start & 0xFFFF_FFFF)
+}
+
+/// Process id a token was made by
+pub fn token_pid(token: u64) -> u32 {
+    (token >> 32) as u32
+}
+
+/// Whether the process that made `token` is still running (None if unknown
+/// on this platform): its pid is alive and, where the platform reports
+/// start times, started when the token says
+pub fn token_alive(token: u64) -> Option<bool> {
+    let pid = token_pid(token);
+    match platform::process_alive(pid)? {
+        false => Some(false),
+        true => Some(platform::process_start_time(pid).is_none_or(|start| start & 0xFFFF_FFFF == token & 0xFFFF_FFFF)),
+    }
+}
+
+/// Slots this writer claimed, checked for takeovers
+pub struct SlotOwnership {
+    shm: Arc<ShmManager>,
+    source_id: u64,
+    symbol_ids: Vec<u64>,
+    token: u64,
+    /// Slots found stamped by another writer at the last check
+    lost: AtomicUsize,
+}
+
+impl SlotOwnership {
+    /// Stamp `symbol_ids` with `token`; fails without stamping any of them if
+    /// one is stamped by another live process (a writer already running)
+    pub fn claim(shm: Arc<ShmManager>, source_id: u64, symbol_ids: Vec<u64>, token: u64) -> Result<Self> {
+        let mut others = Vec::new();
+        let mut previous = Vec::with_capacity(symbol_ids.len());
+        for &symbol_id in &symbol_ids {
+            let stamp = shm.get_slot(source_id, symbol_id)?.owner();
+            let pid = token_pid(stamp);
+            if stamp != 0 && stamp != token && pid != token_pid(token)
+                && token_alive(stamp) == Some(true) && !others.contains(&pid)
+            {
+                others.push(pid);
+            }
+            previous.push(stamp);
+        }
+        if !others.is_empty() {
+            bail!("source {} slots are owned by running process(es) {:?} - another writer is active", source_id, others);
+        }
+        // Stamp only over what was checked: a stamp that changed in between
+        // is a writer starting alongside us, and ours are undone
+        for (i, (&symbol_id, &stamp)) in symbol_ids.iter().zip(&previous).enumerate() {
+            if let Err(found) = shm.claim_slot(source_id, symbol_id, stamp, token)? {
+                for (&id, &stamp) in symbol_ids[..i].iter().zip(&previous) {
+                    shm.claim_slot(source_id, id, token, stamp)?.ok();
+                }
+                bail!("source {} slot {} was claimed by process {} meanwhile - another writer is starting",
+                      source_id, symbol_id, token_pid(found));
+            }
+        }
+        Ok(Self { shm, source_id, symbol_ids, token, lost: AtomicUsize::new(0) })
+    }
+
+    /// Slots now stamped by another writer: (symbol_id, their token)
+    pub fn taken_over(&self) -> Vec<(u64, u64)> {
+        self.symbol_ids
+            .iter()
+            .filter_map(|&id| {
+                let owner = self.shm.get_slot(self.source_id, id).ok()?.owner();
+                (owner != self.token).then_some((id, owner))
+            })
+            .collect()
+    }
+
+    /// Log when the set of taken-over slots changes
+    pub fn check(&self, symbol_names: &HashMap<u64, String>) {
+        let taken = self.taken_over();
+        if self.lost.swap(taken.len(), Ordering::Relaxed) == taken.len() {
+            return;
+        }
+        if taken.is_empty() {
+            eprintln!("[DUP] Source {}: all slots owned by this writer again", self.source_id);
+            return;
+        }
+        let mut pids: Vec<u32> = taken.iter().map(|&(_, token)| token_pid(token)).collect();
+        pids.sort_unstable();
+        pids.dedup();
+        let examples: Vec<&str> = taken.iter().take(5).filter_map(|(id, _)| symbol_names.get(id).map(String::as_str)).collect();
+        eprintln!("[DUP] ERROR: source {}: {} slots claimed by another writer (pid {:?}), e.g. {} - two processes are writing this source",
+                  self.source_id, taken.len(), pids, examples.join(", "));
+    }
+
+    /// Check every `interval`
+    pub fn spawn(self: Arc<Self>, interval: Duration, symbol_names: HashMap<u64, String>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.check(&symbol_names);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_takeover_detection() {
+        let shm = Arc::new(ShmManager::anonymous(2, 4).unwrap());
+
+        // Fresh file: no previous stamps
+        let first = writer_token();
+        let ownership = SlotOwnership::claim(shm.clone(), 1, vec![1, 2], first).unwrap();
+        assert!(ownership.taken_over().is_empty());
+
+        // A second writer (pid 1 is always alive) takes a slot over
+        let second = (1u64 << 32) | (platform::process_start_time(1).unwrap_or(7) & 0xFFFF_FFFF);
+        assert_eq!(shm.claim_slot(1, 2, 0, second).unwrap(), Err(first), "stamps only over the expected token");
+        shm.claim_slot(1, 2, first, second).unwrap().unwrap();
+        assert_eq!(ownership.taken_over(), vec![(2, second)]);
+        #[cfg(unix)]
+        {
+            // A live owner fails the claim, leaving every stamp as it was
+            let err = SlotOwnership::claim(shm.clone(), 1, vec![1, 2], writer_token()).err().unwrap();
+            assert!(err.to_string().contains("[1]"), "{}", err);
+            assert_eq!(shm.get_slot(1, 1).unwrap().owner(), first);
+            assert_eq!(shm.get_slot(1, 2).unwrap().owner(), second);
+        }
+        #[cfg(target_os = "linux")]
+        {
+            // pid 1 started at another time: a reused pid, its stamp is stale
+            let reused = second ^ 1;
+            assert_eq!(token_alive(reused), Some(false));
+            shm.claim_slot(1, 2, second, reused).unwrap().unwrap();
+            let third = SlotOwnership::claim(shm.clone(), 1, vec![1, 2], writer_token()).unwrap();
+            assert!(third.taken_over().is_empty());
+        }
+
+        let init = shm.get_slot(1, 2).unwrap().owner();
+        shm.init_slot(1, 2).unwrap();
+        assert_eq!(shm.get_slot(1, 2).unwrap().owner(), init, "init_slot keeps the stamp");
+    }
+}
@@ -0,0 +1,157 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::ws::{PerfSnapshot, PerfStats};
+
+/// Stats as persisted to the state file
+///
+/// Written periodically so that after an OOM kill or panic the last known
+/// counters survive for post-mortem analysis.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersistedStats {
+    pub pid: u32,
+    pub started_at_unix: u64,
+    pub updated_at_unix: u64,
+    /// Panic message if the writer died from a panic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_panic: Option<String>,
+    #[serde(flatten)]
+    pub perf: PerfSnapshot,
+}
+
+/// Current wall clock time in unix seconds
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Current unix time in microseconds
+pub fn unix_now_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0)
+}
+
+/// Load a previously persisted stats file (None if it doesn't exist)
+pub fn load(path: &str) -> Result<Option<PersistedStats>> {
+    if !Path::new(path).exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read stats file: {}", path))?;
+    let stats = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse stats file: {}", path))?;
+    Ok(Some(stats))
+}
+
+/// Atomically write a JSON file (write to temp file, then rename over the target)
+pub fn write_json_atomic<T: Serialize>(path: &str, value: &T) -> Result<()> {
+    let tmp = format!("{}.tmp", path);
+    let json = serde_json::to_string(value).context("Failed to serialize JSON")?;
+    fs::write(&tmp, json).with_context(|| format!("Failed to write {}", tmp))?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to rename {} -> {}", tmp, path))?;
+    Ok(())
+}
+
+/// Atomically write stats
+pub fn save(path: &str, stats: &PersistedStats) -> Result<()> {
+    write_json_atomic(path, stats)
+}
+
+/// Periodically persists PerfStats to a state file
+pub struct StatsPersister {
+    path: String,
+    started_at_unix: u64,
+    stats: Arc<PerfStats>,
+}
+
+impl StatsPersister {
+    pub fn new(path: String, stats: Arc<PerfStats>) -> Self {
+        Self {
+            path,
+            started_at_unix: unix_now(),
+            stats,
+        }
+    }
+
+    /// Write the current counters
+    pub fn flush(&self) -> Result<()> {
+        self.write(None)
+    }
+
+    /// Write the current counters together with the panic message
+    pub fn flush_panic(&self, message: &str) -> Result<()> {
+        self.write(Some(message.to_string()))
+    }
+
+    fn write(&self, last_panic: Option<String>) -> Result<()> {
+        let persisted = PersistedStats {
+            pid: std::process::id(),
+            started_at_unix: self.started_at_unix,
+            updated_at_unix: unix_now(),
+            last_panic,
+            perf: self.stats.snapshot(),
+        };
+        save(&self.path, &persisted)
+    }
+
+    /// Spawn the periodic flush task
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.flush() {
+                    eprintln!("[WARN] Failed to persist stats: {:#}", e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_load_roundtrip() {
+        let path = std::env::temp_dir().join(format!("persist_test_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let stats = Arc::new(PerfStats::new());
+        stats.record(120);
+        stats.record(7000);
+        stats.record_reconnect();
+
+        let persister = StatsPersister::new(path.to_string(), stats);
+        persister.flush().unwrap();
+
+        let loaded = load(path).unwrap().unwrap();
+        assert_eq!(loaded.pid, std::process::id());
+        assert_eq!(loaded.perf.total_messages, 2);
+        assert_eq!(loaded.perf.max_proc_us, 7000);
+        assert_eq!(loaded.perf.slow_buckets, vec![(1_000, 1), (5_000, 1), (20_000, 0)]);
+        assert_eq!(loaded.perf.reconnects, 1);
+        assert_eq!(loaded.last_panic, None);
+
+        persister.flush_panic("boom").unwrap();
+        assert_eq!(load(path).unwrap().unwrap().last_panic.as_deref(), Some("boom"));
+
+        // Files from before the slow buckets still load
+        let old = r#"{"pid":1,"started_at_unix":0,"updated_at_unix":0,"total_messages":5,"max_proc_us":9,"over_5000us_count":1,"reconnects":0}"#;
+        fs::write(path, old).unwrap();
+        let old = load(path).unwrap().unwrap();
+        assert_eq!((old.perf.total_messages, old.perf.slow_buckets.len()), (5, 0));
+
+        fs::remove_file(path).unwrap();
+        assert!(load(path).unwrap().is_none());
+    }
+}
@@ -0,0 +1,276 @@
+//! Decode pipeline: WebSocket I/O and message processing on separate cores
+//!
+//! The read loops stay on the event loop thread (TLS + WebSocket decode) and
+//! push raw text frames into one SPSC ring per connection. A dedicated decoder
+//! thread drains the rings round-robin and does JSON parse (with the
+//! manager's hooks), price conversion and the SHM write through the usual
+//! message handler.
+//!
+//! Frames carry their receive time (monotonic µs) through the ring. With a
+//! deadline set, a frame that waited longer than it is skipped when a newer
+//! update for the same symbol is pending in the same drain batch: under a
+//! backlog the decoder catches up on latest values instead of replaying
+//! every stale one.
+
+use anyhow::{Context, Result};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::{JoinHandle, Thread};
+use std::time::Duration;
+
+use crate::platform;
+use crate::spsc;
+use crate::hooks::Hooks;
+use crate::ws::{BookTickerData, MessageHandler, PerfStats, WsManager};
+
+/// Frames taken from one ring before moving to the next (fairness)
+const DRAIN_BATCH: usize = 64;
+/// Empty passes spent spinning before the decoder parks
+const IDLE_SPINS: u32 = 2_000;
+/// Upper bound on a park, in case a wakeup is missed
+const PARK_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// Consumer side of the pipeline, run with `spawn`
+pub struct Decoder {
+    /// (frame, receive time in monotonic µs) per connection
+    rings: Vec<spsc::Consumer<(String, i64)>>,
+    handler: MessageHandler,
+    hooks: Arc<Hooks>,
+    strict_schema: bool,
+    stats: Arc<PerfStats>,
+    /// Decoder thread, for producers to unpark (set by spawn)
+    thread: Arc<OnceLock<Thread>>,
+    /// Queueing time after which a superseded frame is skipped (0 = never)
+    deadline_us: i64,
+    /// Decoded frames of the batch being drained, and whether each is skipped
+    /// (kept for its allocation; only filled with a deadline set)
+    batch: Vec<(BookTickerData, bool)>,
+}
+
+/// Route `manager`'s frames through rings of `capacity` frames each to a Decoder
+/// calling the manager's handler
+///
+/// A full ring drops the frame rather than stall the event loop thread its read
+/// loop shares with the other connections; each drop is counted in
+/// `stats.pipeline_full`. Fails if the manager's connections are already shared.
+pub fn split(manager: WsManager, capacity: usize, stats: Arc<PerfStats>) -> Result<(WsManager, Decoder)> {
+    let (handler, hooks, strict_schema) = (manager.handler(), manager.hooks(), manager.strict_schema());
+    let thread: Arc<OnceLock<Thread>> = Arc::new(OnceLock::new());
+    let mut rings = Vec::new();
+
+    let manager = manager.with_raw_handlers(|_| {
+        let (producer, consumer) = spsc::channel(capacity);
+        rings.push(consumer);
+
+        // Only this connection's read loop pushes; the mutex is never contended
+        let producer = Mutex::new(producer);
+        let (thread, stats) = (thread.clone(), stats.clone());
+        Box::new(move |text: String| {
+            stats.record_received();
+            let mut producer = producer.lock().unwrap_or_else(|e| e.into_inner());
+            if producer.push((text, platform::monotonic_us())).is_err() {
+                stats.record_pipeline_full();
+            }
+            if let Some(thread) = thread.get() {
+                thread.unpark();
+            }
+        })
+
+    })?;
+
+    let batch = Vec::with_capacity(DRAIN_BATCH);
+    Ok((manager, Decoder { rings, handler, hooks, strict_schema, stats, thread, deadline_us: 0, batch }))
+}
+
+impl Decoder {
+    /// Skip frames queued longer than `deadline` when a newer update for the
+    /// same symbol is pending (zero = never skip)
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline_us = deadline.as_micros() as i64;
+        self
+    }
+
+    /// Process up to DRAIN_BATCH frames from each ring; returns frames handled
+    pub fn drain(&mut self) -> usize {
+        self.drain_at(platform::monotonic_us)
+    }
+
+    /// `drain` with the deadline checked against `now` (monotonic µs)
+    fn drain_at(&mut self, now: impl Fn() -> i64) -> usize {
+        let mut handled = 0;
+        for ring in &mut self.rings {
+            for _ in 0..DRAIN_BATCH {
+                let Some((text, received_us)) = ring.pop() else {
+                    break;
+                };
+                match self.hooks.decode(&text, self.strict_schema) {
+                    Some(Ok(mut data)) => {
+                        self.stats.record_parsed();
+                        data.received_us = received_us;
+                        // Without a deadline nothing is skipped: handle it right away
+                        if self.deadline_us > 0 {
+                            self.batch.push((data, false));
+                        } else {
+                            (self.handler)(data);
+                        }
+                    }
+                    Some(Err(e)) => {
+                        self.stats.record_frame_error(&e);
+                        crate::eprintln_limited!("[PIPE] Failed to parse message: {}", e);
+                    }
+                    None => self.stats.record_hook_dropped(),
+                }
+                handled += 1;
+            }
+
+            // Past the deadline with a newer update for the symbol behind it: skip
+            if !self.batch.is_empty() {
+                let now = now();
+                for i in 0..self.batch.len() {
+                    let data = &self.batch[i].0;
+                    self.batch[i].1 = now - data.received_us > self.deadline_us
+                        && self.batch[i + 1..].iter().any(|(newer, _)| newer.symbol == data.symbol);
+                }
+            }
+            for (data, skip) in self.batch.drain(..) {
+                if skip {
+                    self.stats.record_deadline_skipped();
+                } else {
+                    (self.handler)(data);
+                }
+            }
+        }
+        handled
+    }
+
+    /// Run the decoder on its own thread, pinned to `cpu_core` if given
+    pub fn spawn(mut self, cpu_core: Option<usize>) -> Result<JoinHandle<()>> {
+        std::thread::Builder::new()
+            .name("decoder".to_string())
+            .spawn(move || {
+                if let Some(cpu) = cpu_core {
+                    if let Err(e) = platform::set_cpu_affinity(cpu) {
+                        eprintln!("[WARN] Failed to pin decoder: {}", e);
+                    }
+                }
+                let _ = self.thread.set(std::thread::current());
+
+                let mut idle = 0u32;
+                loop {
+                    if self.drain() > 0 {
+                        idle = 0;
+                    } else if idle < IDLE_SPINS {
+                        idle += 1;
+                        std::hint::spin_loop();
+                    } else {
+                        std::thread::park_timeout(PARK_TIMEOUT);
+                    }
+                }
+            })
+            .context("Failed to spawn decoder thread")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WsConfig;
+    use crate::ws::BookTickerData;
+
+    #[test]
+    fn test_frames_reach_handler_in_order_through_hooks() {
+        let seen: Arc<Mutex<Vec<String>>> = Arc::default();
+        let sink = seen.clone();
+        let handler: MessageHandler = Arc::new(move |data: BookTickerData| {
+            sink.lock().unwrap().push(data.bid_price);
+        });
+        let stats = Arc::new(PerfStats::new());
+        let hooks = Hooks::new().pre_parse(|text| !text.contains(r#""b":"2.0""#));
+        let manager = WsManager::new(vec!["BTCUSDT".to_string()], handler, WsConfig::default(), stats.clone())
+            .with_hooks(Arc::new(hooks));
+
+        let (manager, mut decoder) = split(manager, 4, stats.clone()).unwrap();
+        for bid in ["1.0", "2.0", "3.0"] {
+            manager.feed_raw(
+                0,
+                format!(r#"{{"stream":"btcusdt@bookTicker","data":{{"s":"BTCUSDT","b":"{}","a":"9.0"}}}}"#, bid),
+            );
+        }
+        manager.feed_raw(0, "not json".to_string());
+
+        assert_eq!(decoder.drain(), 4);
+        assert_eq!(*seen.lock().unwrap(), vec!["1.0", "3.0"]);
+        assert_eq!(stats.snapshot().hook_dropped, 1);
+        assert_eq!(decoder.drain(), 0);
+    }
+
+    #[test]
+    fn test_no_deadline_handles_each_frame_as_decoded() {
+        let log: Arc<Mutex<Vec<String>>> = Arc::default();
+        let (decoded, handled) = (log.clone(), log.clone());
+        let handler: MessageHandler = Arc::new(move |data: BookTickerData| {
+            handled.lock().unwrap().push(format!("handle {}", data.bid_price));
+        });
+        let stats = Arc::new(PerfStats::new());
+        let hooks = Hooks::new().pre_parse(move |_| {
+            decoded.lock().unwrap().push("decode".to_string());
+            true
+        });
+        let manager = WsManager::new(vec!["BTCUSDT".to_string()], handler, WsConfig::default(), stats.clone())
+            .with_hooks(Arc::new(hooks));
+
+        let (manager, mut decoder) = split(manager, 4, stats).unwrap();
+        for bid in ["1.0", "2.0"] {
+            manager.feed_raw(
+                0,
+                format!(r#"{{"stream":"btcusdt@bookTicker","data":{{"s":"BTCUSDT","b":"{}","a":"9.0"}}}}"#, bid),
+            );
+        }
+        assert_eq!(decoder.drain(), 2);
+        assert_eq!(*log.lock().unwrap(), vec!["decode", "handle 1.0", "decode", "handle 2.0"]);
+    }
+
+    #[test]
+    fn test_full_ring_drops_and_counts() {
+        let stats = Arc::new(PerfStats::new());
+        let manager = WsManager::new(vec!["BTCUSDT".to_string()], Arc::new(|_| {}), WsConfig::default(), stats.clone());
+        let (manager, mut decoder) = split(manager, 2, stats.clone()).unwrap();
+        for _ in 0..5 {
+            manager.feed_raw(0, "{}".to_string());
+        }
+        assert_eq!(stats.snapshot().pipeline_full, 3);
+        assert_eq!(decoder.drain(), 2);
+    }
+
+    #[test]
+    fn test_deadline_skips_superseded_frames() {
+        let seen: Arc<Mutex<Vec<String>>> = Arc::default();
+        let sink = seen.clone();
+        let handler: MessageHandler = Arc::new(move |data: BookTickerData| {
+            assert!(data.received_us > 0);
+            sink.lock().unwrap().push(format!("{} {}", data.symbol, data.bid_price));
+        });
+        let stats = Arc::new(PerfStats::new());
+        let symbols = vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()];
+        let manager = WsManager::new(symbols, handler, WsConfig::default(), stats.clone());
+        let (manager, decoder) = split(manager, 8, stats.clone()).unwrap();
+        let mut decoder = decoder.with_deadline(Duration::from_millis(1));
+        let feed = |symbol: &str, bid: &str| manager.feed_raw(
+            0,
+            format!(r#"{{"stream":"x@bookTicker","data":{{"s":"{}","b":"{}","a":"9.0"}}}}"#, symbol, bid),
+        );
+
+        // Within the deadline nothing is skipped
+        feed("BTCUSDT", "1.0");
+        feed("BTCUSDT", "2.0");
+        assert_eq!(decoder.drain(), 2);
+
+        // 10ms late: only the BTC update with a newer one behind it goes
+        for (symbol, bid) in [("BTCUSDT", "3.0"), ("ETHUSDT", "4.0"), ("BTCUSDT", "5.0")] {
+            feed(symbol, bid);
+        }
+        assert_eq!(decoder.drain_at(|| platform::monotonic_us() + 10_000), 3);
+        assert_eq!(*seen.lock().unwrap(), vec!["BTCUSDT 1.0", "BTCUSDT 2.0", "ETHUSDT 4.0", "BTCUSDT 5.0"]);
+        let snap = stats.snapshot();
+        assert_eq!((snap.deadline_skipped, snap.frames_parsed), (1, 5));
+    }
+}
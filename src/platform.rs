@@ -0,0 +1,321 @@
+//! Platform abstraction for OS-specific pieces (clock, CPU affinity, SHM location)
+//!
+//! Linux keeps the optimized paths (clock_gettime, sched_setaffinity, /dev/shm).
+//! Other platforms fall back to std equivalents so the crate builds and runs
+//! on macOS/Windows for development.
+
+use anyhow::Result;
+
+/// Default SHM file location for this platform
+pub fn default_shm_path() -> String {
+    #[cfg(target_os = "linux")]
+    {
+        "/dev/shm/quotes_v1.dat".to_string()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        std::env::temp_dir()
+            .join("quotes_v1.dat")
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Get monotonic timestamp in microseconds
+#[cfg(target_os = "linux")]
+#[inline(always)]
+pub fn monotonic_us() -> i64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
+    }
+    ts.tv_sec * 1_000_000 + ts.tv_nsec / 1_000
+}
+
+/// Get monotonic timestamp in microseconds
+///
+/// Fallback: microseconds since the first call in this process. Not comparable
+/// across processes, which is acceptable for development builds.
+#[cfg(not(target_os = "linux"))]
+#[inline(always)]
+pub fn monotonic_us() -> i64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed().as_micros() as i64
+}
+
+/// Kernel clock discipline state, as reported by adjtimex
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSync {
+    /// Disciplined by NTP/chrony (STA_UNSYNC clear, state not TIME_ERROR)
+    pub synchronized: bool,
+    /// Kernel's maximum error bound (µs)
+    pub max_error_us: i64,
+    /// Kernel's estimated error (µs)
+    pub est_error_us: i64,
+}
+
+/// System clock synchronization status (None where it can't be read)
+#[cfg(target_os = "linux")]
+pub fn clock_sync() -> Option<ClockSync> {
+    // modes = 0: read only, no privileges needed
+    let mut tx: libc::timex = unsafe { std::mem::zeroed() };
+    let state = unsafe { libc::adjtimex(&mut tx) };
+    if state < 0 {
+        return None;
+    }
+    Some(ClockSync {
+        synchronized: state != libc::TIME_ERROR && tx.status & libc::STA_UNSYNC == 0,
+        max_error_us: tx.maxerror as i64,
+        est_error_us: tx.esterror as i64,
+    })
+}
+
+/// System clock synchronization status (None where it can't be read)
+///
+/// Fallback: unknown; the OS keeps its own time service.
+#[cfg(not(target_os = "linux"))]
+pub fn clock_sync() -> Option<ClockSync> {
+    None
+}
+
+/// Set CPU affinity of the current thread to a single core
+pub fn set_cpu_affinity(cpu: usize) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use libc::{cpu_set_t, sched_setaffinity, CPU_SET, CPU_ZERO};
+        use std::mem;
+
+        unsafe {
+            let mut cpu_set: cpu_set_t = mem::zeroed();
+            CPU_ZERO(&mut cpu_set);
+            CPU_SET(cpu, &mut cpu_set);
+
+            let result = sched_setaffinity(
+                0, // current thread
+                mem::size_of::<cpu_set_t>(),
+                &cpu_set,
+            );
+
+            if result != 0 {
+                anyhow::bail!("Failed to set CPU affinity: {}", std::io::Error::last_os_error());
+            }
+        }
+
+        eprintln!("[CPU] Affinity set to core {}", cpu);
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        eprintln!("[CPU] CPU affinity not supported on this platform (core {} ignored)", cpu);
+        Ok(())
+    }
+}
+
+/// Cores the kernel keeps the scheduler off (isolcpus=), None if unknown
+pub fn isolated_cpus() -> Option<Vec<usize>> {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_to_string("/sys/devices/system/cpu/isolated")
+            .ok()
+            .map(|list| parse_cpu_list(&list))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Parse a kernel cpu list ("1,4-6")
+pub fn parse_cpu_list(list: &str) -> Vec<usize> {
+    list.trim()
+        .split(',')
+        .filter_map(|part| match part.split_once('-') {
+            Some((lo, hi)) => Some((lo.trim().parse().ok()?..=hi.trim().parse().ok()?).collect::<Vec<_>>()),
+            None => part.trim().parse().ok().map(|cpu| vec![cpu]),
+        })
+        .flatten()
+        .collect()
+}
+
+/// Free space available to unprivileged users on the filesystem holding `path`
+/// (None if unknown on this platform)
+pub fn fs_available_bytes(path: &str) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        let c_path = std::ffi::CString::new(path).ok()?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+        Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Whether process `pid` exists (None if unknown on this platform)
+pub fn process_alive(pid: u32) -> Option<bool> {
+    #[cfg(unix)]
+    {
+        // Signal 0 only checks; EPERM means it exists under another user
+        if unsafe { libc::kill(pid as libc::pid_t, 0) } == 0 {
+            return Some(true);
+        }
+        Some(std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+/// When process `pid` started, in clock ticks since boot (None if unknown on
+/// this platform or no such process); tells a reused pid from the original
+pub fn process_start_time(pid: u32) -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        // Field 22 of /proc/<pid>/stat; comm (field 2) may contain spaces, so
+        // count from the closing paren, which is followed by field 3
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        stat.get(stat.rfind(')')? + 1..)?.split_whitespace().nth(19)?.parse().ok()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+/// Bytes actually allocated for a file; less than its length for sparse files,
+/// whose missing pages on tmpfs are only allocated at first write
+pub fn allocated_bytes(metadata: &std::fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.blocks() * 512
+    }
+
+    #[cfg(not(unix))]
+    {
+        metadata.len()
+    }
+}
+
+/// Owner uid and permission bits of a file, and the effective uid of this process
+/// (None on platforms without unix permissions)
+pub fn ownership(metadata: &std::fs::Metadata) -> Option<(u32, u32, u32)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some((metadata.uid(), metadata.mode() & 0o7777, unsafe { libc::geteuid() }))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Whether this process may open `path` read-write (owner, group and mode aware)
+pub fn can_read_write(path: &str) -> bool {
+    #[cfg(unix)]
+    {
+        let Ok(c_path) = std::ffi::CString::new(path) else {
+            return false;
+        };
+        unsafe { libc::access(c_path.as_ptr(), libc::R_OK | libc::W_OK) == 0 }
+    }
+
+    #[cfg(not(unix))]
+    {
+        std::fs::metadata(path).is_ok_and(|m| !m.permissions().readonly())
+    }
+}
+
+/// Set a file's permission bits (no-op without unix permissions)
+pub fn set_file_mode(path: &str, mode: u32) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+            .map_err(|e| anyhow::anyhow!("Failed to chmod {:04o} {}: {}", mode, path, e))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (path, mode);
+        Ok(())
+    }
+}
+
+/// Change a file's group by name (no-op without unix permissions)
+pub fn set_file_group(path: &str, group: &str) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let c_group = std::ffi::CString::new(group)?;
+        let c_path = std::ffi::CString::new(path)?;
+        let entry = unsafe { libc::getgrnam(c_group.as_ptr()) };
+        if entry.is_null() {
+            anyhow::bail!("Unknown group: {}", group);
+        }
+        let gid = unsafe { (*entry).gr_gid };
+        if unsafe { libc::chown(c_path.as_ptr(), u32::MAX, gid) } != 0 {
+            anyhow::bail!("Failed to chgrp {} {}: {}", group, path, std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (path, group);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monotonic_us_non_decreasing() {
+        let a = monotonic_us();
+        let b = monotonic_us();
+        assert!(b >= a);
+    }
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(parse_cpu_list("1,4-6\n"), vec![1, 4, 5, 6]);
+        assert_eq!(parse_cpu_list("\n"), Vec::<usize>::new());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_process_start_time() {
+        let start = process_start_time(std::process::id()).unwrap();
+        assert_eq!(process_start_time(std::process::id()), Some(start));
+        assert_eq!(process_start_time(u32::MAX), None);
+    }
+
+    #[test]
+    fn test_default_shm_path() {
+        assert!(default_shm_path().ends_with("quotes_v1.dat"));
+    }
+}
@@ -0,0 +1,172 @@
+//! Startup checks for the SHM file and the system clock
+//!
+//! Catch the environment problems that otherwise show up as cryptic failures
+//! at first write (EACCES from open, SIGBUS from a full tmpfs) and report
+//! them together with what to do about it.
+//!
+//! An unsynchronized clock fails nothing, it just makes every latency figure
+//! downstream wrong; it is warned about (or refused with CLOCK_SYNC_REQUIRE)
+//! at startup and rechecked while running.
+
+use std::fs::OpenOptions;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use memmap2::MmapMut;
+
+use crate::config::ClockSyncConfig;
+use crate::platform::{self, ClockSync};
+
+/// Verify the SHM file exists, is accessible, fits in the filesystem and maps writable
+pub fn check_shm_file(path: &str) -> Result<()> {
+    if !Path::new(path).exists() {
+        bail!("SHM file {} does not exist: create it (with a valid header) before starting the writer", path);
+    }
+
+    let metadata = std::fs::metadata(path)
+        .with_context(|| format!("Cannot stat SHM file {}", path))?;
+    if !metadata.is_file() {
+        bail!("SHM path {} is not a regular file", path);
+    }
+
+    let mut problems = Vec::new();
+
+    let ownership = platform::ownership(&metadata);
+    if !platform::can_read_write(path) {
+        problems.push(match ownership {
+            Some((owner, mode, euid)) => format!(
+                "{} is owned by uid {} with mode {:04o} and is not read-write for uid {}: \
+                 chown it to the writer user or grant group write access",
+                path, owner, mode, euid
+            ),
+            None => format!("{} is read-only: clear the read-only attribute", path),
+        });
+    }
+    if let Some((_, mode, _)) = ownership {
+        if mode & 0o002 != 0 {
+            eprintln!("[WARN] SHM file {} is world-writable (mode {:04o})", path, mode);
+        }
+    }
+
+    // Pages of a sparse tmpfs file are allocated at first write; if they don't
+    // fit the writer dies with SIGBUS instead of an error
+    let missing = metadata.len().saturating_sub(platform::allocated_bytes(&metadata));
+    if missing > 0 {
+        if let Some(available) = platform::fs_available_bytes(path) {
+            if available < missing {
+                problems.push(format!(
+                    "{} needs {} more bytes to be fully allocated but only {} are free on its filesystem: \
+                     enlarge the tmpfs (mount -o remount,size=...) or free space",
+                    path, missing, available
+                ));
+            }
+        }
+    }
+
+    if !problems.is_empty() {
+        bail!("SHM preflight failed:\n  - {}", problems.join("\n  - "));
+    }
+
+    // Open read-write and touch the header to prove the mapping is writable
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Cannot open SHM file {} read-write", path))?;
+    let mut mmap = unsafe { MmapMut::map_mut(&file) }
+        .with_context(|| format!("Cannot mmap SHM file {} read-write", path))?;
+    if let Some(byte) = mmap.first_mut() {
+        // SAFETY: rewrite the existing value; the file has no writer yet
+        unsafe { std::ptr::write_volatile(byte, std::ptr::read_volatile(byte)) };
+    }
+
+    Ok(())
+}
+
+/// Why `status` doesn't count as synchronized, if it doesn't
+fn clock_sync_problem(status: ClockSync, max_error_ms: u64) -> Option<String> {
+    if !status.synchronized {
+        return Some("system clock is not synchronized (NTP/chrony not running or not locked)".to_string());
+    }
+    let max_error_ms_now = status.max_error_us / 1_000;
+    if max_error_ms > 0 && max_error_ms_now > max_error_ms as i64 {
+        return Some(format!("system clock error bound is {} ms (limit {} ms)", max_error_ms_now, max_error_ms));
+    }
+    None
+}
+
+/// Check the system clock is synchronized: fails with `require`, warns otherwise
+pub fn check_clock_sync(config: &ClockSyncConfig) -> Result<()> {
+    let Some(status) = platform::clock_sync() else {
+        eprintln!("[INIT] Clock sync status unavailable on this platform, not checked");
+        return Ok(());
+    };
+    match clock_sync_problem(status, config.max_error_ms) {
+        Some(problem) if config.require => {
+            bail!("{}: check `chronyc tracking` / `timedatectl`, or unset CLOCK_SYNC_REQUIRE", problem)
+        }
+        Some(problem) => eprintln!("[WARN] {}: latency figures will be off, check `chronyc tracking`", problem),
+        None => eprintln!("[INIT] System clock synchronized (max error {} ms, est. {} us)",
+                          status.max_error_us / 1_000, status.est_error_us),
+    }
+    Ok(())
+}
+
+/// Recheck clock sync every `interval`, logging when it is lost or regained
+pub fn spawn_clock_sync_check(config: &ClockSyncConfig, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+    let max_error_ms = config.max_error_ms;
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        let mut was_ok = platform::clock_sync().map(|s| clock_sync_problem(s, max_error_ms).is_none());
+        loop {
+            ticker.tick().await;
+            let Some(status) = platform::clock_sync() else { continue };
+            let problem = clock_sync_problem(status, max_error_ms);
+            match (&problem, was_ok) {
+                (Some(problem), Some(true) | None) => {
+                    eprintln!("[CLOCK] WARNING: {}: latency figures are suspect until it recovers", problem)
+                }
+                (None, Some(false)) => eprintln!("[CLOCK] System clock synchronized again (max error {} ms)",
+                                                 status.max_error_us / 1_000),
+                _ => {}
+            }
+            was_ok = Some(problem.is_none());
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preflight() {
+        let path = std::env::temp_dir().join(format!("preflight_test_{}.dat", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let err = check_shm_file(path).unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{}", err);
+
+        std::fs::write(path, vec![0u8; 8192]).unwrap();
+        check_shm_file(path).unwrap();
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_clock_sync_problem() {
+        let synced = ClockSync { synchronized: true, max_error_us: 40_000, est_error_us: 200 };
+        assert_eq!(clock_sync_problem(synced, 0), None);
+        assert_eq!(clock_sync_problem(synced, 100), None);
+        assert!(clock_sync_problem(synced, 10).unwrap().contains("40 ms"));
+        let unsynced = ClockSync { synchronized: false, ..synced };
+        assert!(clock_sync_problem(unsynced, 0).unwrap().contains("not synchronized"));
+
+        // Warn-only never fails; requiring only fails if the host really is unsynchronized
+        check_clock_sync(&ClockSyncConfig::default()).unwrap();
+        let host_ok = platform::clock_sync().is_none_or(|s| clock_sync_problem(s, 0).is_none());
+        let config = ClockSyncConfig { require: true, ..Default::default() };
+        assert_eq!(check_clock_sync(&config).is_ok(), host_ok);
+    }
+}
@@ -1,5 +1,179 @@
+use std::fmt;
+use std::str::FromStr;
+
 use anyhow::{bail, Result};
 
+use crate::fixed::arith;
+
+/// How digits beyond the 8th decimal are rounded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingMode {
+    /// >= 5 on the 9th decimal rounds up (spec default)
+    #[default]
+    HalfUp,
+    /// Exact halves round to the even neighbour (banker's rounding)
+    HalfEven,
+    /// Drop extra digits
+    Truncate,
+}
+
+impl FromStr for RoundingMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "half_up" | "halfup" => Ok(Self::HalfUp),
+            "half_even" | "halfeven" | "bankers" => Ok(Self::HalfEven),
+            "truncate" | "down" => Ok(Self::Truncate),
+            other => bail!("Unknown rounding mode: {}", other),
+        }
+    }
+}
+
+/// Decimals of the default 1e8 scale (also the most a scale override may keep)
+pub const PRICE_DECIMALS: u32 = 8;
+
+/// A scaled integer price as stored in SHM: `raw / 10^decimals`
+///
+/// Slots default to 1e8 but may carry a per-symbol scale, so the raw integer
+/// never travels without its scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Price {
+    raw: i64,
+    decimals: u32,
+}
+
+impl Price {
+    /// `raw` at scale 10^decimals (at most 1e8)
+    pub fn new(raw: i64, decimals: u32) -> Self {
+        debug_assert!(decimals <= PRICE_DECIMALS);
+        Self { raw, decimals }
+    }
+
+    /// `raw` at the default 1e8 scale
+    pub fn from_1e8(raw: i64) -> Self {
+        Self::new(raw, PRICE_DECIMALS)
+    }
+
+    /// The scaled integer
+    pub fn raw(self) -> i64 {
+        self.raw
+    }
+
+    pub fn decimals(self) -> u32 {
+        self.decimals
+    }
+
+    /// 10^decimals
+    pub fn scale(self) -> u64 {
+        10u64.pow(self.decimals)
+    }
+
+    /// The same price at 1e8 (exact; None on overflow)
+    pub fn to_1e8(self) -> Option<i64> {
+        self.raw.checked_mul(10i64.pow(PRICE_DECIMALS - self.decimals))
+    }
+
+    /// Nearest f64 (exact up to 2^53 raw units); for display and analytics only
+    pub fn to_f64(self) -> f64 {
+        self.raw as f64 / self.scale() as f64
+    }
+
+    /// Exact arbitrary-precision value
+    #[cfg(feature = "decimal")]
+    pub fn to_decimal(self) -> bigdecimal::BigDecimal {
+        bigdecimal::BigDecimal::new(self.raw.into(), self.decimals as i64)
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_price_scaled(f, self.raw, self.decimals)
+    }
+}
+
+/// Format a 1e8-scaled price as a canonical decimal string (the inverse of
+/// `parse_price_i64_1e8`): 10050000000 -> "100.5", 1234 -> "0.00001234"
+pub fn format_price_i64_1e8(value: i64) -> String {
+    format_price_scaled(value, PRICE_DECIMALS)
+}
+
+/// Format a price scaled by 10^decimals as a canonical decimal string
+pub fn format_price_scaled(value: i64, decimals: u32) -> String {
+    let mut out = String::with_capacity(24);
+    write_price_scaled(&mut out, value, decimals).expect("writing to a String");
+    out
+}
+
+/// Write a price scaled by 10^decimals (at most 1e8) without allocating
+///
+/// Integer arithmetic only, so the output is exact: no exponent, no trailing
+/// fractional zeros, no '.' for whole values, a '-' only below zero.
+pub fn write_price_scaled<W: fmt::Write>(out: &mut W, value: i64, decimals: u32) -> fmt::Result {
+    debug_assert!(decimals <= PRICE_DECIMALS);
+    let unit = 10u64.pow(decimals);
+    // unsigned_abs: i64::MIN has no positive i64
+    let (abs, sign) = (value.unsigned_abs(), if value < 0 { "-" } else { "" });
+    let (int, mut frac) = (abs / unit, abs % unit);
+    if frac == 0 {
+        return write!(out, "{}{}", sign, int);
+    }
+    let mut width = decimals as usize;
+    while frac % 10 == 0 {
+        frac /= 10;
+        width -= 1;
+    }
+    write!(out, "{}{}.{:0width$}", sign, int, frac, width = width)
+}
+
+/// Largest accepted exponent magnitude in scientific notation ("1.2e-7")
+const MAX_EXPONENT: i32 = 40;
+
+/// Which input formats are accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Surrounding whitespace, a leading '+', a bare leading/trailing '.'
+    /// and exponent notation are accepted
+    #[default]
+    Lenient,
+    /// Exactly the venue format: digits, optionally '.' followed by digits
+    Strict,
+}
+
+impl FromStr for ParseMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "lenient" => Ok(Self::Lenient),
+            "strict" => Ok(Self::Strict),
+            other => bail!("Unknown parse mode: {}", other),
+        }
+    }
+}
+
+/// Price parser options
+///
+/// Underscores and thousands separators are rejected in every mode.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PriceParseOptions {
+    pub rounding: RoundingMode,
+    pub mode: ParseMode,
+}
+
+/// Process-wide price parsing outcomes, exported by `metrics::MetricsExporter`
+pub struct PriceCounters {
+    /// Strings that failed to parse (any mode, rejects included)
+    pub parse_errors: std::sync::atomic::AtomicU64,
+    /// Strings strict mode rejected for their format (sign, spaces, exponent, ...)
+    pub strict_rejects: std::sync::atomic::AtomicU64,
+}
+
+pub static COUNTERS: PriceCounters = PriceCounters {
+    parse_errors: std::sync::atomic::AtomicU64::new(0),
+    strict_rejects: std::sync::atomic::AtomicU64::new(0),
+};
+
 /// Parse decimal price string to i64 with scale 1e8
 /// Uses decimal arithmetic to avoid float errors
 ///
@@ -11,12 +185,130 @@ use anyhow::{bail, Result};
 /// Round half-up: if the digit after the 8th decimal is >= 5, round up
 #[inline(always)]
 pub fn parse_price_i64_1e8(s: &str) -> Result<i64> {
+    parse_price_with(s, &PriceParseOptions::default())
+}
+
+/// Parse decimal price string to i64 with scale 1e8 using the given options
+///
+/// Rounding is applied to the scaled integer, so a round-up carries through
+/// every digit naturally: "1.999999995" -> 200000000 (2.0).
+///
+/// Scientific notation ("1.2e-7", "5E+3") is converted exactly by shifting
+/// the decimal point in the digit string - no float involved.
+#[inline(always)]
+pub fn parse_price_with(s: &str, opts: &PriceParseOptions) -> Result<i64> {
+    parse_price_scaled(s, PRICE_DECIMALS, opts)
+}
+
+/// Parse a price scale, "1e4" or "10000" (a power of ten up to 1e8), to decimals
+pub fn parse_scale(s: &str) -> Result<u32> {
     let s = s.trim();
+    let decimals = match s.strip_prefix("1e") {
+        Some(exp) => exp.parse::<u32>().ok(),
+        None => s.parse::<u64>().ok().and_then(|v| (0..=PRICE_DECIMALS).find(|&d| 10u64.pow(d) == v)),
+    };
+    match decimals {
+        Some(decimals) if decimals <= PRICE_DECIMALS => Ok(decimals),
+        _ => bail!("Scale must be a power of ten from 1 to 1e8: {:?}", s),
+    }
+}
+
+/// Parse decimal price string to i64 with scale 10^decimals (at most 1e8)
+///
+/// Rounds once, at the first dropped digit, so "1.23444999" at 4 decimals
+/// is 12344 and not a double-rounded 12345.
+#[inline(always)]
+pub fn parse_price_scaled(s: &str, decimals: u32, opts: &PriceParseOptions) -> Result<i64> {
+    let result = parse_scaled(s, decimals, opts);
+    if result.is_err() {
+        COUNTERS.parse_errors.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+    result
+}
+
+#[inline(always)]
+fn parse_scaled(s: &str, decimals: u32, opts: &PriceParseOptions) -> Result<i64> {
+    if decimals > PRICE_DECIMALS {
+        bail!("Price scale 1e{} exceeds 1e{}", decimals, PRICE_DECIMALS);
+    }
+    let s = match opts.mode {
+        ParseMode::Strict => {
+            if let Err(e) = validate_strict(s) {
+                COUNTERS.strict_rejects.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                return Err(e);
+            }
+            return parse_plain(s, decimals, opts);
+        }
+        ParseMode::Lenient => {
+            let s = s.trim();
+            s.strip_prefix('+').unwrap_or(s)
+        }
+    };
 
     if s.is_empty() {
         bail!("Empty price string");
     }
 
+    // Exponent notation is rare: normalize to a plain decimal string (allocates)
+    if let Some(pos) = s.find(['e', 'E']) {
+        let plain = expand_exponent(&s[..pos], &s[pos + 1..])?;
+        return parse_plain(&plain, decimals, opts);
+    }
+
+    parse_plain(s, decimals, opts)
+}
+
+/// Strict venue format: `digits[.digits]`, nothing else
+#[inline(always)]
+fn validate_strict(s: &str) -> Result<()> {
+    let bytes = s.as_bytes();
+    match (bytes.first(), bytes.last()) {
+        (Some(first), Some(last)) if first.is_ascii_digit() && last.is_ascii_digit() => {}
+        (None, _) => bail!("Empty price string"),
+        _ => bail!("Strict mode: price must start and end with a digit: {:?}", s),
+    }
+    if let Some(&ch) = bytes.iter().find(|&&b| !b.is_ascii_digit() && b != b'.') {
+        bail!("Strict mode: invalid character {:?} in {:?}", ch as char, s);
+    }
+    Ok(())
+}
+
+/// Rewrite mantissa * 10^exponent as a plain decimal string
+fn expand_exponent(mantissa: &str, exponent: &str) -> Result<String> {
+    let exp: i32 = exponent.parse()
+        .map_err(|_| anyhow::anyhow!("Invalid exponent: {:?}", exponent))?;
+    if exp.abs() > MAX_EXPONENT {
+        bail!("Exponent out of range: {}", exp);
+    }
+
+    let (int_part, frac_part) = match mantissa.split_once('.') {
+        Some((i, f)) => (i, f),
+        None => (mantissa, ""),
+    };
+    if int_part.is_empty() && frac_part.is_empty() {
+        bail!("Invalid price format: no digits in mantissa");
+    }
+    if frac_part.contains('.') {
+        bail!("Invalid price format: multiple decimal points");
+    }
+
+    let digits = format!("{}{}", int_part, frac_part);
+    let point = int_part.len() as i32 + exp;
+
+    let plain = if point <= 0 {
+        format!("0.{}{}", "0".repeat((-point) as usize), digits)
+    } else if point as usize >= digits.len() {
+        format!("{}{}", digits, "0".repeat(point as usize - digits.len()))
+    } else {
+        let (i, f) = digits.split_at(point as usize);
+        format!("{}.{}", i, f)
+    };
+    Ok(plain)
+}
+
+/// Parse a plain (non-exponent) decimal string
+#[inline(always)]
+fn parse_plain(s: &str, decimals: u32, opts: &PriceParseOptions) -> Result<i64> {
     // Find decimal point
     let parts: Vec<&str> = s.split('.').collect();
 
@@ -27,6 +319,10 @@ pub fn parse_price_i64_1e8(s: &str) -> Result<i64> {
     let integer_part = parts[0];
     let decimal_part = if parts.len() == 2 { parts[1] } else { "" };
 
+    if integer_part.is_empty() && decimal_part.is_empty() {
+        bail!("Invalid price format: no digits");
+    }
+
     // Parse integer part
     let mut result: i64 = 0;
 
@@ -36,22 +332,26 @@ pub fn parse_price_i64_1e8(s: &str) -> Result<i64> {
                 bail!("Invalid character in integer part: {}", ch as char);
             }
             let digit = (ch - b'0') as i64;
-            result = result.checked_mul(10)
+            result = arith::mul(result, 10)
                 .ok_or_else(|| anyhow::anyhow!("Integer overflow"))?;
-            result = result.checked_add(digit)
+            result = arith::add(result, digit)
                 .ok_or_else(|| anyhow::anyhow!("Integer overflow"))?;
         }
     }
 
-    // Scale integer part by 1e8
-    result = result.checked_mul(100_000_000)
+    // Scale integer part by 10^decimals (1e8 by default)
+    let unit = 10i64.pow(decimals);
+    result = arith::mul(result, unit)
         .ok_or_else(|| anyhow::anyhow!("Overflow scaling integer part"))?;
 
-    // Process decimal part (up to 8 digits + 1 for rounding)
+    // Process decimal part (up to `decimals` digits + 1 for rounding)
     if !decimal_part.is_empty() {
         let mut decimal_value: i64 = 0;
-        let mut scale: i64 = 10_000_000; // Start with 1e7 (for first decimal digit)
-        let mut round_digit: Option<u8> = None;
+        let mut scale: i64 = unit / 10; // Weight of the first decimal digit
+        let decimals = decimals as usize;
+        let mut round_digit: u8 = 0;
+        // Any non-zero digit after the rounding digit (distinguishes exact halves)
+        let mut sticky = false;
 
         for (i, ch) in decimal_part.bytes().enumerate() {
             if !ch.is_ascii_digit() {
@@ -60,26 +360,33 @@ pub fn parse_price_i64_1e8(s: &str) -> Result<i64> {
 
             let digit = ch - b'0';
 
-            if i < 8 {
-                // First 8 decimal digits - add to value
+            if i < decimals {
+                // Kept decimal digits - add to value
                 decimal_value += (digit as i64) * scale;
                 scale /= 10;
-            } else if i == 8 {
-                // 9th digit - used for rounding
-                round_digit = Some(digit);
-                break; // We only need the 9th digit for rounding
+            } else if i == decimals {
+                // First dropped digit - used for rounding
+                round_digit = digit;
+            } else if digit != 0 {
+                sticky = true;
             }
         }
 
-        result = result.checked_add(decimal_value)
+        result = arith::add(result, decimal_value)
             .ok_or_else(|| anyhow::anyhow!("Overflow adding decimal part"))?;
 
-        // Round half-up: if 9th digit >= 5, add 1
-        if let Some(d) = round_digit {
-            if d >= 5 {
-                result = result.checked_add(1)
-                    .ok_or_else(|| anyhow::anyhow!("Overflow during rounding"))?;
+        let round_up = match opts.rounding {
+            RoundingMode::HalfUp => round_digit >= 5,
+            RoundingMode::HalfEven => {
+                round_digit > 5 || (round_digit == 5 && (sticky || result & 1 == 1))
             }
+            RoundingMode::Truncate => false,
+        };
+
+        // Adding one unit of the last kept digit carries into the integer part when needed
+        if round_up {
+            result = arith::add(result, 1)
+                .ok_or_else(|| anyhow::anyhow!("Overflow during rounding"))?;
         }
     }
 
@@ -90,6 +397,41 @@ pub fn parse_price_i64_1e8(s: &str) -> Result<i64> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_price_scaling() {
+        let p = Price::from_1e8(6_500_012_345_678);
+        assert_eq!((p.raw(), p.scale()), (6_500_012_345_678, 100_000_000));
+        assert_eq!(p.to_f64(), 65_000.12345678);
+        assert_eq!(p.to_1e8(), Some(6_500_012_345_678));
+
+        let p = Price::new(12_345, 4);
+        assert_eq!(p.to_f64(), 1.2345);
+        assert_eq!(p.to_1e8(), Some(123_450_000));
+        assert_eq!(Price::new(i64::MAX, 0).to_1e8(), None);
+        #[cfg(feature = "decimal")]
+        assert_eq!(p.to_decimal().to_string(), "1.2345");
+    }
+
+    #[test]
+    fn test_format_price() {
+        assert_eq!(format_price_i64_1e8(10_050_000_000), "100.5");
+        assert_eq!(format_price_i64_1e8(1_234), "0.00001234");
+        assert_eq!(format_price_i64_1e8(1_234_567_890_000), "12345.6789");
+        assert_eq!(format_price_i64_1e8(100_000_000), "1");
+        assert_eq!(format_price_i64_1e8(0), "0");
+        assert_eq!(format_price_i64_1e8(-150_000_000), "-1.5");
+        assert_eq!(format_price_i64_1e8(-1), "-0.00000001");
+        assert_eq!(format_price_i64_1e8(i64::MIN), "-92233720368.54775808");
+        assert_eq!(format_price_scaled(12_340, 4), "1.234");
+        assert_eq!(format_price_scaled(7, 0), "7");
+        assert_eq!(Price::new(5, 2).to_string(), "0.05");
+
+        // Round-trips through the parser
+        for v in [1, 99_999_999, 6_500_012_345_678, i64::MAX] {
+            assert_eq!(parse_price_i64_1e8(&format_price_i64_1e8(v)).unwrap(), v);
+        }
+    }
+
     #[test]
     fn test_parse_price_integer() {
         assert_eq!(parse_price_i64_1e8("100").unwrap(), 10_000_000_000);
@@ -119,6 +461,21 @@ mod tests {
         assert_eq!(parse_price_i64_1e8("0.123456789").unwrap(), 12_345_679);
     }
 
+    #[test]
+    fn test_parse_price_scaled() {
+        let opts = PriceParseOptions::default();
+        assert_eq!(parse_price_scaled("0.12345", 4, &opts).unwrap(), 1_235);
+        assert_eq!(parse_price_scaled("1.23444999", 4, &opts).unwrap(), 12_344);
+        assert_eq!(parse_price_scaled("65000.5", 6, &opts).unwrap(), 65_000_500_000);
+        assert_eq!(parse_price_scaled("9.5", 0, &opts).unwrap(), 10);
+        assert_eq!(parse_price_scaled("1.5e-3", 4, &opts).unwrap(), 15);
+        assert_eq!(parse_price_scaled("0.12345", 8, &opts).unwrap(), parse_price_with("0.12345", &opts).unwrap());
+        assert!(parse_price_scaled("1.0", 9, &opts).is_err());
+
+        let truncate = PriceParseOptions { rounding: RoundingMode::Truncate, ..opts };
+        assert_eq!(parse_price_scaled("0.12349", 4, &truncate).unwrap(), 1_234);
+    }
+
     #[test]
     fn test_parse_price_edge_cases() {
         // No decimal point
@@ -149,6 +506,9 @@ mod tests {
         assert!(parse_price_i64_1e8("").is_err());
         assert!(parse_price_i64_1e8("abc").is_err());
         assert!(parse_price_i64_1e8("12.34.56").is_err());
+        assert!(parse_price_i64_1e8(".").is_err());
+        // Garbage past the rounding digit must not be silently ignored
+        assert!(parse_price_i64_1e8("0.000000000a0").is_err());
     }
 
     #[test]
@@ -156,4 +516,87 @@ mod tests {
         // Should handle large prices
         assert_eq!(parse_price_i64_1e8("999999.99999999").unwrap(), 99_999_999_999_999);
     }
+
+    #[test]
+    fn test_parse_price_rounding_carry() {
+        assert_eq!(parse_price_i64_1e8("0.999999995").unwrap(), 100_000_000);
+        assert_eq!(parse_price_i64_1e8("1.999999995").unwrap(), 200_000_000);
+        assert_eq!(parse_price_i64_1e8("9.999999999").unwrap(), 1_000_000_000);
+        assert_eq!(parse_price_i64_1e8("99999.999999995").unwrap(), 10_000_000_000_000);
+    }
+
+    #[test]
+    fn test_parse_price_rounding_modes() {
+        let half_even = PriceParseOptions { rounding: RoundingMode::HalfEven, ..Default::default() };
+        let truncate = PriceParseOptions { rounding: RoundingMode::Truncate, ..Default::default() };
+
+        // Exact halves go to the even neighbour
+        assert_eq!(parse_price_with("0.000000005", &half_even).unwrap(), 0);
+        assert_eq!(parse_price_with("0.000000015", &half_even).unwrap(), 2);
+        assert_eq!(parse_price_with("0.0000000250", &half_even).unwrap(), 2);
+        // Above half always rounds up
+        assert_eq!(parse_price_with("0.0000000050001", &half_even).unwrap(), 1);
+        assert_eq!(parse_price_with("1.999999995", &half_even).unwrap(), 200_000_000);
+
+        assert_eq!(parse_price_with("0.123456789", &truncate).unwrap(), 12_345_678);
+        assert_eq!(parse_price_with("1.999999999", &truncate).unwrap(), 199_999_999);
+
+        assert_eq!("half_even".parse::<RoundingMode>().unwrap(), RoundingMode::HalfEven);
+        assert_eq!("TRUNCATE".parse::<RoundingMode>().unwrap(), RoundingMode::Truncate);
+        assert!("nearest".parse::<RoundingMode>().is_err());
+    }
+
+    #[test]
+    fn test_parse_price_scientific() {
+        assert_eq!(parse_price_i64_1e8("1.2e-7").unwrap(), 12);
+        assert_eq!(parse_price_i64_1e8("1.2E-7").unwrap(), 12);
+        assert_eq!(parse_price_i64_1e8("5e3").unwrap(), 500_000_000_000);
+        assert_eq!(parse_price_i64_1e8("5E+3").unwrap(), 500_000_000_000);
+        assert_eq!(parse_price_i64_1e8("123.456e1").unwrap(), 123_456_000_000);
+        assert_eq!(parse_price_i64_1e8(".5e1").unwrap(), 500_000_000);
+        assert_eq!(parse_price_i64_1e8("1e0").unwrap(), 100_000_000);
+        // Rounding still applies after the shift
+        assert_eq!(parse_price_i64_1e8("9.99999999500e-1").unwrap(), 100_000_000);
+        assert_eq!(parse_price_i64_1e8("4e-9").unwrap(), 0);
+        assert_eq!(parse_price_i64_1e8("5e-9").unwrap(), 1);
+
+        assert!(parse_price_i64_1e8("1e").is_err());
+        assert!(parse_price_i64_1e8("e5").is_err());
+        assert!(parse_price_i64_1e8("1e5e5").is_err());
+        assert!(parse_price_i64_1e8("1.2.3e1").is_err());
+        assert!(parse_price_i64_1e8("1e999").is_err());
+        #[cfg(not(feature = "unchecked-arith"))]
+        assert!(parse_price_i64_1e8("1e30").is_err()); // i64 overflow
+    }
+
+    #[test]
+    fn test_parse_price_lenient() {
+        assert_eq!(parse_price_i64_1e8("+1.5").unwrap(), 150_000_000);
+        assert_eq!(parse_price_i64_1e8("  +1.5\t").unwrap(), 150_000_000);
+        assert_eq!(parse_price_i64_1e8(" 42 ").unwrap(), 4_200_000_000);
+        assert!(parse_price_i64_1e8("+").is_err());
+        assert!(parse_price_i64_1e8("++1").is_err());
+        assert!(parse_price_i64_1e8("-1").is_err());
+        // Never accepted, in any mode
+        assert!(parse_price_i64_1e8("1_000.5").is_err());
+        assert!(parse_price_i64_1e8("1,000.5").is_err());
+        assert!(parse_price_i64_1e8("1 000.5").is_err());
+    }
+
+    #[test]
+    fn test_parse_price_strict() {
+        let strict = PriceParseOptions { mode: ParseMode::Strict, ..Default::default() };
+
+        assert_eq!(parse_price_with("43567.89", &strict).unwrap(), 4_356_789_000_000);
+        assert_eq!(parse_price_with("0.00012345", &strict).unwrap(), 12_345);
+        assert_eq!(parse_price_with("42", &strict).unwrap(), 4_200_000_000);
+        assert_eq!(parse_price_with("0.123456785", &strict).unwrap(), 12_345_679);
+
+        for bad in ["", " 1.5", "1.5 ", "+1.5", ".5", "5.", "1.2e-7", "1_000", "1,000.5", "1.2.3"] {
+            assert!(parse_price_with(bad, &strict).is_err(), "strict accepted {:?}", bad);
+        }
+
+        assert_eq!("STRICT".parse::<ParseMode>().unwrap(), ParseMode::Strict);
+        assert!("loose".parse::<ParseMode>().is_err());
+    }
 }
@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use crate::config::LogConfig;
+use crate::platform::monotonic_us;
+
+static SETTINGS: OnceLock<LogConfig> = OnceLock::new();
+
+/// Install process-wide rate-limit settings (first call wins)
+pub fn init(config: LogConfig) {
+    let _ = SETTINGS.set(config);
+}
+
+fn settings() -> &'static LogConfig {
+    SETTINGS.get_or_init(LogConfig::default)
+}
+
+/// Per-call-site log limiter: at most `burst` lines per interval, the rest are
+/// counted and summarized as "suppressed N similar" when the next window opens
+/// (i.e. on the next occurrence after the interval elapses)
+pub struct LogLimiter {
+    window_start_us: AtomicU64,
+    emitted: AtomicU64,
+    suppressed: AtomicU64,
+}
+
+impl Default for LogLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogLimiter {
+    pub const fn new() -> Self {
+        Self {
+            window_start_us: AtomicU64::new(0),
+            emitted: AtomicU64::new(0),
+            suppressed: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether the caller should log now; `site` names the call site in summaries
+    pub fn allow(&self, site: &str) -> bool {
+        let s = settings();
+        if s.burst == 0 {
+            return true;
+        }
+        let (allowed, suppressed) = self.check(monotonic_us() as u64, s.burst, s.interval_ms * 1000);
+        if suppressed > 0 {
+            eprintln!("[LOG] suppressed {} similar messages at {}", suppressed, site);
+        }
+        allowed
+    }
+
+    /// Returns (allowed, suppressed count from the window that just closed)
+    fn check(&self, now_us: u64, burst: u64, interval_us: u64) -> (bool, u64) {
+        let mut flushed = 0;
+        let start = self.window_start_us.load(Ordering::Relaxed);
+        if now_us.saturating_sub(start) >= interval_us
+            && self.window_start_us
+                .compare_exchange(start, now_us, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            flushed = self.suppressed.swap(0, Ordering::Relaxed);
+            self.emitted.store(0, Ordering::Relaxed);
+        }
+
+        if self.emitted.fetch_add(1, Ordering::Relaxed) < burst {
+            (true, flushed)
+        } else {
+            self.suppressed.fetch_add(1, Ordering::Relaxed);
+            (false, flushed)
+        }
+    }
+}
+
+/// `eprintln!` with a per-call-site rate limit (see LogLimiter)
+#[macro_export]
+macro_rules! eprintln_limited {
+    ($($arg:tt)*) => {{
+        static LIMITER: $crate::ratelog::LogLimiter = $crate::ratelog::LogLimiter::new();
+        if LIMITER.allow(concat!(file!(), ":", line!())) {
+            eprintln!($($arg)*);
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_then_suppress_then_summary() {
+        let limiter = LogLimiter::new();
+        let interval = 1_000_000;
+        let t0 = 5_000_000;
+
+        let allowed: Vec<_> = (0..5).map(|i| limiter.check(t0 + i, 3, interval)).collect();
+        assert_eq!(allowed, vec![(true, 0), (true, 0), (true, 0), (false, 0), (false, 0)]);
+
+        // Next window: summary of the 2 suppressed lines, then logging resumes
+        assert_eq!(limiter.check(t0 + interval, 3, interval), (true, 2));
+        assert_eq!(limiter.check(t0 + interval + 1, 3, interval), (true, 0));
+    }
+}
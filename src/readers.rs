@@ -0,0 +1,418 @@
+//! Reader registration region: consumers announce themselves so the writer
+//! can report lagging or dead readers
+//!
+//! Optional file next to the quotes SHM (little-endian, 64-byte header, then
+//! one entry per reader):
+//! - header: magic `QRDR1\0\0\0`, version (1), max_readers, n_sources,
+//!   n_symbols, entry_size
+//! - entry: 64-byte descriptor (token, heartbeat_unix_us, registered_unix_us,
+//!   reserved, NUL-padded name) followed by one u64 per quote slot
+//!   (`source_id * n_symbols + symbol_id`): the last seq the reader observed
+//!   there (0 = never read)
+//!
+//! A consumer claims a free entry, or one left by a dead process, by CAS on
+//! `token` (pid in the high 32 bits), heartbeats it and records the seq of
+//! each quote it reads. The writer compares those seqs with the slots' and
+//! logs readers that fall behind or stop heartbeating.
+
+use anyhow::{bail, Result};
+use memmap2::MmapMut;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::config::ReadersConfig;
+use crate::ownership::{token_alive, token_pid, writer_token};
+use crate::persist::unix_now_us;
+use crate::region::{self, RegionFormat};
+use crate::shm::ShmManager;
+
+const MAGIC: &[u8; 8] = b"QRDR1\0\0\0";
+const VERSION: u64 = 1;
+const HEADER_SIZE: u64 = 64;
+const FORMAT: RegionFormat = RegionFormat { kind: "reader region", magic: MAGIC, header_size: HEADER_SIZE };
+const DESCRIPTOR_SIZE: u64 = 64;
+const NAME_LEN: usize = 32;
+
+/// Per-reader descriptor at the start of its entry
+#[repr(C)]
+struct Descriptor {
+    /// Claiming process token (0 = free)
+    token: AtomicU64,
+    /// Last heartbeat (0 while the entry is being set up)
+    heartbeat_unix_us: AtomicU64,
+    registered_unix_us: AtomicU64,
+    reserved: u64,
+    name: [u8; NAME_LEN],
+}
+
+const _: () = assert!(std::mem::size_of::<Descriptor>() == DESCRIPTOR_SIZE as usize);
+
+/// Entry size for `n_slots` seqs, rounded up to a cache line
+fn entry_size(n_slots: u64) -> u64 {
+    DESCRIPTOR_SIZE + (n_slots * 8).div_ceil(64) * 64
+}
+
+/// One registered reader as seen in the region
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisteredReader {
+    pub entry: usize,
+    pub pid: u32,
+    pub name: String,
+    pub heartbeat_unix_us: u64,
+    pub registered_unix_us: u64,
+}
+
+/// Memory-mapped reader registration region
+pub struct ReaderRegistry {
+    _mmap: MmapMut,
+    base: *mut u8,
+    max_readers: u64,
+    n_sources: u64,
+    n_symbols: u64,
+    entry_size: u64,
+}
+
+// SAFETY: the mapping lives as long as the registry; shared fields are atomics
+unsafe impl Send for ReaderRegistry {}
+unsafe impl Sync for ReaderRegistry {}
+
+impl ReaderRegistry {
+    /// Open `path`, creating it for `max_readers` readers of an
+    /// `n_sources` x `n_symbols` quotes file (mode/group as for that file)
+    pub fn open_or_create(path: &str, max_readers: u64, n_sources: u64, n_symbols: u64, mode: u32, group: Option<&str>) -> Result<Self> {
+        if !std::path::Path::new(path).exists() {
+            Self::create(path, max_readers, n_sources, n_symbols, mode, group)?;
+        }
+        let registry = Self::open(path)?;
+        if (registry.n_sources, registry.n_symbols) != (n_sources, n_symbols) {
+            bail!("Reader region {} is for {} x {} slots, the quotes file has {} x {}; remove it to recreate",
+                  path, registry.n_sources, registry.n_symbols, n_sources, n_symbols);
+        }
+        Ok(registry)
+    }
+
+    fn create(path: &str, max_readers: u64, n_sources: u64, n_symbols: u64, mode: u32, group: Option<&str>) -> Result<()> {
+        if max_readers == 0 {
+            bail!("Reader region needs room for at least one reader");
+        }
+        let entry_size = entry_size(n_sources * n_symbols);
+        let fields = [VERSION, max_readers, n_sources, n_symbols, entry_size];
+        FORMAT.create(path, &fields, HEADER_SIZE + max_readers * entry_size, mode, group)?;
+        eprintln!("[READERS] Created reader region {} ({} readers)", path, max_readers);
+        Ok(())
+    }
+
+    /// Map an existing region read-write (readers need write access to register)
+    pub fn open(path: &str) -> Result<Self> {
+        let mut mmap = FORMAT.open(path)?;
+        let field = |i: usize| region::field(&mmap, i);
+        let (version, max_readers, n_sources, n_symbols, size) = (field(0), field(1), field(2), field(3), field(4));
+        if version != VERSION {
+            bail!("Unsupported reader region {}: version {}", path, version);
+        }
+        if size != entry_size(n_sources * n_symbols) || mmap.len() as u64 != HEADER_SIZE + max_readers * size {
+            bail!("Reader region {} size {} doesn't match its header", path, mmap.len());
+        }
+
+        let base = mmap.as_mut_ptr();
+        Ok(Self { _mmap: mmap, base, max_readers, n_sources, n_symbols, entry_size: size })
+    }
+
+    pub fn max_readers(&self) -> u64 {
+        self.max_readers
+    }
+
+    fn descriptor(&self, entry: usize) -> *mut Descriptor {
+        // SAFETY: callers pass entry < max_readers
+        unsafe { self.base.add((HEADER_SIZE + entry as u64 * self.entry_size) as usize) as *mut Descriptor }
+    }
+
+    fn token(&self, entry: usize) -> &AtomicU64 {
+        unsafe { &(*self.descriptor(entry)).token }
+    }
+
+    fn heartbeat_cell(&self, entry: usize) -> &AtomicU64 {
+        unsafe { &(*self.descriptor(entry)).heartbeat_unix_us }
+    }
+
+    /// Last observed seq per slot of an entry
+    fn seqs(&self, entry: usize) -> &[AtomicU64] {
+        // SAFETY: the seq array follows the descriptor inside the entry
+        unsafe {
+            let ptr = (self.descriptor(entry) as *mut u8).add(DESCRIPTOR_SIZE as usize) as *const AtomicU64;
+            std::slice::from_raw_parts(ptr, (self.n_sources * self.n_symbols) as usize)
+        }
+    }
+
+    /// Claim an entry as `name` (truncated to 32 bytes); entries of dead
+    /// processes are reused
+    pub fn register(self: &Arc<Self>, name: &str) -> Result<ReaderHandle> {
+        let token = writer_token();
+        for entry in 0..self.max_readers as usize {
+            let current = self.token(entry).load(Ordering::Acquire);
+            let reclaimable = current == 0 || token_alive(current) == Some(false);
+            if !reclaimable || self.token(entry).compare_exchange(current, token, Ordering::AcqRel, Ordering::Relaxed).is_err() {
+                continue;
+            }
+
+            self.heartbeat_cell(entry).store(0, Ordering::Release);
+            for seq in self.seqs(entry) {
+                seq.store(0, Ordering::Relaxed);
+            }
+            let mut padded = [0u8; NAME_LEN];
+            let bytes = &name.as_bytes()[..name.len().min(NAME_LEN)];
+            padded[..bytes.len()].copy_from_slice(bytes);
+            let now = unix_now_us();
+            // SAFETY: the entry is ours; the writer reads these only after a non-zero heartbeat
+            unsafe {
+                let descriptor = self.descriptor(entry);
+                std::ptr::write_volatile(&mut (*descriptor).name, padded);
+                (*descriptor).registered_unix_us.store(now, Ordering::Relaxed);
+            }
+            self.heartbeat_cell(entry).store(now, Ordering::Release);
+            return Ok(ReaderHandle { registry: self.clone(), entry, token });
+        }
+        bail!("No free reader entry ({} registered)", self.max_readers)
+    }
+
+    /// Registered readers that have heartbeated at least once
+    pub fn readers(&self) -> Vec<RegisteredReader> {
+        (0..self.max_readers as usize)
+            .filter_map(|entry| {
+                let token = self.token(entry).load(Ordering::Acquire);
+                let heartbeat_unix_us = self.heartbeat_cell(entry).load(Ordering::Acquire);
+                if token == 0 || heartbeat_unix_us == 0 {
+                    return None;
+                }
+                // SAFETY: name and registration time are written before the first heartbeat
+                let (name, registered_unix_us) = unsafe {
+                    let descriptor = self.descriptor(entry);
+                    (std::ptr::read_volatile(&(*descriptor).name), (*descriptor).registered_unix_us.load(Ordering::Relaxed))
+                };
+                let len = name.iter().position(|&b| b == 0).unwrap_or(NAME_LEN);
+                Some(RegisteredReader {
+                    entry,
+                    pid: token_pid(token),
+                    name: String::from_utf8_lossy(&name[..len]).into_owned(),
+                    heartbeat_unix_us,
+                    registered_unix_us,
+                })
+            })
+            .collect()
+    }
+
+    /// Last seq `entry` observed in slot (source_id, symbol_id), 0 = never
+    pub fn observed(&self, entry: usize, source_id: u64, symbol_id: u64) -> u64 {
+        self.seqs(entry)[(source_id * self.n_symbols + symbol_id) as usize].load(Ordering::Relaxed)
+    }
+}
+
+/// A consumer's registration; released on drop
+pub struct ReaderHandle {
+    registry: Arc<ReaderRegistry>,
+    entry: usize,
+    token: u64,
+}
+
+impl ReaderHandle {
+    pub fn entry(&self) -> usize {
+        self.entry
+    }
+
+    /// Stamp the heartbeat (call at least every few hundred ms)
+    pub fn heartbeat(&self) {
+        self.registry.heartbeat_cell(self.entry).store(unix_now_us(), Ordering::Release);
+    }
+
+    /// Record the seq of a quote just read (see `ShmReader::slot_seq`)
+    #[inline]
+    pub fn observe(&self, source_id: u64, symbol_id: u64, seq: u64) {
+        if source_id < self.registry.n_sources && symbol_id < self.registry.n_symbols {
+            self.registry.seqs(self.entry)[(source_id * self.registry.n_symbols + symbol_id) as usize]
+                .store(seq, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for ReaderHandle {
+    fn drop(&mut self) {
+        let _ = self.registry.token(self.entry).compare_exchange(self.token, 0, Ordering::AcqRel, Ordering::Relaxed);
+    }
+}
+
+/// How a registered reader is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReaderHealth {
+    Ok,
+    /// `slots` read slots are over the lag limit, the worst by `max_missed` updates
+    Lagging { slots: usize, max_missed: u64 },
+    /// No heartbeat within the limit, or the process is gone
+    Dead,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReaderStatus {
+    pub reader: RegisteredReader,
+    pub heartbeat_age_us: u64,
+    pub health: ReaderHealth,
+}
+
+/// Writer side: checks registered readers against the quotes file
+pub struct ReaderMonitor {
+    registry: Arc<ReaderRegistry>,
+    shm: Arc<ShmManager>,
+    dead_after_us: u64,
+    lag_updates: u64,
+    /// Health at the last check per (entry, pid), for transition logs
+    last: Mutex<HashMap<(usize, u32), ReaderHealth>>,
+}
+
+impl ReaderMonitor {
+    pub fn new(registry: Arc<ReaderRegistry>, shm: Arc<ShmManager>, config: &ReadersConfig) -> Self {
+        Self {
+            registry,
+            shm,
+            dead_after_us: config.dead_after_ms * 1000,
+            lag_updates: config.lag_updates,
+            last: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Current status of every registered reader
+    pub fn status(&self, now_unix_us: u64) -> Vec<ReaderStatus> {
+        self.registry
+            .readers()
+            .into_iter()
+            .map(|reader| {
+                let heartbeat_age_us = now_unix_us.saturating_sub(reader.heartbeat_unix_us);
+                let health = if heartbeat_age_us > self.dead_after_us
+                    || crate::platform::process_alive(reader.pid) == Some(false)
+                {
+                    ReaderHealth::Dead
+                } else {
+                    self.lag(reader.entry)
+                };
+                ReaderStatus { reader, heartbeat_age_us, health }
+            })
+            .collect()
+    }
+
+    /// Compare an entry's observed seqs with the slots' (each write adds 2)
+    fn lag(&self, entry: usize) -> ReaderHealth {
+        let (mut slots, mut max_missed) = (0, 0);
+        for source_id in 0..self.registry.n_sources {
+            for symbol_id in 0..self.registry.n_symbols {
+                let observed = self.registry.observed(entry, source_id, symbol_id);
+                let Ok(slot) = self.shm.get_slot(source_id, symbol_id) else {
+                    continue;
+                };
+                if observed == 0 {
+                    continue;
+                }
+                let missed = (slot.seq() & !1).saturating_sub(observed) / 2;
+                if missed > self.lag_updates {
+                    slots += 1;
+                    max_missed = max_missed.max(missed);
+                }
+            }
+        }
+        if slots > 0 {
+            ReaderHealth::Lagging { slots, max_missed }
+        } else {
+            ReaderHealth::Ok
+        }
+    }
+
+    /// Check all readers, logging registrations, health changes and departures
+    pub fn check(&self) -> Vec<ReaderStatus> {
+        let statuses = self.status(unix_now_us());
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let mut seen = HashMap::new();
+        for status in &statuses {
+            let key = (status.reader.entry, status.reader.pid);
+            let reader = &status.reader;
+            match (last.get(&key), status.health) {
+                (Some(before), now) if *before == now => {}
+                (Some(ReaderHealth::Lagging { .. }), ReaderHealth::Lagging { .. }) => {}
+                (before, ReaderHealth::Ok) => {
+                    let what = if before.is_some() { "caught up" } else { "registered" };
+                    eprintln!("[READERS] #{} {} (pid {}) {}", reader.entry, reader.name, reader.pid, what);
+                }
+                (_, ReaderHealth::Lagging { slots, max_missed }) => {
+                    eprintln!("[READERS] WARNING: #{} {} (pid {}) lagging: {} slots over {} updates behind (max {})",
+                              reader.entry, reader.name, reader.pid, slots, self.lag_updates, max_missed);
+                }
+                (_, ReaderHealth::Dead) => {
+                    eprintln!("[READERS] WARNING: #{} {} (pid {}) dead: no heartbeat for {:.1} s",
+                              reader.entry, reader.name, reader.pid, status.heartbeat_age_us as f64 / 1e6);
+                }
+            }
+            seen.insert(key, status.health);
+        }
+        for (entry, pid) in last.keys().filter(|key| !seen.contains_key(key)) {
+            eprintln!("[READERS] #{} (pid {}) unregistered", entry, pid);
+        }
+        *last = seen;
+        statuses
+    }
+
+    /// Check every `interval`
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.check();
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reader_lag_and_liveness() {
+        let path = std::env::temp_dir().join(format!("test_readers_{}.dat.readers", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&path);
+        let shm = Arc::new(ShmManager::anonymous(2, 4).unwrap());
+        shm.init_slot(1, 2).unwrap();
+
+        let registry = Arc::new(ReaderRegistry::open_or_create(&path, 2, 2, 4, 0o600, None).unwrap());
+        assert!(ReaderRegistry::open_or_create(&path, 2, 2, 8, 0o600, None).is_err());
+        let config = ReadersConfig { dead_after_ms: 5_000, lag_updates: 2, ..Default::default() };
+        let monitor = ReaderMonitor::new(registry.clone(), shm.clone(), &config);
+
+        let first = registry.register("strategy-a").unwrap();
+        let second = registry.register("a-very-long-reader-name-over-32-bytes").unwrap();
+        assert!(registry.register("third").is_err());
+        let names: Vec<String> = registry.readers().into_iter().map(|r| r.name).collect();
+        assert_eq!(names, vec!["strategy-a", "a-very-long-reader-name-over-32-"]);
+
+        // `first` keeps up, `second` read once and stopped
+        let slot = shm.get_slot(1, 2).unwrap();
+        slot.write(100, 101, 1);
+        second.observe(1, 2, slot.seq());
+        for i in 0..4 {
+            slot.write(100 + i, 101 + i, 2 + i);
+        }
+        first.observe(1, 2, slot.seq());
+        let health: Vec<ReaderHealth> = monitor.check().into_iter().map(|s| s.health).collect();
+        assert_eq!(health, vec![ReaderHealth::Ok, ReaderHealth::Lagging { slots: 1, max_missed: 4 }]);
+
+        // A stale heartbeat means dead; a released entry can be claimed again
+        let now = unix_now_us();
+        assert_eq!(monitor.status(now + 6_000_000)[0].health, ReaderHealth::Dead);
+        drop(second);
+        assert_eq!(registry.readers().len(), 1);
+        let third = registry.register("third").unwrap();
+        assert_eq!(third.entry(), 1);
+        assert_eq!(registry.observed(1, 1, 2), 0, "a new registration starts with no observed seqs");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
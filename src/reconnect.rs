@@ -0,0 +1,532 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use tokio::time::Duration;
+use tokio_tungstenite::tungstenite;
+
+/// Default wait after HTTP 429 (request rate limit) without Retry-After
+const DEFAULT_429_BACKOFF: Duration = Duration::from_secs(60);
+/// Default wait after HTTP 418 (IP auto-ban) without Retry-After
+const DEFAULT_418_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Shortest wait between token checks, and how long an ordinary connection
+/// yields to a waiting priority one
+const MIN_BUCKET_WAIT: Duration = Duration::from_millis(10);
+
+/// Close status when the stream ends without a close frame (RFC 6455 "abnormal closure")
+pub const CLOSE_ABNORMAL: u16 = 1006;
+/// Close status for a close frame without a payload (RFC 6455 "no status received")
+pub const CLOSE_NO_STATUS: u16 = 1005;
+
+/// Close code and reason sent by the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloseInfo {
+    pub code: u16,
+    pub reason: String,
+}
+
+impl CloseInfo {
+    /// Stream ended without a close frame
+    pub fn abnormal() -> Self {
+        Self { code: CLOSE_ABNORMAL, reason: String::new() }
+    }
+}
+
+/// Why a connection attempt failed or an established session ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectKind {
+    /// Server closed the session (close frame or end of stream)
+    ServerClose(CloseInfo),
+    /// Established session dropped by a read error
+    Reset,
+    /// Established session went silent past the idle threshold
+    Idle,
+    /// Name resolution failed
+    Dns,
+    /// TCP connect refused/unreachable
+    Connect,
+    /// Connect or handshake timed out
+    Timeout,
+    /// TLS handshake/certificate failure
+    Tls,
+    /// HTTP 429 / 418 on the upgrade request
+    RateLimited { status: u16, retry_after: Option<Duration> },
+    /// Other non-101 HTTP status on the upgrade request
+    Http(u16),
+    /// Torn down on operator request (admin `restart`)
+    Requested,
+    /// The session task panicked (caught, see fault::isolate)
+    Panicked,
+    /// Connected, but none of the subscribed symbols delivered a message in time
+    WarmUp,
+    Other,
+}
+
+impl DisconnectKind {
+    /// Session had been established before it ended
+    pub fn was_established(&self) -> bool {
+        matches!(self, Self::ServerClose(_) | Self::Reset | Self::Idle)
+    }
+
+    /// Short name for metric labels
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::ServerClose(_) => "server_close",
+            Self::Reset => "reset",
+            Self::Idle => "idle",
+            Self::Dns => "dns",
+            Self::Connect => "connect",
+            Self::Timeout => "timeout",
+            Self::Tls => "tls",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::Http(_) => "http",
+            Self::Requested => "requested",
+            Self::Panicked => "panicked",
+            Self::WarmUp => "warm_up",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Lifecycle state of a supervised connection (see ws::supervise)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnState {
+    /// DNS, TCP connect, TLS and WebSocket handshake
+    Connecting,
+    /// Upgraded; waiting for the subscribed streams to deliver (the first
+    /// feed frame, or the warm-up when enabled)
+    Subscribing,
+    /// Feed frames flowing
+    Streaming,
+    /// Session over: failover, outage accounting, classifying how it ended
+    Draining,
+    /// Waiting out the reconnect delay and budget (the staggered startup too)
+    Backoff,
+}
+
+impl ConnState {
+    pub const ALL: [Self; 5] = [Self::Connecting, Self::Subscribing, Self::Streaming, Self::Draining, Self::Backoff];
+    pub const COUNT: usize = Self::ALL.len();
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_index(index: usize) -> Self {
+        Self::ALL[index.min(Self::COUNT - 1)]
+    }
+
+    /// Whether a connection in this state may move to `next`
+    pub fn can_enter(self, next: Self) -> bool {
+        use ConnState::*;
+        matches!(
+            (self, next),
+            (Backoff, Connecting)
+                | (Connecting, Subscribing)
+                | (Subscribing, Streaming)
+                | (Connecting | Subscribing | Streaming, Draining)
+                | (Draining, Backoff)
+        )
+    }
+}
+
+/// Connection error carrying its DisconnectKind (wrapped in anyhow::Error)
+#[derive(Debug)]
+pub struct WsError {
+    pub kind: DisconnectKind,
+    message: String,
+}
+
+impl WsError {
+    pub fn new(kind: DisconnectKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+}
+
+impl fmt::Display for WsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({:?})", self.message, self.kind)
+    }
+}
+
+impl std::error::Error for WsError {}
+
+/// A connection gave up after too many consecutive errors (`WsManager::run_all`'s
+/// error; the binary exits with code 3 on it)
+#[derive(Debug)]
+pub struct TooManyErrors {
+    /// Log tag of the connection, e.g. "WS-3"
+    pub tag: String,
+}
+
+impl fmt::Display for TooManyErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] Too many consecutive errors, giving up", self.tag)
+    }
+}
+
+impl std::error::Error for TooManyErrors {}
+
+/// Classify a tungstenite error from the handshake or read loop
+pub fn classify_tungstenite(err: &tungstenite::Error) -> DisconnectKind {
+    use tungstenite::Error;
+
+    match err {
+        Error::Http(response) => {
+            let status = response.status().as_u16();
+            if status == 429 || status == 418 {
+                let retry_after = response
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse::<u64>().ok())
+                    .map(Duration::from_secs);
+                DisconnectKind::RateLimited { status, retry_after }
+            } else {
+                DisconnectKind::Http(status)
+            }
+        }
+        Error::Tls(_) => DisconnectKind::Tls,
+        Error::Io(_) => DisconnectKind::Connect,
+        Error::ConnectionClosed | Error::AlreadyClosed => DisconnectKind::ServerClose(CloseInfo::abnormal()),
+        _ => DisconnectKind::Other,
+    }
+}
+
+/// Classify any error returned by WsConnection::run
+pub fn classify(err: &anyhow::Error) -> DisconnectKind {
+    if let Some(ws_err) = err.downcast_ref::<WsError>() {
+        return ws_err.kind.clone();
+    }
+    if let Some(tung_err) = err.downcast_ref::<tungstenite::Error>() {
+        return classify_tungstenite(tung_err);
+    }
+    DisconnectKind::Other
+}
+
+/// Backoff calculator for reconnections
+pub struct BackoffCalculator {
+    pub attempt: u32,
+    delays_ms: Vec<u64>,
+    max_delay_ms: u64,
+}
+
+impl Default for BackoffCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackoffCalculator {
+    pub fn new() -> Self {
+        Self {
+            attempt: 0,
+            delays_ms: vec![200, 500, 1000, 2000, 5000, 10000, 30000],
+            max_delay_ms: 30000,
+        }
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let delay_ms = if (self.attempt as usize) < self.delays_ms.len() {
+            self.delays_ms[self.attempt as usize]
+        } else {
+            self.max_delay_ms
+        };
+
+        self.attempt += 1;
+        Duration::from_millis(delay_ms)
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+struct BucketState {
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// Reconnect attempts shared by every connection in the process
+///
+/// A network blip drops all connections at once; without a shared budget
+/// they all reconnect in the same second and can trip Binance's per-IP
+/// connection limit (300 per 5 minutes), which turns a blip into a ban.
+/// Attempts take a token; `burst` are available at once and they refill at
+/// `per_min`. Priority connections go first: while one waits, ordinary
+/// connections leave the tokens to it.
+pub struct ReconnectBucket {
+    per_token: Duration,
+    burst: f64,
+    state: Mutex<BucketState>,
+    priority_waiting: AtomicUsize,
+    /// Attempts that had to wait for a token
+    throttled: AtomicU64,
+}
+
+/// A priority connection counted as waiting for a token until dropped
+struct PriorityWaiting<'a>(&'a AtomicUsize);
+
+impl<'a> PriorityWaiting<'a> {
+    fn new(count: &'a AtomicUsize) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Self(count)
+    }
+}
+
+impl Drop for PriorityWaiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl ReconnectBucket {
+    /// Bucket refilling `per_min` tokens a minute, holding up to `burst` (starts full)
+    pub fn new(per_min: u32, burst: u32) -> Self {
+        let burst = burst.max(1) as f64;
+        Self {
+            per_token: Duration::from_secs(60) / per_min.max(1),
+            burst,
+            state: Mutex::new(BucketState { tokens: burst, refilled: Instant::now() }),
+            priority_waiting: AtomicUsize::new(0),
+            throttled: AtomicU64::new(0),
+        }
+    }
+
+    /// Take a token at `now`, or say how long to wait before trying again
+    fn take_at(&self, now: Instant, priority: bool) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let refill = now.saturating_duration_since(state.refilled).as_secs_f64() / self.per_token.as_secs_f64();
+        state.tokens = (state.tokens + refill).min(self.burst);
+        state.refilled = now;
+
+        let yielding = !priority && self.priority_waiting.load(Ordering::Relaxed) > 0;
+        if state.tokens >= 1.0 && !yielding {
+            state.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(self.per_token.mul_f64((1.0 - state.tokens).max(0.0)).max(MIN_BUCKET_WAIT))
+    }
+
+    /// Wait for a token; returns how long that took
+    pub async fn acquire(&self, priority: bool) -> Duration {
+        let start = Instant::now();
+        // Also undone when the wait is cancelled, so other connections don't
+        // keep yielding to a priority connection that went away
+        let _waiting = priority.then(|| PriorityWaiting::new(&self.priority_waiting));
+        let mut waited = false;
+        while let Err(wait) = self.take_at(Instant::now(), priority) {
+            waited = true;
+            tokio::time::sleep(wait).await;
+        }
+        if waited {
+            self.throttled.fetch_add(1, Ordering::Relaxed);
+        }
+        start.elapsed()
+    }
+
+    /// Attempts that had to wait for a token
+    pub fn throttled(&self) -> u64 {
+        self.throttled.load(Ordering::Relaxed)
+    }
+
+    pub fn report(&self) {
+        eprintln!("[STATS] Reconnect budget: {} attempts throttled", self.throttled());
+    }
+}
+
+/// What to do before the next connection attempt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconnectPlan {
+    pub delay: Duration,
+    /// Switch to the next configured endpoint
+    pub rotate_endpoint: bool,
+    /// Counts toward the fatal consecutive-error limit
+    pub counts_as_error: bool,
+}
+
+/// Pick the reconnect policy for a disconnect:
+/// - server close 1000/1001/1005/1006: reconnect immediately
+/// - server close 1012/1013 (restart, try again later) or other codes: keep backing off
+/// - established session dropped/idle: restart the backoff schedule
+/// - 429/418: honor Retry-After (or a long default), not counted as fatal
+/// - TLS failure: rotate endpoint
+/// - operator restart: reconnect immediately, not counted as an error
+/// - warm-up miss: exponential backoff, not counted as an error (quiet or
+///   halted symbols are no reason to give up)
+/// - everything else: exponential backoff
+pub fn plan_reconnect(kind: &DisconnectKind, backoff: &mut BackoffCalculator) -> ReconnectPlan {
+    let mut plan = ReconnectPlan {
+        delay: Duration::ZERO,
+        rotate_endpoint: false,
+        counts_as_error: !kind.was_established(),
+    };
+
+    match kind {
+        DisconnectKind::ServerClose(close) => match close.code {
+            1000 | 1001 | CLOSE_NO_STATUS | CLOSE_ABNORMAL => backoff.reset(),
+            // Restart / try again later / policy violation / server error: reconnecting
+            // right away would just get closed again
+            _ => plan.delay = backoff.next_delay(),
+        },
+        DisconnectKind::Reset | DisconnectKind::Idle => {
+            backoff.reset();
+            plan.delay = backoff.next_delay();
+        }
+        DisconnectKind::RateLimited { status, retry_after } => {
+            let default = if *status == 418 { DEFAULT_418_BACKOFF } else { DEFAULT_429_BACKOFF };
+            plan.delay = retry_after.unwrap_or(default).max(backoff.next_delay());
+            plan.counts_as_error = false;
+        }
+        DisconnectKind::Requested => {
+            backoff.reset();
+            plan.counts_as_error = false;
+        }
+        DisconnectKind::Tls => {
+            plan.rotate_endpoint = true;
+            plan.delay = backoff.next_delay();
+        }
+        DisconnectKind::WarmUp => {
+            plan.delay = backoff.next_delay();
+            plan.counts_as_error = false;
+        }
+        DisconnectKind::Dns
+        | DisconnectKind::Connect
+        | DisconnectKind::Timeout
+        | DisconnectKind::Http(_)
+        | DisconnectKind::Panicked
+        | DisconnectKind::Other => plan.delay = backoff.next_delay(),
+    }
+
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server_close(code: u16) -> DisconnectKind {
+        DisconnectKind::ServerClose(CloseInfo { code, reason: String::new() })
+    }
+
+    #[test]
+    fn test_conn_state_transitions() {
+        use ConnState::*;
+        let cycle = [Backoff, Connecting, Subscribing, Streaming, Draining, Backoff];
+        assert!(cycle.windows(2).all(|w| w[0].can_enter(w[1])));
+        // Failed connects and warm-ups drain without streaming
+        assert!(Connecting.can_enter(Draining) && Subscribing.can_enter(Draining));
+        for (from, to) in [(Backoff, Streaming), (Streaming, Connecting), (Draining, Connecting), (Streaming, Streaming)] {
+            assert!(!from.can_enter(to), "{:?} -> {:?}", from, to);
+        }
+        assert!(ConnState::ALL.iter().all(|&s| ConnState::from_index(s.index()) == s));
+    }
+
+    #[test]
+    fn test_plan_clean_close_immediate() {
+        let mut backoff = BackoffCalculator::new();
+        backoff.next_delay();
+        let plan = plan_reconnect(&server_close(1000), &mut backoff);
+        assert_eq!(plan.delay, Duration::ZERO);
+        assert!(!plan.counts_as_error);
+        assert_eq!(backoff.attempt, 0);
+
+        let plan = plan_reconnect(&DisconnectKind::ServerClose(CloseInfo::abnormal()), &mut backoff);
+        assert_eq!(plan.delay, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_plan_try_again_later_backs_off() {
+        let mut backoff = BackoffCalculator::new();
+        let first = plan_reconnect(&server_close(1013), &mut backoff);
+        let second = plan_reconnect(&server_close(1008), &mut backoff);
+        assert!(first.delay > Duration::ZERO);
+        assert!(second.delay > first.delay);
+        assert!(!second.counts_as_error);
+    }
+
+    #[test]
+    fn test_plan_rate_limited_honors_retry_after() {
+        let mut backoff = BackoffCalculator::new();
+        let kind = DisconnectKind::RateLimited { status: 429, retry_after: Some(Duration::from_secs(120)) };
+        let plan = plan_reconnect(&kind, &mut backoff);
+        assert_eq!(plan.delay, Duration::from_secs(120));
+        assert!(!plan.counts_as_error);
+
+        let kind = DisconnectKind::RateLimited { status: 418, retry_after: None };
+        assert_eq!(plan_reconnect(&kind, &mut backoff).delay, DEFAULT_418_BACKOFF);
+    }
+
+    #[test]
+    fn test_plan_requested_restart_immediate() {
+        let mut backoff = BackoffCalculator::new();
+        backoff.next_delay();
+        let plan = plan_reconnect(&DisconnectKind::Requested, &mut backoff);
+        assert_eq!(plan, ReconnectPlan { delay: Duration::ZERO, rotate_endpoint: false, counts_as_error: false });
+        assert_eq!(backoff.attempt, 0);
+    }
+
+    #[test]
+    fn test_plan_tls_rotates_and_backs_off() {
+        let mut backoff = BackoffCalculator::new();
+        let first = plan_reconnect(&DisconnectKind::Tls, &mut backoff);
+        let second = plan_reconnect(&DisconnectKind::Tls, &mut backoff);
+        assert!(first.rotate_endpoint && second.rotate_endpoint);
+        assert!(second.delay > first.delay);
+        assert!(first.counts_as_error);
+    }
+
+    #[test]
+    fn test_classify_http_rate_limit() {
+        let response = tungstenite::http::Response::builder()
+            .status(429)
+            .header("Retry-After", "30")
+            .body(None)
+            .unwrap();
+        let err = anyhow::Error::new(tungstenite::Error::Http(response));
+        assert_eq!(
+            classify(&err),
+            DisconnectKind::RateLimited { status: 429, retry_after: Some(Duration::from_secs(30)) }
+        );
+
+        let err = anyhow::Error::new(WsError::new(DisconnectKind::Dns, "lookup failed"));
+        assert_eq!(classify(&err), DisconnectKind::Dns);
+        assert_eq!(classify(&anyhow::anyhow!("other")), DisconnectKind::Other);
+    }
+
+    #[test]
+    fn test_reconnect_bucket() {
+        // 60/min: a token a second, 2 at once
+        let bucket = ReconnectBucket::new(60, 2);
+        let t0 = Instant::now();
+        assert_eq!(bucket.take_at(t0, false), Ok(()));
+        assert_eq!(bucket.take_at(t0, false), Ok(()));
+        let wait = bucket.take_at(t0, false).unwrap_err();
+        assert!(wait > Duration::from_millis(990) && wait <= Duration::from_secs(1), "{:?}", wait);
+        assert_eq!(bucket.take_at(t0 + Duration::from_millis(1_500), false), Ok(()));
+
+        // A waiting priority connection gets the next token
+        bucket.priority_waiting.fetch_add(1, Ordering::Relaxed);
+        let t1 = t0 + Duration::from_secs(3);
+        assert_eq!(bucket.take_at(t1, false), Err(MIN_BUCKET_WAIT));
+        assert_eq!(bucket.take_at(t1, true), Ok(()));
+        bucket.priority_waiting.fetch_sub(1, Ordering::Relaxed);
+        assert_eq!(bucket.take_at(t1, false), Ok(()));
+
+        // Refills stop at the burst
+        let t2 = t1 + Duration::from_secs(60);
+        assert_eq!(bucket.take_at(t2, false), Ok(()));
+        assert_eq!(bucket.take_at(t2, false), Ok(()));
+        assert!(bucket.take_at(t2, false).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_priority_wait_stops_yielding() {
+        let bucket = ReconnectBucket::new(1, 1);
+        bucket.acquire(false).await;
+        assert!(tokio::time::timeout(Duration::from_millis(20), bucket.acquire(true)).await.is_err());
+        assert_eq!(bucket.priority_waiting.load(Ordering::Relaxed), 0);
+    }
+}
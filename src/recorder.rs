@@ -0,0 +1,311 @@
+//! Quote recorder: compact binary capture of every written quote, and replay
+//!
+//! File layout (little-endian):
+//! - header: magic `QREC2\0\0\0`, source_id (u64), started_at_unix_us (u64) and
+//!   started_at_mono_us (i64), the monotonic clock at that instant (quote ts are monotonic)
+//! - price scales: a count (u64), then (symbol_id, scale) u64 pairs for the
+//!   symbols not at the default 1e8. `QREC1` recordings, from before scale
+//!   overrides, have no such section and are all at 1e8.
+//! - records: varint symbol_id, then zigzag varints of bid and ask minus the
+//!   previous bid/ask of the same symbol and ts minus the previous record's ts
+//!
+//! A typical update is 6-10 bytes against ~130 bytes of bookTicker JSON. Writes
+//! happen on a background thread; the hot path only queues the quote.
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::sync::{mpsc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::shm::QuoteSnapshot;
+
+pub const MAGIC: &[u8; 8] = b"QREC2\0\0\0";
+/// Recordings without the price scale section
+pub const MAGIC_V1: &[u8; 8] = b"QREC1\0\0\0";
+/// Default price scale (1e8)
+const PRICE_SCALE: u64 = 100_000_000;
+/// More scale entries than this is a corrupt header, not a symbol universe
+const MAX_SCALES: u64 = 1 << 20;
+/// Buffered records are flushed when the feed is quiet this long
+const FLUSH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Delta encoder state
+#[derive(Default)]
+pub struct Encoder {
+    /// Last (bid, ask) per symbol_id
+    prev: HashMap<u64, (i64, i64)>,
+    prev_ts: i64,
+}
+
+impl Encoder {
+    /// Append one record to `out`
+    pub fn encode(&mut self, quote: &QuoteSnapshot, out: &mut Vec<u8>) {
+        let (prev_bid, prev_ask) = self.prev.insert(quote.symbol_id, (quote.bid, quote.ask)).unwrap_or((0, 0));
+        put_varint(out, quote.symbol_id);
+        put_varint(out, zigzag(quote.bid.wrapping_sub(prev_bid)));
+        put_varint(out, zigzag(quote.ask.wrapping_sub(prev_ask)));
+        put_varint(out, zigzag(quote.ts.wrapping_sub(self.prev_ts)));
+        self.prev_ts = quote.ts;
+    }
+}
+
+#[inline]
+fn zigzag(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+#[inline]
+fn unzigzag(v: u64) -> i64 {
+    ((v >> 1) as i64) ^ -((v & 1) as i64)
+}
+
+#[inline]
+fn put_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+/// Records quotes to a file from a background thread
+pub struct Recorder {
+    /// Quotes to write; None asks the writer to flush and stop
+    tx: mpsc::Sender<Option<QuoteSnapshot>>,
+    /// Writer thread, until `finish` joins it
+    writer: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Recorder {
+    /// Create `path` (must not exist) and start the writer thread;
+    /// `price_scales` are the symbol_ids recorded at a scale other than 1e8
+    pub fn create(path: &str, source_id: u64, price_scales: &HashMap<u64, u64>) -> Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .with_context(|| format!("Failed to create recording {}", path))?;
+        let mut out = BufWriter::with_capacity(1 << 16, file);
+        out.write_all(MAGIC)?;
+        out.write_all(&source_id.to_le_bytes())?;
+        out.write_all(&crate::persist::unix_now_us().to_le_bytes())?;
+        out.write_all(&crate::platform::monotonic_us().to_le_bytes())?;
+        let mut scales: Vec<(u64, u64)> = price_scales.iter().map(|(&id, &scale)| (id, scale)).collect();
+        scales.sort_unstable();
+        out.write_all(&(scales.len() as u64).to_le_bytes())?;
+        for (symbol_id, scale) in scales {
+            out.write_all(&symbol_id.to_le_bytes())?;
+            out.write_all(&scale.to_le_bytes())?;
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let path = path.to_string();
+        let handle = std::thread::Builder::new()
+            .name("recorder".to_string())
+            .spawn(move || {
+                if let Err(e) = write_loop(rx, &mut out) {
+                    eprintln!("[REC] Recording to {} stopped: {}", path, e);
+                }
+            })
+            .context("Failed to spawn recorder thread")?;
+
+        Ok(Self { tx, writer: Mutex::new(Some(handle)) })
+    }
+
+    /// Queue a quote (never blocks; dropped if the writer has stopped)
+    #[inline]
+    pub fn record(&self, quote: QuoteSnapshot) {
+        let _ = self.tx.send(Some(quote));
+    }
+
+    /// Write out everything queued so far, flush the file and stop the writer
+    /// (for shutdown: handlers may still hold the recorder; later quotes are
+    /// dropped). Waits for the writer thread; a second call returns at once.
+    pub fn finish(&self) {
+        let Some(writer) = self.writer.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+            return;
+        };
+        let _ = self.tx.send(None);
+        let _ = writer.join();
+    }
+}
+
+fn write_loop(rx: mpsc::Receiver<Option<QuoteSnapshot>>, out: &mut BufWriter<File>) -> io::Result<()> {
+    let mut encoder = Encoder::default();
+    let mut buf = Vec::with_capacity(64);
+    loop {
+        match rx.recv_timeout(FLUSH_INTERVAL) {
+            Ok(Some(quote)) => {
+                buf.clear();
+                encoder.encode(&quote, &mut buf);
+                out.write_all(&buf)?;
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => out.flush()?,
+            Ok(None) | Err(mpsc::RecvTimeoutError::Disconnected) => return out.flush(),
+        }
+    }
+}
+
+/// Replays a recording as QuoteSnapshots
+///
+/// A record cut short at the end of the file (writer killed mid-flush) ends
+/// the iteration like a clean EOF.
+pub struct RecordReader<R> {
+    input: R,
+    pub source_id: u64,
+    pub started_at_unix_us: u64,
+    pub started_at_mono_us: i64,
+    /// Symbols recorded at a scale other than 1e8
+    price_scales: HashMap<u64, u64>,
+    prev: HashMap<u64, (i64, i64)>,
+    prev_ts: i64,
+}
+
+impl RecordReader<BufReader<File>> {
+    pub fn open(path: &str) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Failed to open recording {}", path))?;
+        Self::new(BufReader::with_capacity(1 << 16, file))
+    }
+}
+
+impl<R: Read> RecordReader<R> {
+    pub fn new(mut input: R) -> Result<Self> {
+        let mut header = [0u8; 32];
+        input.read_exact(&mut header).context("Recording header truncated")?;
+        let mut price_scales = HashMap::new();
+        if &header[..8] == MAGIC {
+            let mut word = [0u8; 8];
+            input.read_exact(&mut word).context("Recording header truncated")?;
+            let n_scales = u64::from_le_bytes(word);
+            if n_scales > MAX_SCALES {
+                bail!("Recording header lists {} price scales", n_scales);
+            }
+            for _ in 0..n_scales {
+                let mut entry = [0u8; 16];
+                input.read_exact(&mut entry).context("Recording header truncated")?;
+                let symbol_id = u64::from_le_bytes(entry[..8].try_into().unwrap());
+                price_scales.insert(symbol_id, u64::from_le_bytes(entry[8..].try_into().unwrap()));
+            }
+        } else if &header[..8] != MAGIC_V1 {
+            bail!("Not a quote recording (bad magic)");
+        }
+        Ok(Self {
+            input,
+            source_id: u64::from_le_bytes(header[8..16].try_into().unwrap()),
+            started_at_unix_us: u64::from_le_bytes(header[16..24].try_into().unwrap()),
+            started_at_mono_us: i64::from_le_bytes(header[24..32].try_into().unwrap()),
+            price_scales,
+            prev: HashMap::new(),
+            prev_ts: 0,
+        })
+    }
+
+    /// Scale of `symbol_id`'s recorded prices
+    pub fn price_scale(&self, symbol_id: u64) -> u64 {
+        self.price_scales.get(&symbol_id).copied().unwrap_or(PRICE_SCALE)
+    }
+
+    /// Next varint; Ok(None) on EOF
+    fn varint(&mut self) -> io::Result<Option<u64>> {
+        let mut v = 0u64;
+        let mut byte = [0u8; 1];
+        for shift in (0..64).step_by(7) {
+            if self.input.read(&mut byte)? == 0 {
+                return Ok(None);
+            }
+            v |= ((byte[0] & 0x7f) as u64) << shift;
+            if byte[0] & 0x80 == 0 {
+                return Ok(Some(v));
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::InvalidData, "varint too long"))
+    }
+
+    fn next_record(&mut self) -> io::Result<Option<QuoteSnapshot>> {
+        let Some(symbol_id) = self.varint()? else {
+            return Ok(None);
+        };
+        let (Some(bid), Some(ask), Some(ts)) = (self.varint()?, self.varint()?, self.varint()?) else {
+            return Ok(None);
+        };
+        let prev = self.prev.entry(symbol_id).or_insert((0, 0));
+        prev.0 = prev.0.wrapping_add(unzigzag(bid));
+        prev.1 = prev.1.wrapping_add(unzigzag(ask));
+        self.prev_ts = self.prev_ts.wrapping_add(unzigzag(ts));
+        Ok(Some(QuoteSnapshot { source_id: self.source_id, symbol_id, bid: prev.0, ask: prev.1, ts: self.prev_ts }))
+    }
+}
+
+impl<R: Read> Iterator for RecordReader<R> {
+    type Item = io::Result<QuoteSnapshot>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(symbol_id: u64, bid: i64, ask: i64, ts: i64) -> QuoteSnapshot {
+        QuoteSnapshot { source_id: 1, symbol_id, bid, ask, ts }
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let path = std::env::temp_dir().join(format!("test_recorder_{}.qrec", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let quotes = vec![
+            quote(1, 6_500_012_000_000, 6_500_013_000_000, 1_000_000),
+            quote(2, 350_001_000_000, 350_002_000_000, 1_000_150),
+            quote(1, 6_500_011_000_000, 6_500_013_000_000, 1_000_200),
+            quote(1, -5, i64::MAX, 999_000),
+        ];
+        // Finished while still shared (as with the handler holding it at shutdown)
+        let recorder = std::sync::Arc::new(Recorder::create(path, 1, &HashMap::from([(2, 10_000)])).unwrap());
+        let handler = recorder.clone();
+        for q in &quotes {
+            handler.record(*q);
+        }
+        recorder.finish();
+        recorder.finish();
+        handler.record(quote(3, 1, 2, 2_000_000));
+        assert!(Recorder::create(path, 1, &HashMap::new()).is_err());
+
+        let reader = RecordReader::open(path).unwrap();
+        assert_eq!(reader.source_id, 1);
+        assert_eq!((reader.price_scale(1), reader.price_scale(2)), (100_000_000, 10_000));
+        assert!(reader.started_at_unix_us > 0);
+        let replayed: Vec<_> = reader.map(|q| q.unwrap()).collect();
+        assert_eq!(replayed, quotes);
+
+        // A repeated update of a liquid symbol is a handful of bytes
+        let mut encoder = Encoder::default();
+        let mut buf = Vec::new();
+        encoder.encode(&quotes[0], &mut buf);
+        buf.clear();
+        encoder.encode(&quotes[2], &mut buf);
+        assert!(buf.len() <= 8, "{} bytes", buf.len());
+
+        // Truncated tail ends the replay cleanly
+        let bytes = std::fs::read(path).unwrap();
+        let reader = RecordReader::new(&bytes[..bytes.len() - 1]).unwrap();
+        assert_eq!(reader.count(), quotes.len() - 1);
+
+        // QREC1 recordings have no scale section: everything at 1e8
+        let mut v1 = MAGIC_V1.to_vec();
+        v1.extend_from_slice(&bytes[8..32]);
+        v1.extend_from_slice(&bytes[32 + 8 + 16..]);
+        let reader = RecordReader::new(&v1[..]).unwrap();
+        assert_eq!(reader.price_scale(2), 100_000_000);
+        assert_eq!(reader.map(|q| q.unwrap()).collect::<Vec<_>>(), quotes);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
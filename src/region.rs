@@ -0,0 +1,95 @@
+//! Region files: the memory-mapped side files next to the quotes file
+//! (history ring, liquidation event ring, reader registry, depth region,
+//! sequence region)
+//!
+//! Each starts with an 8-byte magic and little-endian u64 fields, zero-padded
+//! to its header size; what follows is the owning module's. A file is created
+//! whole (sized, header written, mode and group applied, removed again if
+//! that fails) and mapped read-write; the owner validates its own fields.
+
+use std::fs::OpenOptions;
+
+use anyhow::{bail, Context, Result};
+use memmap2::MmapMut;
+
+/// What the files of one kind start with
+pub struct RegionFormat {
+    /// Names the region in errors, e.g. "history ring"
+    pub kind: &'static str,
+    pub magic: &'static [u8; 8],
+    pub header_size: u64,
+}
+
+impl RegionFormat {
+    /// Create `path` (must not exist), `len` bytes long, with `fields` after
+    /// the magic; `mode`/`group` as for the quotes file
+    pub fn create(&self, path: &str, fields: &[u64], len: u64, mode: u32, group: Option<&str>) -> Result<()> {
+        let mut header = vec![0u8; self.header_size as usize];
+        header[0..8].copy_from_slice(self.magic);
+        for (i, v) in fields.iter().enumerate() {
+            header[8 + i * 8..16 + i * 8].copy_from_slice(&v.to_le_bytes());
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .with_context(|| format!("Failed to create {}: {}", self.kind, path))?;
+        file.set_len(len).with_context(|| format!("Failed to size {}", self.kind))?;
+        std::io::Write::write_all(&mut file, &header).with_context(|| format!("Failed to write {} header", self.kind))?;
+        drop(file);
+
+        let permissions = crate::platform::set_file_mode(path, mode)
+            .and_then(|_| group.map_or(Ok(()), |g| crate::platform::set_file_group(path, g)));
+        if let Err(e) = permissions {
+            let _ = std::fs::remove_file(path);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Map an existing file read-write, checking its magic
+    pub fn open(&self, path: &str) -> Result<MmapMut> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {}: {}", self.kind, path))?;
+        let mmap = unsafe { MmapMut::map_mut(&file) }.with_context(|| format!("Failed to map {}: {}", self.kind, path))?;
+
+        if mmap.len() < self.header_size as usize || &mmap[0..8] != self.magic {
+            bail!("{} has no {} header (bad magic)", path, self.kind);
+        }
+        Ok(mmap)
+    }
+}
+
+/// Header field `i`, counting from the one after the magic
+pub fn field(header: &[u8], i: usize) -> u64 {
+    u64::from_le_bytes(header[8 + i * 8..16 + i * 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMAT: RegionFormat = RegionFormat { kind: "test region", magic: b"TREG1\0\0\0", header_size: 64 };
+
+    #[test]
+    fn test_create_and_open() {
+        let path = std::env::temp_dir().join(format!("test_region_{}.dat", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        FORMAT.create(path, &[1, 42], 64 + 128, 0o600, None).unwrap();
+        assert!(FORMAT.create(path, &[1, 42], 64, 0o600, None).is_err(), "never overwrites");
+        let mmap = FORMAT.open(path).unwrap();
+        assert_eq!((mmap.len(), field(&mmap, 0), field(&mmap, 1), field(&mmap, 2)), (192, 1, 42, 0));
+
+        std::fs::write(path, b"garbage").unwrap();
+        let err = FORMAT.open(path).unwrap_err();
+        assert!(err.to_string().contains("no test region header"), "{}", err);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,229 @@
+//! Subscribe list reload without a restart
+//!
+//! On SIGHUP or the admin `reload` command the subscribe file is read again
+//! and diffed against the running main feed: removed symbols are
+//! unsubscribed, added ones get a slot and are subscribed, each on the
+//! connections the change touches only (see `WsManager::resubscribe`).
+//!
+//! The hot path keeps its startup symbol map; symbols first subscribed by a
+//! reload are looked up in `AddedSymbols` only when that map misses. A
+//! removed symbol keeps its slot, which goes stale. Priority feed symbols,
+//! per-symbol scales and the REST pollers' symbol lists still need a restart.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use anyhow::{bail, Context, Result};
+
+use crate::shm::{Quote64, ShmManager};
+use crate::symbols::{self, SubscribeDiff};
+use crate::writer::WriterToken;
+use crate::ws::WsManager;
+
+/// Symbols first subscribed after startup, each with the claim of the
+/// reload that added it
+pub struct AddedSymbols {
+    shm: Arc<ShmManager>,
+    source_id: u64,
+    ids: RwLock<HashMap<String, (u64, Arc<WriterToken>)>>,
+}
+
+impl AddedSymbols {
+    pub fn new(shm: Arc<ShmManager>, source_id: u64) -> Self {
+        Self { shm, source_id, ids: RwLock::default() }
+    }
+
+    /// symbol_id and slot of an added symbol, while its token holds the slot
+    pub fn get(&self, symbol: &str) -> Option<(u64, &Quote64)> {
+        let ids = self.ids.read().unwrap_or_else(|e| e.into_inner());
+        let (symbol_id, token) = ids.get(symbol)?;
+        if !token.holds(*symbol_id) {
+            return None;
+        }
+        Some((*symbol_id, self.shm.get_slot(self.source_id, *symbol_id).ok()?))
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn symbol_for(&self, symbol_id: u64) -> Option<String> {
+        let ids = self.ids.read().unwrap_or_else(|e| e.into_inner());
+        ids.iter().find(|(_, (id, _))| *id == symbol_id).map(|(symbol, _)| symbol.clone())
+    }
+}
+
+/// Re-reads the subscribe file and applies the difference to the main feed
+pub struct Reloader {
+    subscribe_file: String,
+    symbols_tsv: String,
+    /// Slots claimed by the feed at startup
+    startup_ids: Arc<HashMap<String, u64>>,
+    added: Arc<AddedSymbols>,
+    /// On the priority feed, which reloads leave alone
+    priority: Vec<String>,
+    manager: Arc<WsManager>,
+    /// Main feed symbols as of the last reload
+    current: Mutex<Vec<String>>,
+}
+
+impl Reloader {
+    pub fn new(
+        subscribe_file: &str,
+        symbols_tsv: &str,
+        startup_ids: Arc<HashMap<String, u64>>,
+        added: Arc<AddedSymbols>,
+        priority: Vec<String>,
+        manager: Arc<WsManager>,
+        current: Vec<String>,
+    ) -> Self {
+        Self {
+            subscribe_file: subscribe_file.to_string(),
+            symbols_tsv: symbols_tsv.to_string(),
+            startup_ids,
+            added,
+            priority,
+            manager,
+            current: Mutex::new(current),
+        }
+    }
+
+    /// Reload the subscribe file; returns the change applied
+    pub fn reload(&self) -> Result<SubscribeDiff> {
+        let list = symbols::load_subscribe_list(&self.subscribe_file)?;
+        let (_, list, dropped) = symbols::partition_priority(&list, &self.priority);
+        if !dropped.is_empty() {
+            eprintln!("[RELOAD] WARNING: priority symbols stay subscribed until a restart: {}", dropped.join(", "));
+        }
+
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        let diff = SubscribeDiff::between(&current, &list);
+        if diff.is_empty() {
+            eprintln!("[RELOAD] Subscribe list unchanged ({} symbols)", list.len());
+            return Ok(diff);
+        }
+
+        // Slots for symbols never subscribed before; registered before the
+        // SUBSCRIBE goes out so their first frame finds them
+        let new: Vec<&String> = diff
+            .added
+            .iter()
+            .filter(|s| !self.startup_ids.contains_key(*s) && self.added.get(s).is_none())
+            .collect();
+        self.add_slots(&new)?;
+        match self.manager.resubscribe(&diff) {
+            Ok(changed) => {
+                eprintln!("[RELOAD] {} ({} symbols, {} connections changed)", diff, list.len(), changed.len());
+                *current = list;
+                Ok(diff)
+            }
+            Err(e) => {
+                // Dropping the last reference to the token releases the claims
+                self.added.ids.write().unwrap_or_else(|e| e.into_inner()).retain(|s, _| !new.contains(&s));
+                Err(e)
+            }
+        }
+    }
+
+    /// Reload on every SIGHUP
+    #[cfg(unix)]
+    pub fn spawn_on_sighup(self: &Arc<Self>) -> Result<tokio::task::JoinHandle<()>> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangups = signal(SignalKind::hangup()).context("Failed to install SIGHUP handler")?;
+        let reloader = self.clone();
+        Ok(tokio::spawn(async move {
+            while hangups.recv().await.is_some() {
+                eprintln!("[RELOAD] SIGHUP: reloading {}", reloader.subscribe_file);
+                if let Err(e) = reloader.reload() {
+                    eprintln!("[RELOAD] Failed, subscriptions unchanged: {:#}", e);
+                }
+            }
+        }))
+    }
+
+    /// Claim and initialize slots for `new` and register them as added
+    fn add_slots(&self, new: &[&String]) -> Result<()> {
+        if new.is_empty() {
+            return Ok(());
+        }
+        let symbol_info = symbols::load_symbols_info(&self.symbols_tsv).context("Failed to load symbols.tsv")?;
+        let mut ids = Vec::new();
+        for &symbol in new {
+            let Some(info) = symbol_info.get(symbol) else {
+                bail!("Symbol {} from subscribe list not found in symbols.tsv", symbol);
+            };
+            let taken = self.startup_ids.iter().find(|(_, &id)| id == info.symbol_id).map(|(s, _)| s.clone())
+                .or_else(|| self.added.symbol_for(info.symbol_id));
+            if let Some(other) = taken {
+                bail!("Symbols {} and {} share symbol_id {} in symbols.tsv", other, symbol, info.symbol_id);
+            }
+            ids.push(info.symbol_id);
+        }
+
+        let token = Arc::new(WriterToken::claim(self.added.shm.clone(), "feed", self.added.source_id, &ids)?);
+        for &symbol_id in &ids {
+            self.added.shm.init_slot(self.added.source_id, symbol_id)?;
+        }
+        let mut added = self.added.ids.write().unwrap_or_else(|e| e.into_inner());
+        added.extend(new.iter().map(|s| s.to_string()).zip(ids.into_iter().map(|id| (id, token.clone()))));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WsConfig;
+    use crate::ws::{MessageHandler, PerfStats};
+
+    #[test]
+    fn test_reload_diff() {
+        let dir = std::env::temp_dir().join(format!("test_reload_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        let (subscribe, tsv) = (file("subscribe.txt"), file("symbols.tsv"));
+        std::fs::write(&tsv, "0\tBTCUSDT\n1\tETHUSDT\n2\tSOLUSDT\n3\tDUPUSDT\n9\tFARUSDT\n").unwrap();
+        std::fs::write(&subscribe, "BTCUSDT\nETHUSDT\n").unwrap();
+        let shm = Arc::new(ShmManager::anonymous(2, 4).unwrap());
+
+        let startup: HashMap<String, u64> = [("BTCUSDT".to_string(), 0), ("ETHUSDT".to_string(), 1)].into();
+        let _feed = WriterToken::claim(shm.clone(), "feed", 1, &[0, 1]).unwrap();
+        let added = Arc::new(AddedSymbols::new(shm.clone(), 1));
+        let handler: MessageHandler = Arc::new(|_| {});
+        let symbols = vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()];
+        let manager = Arc::new(WsManager::new(symbols.clone(), handler, WsConfig::default(), Arc::new(PerfStats::new())));
+        let reloader = Reloader::new(&subscribe, &tsv, Arc::new(startup), added.clone(), Vec::new(), manager.clone(), symbols);
+
+        assert!(reloader.reload().unwrap().is_empty());
+
+        // SOLUSDT gets its slot, ETHUSDT is unsubscribed but keeps its own
+        std::fs::write(&subscribe, "SOLUSDT\nBTCUSDT\n").unwrap();
+        assert_eq!(reloader.reload().unwrap().to_string(), "+SOLUSDT -ETHUSDT");
+        let (symbol_id, slot) = added.get("SOLUSDT").unwrap();
+        assert_eq!((symbol_id, slot.symbol_id(), added.len()), (2, 2, 1));
+        assert_eq!(shm.writer_claims().holder(1, 2), Some("feed"));
+        assert_eq!(manager.restart_handles()[0].symbols(), vec!["BTCUSDT".to_string(), "SOLUSDT".to_string()]);
+
+        // Re-adding a startup symbol only resubscribes it
+        std::fs::write(&subscribe, "SOLUSDT\nBTCUSDT\nETHUSDT\n").unwrap();
+        assert_eq!(reloader.reload().unwrap().to_string(), "+ETHUSDT");
+        assert_eq!(added.len(), 1);
+
+        // Failures change nothing: an id outside the SHM, then a claimed slot
+        std::fs::write(&subscribe, "SOLUSDT\nBTCUSDT\nETHUSDT\nFARUSDT\n").unwrap();
+        assert!(reloader.reload().is_err());
+        let _other = WriterToken::claim(shm.clone(), "synthetic", 1, &[3]).unwrap();
+        std::fs::write(&subscribe, "SOLUSDT\nBTCUSDT\nETHUSDT\nDUPUSDT\n").unwrap();
+        let err = reloader.reload().unwrap_err();
+        assert!(err.to_string().contains("already written by synthetic"), "{}", err);
+        assert!(added.get("DUPUSDT").is_none());
+        assert_eq!(manager.restart_handles()[0].symbols().len(), 3);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
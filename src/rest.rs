@@ -0,0 +1,280 @@
+//! Minimal HTTP/1.1 client for the venue's REST API
+//!
+//! One keep-alive connection (TLS for https://), one request at a time, which
+//! is all the pollers need. Handles Content-Length and chunked bodies. GETs,
+//! plus POSTs for the trace exporter (see crate::trace).
+
+use anyhow::{anyhow, bail, Context, Result};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+use tokio_tungstenite::tungstenite::http::Uri;
+use tokio_tungstenite::MaybeTlsStream;
+
+/// Upper bound on a response body (futures exchangeInfo runs over 1 MB)
+const MAX_BODY: usize = 8 << 20;
+
+pub struct RestClient {
+    host: String,
+    port: u16,
+    tls: bool,
+    timeout: Duration,
+    conn: Option<BufReader<MaybeTlsStream<TcpStream>>>,
+}
+
+/// Status and body of a response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+    /// Retry-After header in seconds, if present
+    pub retry_after_secs: Option<u64>,
+}
+
+impl RestClient {
+    /// Client for `base_url` (e.g. https://fapi.binance.com), each request bounded by `timeout`
+    pub fn new(base_url: &str, timeout: Duration) -> Result<Self> {
+        let uri: Uri = base_url.parse().with_context(|| format!("Invalid REST URL: {}", base_url))?;
+        let tls = match uri.scheme_str() {
+            Some("https") => true,
+            Some("http") => false,
+            _ => bail!("REST URL must be http:// or https://: {}", base_url),
+        };
+        let host = uri.host().ok_or_else(|| anyhow!("REST URL has no host: {}", base_url))?.to_string();
+        let port = uri.port_u16().unwrap_or(if tls { 443 } else { 80 });
+        Ok(Self { host, port, tls, timeout, conn: None })
+    }
+
+    /// GET `path` (with query string); a dropped keep-alive connection is retried once
+    pub async fn get(&mut self, path: &str) -> Result<Response> {
+        self.send("GET", path, None).await
+    }
+
+    /// POST `body` as `content_type` to `path`; not retried, since the server
+    /// may have acted on it before a reused connection dropped
+    pub async fn post(&mut self, path: &str, content_type: &str, body: &[u8]) -> Result<Response> {
+        self.send("POST", path, Some((content_type, body))).await
+    }
+
+    async fn send(&mut self, method: &str, path: &str, body: Option<(&str, &[u8])>) -> Result<Response> {
+        // Only an idempotent GET is safe to send again
+        let retry = self.conn.is_some() && method == "GET";
+        match timeout(self.timeout, self.request(method, path, body)).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) if retry => {
+                self.conn = None;
+                timeout(self.timeout, self.request(method, path, body)).await.context("REST request timed out")?
+            }
+            Ok(Err(e)) => {
+                self.conn = None;
+                Err(e)
+            }
+            Err(_) => {
+                self.conn = None;
+                bail!("REST request to {} timed out after {:?}", self.host, self.timeout)
+            }
+        }
+    }
+
+    async fn connect(&self) -> Result<BufReader<MaybeTlsStream<TcpStream>>> {
+        let tcp = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .with_context(|| format!("Failed to connect to {}:{}", self.host, self.port))?;
+        tcp.set_nodelay(true)?;
+        let stream = if self.tls {
+            let connector = tokio_native_tls::TlsConnector::from(native_tls::TlsConnector::new()?);
+            let tls = connector.connect(&self.host, tcp).await
+                .with_context(|| format!("TLS handshake with {} failed", self.host))?;
+            MaybeTlsStream::NativeTls(tls)
+        } else {
+            MaybeTlsStream::Plain(tcp)
+        };
+        Ok(BufReader::new(stream))
+    }
+
+    async fn request(&mut self, method: &str, path: &str, body: Option<(&str, &[u8])>) -> Result<Response> {
+        if self.conn.is_none() {
+            self.conn = Some(self.connect().await?);
+        }
+        let conn = self.conn.as_mut().expect("connected above");
+
+        let mut request = format!("{} {} HTTP/1.1\r\nHost: {}\r\nAccept: application/json\r\n", method, path, self.host);
+        if let Some((content_type, body)) = body {
+            request.push_str(&format!("Content-Type: {}\r\nContent-Length: {}\r\n", content_type, body.len()));
+        }
+        request.push_str("\r\n");
+        let mut request = request.into_bytes();
+        if let Some((_, body)) = body {
+            request.extend_from_slice(body);
+        }
+        conn.get_mut().write_all(&request).await?;
+
+        let mut line = String::new();
+        conn.read_line(&mut line).await?;
+        let status: u16 = line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| anyhow!("Bad HTTP status line: {:?}", line.trim_end()))?;
+
+        let (mut content_length, mut chunked, mut close, mut retry_after_secs) = (None, false, false, None);
+        loop {
+            line.clear();
+            if conn.read_line(&mut line).await? == 0 {
+                bail!("Connection closed in HTTP headers");
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            let Some((name, value)) = header.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => content_length = value.parse::<usize>().ok(),
+                "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+                "connection" => close = value.eq_ignore_ascii_case("close"),
+                "retry-after" => retry_after_secs = value.parse().ok(),
+                _ => {}
+            }
+        }
+
+        let body = if chunked {
+            read_chunked(conn).await?
+        } else if let Some(len) = content_length {
+            if len > MAX_BODY {
+                bail!("HTTP body too large ({} bytes)", len);
+            }
+            let mut body = vec![0u8; len];
+            conn.read_exact(&mut body).await?;
+            body
+        } else {
+            // Body runs to connection close
+            close = true;
+            let mut body = Vec::new();
+            conn.take(MAX_BODY as u64).read_to_end(&mut body).await?;
+            body
+        };
+
+        if close {
+            self.conn = None;
+        }
+        Ok(Response { status, body: String::from_utf8(body).context("HTTP body is not UTF-8")?, retry_after_secs })
+    }
+}
+
+async fn read_chunked<R: tokio::io::AsyncBufRead + Unpin>(conn: &mut R) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        conn.read_line(&mut line).await?;
+        let size_field = line.trim_end().split(';').next().unwrap_or("");
+        let size = usize::from_str_radix(size_field.trim(), 16)
+            .map_err(|_| anyhow!("Bad chunk size: {:?}", line.trim_end()))?;
+        if body.len() + size > MAX_BODY {
+            bail!("HTTP body too large");
+        }
+        if size == 0 {
+            // Trailers up to the blank line
+            loop {
+                line.clear();
+                if conn.read_line(&mut line).await? == 0 || line.trim_end().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        conn.read_exact(&mut body[start..]).await?;
+        line.clear();
+        conn.read_line(&mut line).await?; // CRLF after the chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_keep_alive_content_length_and_chunked() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            // Both responses on one connection, so the client must reuse it
+            let (sock, _) = listener.accept().await.unwrap();
+            let mut sock = BufReader::new(sock);
+            let mut requests = Vec::new();
+            for response in [
+                "HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\n{\"a\":\"one\"}",
+                "HTTP/1.1 429 Too Many Requests\r\nTransfer-Encoding: chunked\r\nRetry-After: 7\r\n\r\n4\r\n{\"b\"\r\n3\r\n:2}\r\n0\r\n\r\n",
+            ] {
+                let mut request = String::new();
+                loop {
+                    let mut line = String::new();
+                    sock.read_line(&mut line).await.unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    request.push_str(&line);
+                }
+                requests.push(request.lines().next().unwrap().to_string());
+                sock.get_mut().write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+
+        let mut client = RestClient::new(&format!("http://{}", addr), Duration::from_secs(5)).unwrap();
+        let first = client.get("/one?x=1").await.unwrap();
+        assert_eq!((first.status, first.body.as_str()), (200, r#"{"a":"one"}"#));
+        let second = client.get("/two").await.unwrap();
+        assert_eq!(second, Response { status: 429, body: r#"{"b":2}"#.to_string(), retry_after_secs: Some(7) });
+
+        assert_eq!(server.await.unwrap(), vec!["GET /one?x=1 HTTP/1.1", "GET /two HTTP/1.1"]);
+    }
+
+    #[tokio::test]
+    async fn test_only_get_retried_on_dropped_connection() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            // Each connection answers one request, then drops on reading the next
+            let mut requests = Vec::new();
+            for _ in 0..3 {
+                let (sock, _) = listener.accept().await.unwrap();
+                let mut sock = BufReader::new(sock);
+                for answer in [true, false] {
+                    let mut line = String::new();
+                    if sock.read_line(&mut line).await.unwrap() == 0 {
+                        break;
+                    }
+                    requests.push(line.trim_end().to_string());
+                    while line != "\r\n" {
+                        line.clear();
+                        sock.read_line(&mut line).await.unwrap();
+                    }
+                    if answer {
+                        sock.get_mut().write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}").await.unwrap();
+                    }
+                }
+            }
+            requests
+        });
+
+        let mut client = RestClient::new(&format!("http://{}", addr), Duration::from_secs(5)).unwrap();
+        client.get("/a").await.unwrap();
+        // The server may have acted on a POST before the connection dropped
+        assert!(client.post("/b", "application/json", b"{}").await.is_err());
+        client.get("/c").await.unwrap();
+        // A GET is sent again on a new connection
+        assert_eq!(client.get("/d").await.unwrap().status, 200);
+        drop(client);
+
+        assert_eq!(server.await.unwrap(), vec![
+            "GET /a HTTP/1.1", "POST /b HTTP/1.1",
+            "GET /c HTTP/1.1", "GET /d HTTP/1.1",
+            "GET /d HTTP/1.1",
+        ]);
+    }
+}
@@ -0,0 +1,45 @@
+//! Seqlock ordering primitives shared by Quote64 and the loom model test
+//!
+//! Writer: `write_begin` (seq -> odd) ... data stores ... `write_end` (seq -> even).
+//! Reader: `read_begin` (Acquire, None if odd) ... data loads ... `read_end`
+//! (Acquire fence, then re-check seq). With the `loom` feature the atomics are
+//! loom's, so tests/loom_seqlock.rs can check every interleaving.
+
+#[cfg(feature = "loom")]
+pub use loom::sync::atomic::{fence, AtomicU64, Ordering};
+#[cfg(not(feature = "loom"))]
+pub use std::sync::atomic::{fence, AtomicU64, Ordering};
+
+/// Mark the record as "writing" (odd). Returns the previous even seq.
+#[inline(always)]
+pub fn write_begin(seq: &AtomicU64) -> u64 {
+    let seq0 = seq.load(Ordering::Relaxed);
+    seq.store(seq0.wrapping_add(1), Ordering::Relaxed);
+    // Data stores below must not become visible before the odd seq
+    fence(Ordering::Release);
+    seq0
+}
+
+/// Mark the record as "complete" (even), publishing the data stores
+#[inline(always)]
+pub fn write_end(seq: &AtomicU64, seq0: u64) {
+    seq.store(seq0.wrapping_add(2), Ordering::Release);
+}
+
+/// Start a read. Returns None if a write is in progress.
+#[inline(always)]
+pub fn read_begin(seq: &AtomicU64) -> Option<u64> {
+    let s1 = seq.load(Ordering::Acquire);
+    if (s1 & 1) == 1 {
+        return None;
+    }
+    Some(s1)
+}
+
+/// Finish a read. Returns true if the data loaded since `read_begin` is consistent.
+#[inline(always)]
+pub fn read_end(seq: &AtomicU64, s1: u64) -> bool {
+    // Data loads above must complete before re-checking seq
+    fence(Ordering::Acquire);
+    seq.load(Ordering::Relaxed) == s1
+}
@@ -0,0 +1,189 @@
+//! Exchange sequence sidecar: each quote slot's bookTicker updateId
+//!
+//! `Quote64` has no free bytes, so the exchange's own sequence (`u`) goes to
+//! a separate file with one record per quote slot, written right after the
+//! slot. Readers use it to deduplicate and order quotes and to join them with
+//! other data keyed by updateId (depth events, trade captures).
+//!
+//! Region file layout (little-endian): 64-byte header (magic `QSEQ1\0\0\0`,
+//! version (1), record_size (32), n_sources, n_symbols), then one `Seq32` per
+//! slot at the quotes file's index (`source_id * n_symbols + symbol_id`), each
+//! under its own seqlock. Its `ts` is the `ts` of the quote it belongs to:
+//! a reader joins the two by reading the slot, then the record, and retrying
+//! while the `ts` differ (the writer is between the two writes).
+
+use anyhow::{bail, Result};
+use memmap2::MmapMut;
+
+use crate::region::{self, RegionFormat};
+use crate::seqlock::{self, AtomicU64};
+
+const MAGIC: &[u8; 8] = b"QSEQ1\0\0\0";
+const VERSION: u64 = 1;
+const HEADER_SIZE: u64 = 64;
+const FORMAT: RegionFormat = RegionFormat { kind: "sequence region", magic: MAGIC, header_size: HEADER_SIZE };
+const RECORD_SIZE: u64 = 32;
+
+/// Sequence record (32 bytes, two per cache line)
+#[repr(C, align(32))]
+pub struct Seq32 {
+    pub seq: AtomicU64,
+    /// bookTicker `u` of the quote (0 if the frame had none)
+    pub update_id: u64,
+    /// Exchange event time of the quote (ms since epoch, 0 if absent)
+    pub event_time_ms: u64,
+    /// `ts` of the quote in the slot (monotonic µs)
+    pub ts: i64,
+}
+
+#[cfg(not(feature = "loom"))]
+const _: () = assert!(std::mem::size_of::<Seq32>() == RECORD_SIZE as usize);
+
+/// Copy of one slot's sequence record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceSnapshot {
+    pub update_id: u64,
+    pub event_time_ms: u64,
+    pub ts: i64,
+}
+
+/// Per-slot exchange sequence in a memory-mapped file
+pub struct SequenceRegion {
+    _mmap: MmapMut,
+    records: *mut Seq32,
+    n_sources: u64,
+    n_symbols: u64,
+}
+
+// SAFETY: the mapping lives as long as the region; records are seqlock-protected
+unsafe impl Send for SequenceRegion {}
+unsafe impl Sync for SequenceRegion {}
+
+impl SequenceRegion {
+    /// Open `path`, creating it for the quotes file's `n_sources` x `n_symbols`
+    /// slots (mode/group as for the quotes file) if it doesn't exist
+    pub fn open_or_create(path: &str, n_sources: u64, n_symbols: u64, mode: u32, group: Option<&str>) -> Result<Self> {
+        if !std::path::Path::new(path).exists() {
+            Self::create(path, n_sources, n_symbols, mode, group)?;
+        }
+        let region = Self::open(path)?;
+        if (region.n_sources, region.n_symbols) != (n_sources, n_symbols) {
+            bail!("Sequence region {} is {}x{} slots, the quotes file {}x{}",
+                  path, region.n_sources, region.n_symbols, n_sources, n_symbols);
+        }
+        Ok(region)
+    }
+
+    fn create(path: &str, n_sources: u64, n_symbols: u64, mode: u32, group: Option<&str>) -> Result<()> {
+        if n_sources == 0 || n_symbols == 0 {
+            bail!("Sequence region needs slots (got {} sources, {} symbols)", n_sources, n_symbols);
+        }
+        let fields = [VERSION, RECORD_SIZE, n_sources, n_symbols];
+        FORMAT.create(path, &fields, HEADER_SIZE + n_sources * n_symbols * RECORD_SIZE, mode, group)?;
+        eprintln!("[SEQUENCE] Created sequence region {} ({}x{} slots)", path, n_sources, n_symbols);
+        Ok(())
+    }
+
+    /// Map an existing region read-write, validating its header
+    pub fn open(path: &str) -> Result<Self> {
+        let mut mmap = FORMAT.open(path)?;
+        let field = |i: usize| region::field(&mmap, i);
+        let (version, record_size, n_sources, n_symbols) = (field(0), field(1), field(2), field(3));
+        if version != VERSION || record_size != RECORD_SIZE {
+            bail!("Unsupported sequence region {}: version {}, record size {}", path, version, record_size);
+        }
+        if mmap.len() as u64 != HEADER_SIZE + n_sources * n_symbols * RECORD_SIZE {
+            bail!("Sequence region {} size {} doesn't match {}x{} slots", path, mmap.len(), n_sources, n_symbols);
+        }
+
+        Ok(Self {
+            records: unsafe { mmap.as_mut_ptr().add(HEADER_SIZE as usize) } as *mut Seq32,
+            n_sources,
+            n_symbols,
+            _mmap: mmap,
+        })
+    }
+
+    fn record(&self, source_id: u64, symbol_id: u64) -> Result<*mut Seq32> {
+        if source_id >= self.n_sources || symbol_id >= self.n_symbols {
+            bail!("Slot {}/{} outside the sequence region ({}x{})", source_id, symbol_id, self.n_sources, self.n_symbols);
+        }
+        Ok(unsafe { self.records.add((source_id * self.n_symbols + symbol_id) as usize) })
+    }
+
+    /// Record the sequence of the quote just written to the slot (one writer per slot)
+    pub fn write(&self, source_id: u64, symbol_id: u64, update_id: u64, event_time_ms: u64, ts: i64) -> Result<()> {
+        let record = self.record(source_id, symbol_id)?;
+
+        // SAFETY: one writer per slot; readers detect torn records via the seqlock
+        unsafe {
+            let seq0 = seqlock::write_begin(&(*record).seq);
+            std::ptr::write_volatile(&mut (*record).update_id, update_id);
+            std::ptr::write_volatile(&mut (*record).event_time_ms, event_time_ms);
+            std::ptr::write_volatile(&mut (*record).ts, ts);
+            seqlock::write_end(&(*record).seq, seq0);
+        }
+        Ok(())
+    }
+
+    /// Read a slot's record; None if never written or it stayed busy
+    pub fn read(&self, source_id: u64, symbol_id: u64) -> Option<SequenceSnapshot> {
+        let record = self.record(source_id, symbol_id).ok()?;
+        for _ in 0..1000 {
+            let seq = unsafe { &(*record).seq };
+            let Some(s1) = seqlock::read_begin(seq) else {
+                continue;
+            };
+            // SAFETY: volatile loads so retries re-read the mapped memory
+            let snapshot = unsafe {
+                SequenceSnapshot {
+                    update_id: std::ptr::read_volatile(&(*record).update_id),
+                    event_time_ms: std::ptr::read_volatile(&(*record).event_time_ms),
+                    ts: std::ptr::read_volatile(&(*record).ts),
+                }
+            };
+            if seqlock::read_end(seq, s1) {
+                return (s1 > 0).then_some(snapshot);
+            }
+        }
+        None
+    }
+
+    /// The sequence of the quote with write time `ts`; None while the record
+    /// belongs to another quote (not written yet, or already overwritten)
+    pub fn read_for(&self, source_id: u64, symbol_id: u64, ts: i64) -> Option<SequenceSnapshot> {
+        self.read(source_id, symbol_id).filter(|snapshot| snapshot.ts == ts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_joins_slot_ts() {
+        let path = std::env::temp_dir().join(format!("test_sequence_{}.dat", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let region = SequenceRegion::open_or_create(path, 2, 4, 0o600, None).unwrap();
+        assert_eq!(region.read(1, 3), None);
+        region.write(1, 3, 390497878, 1571889248277, 1_000).unwrap();
+        region.write(1, 3, 390497880, 1571889248280, 1_050).unwrap();
+        assert_eq!(region.read(1, 3), Some(SequenceSnapshot { update_id: 390497880, event_time_ms: 1571889248280, ts: 1_050 }));
+        assert_eq!(region.read(0, 3), None);
+        assert!(region.write(2, 0, 1, 0, 0).is_err());
+
+        // Joined on the quote's ts: an older quote's sequence is gone
+        assert_eq!(region.read_for(1, 3, 1_050).map(|s| s.update_id), Some(390497880));
+        assert_eq!(region.read_for(1, 3, 1_000), None);
+
+        // Reopened against a differently sized quotes file
+        drop(region);
+        assert!(SequenceRegion::open_or_create(path, 2, 8, 0o600, None).is_err());
+        assert_eq!(SequenceRegion::open(path).unwrap().read(1, 3).unwrap().ts, 1_050);
+        std::fs::write(path, b"garbage").unwrap();
+        assert!(SequenceRegion::open(path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,163 @@
+//! Latency budget enforcement ("shed mode")
+//!
+//! Once a second the controller diffs the main feed's processing-time buckets.
+//! When more than 1% of the second's messages took longer than the budget (p99
+//! over budget) for `breach_secs` seconds in a row, it sheds load: quotes equal
+//! to what the slot already holds are no longer rewritten or fanned out, and
+//! the configured non-critical feature flags (recorder by default) are turned
+//! off. As many seconds back within budget restore both. Every transition is
+//! logged.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::config::ShedConfig;
+use crate::flags::{FeatureFlags, Flag};
+use crate::ws::PerfStats;
+
+#[derive(Default)]
+struct ShedState {
+    /// Totals at the previous check: (messages, messages over budget)
+    last: (u64, u64),
+    /// Consecutive checks disagreeing with the current mode
+    streak: u32,
+    /// Flags this controller turned off (restored on recovery)
+    switched_off: Vec<Flag>,
+    transitions: u64,
+}
+
+/// Switches shed mode on and off from the measured p99
+pub struct ShedController {
+    stats: Arc<PerfStats>,
+    flags: Arc<FeatureFlags>,
+    budget_us: u64,
+    breach_secs: u32,
+    shed_flags: Vec<Flag>,
+    active: AtomicBool,
+    /// Unchanged quotes skipped while shedding
+    deduped: AtomicU64,
+    state: Mutex<ShedState>,
+}
+
+impl ShedController {
+    /// `stats` must count messages over `config.budget_us` (`PerfStats::with_budget`)
+    pub fn new(stats: Arc<PerfStats>, flags: Arc<FeatureFlags>, config: &ShedConfig) -> Self {
+        Self {
+            stats,
+            flags,
+            budget_us: config.budget_us,
+            breach_secs: config.breach_secs,
+            shed_flags: config.flags.clone(),
+            active: AtomicBool::new(false),
+            deduped: AtomicU64::new(0),
+            state: Mutex::new(ShedState::default()),
+        }
+    }
+
+    /// Whether load is being shed (one relaxed load)
+    #[inline(always)]
+    pub fn active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    #[inline(always)]
+    pub fn record_deduped(&self) {
+        self.deduped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Check the messages since the last call; returns whether shedding
+    pub fn check(&self) -> bool {
+        let snap = self.stats.snapshot();
+        let over_total = snap.over_budget;
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (total, over) = (snap.total_messages - state.last.0, over_total - state.last.1);
+        state.last = (snap.total_messages, over_total);
+        // p99 > budget <=> more than 1% of messages over it
+        let breached = total > 0 && over * 100 > total;
+
+        let active = self.active();
+        if breached == active {
+            state.streak = 0;
+            return active;
+        }
+        state.streak += 1;
+        if state.streak < self.breach_secs {
+            return active;
+        }
+        state.streak = 0;
+        state.transitions += 1;
+
+        if breached {
+            state.switched_off = self.shed_flags.iter().copied().filter(|&flag| self.flags.set(flag, false)).collect();
+            let names: Vec<&str> = state.switched_off.iter().map(|flag| flag.name()).collect();
+            eprintln!("[SHED] p99 over {} µs for {} s ({} of {} messages last second): shedding, dedup on, off: [{}]",
+                      self.budget_us, self.breach_secs, over, total, names.join(", "));
+        } else {
+            for flag in state.switched_off.drain(..) {
+                self.flags.set(flag, true);
+            }
+            eprintln!("[SHED] p99 within {} µs for {} s: shed mode off, sinks restored", self.budget_us, self.breach_secs);
+        }
+        self.active.store(breached, Ordering::Relaxed);
+        breached
+    }
+
+    /// Check every `interval` (a second, so `breach_secs` counts checks)
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.check();
+            }
+        })
+    }
+
+    pub fn report(&self) {
+        let transitions = self.state.lock().unwrap_or_else(|e| e.into_inner()).transitions;
+        if transitions > 0 {
+            eprintln!("[STATS] Shed mode: {} transitions, {} unchanged quotes skipped",
+                      transitions, self.deduped.load(Ordering::Relaxed));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shed_transitions() {
+        let stats = Arc::new(PerfStats::with_thresholds(vec![1_000], 5_000).with_budget(500));
+        let flags = Arc::new(FeatureFlags::from_spec("-anomaly"));
+        let config = ShedConfig { budget_us: 500, breach_secs: 2, flags: vec![Flag::Recorder, Flag::Anomaly] };
+        let shed = ShedController::new(stats.clone(), flags.clone(), &config);
+        let second = |fast: u64, slow: u64| {
+            (0..fast).for_each(|_| stats.record(100));
+            (0..slow).for_each(|_| stats.record(800));
+            shed.check()
+        };
+
+        // Exactly 1% over is still within budget; an idle second counts as within
+        assert!(!second(99, 1));
+        assert!(!second(0, 0));
+        // One bad second is not enough
+        assert!(!second(90, 10));
+        assert!(!second(100, 0));
+        assert!(!second(90, 10));
+        assert!(second(90, 10));
+        assert!(shed.active());
+        assert!(!flags.enabled(Flag::Recorder));
+
+        // Recovery also needs breach_secs good seconds; only flags we turned off come back
+        assert!(second(100, 0));
+        assert!(!second(100, 0));
+        assert!(flags.enabled(Flag::Recorder));
+        assert!(!flags.enabled(Flag::Anomaly));
+        assert_eq!(shed.state.lock().unwrap().transitions, 2);
+        // The budget is counted apart from the configured slow buckets
+        assert_eq!(stats.snapshot().slow_buckets, vec![(1_000, 0)]);
+    }
+}
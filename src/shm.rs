@@ -1,7 +1,11 @@
+use std::collections::HashMap;
 use std::fs::OpenOptions;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use crate::price::Price;
+use crate::seqlock::{self, AtomicU64, Ordering};
 use anyhow::{bail, Context, Result};
-use memmap2::MmapMut;
+use memmap2::{Mmap, MmapMut, MmapOptions};
+use serde::Serialize;
 
 // Constants from spec
 const MAGIC: &[u8; 8] = b"QSHM1\0\0\0";
@@ -11,6 +15,76 @@ const EXPECTED_RECORDS_OFFSET: u64 = 4096;
 const EXPECTED_PRICE_SCALE: u64 = 100_000_000; // 1e8
 const EXPECTED_TS_SCALE: u64 = 1_000_000; // 1e6 (microseconds!)
 
+// Header reserved space layout: per-source writer state table
+const SOURCE_STATE_OFFSET: u64 = 1024;
+const SOURCE_STATE_SIZE: u64 = 128;
+/// Number of sources that have a state block in the header reserved space
+pub const MAX_HEADER_SOURCES: u64 = (EXPECTED_HEADER_SIZE - SOURCE_STATE_OFFSET) / SOURCE_STATE_SIZE;
+
+/// Writer crashed/panicked: readers should stop trusting this source's slots
+pub const SOURCE_FLAG_WRITER_FAULT: u64 = 1 << 0;
+
+/// Slot status bits (`Quote64::status`): data-quality context for readers
+/// Initialized for this run, no quote written yet
+pub const SLOT_STATUS_INIT: u32 = 1 << 0;
+/// No update within the symbol's SLA tier limit (set by the watchdog)
+pub const SLOT_STATUS_STALE: u32 = 1 << 1;
+/// Last quote had bid > ask
+pub const SLOT_STATUS_CROSSED: u32 = 1 << 2;
+/// Last quote had a zero (or negative) bid or ask
+pub const SLOT_STATUS_ZERO_PRICE: u32 = 1 << 3;
+/// The exchange lists the symbol as not trading
+pub const SLOT_STATUS_HALTED: u32 = 1 << 4;
+/// Bits derived from each quote's prices
+pub const SLOT_STATUS_QUOTE_MASK: u32 = SLOT_STATUS_CROSSED | SLOT_STATUS_ZERO_PRICE;
+
+/// SLOT_STATUS_QUOTE_MASK bits for a bid/ask pair
+#[inline(always)]
+pub fn quote_status(bid: i64, ask: i64) -> u32 {
+    let mut status = 0;
+    if bid <= 0 || ask <= 0 {
+        status |= SLOT_STATUS_ZERO_PRICE;
+    }
+    if bid > ask {
+        status |= SLOT_STATUS_CROSSED;
+    }
+    status
+}
+
+/// A counter alone on its cache line, so writer-side and reader-side counts
+/// don't bounce one line between cores
+#[repr(align(64))]
+pub struct PaddedCounter(pub std::sync::atomic::AtomicU64);
+
+impl std::ops::Deref for PaddedCounter {
+    type Target = std::sync::atomic::AtomicU64;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Process-wide slot activity, exported by `metrics::MetricsExporter`. Quote
+/// writes aren't counted here: the write path stays free of shared counters
+/// and the exporter sums the feeds' PerfStats instead.
+pub struct ShmCounters {
+    /// Slot status words changed (stale, crossed, halted, ...)
+    pub status_changes: PaddedCounter,
+    /// Seqlock read attempts repeated because a write was in progress
+    pub read_retries: PaddedCounter,
+    /// Reads that gave up after the whole retry budget
+    pub busy_reads: PaddedCounter,
+}
+
+pub static COUNTERS: ShmCounters = ShmCounters {
+    status_changes: PaddedCounter(std::sync::atomic::AtomicU64::new(0)),
+    read_retries: PaddedCounter(std::sync::atomic::AtomicU64::new(0)),
+    busy_reads: PaddedCounter(std::sync::atomic::AtomicU64::new(0)),
+};
+
+/// Header bytes covered by `header_crc32`: the magic and the layout fields
+const CHECKSUMMED_HEADER_BYTES: usize = 88;
+
 /// SHM Header (first 4096 bytes)
 #[repr(C)]
 #[derive(Debug)]
@@ -26,22 +100,343 @@ pub struct ShmHeader {
     pub n_symbols: u64,
     pub n_records: u64,
     pub shm_total_size: u64,
+    /// CRC-32 of bytes 0..88 in the low 32 bits (0 = file predates checksums)
+    pub header_crc32: u64,
 }
 
-/// Quote record (64 bytes, cache-line aligned)
+impl ShmHeader {
+    /// CRC-32 of the checksummed bytes as currently mapped
+    pub fn compute_crc32(&self) -> u32 {
+        let base = self as *const ShmHeader as *const u8;
+        let mut bytes = [0u8; CHECKSUMMED_HEADER_BYTES];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            // SAFETY: within the header; volatile so re-checks re-read the mapping
+            *b = unsafe { std::ptr::read_volatile(base.add(i)) };
+        }
+        crc32(&bytes)
+    }
+
+    fn stored_crc32(&self) -> u64 {
+        // SAFETY: aligned field of the mapped header
+        unsafe { std::ptr::read_volatile(&self.header_crc32) }
+    }
+}
+
+/// CRC-32 (IEEE 802.3, as in zlib)
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Per-source writer state (128 bytes) stored in the header reserved space
+/// at offset 1024 + source_id * 128
 #[repr(C, align(64))]
-pub struct Quote64 {
-    pub seq: AtomicU64,
+pub struct SourceState {
+    pub flags: AtomicU64,
+    /// Writer start time (unix µs); 0 while the info strings are being written
+    pub started_at_unix_us: AtomicU64,
+    /// NUL-padded ASCII strings identifying the writer build
+    pub writer_version: [u8; 16],
+    pub git_sha: [u8; 16],
+    pub source_name: [u8; 32],
+    /// Seqlock over the heartbeat pair below (0 = no heartbeat yet)
+    pub heartbeat_seq: AtomicU64,
+    /// Writer's monotonic_us (the clock of quote `ts`) at the last heartbeat
+    pub heartbeat_mono_us: AtomicU64,
+    /// Wall clock (unix µs) at the same instant
+    pub heartbeat_unix_us: AtomicU64,
+    pub reserved: [u64; 3],
+}
+
+/// One heartbeat: the same instant on the writer's monotonic clock and the wall clock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Heartbeat {
+    pub mono_us: i64,
+    pub unix_us: u64,
+}
+
+impl Heartbeat {
+    /// Age of a quote written at `quote_ts` (writer monotonic µs) as of
+    /// `now_unix_us`, without reading the monotonic clock of this process
+    pub fn quote_age_us(&self, quote_ts: i64, now_unix_us: u64) -> i64 {
+        (self.mono_us - quote_ts) + (now_unix_us as i64 - self.unix_us as i64)
+    }
+}
+
+/// Writer build/identity info recorded in a SourceState block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriterInfo {
+    pub version: String,
+    pub git_sha: String,
+    pub source_name: String,
+    pub started_at_unix_us: u64,
+}
+
+/// Copy `s` into a NUL-padded fixed field (truncated if too long)
+fn write_str_field(field: *mut u8, len: usize, s: &str) {
+    let bytes = s.as_bytes();
+    for i in 0..len {
+        // SAFETY: i < len, the caller passes the field's own length
+        unsafe { std::ptr::write_volatile(field.add(i), bytes.get(i).copied().unwrap_or(0)) };
+    }
+}
+
+fn read_str_field(field: &[u8]) -> String {
+    let bytes: Vec<u8> = field
+        .iter()
+        // SAFETY: in-bounds reads of mapped memory another process may update
+        .map(|b| unsafe { std::ptr::read_volatile(b) })
+        .take_while(|&b| b != 0)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(not(feature = "loom"))]
+const _: () = assert!(std::mem::size_of::<SourceState>() as u64 == SOURCE_STATE_SIZE);
+
+impl SourceState {
+    pub fn set_flags(&self, flags: u64) {
+        self.flags.fetch_or(flags, Ordering::Release);
+    }
+
+    pub fn clear_flags(&self, flags: u64) {
+        self.flags.fetch_and(!flags, Ordering::Release);
+    }
+
+    pub fn has_flags(&self, flags: u64) -> bool {
+        self.flags.load(Ordering::Acquire) & flags == flags
+    }
+
+    /// Record which writer build owns this source. The timestamp is cleared
+    /// first and published last, so readers never see half-written strings
+    /// paired with a valid start time.
+    pub fn set_writer_info(&self, info: &WriterInfo) {
+        self.started_at_unix_us.store(0, Ordering::Relaxed);
+        seqlock::fence(Ordering::Release);
+
+        // SAFETY: single writer per source; readers only trust the strings
+        // after observing a non-zero started_at_unix_us
+        unsafe {
+            let ptr = self as *const SourceState as *mut SourceState;
+            write_str_field((*ptr).writer_version.as_mut_ptr(), 16, &info.version);
+            write_str_field((*ptr).git_sha.as_mut_ptr(), 16, &info.git_sha);
+            write_str_field((*ptr).source_name.as_mut_ptr(), 32, &info.source_name);
+        }
+
+        self.started_at_unix_us.store(info.started_at_unix_us.max(1), Ordering::Release);
+    }
+
+    /// Publish a heartbeat (single writer per source)
+    pub fn set_heartbeat(&self, heartbeat: Heartbeat) {
+        let seq0 = seqlock::write_begin(&self.heartbeat_seq);
+        self.heartbeat_mono_us.store(heartbeat.mono_us as u64, Ordering::Relaxed);
+        self.heartbeat_unix_us.store(heartbeat.unix_us, Ordering::Relaxed);
+        seqlock::write_end(&self.heartbeat_seq, seq0);
+    }
+
+    /// Latest heartbeat, None before the first one
+    pub fn heartbeat(&self) -> Option<Heartbeat> {
+        for _ in 0..1000 {
+            let Some(s1) = seqlock::read_begin(&self.heartbeat_seq) else {
+                continue;
+            };
+            let mono_us = self.heartbeat_mono_us.load(Ordering::Relaxed) as i64;
+            let unix_us = self.heartbeat_unix_us.load(Ordering::Relaxed);
+            if seqlock::read_end(&self.heartbeat_seq, s1) {
+                return (s1 != 0).then_some(Heartbeat { mono_us, unix_us });
+            }
+        }
+        None
+    }
+
+    /// Writer info, if a writer has recorded it
+    pub fn writer_info(&self) -> Option<WriterInfo> {
+        let started_at_unix_us = self.started_at_unix_us.load(Ordering::Acquire);
+        if started_at_unix_us == 0 {
+            return None;
+        }
+        Some(WriterInfo {
+            version: read_str_field(&self.writer_version),
+            git_sha: read_str_field(&self.git_sha),
+            source_name: read_str_field(&self.source_name),
+            started_at_unix_us,
+        })
+    }
+}
+
+/// Check the header against the spec constants and the actual file size
+fn validate_header(header: &ShmHeader, file_size: u64) -> Result<()> {
+    // Validate magic
+    if &header.magic != MAGIC {
+        bail!("Invalid magic: expected {:?}, got {:?}", MAGIC, header.magic);
+    }
+
+    // Validate header_size
+    if header.header_size != EXPECTED_HEADER_SIZE {
+        bail!("Invalid header_size: expected {}, got {}", EXPECTED_HEADER_SIZE, header.header_size);
+    }
+
+    // Validate record_size
+    if header.record_size != EXPECTED_RECORD_SIZE {
+        bail!("Invalid record_size: expected {}, got {}", EXPECTED_RECORD_SIZE, header.record_size);
+    }
+
+    // Validate records_offset
+    if header.records_offset != EXPECTED_RECORDS_OFFSET {
+        bail!("Invalid records_offset: expected {}, got {}", EXPECTED_RECORDS_OFFSET, header.records_offset);
+    }
+
+    // Validate price_scale
+    if header.price_scale != EXPECTED_PRICE_SCALE {
+        bail!("Invalid price_scale: expected {}, got {}", EXPECTED_PRICE_SCALE, header.price_scale);
+    }
+
+    // Validate ts_scale (CRITICAL: must be 1e6 for microseconds)
+    if header.ts_scale != EXPECTED_TS_SCALE {
+        bail!("Invalid ts_scale: expected {} (1e6), got {}", EXPECTED_TS_SCALE, header.ts_scale);
+    }
+
+    // Validate total size
+    if header.shm_total_size != file_size {
+        bail!("Size mismatch: header says {}, file is {}", header.shm_total_size, file_size);
+    }
+
+    // Validate n_records
+    let expected_records = header.n_sources * header.n_symbols;
+    if header.n_records != expected_records {
+        bail!("Invalid n_records: expected {}, got {}", expected_records, header.n_records);
+    }
+
+    // Catches corruption that leaves the fields plausible (e.g. swapped counts)
+    let stored = header.stored_crc32();
+    let computed = header.compute_crc32();
+    if stored != 0 && stored != computed as u64 {
+        bail!("Header checksum mismatch: stored {:#010x}, computed {:#010x}", stored, computed);
+    }
+
+    Ok(())
+}
+
+/// Consistent slot read: (source_id, symbol_id, bid, ask, ts)
+pub type SlotRead = (u64, u64, i64, i64, i64);
+
+/// Plain copy of a quote taken with a consistent seqlock read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct QuoteSnapshot {
     pub source_id: u64,
     pub symbol_id: u64,
     pub bid: i64,
     pub ask: i64,
+    /// monotonic_us of the last write (0 = never written)
     pub ts: i64,
-    pub reserved0: u64,
-    pub reserved1: u64,
 }
 
-const _: () = assert!(std::mem::size_of::<Quote64>() == 64);
+/// Write time of a slot: monotonic µs (`monotonic_us`), 0 = never written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct TimestampUs(i64);
+
+impl TimestampUs {
+    pub fn new(us: i64) -> Self {
+        Self(us)
+    }
+
+    pub fn as_micros(self) -> i64 {
+        self.0
+    }
+
+    pub fn is_written(self) -> bool {
+        self.0 != 0
+    }
+
+    /// µs from this write to `now_us` (None if never written)
+    pub fn age_us(self, now_us: i64) -> Option<u64> {
+        self.is_written().then(|| now_us.saturating_sub(self.0).max(0) as u64)
+    }
+}
+
+/// Bytes 16..48 of a Quote64: the part a write stores, copied as one block
+///
+/// `symbol_id` is constant but rides along (rewritten with the same value) so
+/// bid/ask/ts are one 16-aligned 32-byte block inside a single cache line:
+/// two SSE stores instead of three scalar ones, so the odd-seq window and the
+/// readers that retry on it are shorter.
+#[derive(Clone, Copy)]
+#[repr(C, align(16))]
+struct QuotePayload {
+    symbol_id: u64,
+    bid: i64,
+    ask: i64,
+    ts: i64,
+}
+
+impl QuotePayload {
+    /// Store the block with one 32-byte copy
+    ///
+    /// # Safety
+    /// `dst` must be valid for writes and 16-byte aligned
+    #[inline(always)]
+    unsafe fn store(dst: *mut QuotePayload, src: QuotePayload) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            use std::arch::x86_64::{__m128i, _mm_set_epi64x};
+            // SSE2 is baseline on x86_64; the block is 16-aligned, so two aligned stores
+            let dst = dst as *mut __m128i;
+            let (lo, hi) = (_mm_set_epi64x(src.bid, src.symbol_id as i64), _mm_set_epi64x(src.ts, src.ask));
+            std::ptr::write_volatile(dst, lo);
+            std::ptr::write_volatile(dst.add(1), hi);
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        std::ptr::write_volatile(dst, src);
+    }
+
+    /// Load the block with one 32-byte copy
+    ///
+    /// # Safety
+    /// `src` must be valid for reads and 16-byte aligned
+    #[inline(always)]
+    unsafe fn load(src: *const QuotePayload) -> QuotePayload {
+        #[cfg(target_arch = "x86_64")]
+        {
+            use std::arch::x86_64::__m128i;
+            let src = src as *const __m128i;
+            let words: [u64; 4] = std::mem::transmute([std::ptr::read_volatile(src), std::ptr::read_volatile(src.add(1))]);
+            QuotePayload { symbol_id: words[0], bid: words[1] as i64, ask: words[2] as i64, ts: words[3] as i64 }
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        std::ptr::read_volatile(src)
+    }
+}
+
+/// Quote record (64 bytes, cache-line aligned)
+///
+/// Fields are private: read the quote through `quote()` (typed, scaled) or
+/// `snapshot()`/`read()`, which go through the seqlock.
+#[repr(C, align(64))]
+pub struct Quote64 {
+    seq: AtomicU64,
+    source_id: u64,
+    /// symbol_id, bid, ask, ts (offsets 16, 24, 32, 40)
+    payload: QuotePayload,
+    /// Scale of bid/ask for this symbol (0 = the header's price_scale, 1e8)
+    price_scale: u32,
+    /// SLOT_STATUS_* bits, updated atomically (quote bits inside the write's seqlock window)
+    status: u32,
+    /// Token of the writer process that claimed the slot (0 = unclaimed, see ownership.rs)
+    owner: u64,
+}
+
+#[cfg(not(feature = "loom"))]
+const _: () = {
+    assert!(std::mem::size_of::<Quote64>() == 64);
+    assert!(std::mem::offset_of!(Quote64, payload) == 16);
+    assert!(std::mem::offset_of!(Quote64, price_scale) == 48);
+};
 
 impl Quote64 {
     /// Initialize slot with constant fields (source_id, symbol_id)
@@ -49,74 +444,295 @@ impl Quote64 {
     pub fn init_slot(&mut self, source_id: u64, symbol_id: u64) {
         self.seq.store(0, Ordering::Relaxed);
         self.source_id = source_id;
-        self.symbol_id = symbol_id;
-        self.bid = 0;
-        self.ask = 0;
-        self.ts = 0;
-        self.reserved0 = 0;
-        self.reserved1 = 0;
+        self.payload = QuotePayload { symbol_id, bid: 0, ask: 0, ts: 0 };
+        self.price_scale = 0;
+        self.status = SLOT_STATUS_INIT;
+        // `owner` is left alone: it tells a second writer who already claimed the slot
+    }
+
+    /// Hint the CPU to pull the slot's cache line in for an upcoming write
+    /// (`prefetch` feature, x86_64 only; a no-op otherwise)
+    #[inline(always)]
+    pub fn prefetch(&self) {
+        #[cfg(all(feature = "prefetch", target_arch = "x86_64"))]
+        // SAFETY: prefetch is a hint; it never faults and the address is a live slot
+        unsafe {
+            use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+            _mm_prefetch::<_MM_HINT_T0>(self as *const Quote64 as *const i8);
+        }
+    }
+
+    /// Seqlock counter (odd while a write is in progress, +2 per write)
+    pub fn seq(&self) -> u64 {
+        self.seq.load(Ordering::Acquire)
+    }
+
+    /// Source row of the slot (constant after init)
+    pub fn source_id(&self) -> u64 {
+        // SAFETY: plain aligned load; only changed by init_slot
+        unsafe { std::ptr::read_volatile(&self.source_id) }
+    }
+
+    /// Symbol of the slot (set by init; every write stores the same value
+    /// again as part of its 32-byte payload copy)
+    pub fn symbol_id(&self) -> u64 {
+        // SAFETY: plain aligned load; init_slot sets the value and writes
+        // rewrite it unchanged, so a load racing a write still sees it
+        unsafe { std::ptr::read_volatile(&self.payload.symbol_id) }
+    }
+
+    /// Consistent bid, ask and write time, prices at the slot's scale (None
+    /// if the writer kept the slot busy for the whole retry budget)
+    pub fn quote(&self) -> Option<(Price, Price, TimestampUs)> {
+        let (_, _, bid, ask, ts) = self.read()?;
+        let decimals = self.price_scale().ilog10();
+        Some((Price::new(bid, decimals), Price::new(ask, decimals), TimestampUs(ts)))
+    }
+
+    /// Ownership stamp of the slot
+    pub fn owner(&self) -> u64 {
+        // SAFETY: `owner` is 8-byte aligned and only ever accessed atomically
+        unsafe { std::sync::atomic::AtomicU64::from_ptr(&self.owner as *const u64 as *mut u64) }.load(std::sync::atomic::Ordering::Acquire)
+    }
+
+    /// Effective scale of bid/ask (per-symbol override or the 1e8 default)
+    pub fn price_scale(&self) -> u64 {
+        // SAFETY: plain aligned load; only changed before the writer starts
+        match unsafe { std::ptr::read_volatile(&self.price_scale) } {
+            0 => EXPECTED_PRICE_SCALE,
+            scale => scale as u64,
+        }
+    }
+
+    #[inline(always)]
+    fn status_atomic(&self) -> &std::sync::atomic::AtomicU32 {
+        // SAFETY: `status` is 4-byte aligned and only ever accessed atomically
+        unsafe { std::sync::atomic::AtomicU32::from_ptr(&self.status as *const u32 as *mut u32) }
+    }
+
+    /// SLOT_STATUS_* bits
+    pub fn status(&self) -> u32 {
+        self.status_atomic().load(std::sync::atomic::Ordering::Acquire)
+    }
+
+    /// Set the `mask` bits of the status to `bits`; a plain load when nothing
+    /// changes. Safe alongside other updaters (watchdog, writer).
+    #[inline(always)]
+    pub(crate) fn update_status(&self, mask: u32, bits: u32) {
+        use std::sync::atomic::Ordering as StdOrdering;
+
+        let status = self.status_atomic();
+        if status.load(StdOrdering::Relaxed) & mask != bits & mask {
+            let _ = status.fetch_update(StdOrdering::Release, StdOrdering::Relaxed, |s| Some((s & !mask) | (bits & mask)));
+            COUNTERS.status_changes.fetch_add(1, StdOrdering::Relaxed);
+        }
     }
 
     /// Write quote using seqlock protocol
     /// CRITICAL: This must be lock-free and minimal latency
     #[inline(always)]
-    pub fn write(&self, bid: i64, ask: i64, ts: i64) {
-        // Load current seq (should be even)
-        let seq0 = self.seq.load(Ordering::Relaxed);
+    pub(crate) fn write(&self, bid: i64, ask: i64, ts: i64) {
+        self.write_with_status(bid, ask, ts, 0, 0);
+    }
 
-        // Mark as "writing" (odd)
-        self.seq.store(seq0.wrapping_add(1), Ordering::Release);
+    /// Write a quote and, in the same seqlock window, set the `mask` status
+    /// bits to `bits` (SLOT_STATUS_INIT is always cleared)
+    #[inline(always)]
+    pub(crate) fn write_with_status(&self, bid: i64, ask: i64, ts: i64, mask: u32, bits: u32) {
+        // Mark as "writing" (odd), Release fence before data
+        let seq0 = seqlock::write_begin(&self.seq);
 
-        // Write data fields
-        // SAFETY: We have exclusive access to this slot (one writer per slot)
+        // Write data fields in one 32-byte copy
+        // SAFETY: We have exclusive access to this slot (one writer per slot);
+        // symbol_id is read back and stored again unchanged (only init_slot,
+        // before the writer starts, gives it a new value)
         unsafe {
-            let ptr = self as *const Quote64 as *mut Quote64;
-            (*ptr).bid = bid;
-            (*ptr).ask = ask;
-            (*ptr).ts = ts;
+            let ptr = &self.payload as *const QuotePayload as *mut QuotePayload;
+            let symbol_id = std::ptr::read_volatile(&self.payload.symbol_id);
+            QuotePayload::store(ptr, QuotePayload { symbol_id, bid, ask, ts });
         }
+        self.update_status(mask | SLOT_STATUS_INIT, bits);
 
-        // Mark as "complete" (even), with Release fence
-        self.seq.store(seq0.wrapping_add(2), Ordering::Release);
+        // Mark as "complete" (even), with Release
+        seqlock::write_end(&self.seq, seq0);
     }
 
     /// Read quote using seqlock protocol (for testing/debugging)
     #[allow(dead_code)]
-    pub fn read(&self) -> Option<(u64, u64, i64, i64, i64)> {
-        for _ in 0..1000 {
-            let s1 = self.seq.load(Ordering::Acquire);
+    pub fn read(&self) -> Option<SlotRead> {
+        self.read_with_status().map(|(read, _)| read)
+    }
 
+    /// Read quote and SLOT_STATUS_* bits in one seqlock window, so the
+    /// status describes that quote (the writer sets its bits inside the window)
+    pub fn read_with_status(&self) -> Option<(SlotRead, u32)> {
+        for attempt in 0..1000 {
+            if attempt > 0 {
+                COUNTERS.read_retries.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
             // If odd, writer is in progress
-            if (s1 & 1) == 1 {
+            let Some(s1) = seqlock::read_begin(&self.seq) else {
                 continue;
-            }
-
-            let sid = self.source_id;
-            let sym = self.symbol_id;
-            let bid = self.bid;
-            let ask = self.ask;
-            let ts = self.ts;
+            };
 
-            let s2 = self.seq.load(Ordering::Acquire);
+            // SAFETY: volatile loads so retries always re-read the mapped memory
+            let (sid, p) = unsafe { (std::ptr::read_volatile(&self.source_id), QuotePayload::load(&self.payload)) };
+            let (sym, bid, ask, ts) = (p.symbol_id, p.bid, p.ask, p.ts);
+            let status = self.status_atomic().load(std::sync::atomic::Ordering::Relaxed);
 
             // Check if seq changed during read
-            if s1 != s2 {
+            if !seqlock::read_end(&self.seq, s1) {
                 continue;
             }
 
-            return Some((sid, sym, bid, ask, ts));
+            return Some(((sid, sym, bid, ask, ts), status));
         }
+        COUNTERS.busy_reads.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         None
     }
+
+    /// Consistent copy of the quote (None if the writer kept the slot busy
+    /// for the whole retry budget)
+    pub fn snapshot(&self) -> Option<QuoteSnapshot> {
+        self.read().map(|(source_id, symbol_id, bid, ask, ts)| QuoteSnapshot { source_id, symbol_id, bid, ask, ts })
+    }
+}
+
+/// Read-only access to a slot (see `ShmReader::slot`, `ShmManager::view`):
+/// the `Quote64` accessors minus the writes, which would fault on a reader's
+/// pages and are otherwise reserved to the slot's `writer::WriterToken`
+#[derive(Clone, Copy)]
+pub struct SlotView<'a>(pub(crate) &'a Quote64);
+
+impl SlotView<'_> {
+    pub fn seq(&self) -> u64 {
+        self.0.seq()
+    }
+
+    pub fn source_id(&self) -> u64 {
+        self.0.source_id()
+    }
+
+    pub fn symbol_id(&self) -> u64 {
+        self.0.symbol_id()
+    }
+
+    pub fn quote(&self) -> Option<(Price, Price, TimestampUs)> {
+        self.0.quote()
+    }
+
+    pub fn owner(&self) -> u64 {
+        self.0.owner()
+    }
+
+    pub fn price_scale(&self) -> u64 {
+        self.0.price_scale()
+    }
+
+    pub fn status(&self) -> u32 {
+        self.0.status()
+    }
+
+    pub fn read(&self) -> Option<SlotRead> {
+        self.0.read()
+    }
+
+    pub fn read_with_status(&self) -> Option<(SlotRead, u32)> {
+        self.0.read_with_status()
+    }
+
+    pub fn snapshot(&self) -> Option<QuoteSnapshot> {
+        self.0.snapshot()
+    }
+}
+
+/// Records of one source (row `source_id` of the record array)
+///
+/// # Safety
+/// `records_base` must point at `n_sources * n_symbols` records that outlive 'a
+unsafe fn source_row<'a>(records_base: *const Quote64, n_sources: u64, n_symbols: u64, source_id: u64) -> Result<&'a [Quote64]> {
+    if source_id >= n_sources {
+        bail!("source_id {} out of range (max {})", source_id, n_sources);
+    }
+    Ok(std::slice::from_raw_parts(records_base.add((source_id * n_symbols) as usize), n_symbols as usize))
+}
+
+/// Snapshots of selected slots of a row (busy slots omitted)
+fn snapshot_symbols(row: &[Quote64], symbol_ids: &[u64]) -> Result<Vec<QuoteSnapshot>> {
+    let mut out = Vec::with_capacity(symbol_ids.len());
+    for &symbol_id in symbol_ids {
+        let Some(slot) = row.get(symbol_id as usize) else {
+            bail!("symbol_id {} out of range (max {})", symbol_id, row.len());
+        };
+        out.extend(slot.snapshot());
+    }
+    Ok(out)
+}
+
+/// (symbol_id, snapshot) for every slot of a row
+fn snapshot_row(row: &[Quote64]) -> impl Iterator<Item = (u64, Option<QuoteSnapshot>)> + '_ {
+    row.iter().enumerate().map(|(symbol_id, slot)| (symbol_id as u64, slot.snapshot()))
+}
+
+/// When the writable mapping is synced to its backing file
+///
+/// Irrelevant on tmpfs; matters when the SHM file lives on persistent storage
+/// and should survive a host crash, at the cost of msync I/O.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MsyncPolicy {
+    /// Leave writeback to the kernel
+    #[default]
+    Never,
+    /// msync at a fixed interval from a background thread (and on shutdown)
+    Periodic,
+    /// msync once on graceful shutdown
+    OnShutdown,
+}
+
+impl std::str::FromStr for MsyncPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "never" | "none" => Ok(Self::Never),
+            "periodic" => Ok(Self::Periodic),
+            "shutdown" | "on_shutdown" | "on-shutdown" => Ok(Self::OnShutdown),
+            other => bail!("Unknown msync policy: {}", other),
+        }
+    }
+}
+
+/// Header of a new file for `n_sources` x `n_symbols` slots (checksum set),
+/// and the file's total size
+fn new_header(n_sources: u64, n_symbols: u64) -> (Vec<u8>, u64) {
+    let n_records = n_sources * n_symbols;
+    let total = EXPECTED_RECORDS_OFFSET + n_records * EXPECTED_RECORD_SIZE;
+
+    let mut header = vec![0u8; EXPECTED_HEADER_SIZE as usize];
+    header[0..8].copy_from_slice(MAGIC);
+    let fields = [
+        1, EXPECTED_HEADER_SIZE, EXPECTED_RECORD_SIZE, EXPECTED_RECORDS_OFFSET,
+        EXPECTED_PRICE_SCALE, EXPECTED_TS_SCALE, n_sources, n_symbols, n_records, total,
+    ];
+    for (i, v) in fields.iter().enumerate() {
+        header[8 + i * 8..16 + i * 8].copy_from_slice(&v.to_le_bytes());
+    }
+    let crc = crc32(&header[..CHECKSUMMED_HEADER_BYTES]) as u64;
+    header[CHECKSUMMED_HEADER_BYTES..CHECKSUMMED_HEADER_BYTES + 8].copy_from_slice(&crc.to_le_bytes());
+    (header, total)
 }
 
 /// SHM manager
 pub struct ShmManager {
-    #[allow(dead_code)]
     mmap: MmapMut,
+    /// File path, re-checked by `verify_header` (None = anonymous mapping)
+    path: Option<String>,
+    header_base: *mut u8,
     records_base: *mut Quote64,
     n_symbols: u64,
     n_sources: u64,
+    /// Slots claimed by in-process writers (see `writer`)
+    writers: crate::writer::WriterClaims,
 }
 
 unsafe impl Send for ShmManager {}
@@ -135,58 +751,31 @@ impl ShmManager {
             .context("Failed to get file metadata")?;
         let file_size = metadata.len();
 
-        let mut mmap = unsafe {
+        let mmap = unsafe {
             MmapMut::map_mut(&file)
                 .context("Failed to mmap file")?
         };
 
+        Self::from_mmap(mmap, Some(path.to_string()), file_size)
+    }
+
+    /// Validate the header of a fresh mapping and take it over
+    fn from_mmap(mut mmap: MmapMut, path: Option<String>, file_size: u64) -> Result<Self> {
         // Parse and validate header
         let header = unsafe {
             &*(mmap.as_ptr() as *const ShmHeader)
         };
-
-        // Validate magic
-        if &header.magic != MAGIC {
-            bail!("Invalid magic: expected {:?}, got {:?}", MAGIC, header.magic);
+        validate_header(header, file_size)?;
+        if header.stored_crc32() == 0 {
+            // File created before checksums: stamp it now that the fields checked out
+            let crc = header.compute_crc32() as u64;
+            // SAFETY: aligned field of the writable mapping; readers only validate it
+            unsafe { std::ptr::write_volatile(std::ptr::addr_of!(header.header_crc32) as *mut u64, crc) };
+            eprintln!("[SHM] Stamped header checksum {:#010x}", crc);
         }
 
-        // Validate header_size
-        if header.header_size != EXPECTED_HEADER_SIZE {
-            bail!("Invalid header_size: expected {}, got {}", EXPECTED_HEADER_SIZE, header.header_size);
-        }
-
-        // Validate record_size
-        if header.record_size != EXPECTED_RECORD_SIZE {
-            bail!("Invalid record_size: expected {}, got {}", EXPECTED_RECORD_SIZE, header.record_size);
-        }
-
-        // Validate records_offset
-        if header.records_offset != EXPECTED_RECORDS_OFFSET {
-            bail!("Invalid records_offset: expected {}, got {}", EXPECTED_RECORDS_OFFSET, header.records_offset);
-        }
-
-        // Validate price_scale
-        if header.price_scale != EXPECTED_PRICE_SCALE {
-            bail!("Invalid price_scale: expected {}, got {}", EXPECTED_PRICE_SCALE, header.price_scale);
-        }
-
-        // Validate ts_scale (CRITICAL: must be 1e6 for microseconds)
-        if header.ts_scale != EXPECTED_TS_SCALE {
-            bail!("Invalid ts_scale: expected {} (1e6), got {}", EXPECTED_TS_SCALE, header.ts_scale);
-        }
-
-        // Validate total size
-        if header.shm_total_size != file_size {
-            bail!("Size mismatch: header says {}, file is {}", header.shm_total_size, file_size);
-        }
-
-        // Validate n_records
-        let expected_records = header.n_sources * header.n_symbols;
-        if header.n_records != expected_records {
-            bail!("Invalid n_records: expected {}, got {}", expected_records, header.n_records);
-        }
-
-        // Calculate records base pointer
+        // Calculate base pointers
+        let header_base = mmap.as_mut_ptr();
         let records_base = unsafe {
             mmap.as_mut_ptr().add(header.records_offset as usize) as *mut Quote64
         };
@@ -196,15 +785,87 @@ impl ShmManager {
 
         Ok(Self {
             mmap,
+            path,
+            header_base,
             records_base,
             n_symbols: header.n_symbols,
             n_sources: header.n_sources,
+            writers: Default::default(),
         })
     }
 
+    /// Open an existing SHM file read-only for tools and readers
+    /// (only needs read permission; see ShmReader)
+    pub fn open_readonly(path: &str) -> Result<ShmReader> {
+        ShmReader::open(path)
+    }
+
+    /// Create a zeroed SHM file with a valid header, then open it
+    ///
+    /// `mode` is applied explicitly (not filtered by the umask) and `group`, if
+    /// given, lets reader processes running as another user map the file.
+    pub fn create(path: &str, n_sources: u64, n_symbols: u64, mode: u32, group: Option<&str>) -> Result<Self> {
+        let (header, total) = new_header(n_sources, n_symbols);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .with_context(|| format!("Failed to create SHM file: {}", path))?;
+        file.set_len(total).context("Failed to size SHM file")?;
+        std::io::Write::write_all(&mut file, &header).context("Failed to write SHM header")?;
+        drop(file);
+
+        let permissions = crate::platform::set_file_mode(path, mode)
+            .and_then(|_| group.map_or(Ok(()), |g| crate::platform::set_file_group(path, g)));
+        if let Err(e) = permissions {
+            // Don't leave a file readers can't map behind
+            let _ = std::fs::remove_file(path);
+            return Err(e);
+        }
+
+        eprintln!("[SHM] Created {} ({} bytes, mode {:04o}, group {})",
+                  path, total, mode, group.unwrap_or("default"));
+        Self::open(path)
+    }
+
+    /// SHM in an anonymous mapping with a synthesized header, for tests and
+    /// benches: no file, so nothing touches /dev/shm and other processes
+    /// can't map it. `verify_header` checks against the mapping's length.
+    pub fn anonymous(n_sources: u64, n_symbols: u64) -> Result<Self> {
+        let (header, total) = new_header(n_sources, n_symbols);
+        let mut mmap = MmapOptions::new().len(total as usize).map_anon().context("Failed to map anonymous SHM")?;
+        mmap[..header.len()].copy_from_slice(&header);
+        Self::from_mmap(mmap, None, total)
+    }
+
+    /// File path, or "<anonymous>" for an anonymous mapping
+    pub fn path(&self) -> &str {
+        self.path.as_deref().unwrap_or("<anonymous>")
+    }
+
+    pub fn n_sources(&self) -> u64 {
+        self.n_sources
+    }
+
+    pub fn n_symbols(&self) -> u64 {
+        self.n_symbols
+    }
+
+    /// Which component writes each slot in this process
+    pub fn writer_claims(&self) -> &crate::writer::WriterClaims {
+        &self.writers
+    }
+
+    /// Read-only access to the slot for (source_id, symbol_id); writes go
+    /// through a `writer::WriterToken` claiming the slot
+    pub fn view(&self, source_id: u64, symbol_id: u64) -> Result<SlotView<'_>> {
+        self.get_slot(source_id, symbol_id).map(SlotView)
+    }
+
     /// Get slot for (source_id, symbol_id)
     #[inline(always)]
-    pub fn get_slot(&self, source_id: u64, symbol_id: u64) -> Result<&Quote64> {
+    pub(crate) fn get_slot(&self, source_id: u64, symbol_id: u64) -> Result<&Quote64> {
         if source_id >= self.n_sources {
             bail!("source_id {} out of range (max {})", source_id, self.n_sources);
         }
@@ -220,8 +881,27 @@ impl ShmManager {
         }
     }
 
+    /// Seqlock-consistent (symbol_id, snapshot) for every slot of a source;
+    /// snapshot is None only if the writer kept that slot busy through all retries
+    pub fn iter_slots(&self, source_id: u64) -> Result<impl Iterator<Item = (u64, Option<QuoteSnapshot>)> + '_> {
+        // SAFETY: records_base covers n_sources * n_symbols records for the mmap's lifetime
+        let row = unsafe { source_row(self.records_base, self.n_sources, self.n_symbols, source_id)? };
+        Ok(snapshot_row(row))
+    }
+
+    /// Consistent copies of the given symbols' quotes, in request order.
+    /// Each read is retry-bounded; a slot the writer kept busy through all
+    /// retries is left out (match results by `symbol_id`).
+    pub fn snapshot(&self, source_id: u64, symbol_ids: &[u64]) -> Result<Vec<QuoteSnapshot>> {
+        // SAFETY: records_base covers n_sources * n_symbols records for the mmap's lifetime
+        let row = unsafe { source_row(self.records_base, self.n_sources, self.n_symbols, source_id)? };
+        snapshot_symbols(row, symbol_ids)
+    }
+
     /// Initialize slot with constant fields
-    pub fn init_slot(&mut self, source_id: u64, symbol_id: u64) -> Result<()> {
+    /// Takes &self so several sources can share one mapping (see ShmRegistry);
+    /// must run before the source's writer starts writing
+    pub fn init_slot(&self, source_id: u64, symbol_id: u64) -> Result<()> {
         if source_id >= self.n_sources {
             bail!("source_id {} out of range", source_id);
         }
@@ -231,6 +911,7 @@ impl ShmManager {
 
         let idx = source_id * self.n_symbols + symbol_id;
 
+        // SAFETY: one writer per source, and it hasn't started writing yet
         unsafe {
             let ptr = self.records_base.add(idx as usize);
             (*ptr).init_slot(source_id, symbol_id);
@@ -238,41 +919,685 @@ impl ShmManager {
 
         Ok(())
     }
+
+    /// Record a per-symbol price scale override in the slot (after init_slot,
+    /// before the source's writer starts writing)
+    pub fn set_price_scale(&self, source_id: u64, symbol_id: u64, scale: u64) -> Result<()> {
+        let slot = self.get_slot(source_id, symbol_id)?;
+        let stored = match scale {
+            EXPECTED_PRICE_SCALE => 0,
+            scale => u32::try_from(scale).with_context(|| format!("Price scale {} too large", scale))?,
+        };
+        // SAFETY: same single-writer-before-start contract as init_slot
+        unsafe {
+            let ptr = slot as *const Quote64 as *mut Quote64;
+            std::ptr::write_volatile(&mut (*ptr).price_scale, stored);
+        }
+        Ok(())
+    }
+
+    /// Stamp a slot with a writer's ownership token if it still carries
+    /// `current`; the inner result is `compare_exchange`'s (the stamp found)
+    pub fn claim_slot(&self, source_id: u64, symbol_id: u64, current: u64, token: u64) -> Result<std::result::Result<u64, u64>> {
+        let slot = self.get_slot(source_id, symbol_id)?;
+        // SAFETY: `owner` is 8-byte aligned and only ever accessed atomically
+        let owner = unsafe { std::sync::atomic::AtomicU64::from_ptr(&slot.owner as *const u64 as *mut u64) };
+        Ok(owner.compare_exchange(current, token, std::sync::atomic::Ordering::AcqRel, std::sync::atomic::Ordering::Acquire))
+    }
+
+    /// Get the writer state block for a source from the header reserved space
+    pub fn source_state(&self, source_id: u64) -> Result<&SourceState> {
+        if source_id >= self.n_sources || source_id >= MAX_HEADER_SOURCES {
+            bail!("source_id {} has no header state block (max {})",
+                  source_id, self.n_sources.min(MAX_HEADER_SOURCES));
+        }
+
+        unsafe {
+            let ptr = self.header_base
+                .add((SOURCE_STATE_OFFSET + source_id * SOURCE_STATE_SIZE) as usize);
+            Ok(&*(ptr as *const SourceState))
+        }
+    }
+
+    /// Mark a source as faulted so readers stop trusting its data
+    pub fn mark_writer_fault(&self, source_id: u64) -> Result<()> {
+        self.source_state(source_id)?.set_flags(SOURCE_FLAG_WRITER_FAULT);
+        Ok(())
+    }
+
+    /// Re-validate the mapped header (fields and checksum) against the file's
+    /// current size, e.g. to catch another process corrupting or truncating it
+    pub fn verify_header(&self) -> Result<()> {
+        let file_size = match &self.path {
+            Some(path) => std::fs::metadata(path)
+                .with_context(|| format!("SHM file {} no longer accessible", path))?
+                .len(),
+            None => self.mmap.len() as u64,
+        };
+        // SAFETY: the mapping starts with the header
+        validate_header(unsafe { &*(self.header_base as *const ShmHeader) }, file_size)
+    }
+
+    /// Re-verify the header every `interval` on a background thread, logging
+    /// when it goes bad and when it is valid again
+    pub fn spawn_header_check(self: Arc<Self>, interval: std::time::Duration) -> Result<std::thread::JoinHandle<()>> {
+        std::thread::Builder::new()
+            .name("shm-hdrcheck".to_string())
+            .spawn(move || {
+                let mut valid = true;
+                loop {
+                    std::thread::sleep(interval);
+                    let result = self.verify_header();
+                    match (&result, valid) {
+                        (Err(e), true) => eprintln!("[SHM] ERROR: header of {} corrupted: {:#}", self.path(), e),
+                        (Ok(()), false) => eprintln!("[SHM] Header of {} valid again", self.path()),
+                        _ => {}
+                    }
+                    valid = result.is_ok();
+                }
+            })
+            .context("Failed to spawn header check thread")
+    }
+
+    /// Synchronously write dirty pages back to the file (msync MS_SYNC)
+    pub fn flush(&self) -> Result<()> {
+        self.mmap.flush().context("Failed to msync SHM")
+    }
+
+    /// Flush every `interval` on a background thread, off the write path
+    pub fn spawn_periodic_flush(self: Arc<Self>, interval: std::time::Duration) -> Result<std::thread::JoinHandle<()>> {
+        std::thread::Builder::new()
+            .name("shm-msync".to_string())
+            .spawn(move || loop {
+                std::thread::sleep(interval);
+                if let Err(e) = self.flush() {
+                    crate::eprintln_limited!("[SHM] {:#}", e);
+                }
+            })
+            .context("Failed to spawn msync thread")
+    }
+
+    /// Stamp `source_id`'s heartbeat every `interval` on a background thread,
+    /// so readers can age quotes against the wall clock
+    pub fn spawn_heartbeat(self: Arc<Self>, source_id: u64, interval: std::time::Duration) -> Result<std::thread::JoinHandle<()>> {
+        self.source_state(source_id)?;
+        std::thread::Builder::new()
+            .name("shm-heartbeat".to_string())
+            .spawn(move || loop {
+                if let Ok(state) = self.source_state(source_id) {
+                    state.set_heartbeat(Heartbeat { mono_us: monotonic_us(), unix_us: crate::persist::unix_now_us() });
+                }
+                std::thread::sleep(interval);
+            })
+            .context("Failed to spawn heartbeat thread")
+    }
 }
 
-/// Get monotonic timestamp in microseconds
-#[inline(always)]
-pub fn monotonic_us() -> i64 {
-    let mut ts = libc::timespec {
-        tv_sec: 0,
-        tv_nsec: 0,
-    };
-    unsafe {
-        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
+/// Read-only view of an SHM file
+///
+/// Maps the file without write access, so tooling can run under accounts that
+/// only have read permission. Exposes seqlock reads only: there is no way to
+/// get at a writable slot through it.
+pub struct ShmReader {
+    #[allow(dead_code)]
+    mmap: Mmap,
+    header_base: *const u8,
+    records_base: *const Quote64,
+    n_symbols: u64,
+    n_sources: u64,
+}
+
+unsafe impl Send for ShmReader {}
+unsafe impl Sync for ShmReader {}
+
+impl ShmReader {
+    /// Open and validate an SHM file read-only
+    pub fn open(path: &str) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .open(path)
+            .with_context(|| format!("Failed to open SHM file read-only: {}", path))?;
+        let file_size = file.metadata().context("Failed to get file metadata")?.len();
+
+        let mmap = unsafe { Mmap::map(&file).context("Failed to mmap file")? };
+        if (mmap.len() as u64) < EXPECTED_HEADER_SIZE {
+            bail!("SHM file too small for a header: {} bytes", mmap.len());
+        }
+
+        let header = unsafe { &*(mmap.as_ptr() as *const ShmHeader) };
+        validate_header(header, file_size)?;
+
+        let header_base = mmap.as_ptr();
+        let records_base = unsafe { header_base.add(header.records_offset as usize) as *const Quote64 };
+        let (n_symbols, n_sources) = (header.n_symbols, header.n_sources);
+
+        Ok(Self { mmap, header_base, records_base, n_symbols, n_sources })
+    }
+
+    pub fn n_sources(&self) -> u64 {
+        self.n_sources
+    }
+
+    pub fn n_symbols(&self) -> u64 {
+        self.n_symbols
+    }
+
+    /// Seqlock-read a slot: (source_id, symbol_id, bid, ask, ts),
+    /// None if the writer kept it busy for the whole retry budget
+    pub fn read(&self, source_id: u64, symbol_id: u64) -> Result<Option<SlotRead>> {
+        if source_id >= self.n_sources {
+            bail!("source_id {} out of range (max {})", source_id, self.n_sources);
+        }
+        if symbol_id >= self.n_symbols {
+            bail!("symbol_id {} out of range (max {})", symbol_id, self.n_symbols);
+        }
+
+        let idx = source_id * self.n_symbols + symbol_id;
+        // SAFETY: in range; Quote64::read only loads
+        Ok(unsafe { (*self.records_base.add(idx as usize)).read() })
+    }
+
+    /// Seqlock-consistent (symbol_id, snapshot) for every slot of a source
+    pub fn iter_slots(&self, source_id: u64) -> Result<impl Iterator<Item = (u64, Option<QuoteSnapshot>)> + '_> {
+        // SAFETY: records_base covers n_sources * n_symbols records for the mmap's lifetime
+        let row = unsafe { source_row(self.records_base, self.n_sources, self.n_symbols, source_id)? };
+        Ok(snapshot_row(row))
+    }
+
+    /// A slot, for its typed accessors (`SlotView::quote`); read-only, as the mapping is
+    pub fn slot(&self, source_id: u64, symbol_id: u64) -> Result<SlotView<'_>> {
+        // SAFETY: records_base covers n_sources * n_symbols records for the mmap's lifetime
+        let row = unsafe { source_row(self.records_base, self.n_sources, self.n_symbols, source_id)? };
+        row.get(symbol_id as usize)
+            .map(SlotView)
+            .with_context(|| format!("symbol_id {} out of range (max {})", symbol_id, self.n_symbols))
+    }
+
+    /// A slot's seqlock counter (even when no write is in progress); a
+    /// registered reader records it with `ReaderHandle::observe`
+    pub fn slot_seq(&self, source_id: u64, symbol_id: u64) -> Result<u64> {
+        Ok(self.slot(source_id, symbol_id)?.seq())
+    }
+
+    /// Consistent copies of the given symbols' quotes, in request order.
+    /// Each read is retry-bounded; a slot the writer kept busy through all
+    /// retries is left out (match results by `symbol_id`).
+    pub fn snapshot(&self, source_id: u64, symbol_ids: &[u64]) -> Result<Vec<QuoteSnapshot>> {
+        // SAFETY: records_base covers n_sources * n_symbols records for the mmap's lifetime
+        let row = unsafe { source_row(self.records_base, self.n_sources, self.n_symbols, source_id)? };
+        snapshot_symbols(row, symbol_ids)
+    }
+
+    fn source_state(&self, source_id: u64) -> Result<&SourceState> {
+        if source_id >= self.n_sources || source_id >= MAX_HEADER_SOURCES {
+            bail!("source_id {} has no header state block (max {})",
+                  source_id, self.n_sources.min(MAX_HEADER_SOURCES));
+        }
+
+        // SAFETY: in range; only the load methods are called on it
+        unsafe {
+            let ptr = self.header_base.add((SOURCE_STATE_OFFSET + source_id * SOURCE_STATE_SIZE) as usize);
+            Ok(&*(ptr as *const SourceState))
+        }
+    }
+
+    /// Re-validate the mapped header (fields and checksum); a reader that
+    /// gets an error should re-open the file rather than trust slot indices
+    pub fn verify_header(&self) -> Result<()> {
+        // SAFETY: the mapping starts with the header
+        validate_header(unsafe { &*(self.header_base as *const ShmHeader) }, self.mmap.len() as u64)
     }
-    ts.tv_sec * 1_000_000 + ts.tv_nsec / 1_000
+
+    /// SOURCE_FLAG_* bits of a source
+    pub fn source_flags(&self, source_id: u64) -> Result<u64> {
+        Ok(self.source_state(source_id)?.flags.load(Ordering::Acquire))
+    }
+
+    /// Writer build info recorded for a source
+    pub fn writer_info(&self, source_id: u64) -> Result<Option<WriterInfo>> {
+        Ok(self.source_state(source_id)?.writer_info())
+    }
+
+    /// Latest heartbeat of a source's writer, for quote ages across processes
+    pub fn heartbeat(&self, source_id: u64) -> Result<Option<Heartbeat>> {
+        Ok(self.source_state(source_id)?.heartbeat())
+    }
+}
+
+/// Routes sources to SHM files (e.g. quotes_binance_fut.dat / quotes_binance_spot.dat)
+///
+/// Each file is mapped once; sources registered with the same path share the mapping.
+#[derive(Default)]
+pub struct ShmRegistry {
+    files: HashMap<String, Arc<ShmManager>>,
+    /// Indexed by source_id
+    by_source: Vec<Option<Arc<ShmManager>>>,
+}
+
+impl ShmRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map `source_id` to the SHM file at `path`, opening it if not mapped yet
+    pub fn register(&mut self, source_id: u64, path: &str) -> Result<Arc<ShmManager>> {
+        if let Some(Some(existing)) = self.by_source.get(source_id as usize) {
+            if !self.files.get(path).is_some_and(|shm| Arc::ptr_eq(shm, existing)) {
+                bail!("source_id {} is already routed to a different SHM file", source_id);
+            }
+            return Ok(existing.clone());
+        }
+
+        let shm = match self.files.get(path) {
+            Some(shm) => shm.clone(),
+            None => {
+                let shm = Arc::new(ShmManager::open(path)?);
+                self.files.insert(path.to_string(), shm.clone());
+                shm
+            }
+        };
+        if source_id >= shm.n_sources {
+            bail!("source_id {} out of range for {} (max {})", source_id, path, shm.n_sources);
+        }
+
+        let idx = source_id as usize;
+        if self.by_source.len() <= idx {
+            self.by_source.resize(idx + 1, None);
+        }
+        self.by_source[idx] = Some(shm.clone());
+        Ok(shm)
+    }
+
+    /// SHM mapping for a source
+    #[inline(always)]
+    pub fn get(&self, source_id: u64) -> Result<&Arc<ShmManager>> {
+        match self.by_source.get(source_id as usize) {
+            Some(Some(shm)) => Ok(shm),
+            _ => bail!("source_id {} has no SHM route", source_id),
+        }
+    }
+
+    /// Every mapped file, once each
+    pub fn files(&self) -> impl Iterator<Item = &Arc<ShmManager>> + '_ {
+        self.files.values()
+    }
+
+    /// Registered source ids, ascending
+    pub fn sources(&self) -> impl Iterator<Item = u64> + '_ {
+        self.by_source
+            .iter()
+            .enumerate()
+            .filter(|(_, shm)| shm.is_some())
+            .map(|(id, _)| id as u64)
+    }
+}
+
+pub use crate::platform::monotonic_us;
+
+/// C header for non-Rust readers (`include/quotes_shm.h`): layout constants,
+/// `ShmHeader`, `SourceState` and `Quote64` with offsets taken from the Rust
+/// structs (checked by static asserts), and the seqlock read protocol as
+/// inline functions. Regenerate with `cargo run --bin shm_header`.
+pub fn c_header() -> String {
+    use std::fmt::Write;
+    use std::mem::{offset_of, size_of};
+
+    /// `typedef struct` with the Rust offset of each field asserted
+    fn c_struct(out: &mut String, name: &str, size: usize, fields: &[(&str, &str, usize)]) {
+        let _ = writeln!(out, "typedef struct {} {{", name);
+        for (c_type, field, offset) in fields {
+            let (c_type, array) = c_type.split_once('[').map_or((*c_type, String::new()), |(t, n)| (t, format!("[{}", n)));
+            let decl = format!("{} {}{};", c_type, field, array);
+            let _ = writeln!(out, "    {:<32}/* offset {} */", decl, offset);
+        }
+        let _ = writeln!(out, "}} {};\n", name);
+        let _ = writeln!(out, "QSHM_STATIC_ASSERT(sizeof({}) == {}, \"{} size\");", name, size, name);
+        for (_, field, offset) in fields {
+            let field = field.split('[').next().unwrap_or(field);
+            let _ = writeln!(out, "QSHM_STATIC_ASSERT(offsetof({}, {}) == {}, \"{}.{}\");", name, field, offset, name, field);
+        }
+        out.push('\n');
+    }
+
+    let mut out = String::new();
+    out.push_str(r#"/* quotes_shm.h - layout of the quotes SHM file for non-Rust readers
+ *
+ * Generated by `cargo run --bin shm_header` from src/shm.rs; do not edit.
+ * All fields are little-endian. A slot is read with the seqlock protocol in
+ * qshm_read_quote(): never trust a Quote64 copied without it.
+ */
+#ifndef QUOTES_SHM_H
+#define QUOTES_SHM_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+#define QSHM_STATIC_ASSERT(cond, msg) static_assert(cond, msg)
+extern "C" {
+#else
+#define QSHM_STATIC_ASSERT(cond, msg) _Static_assert(cond, msg)
+#endif
+
+"#);
+    let _ = writeln!(out, "#define QSHM_MAGIC \"{}\"", String::from_utf8_lossy(MAGIC).replace('\0', "\\0"));
+    for (name, value) in [
+        ("QSHM_HEADER_SIZE", EXPECTED_HEADER_SIZE),
+        ("QSHM_RECORD_SIZE", EXPECTED_RECORD_SIZE),
+        ("QSHM_RECORDS_OFFSET", EXPECTED_RECORDS_OFFSET),
+        ("QSHM_PRICE_SCALE", EXPECTED_PRICE_SCALE),
+        ("QSHM_TS_SCALE", EXPECTED_TS_SCALE),
+        ("QSHM_CHECKSUMMED_HEADER_BYTES", CHECKSUMMED_HEADER_BYTES as u64),
+        ("QSHM_SOURCE_STATE_OFFSET", SOURCE_STATE_OFFSET),
+        ("QSHM_SOURCE_STATE_SIZE", SOURCE_STATE_SIZE),
+        ("QSHM_MAX_HEADER_SOURCES", MAX_HEADER_SOURCES),
+        ("QSHM_SOURCE_FLAG_WRITER_FAULT", SOURCE_FLAG_WRITER_FAULT),
+    ] {
+        let _ = writeln!(out, "#define {} {}ULL", name, value);
+    }
+    out.push('\n');
+    out.push_str("/* Quote64.status bits */\n");
+    for (name, value) in [
+        ("QSHM_SLOT_STATUS_INIT", SLOT_STATUS_INIT),
+        ("QSHM_SLOT_STATUS_STALE", SLOT_STATUS_STALE),
+        ("QSHM_SLOT_STATUS_CROSSED", SLOT_STATUS_CROSSED),
+        ("QSHM_SLOT_STATUS_ZERO_PRICE", SLOT_STATUS_ZERO_PRICE),
+        ("QSHM_SLOT_STATUS_HALTED", SLOT_STATUS_HALTED),
+    ] {
+        let _ = writeln!(out, "#define {} {}u", name, value);
+    }
+    out.push('\n');
+
+    out.push_str("/* First bytes of the 4096-byte header; header_crc32 covers bytes 0..88 (0 = no checksum) */\n");
+    c_struct(&mut out, "qshm_header", size_of::<ShmHeader>(), &[
+        ("uint8_t[8]", "magic", offset_of!(ShmHeader, magic)),
+        ("uint64_t", "version", offset_of!(ShmHeader, version)),
+        ("uint64_t", "header_size", offset_of!(ShmHeader, header_size)),
+        ("uint64_t", "record_size", offset_of!(ShmHeader, record_size)),
+        ("uint64_t", "records_offset", offset_of!(ShmHeader, records_offset)),
+        ("uint64_t", "price_scale", offset_of!(ShmHeader, price_scale)),
+        ("uint64_t", "ts_scale", offset_of!(ShmHeader, ts_scale)),
+        ("uint64_t", "n_sources", offset_of!(ShmHeader, n_sources)),
+        ("uint64_t", "n_symbols", offset_of!(ShmHeader, n_symbols)),
+        ("uint64_t", "n_records", offset_of!(ShmHeader, n_records)),
+        ("uint64_t", "shm_total_size", offset_of!(ShmHeader, shm_total_size)),
+        ("uint64_t", "header_crc32", offset_of!(ShmHeader, header_crc32)),
+    ]);
+    out.push_str("/* Writer state of source s at QSHM_SOURCE_STATE_OFFSET + s * QSHM_SOURCE_STATE_SIZE */\n");
+    c_struct(&mut out, "qshm_source_state", size_of::<SourceState>(), &[
+        ("uint64_t", "flags", offset_of!(SourceState, flags)),
+        ("uint64_t", "started_at_unix_us", offset_of!(SourceState, started_at_unix_us)),
+        ("char[16]", "writer_version", offset_of!(SourceState, writer_version)),
+        ("char[16]", "git_sha", offset_of!(SourceState, git_sha)),
+        ("char[32]", "source_name", offset_of!(SourceState, source_name)),
+        ("uint64_t", "heartbeat_seq", offset_of!(SourceState, heartbeat_seq)),
+        ("uint64_t", "heartbeat_mono_us", offset_of!(SourceState, heartbeat_mono_us)),
+        ("uint64_t", "heartbeat_unix_us", offset_of!(SourceState, heartbeat_unix_us)),
+        ("uint64_t[3]", "reserved", offset_of!(SourceState, reserved)),
+    ]);
+    out.push_str("/* Slot of (source_id, symbol_id) at QSHM_RECORDS_OFFSET + (source_id * n_symbols + symbol_id) * 64 */\n");
+    c_struct(&mut out, "qshm_quote64", size_of::<Quote64>(), &[
+        ("uint64_t", "seq", offset_of!(Quote64, seq)),
+        ("uint64_t", "source_id", offset_of!(Quote64, source_id)),
+        ("uint64_t", "symbol_id", offset_of!(Quote64, payload.symbol_id)),
+        ("int64_t", "bid", offset_of!(Quote64, payload.bid)),
+        ("int64_t", "ask", offset_of!(Quote64, payload.ask)),
+        ("int64_t", "ts", offset_of!(Quote64, payload.ts)),
+        ("uint32_t", "price_scale", offset_of!(Quote64, price_scale)),
+        ("uint32_t", "status", offset_of!(Quote64, status)),
+        ("uint64_t", "owner", offset_of!(Quote64, owner)),
+    ]);
+
+    out.push_str(r#"/* Consistent copy of a slot */
+typedef struct qshm_quote {
+    uint64_t source_id;
+    uint64_t symbol_id;
+    int64_t bid;        /* * price_scale (0 = QSHM_PRICE_SCALE) */
+    int64_t ask;
+    int64_t ts;         /* writer monotonic µs */
+    uint32_t price_scale;
+    uint32_t status;
+} qshm_quote;
+
+static inline const qshm_quote64 *qshm_slot(const void *base, uint64_t source_id, uint64_t symbol_id) {
+    const qshm_header *header = (const qshm_header *)base;
+    if (source_id >= header->n_sources || symbol_id >= header->n_symbols) {
+        return NULL;
+    }
+    return (const qshm_quote64 *)((const char *)base + QSHM_RECORDS_OFFSET) + source_id * header->n_symbols + symbol_id;
+}
+
+/*
+ * Seqlock read (the writer makes seq odd, stores the fields, makes it even):
+ *   1. s1 = seq (acquire); odd means a write is in progress: retry
+ *   2. copy the fields
+ *   3. acquire fence, then re-read seq: unchanged means the copy is consistent
+ * seq 0 means the slot was never written. Returns 1 on success, 0 if the slot
+ * stayed busy for `max_retries` attempts or was never written.
+ */
+static inline int qshm_read_quote(const qshm_quote64 *slot, qshm_quote *out, int max_retries) {
+    for (int i = 0; i < max_retries; i++) {
+        uint64_t s1 = __atomic_load_n(&slot->seq, __ATOMIC_ACQUIRE);
+        if (s1 & 1) {
+            continue;
+        }
+        const volatile qshm_quote64 *v = slot;
+        out->source_id = v->source_id;
+        out->symbol_id = v->symbol_id;
+        out->bid = v->bid;
+        out->ask = v->ask;
+        out->ts = v->ts;
+        out->price_scale = v->price_scale;
+        out->status = __atomic_load_n(&slot->status, __ATOMIC_RELAXED);
+        __atomic_thread_fence(__ATOMIC_ACQUIRE);
+        if (__atomic_load_n(&slot->seq, __ATOMIC_RELAXED) == s1) {
+            return s1 != 0;
+        }
+    }
+    return 0;
+}
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* QUOTES_SHM_H */
+"#);
+    out
+}
+
+/// Create a temp SHM file with a valid header (no checksum yet, as
+/// written before checksums existed); shared by the crate's unit tests
+#[cfg(test)]
+pub(crate) fn create_test_shm(name: &str, n_sources: u64, n_symbols: u64) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("shm_test_{}_{}.dat", name, std::process::id()));
+    let n_records = n_sources * n_symbols;
+    let total = EXPECTED_RECORDS_OFFSET + n_records * EXPECTED_RECORD_SIZE;
+
+    let mut buf = vec![0u8; total as usize];
+    buf[0..8].copy_from_slice(MAGIC);
+    let fields = [
+        1, EXPECTED_HEADER_SIZE, EXPECTED_RECORD_SIZE, EXPECTED_RECORDS_OFFSET,
+        EXPECTED_PRICE_SCALE, EXPECTED_TS_SCALE, n_sources, n_symbols, n_records, total,
+    ];
+    for (i, v) in fields.iter().enumerate() {
+        buf[8 + i * 8..16 + i * 8].copy_from_slice(&v.to_le_bytes());
+    }
+    std::fs::write(&path, buf).unwrap();
+    path
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_source_state_flags() {
+        let path = create_test_shm("source_state", 2, 4);
+        let shm = ShmManager::open(path.to_str().unwrap()).unwrap();
+
+        let state = shm.source_state(1).unwrap();
+        assert!(!state.has_flags(SOURCE_FLAG_WRITER_FAULT));
+        shm.mark_writer_fault(1).unwrap();
+        assert!(state.has_flags(SOURCE_FLAG_WRITER_FAULT));
+        state.clear_flags(SOURCE_FLAG_WRITER_FAULT);
+        assert!(!state.has_flags(SOURCE_FLAG_WRITER_FAULT));
+
+        assert_eq!(state.writer_info(), None);
+        let info = WriterInfo {
+            version: "0.1.0".to_string(),
+            git_sha: "0123456789abcdef-too-long".to_string(),
+            source_name: "binance_futures".to_string(),
+            started_at_unix_us: 1_700_000_000_000_000,
+        };
+        state.set_writer_info(&info);
+        let read = state.writer_info().unwrap();
+        assert_eq!(read.git_sha, "0123456789abcdef");
+        assert_eq!(read.source_name, "binance_futures");
+        assert_eq!(read.started_at_unix_us, info.started_at_unix_us);
+        assert!(!state.has_flags(SOURCE_FLAG_WRITER_FAULT));
+
+        // Out of range sources have no state block
+        assert!(shm.source_state(2).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let path = create_test_shm("heartbeat", 2, 4);
+        let path = path.to_str().unwrap();
+        let shm = Arc::new(ShmManager::open(path).unwrap());
+        let state = shm.source_state(1).unwrap();
+        assert_eq!(state.heartbeat(), None);
+
+        let heartbeat = Heartbeat { mono_us: 5_000_000, unix_us: 1_700_000_000_000_000 };
+        state.set_heartbeat(heartbeat);
+        assert_eq!(state.heartbeat(), Some(heartbeat));
+        // Written 1.5 s before the heartbeat, read 0.5 s after it
+        assert_eq!(heartbeat.quote_age_us(3_500_000, 1_700_000_000_500_000), 2_000_000);
+
+        assert!(shm.clone().spawn_heartbeat(2, std::time::Duration::from_millis(1)).is_err());
+        shm.clone().spawn_heartbeat(0, std::time::Duration::from_millis(1)).unwrap();
+        let reader = ShmManager::open_readonly(path).unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let beat = loop {
+            if let Some(beat) = reader.heartbeat(0).unwrap() {
+                break beat;
+            }
+            assert!(std::time::Instant::now() < deadline, "no heartbeat");
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        };
+        assert!(beat.mono_us <= monotonic_us() && beat.unix_us <= crate::persist::unix_now_us());
+        assert_eq!(reader.heartbeat(1).unwrap(), Some(heartbeat));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_header_checksum() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+        let fresh = ShmManager::anonymous(2, 4).unwrap();
+        let header = unsafe { &*(fresh.header_base as *const ShmHeader) };
+        assert_eq!(header.header_crc32, header.compute_crc32() as u64);
+
+        // Files from before checksums are stamped by the writer on open
+        let path = create_test_shm("checksum", 2, 4);
+        let path = path.to_str().unwrap();
+        ShmManager::open_readonly(path).unwrap();
+        let shm = ShmManager::open(path).unwrap();
+        let header = unsafe { &*(shm.header_base as *const ShmHeader) };
+        assert_eq!(header.header_crc32, header.compute_crc32() as u64);
+        shm.verify_header().unwrap();
+
+        // Swapped counts keep n_records and the file size plausible
+        let n_symbols = std::ptr::addr_of!(header.n_symbols) as *mut u64;
+        let n_sources = std::ptr::addr_of!(header.n_sources) as *mut u64;
+        unsafe {
+            std::ptr::write_volatile(n_symbols, 2);
+            std::ptr::write_volatile(n_sources, 4);
+        }
+        assert!(format!("{:#}", shm.verify_header().unwrap_err()).contains("checksum mismatch"));
+        assert!(ShmManager::open_readonly(path).is_err());
+        unsafe {
+            std::ptr::write_volatile(n_symbols, 4);
+            std::ptr::write_volatile(n_sources, 2);
+        }
+        let reader = ShmManager::open_readonly(path).unwrap();
+        reader.verify_header().unwrap();
+
+        // Truncation
+        std::fs::OpenOptions::new().write(true).open(path).unwrap().set_len(4096 + 64).unwrap();
+        assert!(shm.verify_header().is_err());
+        drop((shm, reader));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_slot_status() {
+        let path = create_test_shm("slot_status", 2, 4);
+        let shm = ShmManager::open(path.to_str().unwrap()).unwrap();
+        shm.init_slot(1, 2).unwrap();
+        let slot = shm.get_slot(1, 2).unwrap();
+        assert_eq!(slot.status(), SLOT_STATUS_INIT);
+
+        slot.update_status(SLOT_STATUS_HALTED, SLOT_STATUS_HALTED);
+        slot.write_with_status(200, 100, 1, SLOT_STATUS_QUOTE_MASK, quote_status(200, 100));
+        assert_eq!(slot.status(), SLOT_STATUS_HALTED | SLOT_STATUS_CROSSED);
+        slot.write_with_status(0, 100, 2, SLOT_STATUS_QUOTE_MASK, quote_status(0, 100));
+        assert_eq!(slot.status(), SLOT_STATUS_HALTED | SLOT_STATUS_ZERO_PRICE);
+        slot.update_status(SLOT_STATUS_HALTED | SLOT_STATUS_STALE, SLOT_STATUS_STALE);
+        slot.write(100, 200, 3);
+        assert_eq!(slot.status(), SLOT_STATUS_ZERO_PRICE | SLOT_STATUS_STALE, "plain writes keep quote bits");
+        assert_eq!(slot.read_with_status(), Some(((1, 2, 100, 200, 3), SLOT_STATUS_ZERO_PRICE | SLOT_STATUS_STALE)));
+        assert_eq!(quote_status(100, 100), 0);
+
+        // Status shares the old 64-bit price_scale word without disturbing it
+        shm.set_price_scale(1, 2, 10_000).unwrap();
+        assert_eq!(slot.price_scale(), 10_000);
+        assert!(shm.set_price_scale(1, 2, 1 << 40).is_err());
+        shm.init_slot(1, 2).unwrap();
+        assert_eq!((slot.status(), slot.price_scale()), (SLOT_STATUS_INIT, EXPECTED_PRICE_SCALE));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
     #[test]
     fn test_quote64_size() {
         assert_eq!(std::mem::size_of::<Quote64>(), 64);
     }
 
+    #[test]
+    fn test_payload_layout() {
+        let mut quote = Quote64 {
+            seq: AtomicU64::new(0),
+            source_id: 0,
+            payload: QuotePayload { symbol_id: 0, bid: 0, ask: 0, ts: 0 },
+            price_scale: 0,
+            status: 0,
+            owner: 0,
+        };
+        quote.init_slot(1, 7);
+        quote.write(-5, 9, 123);
+
+        // The block copy lands at the ABI offsets and leaves the neighbours alone
+        let bytes = unsafe { std::slice::from_raw_parts(&quote as *const Quote64 as *const u8, 64) };
+        let word = |off: usize| i64::from_le_bytes(bytes[off..off + 8].try_into().unwrap());
+        assert_eq!((word(8), word(16), word(24), word(32), word(40)), (1, 7, -5, 9, 123));
+        assert_eq!((quote.price_scale(), quote.status(), quote.owner()), (EXPECTED_PRICE_SCALE, 0, 0));
+        assert_eq!(quote.read(), Some((1, 7, -5, 9, 123)));
+    }
+
     #[test]
     fn test_seqlock() {
         let quote = Quote64 {
             seq: AtomicU64::new(0),
             source_id: 1,
-            symbol_id: 10,
-            bid: 0,
-            ask: 0,
-            ts: 0,
-            reserved0: 0,
-            reserved1: 0,
+            payload: QuotePayload { symbol_id: 10, bid: 0, ask: 0, ts: 0 },
+            price_scale: 0,
+            status: 0,
+            owner: 0,
         };
 
         // Write
@@ -288,4 +1613,172 @@ mod tests {
         assert_eq!(ask, 10000100000);
         assert_eq!(ts, 123456789);
     }
+
+    #[test]
+    fn test_create() {
+        let path = std::env::temp_dir().join(format!("shm_test_create_{}.dat", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let shm = ShmManager::create(path, 2, 8, 0o640, None).unwrap();
+        shm.init_slot(1, 7).unwrap();
+        assert_eq!(shm.get_slot(1, 7).unwrap().read(), Some((1, 7, 0, 0, 0)));
+        assert!(shm.get_slot(2, 0).is_err());
+        assert_eq!(std::fs::metadata(path).unwrap().len(), 4096 + 16 * 64);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(path).unwrap().permissions().mode() & 0o777, 0o640);
+        }
+
+        // Never clobbers an existing file
+        assert!(ShmManager::create(path, 2, 8, 0o640, None).is_err());
+        let bad_group = path.replace(".dat", "_g.dat");
+        assert!(ShmManager::create(&bad_group, 1, 1, 0o640, Some("no-such-group-xyz")).is_err());
+        assert!(!std::path::Path::new(&bad_group).exists());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_anonymous() {
+        let shm = ShmManager::anonymous(2, 8).unwrap();
+        assert_eq!((shm.n_sources(), shm.n_symbols(), shm.path()), (2, 8, "<anonymous>"));
+        shm.verify_header().unwrap();
+        shm.init_slot(1, 7).unwrap();
+        shm.get_slot(1, 7).unwrap().write(100, 101, 5);
+        assert_eq!(shm.snapshot(1, &[7]).unwrap(), vec![QuoteSnapshot { source_id: 1, symbol_id: 7, bid: 100, ask: 101, ts: 5 }]);
+        shm.flush().unwrap();
+        assert!(ShmManager::anonymous(1, 1).unwrap().get_slot(0, 1).is_err());
+    }
+
+    #[test]
+    fn test_iter_slots_and_snapshot() {
+        let path = create_test_shm("iter_slots", 2, 3);
+        let shm = ShmManager::open(path.to_str().unwrap()).unwrap();
+        for symbol_id in 0..3 {
+            shm.init_slot(1, symbol_id).unwrap();
+        }
+        shm.get_slot(1, 2).unwrap().write(200, 201, 5);
+
+        let slots: Vec<_> = shm.iter_slots(1).unwrap().collect();
+        assert_eq!(slots.len(), 3);
+        assert_eq!(slots[0], (0, Some(QuoteSnapshot { source_id: 1, symbol_id: 0, bid: 0, ask: 0, ts: 0 })));
+        assert_eq!(slots[2], (2, Some(QuoteSnapshot { source_id: 1, symbol_id: 2, bid: 200, ask: 201, ts: 5 })));
+        assert!(shm.iter_slots(2).is_err());
+
+        let reader = ShmManager::open_readonly(path.to_str().unwrap()).unwrap();
+        assert!(reader.iter_slots(1).unwrap().eq(shm.iter_slots(1).unwrap()));
+
+        let snap = shm.snapshot(1, &[2, 0]).unwrap();
+        assert_eq!(snap.iter().map(|q| (q.symbol_id, q.bid)).collect::<Vec<_>>(), vec![(2, 200), (0, 0)]);
+        assert_eq!(reader.snapshot(1, &[2, 0]).unwrap(), snap);
+        assert!(shm.snapshot(1, &[3]).is_err());
+        assert!(reader.snapshot(2, &[0]).is_err());
+
+        // A slot stuck mid-write (odd seq) is left out after the retry budget
+        shm.get_slot(1, 1).unwrap().seq.store(1, Ordering::Relaxed);
+        assert_eq!(shm.snapshot(1, &[1, 2]).unwrap().len(), 1);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_open_readonly() {
+        let path = create_test_shm("readonly", 2, 4);
+        let path = path.to_str().unwrap();
+
+        let writer = ShmManager::open(path).unwrap();
+        writer.init_slot(1, 3).unwrap();
+        writer.get_slot(1, 3).unwrap().write(10, 11, 12);
+        writer.mark_writer_fault(1).unwrap();
+        writer.set_price_scale(1, 2, 10_000).unwrap();
+        writer.get_slot(1, 2).unwrap().write(12_345, 12_346, 7);
+
+        // Read permission is enough
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o444)).unwrap();
+        }
+
+        let reader = ShmManager::open_readonly(path).unwrap();
+        assert_eq!((reader.n_sources(), reader.n_symbols()), (2, 4));
+        assert_eq!(reader.read(1, 3).unwrap(), Some((1, 3, 10, 11, 12)));
+        assert!(reader.read(2, 0).is_err());
+        assert!(reader.slot(1, 4).is_err());
+        assert_eq!(reader.source_flags(1).unwrap(), SOURCE_FLAG_WRITER_FAULT);
+        assert_eq!(reader.writer_info(1).unwrap(), None);
+
+        // Typed accessors carry the slot's scale
+        let slot = reader.slot(1, 3).unwrap();
+        assert_eq!((slot.source_id(), slot.symbol_id(), slot.seq()), (1, 3, 2));
+        let (bid, _, ts) = slot.quote().unwrap();
+        assert_eq!((bid, ts.as_micros(), ts.age_us(20)), (Price::from_1e8(10), 12, Some(8)));
+        let (bid, ask, _) = reader.slot(1, 2).unwrap().quote().unwrap();
+        assert_eq!((bid.to_f64(), ask.to_1e8()), (1.2345, Some(123_460_000)));
+        assert!(!reader.slot(1, 0).unwrap().quote().unwrap().2.is_written());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_registry_routes_sources_to_files() {
+        let fut = create_test_shm("registry_fut", 3, 4);
+        let spot = create_test_shm("registry_spot", 3, 4);
+        let (fut, spot) = (fut.to_str().unwrap(), spot.to_str().unwrap());
+
+        let mut registry = ShmRegistry::new();
+        let a = registry.register(1, fut).unwrap();
+        let b = registry.register(2, spot).unwrap();
+        let c = registry.register(0, fut).unwrap();
+        assert!(Arc::ptr_eq(&a, &c));
+        assert!(!Arc::ptr_eq(&a, &b));
+        assert!(registry.register(1, spot).is_err());
+        assert!(registry.register(5, spot).is_err());
+        assert!(registry.get(3).is_err());
+        assert_eq!(registry.sources().collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        registry.get(1).unwrap().init_slot(1, 2).unwrap();
+        registry.get(2).unwrap().init_slot(2, 2).unwrap();
+        registry.get(1).unwrap().get_slot(1, 2).unwrap().write(100, 101, 7);
+        assert_eq!(registry.get(1).unwrap().get_slot(1, 2).unwrap().read(), Some((1, 2, 100, 101, 7)));
+        assert_eq!(registry.get(2).unwrap().get_slot(2, 2).unwrap().read(), Some((2, 2, 0, 0, 0)));
+        // Same slot index in the other file is untouched
+        assert_eq!(registry.get(2).unwrap().get_slot(1, 2).unwrap().read(), Some((0, 0, 0, 0, 0)));
+
+        std::fs::remove_file(fut).unwrap();
+        std::fs::remove_file(spot).unwrap();
+    }
+
+    #[test]
+    fn test_msync_policy_and_flush() {
+        assert_eq!("periodic".parse::<MsyncPolicy>().unwrap(), MsyncPolicy::Periodic);
+        assert_eq!("Shutdown".parse::<MsyncPolicy>().unwrap(), MsyncPolicy::OnShutdown);
+        assert_eq!("on-shutdown".parse::<MsyncPolicy>().unwrap(), MsyncPolicy::OnShutdown);
+        assert_eq!("never".parse::<MsyncPolicy>().unwrap(), MsyncPolicy::default());
+        assert!("always".parse::<MsyncPolicy>().is_err());
+
+        let path = create_test_shm("msync", 2, 2);
+        let shm = ShmManager::open(path.to_str().unwrap()).unwrap();
+        shm.init_slot(1, 1).unwrap();
+        shm.get_slot(1, 1).unwrap().write(0x1234_5678, 0x1234_5679, 9);
+        shm.flush().unwrap();
+
+        let file = std::fs::read(&path).unwrap();
+        let offset = (EXPECTED_RECORDS_OFFSET + 3 * EXPECTED_RECORD_SIZE) as usize;
+        let record = &file[offset..offset + EXPECTED_RECORD_SIZE as usize];
+        assert!(record.windows(8).any(|w| w == 0x1234_5678i64.to_le_bytes()));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[cfg(not(feature = "loom"))]
+    fn test_c_header_up_to_date() {
+        let header = c_header();
+        assert!(header.contains("QSHM_STATIC_ASSERT(offsetof(qshm_quote64, ts) == 40, \"qshm_quote64.ts\");"));
+        assert!(header.contains("QSHM_STATIC_ASSERT(sizeof(qshm_source_state) == 128, \"qshm_source_state size\");"));
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/include/quotes_shm.h");
+        let checked_in = std::fs::read_to_string(path).unwrap();
+        assert!(checked_in == header, "{} is out of date: cargo run --bin shm_header > include/quotes_shm.h", path);
+    }
 }
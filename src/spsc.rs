@@ -0,0 +1,145 @@
+//! Bounded single-producer/single-consumer ring buffer
+//!
+//! Used by the decode pipeline to hand raw frames from a WebSocket read loop to
+//! the decoder thread. One ring per connection keeps it strictly SPSC: the
+//! producer only stores `tail`, the consumer only stores `head`.
+
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Keeps head and tail on separate cache lines
+#[repr(align(64))]
+struct Padded(AtomicUsize);
+
+struct Ring<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    mask: usize,
+    /// Next slot to pop (written by the consumer)
+    head: Padded,
+    /// Next slot to push (written by the producer)
+    tail: Padded,
+}
+
+// Slots are only touched by the side that owns them per head/tail
+unsafe impl<T: Send> Send for Ring<T> {}
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        let tail = *self.tail.0.get_mut();
+        let mut head = *self.head.0.get_mut();
+        while head != tail {
+            unsafe { self.slots[head & self.mask].get_mut().assume_init_drop() };
+            head = head.wrapping_add(1);
+        }
+    }
+}
+
+/// Sending half
+pub struct Producer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+/// Receiving half
+pub struct Consumer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+/// Create a ring holding `capacity` items (rounded up to a power of two)
+pub fn channel<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    let capacity = capacity.max(2).next_power_of_two();
+    let slots = (0..capacity).map(|_| UnsafeCell::new(MaybeUninit::uninit())).collect();
+    let ring = Arc::new(Ring {
+        slots,
+        mask: capacity - 1,
+        head: Padded(AtomicUsize::new(0)),
+        tail: Padded(AtomicUsize::new(0)),
+    });
+    (Producer { ring: ring.clone() }, Consumer { ring })
+}
+
+impl<T> Producer<T> {
+    /// Enqueue, or hand the value back if the ring is full
+    #[inline]
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        let ring = &*self.ring;
+        let tail = ring.tail.0.load(Ordering::Relaxed);
+        if tail.wrapping_sub(ring.head.0.load(Ordering::Acquire)) > ring.mask {
+            return Err(value);
+        }
+        unsafe { (*ring.slots[tail & ring.mask].get()).write(value) };
+        // Publish the slot contents with the new tail
+        ring.tail.0.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+}
+
+impl<T> Consumer<T> {
+    /// Dequeue the oldest item, if any
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        let ring = &*self.ring;
+        let head = ring.head.0.load(Ordering::Relaxed);
+        if head == ring.tail.0.load(Ordering::Acquire) {
+            return None;
+        }
+        let value = unsafe { (*ring.slots[head & ring.mask].get()).assume_init_read() };
+        // Release the slot back to the producer only after it's been read
+        ring.head.0.store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+
+    /// Items currently queued
+    pub fn len(&self) -> usize {
+        let tail = self.ring.tail.0.load(Ordering::Acquire);
+        tail.wrapping_sub(self.ring.head.0.load(Ordering::Relaxed))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fifo_and_full() {
+        let (mut tx, mut rx) = channel(3);
+        for i in 0..4 {
+            tx.push(i).unwrap();
+        }
+        assert_eq!(tx.push(4), Err(4));
+        assert_eq!(rx.len(), 4);
+        assert_eq!(rx.pop(), Some(0));
+        tx.push(4).unwrap();
+        assert_eq!((1..5).map(|_| rx.pop().unwrap()).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!(rx.pop(), None);
+    }
+
+    #[test]
+    fn test_cross_thread_order() {
+        let (mut tx, mut rx) = channel(64);
+        let producer = std::thread::spawn(move || {
+            for i in 0..100_000u64 {
+                let mut value = i.to_string();
+                while let Err(back) = tx.push(value) {
+                    value = back;
+                    std::hint::spin_loop();
+                }
+            }
+        });
+        let mut expected = 0u64;
+        while expected < 100_000 {
+            if let Some(value) = rx.pop() {
+                assert_eq!(value, expected.to_string());
+                expected += 1;
+            }
+        }
+        producer.join().unwrap();
+        assert!(rx.is_empty());
+    }
+}
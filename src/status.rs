@@ -0,0 +1,153 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::persist::{unix_now, write_json_atomic};
+use crate::watchdog::{SlaWatchdog, TierStatus};
+use crate::ws::PerfStats;
+
+/// Crate version
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Git commit the binary was built from ("unknown" outside a checkout)
+pub const GIT_SHA: &str = env!("GIT_SHA");
+
+/// Machine-readable writer status for fleet inventory tooling
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WriterStatus {
+    pub version: String,
+    pub git_sha: String,
+    pub pid: u32,
+    pub started_at_unix: u64,
+    pub updated_at_unix: u64,
+    /// Hash of the effective runtime configuration (see config_digest)
+    pub config_digest: String,
+    pub shm_path: String,
+    pub source_id: u64,
+    pub subscribed_symbols: usize,
+    pub connections: usize,
+    pub total_messages: u64,
+    pub reconnects: u64,
+    /// Per-tier staleness (empty when the SLA watchdog is off)
+    #[serde(default)]
+    pub sla_tiers: Vec<TierStatus>,
+}
+
+/// Stable 64-bit FNV-1a digest of the configuration structs' Debug output,
+/// so two writers with the same digest run with identical settings
+pub fn config_digest(parts: &[&dyn Debug]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for byte in format!("{:?}\n", part).bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+/// Keeps the status file up to date with live counters
+pub struct StatusWriter {
+    path: String,
+    base: WriterStatus,
+    stats: Vec<Arc<PerfStats>>,
+    watchdog: Option<Arc<SlaWatchdog>>,
+}
+
+impl StatusWriter {
+    /// `base` holds the static fields; counters are summed over `stats`
+    pub fn new(path: String, base: WriterStatus, stats: Vec<Arc<PerfStats>>) -> Self {
+        Self { path, base, stats, watchdog: None }
+    }
+
+    /// Include the watchdog's tier metrics
+    pub fn with_watchdog(mut self, watchdog: Arc<SlaWatchdog>) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
+    /// Current status with refreshed counters
+    pub fn status(&self) -> WriterStatus {
+        let mut status = self.base.clone();
+        status.updated_at_unix = unix_now();
+        for stats in &self.stats {
+            let snap = stats.snapshot();
+            status.total_messages += snap.total_messages;
+            status.reconnects += snap.reconnects;
+        }
+        if let Some(watchdog) = &self.watchdog {
+            status.sla_tiers = watchdog.status();
+        }
+        status
+    }
+
+    pub fn flush(&self) -> Result<()> {
+        write_json_atomic(&self.path, &self.status())
+    }
+
+    /// Spawn the periodic update task
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.flush() {
+                    eprintln!("[WARN] Failed to write status file: {:#}", e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_digest_stable() {
+        let a = config_digest(&[&1u32, &"x"]);
+        assert_eq!(a, config_digest(&[&1u32, &"x"]));
+        assert_ne!(a, config_digest(&[&2u32, &"x"]));
+        assert_eq!(a.len(), 16);
+    }
+
+    #[test]
+    fn test_status_counters_summed() {
+        let path = std::env::temp_dir().join(format!("status_test_{}.json", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+
+        let main_stats = Arc::new(PerfStats::new());
+        let prio_stats = Arc::new(PerfStats::new());
+        main_stats.record(10);
+        prio_stats.record(10);
+        prio_stats.record_reconnect();
+
+        let base = WriterStatus {
+            version: VERSION.to_string(),
+            git_sha: GIT_SHA.to_string(),
+            pid: std::process::id(),
+            started_at_unix: unix_now(),
+            updated_at_unix: 0,
+            config_digest: config_digest(&[&"cfg"]),
+            shm_path: "/dev/shm/quotes_v1.dat".to_string(),
+            source_id: 1,
+            subscribed_symbols: 3,
+            connections: 2,
+            total_messages: 0,
+            reconnects: 0,
+            sla_tiers: Vec::new(),
+        };
+        let writer = StatusWriter::new(path.clone(), base, vec![main_stats, prio_stats]);
+        writer.flush().unwrap();
+
+        let loaded: WriterStatus = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(loaded.total_messages, 2);
+        assert_eq!(loaded.reconnects, 1);
+        assert_eq!(loaded.connections, 2);
+        assert!(loaded.updated_at_unix > 0);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,16 +1,43 @@
 use std::collections::HashMap;
 use std::fs;
+use std::str::FromStr;
 use anyhow::{bail, Context, Result};
 
 /// Symbol mapping: symbol name -> symbol_id
 pub type SymbolMap = HashMap<String, u64>;
 
+/// A symbols.tsv row: id and name, plus the optional metadata columns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolInfo {
+    pub symbol_id: u64,
+    pub symbol: String,
+    /// Price tick * 1e8
+    pub tick_size: Option<i64>,
+    /// Quantity step * 1e8
+    pub lot_size: Option<i64>,
+    pub quote_asset: Option<String>,
+    /// Venue trading status (TRADING, SETTLING, ...)
+    pub status: Option<String>,
+}
+
 /// Load symbols.tsv file
-/// Format: <symbol_id>\t<SYMBOL>
+/// Format: <symbol_id>\t<SYMBOL>[\t<tick_size>\t<lot_size>\t<quote_asset>\t<status>]
 pub fn load_symbols_tsv(path: &str) -> Result<SymbolMap> {
+    Ok(load_symbols_info(path)?.into_iter().map(|(symbol, info)| (symbol, info.symbol_id)).collect())
+}
+
+/// Load symbols.tsv with its metadata columns, keyed by symbol
+pub fn load_symbols_info(path: &str) -> Result<HashMap<String, SymbolInfo>> {
     let content = fs::read_to_string(path)
         .with_context(|| format!("Failed to read symbols file: {}", path))?;
+    let map = parse_symbols_tsv(&content)?;
+    eprintln!("[SYMBOLS] Loaded {} symbols from {} ({} with tick size)",
+              map.len(), path, map.values().filter(|info| info.tick_size.is_some()).count());
+    Ok(map)
+}
 
+/// Parse symbols.tsv content; metadata columns may be omitted, empty or `-`
+pub fn parse_symbols_tsv(content: &str) -> Result<HashMap<String, SymbolInfo>> {
     let mut map = HashMap::new();
 
     for (line_num, line) in content.lines().enumerate() {
@@ -21,43 +48,202 @@ pub fn load_symbols_tsv(path: &str) -> Result<SymbolMap> {
             continue;
         }
 
-        let parts: Vec<&str> = line.split('\t').collect();
-        if parts.len() != 2 {
-            bail!("Invalid format at line {}: expected <id>\\t<symbol>, got: {}", line_num + 1, line);
+        let parts: Vec<&str> = line.split('\t').map(str::trim).collect();
+        if parts.len() < 2 || parts.len() > 6 {
+            bail!("Invalid format at line {}: expected <id>\\t<symbol>[\\t<tick>\\t<lot>\\t<quote>\\t<status>], got: {}",
+                  line_num + 1, line);
         }
 
         let symbol_id: u64 = parts[0].parse()
             .with_context(|| format!("Invalid symbol_id at line {}: {}", line_num + 1, parts[0]))?;
         let symbol = parts[1].to_uppercase();
+        let column = |i: usize| parts.get(i).copied().filter(|v| !v.is_empty() && *v != "-");
+        let step = |i: usize, name: &str| -> Result<Option<i64>> {
+            column(i)
+                .map(|v| match crate::price::parse_price_i64_1e8(v) {
+                    Ok(step) if step > 0 => Ok(step),
+                    _ => bail!("Invalid {} at line {}: {}", name, line_num + 1, v),
+                })
+                .transpose()
+        };
 
-        if map.insert(symbol.clone(), symbol_id).is_some() {
+        let info = SymbolInfo {
+            symbol_id,
+            symbol: symbol.clone(),
+            tick_size: step(2, "tick size")?,
+            lot_size: step(3, "lot size")?,
+            quote_asset: column(4).map(str::to_uppercase),
+            status: column(5).map(str::to_uppercase),
+        };
+        if map.insert(symbol.clone(), info).is_some() {
             bail!("Duplicate symbol: {}", symbol);
         }
     }
 
-    eprintln!("[SYMBOLS] Loaded {} symbols from {}", map.len(), path);
     Ok(map)
 }
 
-/// Load subscribe list file
-/// Format: one symbol per line
-pub fn load_subscribe_list(path: &str) -> Result<Vec<String>> {
+/// Market data stream a subscribe entry asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    BookTicker,
+    AggTrade,
+    Depth,
+}
+
+impl FromStr for StreamKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "bookticker" => Ok(Self::BookTicker),
+            "aggtrade" => Ok(Self::AggTrade),
+            "depth" => Ok(Self::Depth),
+            _ => bail!("Unknown stream {:?} (expected bookTicker, aggTrade or depth)", s),
+        }
+    }
+}
+
+/// Per-symbol options of a subscribe file line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolOptions {
+    pub streams: Vec<StreamKind>,
+    /// Goes on the priority feed (merged with PRIORITY_SYMBOLS)
+    pub priority: bool,
+    /// Re-broadcast conflation interval (overrides BROADCAST_CONFLATE_MS; 0 = every update)
+    pub conflate_ms: Option<u64>,
+    /// Price decimals (merged with PRICE_SCALE_OVERRIDES, which win)
+    pub scale_decimals: Option<u32>,
+}
+
+impl Default for SymbolOptions {
+    fn default() -> Self {
+        Self { streams: vec![StreamKind::BookTicker], priority: false, conflate_ms: None, scale_decimals: None }
+    }
+}
+
+/// One subscribed symbol with its options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscribeEntry {
+    pub symbol: String,
+    pub options: SymbolOptions,
+}
+
+/// Parse one subscribe file line; None for blank and comment lines
+///
+/// Format: `SYMBOL [streams=bookTicker,aggTrade,depth] [priority] [conflate_ms=N] [scale=1e4]`
+/// with `#` starting a comment. A bare symbol is the v1 format.
+pub fn parse_subscribe_line(line: &str) -> Result<Option<SubscribeEntry>> {
+    let line = line.split('#').next().unwrap_or("").trim();
+    let mut fields = line.split_whitespace();
+    let Some(symbol) = fields.next() else {
+        return Ok(None);
+    };
+
+    let mut options = SymbolOptions::default();
+    for field in fields {
+        match field.split_once('=') {
+            None if field == "priority" => options.priority = true,
+            Some(("streams", list)) => {
+                options.streams = list.split(',').map(str::parse).collect::<Result<_>>()?;
+                options.streams.dedup();
+            }
+            Some(("conflate_ms", ms)) => {
+                options.conflate_ms = Some(ms.parse().with_context(|| format!("Invalid conflate_ms: {:?}", ms))?);
+            }
+            Some(("scale", scale)) => options.scale_decimals = Some(crate::price::parse_scale(scale)?),
+            _ => bail!("Unknown option {:?}", field),
+        }
+    }
+    Ok(Some(SubscribeEntry { symbol: symbol.to_uppercase(), options }))
+}
+
+/// Load subscribe file with per-symbol options (see `parse_subscribe_line`)
+pub fn load_subscribe_file(path: &str) -> Result<Vec<SubscribeEntry>> {
     let content = fs::read_to_string(path)
         .with_context(|| format!("Failed to read subscribe file: {}", path))?;
 
-    let symbols: Vec<String> = content
-        .lines()
-        .map(|line| line.trim())
-        .filter(|line| !line.is_empty())
-        .map(|line| line.to_uppercase())
-        .collect();
+    let mut entries = Vec::new();
+    for (line_num, line) in content.lines().enumerate() {
+        let entry = parse_subscribe_line(line)
+            .with_context(|| format!("Invalid subscribe entry at line {}: {}", line_num + 1, line.trim()))?;
+        entries.extend(entry);
+    }
 
-    if symbols.is_empty() {
+    if entries.is_empty() {
         bail!("Subscribe list is empty: {}", path);
     }
 
-    eprintln!("[SUBSCRIBE] Loaded {} symbols from {}", symbols.len(), path);
-    Ok(symbols)
+    // A repeated symbol would be subscribed (and written) twice
+    let mut seen = std::collections::HashSet::new();
+    let mut duplicates = Vec::new();
+    entries.retain(|entry| {
+        let first = seen.insert(entry.symbol.clone());
+        if !first {
+            duplicates.push(entry.symbol.clone());
+        }
+        first
+    });
+    if !duplicates.is_empty() {
+        eprintln!("[SUBSCRIBE] WARNING: duplicate symbols ignored: {}", duplicates.join(", "));
+    }
+
+    eprintln!("[SUBSCRIBE] Loaded {} symbols from {}", entries.len(), path);
+    Ok(entries)
+}
+
+/// Symbols of the entries that want the bookTicker stream, in file order
+pub fn book_ticker_symbols(entries: &[SubscribeEntry]) -> Vec<String> {
+    entries
+        .iter()
+        .filter(|entry| entry.options.streams.contains(&StreamKind::BookTicker))
+        .map(|entry| entry.symbol.clone())
+        .collect()
+}
+
+/// Load subscribe list file: the symbols to take bookTicker quotes for
+pub fn load_subscribe_list(path: &str) -> Result<Vec<String>> {
+    Ok(Universe::load(path)?.book_ticker)
+}
+
+/// The subscribe file as loaded at startup: every entry with its options and
+/// the bookTicker symbols among them
+#[derive(Debug, Clone)]
+pub struct Universe {
+    entries: Vec<SubscribeEntry>,
+    book_ticker: Vec<String>,
+}
+
+impl Universe {
+    /// Load `path`; errors if it has no bookTicker symbols
+    pub fn load(path: &str) -> Result<Self> {
+        let entries = load_subscribe_file(path)?;
+        let book_ticker = book_ticker_symbols(&entries);
+        if book_ticker.is_empty() {
+            bail!("No bookTicker symbols in subscribe list: {}", path);
+        }
+        Ok(Self { entries, book_ticker })
+    }
+
+    /// Every subscribed symbol with its options, in file order
+    pub fn entries(&self) -> &[SubscribeEntry] {
+        &self.entries
+    }
+
+    /// Symbols to take bookTicker quotes for, in file order
+    pub fn book_ticker(&self) -> &[String] {
+        &self.book_ticker
+    }
+
+    /// Symbols requesting `kind`, in file order
+    pub fn with_stream(&self, kind: StreamKind) -> Vec<String> {
+        self.entries.iter().filter(|e| e.options.streams.contains(&kind)).map(|e| e.symbol.clone()).collect()
+    }
+
+    /// Symbols marked `priority`
+    pub fn priority(&self) -> Vec<String> {
+        self.entries.iter().filter(|e| e.options.priority).map(|e| e.symbol.clone()).collect()
+    }
 }
 
 /// Validate that all subscribe symbols exist in symbol map
@@ -73,16 +259,74 @@ pub fn validate_symbols(subscribe_list: &[String], symbol_map: &SymbolMap) -> Re
 /// Create symbol_id lookup map from subscribe list
 pub fn create_symbol_id_map(subscribe_list: &[String], symbol_map: &SymbolMap) -> Result<HashMap<String, u64>> {
     let mut result = HashMap::new();
+    let mut owners: HashMap<u64, &str> = HashMap::new();
 
     for symbol in subscribe_list {
         let symbol_id = symbol_map.get(symbol)
             .ok_or_else(|| anyhow::anyhow!("Symbol {} not found in symbols.tsv", symbol))?;
+        // Two symbols on one symbol_id would overwrite each other's slot
+        if let Some(other) = owners.insert(*symbol_id, symbol) {
+            if other != symbol {
+                bail!("Symbols {} and {} share symbol_id {} in symbols.tsv", other, symbol, symbol_id);
+            }
+        }
         result.insert(symbol.clone(), *symbol_id);
     }
 
     Ok(result)
 }
 
+/// Split the subscribe list into (priority, rest), preserving order.
+/// Priority symbols that aren't subscribed are returned separately so callers can warn.
+pub fn partition_priority(subscribe_list: &[String], priority: &[String]) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let (prio, rest): (Vec<String>, Vec<String>) = subscribe_list
+        .iter()
+        .cloned()
+        .partition(|s| priority.contains(s));
+
+    let missing = priority
+        .iter()
+        .filter(|s| !subscribe_list.contains(s))
+        .cloned()
+        .collect();
+
+    (prio, rest, missing)
+}
+
+/// Change between two subscribe lists
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubscribeDiff {
+    /// In the new list only, in its order
+    pub added: Vec<String>,
+    /// In the old list only, in its order
+    pub removed: Vec<String>,
+}
+
+impl SubscribeDiff {
+    pub fn between(old: &[String], new: &[String]) -> Self {
+        Self {
+            added: new.iter().filter(|s| !old.contains(s)).cloned().collect(),
+            removed: old.iter().filter(|s| !new.contains(s)).cloned().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl std::fmt::Display for SubscribeDiff {
+    /// "+NEWUSDT -OLDUSDT", or "no changes"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no changes");
+        }
+        let added = self.added.iter().map(|s| format!("+{}", s));
+        let removed = self.removed.iter().map(|s| format!("-{}", s));
+        write!(f, "{}", added.chain(removed).collect::<Vec<_>>().join(" "))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,4 +341,107 @@ mod tests {
         assert_eq!(map.get("ETHUSDT"), Some(&2));
         assert_eq!(map.get("XRPUSDT"), None);
     }
+
+    #[test]
+    fn test_symbols_tsv_metadata() {
+        let map = parse_symbols_tsv("1\tBTCUSDT\n2\tethusdt\t0.01\t0.001\tusdt\tTRADING\n3\tXRPUSDT\t0.0001\t-\t\n").unwrap();
+        assert_eq!(map["BTCUSDT"], SymbolInfo {
+            symbol_id: 1, symbol: "BTCUSDT".to_string(), tick_size: None, lot_size: None, quote_asset: None, status: None,
+        });
+        assert_eq!(map["ETHUSDT"], SymbolInfo {
+            symbol_id: 2,
+            symbol: "ETHUSDT".to_string(),
+            tick_size: Some(1_000_000),
+            lot_size: Some(100_000),
+            quote_asset: Some("USDT".to_string()),
+            status: Some("TRADING".to_string()),
+        });
+        assert_eq!((map["XRPUSDT"].tick_size, map["XRPUSDT"].lot_size), (Some(10_000), None));
+
+        for bad in ["1", "1\tX\t0.1\t1\tUSDT\tTRADING\textra", "1\tX\tabc", "1\tX\t0", "1\tX\n2\tX"] {
+            assert!(parse_symbols_tsv(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_subscriptions() {
+        let path = std::env::temp_dir().join(format!("subscribe_dup_{}.txt", std::process::id()));
+        std::fs::write(&path, "BTCUSDT\nETHUSDT\nbtcusdt streams=depth\nXRPUSDT\nETHUSDT\n").unwrap();
+        let entries = load_subscribe_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(entries.len(), 3, "the first occurrence wins, options and all");
+        let unique = book_ticker_symbols(&entries);
+        assert_eq!(unique, vec!["BTCUSDT", "ETHUSDT", "XRPUSDT"]);
+        std::fs::remove_file(path).unwrap();
+
+        let map = SymbolMap::from([("BTCUSDT".to_string(), 1), ("XBTUSDT".to_string(), 1), ("ETHUSDT".to_string(), 2)]);
+        assert!(create_symbol_id_map(&unique[..2], &map).is_ok());
+        let shared = vec!["BTCUSDT".to_string(), "XBTUSDT".to_string()];
+        assert!(create_symbol_id_map(&shared, &map).is_err());
+    }
+
+    #[test]
+    fn test_subscribe_line_options() {
+        assert_eq!(parse_subscribe_line("  # comment").unwrap(), None);
+        assert_eq!(parse_subscribe_line("btcusdt").unwrap().unwrap(),
+                   SubscribeEntry { symbol: "BTCUSDT".to_string(), options: SymbolOptions::default() });
+
+        let entry = parse_subscribe_line("SHIBUSDT streams=bookTicker,aggTrade priority conflate_ms=100 scale=1e4 # meme")
+            .unwrap().unwrap();
+        assert_eq!(entry.options, SymbolOptions {
+            streams: vec![StreamKind::BookTicker, StreamKind::AggTrade],
+            priority: true,
+            conflate_ms: Some(100),
+            scale_decimals: Some(4),
+        });
+
+        for bad in ["X streams=trades", "X scale=5000", "X conflate_ms=-1", "X fast"] {
+            assert!(parse_subscribe_line(bad).is_err(), "{}", bad);
+        }
+
+        let entries: Vec<SubscribeEntry> = ["BTCUSDT", "ETHUSDT streams=depth", "XRPUSDT streams=depth,bookTicker"]
+            .iter().map(|line| parse_subscribe_line(line).unwrap().unwrap()).collect();
+        assert_eq!(book_ticker_symbols(&entries), vec!["BTCUSDT", "XRPUSDT"]);
+    }
+
+    #[test]
+    fn test_universe() {
+        let path = std::env::temp_dir().join(format!("universe_{}.txt", std::process::id()));
+        std::fs::write(&path, "BTCUSDT priority\nETHUSDT streams=depth\nXRPUSDT streams=depth,bookTicker\n").unwrap();
+        let universe = Universe::load(path.to_str().unwrap()).unwrap();
+        assert_eq!(universe.entries().len(), 3);
+        assert_eq!(universe.book_ticker(), ["BTCUSDT", "XRPUSDT"]);
+        assert_eq!(universe.with_stream(StreamKind::Depth), vec!["ETHUSDT", "XRPUSDT"]);
+        assert_eq!(universe.priority(), vec!["BTCUSDT"]);
+
+        std::fs::write(&path, "ETHUSDT streams=depth\n").unwrap();
+        assert!(Universe::load(path.to_str().unwrap()).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_partition_priority() {
+        let subscribe: Vec<String> = ["BTCUSDT", "XRPUSDT", "ETHUSDT", "DOGEUSDT"]
+            .iter().map(|s| s.to_string()).collect();
+        let priority: Vec<String> = ["ETHUSDT", "BTCUSDT", "SOLUSDT"]
+            .iter().map(|s| s.to_string()).collect();
+
+        let (prio, rest, missing) = partition_priority(&subscribe, &priority);
+        assert_eq!(prio, vec!["BTCUSDT", "ETHUSDT"]);
+        assert_eq!(rest, vec!["XRPUSDT", "DOGEUSDT"]);
+        assert_eq!(missing, vec!["SOLUSDT"]);
+    }
+
+    #[test]
+    fn test_subscribe_diff() {
+        let list = |symbols: &[&str]| symbols.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let diff = SubscribeDiff::between(&list(&["BTCUSDT", "XRPUSDT", "ETHUSDT"]), &list(&["SOLUSDT", "ETHUSDT", "BTCUSDT", "ADAUSDT"]));
+        assert_eq!(diff.added, list(&["SOLUSDT", "ADAUSDT"]));
+        assert_eq!(diff.removed, list(&["XRPUSDT"]));
+        assert_eq!(diff.to_string(), "+SOLUSDT +ADAUSDT -XRPUSDT");
+
+        // Reordering isn't a change
+        let diff = SubscribeDiff::between(&list(&["BTCUSDT", "ETHUSDT"]), &list(&["ETHUSDT", "BTCUSDT"]));
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "no changes");
+    }
 }
@@ -0,0 +1,189 @@
+//! Synthetic cross rates derived from two subscribed legs
+//!
+//! `ETHBTC = ETHUSDT / BTCUSDT`: on every update of either leg the cross is
+//! recomputed from the legs' SHM slots and written to its own symbol_id.
+//! Division is exact in i128 fixed point; the bid rounds down and the ask
+//! rounds up, so the synthetic spread is never narrower than the real one.
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::config::SyntheticPair;
+use crate::shm::{quote_status, ShmManager, SLOT_STATUS_QUOTE_MASK};
+use crate::writer::WriterToken;
+
+/// Fixed-point scale of SHM prices (1e8)
+const PRICE_SCALE: i128 = 100_000_000;
+
+struct Cross {
+    symbol: String,
+    symbol_id: u64,
+    base_id: u64,
+    quote_id: u64,
+    /// Legs may be written from different threads (priority feed); the cross
+    /// slot must still have a single writer at a time
+    write_lock: Mutex<()>,
+}
+
+/// Cross quote from leg quotes (all * 1e8): (bid, ask), None if a leg has no
+/// usable price yet or the result doesn't fit
+pub fn cross_quote(base_bid: i64, base_ask: i64, quote_bid: i64, quote_ask: i64) -> Option<(i64, i64)> {
+    if base_bid <= 0 || base_ask <= 0 || quote_bid <= 0 || quote_ask <= 0 {
+        return None;
+    }
+    // Sell base for quote-currency, buy the quote leg back at its ask (and vice versa)
+    let bid = base_bid as i128 * PRICE_SCALE / quote_ask as i128;
+    let ask = div_ceil(base_ask as i128 * PRICE_SCALE, quote_bid as i128);
+    Some((i64::try_from(bid).ok()?, i64::try_from(ask).ok()?))
+}
+
+/// Ceiling division for a non-negative numerator and positive divisor
+fn div_ceil(num: i128, den: i128) -> i128 {
+    (num + den - 1) / den
+}
+
+/// Computes and writes the configured crosses
+pub struct CrossEngine {
+    shm: Arc<ShmManager>,
+    source_id: u64,
+    crosses: Vec<Cross>,
+    /// Leg symbol_id -> indexes into `crosses`
+    by_leg: Vec<Vec<usize>>,
+    writer: WriterToken,
+}
+
+impl CrossEngine {
+    /// Resolve `pairs` (legs must be subscribed, the cross itself listed in
+    /// symbols.tsv but not subscribed) and initialize the cross slots
+    pub fn new(
+        shm: Arc<ShmManager>,
+        source_id: u64,
+        pairs: &[SyntheticPair],
+        symbol_map: &HashMap<String, u64>,
+        subscribed: &HashMap<String, u64>,
+    ) -> Result<Self> {
+        let mut crosses = Vec::new();
+        for pair in pairs {
+            if subscribed.contains_key(&pair.symbol) {
+                bail!("Synthetic {} is also subscribed", pair.symbol);
+            }
+            let symbol_id = *symbol_map.get(&pair.symbol)
+                .with_context(|| format!("Synthetic {} not found in symbols.tsv", pair.symbol))?;
+            let leg = |leg: &str| subscribed.get(leg).copied()
+                .with_context(|| format!("Leg {} of synthetic {} is not subscribed", leg, pair.symbol));
+            let (base_id, quote_id) = (leg(&pair.base)?, leg(&pair.quote)?);
+            for id in [base_id, quote_id] {
+                if shm.get_slot(source_id, id)?.price_scale() != PRICE_SCALE as u64 {
+                    bail!("Legs of synthetic {} must use the default 1e8 price scale", pair.symbol);
+                }
+            }
+
+            shm.init_slot(source_id, symbol_id)
+                .with_context(|| format!("Failed to init slot for synthetic {}", pair.symbol))?;
+            crosses.push(Cross { symbol: pair.symbol.clone(), symbol_id, base_id, quote_id, write_lock: Mutex::new(()) });
+        }
+
+        let n_legs = crosses.iter().map(|c| c.base_id.max(c.quote_id) as usize + 1).max().unwrap_or(0);
+        let mut by_leg = vec![Vec::new(); n_legs];
+        for (i, cross) in crosses.iter().enumerate() {
+            by_leg[cross.base_id as usize].push(i);
+            if cross.quote_id != cross.base_id {
+                by_leg[cross.quote_id as usize].push(i);
+            }
+        }
+        let ids: Vec<u64> = crosses.iter().map(|c| c.symbol_id).collect();
+        let writer = WriterToken::claim(shm.clone(), "synthetic", source_id, &ids)?;
+        Ok(Self { shm, source_id, crosses, by_leg, writer })
+    }
+
+    /// (symbol, symbol_id) of every cross
+    pub fn symbols(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.crosses.iter().map(|c| (c.symbol.as_str(), c.symbol_id))
+    }
+
+    /// Recompute the crosses using `symbol_id` after it was written at `ts`;
+    /// returns how many were written
+    #[inline]
+    pub fn on_update(&self, symbol_id: u64, ts: i64) -> usize {
+        let Some(dependents) = self.by_leg.get(symbol_id as usize) else {
+            return 0;
+        };
+        let mut written = 0;
+        for &i in dependents {
+            let cross = &self.crosses[i];
+            let _guard = cross.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+            let (Some(base), Some(quote)) = (self.read(cross.base_id), self.read(cross.quote_id)) else {
+                continue;
+            };
+            let Some((bid, ask)) = cross_quote(base.0, base.1, quote.0, quote.1) else {
+                continue;
+            };
+            if let Ok(slot) = self.writer.slot(cross.symbol_id) {
+                slot.write_with_status(bid, ask, ts, SLOT_STATUS_QUOTE_MASK, quote_status(bid, ask));
+                written += 1;
+            }
+        }
+        written
+    }
+
+    /// Consistent (bid, ask) of a leg
+    fn read(&self, symbol_id: u64) -> Option<(i64, i64)> {
+        let quote = self.shm.get_slot(self.source_id, symbol_id).ok()?.snapshot()?;
+        Some((quote.bid, quote.ask))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SyntheticConfig;
+
+    #[test]
+    fn test_cross_quote_rounding() {
+        // 3000 / 60000 = 0.05 exactly
+        assert_eq!(cross_quote(300_000_000_000, 300_000_000_000, 6_000_000_000_000, 6_000_000_000_000),
+                   Some((5_000_000, 5_000_000)));
+        // 1 / 3 = 0.333333333..: bid down, ask up
+        assert_eq!(cross_quote(100_000_000, 100_000_000, 300_000_000, 300_000_000), Some((33_333_333, 33_333_334)));
+        // Bid uses the quote leg's ask, ask its bid
+        assert_eq!(cross_quote(200_000_000, 300_000_000, 100_000_000, 200_000_000), Some((100_000_000, 300_000_000)));
+        assert_eq!(cross_quote(100_000_000, 100_000_000, 0, 100_000_000), None);
+        assert_eq!(cross_quote(i64::MAX, i64::MAX, 1, 1), None);
+    }
+
+    #[test]
+    fn test_engine_writes_cross_slot() {
+        let pairs = SyntheticConfig::parse_pairs("ethbtc = ETHUSDT/BTCUSDT; bad; X=/Y");
+        assert_eq!(pairs, vec![SyntheticPair {
+            symbol: "ETHBTC".to_string(), base: "ETHUSDT".to_string(), quote: "BTCUSDT".to_string(),
+        }]);
+
+        let shm = Arc::new(ShmManager::anonymous(2, 4).unwrap());
+        let symbol_map: HashMap<String, u64> =
+            [("BTCUSDT", 0), ("ETHUSDT", 1), ("ETHBTC", 3)].map(|(s, id)| (s.to_string(), id)).into();
+        let subscribed: HashMap<String, u64> = [("BTCUSDT", 0), ("ETHUSDT", 1)].map(|(s, id)| (s.to_string(), id)).into();
+        for id in [0, 1] {
+            shm.init_slot(1, id).unwrap();
+        }
+
+        shm.set_price_scale(1, 0, 10_000).unwrap();
+        assert!(CrossEngine::new(shm.clone(), 1, &pairs, &symbol_map, &subscribed).is_err());
+        shm.set_price_scale(1, 0, 100_000_000).unwrap();
+
+        let unknown = [SyntheticPair { symbol: "NOPE".to_string(), ..pairs[0].clone() }];
+        assert!(CrossEngine::new(shm.clone(), 1, &unknown, &symbol_map, &subscribed).is_err());
+        let engine = CrossEngine::new(shm.clone(), 1, &pairs, &symbol_map, &subscribed).unwrap();
+        assert_eq!(engine.symbols().collect::<Vec<_>>(), vec![("ETHBTC", 3)]);
+
+        // Only one leg has a price yet
+        shm.get_slot(1, 1).unwrap().write(300_000_000_000, 300_100_000_000, 10);
+        assert_eq!(engine.on_update(1, 10), 0);
+        shm.get_slot(1, 0).unwrap().write(6_000_000_000_000, 6_000_100_000_000, 20);
+        assert_eq!(engine.on_update(0, 20), 1);
+        assert_eq!(engine.on_update(2, 20), 0);
+
+        let quote = shm.get_slot(1, 3).unwrap().snapshot().unwrap();
+        assert_eq!((quote.symbol_id, quote.bid, quote.ask, quote.ts), (3, 4_999_916, 5_001_667, 20));
+    }
+}
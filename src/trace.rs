@@ -0,0 +1,453 @@
+//! OpenTelemetry trace spans over OTLP/HTTP (JSON encoding)
+//!
+//! For reconstructing incident timelines in a tracing backend: what startup
+//! spent its time on, when each connection reconnected and why, how long the
+//! TCP + TLS + WebSocket handshake and the resubscription took, and which REST
+//! bootstrap requests (depth snapshots) ran in between. Spans:
+//! - `startup`, with `startup.symbols` and `startup.shm` children, ended once
+//!   the feeds are started;
+//! - `ws.connect` (first session) / `ws.reconnect` per connection attempt,
+//!   with `ws.handshake` and `ws.subscribe` children, ended when the session
+//!   streams (or with an error when it ends before);
+//! - `ws.resubscribe` per SUBSCRIBE / UNSUBSCRIBE / LIST_SUBSCRIPTIONS request
+//!   on a live session, ended by its response;
+//! - `rest.depth_snapshot` per order book snapshot fetched.
+//!
+//! Off unless OTEL_EXPORTER_OTLP_ENDPOINT is set. Finished spans are queued
+//! without blocking (dropped when OTEL_BSP_MAX_QUEUE_SIZE are pending) and
+//! POSTed to `<endpoint>/v1/traces` every OTEL_BSP_SCHEDULE_DELAY ms.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value as Json};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::http::Uri;
+
+use crate::config::TraceConfig;
+use crate::rest::RestClient;
+
+/// Spans per export request
+const MAX_BATCH: usize = 512;
+/// OTLP span kinds
+const KIND_INTERNAL: u8 = 1;
+const KIND_CLIENT: u8 = 3;
+
+/// Span attribute value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Bool(bool),
+}
+
+impl From<&str> for Value {
+    fn from(v: &str) -> Self {
+        Self::Str(v.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Self {
+        Self::Str(v)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(v: i64) -> Self {
+        Self::Int(v)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(v: u64) -> Self {
+        Self::Int(v as i64)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Self::Bool(v)
+    }
+}
+
+/// Where finished spans go
+#[derive(Debug)]
+struct Sink {
+    spans: mpsc::Sender<Span>,
+    dropped: AtomicU64,
+}
+
+/// One operation; exported when `end` is called (a span dropped without it is not)
+#[derive(Debug)]
+pub struct Span {
+    trace_id: u128,
+    span_id: u64,
+    parent_id: Option<u64>,
+    name: &'static str,
+    kind: u8,
+    start_ns: u64,
+    end_ns: u64,
+    attributes: Vec<(&'static str, Value)>,
+    /// Error description (None = ok)
+    error: Option<String>,
+    /// None when tracing is off
+    sink: Option<Arc<Sink>>,
+}
+
+impl Span {
+    /// Whether the span will be exported (attributes are skipped when not)
+    pub fn is_recording(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    pub fn attr(&mut self, key: &'static str, value: impl Into<Value>) {
+        if self.is_recording() {
+            self.attributes.push((key, value.into()));
+        }
+    }
+
+    /// Mark the operation failed
+    pub fn fail(&mut self, error: impl Into<String>) {
+        if self.is_recording() {
+            self.error = Some(error.into());
+        }
+    }
+
+    /// A client call to another service (REST, WebSocket handshake)
+    pub fn client(mut self) -> Self {
+        self.kind = KIND_CLIENT;
+        self
+    }
+
+    /// Move the start back by `elapsed`, for operations timed elsewhere
+    pub fn started_before(mut self, elapsed: Duration) -> Self {
+        self.start_ns = self.start_ns.saturating_sub(elapsed.as_nanos() as u64);
+        self
+    }
+
+    /// Finish now and queue for export
+    pub fn end(mut self) {
+        let Some(sink) = self.sink.take() else {
+            return;
+        };
+        self.end_ns = unix_ns().max(self.start_ns);
+        if sink.spans.try_send(self).is_err() {
+            sink.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Creates spans and exports the finished ones
+pub struct Tracer {
+    sink: Option<Arc<Sink>>,
+    service: String,
+    /// Taken by `start` (or `take_finished`)
+    finished: Mutex<Option<mpsc::Receiver<Span>>>,
+    /// Collector client and request path (None = no collector)
+    collector: Mutex<Option<(RestClient, String)>>,
+    interval: Duration,
+    /// Span ids are a bijective mix of this counter, so never repeat
+    next_id: AtomicU64,
+    /// Random high half of trace ids, per process
+    trace_base: u64,
+    exported: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl Tracer {
+    /// Spans are not recorded
+    pub fn disabled() -> Self {
+        Self {
+            sink: None,
+            service: String::new(),
+            finished: Mutex::new(None),
+            collector: Mutex::new(None),
+            interval: Duration::MAX,
+            next_id: AtomicU64::new(1),
+            trace_base: 0,
+            exported: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        }
+    }
+
+    /// Records up to `queue` finished spans for `take_finished`, without a collector
+    pub fn new(service: &str, queue: usize) -> Self {
+        let (spans, finished) = mpsc::channel(queue.max(1));
+        let seed = unix_ns() ^ ((std::process::id() as u64) << 32);
+        Self {
+            sink: Some(Arc::new(Sink { spans, dropped: AtomicU64::new(0) })),
+            service: service.to_string(),
+            finished: Mutex::new(Some(finished)),
+            next_id: AtomicU64::new(mix(seed)),
+            trace_base: mix(!seed),
+            ..Self::disabled()
+        }
+    }
+
+    /// Exporting to OTEL_EXPORTER_OTLP_ENDPOINT, or disabled if unset
+    pub fn from_config(config: &TraceConfig) -> Result<Self> {
+        let Some(endpoint) = &config.endpoint else {
+            return Ok(Self::disabled());
+        };
+        let uri: Uri = endpoint.parse().with_context(|| format!("Invalid OTLP endpoint: {}", endpoint))?;
+        let path = format!("{}/v1/traces", uri.path().trim_end_matches('/'));
+        let client = RestClient::new(endpoint, Duration::from_secs(10))?;
+        eprintln!("[INIT] Trace spans to {}{} as {} every {} ms", endpoint, path, config.service_name, config.interval_ms);
+        Ok(Self {
+            collector: Mutex::new(Some((client, path))),
+            interval: Duration::from_millis(config.interval_ms),
+            ..Self::new(&config.service_name, config.queue)
+        })
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    /// Root span of a new trace
+    pub fn start(&self, name: &'static str) -> Span {
+        let trace_id = ((self.trace_base as u128) << 64) | self.id() as u128;
+        self.span(trace_id, None, name)
+    }
+
+    /// Span under `parent`, in its trace
+    pub fn child(&self, parent: &Span, name: &'static str) -> Span {
+        self.span(parent.trace_id, Some(parent.span_id), name)
+    }
+
+    fn span(&self, trace_id: u128, parent_id: Option<u64>, name: &'static str) -> Span {
+        let recording = self.enabled();
+        Span {
+            trace_id,
+            span_id: if recording { self.id() } else { 0 },
+            parent_id,
+            name,
+            kind: KIND_INTERNAL,
+            start_ns: if recording { unix_ns() } else { 0 },
+            end_ns: 0,
+            attributes: Vec::new(),
+            error: None,
+            sink: self.sink.clone(),
+        }
+    }
+
+    fn id(&self) -> u64 {
+        mix(self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Finished spans not yet exported (all of them when there is no collector)
+    pub fn take_finished(&self) -> Vec<Span> {
+        let mut finished = self.finished.lock().unwrap_or_else(|e| e.into_inner());
+        let mut spans = Vec::new();
+        if let Some(finished) = finished.as_mut() {
+            while let Ok(span) = finished.try_recv() {
+                spans.push(span);
+            }
+        }
+        spans
+    }
+
+    /// (exported, dropped when the queue was full, lost in failed exports)
+    pub fn counts(&self) -> (u64, u64, u64) {
+        let dropped = self.sink.as_ref().map_or(0, |sink| sink.dropped.load(Ordering::Relaxed));
+        (self.exported.load(Ordering::Relaxed), dropped, self.failed.load(Ordering::Relaxed))
+    }
+
+    /// Export the finished spans every interval (no-op without a collector); call once
+    pub fn spawn_export(self: Arc<Self>) {
+        let collector = self.collector.lock().unwrap_or_else(|e| e.into_inner()).take();
+        let finished = self.finished.lock().unwrap_or_else(|e| e.into_inner()).take();
+        let (Some((mut client, path)), Some(mut finished)) = (collector, finished) else {
+            return;
+        };
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            loop {
+                ticker.tick().await;
+                let mut batch = Vec::new();
+                while let Ok(span) = finished.try_recv() {
+                    batch.push(span);
+                    if batch.len() == MAX_BATCH {
+                        self.export(&mut client, &path, &mut batch).await;
+                    }
+                }
+                if !batch.is_empty() {
+                    self.export(&mut client, &path, &mut batch).await;
+                }
+            }
+        });
+    }
+
+    async fn export(&self, client: &mut RestClient, path: &str, batch: &mut Vec<Span>) {
+        let body = self.encode(batch).to_string();
+        let n = batch.len() as u64;
+        batch.clear();
+        let error = match client.post(path, "application/json", body.as_bytes()).await {
+            Ok(response) if (200..300).contains(&response.status) => {
+                self.exported.fetch_add(n, Ordering::Relaxed);
+                return;
+            }
+            Ok(response) => format!("HTTP {}: {}", response.status, response.body.trim()),
+            Err(e) => format!("{:#}", e),
+        };
+        self.failed.fetch_add(n, Ordering::Relaxed);
+        crate::eprintln_limited!("[WARN] Failed to export {} trace spans: {}", n, error);
+    }
+
+    /// OTLP/JSON ExportTraceServiceRequest for `spans`
+    pub fn encode(&self, spans: &[Span]) -> Json {
+        let spans: Vec<Json> = spans.iter().map(encode_span).collect();
+        json!({
+            "resourceSpans": [{
+                "resource": {"attributes": [attribute("service.name", &Value::Str(self.service.clone()))]},
+                "scopeSpans": [{
+                    "scope": {"name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION")},
+                    "spans": spans,
+                }],
+            }],
+        })
+    }
+}
+
+fn encode_span(span: &Span) -> Json {
+    let mut encoded = json!({
+        "traceId": format!("{:032x}", span.trace_id),
+        "spanId": format!("{:016x}", span.span_id),
+        "name": span.name,
+        "kind": span.kind,
+        "startTimeUnixNano": span.start_ns.to_string(),
+        "endTimeUnixNano": span.end_ns.to_string(),
+        "attributes": span.attributes.iter().map(|(key, value)| attribute(key, value)).collect::<Vec<_>>(),
+        "status": match &span.error {
+            Some(message) => json!({"code": 2, "message": message}),
+            None => json!({"code": 1}),
+        },
+    });
+    if let Some(parent) = span.parent_id {
+        encoded["parentSpanId"] = json!(format!("{:016x}", parent));
+    }
+    encoded
+}
+
+fn attribute(key: &str, value: &Value) -> Json {
+    let value = match value {
+        Value::Str(s) => json!({"stringValue": s}),
+        // int64 is a string in the protobuf JSON mapping
+        Value::Int(i) => json!({"intValue": i.to_string()}),
+        Value::Bool(b) => json!({"boolValue": b}),
+    };
+    json!({"key": key, "value": value})
+}
+
+/// splitmix64 finalizer (a bijection, so distinct inputs give distinct ids)
+fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn unix_ns() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    #[test]
+    fn test_spans() {
+        let off = Tracer::disabled();
+        let mut span = off.start("startup");
+        span.attr("symbols", 3u64);
+        assert!(!span.is_recording());
+        span.end();
+        assert!(off.take_finished().is_empty());
+
+        let tracer = Tracer::new("writer", 2);
+        let mut root = tracer.start("ws.reconnect");
+        root.attr("conn", "WS-1");
+        root.attr("attempt", 2u64);
+        let mut child = tracer.child(&root, "ws.handshake").client().started_before(Duration::from_millis(40));
+        child.fail("TLS handshake failed");
+        child.end();
+        root.end();
+        tracer.start("dropped").end();
+        assert_eq!(tracer.counts(), (0, 1, 0));
+
+        let spans = tracer.take_finished();
+        let encoded = tracer.encode(&spans);
+        assert_eq!(encoded["resourceSpans"][0]["resource"]["attributes"][0]["value"]["stringValue"], "writer");
+        let spans = &encoded["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        let (child, root) = (&spans[0], &spans[1]);
+        assert_eq!((child["name"].as_str(), root["name"].as_str()), (Some("ws.handshake"), Some("ws.reconnect")));
+        assert_eq!(child["traceId"], root["traceId"]);
+        assert_eq!(child["parentSpanId"], root["spanId"]);
+        assert!(root.get("parentSpanId").is_none());
+        assert_eq!((root["traceId"].as_str().unwrap().len(), root["spanId"].as_str().unwrap().len()), (32, 16));
+        assert_ne!(child["spanId"], root["spanId"]);
+        assert_eq!((child["kind"].as_u64(), root["kind"].as_u64()), (Some(3), Some(1)));
+        assert_eq!(child["status"], json!({"code": 2, "message": "TLS handshake failed"}));
+        assert_eq!(root["status"], json!({"code": 1}));
+        assert_eq!(root["attributes"], json!([
+            {"key": "conn", "value": {"stringValue": "WS-1"}},
+            {"key": "attempt", "value": {"intValue": "2"}},
+        ]));
+        let ns = |span: &Json, key: &str| span[key].as_str().unwrap().parse::<u64>().unwrap();
+        assert!(ns(child, "endTimeUnixNano") - ns(child, "startTimeUnixNano") >= 40_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_export() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let collector = tokio::spawn(async move {
+            let (sock, _) = listener.accept().await.unwrap();
+            let mut sock = BufReader::new(sock);
+            let (mut head, mut length) = (Vec::new(), 0);
+            loop {
+                let mut line = String::new();
+                sock.read_line(&mut line).await.unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                head.push(line.trim_end().to_string());
+            }
+            let mut body = vec![0u8; length];
+            sock.read_exact(&mut body).await.unwrap();
+            sock.get_mut().write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}").await.unwrap();
+            (head, serde_json::from_slice::<Json>(&body).unwrap())
+        });
+
+        let config = TraceConfig {
+            endpoint: Some(format!("http://{}/otlp", addr)),
+            interval_ms: 10,
+            ..TraceConfig::default()
+        };
+        let tracer = Arc::new(Tracer::from_config(&config).unwrap());
+        let mut span = tracer.start("rest.depth_snapshot");
+        span.attr("symbol", "BTCUSDT");
+        span.end();
+        tracer.clone().spawn_export();
+
+        let (head, body) = collector.await.unwrap();
+        assert_eq!(head[0], "POST /otlp/v1/traces HTTP/1.1");
+        assert!(head.contains(&"Content-Type: application/json".to_string()));
+        assert_eq!(body["resourceSpans"][0]["scopeSpans"][0]["spans"][0]["name"], "rest.depth_snapshot");
+        for _ in 0..100 {
+            if tracer.counts().0 == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(tracer.counts(), (1, 0, 0));
+        assert!(Tracer::from_config(&TraceConfig::default()).is_ok_and(|tracer| !tracer.enabled()));
+    }
+}
@@ -0,0 +1,216 @@
+//! Instrument trading status poller: /fapi/v1/exchangeInfo
+//!
+//! Every subscribed symbol's `status` (TRADING, BREAK, SETTLING, CLOSE, ...)
+//! is tracked; a symbol not TRADING, or no longer listed, gets the HALTED slot
+//! status bit so consumers stop quoting it. Each change is logged and, with an
+//! event log configured, appended to it as a JSON line.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+use tokio::time::Duration;
+
+use crate::rest::RestClient;
+use crate::shm::{ShmManager, SLOT_STATUS_HALTED};
+
+/// Pause after a 429/418 without Retry-After
+const RATE_LIMIT_PAUSE: Duration = Duration::from_secs(60);
+
+/// Status recorded for a subscribed symbol missing from exchangeInfo
+pub const NOT_LISTED: &str = "NOT_LISTED";
+
+#[derive(Debug, Deserialize)]
+struct ExchangeInfo {
+    symbols: Vec<SymbolStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SymbolStatus {
+    symbol: String,
+    status: String,
+}
+
+/// Parse an exchangeInfo response: symbol -> status
+pub fn parse_exchange_info(body: &str) -> Result<HashMap<String, String>> {
+    let info: ExchangeInfo = serde_json::from_str(body).context("Invalid exchangeInfo response")?;
+    if info.symbols.is_empty() {
+        anyhow::bail!("exchangeInfo lists no symbols");
+    }
+    Ok(info.symbols.into_iter().map(|s| (s.symbol, s.status)).collect())
+}
+
+/// Whether a venue status means quotes shouldn't be used
+pub fn is_halted(status: &str) -> bool {
+    !status.eq_ignore_ascii_case("TRADING")
+}
+
+/// One status change, as written to the event log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatusEvent {
+    pub unix_us: u64,
+    pub symbol: String,
+    /// None when the status wasn't known before (no symbols.tsv status column)
+    pub from: Option<String>,
+    pub to: String,
+    pub halted: bool,
+}
+
+struct Tracked {
+    symbol: String,
+    symbol_id: u64,
+    status: Option<String>,
+}
+
+pub struct TradingStatusPoller {
+    client: RestClient,
+    shm: Arc<ShmManager>,
+    source_id: u64,
+    tracked: Vec<Tracked>,
+    event_log: Option<std::fs::File>,
+}
+
+impl TradingStatusPoller {
+    /// Poller for `symbols` (symbol, symbol_id, status known at startup)
+    pub fn new(client: RestClient, shm: Arc<ShmManager>, source_id: u64, symbols: Vec<(String, u64, Option<String>)>) -> Self {
+        let mut tracked: Vec<Tracked> = symbols
+            .into_iter()
+            .map(|(symbol, symbol_id, status)| Tracked { symbol, symbol_id, status })
+            .collect();
+        tracked.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        Self { client, shm, source_id, tracked, event_log: None }
+    }
+
+    /// Also append every change to `path` (JSON lines)
+    pub fn with_event_log(mut self, path: &str) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open trading status log {}", path))?;
+        self.event_log = Some(file);
+        Ok(self)
+    }
+
+    /// Apply a full status map: update slot bits, log and return the changes
+    pub fn apply(&mut self, statuses: &HashMap<String, String>, unix_us: u64) -> Vec<StatusEvent> {
+        let mut events = Vec::new();
+        for tracked in &mut self.tracked {
+            let status = statuses.get(&tracked.symbol).map_or(NOT_LISTED, String::as_str);
+            if tracked.status.as_deref() == Some(status) {
+                continue;
+            }
+            let halted = is_halted(status);
+            if let Ok(slot) = self.shm.get_slot(self.source_id, tracked.symbol_id) {
+                slot.update_status(SLOT_STATUS_HALTED, if halted { SLOT_STATUS_HALTED } else { 0 });
+            }
+            let event = StatusEvent {
+                unix_us,
+                symbol: tracked.symbol.clone(),
+                from: tracked.status.replace(status.to_string()),
+                to: status.to_string(),
+                halted,
+            };
+            // A status first learned here is only news if it isn't TRADING
+            if event.from.is_some() || halted {
+                eprintln!("[STATUS] {} {} -> {}{}", event.symbol, event.from.as_deref().unwrap_or("?"), event.to,
+                          if halted { " (halted)" } else { "" });
+                events.push(event);
+            }
+        }
+
+        if let Some(log) = &mut self.event_log {
+            for event in &events {
+                let line = serde_json::to_string(event).expect("StatusEvent serializes");
+                if let Err(e) = writeln!(log, "{}", line) {
+                    crate::eprintln_limited!("[STATUS] Event log write failed: {}", e);
+                }
+            }
+        }
+        events
+    }
+
+    /// Fetch exchangeInfo and apply it; Ok(Some(pause)) when rate limited
+    pub async fn poll(&mut self) -> Result<Option<Duration>> {
+        let response = self.client.get("/fapi/v1/exchangeInfo").await?;
+        if response.status == 429 || response.status == 418 {
+            let pause = response.retry_after_secs.map_or(RATE_LIMIT_PAUSE, Duration::from_secs);
+            eprintln!("[STATUS] Rate limited (HTTP {}), pausing {:?}", response.status, pause);
+            return Ok(Some(pause));
+        }
+        if response.status != 200 {
+            anyhow::bail!("HTTP {}: {}", response.status, response.body.trim());
+        }
+        let statuses = parse_exchange_info(&response.body)?;
+        self.apply(&statuses, crate::persist::unix_now_us());
+        Ok(None)
+    }
+
+    /// Poll forever, starting a round every `interval`
+    pub async fn run(mut self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match self.poll().await {
+                Ok(Some(pause)) => tokio::time::sleep(pause).await,
+                Ok(None) => {}
+                Err(e) => crate::eprintln_limited!("[STATUS] exchangeInfo poll failed: {:#}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_changes_set_halted() {
+        let body = r#"{"timezone":"UTC","symbols":[
+            {"symbol":"BTCUSDT","pair":"BTCUSDT","status":"TRADING","filters":[]},
+            {"symbol":"ETHUSDT","status":"BREAK"}]}"#;
+        let statuses = parse_exchange_info(body).unwrap();
+        assert_eq!(statuses["ETHUSDT"], "BREAK");
+        assert!(parse_exchange_info(r#"{"symbols":[]}"#).is_err());
+
+        let log_path = std::env::temp_dir().join(format!("test_trading_status_{}.log", std::process::id()));
+        let log_path = log_path.to_str().unwrap();
+        let _ = std::fs::remove_file(log_path);
+        let shm = Arc::new(ShmManager::anonymous(2, 4).unwrap());
+        for id in 1..4 {
+            shm.init_slot(1, id).unwrap();
+        }
+
+        let client = RestClient::new("http://127.0.0.1:1", Duration::from_secs(1)).unwrap();
+        let symbols = vec![
+            ("BTCUSDT".to_string(), 1, Some("TRADING".to_string())),
+            ("ETHUSDT".to_string(), 2, None),
+            ("XRPUSDT".to_string(), 3, None),
+        ];
+        let mut poller = TradingStatusPoller::new(client, shm.clone(), 1, symbols).with_event_log(log_path).unwrap();
+        let halted = |id| shm.get_slot(1, id).unwrap().status() & SLOT_STATUS_HALTED != 0;
+
+        // BTCUSDT unchanged; ETHUSDT first seen halted; XRPUSDT delisted
+        let events = poller.apply(&statuses, 10);
+        let changed: Vec<(&str, &str)> = events.iter().map(|e| (e.symbol.as_str(), e.to.as_str())).collect();
+        assert_eq!(changed, vec![("ETHUSDT", "BREAK"), ("XRPUSDT", NOT_LISTED)]);
+        assert!(!halted(1) && halted(2) && halted(3));
+
+        // Trading resumes
+        let mut statuses = statuses;
+        statuses.insert("ETHUSDT".to_string(), "TRADING".to_string());
+        let events = poller.apply(&statuses, 20);
+        assert_eq!(events, vec![StatusEvent {
+            unix_us: 20, symbol: "ETHUSDT".to_string(), from: Some("BREAK".to_string()), to: "TRADING".to_string(), halted: false,
+        }]);
+        assert!(!halted(2));
+        assert!(poller.apply(&statuses, 30).is_empty());
+
+        let log = std::fs::read_to_string(log_path).unwrap();
+        assert_eq!(log.lines().count(), 3);
+        assert!(log.lines().last().unwrap().contains(r#""from":"BREAK","to":"TRADING""#));
+        std::fs::remove_file(log_path).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+//! Experimental io_uring socket path (feature `io-uring`, Linux only)
+//!
+//! Runs WebSocket connections on a tokio-uring runtime, where socket reads and
+//! writes are io_uring submissions instead of epoll readiness + syscalls. The
+//! TLS/WebSocket stack is unchanged: `UringStream` adapts the completion-based
+//! tokio-uring socket to tokio's AsyncRead/AsyncWrite.
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{ready, Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_uring::net::TcpStream;
+
+/// Size of each io_uring read submission
+const READ_BUF_SIZE: usize = 64 * 1024;
+
+type BufFuture = Pin<Box<dyn Future<Output = (io::Result<usize>, Vec<u8>)>>>;
+
+/// Run a future on a tokio-uring runtime (blocks the calling thread)
+///
+/// tokio timers, `tokio::task::spawn_local` and DNS lookups work inside.
+pub fn start<F: Future>(future: F) -> F::Output {
+    tokio_uring::start(future)
+}
+
+/// TCP connect through io_uring
+pub async fn connect(addr: SocketAddr) -> io::Result<UringStream> {
+    let stream = TcpStream::connect(addr).await?;
+    stream.set_nodelay(true)?;
+    Ok(UringStream::new(stream))
+}
+
+/// tokio AsyncRead/AsyncWrite over a tokio-uring TcpStream
+///
+/// Like other completion-based adapters, a write that returned Pending must be
+/// retried with the same data (as tokio-tungstenite/native-tls do).
+pub struct UringStream {
+    stream: Rc<TcpStream>,
+    /// Filled read buffer and how much of it has been consumed
+    read_buf: Option<Vec<u8>>,
+    read_pos: usize,
+    read_fut: Option<BufFuture>,
+    write_fut: Option<BufFuture>,
+    /// Result of a write completed by poll_flush, reported by the next poll_write
+    write_done: Option<io::Result<usize>>,
+}
+
+impl UringStream {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream: Rc::new(stream),
+            read_buf: Some(Vec::with_capacity(READ_BUF_SIZE)),
+            read_pos: 0,
+            read_fut: None,
+            write_fut: None,
+            write_done: None,
+        }
+    }
+
+    fn poll_pending_write(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let Some(fut) = self.write_fut.as_mut() else {
+            return Poll::Ready(Ok(0));
+        };
+        let (result, _) = ready!(fut.as_mut().poll(cx));
+        self.write_fut = None;
+        Poll::Ready(result)
+    }
+}
+
+impl AsyncRead for UringStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, out: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            // Serve buffered bytes first
+            if let Some(buf) = &this.read_buf {
+                if this.read_pos < buf.len() {
+                    let n = (buf.len() - this.read_pos).min(out.remaining());
+                    out.put_slice(&buf[this.read_pos..this.read_pos + n]);
+                    this.read_pos += n;
+                    return Poll::Ready(Ok(()));
+                }
+            }
+
+            if this.read_fut.is_none() {
+                let mut buf = this.read_buf.take().unwrap_or_default();
+                buf.clear();
+                buf.reserve(READ_BUF_SIZE);
+                let stream = this.stream.clone();
+                this.read_fut = Some(Box::pin(async move { stream.read(buf).await }));
+            }
+
+            let fut = this.read_fut.as_mut().expect("read submitted above");
+            let (result, buf) = ready!(fut.as_mut().poll(cx));
+            this.read_fut = None;
+            this.read_buf = Some(buf);
+            this.read_pos = 0;
+            // 0 bytes = EOF: leave `out` untouched
+            if result? == 0 {
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+}
+
+impl AsyncWrite for UringStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, data: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if let Some(done) = this.write_done.take() {
+            return Poll::Ready(done);
+        }
+        if this.write_fut.is_none() {
+            let stream = this.stream.clone();
+            let buf = data.to_vec();
+            this.write_fut = Some(Box::pin(async move { stream.write(buf).submit().await }));
+        }
+        this.poll_pending_write(cx)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if this.write_fut.is_some() {
+            let result = ready!(this.poll_pending_write(cx));
+            this.write_done = Some(result);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        Poll::Ready(self.stream.shutdown(std::net::Shutdown::Write))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_loopback_round_trip() {
+        start(async {
+            let listener = tokio_uring::net::TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+            let addr = listener.local_addr().unwrap();
+            // Echo server, also on io_uring; closes its side after our shutdown
+            tokio_uring::spawn(async move {
+                let (sock, _) = listener.accept().await.unwrap();
+                let mut buf = Vec::with_capacity(4096);
+                loop {
+                    let (n, read) = sock.read(buf).await;
+                    if n.unwrap() == 0 {
+                        return;
+                    }
+                    let (written, mut read) = sock.write_all(read).await;
+                    written.unwrap();
+                    read.clear();
+                    buf = read;
+                }
+            });
+
+            let mut stream = connect(addr).await.unwrap();
+            // Larger than one read submission, so reads span several completions
+            let data: Vec<u8> = (0..READ_BUF_SIZE * 2 + 17).map(|i| i as u8).collect();
+            let echoed = {
+                let mut echoed = vec![0u8; data.len()];
+                let (mut reader, mut writer) = tokio::io::split(&mut stream);
+                let read = async { reader.read_exact(&mut echoed).await.unwrap() };
+                let write = async {
+                    writer.write_all(&data).await.unwrap();
+                    writer.flush().await.unwrap();
+                };
+                tokio::join!(read, write);
+                echoed
+            };
+            assert_eq!(echoed, data);
+
+            stream.shutdown().await.unwrap();
+            let mut rest = Vec::new();
+            assert_eq!(stream.read_to_end(&mut rest).await.unwrap(), 0);
+        });
+    }
+}
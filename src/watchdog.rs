@@ -0,0 +1,196 @@
+//! Per-tier update SLA watchdog
+//!
+//! Symbols are assigned to tiers (e.g. tier1 must tick within 2s, tier3 within
+//! 60s). The watchdog periodically seqlock-reads each symbol's last write time
+//! from SHM, alerts when a symbol breaches its tier's limit (and when it
+//! recovers), and keeps tier-level staleness metrics for export.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::SlaTier;
+use crate::shm::{monotonic_us, ShmManager, SLOT_STATUS_STALE};
+
+/// Tier-level staleness metrics from the latest check
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TierStatus {
+    pub name: String,
+    pub max_age_ms: u64,
+    pub symbols: usize,
+    /// Symbols currently over max_age_ms
+    pub stale: usize,
+    /// Oldest last-update age in the tier
+    pub worst_age_ms: u64,
+    pub worst_symbol: String,
+    /// Stale transitions since start
+    pub alerts: u64,
+}
+
+struct Watched {
+    symbol: String,
+    symbol_id: u64,
+    tier: usize,
+    stale: bool,
+}
+
+/// Checks every symbol against its tier's SLA
+pub struct SlaWatchdog {
+    shm: Arc<ShmManager>,
+    source_id: u64,
+    watched: Mutex<Vec<Watched>>,
+    /// Ages of never-written slots count from here
+    started_us: i64,
+    status: Mutex<Vec<TierStatus>>,
+}
+
+impl SlaWatchdog {
+    /// Assign symbols to tiers: explicit lists first, the rest to the first
+    /// catch-all (`*`) tier; symbols matching no tier are not watched
+    pub fn new(shm: Arc<ShmManager>, source_id: u64, tiers: &[SlaTier], symbol_id_map: &HashMap<String, u64>) -> Self {
+        let catch_all = tiers.iter().position(|t| t.symbols.is_empty());
+        let mut watched: Vec<Watched> = symbol_id_map
+            .iter()
+            .filter_map(|(symbol, &symbol_id)| {
+                let tier = tiers.iter().position(|t| t.symbols.contains(symbol)).or(catch_all)?;
+                Some(Watched { symbol: symbol.clone(), symbol_id, tier, stale: false })
+            })
+            .collect();
+        watched.sort_by_key(|w| w.symbol_id);
+
+        let status = tiers
+            .iter()
+            .enumerate()
+            .map(|(i, t)| TierStatus {
+                name: t.name.clone(),
+                max_age_ms: t.max_age_ms,
+                symbols: watched.iter().filter(|w| w.tier == i).count(),
+                ..Default::default()
+            })
+            .collect();
+
+        Self {
+            shm,
+            source_id,
+            watched: Mutex::new(watched),
+            started_us: monotonic_us(),
+            status: Mutex::new(status),
+        }
+    }
+
+    /// Check all symbols at `now_us`, log transitions, update tier metrics
+    pub fn check(&self, now_us: i64) {
+        let mut watched = self.watched.lock().unwrap_or_else(|e| e.into_inner());
+        let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+        for tier in status.iter_mut() {
+            tier.stale = 0;
+            tier.worst_age_ms = 0;
+            tier.worst_symbol.clear();
+        }
+
+        for w in watched.iter_mut() {
+            let Ok(slot) = self.shm.get_slot(self.source_id, w.symbol_id) else {
+                continue;
+            };
+            // A slot the writer keeps busy is being updated, so it isn't stale
+            let Some(quote) = slot.snapshot() else {
+                continue;
+            };
+            let last = quote.ts.max(self.started_us);
+            let age_ms = (now_us - last).max(0) as u64 / 1_000;
+
+            let tier = &mut status[w.tier];
+            if age_ms >= tier.worst_age_ms {
+                tier.worst_age_ms = age_ms;
+                tier.worst_symbol.clone_from(&w.symbol);
+            }
+
+            let stale = age_ms > tier.max_age_ms;
+            slot.update_status(SLOT_STATUS_STALE, if stale { SLOT_STATUS_STALE } else { 0 });
+            if stale {
+                tier.stale += 1;
+            }
+            if stale && !w.stale {
+                tier.alerts += 1;
+                eprintln!("[SLA] {} {} stale: no update for {} ms (limit {} ms)",
+                          tier.name, w.symbol, age_ms, tier.max_age_ms);
+            } else if !stale && w.stale {
+                eprintln!("[SLA] {} {} recovered", tier.name, w.symbol);
+            }
+            w.stale = stale;
+        }
+    }
+
+    /// Tier metrics from the latest check
+    pub fn status(&self) -> Vec<TierStatus> {
+        self.status.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn report(&self) {
+        for tier in self.status() {
+            eprintln!("[STATS] SLA {} (<= {} ms): {} symbols, {} stale, worst {} ms ({}), {} alerts",
+                      tier.name, tier.max_age_ms, tier.symbols, tier.stale,
+                      tier.worst_age_ms, tier.worst_symbol, tier.alerts);
+        }
+    }
+
+    /// Spawn the periodic check task
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.check(monotonic_us());
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SlaConfig;
+
+    #[test]
+    fn test_tiered_staleness() {
+        let shm = Arc::new(ShmManager::anonymous(2, 4).unwrap());
+        for id in 1..4 {
+            shm.init_slot(1, id).unwrap();
+        }
+
+        let tiers = SlaConfig::parse_tiers("tier1:2000:BTCUSDT|ETHUSDT; tier3:60000:*").unwrap();
+        let map = HashMap::from([
+            ("BTCUSDT".to_string(), 1),
+            ("ETHUSDT".to_string(), 2),
+            ("DOGEUSDT".to_string(), 3),
+        ]);
+        let watchdog = SlaWatchdog::new(shm.clone(), 1, &tiers, &map);
+
+        let t0 = watchdog.started_us;
+        shm.get_slot(1, 1).unwrap().write(1, 2, t0 + 4_000_000);
+        watchdog.check(t0 + 5_000_000);
+
+        let status = watchdog.status();
+        assert_eq!((status[0].symbols, status[1].symbols), (2, 1));
+        // ETHUSDT never ticked in 5s (tier1 limit 2s); BTCUSDT ticked 1s ago
+        assert_eq!(status[0].stale, 1);
+        assert_eq!(status[0].worst_symbol, "ETHUSDT");
+        assert_eq!(status[0].worst_age_ms, 5_000);
+        // DOGEUSDT is within the 60s tier3 limit
+        assert_eq!(status[1].stale, 0);
+        assert_eq!(shm.get_slot(1, 2).unwrap().status() & SLOT_STATUS_STALE, SLOT_STATUS_STALE);
+        assert_eq!(shm.get_slot(1, 1).unwrap().status() & SLOT_STATUS_STALE, 0);
+
+        // Alerts count transitions, not checks
+        watchdog.check(t0 + 6_000_000);
+        assert_eq!(watchdog.status()[0].alerts, 1);
+        shm.get_slot(1, 1).unwrap().write(1, 2, t0 + 6_000_000);
+        shm.get_slot(1, 2).unwrap().write(1, 2, t0 + 6_000_000);
+        watchdog.check(t0 + 6_500_000);
+        assert_eq!(watchdog.status()[0].stale, 0);
+        assert_eq!(shm.get_slot(1, 2).unwrap().status(), 0);
+
+    }
+}
@@ -0,0 +1,178 @@
+//! Single-writer claims on SHM slots
+//!
+//! A slot's seqlock assumes one writer; two concurrent writers can interleave
+//! their sequence bumps and let a reader accept a torn quote. Who may write
+//! is settled at two levels:
+//!
+//! - process: each writer stamps the slots it owns (see `ownership`), so a
+//!   second process on the same source is reported by both sides;
+//! - component: within the process, every quote write goes through a
+//!   [`WriterToken`] claiming the (source_id, symbol_id) slots for one named
+//!   component (the feed handler, the synthetic cross engine, the open
+//!   interest poller, symbols added by a reload). A slot already claimed by
+//!   another component can't be claimed again until that token is dropped, so
+//!   wiring two components onto the same slots fails at startup instead of
+//!   corrupting quotes. The `Quote64` writes are crate-private; code outside
+//!   the crate writes through a token's [`SlotWriter`].
+//!
+//! Claims are held per mapping; the `ShmRegistry` maps each file once per
+//! process. Status bit updates (halted, stale) are atomic and stay open to
+//! any component.
+
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::shm::{Quote64, ShmManager, SlotView};
+
+/// Slots claimed in one mapping: (source_id, symbol_id) -> component
+#[derive(Default)]
+pub struct WriterClaims {
+    slots: Mutex<HashMap<(u64, u64), &'static str>>,
+}
+
+impl WriterClaims {
+    /// Component writing (source_id, symbol_id), if claimed
+    pub fn holder(&self, source_id: u64, symbol_id: u64) -> Option<&'static str> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner()).get(&(source_id, symbol_id)).copied()
+    }
+}
+
+/// Exclusive right of one component to write a set of slots of a source
+///
+/// Not `Clone`: share it behind an `Arc` within the component.
+pub struct WriterToken {
+    shm: Arc<ShmManager>,
+    component: &'static str,
+    source_id: u64,
+    /// Indexed by symbol_id
+    claimed: Vec<bool>,
+}
+
+impl WriterToken {
+    /// Claim `symbol_ids` of `source_id` for `component`; fails, claiming
+    /// nothing, if any is out of range or held by another component
+    pub fn claim(shm: Arc<ShmManager>, component: &'static str, source_id: u64, symbol_ids: &[u64]) -> Result<Self> {
+        for &symbol_id in symbol_ids {
+            shm.get_slot(source_id, symbol_id)?;
+        }
+        let mut slots = shm.writer_claims().slots.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((id, holder)) = symbol_ids.iter().find_map(|&id| Some((id, *slots.get(&(source_id, id))?))) {
+            bail!("{}: slot ({}, {}) is already written by {}", component, source_id, id, holder);
+        }
+        let n = symbol_ids.iter().max().map_or(0, |&id| id as usize + 1);
+        let mut claimed = vec![false; n];
+        for &symbol_id in symbol_ids {
+            slots.insert((source_id, symbol_id), component);
+            claimed[symbol_id as usize] = true;
+        }
+        drop(slots);
+        Ok(Self { shm, component, source_id, claimed })
+    }
+
+    pub fn component(&self) -> &'static str {
+        self.component
+    }
+
+    pub fn source_id(&self) -> u64 {
+        self.source_id
+    }
+
+    /// Number of slots held
+    pub fn len(&self) -> usize {
+        self.claimed.iter().filter(|&&c| c).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether this token holds the slot of `symbol_id`
+    #[inline(always)]
+    pub fn holds(&self, symbol_id: u64) -> bool {
+        self.claimed.get(symbol_id as usize).copied().unwrap_or(false)
+    }
+
+    /// The slot of `symbol_id` for writing; errors if not claimed by this token
+    #[inline(always)]
+    pub(crate) fn slot(&self, symbol_id: u64) -> Result<&Quote64> {
+        if !self.holds(symbol_id) {
+            bail!("{} does not hold slot ({}, {})", self.component, self.source_id, symbol_id);
+        }
+        self.shm.get_slot(self.source_id, symbol_id)
+    }
+
+    /// Write access to the slot of `symbol_id`; errors if not claimed by this token
+    pub fn writer(&self, symbol_id: u64) -> Result<SlotWriter<'_>> {
+        self.slot(symbol_id).map(SlotWriter)
+    }
+}
+
+/// Write access to one slot claimed by a `WriterToken`
+#[derive(Clone, Copy)]
+pub struct SlotWriter<'a>(&'a Quote64);
+
+impl SlotWriter<'_> {
+    /// Pull the slot's cache line in ahead of the write
+    #[inline(always)]
+    pub fn prefetch(&self) {
+        self.0.prefetch();
+    }
+
+    /// Write a quote (seqlock protocol)
+    #[inline(always)]
+    pub fn write(&self, bid: i64, ask: i64, ts: i64) {
+        self.0.write(bid, ask, ts);
+    }
+
+    /// Write a quote and set the `mask` status bits to `bits` in the same seqlock window
+    #[inline(always)]
+    pub fn write_with_status(&self, bid: i64, ask: i64, ts: i64, mask: u32, bits: u32) {
+        self.0.write_with_status(bid, ask, ts, mask, bits);
+    }
+
+    /// Read-only view of the same slot
+    pub fn view(&self) -> SlotView<'_> {
+        SlotView(self.0)
+    }
+}
+
+impl Drop for WriterToken {
+    fn drop(&mut self) {
+        let mut slots = self.shm.writer_claims().slots.lock().unwrap_or_else(|e| e.into_inner());
+        for (symbol_id, _) in self.claimed.iter().enumerate().filter(|(_, &c)| c) {
+            slots.remove(&(self.source_id, symbol_id as u64));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exclusive_claims() {
+        let shm = Arc::new(ShmManager::anonymous(2, 4).unwrap());
+
+        let feed = WriterToken::claim(shm.clone(), "feed", 1, &[0, 2]).unwrap();
+        assert_eq!((feed.component(), feed.len()), ("feed", 2));
+        feed.slot(2).unwrap().write(100, 200, 1);
+        assert!(feed.slot(1).is_err());
+        assert!(feed.slot(9).is_err());
+
+        // Overlapping claims fail without taking the free slots; other sources are separate
+        assert!(WriterToken::claim(shm.clone(), "synthetic", 1, &[1, 2]).is_err());
+        assert_eq!(shm.writer_claims().holder(1, 1), None);
+        let oi = WriterToken::claim(shm.clone(), "open-interest", 0, &[0, 2]).unwrap();
+        assert!(WriterToken::claim(shm.clone(), "synthetic", 1, &[4]).is_err());
+        let synthetic = WriterToken::claim(shm.clone(), "synthetic", 1, &[1, 3]).unwrap();
+        assert_eq!(shm.writer_claims().holder(1, 3), Some("synthetic"));
+        assert_eq!(shm.writer_claims().holder(0, 2), Some("open-interest"));
+
+        // Dropping a token frees its slots
+        drop(feed);
+        assert_eq!(shm.writer_claims().holder(1, 2), None);
+        WriterToken::claim(shm.clone(), "synthetic", 1, &[2]).unwrap();
+        drop((oi, synthetic));
+    }
+}
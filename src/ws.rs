@@ -1,11 +1,104 @@
-use anyhow::{Context, Result};
-use futures_util::{SinkExt, StreamExt};
+use anyhow::{anyhow, Context, Result};
+use futures_util::{FutureExt, SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::net::SocketAddr;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{lookup_host, TcpStream};
+use tokio::sync::Notify;
+use tokio::time::{timeout, Duration, Instant};
+use tokio_tungstenite::{client_async, MaybeTlsStream};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue, Uri};
+use tokio_tungstenite::tungstenite::Message;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 
-const WS_BASE: &str = "wss://fstream.binance.com";
+use crate::config::WsConfig;
+use crate::hooks::Hooks;
+use crate::metrics::{Metrics, NoopMetrics};
+use crate::trace::{Span, Tracer};
+use crate::symbols::SubscribeDiff;
+use crate::reconnect::{self, BackoffCalculator, CloseInfo, ConnState, DisconnectKind, ReconnectBucket, WsError};
+
+pub const WS_BASE: &str = "wss://fstream.binance.com";
 const CHUNK_SIZE: usize = 100; // Max streams per connection
+/// Max frames handled back-to-back before yielding to other connections
+const MAX_BATCH: usize = 256;
+/// Batch size histogram buckets: [1], [2,3], [4,7], ... [128,255], [MAX_BATCH]
+const BATCH_BUCKETS: usize = 9;
+/// Client ping interval of the default keepalive
+pub const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(30);
+/// How often Binance pings each connection
+pub const SERVER_PING_INTERVAL: Duration = Duration::from_secs(180);
+
+/// What a session sends to keep the connection alive (WS_KEEPALIVE). The
+/// server's pings are answered with pongs in every mode, as the protocol
+/// requires; Binance pings every 3 minutes and drops connections that don't
+/// answer, and accepts unsolicited pongs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keepalive {
+    /// `ping:MS` - ping every interval; the server's pongs count as reads for the idle timeout
+    Ping(Duration),
+    /// `pong:MS` - unsolicited pong every interval: keeps NATs and the server's
+    /// timer fed without asking for a reply
+    Pong(Duration),
+    /// `none` - send nothing of our own, only answer the server's pings
+    None,
+}
+
+impl Default for Keepalive {
+    fn default() -> Self {
+        Self::Ping(DEFAULT_PING_INTERVAL)
+    }
+}
+
+impl std::str::FromStr for Keepalive {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, interval) = s.trim().split_once(':').unwrap_or((s.trim(), ""));
+        let interval = || match interval.trim().parse::<u64>() {
+            Ok(ms) if ms > 0 => Ok(Duration::from_millis(ms)),
+            _ => Err(anyhow!("Expected a positive interval in ms in {:?}", s)),
+        };
+        match kind.to_ascii_lowercase().as_str() {
+            "ping" => Ok(Self::Ping(interval()?)),
+            "pong" => Ok(Self::Pong(interval()?)),
+            "none" => Ok(Self::None),
+            other => Err(anyhow!("Unknown keepalive {:?} (ping:MS, pong:MS, none)", other)),
+        }
+    }
+}
+
+impl Keepalive {
+    /// How often a frame is sent (None = never)
+    pub fn interval(&self) -> Option<Duration> {
+        match *self {
+            Self::Ping(every) | Self::Pong(every) => Some(every),
+            Self::None => None,
+        }
+    }
+
+    /// Idle timeout that suits this mode: with client pings their pongs come
+    /// back within the interval; otherwise a quiet stream reads only the
+    /// server's pings, so the timeout has to outlast SERVER_PING_INTERVAL
+    pub fn default_idle_timeout_ms(&self) -> u64 {
+        match self {
+            Self::Ping(_) => 60_000,
+            _ => (SERVER_PING_INTERVAL + Duration::from_secs(30)).as_millis() as u64,
+        }
+    }
+
+    fn frame(&self) -> Message {
+        match self {
+            Self::Pong(_) => Message::Pong(Vec::new()),
+            _ => Message::Ping(Vec::new()),
+        }
+    }
+}
 
 /// Binance Futures bookTicker message
 #[derive(Debug, Deserialize, Serialize)]
@@ -16,25 +109,171 @@ pub struct BookTickerData {
     pub bid_price: String,
     #[serde(rename = "a")]
     pub ask_price: String,
-    // We ignore other fields (u, B, A, etc.) for performance
+    /// Order book update id (0 if absent), used for A/B arbitration
+    #[serde(rename = "u", default, deserialize_with = "lenient_u64")]
+    pub update_id: u64,
+    /// Exchange event time, ms (0 if absent), used for clock drift detection
+    #[serde(rename = "E", default, deserialize_with = "lenient_u64")]
+    pub event_time_ms: u64,
+    /// Local time the frame was read off the socket (monotonic µs, 0 if unknown)
+    #[serde(skip)]
+    pub received_us: i64,
+    /// Combined-stream name the frame arrived on ("btcusdt@bookTicker"; empty
+    /// if unknown), for dispatch without a symbol lookup
+    #[serde(skip)]
+    pub stream: String,
+    // We ignore other fields (B, A, etc.) for performance
+}
+
+/// `u`/`E` as an unsigned integer, 0 for anything else (lenient parsing only
+/// needs s, b and a; strict mode rejects a malformed `u` or `E` separately)
+fn lenient_u64<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    use serde::de::{IgnoredAny, MapAccess, SeqAccess, Visitor};
+
+    struct UpdateId;
+    impl<'de> Visitor<'de> for UpdateId {
+        type Value = u64;
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("an update id")
+        }
+        fn visit_u64<E>(self, v: u64) -> Result<u64, E> {
+            Ok(v)
+        }
+        fn visit_i64<E>(self, _: i64) -> Result<u64, E> {
+            Ok(0)
+        }
+        fn visit_f64<E>(self, _: f64) -> Result<u64, E> {
+            Ok(0)
+        }
+        fn visit_bool<E>(self, _: bool) -> Result<u64, E> {
+            Ok(0)
+        }
+        fn visit_str<E>(self, _: &str) -> Result<u64, E> {
+            Ok(0)
+        }
+        fn visit_unit<E>(self) -> Result<u64, E> {
+            Ok(0)
+        }
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<u64, A::Error> {
+            while seq.next_element::<IgnoredAny>()?.is_some() {}
+            Ok(0)
+        }
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<u64, A::Error> {
+            while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+            Ok(0)
+        }
+    }
+    deserializer.deserialize_any(UpdateId)
 }
 
 /// Wrapper message from combined stream
 #[derive(Debug, Deserialize)]
 pub struct StreamMessage {
-    #[allow(dead_code)]
     pub stream: String,
     pub data: BookTickerData,
 }
 
+/// Response to a SUBSCRIBE/UNSUBSCRIBE/LIST_SUBSCRIPTIONS request
+#[derive(Debug, Deserialize)]
+struct ControlResponse {
+    id: u64,
+    #[serde(default)]
+    result: Option<serde_json::Value>,
+    #[serde(default)]
+    error: Option<ControlError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ControlError {
+    code: i64,
+    msg: String,
+}
+
+/// Why a text frame produced no message
+#[derive(Debug)]
+pub enum FrameError {
+    /// Not JSON or not a combined-stream bookTicker message
+    Parse(serde_json::Error),
+    /// Strict mode: a bookTicker field is missing or malformed
+    Schema(String),
+}
+
+impl std::fmt::Display for FrameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(e) => write!(f, "{}", e),
+            Self::Schema(reason) => write!(f, "schema violation: {}", reason),
+        }
+    }
+}
+
+/// Parse a frame, in strict mode also checking the full bookTicker schema
+#[inline]
+pub fn decode_frame(text: &str, strict: bool) -> Result<BookTickerData, FrameError> {
+    if strict {
+        parse_frame_strict(text)
+    } else {
+        parse_frame(text).map_err(FrameError::Parse)
+    }
+}
+
+/// Parse a frame requiring u, s, b, B, a, A, T and E to be present and well-formed
+pub fn parse_frame_strict(text: &str) -> Result<BookTickerData, FrameError> {
+    let msg: serde_json::Value = serde_json::from_str(text).map_err(FrameError::Parse)?;
+    let data = msg.get("data").and_then(|d| d.as_object())
+        .ok_or_else(|| FrameError::Schema("no data object".to_string()))?;
+    let field = |name: &str| data.get(name).ok_or_else(|| FrameError::Schema(format!("missing {}", name)));
+
+    let positive = |name: &str| match field(name)?.as_u64() {
+        Some(v) if v > 0 => Ok(v),
+        _ => Err(FrameError::Schema(format!("{} is not a positive integer", name))),
+    };
+    let (update_id, _, event_time_ms) = (positive("u")?, positive("T")?, positive("E")?);
+    let symbol = field("s")?.as_str()
+        .filter(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit()))
+        .ok_or_else(|| FrameError::Schema("s is not an uppercase symbol".to_string()))?;
+    let strict = crate::price::PriceParseOptions { mode: crate::price::ParseMode::Strict, ..Default::default() };
+    let decimal = |name: &str| -> Result<String, FrameError> {
+        let value = field(name)?.as_str()
+            .ok_or_else(|| FrameError::Schema(format!("{} is not a string", name)))?;
+        crate::price::parse_price_with(value, &strict)
+            .map_err(|e| FrameError::Schema(format!("{} {:?}: {}", name, value, e)))?;
+        Ok(value.to_string())
+    };
+    decimal("B")?;
+    decimal("A")?;
+    let stream = msg.get("stream").and_then(|s| s.as_str()).unwrap_or_default().to_string();
+    Ok(BookTickerData { symbol: symbol.to_string(), bid_price: decimal("b")?, ask_price: decimal("a")?, update_id, event_time_ms, received_us: 0, stream })
+}
+
+/// bookTicker stream name of a symbol ("BTCUSDT" -> "btcusdt@bookTicker")
+pub fn stream_name(symbol: &str) -> String {
+    format!("{}@bookTicker", symbol.to_lowercase())
+}
+
 /// Create WebSocket URL for a chunk of symbols
-fn create_ws_url(symbols: &[String]) -> String {
-    let streams: Vec<String> = symbols
-        .iter()
-        .map(|s| format!("{}@bookTicker", s.to_lowercase()))
-        .collect();
+fn create_ws_url(base: &str, symbols: &[String]) -> String {
+    let streams: Vec<String> = symbols.iter().map(|s| stream_name(s)).collect();
 
-    format!("{}/stream?streams={}", WS_BASE, streams.join("/"))
+    format!("{}/stream?streams={}", base, streams.join("/"))
+}
+
+/// All-market bookTicker stream (every listed symbol on one connection)
+fn all_market_url(base: &str) -> String {
+    format!("{}/stream?streams=!bookTicker", base)
+}
+
+/// Append extra query parameters to a stream URL
+fn with_query(mut url: String, query: &[(String, String)]) -> String {
+    for (key, value) in query {
+        url.push(if url.contains('?') { '&' } else { '?' });
+        url.push_str(key);
+        if !value.is_empty() {
+            url.push('=');
+            url.push_str(value);
+        }
+    }
+    url
 }
 
 /// Split symbols into chunks of CHUNK_SIZE
@@ -45,284 +284,3298 @@ pub fn chunk_symbols(symbols: &[String]) -> Vec<Vec<String>> {
         .collect()
 }
 
-/// Message handler callback
-pub type MessageHandler = Arc<dyn Fn(BookTickerData) + Send + Sync>;
+/// Stable chunking: target fill of a connection, leaving room for additions
+/// before a symbol has to spill to a neighbouring connection
+const STABLE_FILL_PCT: usize = 80;
 
-/// WebSocket connection manager
-pub struct WsConnection {
-    symbols: Vec<String>,
-    handler: MessageHandler,
+/// Split symbols into chunks of at most CHUNK_SIZE with an assignment that
+/// depends only on each symbol's name, not on the subscribe file order
+///
+/// Jump consistent hashing over a stable FNV-1a hash: adding or removing a
+/// symbol moves only that symbol, and growing to one more connection moves
+/// about 1/n of the symbols. A full chunk spills to the next one.
+pub fn chunk_symbols_stable(symbols: &[String]) -> Vec<Vec<String>> {
+    let per_chunk = CHUNK_SIZE * STABLE_FILL_PCT / 100;
+    let n_chunks = symbols.len().div_ceil(per_chunk).max(1);
+
+    let mut sorted: Vec<&String> = symbols.iter().collect();
+    sorted.sort();
+    let mut chunks: Vec<Vec<String>> = vec![Vec::new(); n_chunks];
+    for symbol in sorted {
+        let mut chunk = jump_hash(fnv1a(symbol.as_bytes()), n_chunks);
+        while chunks[chunk].len() >= CHUNK_SIZE {
+            chunk = (chunk + 1) % n_chunks;
+        }
+        chunks[chunk].push(symbol.clone());
+    }
+    chunks.retain(|chunk| !chunk.is_empty());
+    chunks
 }
 
-impl WsConnection {
-    pub fn new(symbols: Vec<String>, handler: MessageHandler) -> Self {
-        Self { symbols, handler }
+/// Chunks for `symbols` under `config` (file order or stable assignment)
+pub fn chunk_for(symbols: &[String], config: &WsConfig) -> Vec<Vec<String>> {
+    if config.stable_chunks {
+        chunk_symbols_stable(symbols)
+    } else {
+        chunk_symbols(symbols)
     }
+}
 
-    /// Connect and start receiving messages
-    /// Returns when connection closes or error occurs
-    pub async fn run(&self) -> Result<()> {
-        let url = create_ws_url(&self.symbols);
+/// 64-bit FNV-1a (stable across builds, unlike std's DefaultHasher)
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
 
-        eprintln!("[WS] Connecting to {} streams...", self.symbols.len());
+/// Jump consistent hash (Lamping & Veach): bucket in 0..buckets
+fn jump_hash(mut key: u64, buckets: usize) -> usize {
+    let (mut b, mut j) = (-1i64, 0i64);
+    while j < buckets as i64 {
+        b = j;
+        key = key.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+        j = ((b + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    b as usize
+}
 
-        let (ws_stream, _) = connect_async(&url)
-            .await
-            .with_context(|| format!("Failed to connect to {}", url))?;
+/// Message handler callback
+pub type MessageHandler = Arc<dyn Fn(BookTickerData) + Send + Sync>;
 
-        eprintln!("[WS] Connected! Receiving messages...");
+/// Raw text frame callback (decode pipeline: parsing happens elsewhere)
+pub type RawHandler = Box<dyn Fn(String) + Send + Sync>;
 
-        let (mut write, mut read) = ws_stream.split();
+/// Parse a combined-stream text frame
+#[inline]
+pub fn parse_frame(text: &str) -> serde_json::Result<BookTickerData> {
+    serde_json::from_str::<StreamMessage>(text).map(|msg| BookTickerData { stream: msg.stream, ..msg.data })
+}
 
-        // Spawn ping task
-        let ping_task = tokio::spawn(async move {
-            loop {
-                tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
-                if write.send(Message::Ping(vec![])).await.is_err() {
-                    break;
-                }
-            }
-        });
+/// Streams of a connection, changed at runtime by `WsManager::resubscribe`
+#[derive(Default)]
+struct Subscription {
+    symbols: Vec<String>,
+    /// SUBSCRIBE/UNSUBSCRIBE requests for the live session (a new session
+    /// connects with the updated `symbols` instead)
+    pending: Vec<String>,
+    /// Requests sent (or queued) on the live session, by id, until answered
+    awaiting: HashMap<u64, Awaited>,
+    /// `ws.resubscribe` span of each awaited request, ended by its response
+    spans: HashMap<u64, Span>,
+}
 
-        // Process messages
-        while let Some(msg) = read.next().await {
-            match msg {
-                Ok(Message::Text(text)) => {
-                    // Parse and handle message
-                    match serde_json::from_str::<StreamMessage>(&text) {
-                        Ok(stream_msg) => {
-                            (self.handler)(stream_msg.data);
-                        }
-                        Err(e) => {
-                            eprintln!("[WS] Failed to parse message: {}", e);
-                            // Don't exit on parse errors - might be other message types
-                        }
-                    }
-                }
-                Ok(Message::Ping(data)) => {
-                    // Tungstenite handles pong automatically
-                    drop(data);
-                }
-                Ok(Message::Pong(_)) => {
-                    // Expected response to our pings
-                }
-                Ok(Message::Close(_)) => {
-                    eprintln!("[WS] Connection closed by server");
-                    break;
-                }
-                Err(e) => {
-                    eprintln!("[WS] Error receiving message: {}", e);
-                    break;
-                }
-                _ => {}
+/// What a request id asked for
+#[derive(Debug, Clone, PartialEq)]
+enum Awaited {
+    /// SUBSCRIBE or UNSUBSCRIBE of these streams
+    Change { method: &'static str, streams: Vec<String> },
+    /// LIST_SUBSCRIPTIONS, checked against the streams we mean to have
+    List,
+}
+
+/// Symbols a new session still has to deliver before it counts as healthy
+#[derive(Default)]
+struct WarmUp {
+    /// Subscribed symbols without a message yet this session
+    missing: HashSet<String>,
+    /// Missing count at which the session is healthy
+    done_at: usize,
+    /// Symbols subscribed when the warm-up started
+    subscribed: usize,
+    started_us: i64,
+}
+
+/// Symbol of a bookTicker frame (`"s":"..."`), without a full parse
+fn frame_symbol(text: &str) -> Option<&str> {
+    let start = text.find(r#""s":""#)? + 5;
+    let len = text[start..].find('"')?;
+    Some(&text[start..start + len])
+}
+
+impl Subscription {
+    /// Queue a SUBSCRIBE/UNSUBSCRIBE of `streams` for the live session
+    fn queue(&mut self, tracer: &Tracer, method: &'static str, streams: Vec<String>) {
+        let id = REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+        let mut span = tracer.start("ws.resubscribe").client();
+        span.attr("method", method);
+        span.attr("streams", streams.len() as u64);
+        self.spans.insert(id, span);
+        self.pending.push(serde_json::json!({ "method": method, "params": streams, "id": id }).to_string());
+        self.awaiting.insert(id, Awaited::Change { method, streams });
+    }
+
+    /// Forget request `id`, ending its span (failed with `error`, if any)
+    fn finish(&mut self, id: u64, error: Option<String>) {
+        if let Some(mut span) = self.spans.remove(&id) {
+            if let Some(error) = error {
+                span.fail(error);
             }
+            span.end();
         }
+    }
 
-        ping_task.abort();
-
-        Ok(())
+    /// Drop every awaited request, e.g. when its session is gone
+    fn abandon(&mut self, reason: &str) {
+        self.awaiting.clear();
+        for (_, mut span) in self.spans.drain() {
+            span.fail(reason);
+            span.end();
+        }
     }
 }
 
-/// Backoff calculator for reconnections
-struct BackoffCalculator {
-    attempt: u32,
-    delays_ms: Vec<u64>,
-    max_delay_ms: u64,
+/// Ids of SUBSCRIBE/UNSUBSCRIBE/LIST_SUBSCRIPTIONS requests
+static REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// WebSocket connection manager
+pub struct WsConnection {
+    subscription: Arc<std::sync::Mutex<Subscription>>,
+    /// `subscription.pending` has requests to send
+    resubscribe: Notify,
+    handler: MessageHandler,
+    config: WsConfig,
+    stats: Arc<PerfStats>,
+    /// Index into config.endpoints(), advanced on TLS failures
+    endpoint_idx: AtomicUsize,
+    conn_stats: Arc<ConnStats>,
+    /// When set, text frames go here unparsed instead of to `handler`
+    raw_handler: Option<RawHandler>,
+    /// Explicit stream list (e.g. "!forceOrder@arr") instead of `symbols`' bookTicker streams
+    streams: Option<String>,
+    hooks: Arc<Hooks>,
+    /// Operator restart request (see `RestartHandle`)
+    restart: Arc<Notify>,
+    /// Warm spare pairing: (pair state, this connection's role)
+    failover: Option<(Arc<Failover>, usize)>,
+    /// Second connection of a chunk (warm spare or B line): (chunk, log tag suffix)
+    pair: Option<(usize, &'static str)>,
+    /// Session still in warm-up (see `WsConfig::warmup_timeout_ms`); checked per frame
+    warming: AtomicBool,
+    warmup: std::sync::Mutex<WarmUp>,
+    /// Log tag of state transitions, set by the supervisor
+    tag: std::sync::OnceLock<String>,
+    /// Connection events: time per state, disconnects
+    metrics: Arc<dyn Metrics>,
+    tracer: Arc<Tracer>,
+    /// Span of the current connection attempt, until it streams or fails
+    span: std::sync::Mutex<Option<Span>>,
+    /// Test mode delaying every text frame before it is handled (INJECT_LATENCY)
+    latency: Option<Arc<crate::inject::LatencyInjector>>,
 }
 
-impl BackoffCalculator {
-    fn new() -> Self {
-        Self {
-            attempt: 0,
-            delays_ms: vec![200, 500, 1000, 2000, 5000, 10000, 30000],
-            max_delay_ms: 30000,
-        }
+/// Role of the connection a pair starts out reading from
+pub const PRIMARY: usize = 0;
+/// Role of its idle duplicate
+pub const SPARE: usize = 1;
+
+/// A primary connection and its warm spare, subscribed to the same streams.
+/// Only the active one's frames are handled; when it drops while the other is
+/// up, the other takes over without a reconnect gap.
+pub struct Failover {
+    /// Role whose frames are handled (read on every frame)
+    active: AtomicUsize,
+    /// Whether each role has a live session
+    up: std::sync::Mutex<[bool; 2]>,
+}
+
+impl Default for Failover {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    fn next_delay(&mut self) -> tokio::time::Duration {
-        let delay_ms = if (self.attempt as usize) < self.delays_ms.len() {
-            self.delays_ms[self.attempt as usize]
-        } else {
-            self.max_delay_ms
-        };
+impl Failover {
+    pub fn new() -> Self {
+        Self { active: AtomicUsize::new(PRIMARY), up: std::sync::Mutex::new([false; 2]) }
+    }
 
-        self.attempt += 1;
-        tokio::time::Duration::from_millis(delay_ms)
+    /// Whether `role`'s frames are handled (one relaxed load)
+    #[inline(always)]
+    pub fn is_active(&self, role: usize) -> bool {
+        self.active.load(Ordering::Relaxed) == role
+    }
+
+    /// `role` has a session; it becomes active if the active one has none
+    pub fn mark_up(&self, role: usize) {
+        let mut up = self.up.lock().unwrap_or_else(|e| e.into_inner());
+        up[role] = true;
+        if !up[self.active.load(Ordering::Relaxed)] {
+            self.active.store(role, Ordering::Relaxed);
+        }
     }
 
-    fn reset(&mut self) {
-        self.attempt = 0;
+    /// `role` lost its session; true if the other one was promoted
+    pub fn mark_down(&self, role: usize) -> bool {
+        let mut up = self.up.lock().unwrap_or_else(|e| e.into_inner());
+        up[role] = false;
+        let other = 1 - role;
+        if self.is_active(role) && up[other] {
+            self.active.store(other, Ordering::Relaxed);
+            return true;
+        }
+        false
     }
 }
 
-/// Multi-connection manager with fairness
-pub struct WsManager {
-    connections: Vec<WsConnection>,
+/// Asks one connection to drop its session and reconnect, leaving the others
+/// (and the SHM slots) alone
+#[derive(Clone)]
+pub struct RestartHandle {
+    /// Log tag, e.g. "WS-3" or "WS-P0"
+    pub tag: String,
+    subscription: Arc<std::sync::Mutex<Subscription>>,
+    signal: Arc<Notify>,
+    stats: Arc<ConnStats>,
 }
 
-impl WsManager {
-    pub fn new(symbols: Vec<String>, handler: MessageHandler) -> Self {
-        let chunks = chunk_symbols(&symbols);
-        let n_connections = chunks.len();
+impl RestartHandle {
+    /// Symbols the connection currently carries
+    pub fn symbols(&self) -> Vec<String> {
+        self.subscription.lock().unwrap_or_else(|e| e.into_inner()).symbols.clone()
+    }
 
-        eprintln!("[WS] Creating {} connections for {} symbols", n_connections, symbols.len());
+    /// Restart now: tears down the current session (or skips a pending backoff)
+    pub fn restart(&self) {
+        self.signal.notify_one();
+    }
 
-        let connections: Vec<_> = chunks
-            .into_iter()
-            .map(|chunk| WsConnection::new(chunk, handler.clone()))
-            .collect();
+    /// (text frames, payload bytes) the connection has read
+    pub fn traffic(&self) -> (u64, u64) {
+        self.stats.traffic()
+    }
 
-        Self { connections }
+    /// Lifecycle state and time per state (see ConnStats::state_times)
+    pub fn state_times(&self, now_us: i64) -> ((ConnState, u64), [(u64, u64); ConnState::COUNT]) {
+        self.stats.state_times(now_us)
     }
+}
 
-    /// Run all connections concurrently with exponential backoff
-    pub async fn run_all(&self) -> Result<()> {
-        // Clone connections for 'static lifetime
-        let connections: Vec<WsConnection> = self.connections
-            .iter()
-            .map(|c| WsConnection {
-                symbols: c.symbols.clone(),
-                handler: c.handler.clone(),
-            })
-            .collect();
+impl WsConnection {
+    pub fn new(symbols: Vec<String>, handler: MessageHandler, config: WsConfig, stats: Arc<PerfStats>) -> Self {
+        Self {
+            subscription: Arc::new(std::sync::Mutex::new(Subscription { symbols, ..Default::default() })),
+            resubscribe: Notify::new(),
+            handler,
+            config,
+            stats,
+            endpoint_idx: AtomicUsize::new(0),
+            conn_stats: Arc::default(),
+            raw_handler: None,
+            streams: None,
+            hooks: Arc::default(),
+            restart: Arc::default(),
+            failover: None,
+            pair: None,
+            warming: AtomicBool::new(false),
+            warmup: std::sync::Mutex::default(),
+            tag: std::sync::OnceLock::new(),
+            metrics: Arc::new(NoopMetrics),
+            tracer: Arc::new(Tracer::disabled()),
+            span: std::sync::Mutex::new(None),
+            latency: None,
+        }
+    }
 
-        let tasks: Vec<_> = connections
-            .into_iter()
-            .enumerate()
-            .map(|(i, conn)| {
-                tokio::spawn(async move {
-                    // Staggered startup: 1 second delay between connections to avoid rate limits
-                    let startup_delay = tokio::time::Duration::from_secs(i as u64);
-                    if startup_delay.as_millis() > 0 {
-                        eprintln!("[WS-{}] Waiting {:?} before startup (rate limiting)...", i, startup_delay);
-                        tokio::time::sleep(startup_delay).await;
-                    }
+    /// Subscribe to `streams` instead of the symbols' bookTicker streams
+    pub fn with_streams(mut self, streams: &str) -> Self {
+        self.streams = Some(streams.to_string());
+        self
+    }
 
-                    let mut backoff = BackoffCalculator::new();
-                    let mut consecutive_errors = 0;
-
-                    loop {
-                        eprintln!("[WS-{}] Starting connection (attempt {})...", i, backoff.attempt + 1);
-
-                        match conn.run().await {
-                            Ok(_) => {
-                                eprintln!("[WS-{}] Connection closed gracefully", i);
-                                backoff.reset();
-                                consecutive_errors = 0;
-                            }
-                            Err(e) => {
-                                consecutive_errors += 1;
-                                eprintln!("[WS-{}] Connection error ({}): {}", i, consecutive_errors, e);
-
-                                // Fatal after too many consecutive errors
-                                if consecutive_errors > 10 {
-                                    eprintln!("[WS-{}] FATAL: Too many consecutive errors, giving up", i);
-                                    std::process::exit(3);
-                                }
-                            }
-                        }
+    /// Pair with another connection on the same streams as `role` (PRIMARY or SPARE)
+    pub fn with_failover(mut self, failover: Arc<Failover>, role: usize) -> Self {
+        self.failover = Some((failover, role));
+        self
+    }
 
-                        // Reconnect with backoff + jitter to avoid thundering herd
-                        let base_delay = backoff.next_delay();
-                        let jitter_ms = (i as u64 * 50) % 500; // 0-500ms jitter based on connection id
-                        let delay = base_delay + tokio::time::Duration::from_millis(jitter_ms);
-                        eprintln!("[WS-{}] Reconnecting in {:?}...", i, delay);
-                        tokio::time::sleep(delay).await;
-                    }
-                })
-            })
-            .collect();
+    /// Hand text frames to `raw` unparsed
+    pub fn with_raw_handler(mut self, raw: RawHandler) -> Self {
+        self.raw_handler = Some(raw);
+        self
+    }
 
-        // Wait for all tasks (they should never complete normally)
-        for task in tasks {
-            let _ = task.await;
+    /// Symbols this connection carries
+    pub fn symbols(&self) -> Vec<String> {
+        self.subscription.lock().unwrap_or_else(|e| e.into_inner()).symbols.clone()
+    }
+
+    /// Add and remove bookTicker streams: SUBSCRIBE/UNSUBSCRIBE on the live
+    /// session, and the next connect asks for the new list
+    pub fn update_streams(&self, add: &[String], remove: &[String]) {
+        let mut subscription = self.subscription.lock().unwrap_or_else(|e| e.into_inner());
+        subscription.symbols.retain(|s| !remove.contains(s));
+        for symbol in add {
+            if !subscription.symbols.contains(symbol) {
+                subscription.symbols.push(symbol.clone());
+            }
+        }
+        for (method, symbols) in [("SUBSCRIBE", add), ("UNSUBSCRIBE", remove)] {
+            if symbols.is_empty() {
+                continue;
+            }
+            let streams = symbols.iter().map(|s| stream_name(s)).collect();
+            subscription.queue(&self.tracer, method, streams);
         }
+        drop(subscription);
+        self.resubscribe.notify_one();
+    }
 
-        Ok(())
+    /// Streams this connection means to be subscribed to
+    fn intended_streams(&self) -> Vec<String> {
+        match &self.streams {
+            Some(streams) => streams.split('/').map(str::to_string).collect(),
+            None if self.config.all_market => vec!["!bookTicker".to_string()],
+            None => self.symbols().iter().map(|s| stream_name(s)).collect(),
+        }
     }
-}
 
-/// Performance statistics
-pub struct PerfStats {
-    pub max_proc_us: std::sync::atomic::AtomicU64,
-    pub over_5000us_count: std::sync::atomic::AtomicU64,
-    pub total_messages: std::sync::atomic::AtomicU64,
-}
+    /// LIST_SUBSCRIPTIONS request for the live session
+    fn list_request(&self) -> String {
+        let id = REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+        let mut subscription = self.subscription.lock().unwrap_or_else(|e| e.into_inner());
+        // An unanswered earlier check is superseded
+        let superseded: Vec<u64> = subscription.awaiting.iter()
+            .filter(|(_, awaited)| **awaited == Awaited::List)
+            .map(|(&id, _)| id)
+            .collect();
+        for previous in superseded {
+            subscription.awaiting.remove(&previous);
+            subscription.finish(previous, Some("superseded by a newer check".to_string()));
+        }
+        let mut span = self.tracer.start("ws.resubscribe").client();
+        span.attr("method", "LIST_SUBSCRIPTIONS");
+        subscription.spans.insert(id, span);
+        subscription.awaiting.insert(id, Awaited::List);
+        serde_json::json!({ "method": "LIST_SUBSCRIPTIONS", "id": id }).to_string()
+    }
 
-impl PerfStats {
-    pub fn new() -> Self {
-        Self {
-            max_proc_us: std::sync::atomic::AtomicU64::new(0),
-            over_5000us_count: std::sync::atomic::AtomicU64::new(0),
-            total_messages: std::sync::atomic::AtomicU64::new(0),
+    /// Handle a request response; false if `text` isn't one
+    fn handle_control(&self, text: &str) -> bool {
+        if text.starts_with(r#"{"stream""#) {
+            return false;
+        }
+        let Ok(response) = serde_json::from_str::<ControlResponse>(text) else {
+            return false;
+        };
+        let mut subscription = self.subscription.lock().unwrap_or_else(|e| e.into_inner());
+        // Unknown ids: answers to an earlier session's requests
+        let Some(awaited) = subscription.awaiting.remove(&response.id) else {
+            return true;
+        };
+        let error = response.error.as_ref().map(|error| format!("{} (code {})", error.msg, error.code));
+        subscription.finish(response.id, error);
+        match (awaited, response.error, response.result) {
+            (Awaited::Change { method, streams }, Some(error), _) => {
+                self.stats.record_subscribe_error();
+                eprintln!("[WS] {} {} rejected: {} (code {})", method, streams.join(","), error.msg, error.code);
+            }
+            (Awaited::List, Some(error), _) => {
+                eprintln!("[WS] LIST_SUBSCRIPTIONS failed: {} (code {})", error.msg, error.code);
+            }
+            (Awaited::List, None, Some(serde_json::Value::Array(listed))) => {
+                let listed: HashSet<&str> = listed.iter().filter_map(|s| s.as_str()).collect();
+                drop(subscription);
+                let missing: Vec<String> = self.intended_streams().into_iter()
+                    .filter(|s| !listed.contains(s.as_str()))
+                    .collect();
+                if !missing.is_empty() {
+                    eprintln!("[WS] {} streams missing from the server's subscriptions, resubscribing: {}",
+                              missing.len(), missing.join(","));
+                    self.stats.record_subscriptions_repaired(missing.len() as u64);
+                    self.subscription.lock().unwrap_or_else(|e| e.into_inner()).queue(&self.tracer, "SUBSCRIBE", missing);
+                    self.resubscribe.notify_one();
+                }
+            }
+            _ => {}
         }
+        true
     }
 
-    #[inline(always)]
-    pub fn record(&self, proc_us: u64) {
-        use std::sync::atomic::Ordering;
+    /// Processing time accounting for this connection
+    pub fn conn_stats(&self) -> &Arc<ConnStats> {
+        &self.conn_stats
+    }
 
-        self.total_messages.fetch_add(1, Ordering::Relaxed);
+    /// Endpoint base URL used for the next connection attempt
+    pub fn endpoint(&self) -> String {
+        let endpoints = self.config.endpoints();
+        endpoints[self.endpoint_idx.load(Ordering::Relaxed) % endpoints.len()].clone()
+    }
 
-        // Update max
-        let mut current_max = self.max_proc_us.load(Ordering::Relaxed);
-        while proc_us > current_max {
-            match self.max_proc_us.compare_exchange_weak(
-                current_max,
-                proc_us,
-                Ordering::Relaxed,
-                Ordering::Relaxed,
-            ) {
-                Ok(_) => break,
-                Err(x) => current_max = x,
-            }
-        }
+    /// Switch to the next configured endpoint
+    pub fn rotate_endpoint(&self) {
+        self.endpoint_idx.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Parse the endpoint URL for this connection's streams
+    fn target(&self) -> Result<Target> {
+        let url = if let Some(streams) = &self.streams {
+            format!("{}/stream?streams={}", self.endpoint(), streams)
+        } else if self.config.all_market {
+            all_market_url(&self.endpoint())
+        } else {
+            // The new session subscribes to the current list; queued requests are moot
+            let mut subscription = self.subscription.lock().unwrap_or_else(|e| e.into_inner());
+            subscription.pending.clear();
+            subscription.abandon("session ended");
+            create_ws_url(&self.endpoint(), &subscription.symbols)
+        };
+        let url = with_query(url, &self.config.query);
+        let uri: Uri = url.parse().with_context(|| format!("Invalid URL: {}", url))?;
+        let host = uri.host().ok_or_else(|| anyhow!("URL has no host: {}", url))?.to_string();
+        let port = uri.port_u16().unwrap_or(if uri.scheme_str() == Some("ws") { 80 } else { 443 });
+        Ok(Target { url, host, port })
+    }
 
-        // Count > 5000us
-        if proc_us > 5000 {
-            self.over_5000us_count.fetch_add(1, Ordering::Relaxed);
+    /// Upgrade request for `target` with the configured extra headers
+    fn handshake_request(&self, target: &Target) -> Result<Request> {
+        let mut request = target.url.as_str().into_client_request()
+            .with_context(|| format!("Invalid URL: {}", target.url))?;
+        for (name, value) in &self.config.headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).with_context(|| format!("Invalid header name {:?}", name))?;
+            let value = HeaderValue::from_str(value).with_context(|| format!("Invalid value for header {}", name))?;
+            request.headers_mut().append(name, value);
         }
+        Ok(request)
     }
 
-    pub fn report(&self) {
-        use std::sync::atomic::Ordering;
+    /// Resolve the target host
+    async fn resolve(target: &Target) -> Result<Vec<SocketAddr>> {
+        let addrs: Vec<_> = lookup_host((target.host.as_str(), target.port))
+            .await
+            .map_err(|e| WsError::new(DisconnectKind::Dns, format!("DNS lookup for {} failed: {}", target.host, e)))?
+            .collect();
+        if addrs.is_empty() {
+            return Err(WsError::new(DisconnectKind::Dns, format!("DNS lookup for {} returned no addresses", target.host)).into());
+        }
+        Ok(addrs)
+    }
 
-        let total = self.total_messages.load(Ordering::Relaxed);
-        let max = self.max_proc_us.load(Ordering::Relaxed);
-        let over5ms = self.over_5000us_count.load(Ordering::Relaxed);
+    /// Resolve and connect to the fastest reachable address with `connect`
+    /// (see `race_connect`), under the connect timeout (a blackholed route can
+    /// hang for minutes)
+    async fn connect_with<S, F, Fut>(&self, target: &Target, connect: F) -> Result<S>
+    where
+        F: Fn(SocketAddr) -> Fut,
+        Fut: Future<Output = std::io::Result<S>>,
+    {
+        let attempt = async {
+            let addrs = Self::resolve(target).await?;
+            let stagger = Duration::from_millis(self.config.connect_race_delay_ms);
+            match race_connect(&addrs, stagger, connect).await {
+                Ok((stream, addr)) => {
+                    if addr != addrs[0] {
+                        eprintln!("[WS] Connected via {} ({} addresses raced)", addr, addrs.len());
+                    }
+                    Ok(stream)
+                }
+                Err(e) => Err(WsError::new(
+                    DisconnectKind::Connect,
+                    format!("TCP connect to {}:{} failed: {}", target.host, target.port, e),
+                ).into()),
+            }
+        };
 
-        eprintln!("\n[STATS] Total messages: {}", total);
-        eprintln!("[STATS] Max processing time: {} µs", max);
-        eprintln!("[STATS] Messages > 5000µs: {}", over5ms);
-        if total > 0 {
-            eprintln!("[STATS] > 5ms rate: {:.2}%", (over5ms as f64 / total as f64) * 100.0);
+        let connect_timeout = Duration::from_millis(self.config.connect_timeout_ms);
+        match timeout(connect_timeout, attempt).await {
+            Ok(result) => result,
+            Err(_) => {
+                self.stats.record_connect_timeout();
+                Err(WsError::new(
+                    DisconnectKind::Timeout,
+                    format!("TCP connect to {}:{} timed out after {:?}", target.host, target.port, connect_timeout),
+                ).into())
+            }
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Connect and start receiving messages
+    /// Returns the server's close code/reason when the session ends normally; errors
+    /// carry a DisconnectKind (see reconnect::classify) for the reconnect policy
+    pub async fn run(&self) -> Result<CloseInfo> {
+        let target = self.target()?;
+        eprintln!("[WS] Connecting to {} streams...", self.symbols().len());
 
-    #[test]
-    fn test_chunk_symbols() {
-        let symbols: Vec<String> = (0..1000).map(|i| format!("SYM{}", i)).collect();
-        let chunks = chunk_symbols(&symbols);
+        let tcp = self.connect_with(&target, TcpStream::connect).await?;
+        tcp.set_nodelay(true).context("Failed to set TCP_NODELAY")?;
 
-        // 1000 symbols should make 10 chunks (100 * 10)
-        assert_eq!(chunks.len(), 10);
-        assert_eq!(chunks[0].len(), 100);
-        assert_eq!(chunks[9].len(), 100);
+        self.session(&target, tcp).await
     }
 
-    #[test]
-    fn test_create_ws_url() {
-        let symbols = vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()];
-        let url = create_ws_url(&symbols);
+    /// Same as run(), with the socket on io_uring; must run inside uring::start
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    pub async fn run_uring(&self) -> Result<CloseInfo> {
+        let target = self.target()?;
+        eprintln!("[WS] Connecting to {} streams (io_uring)...", self.symbols().len());
 
-        assert!(url.contains("wss://fstream.binance.com/stream?streams="));
-        assert!(url.contains("btcusdt@bookTicker"));
-        assert!(url.contains("ethusdt@bookTicker"));
+        let stream = self.connect_with(&target, crate::uring::connect).await?;
+
+        self.session(&target, stream).await
+    }
+
+    /// TLS + WebSocket handshake over a connected socket, then the read loop
+    async fn session<S>(&self, target: &Target, stream: S) -> Result<CloseInfo>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let host = &target.host;
+
+        // TLS + WebSocket upgrade with its own timeout
+        let handshake_timeout = Duration::from_millis(self.config.handshake_timeout_ms);
+        let request = self.handshake_request(target)?;
+        let handshake = async {
+            let stream = wrap_tls(target, stream).await?;
+            client_async(request, stream).await.map_err(|e| {
+                let kind = reconnect::classify_tungstenite(&e);
+                anyhow::Error::from(WsError::new(kind, format!("Handshake failed with {}: {}", host, e)))
+            })
+        };
+        let (ws_stream, _) = match timeout(handshake_timeout, handshake).await {
+            Ok(result) => result?,
+            Err(_) => {
+                self.stats.record_handshake_timeout();
+                return Err(WsError::new(
+                    DisconnectKind::Timeout,
+                    format!("TLS/WebSocket handshake with {} timed out after {:?}", host, handshake_timeout),
+                ).into());
+            }
+        };
+
+        eprintln!("[WS] Connected! Receiving messages...");
+        self.enter(ConnState::Subscribing);
+        self.conn_stats.mark_up(crate::platform::monotonic_us());
+        if let Some((failover, role)) = &self.failover {
+            failover.mark_up(*role);
+        }
+
+        let (mut write, mut read) = ws_stream.split();
+
+        // Keepalive frames share the loop with reads (no separate task, so the
+        // session future needn't be Send); a silent socket for idle_timeout forces
+        // a reconnect (NAT-timed-out connections aren't always surfaced promptly)
+        let keepalive = self.config.keepalive;
+        let keepalive_every = keepalive.interval().unwrap_or(Duration::from_secs(3600));
+        let mut keepalive_tick = tokio::time::interval_at(Instant::now() + keepalive_every, keepalive_every);
+        let idle_timeout = (self.config.idle_timeout_ms > 0)
+            .then(|| Duration::from_millis(self.config.idle_timeout_ms));
+        let idle = tokio::time::sleep(idle_timeout.unwrap_or(Duration::MAX / 4));
+        tokio::pin!(idle);
+        let warmup_timeout = self.start_warmup().then(|| Duration::from_millis(self.config.warmup_timeout_ms));
+        let warmup = tokio::time::sleep(warmup_timeout.unwrap_or(Duration::MAX / 4));
+        tokio::pin!(warmup);
+        let check_every = Duration::from_millis(self.config.subscription_check_ms.max(1));
+        let mut check = tokio::time::interval_at(Instant::now() + check_every, check_every);
+        let chaos_lifetime = self.chaos_lifetime();
+        let chaos_close = tokio::time::sleep(chaos_lifetime.unwrap_or(Duration::MAX / 4));
+        tokio::pin!(chaos_close);
+
+        loop {
+            let next = tokio::select! {
+                next = read.next() => next,
+                _ = keepalive_tick.tick(), if keepalive.interval().is_some() => {
+                    // A dead socket surfaces as a read error
+                    let _ = write.send(keepalive.frame()).await;
+                    continue;
+                }
+                _ = self.resubscribe.notified() => {
+                    let requests = std::mem::take(&mut self.subscription.lock().unwrap_or_else(|e| e.into_inner()).pending);
+                    for request in requests {
+                        let _ = write.send(Message::Text(request)).await;
+                    }
+                    continue;
+                }
+                _ = check.tick(), if self.config.subscription_check_ms > 0 => {
+                    let _ = write.send(Message::Text(self.list_request())).await;
+                    continue;
+                }
+                _ = &mut warmup, if warmup_timeout.is_some() && self.warming.load(Ordering::Relaxed) => {
+                    self.warming.store(false, Ordering::Relaxed);
+                    self.stats.record_warmup_failure();
+                    // A session that delivers some symbols works: ask again for the silent ones
+                    if !self.resubscribe_silent() {
+                        return Err(WsError::new(DisconnectKind::WarmUp, self.warmup_failure()).into());
+                    }
+                    continue;
+                }
+                _ = &mut idle, if idle_timeout.is_some() => {
+                    let limit = idle_timeout.unwrap_or_default();
+                    self.stats.record_idle_timeout();
+                    return Err(WsError::new(
+                        DisconnectKind::Idle,
+                        format!("No data received for {:?}, forcing reconnect", limit),
+                    ).into());
+                }
+                _ = &mut chaos_close, if chaos_lifetime.is_some() => {
+                    self.stats.record_chaos_close();
+                    return Err(WsError::new(
+                        DisconnectKind::Reset,
+                        format!("Chaos: session dropped after {:?}", chaos_lifetime.unwrap_or_default()),
+                    ).into());
+                }
+            };
+            if let Some(limit) = idle_timeout {
+                idle.as_mut().reset(Instant::now() + limit);
+            }
+
+            // Under bursts several frames are usually readable at once: handle
+            // them back-to-back before going back to select!/the scheduler
+            let mut next = next;
+            let mut batch = 0;
+            let end = loop {
+                let Some(msg) = next else {
+                    eprintln!("[WS] Stream ended without close frame");
+                    self.stats.record_close(reconnect::CLOSE_ABNORMAL);
+                    break Some(Ok(CloseInfo::abnormal()));
+                };
+                // Test mode: the wait is before the frame's receive time, so it
+                // counts as neither parse nor processing time
+                if let (Some(latency), Ok(Message::Text(_))) = (&self.latency, &msg) {
+                    self.stats.record_injected(latency.delay().await);
+                }
+                if let Some(end) = self.handle_message(msg) {
+                    break Some(end);
+                }
+                batch += 1;
+                if batch >= MAX_BATCH {
+                    break None;
+                }
+                match read.next().now_or_never() {
+                    Some(more) => next = more,
+                    None => break None,
+                }
+            };
+            self.stats.record_batch(batch);
+            if let Some(end) = end {
+                return end;
+            }
+        }
+    }
+
+    /// How long the chaos test mode lets a new session live (None = off)
+    #[cfg(feature = "chaos")]
+    fn chaos_lifetime(&self) -> Option<Duration> {
+        self.config.chaos.as_ref().and_then(|chaos| chaos.session_lifetime())
+    }
+
+    #[cfg(not(feature = "chaos"))]
+    fn chaos_lifetime(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Move to `next` (see ConnState), logging the transition
+    fn enter(&self, next: ConnState) {
+        let tag = self.tag.get().map_or("WS", String::as_str);
+        match self.conn_stats.enter(next, crate::platform::monotonic_us()) {
+            Ok((previous, spent_us)) => {
+                eprintln!("[{}] {:?} -> {:?} (after {} ms)", tag, previous, next, spent_us / 1000);
+                self.metrics.timing("state_us", &[("conn", tag), ("state", &format!("{:?}", previous))], spent_us);
+                self.trace_transition(previous, next, spent_us);
+            }
+            Err(current) => eprintln!("[WARN] [{}] Ignored state transition {:?} -> {:?}", tag, current, next),
+        }
+    }
+
+    /// Open the span of a connection attempt (`reason`: how the previous session ended)
+    fn begin_span(&self, attempt: u32, reason: Option<&'static str>) {
+        let mut span = self.tracer.start(if reason.is_some() { "ws.reconnect" } else { "ws.connect" });
+        if span.is_recording() {
+            span.attr("conn", self.tag.get().map_or("WS", String::as_str));
+            span.attr("attempt", attempt as u64);
+            span.attr("endpoint", self.endpoint());
+            span.attr("streams", self.symbols().len() as u64);
+            if let Some(reason) = reason {
+                span.attr("reconnect.reason", reason);
+            }
+        }
+        *self.span.lock().unwrap_or_else(|e| e.into_inner()) = Some(span);
+    }
+
+    /// Close the attempt's handshake / subscribe child as its state is left;
+    /// the attempt itself ends once the session streams
+    fn trace_transition(&self, previous: ConnState, next: ConnState, spent_us: u64) {
+        let mut current = self.span.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(span) = current.as_ref() else {
+            return;
+        };
+        let name = match previous {
+            ConnState::Connecting => "ws.handshake",
+            ConnState::Subscribing => "ws.subscribe",
+            _ => return,
+        };
+        let mut child = self.tracer.child(span, name).client().started_before(Duration::from_micros(spent_us));
+        if next == ConnState::Draining {
+            child.fail("session ended");
+        }
+        child.end();
+        if next == ConnState::Streaming {
+            if let Some(span) = current.take() {
+                span.end();
+            }
+        }
+    }
+
+    /// End an attempt that never streamed with how it ended
+    fn fail_span(&self, kind: &DisconnectKind, error: String) {
+        if let Some(mut span) = self.span.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            span.attr("disconnect.kind", kind.label());
+            span.fail(error);
+            span.end();
+        }
+    }
+
+    /// Arm the warm-up check for a new session; false if it doesn't apply
+    /// (disabled, or a connection on explicit streams)
+    fn start_warmup(&self) -> bool {
+        let symbols = self.symbols();
+        if self.config.warmup_timeout_ms == 0 || self.streams.is_some() || symbols.is_empty() {
+            return false;
+        }
+        let required = (symbols.len() * self.config.warmup_min_pct.min(100) as usize).div_ceil(100);
+        *self.warmup.lock().unwrap_or_else(|e| e.into_inner()) = WarmUp {
+            done_at: symbols.len() - required,
+            subscribed: symbols.len(),
+            missing: symbols.into_iter().collect(),
+            started_us: crate::platform::monotonic_us(),
+        };
+        self.warming.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Tick off the frame's symbol; ends the warm-up once enough have arrived
+    fn observe_warmup(&self, text: &str, now_us: i64) {
+        let Some(symbol) = frame_symbol(text) else {
+            return;
+        };
+        let mut warmup = self.warmup.lock().unwrap_or_else(|e| e.into_inner());
+        if warmup.missing.remove(symbol) && warmup.missing.len() <= warmup.done_at {
+            self.warming.store(false, Ordering::Relaxed);
+            eprintln!("[WS] Warm-up complete: {} symbols missing after {} ms",
+                      warmup.missing.len(), (now_us - warmup.started_us) / 1000);
+        }
+    }
+
+    /// After a missed warm-up: SUBSCRIBE the still-silent symbols again on the
+    /// live session; false if none delivered at all (the session is reconnected)
+    fn resubscribe_silent(&self) -> bool {
+        let warmup = self.warmup.lock().unwrap_or_else(|e| e.into_inner());
+        if warmup.missing.len() >= warmup.subscribed {
+            return false;
+        }
+        let mut missing: Vec<&String> = warmup.missing.iter().collect();
+        missing.sort();
+        let shown: Vec<&str> = missing.iter().take(5).map(|s| s.as_str()).collect();
+        eprintln!("[WS] Warm-up missed: {} subscribed symbols silent after {} ms, resubscribing: {}{}",
+                  missing.len(), self.config.warmup_timeout_ms, shown.join(", "),
+                  if missing.len() > shown.len() { ", ..." } else { "" });
+        let streams = missing.iter().map(|s| stream_name(s)).collect();
+        drop(warmup);
+        self.subscription.lock().unwrap_or_else(|e| e.into_inner()).queue(&self.tracer, "SUBSCRIBE", streams);
+        self.resubscribe.notify_one();
+        true
+    }
+
+    /// Why the warm-up failed, naming a few missing symbols
+    fn warmup_failure(&self) -> String {
+        let warmup = self.warmup.lock().unwrap_or_else(|e| e.into_inner());
+        let mut missing: Vec<&String> = warmup.missing.iter().collect();
+        missing.sort();
+        let shown: Vec<&str> = missing.iter().take(5).map(|s| s.as_str()).collect();
+        format!("Warm-up failed: {} subscribed symbols silent after {} ms (need at most {}): {}{}",
+                missing.len(), self.config.warmup_timeout_ms, warmup.done_at, shown.join(", "),
+                if missing.len() > shown.len() { ", ..." } else { "" })
+    }
+
+    /// Handle one received message; Some(result) ends the session
+    #[inline]
+    fn handle_message(&self, msg: Result<Message, tokio_tungstenite::tungstenite::Error>) -> Option<Result<CloseInfo>> {
+        match msg {
+            Ok(Message::Text(text)) => {
+                let t_start = crate::platform::monotonic_us();
+                if self.warming.load(Ordering::Relaxed) {
+                    self.observe_warmup(&text, t_start);
+                }
+                // Bandwidth counts every frame read, a standby spare's included
+                self.conn_stats.record_bytes(text.len());
+                self.stats.record_bytes(text.len());
+                // Request responses aren't feed frames
+                if self.handle_control(&text) {
+                    return None;
+                }
+                // Subscriptions delivering: streaming once the warm-up (if any) is over
+                if self.conn_stats.state() == ConnState::Subscribing && !self.warming.load(Ordering::Relaxed) {
+                    self.enter(ConnState::Streaming);
+                }
+                // A standby spare reads and discards: the primary's copy is written
+                if let Some((failover, role)) = &self.failover {
+                    if !failover.is_active(*role) {
+                        return None;
+                    }
+                }
+
+                #[cfg(feature = "chaos")]
+                let text = {
+                    let mut text = text;
+                    if self.config.chaos.as_ref().is_some_and(|chaos| chaos.corrupt(&mut text)) {
+                        self.stats.record_chaos_corrupted();
+                    }
+                    text
+                };
+
+                if let Some(raw) = &self.raw_handler {
+                    raw(text);
+                } else {
+                    // Parse (with user hooks) and handle message
+                    self.stats.record_received();
+                    match self.hooks.decode(&text, self.config.strict_schema) {
+                        Some(Ok(mut data)) => {
+                            self.stats.record_parsed();
+                            data.received_us = t_start;
+                            (self.handler)(data);
+                        }
+                        Some(Err(e)) => {
+                            // Don't exit on parse errors - might be other message types
+                            self.stats.record_frame_error(&e);
+                            crate::eprintln_limited!("[WS] Failed to parse message: {}", e);
+                        }
+                        None => self.stats.record_hook_dropped(),
+                    }
+                }
+
+                self.conn_stats.record((crate::platform::monotonic_us() - t_start) as u64);
+            }
+            Ok(Message::Ping(data)) => {
+                // Tungstenite queues the pong, sent with the next read or write
+                self.stats.record_server_ping();
+                drop(data);
+            }
+            Ok(Message::Pong(_)) => {
+                // Expected response to our pings
+                self.stats.record_pong();
+            }
+            Ok(Message::Close(frame)) => {
+                let close = match frame {
+                    Some(frame) => CloseInfo { code: frame.code.into(), reason: frame.reason.into_owned() },
+                    None => CloseInfo { code: reconnect::CLOSE_NO_STATUS, reason: String::new() },
+                };
+                eprintln!("[WS] Connection closed by server: code {} reason {:?}", close.code, close.reason);
+                self.stats.record_close(close.code);
+                return Some(Ok(close));
+            }
+            Err(e) => {
+                eprintln!("[WS] Error receiving message: {}", e);
+                return Some(Err(WsError::new(DisconnectKind::Reset, format!("Read failed: {}", e)).into()));
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Connect to whichever of `addrs` accepts first (happy eyeballs, RFC 8305):
+/// attempts start in order, the next one `stagger` after the previous unless
+/// that one already failed, and the losers are dropped. Returns the stream
+/// and its address, or the last error when every address failed.
+pub async fn race_connect<S, F, Fut>(addrs: &[SocketAddr], stagger: Duration, connect: F) -> std::io::Result<(S, SocketAddr)>
+where
+    F: Fn(SocketAddr) -> Fut,
+    Fut: Future<Output = std::io::Result<S>>,
+{
+    let attempt = |addr| {
+        let connecting = connect(addr);
+        async move { (addr, connecting.await) }
+    };
+    let mut remaining = addrs.iter().copied();
+    let mut attempts = futures_util::stream::FuturesUnordered::new();
+    let mut last_err = None;
+    loop {
+        if attempts.is_empty() {
+            match remaining.next() {
+                Some(addr) => attempts.push(attempt(addr)),
+                None => {
+                    return Err(last_err.unwrap_or_else(|| std::io::Error::other("no addresses to connect to")));
+                }
+            }
+        }
+        tokio::select! {
+            Some((addr, result)) = attempts.next() => match result {
+                Ok(stream) => return Ok((stream, addr)),
+                Err(e) => {
+                    last_err = Some(e);
+                    attempts.extend(remaining.next().map(attempt));
+                }
+            },
+            _ = tokio::time::sleep(stagger), if remaining.len() > 0 => {
+                attempts.extend(remaining.next().map(attempt));
+            }
+        }
+    }
+}
+
+/// TLS-wrap the socket for wss:// endpoints
+/// (done here rather than by client_async_tls, which requires a Send socket)
+async fn wrap_tls<S>(target: &Target, stream: S) -> Result<MaybeTlsStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    if !target.url.starts_with("wss://") {
+        return Ok(MaybeTlsStream::Plain(stream));
+    }
+    let connector = native_tls::TlsConnector::new()
+        .map_err(|e| WsError::new(DisconnectKind::Tls, format!("TLS setup failed: {}", e)))?;
+    let tls = tokio_native_tls::TlsConnector::from(connector)
+        .connect(&target.host, stream)
+        .await
+        .map_err(|e| WsError::new(DisconnectKind::Tls, format!("TLS handshake with {} failed: {}", target.host, e)))?;
+    Ok(MaybeTlsStream::NativeTls(tls))
+}
+
+/// Resolved endpoint of one connection attempt
+struct Target {
+    url: String,
+    host: String,
+    port: u16,
+}
+
+/// Pass only `symbols` on to `handler`, counting the rest as filtered
+/// (the all-market stream carries every listed symbol)
+pub fn subscribed_only(handler: MessageHandler, symbols: &[String], stats: Arc<PerfStats>) -> MessageHandler {
+    let symbols: HashSet<String> = symbols.iter().cloned().collect();
+    Arc::new(move |data: BookTickerData| {
+        if symbols.contains(&data.symbol) {
+            handler(data);
+        } else {
+            stats.record_filtered();
+        }
+    })
+}
+
+/// Pass each update on once: the first copy of a symbol's update id (from
+/// either line) goes to `handler`, later copies and older ids are counted as
+/// arbitrated. Frames without an update id always pass.
+pub fn arbitrated(handler: MessageHandler, symbols: &[String], stats: Arc<PerfStats>) -> MessageHandler {
+    let last: HashMap<String, AtomicU64> = symbols.iter().map(|s| (s.clone(), AtomicU64::new(0))).collect();
+    Arc::new(move |data: BookTickerData| {
+        let first = data.update_id == 0
+            || last.get(&data.symbol).is_none_or(|last| last.fetch_max(data.update_id, Ordering::Relaxed) < data.update_id);
+        if first {
+            handler(data);
+        } else {
+            stats.record_arbitrated();
+        }
+    })
+}
+
+/// Multi-connection manager with fairness
+pub struct WsManager {
+    connections: Vec<Arc<WsConnection>>,
+    /// Handler the connections call (filters unsubscribed symbols in all-market mode)
+    handler: MessageHandler,
+    stats: Arc<PerfStats>,
+    /// Prefix for connection tags in logs (e.g. "P" -> "[WS-P0]")
+    label: String,
+    hooks: Arc<Hooks>,
+    /// Process-wide reconnect budget, and whether these connections go first
+    reconnect_bucket: Option<(Arc<ReconnectBucket>, bool)>,
+    /// Give up after too many consecutive errors (off for auxiliary feeds)
+    give_up: bool,
+}
+
+impl WsManager {
+    pub fn new(symbols: Vec<String>, handler: MessageHandler, config: WsConfig, stats: Arc<PerfStats>) -> Self {
+        let handler = if config.arbitrate {
+            eprintln!("[WS] A/B arbitration: every chunk on two connections, first copy of each update wins");
+            arbitrated(handler, &symbols, stats.clone())
+        } else {
+            handler
+        };
+        if config.all_market {
+            eprintln!("[WS] All-market bookTicker stream on 1 connection, keeping {} symbols", symbols.len());
+            let handler = subscribed_only(handler, &symbols, stats.clone());
+            let connections = Self::pair_spares(vec![symbols], &handler, &config, &stats);
+            return Self { connections, handler, stats, label: String::new(), hooks: Arc::default(), reconnect_bucket: None, give_up: true };
+        }
+
+        let chunks = chunk_for(&symbols, &config);
+        let n_connections = chunks.len();
+
+        eprintln!("[WS] Creating {} connections for {} symbols{}", n_connections, symbols.len(),
+                  if config.warm_spares { " (plus a warm spare each)" } else { "" });
+
+        let connections = Self::pair_spares(chunks, &handler, &config, &stats);
+        Self { connections, handler, stats, label: String::new(), hooks: Arc::default(), reconnect_bucket: None, give_up: true }
+    }
+
+    /// One connection per chunk; with A/B arbitration each followed by its B
+    /// line (starting on the next endpoint), with warm spares by its spare
+    fn pair_spares(chunks: Vec<Vec<String>>, handler: &MessageHandler, config: &WsConfig, stats: &Arc<PerfStats>) -> Vec<Arc<WsConnection>> {
+        let connect = |chunk| WsConnection::new(chunk, handler.clone(), config.clone(), stats.clone());
+        if !config.arbitrate && !config.warm_spares {
+            return chunks.into_iter().map(|chunk| Arc::new(connect(chunk))).collect();
+        }
+        chunks
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, chunk)| {
+                let (mut first, mut second) = (connect(chunk.clone()), connect(chunk));
+                if config.arbitrate {
+                    second.rotate_endpoint();
+                    second.pair = Some((i, "b"));
+                } else {
+                    let failover = Arc::new(Failover::new());
+                    first = first.with_failover(failover.clone(), PRIMARY);
+                    second = second.with_failover(failover, SPARE);
+                    second.pair = Some((i, "s"));
+                }
+                first.pair = Some((i, ""));
+                [Arc::new(first), Arc::new(second)]
+            })
+            .collect()
+    }
+
+    /// Manage prepared connections (e.g. with custom streams and raw handlers)
+    pub fn from_connections(connections: Vec<WsConnection>, stats: Arc<PerfStats>) -> Self {
+        let handler = connections.first().map_or_else(|| Arc::new(|_| {}) as MessageHandler, |c| c.handler.clone());
+        let connections = connections.into_iter().map(Arc::new).collect();
+        Self { connections, handler, stats, label: String::new(), hooks: Arc::default(), reconnect_bucket: None, give_up: true }
+    }
+
+    /// Number of connections for `symbols` under `config`
+    pub fn connection_count(symbols: &[String], config: &WsConfig) -> usize {
+        let chunks = if config.all_market { 1 } else { chunk_for(symbols, config).len() };
+        if config.arbitrate || config.warm_spares {
+            chunks * 2
+        } else {
+            chunks
+        }
+    }
+
+    /// The handler connections deliver parsed messages to
+    pub fn handler(&self) -> MessageHandler {
+        self.handler.clone()
+    }
+
+    /// Set the log label for this manager's connections
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = label.to_string();
+        self
+    }
+
+    /// Run `hooks` on every parsed frame (see crate::hooks); call before running
+    pub fn with_hooks(mut self, hooks: Arc<Hooks>) -> Self {
+        for conn in self.connections.iter_mut() {
+            if let Some(conn) = Arc::get_mut(conn) {
+                conn.hooks = hooks.clone();
+            }
+        }
+        self.hooks = hooks;
+        self
+    }
+
+    /// Report connection events to `metrics` (see crate::metrics); call before running
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        for conn in self.connections.iter_mut() {
+            if let Some(conn) = Arc::get_mut(conn) {
+                conn.metrics = metrics.clone();
+            }
+        }
+        self
+    }
+
+    /// Trace connection attempts with `tracer` (see crate::trace); call before running
+    pub fn with_tracer(mut self, tracer: Arc<Tracer>) -> Self {
+        for conn in self.connections.iter_mut() {
+            if let Some(conn) = Arc::get_mut(conn) {
+                conn.tracer = tracer.clone();
+            }
+        }
+        self
+    }
+
+    /// Delay every text frame by a draw from `latency` before handling it
+    /// (see crate::inject); call before running
+    pub fn with_latency(mut self, latency: Arc<crate::inject::LatencyInjector>) -> Self {
+        for conn in self.connections.iter_mut() {
+            if let Some(conn) = Arc::get_mut(conn) {
+                conn.latency = Some(latency.clone());
+            }
+        }
+        self
+    }
+
+    /// Keep reconnecting (with capped backoff) however many consecutive
+    /// errors, for auxiliary feeds that must never take the quotes down
+    pub fn retry_forever(mut self) -> Self {
+        self.give_up = false;
+        self
+    }
+
+    /// Take reconnect attempts from `bucket`, ahead of others if `priority`
+    pub fn with_reconnect_bucket(mut self, bucket: Arc<ReconnectBucket>, priority: bool) -> Self {
+        self.reconnect_bucket = Some((bucket, priority));
+        self
+    }
+
+    /// Hooks the connections run on parsed frames
+    pub fn hooks(&self) -> Arc<Hooks> {
+        self.hooks.clone()
+    }
+
+    /// Whether frames are checked against the full bookTicker schema (WS_STRICT_SCHEMA)
+    pub fn strict_schema(&self) -> bool {
+        self.connections.first().is_some_and(|c| c.config.strict_schema)
+    }
+
+    /// Give each connection a raw frame handler made by `make(connection index)`
+    /// (see WsConnection::with_raw_handler); call before running. Fails if a
+    /// connection is already shared, which would leave it on the parsed path.
+    pub fn with_raw_handlers(mut self, mut make: impl FnMut(usize) -> RawHandler) -> Result<Self> {
+        for (i, conn) in self.connections.iter_mut().enumerate() {
+            let conn = Arc::get_mut(conn).with_context(|| format!("Connection {} is shared, can't give it a raw handler", i))?;
+            conn.raw_handler = Some(make(i));
+        }
+        Ok(self)
+    }
+
+    /// Deliver a text frame as if connection `i` had read it
+    #[cfg(test)]
+    pub(crate) fn feed_raw(&self, i: usize, text: String) {
+        if let Some(raw) = &self.connections[i].raw_handler {
+            raw(text);
+        }
+    }
+
+    /// Log tag of connection `i`: "WS-3", or "WS-3s" / "WS-3b" for chunk 3's
+    /// warm spare / B line
+    fn tag(&self, i: usize) -> String {
+        match self.connections[i].pair {
+            Some((chunk, suffix)) => format!("WS-{}{}{}", self.label, chunk, suffix),
+            None => format!("WS-{}{}", self.label, i),
+        }
+    }
+
+    /// (log tag, stream count, stats) per connection
+    pub fn conn_stats(&self) -> Vec<(String, usize, Arc<ConnStats>)> {
+        self.connections
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (self.tag(i), c.symbols().len(), c.conn_stats.clone()))
+            .collect()
+    }
+
+    /// One restart handle per connection
+    pub fn restart_handles(&self) -> Vec<RestartHandle> {
+        self.connections
+            .iter()
+            .enumerate()
+            .map(|(i, c)| RestartHandle {
+                tag: self.tag(i),
+                subscription: c.subscription.clone(),
+                signal: c.restart.clone(),
+                stats: c.conn_stats.clone(),
+            })
+            .collect()
+    }
+
+    /// Apply a subscribe list change to the running connections
+    ///
+    /// Removed symbols are unsubscribed on the connections carrying them; each
+    /// added one is subscribed on one chunk (and its warm spare or B line),
+    /// picked like the initial chunking: by hash with WS_STABLE_CHUNKS, else
+    /// the least loaded, spilling over full ones. Untouched connections keep
+    /// their sessions. Nothing is applied if the additions don't fit or the
+    /// streams aren't per-symbol (all-market, explicit streams). Returns the
+    /// tags of the connections changed.
+    pub fn resubscribe(&self, diff: &SubscribeDiff) -> Result<Vec<String>> {
+        let Some(first) = self.connections.first() else {
+            return Err(anyhow!("no connections to resubscribe"));
+        };
+        if first.config.all_market || first.streams.is_some() {
+            return Err(anyhow!("streams are not per-symbol; restart to change the subscribe list"));
+        }
+        let chunk_of = |i: usize| self.connections[i].pair.map_or(i, |(chunk, _)| chunk);
+        let n_chunks = (0..self.connections.len()).map(chunk_of).max().map_or(0, |c| c + 1);
+        let mut carried: Vec<Vec<String>> = vec![Vec::new(); n_chunks];
+        for (i, conn) in self.connections.iter().enumerate() {
+            if carried[chunk_of(i)].is_empty() {
+                carried[chunk_of(i)] = conn.symbols();
+            }
+        }
+
+        let remove: Vec<Vec<String>> = carried
+            .iter()
+            .map(|symbols| diff.removed.iter().filter(|s| symbols.contains(s)).cloned().collect())
+            .collect();
+        let mut sizes: Vec<usize> = carried.iter().zip(&remove).map(|(c, r)| c.len() - r.len()).collect();
+        let mut add: Vec<Vec<String>> = vec![Vec::new(); n_chunks];
+        for symbol in &diff.added {
+            if carried.iter().zip(&remove).any(|(c, r)| c.contains(symbol) && !r.contains(symbol)) {
+                continue;
+            }
+            let mut chunk = if first.config.stable_chunks {
+                jump_hash(fnv1a(symbol.as_bytes()), n_chunks)
+            } else {
+                (0..n_chunks).min_by_key(|&c| sizes[c]).unwrap_or(0)
+            };
+            for _ in 0..n_chunks {
+                if sizes[chunk] < CHUNK_SIZE {
+                    break;
+                }
+                chunk = (chunk + 1) % n_chunks;
+            }
+            if sizes[chunk] >= CHUNK_SIZE {
+                return Err(anyhow!("no room for {} ({} connections full); restart to add connections", symbol, n_chunks));
+            }
+            sizes[chunk] += 1;
+            add[chunk].push(symbol.clone());
+        }
+
+        let mut changed = Vec::new();
+        for (i, conn) in self.connections.iter().enumerate() {
+            let (add, remove) = (&add[chunk_of(i)], &remove[chunk_of(i)]);
+            if add.is_empty() && remove.is_empty() {
+                continue;
+            }
+            let tag = self.tag(i);
+            eprintln!("[{}] Resubscribing: {}", tag, SubscribeDiff { added: add.clone(), removed: remove.clone() });
+            conn.update_streams(add, remove);
+            changed.push(tag);
+        }
+        Ok(changed)
+    }
+
+    /// Log per-connection load every `interval` on the current runtime
+    pub fn spawn_conn_reporter(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let conns = self.conn_stats();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            let mut last = tokio::time::Instant::now();
+            loop {
+                ticker.tick().await;
+                let wall_us = last.elapsed().as_micros().max(1) as u64;
+                last = tokio::time::Instant::now();
+                for (tag, streams, stats) in &conns {
+                    let (messages, busy_us, max_us, bytes) = stats.take_interval();
+                    let (frames, total_bytes) = stats.traffic();
+                    eprintln!("[CONN] {} ({} streams, {:?}): {} msgs ({:.0}/s), busy {:.2}%, avg {} µs, max {} µs, \
+                               {:.1} KB/s, avg frame {} B",
+                              tag, streams, stats.state(), messages, messages as f64 * 1e6 / wall_us as f64,
+                              busy_us as f64 * 100.0 / wall_us as f64,
+                              busy_us.checked_div(messages).unwrap_or(0), max_us,
+                              bytes as f64 * 1e6 / 1024.0 / wall_us as f64, total_bytes.checked_div(frames).unwrap_or(0));
+                }
+            }
+        })
+    }
+
+    /// Feed captured text frames through the first connection's read path
+    /// (decode, hooks, all-market filter, handler) as if just read, without
+    /// connecting; the handler is shared, so the frames' symbols don't matter
+    pub fn replay<S: Into<String>>(&self, frames: impl IntoIterator<Item = S>) {
+        let Some(conn) = self.connections.first() else {
+            return;
+        };
+        for frame in frames {
+            conn.handle_message(Ok(Message::Text(frame.into())));
+        }
+    }
+
+    /// Whether the config asks for the io_uring socket path (run_all_uring)
+    pub fn io_uring(&self) -> bool {
+        self.connections.first().is_some_and(|c| c.config.io_uring)
+    }
+
+    /// Run all connections concurrently with exponential backoff; returns the
+    /// first connection giving up (reconnect::TooManyErrors), stopping the others
+    pub async fn run_all(&self) -> Result<()> {
+        let tasks = self.connections
+            .iter()
+            .enumerate()
+            .map(|(i, conn)| {
+                let tag = self.tag(i);
+                let bucket = self.reconnect_bucket.clone();
+                tokio::spawn(supervise(conn.clone(), i, tag, self.stats.clone(), bucket, self.give_up,
+                                       |c| async move { c.run().await }))
+            })
+            .collect();
+
+        first_error(tasks).await
+    }
+
+    /// run_all() with io_uring sockets; must run inside uring::start
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    pub async fn run_all_uring(&self) -> Result<()> {
+        let tasks = self.connections
+            .iter()
+            .enumerate()
+            .map(|(i, conn)| {
+                let tag = self.tag(i);
+                let bucket = self.reconnect_bucket.clone();
+                tokio::task::spawn_local(supervise(conn.clone(), i, tag, self.stats.clone(), bucket, self.give_up,
+                                                   |c| async move { c.run_uring().await }))
+            })
+            .collect();
+
+        first_error(tasks).await
+    }
+}
+
+/// Wait for the supervision tasks; on the first error abort the rest and return it
+async fn first_error(mut tasks: Vec<tokio::task::JoinHandle<Result<()>>>) -> Result<()> {
+    while !tasks.is_empty() {
+        let (result, _, rest) = futures_util::future::select_all(tasks).await;
+        tasks = rest;
+        if let Ok(Err(e)) = result {
+            for task in &tasks {
+                task.abort();
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Keep one connection alive, cycling through the ConnState states (see Supervisor)
+async fn supervise<F, Fut>(
+    conn: Arc<WsConnection>,
+    i: usize,
+    tag: String,
+    stats: Arc<PerfStats>,
+    bucket: Option<(Arc<ReconnectBucket>, bool)>,
+    give_up: bool,
+    run: F,
+) -> Result<()>
+where
+    F: Fn(Arc<WsConnection>) -> Fut,
+    Fut: Future<Output = Result<CloseInfo>>,
+{
+    let _ = conn.tag.set(tag.clone());
+    let mut supervisor = Supervisor {
+        conn, i, tag, stats, bucket, give_up, backoff: BackoffCalculator::new(), consecutive_errors: 0, previous: None,
+    };
+    supervisor.start().await;
+    loop {
+        let result = supervisor.connect(&run).await;
+        let kind = supervisor.drain(result);
+        supervisor.back_off(&kind).await?;
+    }
+}
+
+/// One connection's supervision as a state machine:
+/// Backoff (staggered startup) -> Connecting -> Subscribing -> Streaming, the
+/// last two entered by the session itself; when the session ends, Draining
+/// (failover, outage accounting, classifying the end) -> Backoff (reconnect
+/// policy delay, then the shared reconnect budget, if any) -> Connecting
+struct Supervisor {
+    conn: Arc<WsConnection>,
+    i: usize,
+    tag: String,
+    stats: Arc<PerfStats>,
+    bucket: Option<(Arc<ReconnectBucket>, bool)>,
+    /// Whether too many consecutive errors are fatal
+    give_up: bool,
+    backoff: BackoffCalculator,
+    consecutive_errors: u32,
+    /// How the last session ended (None before the first)
+    previous: Option<&'static str>,
+}
+
+impl Supervisor {
+    /// Staggered startup (in the initial Backoff): 1 second delay between
+    /// connections to avoid rate limits
+    async fn start(&self) {
+        let startup_delay = tokio::time::Duration::from_secs(self.i as u64);
+        if startup_delay.as_millis() > 0 {
+            eprintln!("[{}] Waiting {:?} before startup (rate limiting)...", self.tag, startup_delay);
+            tokio::select! {
+                _ = tokio::time::sleep(startup_delay) => {}
+                _ = self.conn.restart.notified() => {}
+            }
+        }
+    }
+
+    /// Connecting: run one session until it ends
+    async fn connect<F, Fut>(&self, run: &F) -> Result<CloseInfo>
+    where
+        F: Fn(Arc<WsConnection>) -> Fut,
+        Fut: Future<Output = Result<CloseInfo>>,
+    {
+        self.conn.begin_span(self.backoff.attempt + 1, self.previous);
+        self.conn.enter(ConnState::Connecting);
+        eprintln!("[{}] Starting connection (attempt {})...", self.tag, self.backoff.attempt + 1);
+
+        // An operator restart drops the session future (and its socket) wherever it is;
+        // a panic in it (handler included) ends this session only
+        tokio::select! {
+            result = crate::fault::isolate(run(self.conn.clone())) => result.unwrap_or_else(|message| {
+                self.stats.record_panic();
+                eprintln!("[ALERT] [{}] Connection task panicked: {}; restarting it", self.tag, message);
+                Err(WsError::new(DisconnectKind::Panicked, message).into())
+            }),
+            _ = self.conn.restart.notified() => {
+                Err(WsError::new(DisconnectKind::Requested, "Restart requested by operator").into())
+            }
+        }
+    }
+
+    /// Draining: hand over to the warm spare, start the outage, classify how the session ended
+    fn drain(&self, result: Result<CloseInfo>) -> DisconnectKind {
+        self.conn.enter(ConnState::Draining);
+        self.conn.conn_stats.mark_down(crate::platform::monotonic_us());
+        if let Some((failover, role)) = &self.conn.failover {
+            if failover.mark_down(*role) {
+                self.stats.record_failover();
+                eprintln!("[{}] Down: {} takes over", self.tag, if *role == PRIMARY { "warm spare" } else { "primary" });
+            }
+        }
+        let (kind, error) = match result {
+            Ok(close) => {
+                eprintln!("[{}] Connection closed (code {})", self.tag, close.code);
+                let error = format!("Closed by server (code {})", close.code);
+                (DisconnectKind::ServerClose(close), error)
+            }
+            Err(e) => {
+                let kind = reconnect::classify(&e);
+                eprintln!("[{}] Connection error ({:?}): {}", self.tag, kind, e);
+                (kind, e.to_string())
+            }
+        };
+        self.conn.fail_span(&kind, error);
+        self.conn.metrics.counter("disconnects", &[("conn", &self.tag), ("kind", kind.label())], 1);
+        kind
+    }
+
+    /// Track consecutive errors across sessions; true once there were too many
+    /// (never when not giving up)
+    fn count_error(&mut self, kind: &DisconnectKind, plan: &reconnect::ReconnectPlan) -> bool {
+        if kind.was_established() {
+            self.consecutive_errors = 0;
+        } else if plan.counts_as_error {
+            self.consecutive_errors += 1;
+        }
+        self.give_up && self.consecutive_errors > 10
+    }
+
+    /// Backoff: apply the reconnect plan for `kind` (failing after too many
+    /// consecutive errors), then wait out its delay and the reconnect budget
+    async fn back_off(&mut self, kind: &DisconnectKind) -> Result<()> {
+        self.conn.enter(ConnState::Backoff);
+        self.previous = Some(kind.label());
+        let plan = reconnect::plan_reconnect(kind, &mut self.backoff);
+        if self.count_error(kind, &plan) {
+            eprintln!("[{}] FATAL: Too many consecutive errors, giving up", self.tag);
+            return Err(reconnect::TooManyErrors { tag: self.tag.clone() }.into());
+        }
+        let tag = &self.tag;
+        if plan.rotate_endpoint {
+            self.conn.rotate_endpoint();
+            eprintln!("[{}] Rotating endpoint to {}", tag, self.conn.endpoint());
+        }
+
+        self.stats.record_reconnect();
+
+        // Reconnect after the planned delay + jitter to avoid thundering herd
+        let jitter_ms = (self.i as u64 * 50) % 500; // 0-500ms jitter based on connection id
+        let delay = plan.delay + tokio::time::Duration::from_millis(jitter_ms);
+        #[cfg(feature = "chaos")]
+        let delay = delay + self.conn.config.chaos.as_ref().map_or(Duration::ZERO, |chaos| chaos.reconnect_delay());
+        eprintln!("[{}] Reconnecting in {:?}...", tag, delay);
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = self.conn.restart.notified() => eprintln!("[{}] Restart requested, reconnecting now", tag),
+        }
+        if let Some((bucket, priority)) = &self.bucket {
+            let waited = bucket.acquire(*priority).await;
+            if waited >= tokio::time::Duration::from_millis(100) {
+                eprintln!("[{}] Waited {:?} for the reconnect budget", tag, waited);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Per-connection processing time: time from a frame being read to its
+/// handler returning (JSON parse + price parse + SHM write), i.e. how much of
+/// the core this connection's symbols consume
+///
+/// Also tracks outages: from the end of an established session to the next
+/// established one (the initial connect doesn't count)
+pub struct ConnStats {
+    messages: std::sync::atomic::AtomicU64,
+    busy_us: std::sync::atomic::AtomicU64,
+    max_busy_us: std::sync::atomic::AtomicU64,
+    /// DOWN_NEVER_UP, 0 while up, else monotonic µs the current outage started
+    down_since_us: std::sync::atomic::AtomicI64,
+    outages: std::sync::atomic::AtomicU64,
+    downtime_us: std::sync::atomic::AtomicU64,
+    longest_outage_us: std::sync::atomic::AtomicU64,
+    /// Text frames and payload bytes read, standby included (bytes also per interval)
+    frames: std::sync::atomic::AtomicU64,
+    bytes: std::sync::atomic::AtomicU64,
+    interval_bytes: std::sync::atomic::AtomicU64,
+    /// Lifecycle state (ConnState index) and monotonic µs it was entered
+    state: std::sync::atomic::AtomicUsize,
+    state_since_us: std::sync::atomic::AtomicI64,
+    /// Per state: times entered, µs spent in it (finished stays)
+    state_entries: [std::sync::atomic::AtomicU64; ConnState::COUNT],
+    state_us: [std::sync::atomic::AtomicU64; ConnState::COUNT],
+}
+
+const DOWN_NEVER_UP: i64 = -1;
+
+impl Default for ConnStats {
+    fn default() -> Self {
+        Self {
+            messages: Default::default(),
+            busy_us: Default::default(),
+            max_busy_us: Default::default(),
+            down_since_us: std::sync::atomic::AtomicI64::new(DOWN_NEVER_UP),
+            outages: Default::default(),
+            downtime_us: Default::default(),
+            longest_outage_us: Default::default(),
+            frames: Default::default(),
+            bytes: Default::default(),
+            interval_bytes: Default::default(),
+            // Connections start out waiting for their staggered startup
+            state: std::sync::atomic::AtomicUsize::new(ConnState::Backoff.index()),
+            state_since_us: std::sync::atomic::AtomicI64::new(crate::platform::monotonic_us()),
+            state_entries: std::array::from_fn(|i| std::sync::atomic::AtomicU64::new((i == ConnState::Backoff.index()) as u64)),
+            state_us: Default::default(),
+        }
+    }
+}
+
+impl ConnStats {
+    /// Session established at `now_us`; closes the current outage
+    pub fn mark_up(&self, now_us: i64) {
+        use std::sync::atomic::Ordering;
+
+        let since = self.down_since_us.swap(0, Ordering::Relaxed);
+        if since > 0 {
+            let down_us = now_us.saturating_sub(since).max(0) as u64;
+            self.outages.fetch_add(1, Ordering::Relaxed);
+            self.downtime_us.fetch_add(down_us, Ordering::Relaxed);
+            fetch_max_relaxed(&self.longest_outage_us, down_us);
+        }
+    }
+
+    /// Session ended at `now_us`; starts an outage if the connection was up
+    pub fn mark_down(&self, now_us: i64) {
+        use std::sync::atomic::Ordering;
+
+        let _ = self.down_since_us.compare_exchange(0, now_us.max(1), Ordering::Relaxed, Ordering::Relaxed);
+    }
+
+    /// (outages, total downtime µs, longest outage µs), counting an ongoing one up to `now_us`
+    pub fn downtime(&self, now_us: i64) -> (u64, u64, u64) {
+        use std::sync::atomic::Ordering;
+
+        let (mut outages, mut total, mut longest) = (
+            self.outages.load(Ordering::Relaxed),
+            self.downtime_us.load(Ordering::Relaxed),
+            self.longest_outage_us.load(Ordering::Relaxed),
+        );
+        let since = self.down_since_us.load(Ordering::Relaxed);
+        if since > 0 {
+            let down_us = now_us.saturating_sub(since).max(0) as u64;
+            outages += 1;
+            total += down_us;
+            longest = longest.max(down_us);
+        }
+        (outages, total, longest)
+    }
+
+    #[inline(always)]
+    pub fn record(&self, busy_us: u64) {
+        use std::sync::atomic::Ordering;
+
+        self.messages.fetch_add(1, Ordering::Relaxed);
+        self.busy_us.fetch_add(busy_us, Ordering::Relaxed);
+        fetch_max_relaxed(&self.max_busy_us, busy_us);
+    }
+
+    /// A text frame of `len` bytes was read
+    #[inline(always)]
+    pub fn record_bytes(&self, len: usize) {
+        use std::sync::atomic::Ordering;
+
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(len as u64, Ordering::Relaxed);
+        self.interval_bytes.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    /// (text frames, payload bytes) read since startup
+    pub fn traffic(&self) -> (u64, u64) {
+        use std::sync::atomic::Ordering;
+
+        (self.frames.load(Ordering::Relaxed), self.bytes.load(Ordering::Relaxed))
+    }
+
+    #[inline(always)]
+    pub fn state(&self) -> ConnState {
+        ConnState::from_index(self.state.load(std::sync::atomic::Ordering::Relaxed))
+    }
+
+    /// Move to `next` at `now_us` (one writer: the connection's supervisor task).
+    /// Ok((previous state, µs spent in it)), or Err(current state) if the
+    /// transition isn't valid, changing nothing
+    pub fn enter(&self, next: ConnState, now_us: i64) -> Result<(ConnState, u64), ConnState> {
+        use std::sync::atomic::Ordering;
+
+        let current = self.state();
+        if !current.can_enter(next) {
+            return Err(current);
+        }
+        let since = self.state_since_us.swap(now_us, Ordering::Relaxed);
+        let spent_us = now_us.saturating_sub(since).max(0) as u64;
+        self.state_us[current.index()].fetch_add(spent_us, Ordering::Relaxed);
+        self.state_entries[next.index()].fetch_add(1, Ordering::Relaxed);
+        self.state.store(next.index(), Ordering::Relaxed);
+        Ok((current, spent_us))
+    }
+
+    /// (current state, µs in it so far) and per state (times entered, µs spent),
+    /// the current stay included up to `now_us`
+    pub fn state_times(&self, now_us: i64) -> ((ConnState, u64), [(u64, u64); ConnState::COUNT]) {
+        use std::sync::atomic::Ordering;
+
+        let current = self.state();
+        let in_state_us = now_us.saturating_sub(self.state_since_us.load(Ordering::Relaxed)).max(0) as u64;
+        let times = std::array::from_fn(|i| {
+            let spent_us = self.state_us[i].load(Ordering::Relaxed);
+            (self.state_entries[i].load(Ordering::Relaxed), spent_us + if i == current.index() { in_state_us } else { 0 })
+        });
+        ((current, in_state_us), times)
+    }
+
+    /// Drain the counters: (messages, busy_us, max_busy_us, bytes)
+    pub fn take_interval(&self) -> (u64, u64, u64, u64) {
+        use std::sync::atomic::Ordering;
+
+        (
+            self.messages.swap(0, Ordering::Relaxed),
+            self.busy_us.swap(0, Ordering::Relaxed),
+            self.max_busy_us.swap(0, Ordering::Relaxed),
+            self.interval_bytes.swap(0, Ordering::Relaxed),
+        )
+    }
+}
+
+/// Default slow-message buckets (1ms / 5ms / 20ms)
+pub const DEFAULT_SLOW_THRESHOLDS_US: [u64; 3] = [1_000, 5_000, 20_000];
+/// Default threshold for the periodic slow-message warning
+pub const DEFAULT_SLOW_WARN_US: u64 = 5_000;
+
+/// Performance statistics
+pub struct PerfStats {
+    pub max_proc_us: std::sync::atomic::AtomicU64,
+    /// Sum of handler (write path) times, for the average
+    pub proc_us_total: std::sync::atomic::AtomicU64,
+    /// Socket read to handler start (JSON decode, hooks, pipeline queueing)
+    pub max_parse_us: std::sync::atomic::AtomicU64,
+    pub parse_us_total: std::sync::atomic::AtomicU64,
+    /// Messages with a receive timestamp (the parse average's count)
+    pub parse_timed: std::sync::atomic::AtomicU64,
+    pub total_messages: std::sync::atomic::AtomicU64,
+    pub reconnects: std::sync::atomic::AtomicU64,
+    /// Warm spares (or recovered primaries) promoted when the active connection dropped
+    pub failovers: std::sync::atomic::AtomicU64,
+    /// Connection sessions ended by a caught panic (and restarted)
+    pub panics: std::sync::atomic::AtomicU64,
+    /// Messages delayed by latency injection, and the delay added (INJECT_LATENCY)
+    pub injected: std::sync::atomic::AtomicU64,
+    pub injected_us_total: std::sync::atomic::AtomicU64,
+    /// Sessions dropped and frames corrupted by the chaos test mode (CHAOS_*)
+    pub chaos_closes: std::sync::atomic::AtomicU64,
+    pub chaos_corrupted: std::sync::atomic::AtomicU64,
+    /// Sessions reconnected for failing warm-up (symbols silent after connecting)
+    pub warmup_failures: std::sync::atomic::AtomicU64,
+    /// Keepalive: pings the server sent (each answered), pongs it sent
+    pub server_pings: std::sync::atomic::AtomicU64,
+    pub pongs: std::sync::atomic::AtomicU64,
+    /// SUBSCRIBE/UNSUBSCRIBE requests the server answered with an error
+    pub subscribe_errors: std::sync::atomic::AtomicU64,
+    /// Streams resubscribed after LIST_SUBSCRIPTIONS showed them missing
+    pub subscriptions_repaired: std::sync::atomic::AtomicU64,
+    /// A/B arbitration: updates the other line delivered first
+    pub arbitrated: std::sync::atomic::AtomicU64,
+    pub connect_timeouts: std::sync::atomic::AtomicU64,
+    pub handshake_timeouts: std::sync::atomic::AtomicU64,
+    pub idle_timeouts: std::sync::atomic::AtomicU64,
+    /// Frames dropped on a full decode pipeline ring
+    pub pipeline_full: std::sync::atomic::AtomicU64,
+    /// All-market stream messages for symbols we don't subscribe to
+    pub filtered_messages: std::sync::atomic::AtomicU64,
+    /// Messages dropped by user hooks
+    pub hook_dropped: std::sync::atomic::AtomicU64,
+    /// Frames that weren't valid bookTicker JSON
+    pub parse_errors: std::sync::atomic::AtomicU64,
+    /// Strict mode: frames with missing or malformed bookTicker fields
+    pub schema_violations: std::sync::atomic::AtomicU64,
+    /// Drop funnel (see PerfSnapshot::unaccounted): text frames taken off the feed connections
+    pub frames_received: std::sync::atomic::AtomicU64,
+    /// Frames decoded into a bookTicker message
+    pub frames_parsed: std::sync::atomic::AtomicU64,
+    /// Messages whose bid or ask didn't parse as a price
+    pub price_errors: std::sync::atomic::AtomicU64,
+    /// Unchanged quotes skipped while shedding
+    pub deduped: std::sync::atomic::AtomicU64,
+    /// Superseded frames skipped past the decode pipeline deadline
+    pub deadline_skipped: std::sync::atomic::AtomicU64,
+    /// Text frame payload read off the feed connections, standby spares included
+    pub bytes_received: std::sync::atomic::AtomicU64,
+    /// Frames handled per read wakeup, power-of-two buckets
+    batch_sizes: [std::sync::atomic::AtomicU64; BATCH_BUCKETS],
+    /// Server close frames by close code (cold path, so a mutex is fine)
+    close_codes: std::sync::Mutex<std::collections::BTreeMap<u16, u64>>,
+    /// Bucket thresholds (µs), ascending
+    thresholds_us: Vec<u64>,
+    /// Messages strictly over thresholds_us[i]
+    over_counts: Vec<std::sync::atomic::AtomicU64>,
+    /// Latency budget (µs, 0 = none, see with_budget) and messages over it
+    budget_us: u64,
+    over_budget: std::sync::atomic::AtomicU64,
+    /// Slow-message warning threshold and per-interval counters (drained by the reporter)
+    warn_us: u64,
+    interval_slow: std::sync::atomic::AtomicU64,
+    interval_max_us: std::sync::atomic::AtomicU64,
+    /// Write-path latency per symbol_id (empty = not tracked, see with_symbol_latency)
+    symbol_latency: Vec<SymbolLatency>,
+}
+
+/// One symbol's write-path latency counters
+#[derive(Default)]
+struct SymbolLatency {
+    messages: std::sync::atomic::AtomicU64,
+    proc_us_total: std::sync::atomic::AtomicU64,
+    max_proc_us: std::sync::atomic::AtomicU64,
+    /// Messages over the slow-message warning threshold
+    slow: std::sync::atomic::AtomicU64,
+}
+
+/// One symbol's write-path latency totals
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SymbolLatencySnapshot {
+    pub symbol_id: u64,
+    pub messages: u64,
+    pub proc_us_total: u64,
+    pub max_proc_us: u64,
+    pub slow: u64,
+}
+
+/// Point-in-time copy of PerfStats counters
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PerfSnapshot {
+    pub total_messages: u64,
+    pub max_proc_us: u64,
+    /// (threshold_us, messages over threshold)
+    #[serde(default)]
+    pub slow_buckets: Vec<(u64, u64)>,
+    #[serde(default)]
+    pub proc_us_total: u64,
+    #[serde(default)]
+    pub max_parse_us: u64,
+    #[serde(default)]
+    pub parse_us_total: u64,
+    #[serde(default)]
+    pub parse_timed: u64,
+    pub reconnects: u64,
+    #[serde(default)]
+    pub failovers: u64,
+    #[serde(default)]
+    pub panics: u64,
+    #[serde(default)]
+    pub injected: u64,
+    #[serde(default)]
+    pub injected_us_total: u64,
+    #[serde(default)]
+    pub chaos_closes: u64,
+    #[serde(default)]
+    pub chaos_corrupted: u64,
+    #[serde(default)]
+    pub warmup_failures: u64,
+    #[serde(default)]
+    pub server_pings: u64,
+    #[serde(default)]
+    pub pongs: u64,
+    #[serde(default)]
+    pub subscribe_errors: u64,
+    #[serde(default)]
+    pub subscriptions_repaired: u64,
+    #[serde(default)]
+    pub arbitrated: u64,
+    #[serde(default)]
+    pub connect_timeouts: u64,
+    #[serde(default)]
+    pub handshake_timeouts: u64,
+    #[serde(default)]
+    pub idle_timeouts: u64,
+    /// (close code, count), ascending by code
+    #[serde(default)]
+    pub close_codes: Vec<(u16, u64)>,
+    #[serde(default)]
+    pub pipeline_full: u64,
+    #[serde(default)]
+    pub filtered_messages: u64,
+    /// (smallest batch size in bucket, read wakeups), power-of-two buckets
+    #[serde(default)]
+    pub batch_sizes: Vec<(u64, u64)>,
+    #[serde(default)]
+    pub hook_dropped: u64,
+    #[serde(default)]
+    pub parse_errors: u64,
+    #[serde(default)]
+    pub schema_violations: u64,
+    #[serde(default)]
+    pub frames_received: u64,
+    #[serde(default)]
+    pub frames_parsed: u64,
+    #[serde(default)]
+    pub price_errors: u64,
+    #[serde(default)]
+    pub deduped: u64,
+    #[serde(default)]
+    pub deadline_skipped: u64,
+    #[serde(default)]
+    pub bytes_received: u64,
+    /// Messages over the latency budget (see PerfStats::with_budget)
+    #[serde(default)]
+    pub over_budget: u64,
+}
+
+impl PerfSnapshot {
+    /// Received frames with no recorded outcome
+    ///
+    /// Every frame ends in exactly one of: parse error, schema violation, hook
+    /// drop, not subscribed, A/B duplicate, price error, deduped, skipped past
+    /// the pipeline deadline or written
+    /// (`total_messages`). Crossed and zero-price quotes are written with their
+    /// status bits, not dropped. Frames still queued in the decode pipeline
+    /// show up here briefly; anything else means a path drops without counting.
+    pub fn unaccounted(&self) -> u64 {
+        let outcomes = [
+            self.parse_errors,
+            self.schema_violations,
+            self.hook_dropped,
+            self.filtered_messages,
+            self.arbitrated,
+            self.price_errors,
+            self.deduped,
+            self.deadline_skipped,
+            self.total_messages,
+        ];
+        self.frames_received.saturating_sub(outcomes.iter().sum())
+    }
+}
+
+impl Default for PerfStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lock-free "store max" on an atomic
+#[inline(always)]
+fn fetch_max_relaxed(target: &std::sync::atomic::AtomicU64, value: u64) {
+    use std::sync::atomic::Ordering;
+
+    let mut current = target.load(Ordering::Relaxed);
+    while value > current {
+        match target.compare_exchange_weak(current, value, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => break,
+            Err(x) => current = x,
+        }
+    }
+}
+
+impl PerfStats {
+    pub fn new() -> Self {
+        Self::with_thresholds(DEFAULT_SLOW_THRESHOLDS_US.to_vec(), DEFAULT_SLOW_WARN_US)
+    }
+
+    /// Create with custom bucket thresholds and warning threshold (µs)
+    pub fn with_thresholds(mut thresholds_us: Vec<u64>, warn_us: u64) -> Self {
+        thresholds_us.sort_unstable();
+        thresholds_us.dedup();
+        let over_counts = thresholds_us
+            .iter()
+            .map(|_| std::sync::atomic::AtomicU64::new(0))
+            .collect();
+
+        Self {
+            max_proc_us: std::sync::atomic::AtomicU64::new(0),
+            proc_us_total: std::sync::atomic::AtomicU64::new(0),
+            max_parse_us: std::sync::atomic::AtomicU64::new(0),
+            parse_us_total: std::sync::atomic::AtomicU64::new(0),
+            parse_timed: std::sync::atomic::AtomicU64::new(0),
+            total_messages: std::sync::atomic::AtomicU64::new(0),
+            reconnects: std::sync::atomic::AtomicU64::new(0),
+            failovers: std::sync::atomic::AtomicU64::new(0),
+            panics: std::sync::atomic::AtomicU64::new(0),
+            injected: std::sync::atomic::AtomicU64::new(0),
+            injected_us_total: std::sync::atomic::AtomicU64::new(0),
+            chaos_closes: std::sync::atomic::AtomicU64::new(0),
+            chaos_corrupted: std::sync::atomic::AtomicU64::new(0),
+            warmup_failures: std::sync::atomic::AtomicU64::new(0),
+            server_pings: std::sync::atomic::AtomicU64::new(0),
+            pongs: std::sync::atomic::AtomicU64::new(0),
+            subscribe_errors: std::sync::atomic::AtomicU64::new(0),
+            subscriptions_repaired: std::sync::atomic::AtomicU64::new(0),
+            arbitrated: std::sync::atomic::AtomicU64::new(0),
+            connect_timeouts: std::sync::atomic::AtomicU64::new(0),
+            handshake_timeouts: std::sync::atomic::AtomicU64::new(0),
+            idle_timeouts: std::sync::atomic::AtomicU64::new(0),
+            pipeline_full: std::sync::atomic::AtomicU64::new(0),
+            filtered_messages: std::sync::atomic::AtomicU64::new(0),
+            hook_dropped: std::sync::atomic::AtomicU64::new(0),
+            parse_errors: std::sync::atomic::AtomicU64::new(0),
+            schema_violations: std::sync::atomic::AtomicU64::new(0),
+            frames_received: std::sync::atomic::AtomicU64::new(0),
+            frames_parsed: std::sync::atomic::AtomicU64::new(0),
+            price_errors: std::sync::atomic::AtomicU64::new(0),
+            deduped: std::sync::atomic::AtomicU64::new(0),
+            deadline_skipped: std::sync::atomic::AtomicU64::new(0),
+            bytes_received: std::sync::atomic::AtomicU64::new(0),
+            batch_sizes: Default::default(),
+            close_codes: std::sync::Mutex::new(std::collections::BTreeMap::new()),
+            thresholds_us,
+            over_counts,
+            budget_us: 0,
+            over_budget: std::sync::atomic::AtomicU64::new(0),
+            warn_us,
+            interval_slow: std::sync::atomic::AtomicU64::new(0),
+            interval_max_us: std::sync::atomic::AtomicU64::new(0),
+            symbol_latency: Vec::new(),
+        }
+    }
+
+    /// Also keep latency counts and maxima per symbol_id below `n_symbols`
+    /// (higher ids, e.g. added by a reload past the array, aren't tracked)
+    pub fn with_symbol_latency(mut self, n_symbols: usize) -> Self {
+        self.symbol_latency = (0..n_symbols).map(|_| SymbolLatency::default()).collect();
+        self
+    }
+
+    /// Also count messages over `budget_us` (the shed controller's input),
+    /// apart from the configured slow-message buckets
+    pub fn with_budget(mut self, budget_us: u64) -> Self {
+        self.budget_us = budget_us;
+        self
+    }
+
+    pub fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_failover(&self) {
+        self.failovers.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_panic(&self) {
+        self.panics.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_injected(&self, delay_us: u64) {
+        self.injected.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.injected_us_total.fetch_add(delay_us, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_chaos_close(&self) {
+        self.chaos_closes.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_chaos_corrupted(&self) {
+        self.chaos_corrupted.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_warmup_failure(&self) {
+        self.warmup_failures.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_server_ping(&self) {
+        self.server_pings.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_pong(&self) {
+        self.pongs.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_subscribe_error(&self) {
+        self.subscribe_errors.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_subscriptions_repaired(&self, streams: u64) {
+        self.subscriptions_repaired.fetch_add(streams, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn record_arbitrated(&self) {
+        self.arbitrated.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_connect_timeout(&self) {
+        self.connect_timeouts.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_handshake_timeout(&self) {
+        self.handshake_timeouts.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_idle_timeout(&self) {
+        self.idle_timeouts.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Count a read wakeup that handled `frames` frames
+    #[inline(always)]
+    pub fn record_batch(&self, frames: usize) {
+        if frames == 0 {
+            return;
+        }
+        let bucket = (frames.ilog2() as usize).min(BATCH_BUCKETS - 1);
+        self.batch_sizes[bucket].fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn record_filtered(&self) {
+        self.filtered_messages.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn record_hook_dropped(&self) {
+        self.hook_dropped.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn record_received(&self) {
+        self.frames_received.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn record_bytes(&self, len: usize) {
+        self.bytes_received.fetch_add(len as u64, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn record_parsed(&self) {
+        self.frames_parsed.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_price_error(&self) {
+        self.price_errors.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn record_deduped(&self) {
+        self.deduped.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_deadline_skipped(&self) {
+        self.deadline_skipped.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Count a frame that produced no message, by cause
+    pub fn record_frame_error(&self, error: &FrameError) {
+        let counter = match error {
+            FrameError::Parse(_) => &self.parse_errors,
+            FrameError::Schema(_) => &self.schema_violations,
+        };
+        counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_pipeline_full(&self) {
+        self.pipeline_full.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn record_close(&self, code: u16) {
+        let mut codes = self.close_codes.lock().unwrap_or_else(|e| e.into_inner());
+        *codes.entry(code).or_insert(0) += 1;
+    }
+
+    pub fn snapshot(&self) -> PerfSnapshot {
+        use std::sync::atomic::Ordering;
+
+        PerfSnapshot {
+            total_messages: self.total_messages.load(Ordering::Relaxed),
+            max_proc_us: self.max_proc_us.load(Ordering::Relaxed),
+            slow_buckets: self.thresholds_us
+                .iter()
+                .zip(&self.over_counts)
+                .map(|(&t, c)| (t, c.load(Ordering::Relaxed)))
+                .collect(),
+            proc_us_total: self.proc_us_total.load(Ordering::Relaxed),
+            max_parse_us: self.max_parse_us.load(Ordering::Relaxed),
+            parse_us_total: self.parse_us_total.load(Ordering::Relaxed),
+            parse_timed: self.parse_timed.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            failovers: self.failovers.load(Ordering::Relaxed),
+            panics: self.panics.load(Ordering::Relaxed),
+            injected: self.injected.load(Ordering::Relaxed),
+            injected_us_total: self.injected_us_total.load(Ordering::Relaxed),
+            chaos_closes: self.chaos_closes.load(Ordering::Relaxed),
+            chaos_corrupted: self.chaos_corrupted.load(Ordering::Relaxed),
+            warmup_failures: self.warmup_failures.load(Ordering::Relaxed),
+            server_pings: self.server_pings.load(Ordering::Relaxed),
+            pongs: self.pongs.load(Ordering::Relaxed),
+            subscribe_errors: self.subscribe_errors.load(Ordering::Relaxed),
+            subscriptions_repaired: self.subscriptions_repaired.load(Ordering::Relaxed),
+            arbitrated: self.arbitrated.load(Ordering::Relaxed),
+            connect_timeouts: self.connect_timeouts.load(Ordering::Relaxed),
+            handshake_timeouts: self.handshake_timeouts.load(Ordering::Relaxed),
+            idle_timeouts: self.idle_timeouts.load(Ordering::Relaxed),
+            close_codes: self.close_codes
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .iter()
+                .map(|(&code, &count)| (code, count))
+                .collect(),
+            pipeline_full: self.pipeline_full.load(Ordering::Relaxed),
+            filtered_messages: self.filtered_messages.load(Ordering::Relaxed),
+            batch_sizes: self.batch_sizes
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (1u64 << i, c.load(Ordering::Relaxed)))
+                .filter(|&(_, count)| count > 0)
+                .collect(),
+            hook_dropped: self.hook_dropped.load(Ordering::Relaxed),
+            parse_errors: self.parse_errors.load(Ordering::Relaxed),
+            schema_violations: self.schema_violations.load(Ordering::Relaxed),
+            frames_received: self.frames_received.load(Ordering::Relaxed),
+            frames_parsed: self.frames_parsed.load(Ordering::Relaxed),
+            price_errors: self.price_errors.load(Ordering::Relaxed),
+            deduped: self.deduped.load(Ordering::Relaxed),
+            deadline_skipped: self.deadline_skipped.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            over_budget: self.over_budget.load(Ordering::Relaxed),
+        }
+    }
+
+    #[inline(always)]
+    pub fn record(&self, proc_us: u64) {
+        use std::sync::atomic::Ordering;
+
+        self.total_messages.fetch_add(1, Ordering::Relaxed);
+        self.proc_us_total.fetch_add(proc_us, Ordering::Relaxed);
+
+        // Update max
+        fetch_max_relaxed(&self.max_proc_us, proc_us);
+
+        // Count per bucket (thresholds are ascending, so stop at the first miss)
+        for (threshold, count) in self.thresholds_us.iter().zip(&self.over_counts) {
+            if proc_us <= *threshold {
+                break;
+            }
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+        if self.budget_us > 0 && proc_us > self.budget_us {
+            self.over_budget.fetch_add(1, Ordering::Relaxed);
+        }
+
+        // Slow-message warning is logged later by the background reporter
+        if proc_us > self.warn_us {
+            self.interval_slow.fetch_add(1, Ordering::Relaxed);
+            fetch_max_relaxed(&self.interval_max_us, proc_us);
+        }
+    }
+
+    /// Attribute a message's processing time to its symbol (no-op unless tracked)
+    #[inline(always)]
+    pub fn record_symbol(&self, symbol_id: u64, proc_us: u64) {
+        use std::sync::atomic::Ordering;
+
+        let Some(symbol) = self.symbol_latency.get(symbol_id as usize) else {
+            return;
+        };
+        symbol.messages.fetch_add(1, Ordering::Relaxed);
+        symbol.proc_us_total.fetch_add(proc_us, Ordering::Relaxed);
+        fetch_max_relaxed(&symbol.max_proc_us, proc_us);
+        if proc_us > self.warn_us {
+            symbol.slow.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Per-symbol totals of symbols with messages, slowest (max) first
+    pub fn symbol_latency(&self) -> Vec<SymbolLatencySnapshot> {
+        use std::sync::atomic::Ordering;
+
+        let mut symbols: Vec<SymbolLatencySnapshot> = self.symbol_latency
+            .iter()
+            .enumerate()
+            .map(|(id, symbol)| SymbolLatencySnapshot {
+                symbol_id: id as u64,
+                messages: symbol.messages.load(Ordering::Relaxed),
+                proc_us_total: symbol.proc_us_total.load(Ordering::Relaxed),
+                max_proc_us: symbol.max_proc_us.load(Ordering::Relaxed),
+                slow: symbol.slow.load(Ordering::Relaxed),
+            })
+            .filter(|symbol| symbol.messages > 0)
+            .collect();
+        symbols.sort_by(|a, b| b.max_proc_us.cmp(&a.max_proc_us).then(b.slow.cmp(&a.slow)).then(a.symbol_id.cmp(&b.symbol_id)));
+        symbols
+    }
+
+    /// Log the `top` slowest symbols with their share of messages and of slow
+    /// messages, to tell whether slowness clusters on a few (high-rate) symbols
+    pub fn report_symbols(&self, names: &HashMap<String, u64>, top: usize) {
+        let symbols = self.symbol_latency();
+        if symbols.is_empty() {
+            return;
+        }
+        let by_id: HashMap<u64, &str> = names.iter().map(|(name, &id)| (id, name.as_str())).collect();
+        let messages: u64 = symbols.iter().map(|s| s.messages).sum();
+        let slow: u64 = symbols.iter().map(|s| s.slow).sum();
+        eprintln!("[STATS] Slowest symbols ({} with messages, {} messages over {} µs):", symbols.len(), slow, self.warn_us);
+        for s in symbols.iter().take(top) {
+            let name = by_id.get(&s.symbol_id).map_or_else(|| format!("#{}", s.symbol_id), |name| name.to_string());
+            eprintln!("[STATS]   {}: max {} µs, avg {:.1} µs, {} messages ({:.1}%), {} slow ({:.1}%)",
+                      name, s.max_proc_us, s.proc_us_total as f64 / s.messages as f64,
+                      s.messages, s.messages as f64 * 100.0 / messages as f64,
+                      s.slow, if slow > 0 { s.slow as f64 * 100.0 / slow as f64 } else { 0.0 });
+        }
+    }
+
+    /// Record the parse side of a message: socket read to handler start
+    #[inline(always)]
+    pub fn record_parse(&self, parse_us: u64) {
+        use std::sync::atomic::Ordering;
+
+        self.parse_timed.fetch_add(1, Ordering::Relaxed);
+        self.parse_us_total.fetch_add(parse_us, Ordering::Relaxed);
+        fetch_max_relaxed(&self.max_parse_us, parse_us);
+    }
+
+    /// Drain the per-interval slow-message counters: (count, max_us)
+    pub fn take_interval_slow(&self) -> (u64, u64) {
+        use std::sync::atomic::Ordering;
+
+        let count = self.interval_slow.swap(0, Ordering::Relaxed);
+        let max = self.interval_max_us.swap(0, Ordering::Relaxed);
+        (count, max)
+    }
+
+    /// Log a warning if any slow messages were seen since the last call
+    pub fn report_interval(&self) {
+        let (count, max) = self.take_interval_slow();
+        if count > 0 {
+            eprintln!("[WARN] {} messages over {} µs in last interval (max {} µs)",
+                      count, self.warn_us, max);
+        }
+    }
+
+    pub fn report(&self) {
+        let snap = self.snapshot();
+        let total = snap.total_messages;
+        let max = snap.max_proc_us;
+
+        eprintln!("\n[STATS] Total messages: {}", total);
+        eprintln!("[STATS] Max processing time: {} µs", max);
+        if snap.parse_timed > 0 && total > 0 {
+            eprintln!("[STATS] Processing split: parse avg {:.1} µs (max {}), write avg {:.1} µs (max {})",
+                      snap.parse_us_total as f64 / snap.parse_timed as f64, snap.max_parse_us,
+                      snap.proc_us_total as f64 / total as f64, max);
+        }
+        for (threshold, count) in &snap.slow_buckets {
+            eprintln!("[STATS] Messages > {}µs: {}", threshold, count);
+            if total > 0 {
+                eprintln!("[STATS] > {}µs rate: {:.2}%", threshold, (*count as f64 / total as f64) * 100.0);
+            }
+        }
+        eprintln!("[STATS] Reconnects: {}", snap.reconnects);
+        if snap.failovers > 0 {
+            eprintln!("[STATS] Failovers to warm spares: {}", snap.failovers);
+        }
+        if snap.panics > 0 {
+            eprintln!("[STATS] Connection panics (restarted): {}", snap.panics);
+        }
+        if snap.injected > 0 {
+            eprintln!("[STATS] Injected latency: {} messages delayed, avg {}µs",
+                      snap.injected, snap.injected_us_total / snap.injected);
+        }
+        if snap.chaos_closes > 0 || snap.chaos_corrupted > 0 {
+            eprintln!("[STATS] Chaos: {} sessions dropped, {} frames corrupted", snap.chaos_closes, snap.chaos_corrupted);
+        }
+        if snap.arbitrated > 0 {
+            eprintln!("[STATS] A/B arbitration: {} delivered ({} duplicate or older copies dropped)",
+                      total, snap.arbitrated);
+        }
+        eprintln!("[STATS] Timeouts: connect {}, handshake {}, idle {}",
+                  snap.connect_timeouts, snap.handshake_timeouts, snap.idle_timeouts);
+        if snap.warmup_failures > 0 {
+            eprintln!("[STATS] Warm-up misses (resubscribed or reconnected): {}", snap.warmup_failures);
+        }
+        if snap.server_pings + snap.pongs > 0 {
+            eprintln!("[STATS] Keepalive: {} server pings answered, {} pongs received", snap.server_pings, snap.pongs);
+        }
+        if snap.subscribe_errors + snap.subscriptions_repaired > 0 {
+            eprintln!("[STATS] Subscriptions: {} requests rejected, {} missing streams resubscribed",
+                      snap.subscribe_errors, snap.subscriptions_repaired);
+        }
+        for (code, count) in &snap.close_codes {
+            eprintln!("[STATS] Close code {}: {}", code, count);
+        }
+        if snap.filtered_messages > 0 {
+            let received = total + snap.filtered_messages;
+            eprintln!("[STATS] Filtered (not subscribed): {} ({:.1}% of received)",
+                      snap.filtered_messages, snap.filtered_messages as f64 * 100.0 / received as f64);
+        }
+        eprintln!("[STATS] Parse errors: {}, schema violations: {}", snap.parse_errors, snap.schema_violations);
+        if snap.hook_dropped > 0 {
+            eprintln!("[STATS] Dropped by hooks: {}", snap.hook_dropped);
+        }
+        for (size, count) in &snap.batch_sizes {
+            eprintln!("[STATS] Read batches of {}+ frames: {}", size, count);
+        }
+        if snap.pipeline_full > 0 {
+            eprintln!("[STATS] Decode pipeline full: {}", snap.pipeline_full);
+        }
+        if snap.bytes_received > 0 {
+            eprintln!("[STATS] Received {:.1} MB of text frames", snap.bytes_received as f64 / (1024.0 * 1024.0));
+        }
+        if snap.frames_received > 0 {
+            eprintln!("[STATS] Funnel: received {} -> parsed {} -> written {}",
+                      snap.frames_received, snap.frames_parsed, total);
+            eprintln!("[STATS] Funnel drops: parse {}, schema {}, hooks {}, not subscribed {}, duplicates {}, \
+                       bad price {}, deduped {}, past deadline {}, unaccounted {}",
+                      snap.parse_errors, snap.schema_violations, snap.hook_dropped, snap.filtered_messages,
+                      snap.arbitrated, snap.price_errors, snap.deduped, snap.deadline_skipped, snap.unaccounted());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_symbols() {
+        let symbols: Vec<String> = (0..1000).map(|i| format!("SYM{}", i)).collect();
+        let chunks = chunk_symbols(&symbols);
+
+        // 1000 symbols should make 10 chunks (100 * 10)
+        assert_eq!(chunks.len(), 10);
+        assert_eq!(chunks[0].len(), 100);
+        assert_eq!(chunks[9].len(), 100);
+    }
+
+    #[test]
+    fn test_chunk_symbols_stable() {
+        let symbols: Vec<String> = (0..1000).map(|i| format!("SYM{}", i)).collect();
+        let chunks = chunk_symbols_stable(&symbols);
+        let chunk_of = |chunks: &[Vec<String>], symbol: &str| chunks.iter().position(|c| c.iter().any(|s| s == symbol));
+
+        assert_eq!(chunks.len(), 13);
+        assert!(chunks.iter().all(|c| c.len() <= CHUNK_SIZE));
+        assert_eq!(chunks.iter().map(Vec::len).sum::<usize>(), 1000);
+
+        // File order doesn't matter
+        let mut reversed = symbols.clone();
+        reversed.reverse();
+        assert_eq!(chunk_symbols_stable(&reversed), chunks);
+
+        // Removing a symbol keeps everyone else where they were
+        let fewer: Vec<String> = symbols.iter().filter(|s| *s != "SYM500").cloned().collect();
+        let after = chunk_symbols_stable(&fewer);
+        assert!(fewer.iter().all(|s| chunk_of(&after, s) == chunk_of(&chunks, s)));
+
+        // One more connection moves only a fraction of the symbols
+        let more: Vec<String> = (0..1100).map(|i| format!("SYM{}", i)).collect();
+        let grown = chunk_symbols_stable(&more);
+        assert_eq!(grown.len(), 14);
+        let moved = symbols.iter().filter(|s| chunk_of(&grown, s) != chunk_of(&chunks, s)).count();
+        assert!(moved < 250, "{} moved", moved);
+    }
+
+    #[test]
+    fn test_create_ws_url() {
+        let symbols = vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()];
+        let url = create_ws_url(WS_BASE, &symbols);
+
+        assert!(url.contains("wss://fstream.binance.com/stream?streams="));
+        assert!(url.contains("btcusdt@bookTicker"));
+        assert!(url.contains("ethusdt@bookTicker"));
+    }
+
+    #[test]
+    fn test_perf_stats_buckets() {
+        let stats = PerfStats::with_thresholds(vec![20_000, 1_000, 5_000], 5_000);
+        for us in [500, 1_500, 6_000, 25_000] {
+            stats.record(us);
+        }
+        stats.record_parse(30);
+        stats.record_parse(10);
+
+        let snap = stats.snapshot();
+        assert_eq!(snap.total_messages, 4);
+        assert_eq!((snap.max_proc_us, snap.proc_us_total), (25_000, 33_000));
+        assert_eq!((snap.parse_timed, snap.max_parse_us, snap.parse_us_total), (2, 30, 40));
+        assert_eq!(snap.slow_buckets, vec![(1_000, 3), (5_000, 2), (20_000, 1)]);
+
+        assert_eq!(stats.take_interval_slow(), (2, 25_000));
+        assert_eq!(stats.take_interval_slow(), (0, 0));
+    }
+
+    #[test]
+    fn test_symbol_latency() {
+        // Not tracked unless asked for
+        let off = PerfStats::new();
+        off.record_symbol(0, 100);
+        assert!(off.symbol_latency().is_empty());
+
+        let stats = PerfStats::with_thresholds(vec![1_000], 5_000).with_symbol_latency(4);
+        for (id, us) in [(1, 200), (1, 7_000), (1, 6_000), (3, 9_000), (3, 100), (2, 50)] {
+            stats.record_symbol(id, us);
+        }
+        // Past the array: ignored
+        stats.record_symbol(4, 50_000);
+
+        let snapshot = |symbol_id, messages, proc_us_total, max_proc_us, slow| {
+            SymbolLatencySnapshot { symbol_id, messages, proc_us_total, max_proc_us, slow }
+        };
+        assert_eq!(stats.symbol_latency(), vec![
+            snapshot(3, 2, 9_100, 9_000, 1),
+            snapshot(1, 3, 13_200, 7_000, 2),
+            snapshot(2, 1, 50, 50, 0),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_race_connect() {
+        let addrs: Vec<SocketAddr> = (1..=3).map(|i| SocketAddr::from(([10, 0, 0, i], 443))).collect();
+        let started = std::sync::Mutex::new(Vec::new());
+        // .1 blackholed, .2 accepts after 20 ms, .3 refuses
+        let connect = |addr: SocketAddr| {
+            started.lock().unwrap().push(addr);
+            async move {
+                match addr.ip().to_string().as_str() {
+                    "10.0.0.1" => std::future::pending().await,
+                    "10.0.0.2" => {
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        Ok(addr.port())
+                    }
+                    _ => Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused)),
+                }
+            }
+        };
+
+        // The second address starts 50 ms in and wins; the third never starts
+        let t0 = Instant::now();
+        let (_, addr) = race_connect(&addrs, Duration::from_millis(50), connect).await.unwrap();
+        assert_eq!(addr, addrs[1]);
+        assert!(t0.elapsed() >= Duration::from_millis(70));
+        assert_eq!(started.lock().unwrap().len(), 2);
+
+        // A refused attempt starts the next one without waiting
+        started.lock().unwrap().clear();
+        let order = [addrs[2], addrs[1]];
+        let t0 = Instant::now();
+        let (_, addr) = race_connect(&order, Duration::from_secs(10), connect).await.unwrap();
+        assert_eq!(addr, addrs[1]);
+        assert!(t0.elapsed() < Duration::from_secs(5));
+
+        let err = race_connect(&addrs[2..], Duration::ZERO, connect).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+        assert!(race_connect(&[], Duration::ZERO, connect).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_handshake_timeout() {
+        // Listener that accepts TCP but never answers the TLS handshake
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let _hold = tokio::spawn(async move {
+            let (_sock, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        });
+
+        let config = WsConfig {
+            base_url: format!("wss://{}", addr),
+            handshake_timeout_ms: 100,
+            ..Default::default()
+        };
+        let stats = Arc::new(PerfStats::new());
+        let handler: MessageHandler = Arc::new(|_| {});
+        let conn = WsConnection::new(vec!["BTCUSDT".to_string()], handler, config, stats.clone());
+
+        let err = conn.run().await.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+        assert_eq!(stats.snapshot().handshake_timeouts, 1);
+        assert_eq!(stats.snapshot().connect_timeouts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_forces_reconnect() {
+        // Plain ws:// server that completes the handshake and then stays silent
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let _server = tokio::spawn(async move {
+            let (sock, _) = listener.accept().await.unwrap();
+            let _ws = tokio_tungstenite::accept_async(sock).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        });
+
+        let config = WsConfig {
+            base_url: format!("ws://{}", addr),
+            idle_timeout_ms: 100,
+            ..Default::default()
+        };
+        let stats = Arc::new(PerfStats::new());
+        let handler: MessageHandler = Arc::new(|_| {});
+        let conn = WsConnection::new(vec!["BTCUSDT".to_string()], handler, config, stats.clone());
+
+        let err = conn.run().await.unwrap_err();
+        assert!(err.to_string().contains("No data received"), "{}", err);
+        assert_eq!(stats.snapshot().idle_timeouts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_restart_one_connection() {
+        // Silent ws:// server reporting each accepted session
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (accepted, mut sessions) = tokio::sync::mpsc::unbounded_channel();
+        let _server = tokio::spawn(async move {
+            loop {
+                let (sock, _) = listener.accept().await.unwrap();
+                let accepted = accepted.clone();
+                tokio::spawn(async move {
+                    let ws = tokio_tungstenite::accept_async(sock).await.unwrap();
+                    accepted.send(()).unwrap();
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    drop(ws);
+                });
+            }
+        });
+
+        let config = WsConfig { base_url: format!("ws://{}", addr), ..Default::default() };
+        let stats = Arc::new(PerfStats::new());
+        let handler: MessageHandler = Arc::new(|_| {});
+        let manager = Arc::new(WsManager::new(vec!["BTCUSDT".to_string()], handler, config, stats.clone()).with_label("T"));
+        let handles = manager.restart_handles();
+        assert_eq!(handles.len(), 1);
+        assert_eq!((handles[0].tag.as_str(), handles[0].symbols()), ("WS-T0", vec!["BTCUSDT".to_string()]));
+        let runner = manager.clone();
+        let _run = tokio::spawn(async move { runner.run_all().await });
+
+        tokio::time::timeout(Duration::from_secs(5), sessions.recv()).await.unwrap().unwrap();
+        handles[0].restart();
+        // Reconnects right away instead of waiting out a backoff
+        tokio::time::timeout(Duration::from_secs(5), sessions.recv()).await.unwrap().unwrap();
+        assert_eq!(stats.snapshot().reconnects, 1);
+    }
+
+    #[tokio::test]
+    async fn test_handler_panic_restarts_connection() {
+        // ws:// server sending one quote per session
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (accepted, mut sessions) = tokio::sync::mpsc::unbounded_channel();
+        let _server = tokio::spawn(async move {
+            loop {
+                let (sock, _) = listener.accept().await.unwrap();
+                let accepted = accepted.clone();
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(sock).await.unwrap();
+                    accepted.send(()).unwrap();
+                    let frame = r#"{"stream":"btcusdt@bookTicker","data":{"u":1,"s":"BTCUSDT","b":"1.0","B":"1","a":"1.1","A":"1","T":1,"E":1}}"#;
+                    ws.send(Message::Text(frame.to_string())).await.unwrap();
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                });
+            }
+        });
+
+        let config = WsConfig { base_url: format!("ws://{}", addr), ..Default::default() };
+        let stats = Arc::new(PerfStats::new());
+        let panicked = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let flag = panicked.clone();
+        let handler: MessageHandler = Arc::new(move |_| {
+            if !flag.swap(true, Ordering::Relaxed) {
+                panic!("handler bug");
+            }
+        });
+        let tracer = Arc::new(Tracer::new("test", 64));
+        let manager = Arc::new(WsManager::new(vec!["BTCUSDT".to_string()], handler, config, stats.clone())
+            .with_tracer(tracer.clone()));
+        let runner = manager.clone();
+        let _run = tokio::spawn(async move { runner.run_all().await });
+
+        // The panic ends the session, not the task: a new one starts
+        tokio::time::timeout(Duration::from_secs(5), sessions.recv()).await.unwrap().unwrap();
+        tokio::time::timeout(Duration::from_secs(5), sessions.recv()).await.unwrap().unwrap();
+        assert!(panicked.load(Ordering::Relaxed));
+        // Each attempt traced with its handshake and subscribe phases, ended once streaming
+        let mut spans = Vec::new();
+        for _ in 0..500 {
+            spans.extend(tracer.take_finished());
+            if spans.len() >= 6 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let encoded = tracer.encode(&spans);
+        let spans = encoded["resourceSpans"][0]["scopeSpans"][0]["spans"].as_array().unwrap();
+        let names: Vec<&str> = spans.iter().map(|span| span["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["ws.handshake", "ws.subscribe", "ws.connect", "ws.handshake", "ws.subscribe", "ws.reconnect"]);
+        assert_eq!(spans[3]["parentSpanId"], spans[5]["spanId"]);
+        assert!(spans[5]["attributes"].as_array().unwrap()
+            .contains(&serde_json::json!({"key": "reconnect.reason", "value": {"stringValue": "panicked"}})));
+        assert_eq!((stats.snapshot().panics, stats.snapshot().reconnects), (1, 1));
+        // Streamed, drained, backed off and connected again
+        let (_, times) = manager.conn_stats()[0].2.state_times(crate::platform::monotonic_us());
+        let entries: Vec<u64> = times.iter().map(|&(entries, _)| entries).collect();
+        assert_eq!((entries[0], entries[2] >= 1, entries[3], entries[4]), (2, true, 1, 2));
+    }
+
+    #[cfg(feature = "chaos")]
+    #[tokio::test]
+    async fn test_chaos_drops_and_corrupts() {
+        // ws:// server streaming quotes until the client goes away
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (accepted, mut sessions) = tokio::sync::mpsc::unbounded_channel();
+        let _server = tokio::spawn(async move {
+            loop {
+                let (sock, _) = listener.accept().await.unwrap();
+                let accepted = accepted.clone();
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(sock).await.unwrap();
+                    accepted.send(()).unwrap();
+                    let frame = r#"{"stream":"btcusdt@bookTicker","data":{"u":1,"s":"BTCUSDT","b":"1.0","B":"1","a":"1.1","A":"1","T":1,"E":1}}"#;
+                    while ws.send(Message::Text(frame.to_string())).await.is_ok() {
+                        tokio::time::sleep(Duration::from_millis(1)).await;
+                    }
+                });
+            }
+        });
+
+        let chaos = crate::chaos::Chaos::new(crate::config::ChaosConfig {
+            close_mean_ms: 100,
+            reconnect_delay_ms: 20,
+            corrupt_pct: 50.0,
+            seed: 9,
+        });
+        let config = WsConfig { base_url: format!("ws://{}", addr), chaos: Some(Arc::new(chaos)), ..Default::default() };
+        let stats = Arc::new(PerfStats::new());
+        let handled = Arc::new(AtomicU64::new(0));
+        let count = handled.clone();
+        let handler: MessageHandler = Arc::new(move |_| {
+            count.fetch_add(1, Ordering::Relaxed);
+        });
+        let manager = Arc::new(WsManager::new(vec!["BTCUSDT".to_string()], handler, config, stats.clone()));
+        let _run = tokio::spawn(async move { manager.run_all().await });
+
+        // Dropped sessions are reconnected; corrupted frames are counted, not fatal
+        for _ in 0..3 {
+            tokio::time::timeout(Duration::from_secs(5), sessions.recv()).await.unwrap().unwrap();
+        }
+        let snap = stats.snapshot();
+        assert!(snap.chaos_closes >= 2 && snap.reconnects >= 2, "{:?}", (snap.chaos_closes, snap.reconnects));
+        assert!(snap.chaos_corrupted > 0 && snap.parse_errors > 0, "{:?}", (snap.chaos_corrupted, snap.parse_errors));
+        assert!(handled.load(Ordering::Relaxed) > 0);
+    }
+
+    #[tokio::test]
+    async fn test_warmup_needs_subscribed_symbols() {
+        // ws:// server that only ever sends BTCUSDT, reporting what the client sends
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (requests, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let _server = tokio::spawn(async move {
+            loop {
+                let (sock, _) = listener.accept().await.unwrap();
+                let requests = requests.clone();
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(sock).await.unwrap();
+                    let frame = r#"{"stream":"btcusdt@bookTicker","data":{"u":1,"s":"BTCUSDT","b":"1.0","a":"1.1"}}"#;
+                    ws.send(Message::Text(frame.to_string())).await.unwrap();
+                    while let Some(Ok(message)) = ws.next().await {
+                        if let Message::Text(text) = message {
+                            let _ = requests.send(text);
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(frame_symbol(r#"{"stream":"x","data":{"e":"bookTicker","s":"ETHUSDT","b":"1"}}"#), Some("ETHUSDT"));
+
+        let handler: MessageHandler = Arc::new(|_| {});
+        let connect = |symbols: &[&str], warmup_min_pct: u64| {
+            let config = WsConfig {
+                base_url: format!("ws://{}", addr),
+                idle_timeout_ms: 300,
+                warmup_timeout_ms: 100,
+                warmup_min_pct,
+                subscription_check_ms: 0,
+                ..Default::default()
+            };
+            let stats = Arc::new(PerfStats::new());
+            let symbols = symbols.iter().map(|s| s.to_string()).collect();
+            (WsConnection::new(symbols, handler.clone(), config, stats.clone()), stats)
+        };
+
+        // Connected, but ETHUSDT never shows up: asked for again on the live session
+        let (conn, stats) = connect(&["BTCUSDT", "ETHUSDT"], 100);
+        let err = conn.run().await.unwrap_err();
+        assert!(matches!(reconnect::classify(&err), DisconnectKind::Idle), "{}", err);
+        let request: serde_json::Value = serde_json::from_str(&received.try_recv().unwrap()).unwrap();
+        assert_eq!((request["method"].as_str(), request["params"][0].as_str()), (Some("SUBSCRIBE"), Some("ethusdt@bookTicker")));
+        assert_eq!(stats.snapshot().warmup_failures, 1);
+
+        // Half the symbols is enough: nothing to resubscribe
+        let (conn, stats) = connect(&["BTCUSDT", "ETHUSDT"], 50);
+        let err = conn.run().await.unwrap_err();
+        assert!(matches!(reconnect::classify(&err), DisconnectKind::Idle), "{}", err);
+        assert_eq!(stats.snapshot().warmup_failures, 0);
+        assert!(received.try_recv().is_err());
+
+        // None of the symbols delivered: the session is reconnected
+        let (conn, stats) = connect(&["ETHUSDT"], 100);
+        let err = conn.run().await.unwrap_err();
+        assert!(matches!(reconnect::classify(&err), DisconnectKind::WarmUp), "{}", err);
+        assert!(err.to_string().contains("ETHUSDT"), "{}", err);
+        assert_eq!(stats.snapshot().warmup_failures, 1);
+    }
+
+    fn supervisor(give_up: bool) -> Supervisor {
+        let stats = Arc::new(PerfStats::new());
+        let conn = Arc::new(WsConnection::new(vec!["BTCUSDT".to_string()], Arc::new(|_| {}), WsConfig::default(), stats.clone()));
+        Supervisor {
+            conn, i: 0, tag: "WS-0".to_string(), stats, bucket: None, give_up,
+            backoff: BackoffCalculator::new(), consecutive_errors: 0, previous: None,
+        }
+    }
+
+    #[test]
+    fn test_warmup_misses_never_fatal() {
+        let mut supervisor = supervisor(true);
+        let mut fatal = |kind: DisconnectKind| {
+            let plan = reconnect::plan_reconnect(&kind, &mut supervisor.backoff);
+            supervisor.count_error(&kind, &plan)
+        };
+        for _ in 0..100 {
+            assert!(!fatal(DisconnectKind::WarmUp));
+        }
+        // Between connect failures, warm-up misses neither count nor reset the run
+        for i in 0..10 {
+            assert!(!fatal(DisconnectKind::Connect), "{}", i);
+            assert!(!fatal(DisconnectKind::WarmUp));
+        }
+        assert!(fatal(DisconnectKind::Connect));
+    }
+
+    #[tokio::test]
+    async fn test_first_error_stops_the_rest() {
+        let (_keep, never) = tokio::sync::oneshot::channel::<()>();
+        let waiting = tokio::spawn(async move {
+            let _ = never.await;
+            Ok(())
+        });
+        let gave_up = tokio::spawn(async { Err(reconnect::TooManyErrors { tag: "WS-1".to_string() }.into()) });
+        let abort = waiting.abort_handle();
+        let err = first_error(vec![waiting, gave_up]).await.unwrap_err();
+        assert!(err.downcast_ref::<reconnect::TooManyErrors>().is_some(), "{}", err);
+        tokio::task::yield_now().await;
+        assert!(abort.is_finished());
+        assert!(first_error(Vec::new()).await.is_ok());
+    }
+
+    #[test]
+    fn test_retry_forever_never_fatal() {
+        let mut supervisor = supervisor(false);
+        for _ in 0..100 {
+            let plan = reconnect::plan_reconnect(&DisconnectKind::Connect, &mut supervisor.backoff);
+            assert!(!supervisor.count_error(&DisconnectKind::Connect, &plan));
+        }
+        assert_eq!(supervisor.consecutive_errors, 100);
+    }
+
+    #[tokio::test]
+    async fn test_keepalive() {
+        assert_eq!("ping:15000".parse::<Keepalive>().unwrap(), Keepalive::Ping(Duration::from_secs(15)));
+        assert_eq!(" PONG:500 ".parse::<Keepalive>().unwrap(), Keepalive::Pong(Duration::from_millis(500)));
+        assert_eq!("none".parse::<Keepalive>().unwrap(), Keepalive::None);
+        assert_eq!(Keepalive::default().default_idle_timeout_ms(), 60_000);
+        assert!(Keepalive::None.default_idle_timeout_ms() > SERVER_PING_INTERVAL.as_millis() as u64);
+        for bad in ["ping", "ping:0", "pong:x", "heartbeat:10"] {
+            assert!(bad.parse::<Keepalive>().is_err(), "{}", bad);
+        }
+
+        // Server pings once, then tallies the client's (pings, pongs) for 200 ms and closes
+        let session = |keepalive: Keepalive| async move {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let server = tokio::spawn(async move {
+                let (sock, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(sock).await.unwrap();
+                ws.send(Message::Ping(b"hb".to_vec())).await.unwrap();
+                let (mut pings, mut pongs, mut answered) = (0, 0, false);
+                let deadline = Instant::now() + Duration::from_millis(200);
+                while let Ok(Some(Ok(msg))) = tokio::time::timeout_at(deadline, ws.next()).await {
+                    match msg {
+                        Message::Ping(_) => pings += 1,
+                        Message::Pong(data) if data == b"hb" => answered = true,
+                        Message::Pong(_) => pongs += 1,
+                        _ => {}
+                    }
+                }
+                ws.close(None).await.unwrap();
+                (pings, pongs, answered)
+            });
+            let config = WsConfig { base_url: format!("ws://{}", addr), keepalive, ..Default::default() };
+            let stats = Arc::new(PerfStats::new());
+            let conn = WsConnection::new(vec!["BTCUSDT".to_string()], Arc::new(|_| {}), config, stats.clone());
+            conn.run().await.unwrap();
+            let (pings, pongs, answered) = server.await.unwrap();
+            assert!(answered, "server ping not answered");
+            assert_eq!(stats.snapshot().server_pings, 1);
+            (pings, pongs, stats.snapshot().pongs)
+        };
+
+        let (pings, pongs, pongs_received) = session(Keepalive::Ping(Duration::from_millis(20))).await;
+        assert!(pings >= 3 && pongs == 0, "{} {}", pings, pongs);
+        assert!(pongs_received >= 1);
+        let (pings, pongs, pongs_received) = session(Keepalive::Pong(Duration::from_millis(20))).await;
+        assert!(pings == 0 && pongs >= 3, "{} {}", pings, pongs);
+        assert_eq!(pongs_received, 0);
+        assert_eq!(session(Keepalive::None).await, (0, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_close_frame_code_recorded() {
+        use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+        use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let _server = tokio::spawn(async move {
+            let (sock, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(sock).await.unwrap();
+            let frame = CloseFrame { code: CloseCode::Again, reason: "maintenance".into() };
+            ws.close(Some(frame)).await.unwrap();
+        });
+
+        let config = WsConfig { base_url: format!("ws://{}", addr), ..Default::default() };
+        let stats = Arc::new(PerfStats::new());
+        let handler: MessageHandler = Arc::new(|_| {});
+        let conn = WsConnection::new(vec!["BTCUSDT".to_string()], handler, config, stats.clone());
+
+        let close = conn.run().await.unwrap();
+        assert_eq!(close, CloseInfo { code: 1013, reason: "maintenance".to_string() });
+        assert_eq!(stats.snapshot().close_codes, vec![(1013, 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_headers_and_query() {
+        use tokio::io::{AsyncBufReadExt, BufReader};
+
+        // Capture the upgrade request head, then hang up
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (sock, _) = listener.accept().await.unwrap();
+            let mut lines = BufReader::new(sock).lines();
+            let mut head = Vec::new();
+            while let Some(line) = lines.next_line().await.unwrap().filter(|line| !line.is_empty()) {
+                head.push(line);
+            }
+            head
+        });
+
+        let config = WsConfig {
+            base_url: format!("ws://{}", addr),
+            headers: vec![("User-Agent".to_string(), "siro/1.0".to_string()), ("X-MBX-APIKEY".to_string(), "k".to_string())],
+            query: vec![("timeUnit".to_string(), "MICROSECOND".to_string())],
+            ..Default::default()
+        };
+        let handler: MessageHandler = Arc::new(|_| {});
+        let conn = WsConnection::new(vec!["BTCUSDT".to_string()], handler, config, Arc::new(PerfStats::new()));
+        assert!(conn.run().await.is_err());
+
+        let head = server.await.unwrap();
+        assert_eq!(head[0], "GET /stream?streams=btcusdt@bookTicker&timeUnit=MICROSECOND HTTP/1.1");
+        let header = |name: &str| head.iter().find_map(|line| {
+            let (n, v) = line.split_once(": ")?;
+            n.eq_ignore_ascii_case(name).then(|| v.to_string())
+        });
+        assert_eq!((header("user-agent").as_deref(), header("x-mbx-apikey").as_deref()), (Some("siro/1.0"), Some("k")));
+        assert_eq!(with_query("wss://x/ws".to_string(), &[("a".to_string(), String::new())]), "wss://x/ws?a");
+    }
+
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    #[test]
+    fn test_uring_session_close_code() {
+        use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+        use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+
+        // Server on its own runtime; the client session runs on io_uring
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let server = std::thread::spawn(move || {
+            let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+            rt.block_on(async move {
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                let (sock, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(sock).await.unwrap();
+                let frame = CloseFrame { code: CloseCode::Away, reason: "bye".into() };
+                ws.close(Some(frame)).await.unwrap();
+            });
+        });
+
+        let config = WsConfig { base_url: format!("ws://{}", addr), io_uring: true, ..Default::default() };
+        let stats = Arc::new(PerfStats::new());
+        let handler: MessageHandler = Arc::new(|_| {});
+        let conn = WsConnection::new(vec!["BTCUSDT".to_string()], handler, config, stats.clone());
+
+        let close = crate::uring::start(conn.run_uring()).unwrap();
+        assert_eq!(close, CloseInfo { code: 1001, reason: "bye".to_string() });
+        assert_eq!(stats.snapshot().close_codes, vec![(1001, 1)]);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_warm_spare_failover() {
+        let seen: Arc<std::sync::Mutex<Vec<String>>> = Arc::default();
+        let sink = seen.clone();
+        let handler: MessageHandler = Arc::new(move |data: BookTickerData| sink.lock().unwrap().push(data.symbol));
+        let symbols = vec!["BTCUSDT".to_string()];
+        let config = WsConfig { warm_spares: true, ..Default::default() };
+        let stats = Arc::new(PerfStats::new());
+
+        let manager = WsManager::new(symbols.clone(), handler, config.clone(), stats).with_label("P");
+        assert_eq!(manager.connections.len(), WsManager::connection_count(&symbols, &config));
+        let tags: Vec<String> = manager.restart_handles().into_iter().map(|h| h.tag).collect();
+        assert_eq!(tags, vec!["WS-P0", "WS-P0s"]);
+
+        let (primary, spare) = (&manager.connections[0], &manager.connections[1]);
+        let frame = |symbol: &str| Message::Text(format!(r#"{{"stream":"x","data":{{"s":"{}","b":"1","a":"2"}}}}"#, symbol));
+        let failover = &primary.failover.as_ref().unwrap().0;
+        // The spare comes up first while the primary is still connecting
+        failover.mark_up(SPARE);
+        failover.mark_up(PRIMARY);
+        assert!(failover.is_active(SPARE));
+        assert!(!failover.mark_down(PRIMARY));
+        failover.mark_up(PRIMARY);
+        assert!(failover.mark_down(SPARE));
+
+        // Spare reconnected: standby while the primary is active
+        failover.mark_up(SPARE);
+        primary.handle_message(Ok(frame("FROMPRIMARY")));
+        spare.handle_message(Ok(frame("FROMSPARE")));
+        assert!(failover.mark_down(PRIMARY));
+        spare.handle_message(Ok(frame("PROMOTED")));
+        primary.handle_message(Ok(frame("DOWN")));
+        assert_eq!(*seen.lock().unwrap(), vec!["FROMPRIMARY", "PROMOTED"]);
+    }
+
+    #[test]
+    fn test_ab_arbitration() {
+        let seen: Arc<std::sync::Mutex<Vec<(String, u64)>>> = Arc::default();
+        let sink = seen.clone();
+        let handler: MessageHandler = Arc::new(move |data: BookTickerData| sink.lock().unwrap().push((data.symbol, data.update_id)));
+        let symbols = vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()];
+        let config = WsConfig {
+            fallback_urls: vec!["wss://fstream-b.example".to_string()],
+            arbitrate: true,
+            ..Default::default()
+        };
+        let stats = Arc::new(PerfStats::new());
+
+        let manager = WsManager::new(symbols.clone(), handler, config.clone(), stats.clone());
+        assert_eq!(manager.connections.len(), WsManager::connection_count(&symbols, &config));
+        let tags: Vec<String> = manager.restart_handles().into_iter().map(|h| h.tag).collect();
+        assert_eq!(tags, vec!["WS-0", "WS-0b"]);
+        let (a, b) = (&manager.connections[0], &manager.connections[1]);
+        assert_eq!((a.endpoint(), b.endpoint()), (WS_BASE.to_string(), "wss://fstream-b.example".to_string()));
+
+        let frame = |symbol: &str, u: u64| Message::Text(format!(r#"{{"stream":"x","data":{{"u":{},"s":"{}","b":"1","a":"2"}}}}"#, u, symbol));
+        a.handle_message(Ok(frame("BTCUSDT", 5)));
+        b.handle_message(Ok(frame("BTCUSDT", 5)));
+        b.handle_message(Ok(frame("BTCUSDT", 6)));
+        a.handle_message(Ok(frame("BTCUSDT", 4)));
+        a.handle_message(Ok(frame("BTCUSDT", 6)));
+        // Ids are per symbol
+        a.handle_message(Ok(frame("ETHUSDT", 3)));
+        b.handle_message(Ok(Message::Text(r#"{"stream":"x","data":{"s":"ETHUSDT","b":"1","a":"2"}}"#.to_string())));
+        let expected: Vec<(String, u64)> = [("BTCUSDT", 5), ("BTCUSDT", 6), ("ETHUSDT", 3), ("ETHUSDT", 0)]
+            .map(|(s, u)| (s.to_string(), u))
+            .into();
+        assert_eq!(*seen.lock().unwrap(), expected);
+        assert_eq!(stats.snapshot().arbitrated, 3);
+    }
+
+    #[test]
+    fn test_resubscribe_plan() {
+        let handler: MessageHandler = Arc::new(|_| {});
+        let symbols: Vec<String> = (0..150).map(|i| format!("S{}USDT", i)).collect();
+        let config = WsConfig { warm_spares: true, ..Default::default() };
+        let manager = WsManager::new(symbols, handler.clone(), config, Arc::new(PerfStats::new()));
+        assert_eq!(manager.connections.len(), 4);
+
+        // Additions go to the emptier chunk (and its spare), removals where carried
+        let diff = SubscribeDiff { added: vec!["NEWUSDT".to_string()], removed: vec!["S1USDT".to_string(), "S120USDT".to_string()] };
+        assert_eq!(manager.resubscribe(&diff).unwrap(), vec!["WS-0", "WS-0s", "WS-1", "WS-1s"]);
+        let (first, second) = (manager.connections[1].symbols(), manager.connections[3].symbols());
+        assert!(!first.contains(&"S1USDT".to_string()) && !second.contains(&"S120USDT".to_string()));
+        assert_eq!((first.len(), second.len()), (99, 50));
+        assert!(second.contains(&"NEWUSDT".to_string()));
+        let pending = manager.connections[2].subscription.lock().unwrap().pending.clone();
+        assert_eq!(pending.len(), 2);
+        assert!(pending[0].contains(r#""method":"SUBSCRIBE","params":["newusdt@bookTicker"]"#), "{}", pending[0]);
+        assert!(pending[1].contains(r#""method":"UNSUBSCRIBE","params":["s120usdt@bookTicker"]"#), "{}", pending[1]);
+
+        // A connecting session asks for the new list instead
+        let target = manager.connections[2].target().unwrap();
+        assert!(target.url.contains("newusdt@bookTicker") && !target.url.contains("s120usdt"));
+        assert!(manager.connections[2].subscription.lock().unwrap().pending.is_empty());
+
+        // All or nothing when additions don't fit
+        let added = (0..52).map(|i| format!("N{}USDT", i)).collect();
+        let err = manager.resubscribe(&SubscribeDiff { added, removed: Vec::new() }).unwrap_err();
+        assert!(err.to_string().contains("no room for N51USDT"), "{}", err);
+        assert_eq!(manager.connections[3].symbols().len(), 50);
+
+        let all_market = WsManager::new(vec!["BTCUSDT".to_string()], handler, WsConfig { all_market: true, ..Default::default() }, Arc::new(PerfStats::new()));
+        assert!(all_market.resubscribe(&diff).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_live_resubscribe() {
+        // ws:// server reporting the session and every text frame the client sends
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (received, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        let _server = tokio::spawn(async move {
+            let (sock, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(sock).await.unwrap();
+            received.send(String::new()).unwrap();
+            while let Some(Ok(msg)) = ws.next().await {
+                if let Message::Text(text) = msg {
+                    received.send(text).unwrap();
+                }
+            }
+        });
+
+        let config = WsConfig { base_url: format!("ws://{}", addr), ..Default::default() };
+        let handler: MessageHandler = Arc::new(|_| {});
+        let manager = Arc::new(WsManager::new(vec!["BTCUSDT".to_string()], handler, config, Arc::new(PerfStats::new())));
+        let runner = manager.clone();
+        let _run = tokio::spawn(async move { runner.run_all().await });
+        let wait = Duration::from_secs(5);
+        assert_eq!(timeout(wait, requests.recv()).await.unwrap().unwrap(), "");
+
+        let diff = SubscribeDiff { added: vec!["ETHUSDT".to_string()], removed: vec!["BTCUSDT".to_string()] };
+        manager.resubscribe(&diff).unwrap();
+        let subscribe: serde_json::Value = serde_json::from_str(&timeout(wait, requests.recv()).await.unwrap().unwrap()).unwrap();
+        assert_eq!((subscribe["method"].as_str(), subscribe["params"][0].as_str()), (Some("SUBSCRIBE"), Some("ethusdt@bookTicker")));
+        let unsubscribe: serde_json::Value = serde_json::from_str(&timeout(wait, requests.recv()).await.unwrap().unwrap()).unwrap();
+        assert_eq!((unsubscribe["method"].as_str(), unsubscribe["params"][0].as_str()), (Some("UNSUBSCRIBE"), Some("btcusdt@bookTicker")));
+        assert_ne!(subscribe["id"], unsubscribe["id"]);
+        assert_eq!(manager.restart_handles()[0].symbols(), vec!["ETHUSDT".to_string()]);
+    }
+
+    #[test]
+    fn test_subscription_check_repairs_missing_streams() {
+        let symbols = vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()];
+        let stats = Arc::new(PerfStats::new());
+        let handler: MessageHandler = Arc::new(|_| {});
+        let mut conn = WsConnection::new(symbols, handler, WsConfig::default(), stats.clone());
+        let tracer = Arc::new(Tracer::new("test", 64));
+        conn.tracer = tracer.clone();
+        let response = |id: &serde_json::Value, body: &str| format!(r#"{{{},"id":{}}}"#, body, id);
+
+        // Feed frames and stray acks (an earlier session's ids)
+        assert!(!conn.handle_control(r#"{"stream":"btcusdt@bookTicker","data":{"s":"BTCUSDT"}}"#));
+        assert!(!conn.handle_control("not json"));
+        assert!(conn.handle_control(r#"{"result":null,"id":999999}"#));
+
+        // The server lost ethusdt: it is subscribed again
+        let list: serde_json::Value = serde_json::from_str(&conn.list_request()).unwrap();
+        assert_eq!(list["method"], "LIST_SUBSCRIPTIONS");
+        assert!(conn.handle_control(&response(&list["id"], r#""result":["btcusdt@bookTicker"]"#)));
+        let pending = conn.subscription.lock().unwrap().pending.clone();
+        assert_eq!(pending.len(), 1);
+        let subscribe: serde_json::Value = serde_json::from_str(&pending[0]).unwrap();
+        assert_eq!((subscribe["method"].as_str(), subscribe["params"][0].as_str()), (Some("SUBSCRIBE"), Some("ethusdt@bookTicker")));
+        assert_eq!(stats.snapshot().subscriptions_repaired, 1);
+
+        // Rejections are counted; a complete list changes nothing
+        assert!(conn.handle_control(&response(&subscribe["id"], r#""error":{"code":2,"msg":"Invalid request"}"#)));
+        assert_eq!(stats.snapshot().subscribe_errors, 1);
+        let list: serde_json::Value = serde_json::from_str(&conn.list_request()).unwrap();
+        assert!(conn.handle_control(&response(&list["id"], r#""result":["ethusdt@bookTicker","btcusdt@bookTicker"]"#)));
+        assert_eq!((conn.subscription.lock().unwrap().pending.len(), stats.snapshot().subscriptions_repaired), (1, 1));
+
+        // One ws.resubscribe span per request, ended by its response; a check
+        // superseded before its answer and requests of a lost session fail
+        let unanswered: serde_json::Value = serde_json::from_str(&conn.list_request()).unwrap();
+        conn.list_request();
+        conn.update_streams(&["SOLUSDT".to_string()], &[]);
+        conn.target().unwrap();
+        assert!(conn.handle_control(&response(&unanswered["id"], r#""result":[]"#)));
+        let spans = tracer.encode(&tracer.take_finished());
+        let spans = spans["resourceSpans"][0]["scopeSpans"][0]["spans"].as_array().unwrap();
+        let method = |span: &serde_json::Value| span["attributes"][0]["value"]["stringValue"].as_str().unwrap().to_string();
+        let summary: Vec<(String, Option<&str>)> = spans.iter().map(|span| (method(span), span["status"]["message"].as_str())).collect();
+        assert!(spans.iter().all(|span| span["name"] == "ws.resubscribe"));
+        assert_eq!(summary[..4], [
+            ("LIST_SUBSCRIPTIONS".to_string(), None),
+            ("SUBSCRIBE".to_string(), Some("Invalid request (code 2)")),
+            ("LIST_SUBSCRIPTIONS".to_string(), None),
+            ("LIST_SUBSCRIPTIONS".to_string(), Some("superseded by a newer check")),
+        ]);
+        let mut lost: Vec<_> = summary[4..].to_vec();
+        lost.sort();
+        assert_eq!(lost, [
+            ("LIST_SUBSCRIPTIONS".to_string(), Some("session ended")),
+            ("SUBSCRIBE".to_string(), Some("session ended")),
+        ]);
+    }
+
+    #[test]
+    fn test_drop_funnel() {
+        let stats = Arc::new(PerfStats::new());
+        let sink = stats.clone();
+        // Written unless the bid doesn't parse
+        let handler: MessageHandler = Arc::new(move |data: BookTickerData| match data.bid_price.as_str() {
+            "x" => sink.record_price_error(),
+            "silent" => {}
+            _ => sink.record(1),
+        });
+        let config = WsConfig { all_market: true, ..Default::default() };
+        let manager = WsManager::new(vec!["BTCUSDT".to_string()], handler, config, stats.clone());
+        let conn = &manager.connections[0];
+
+        let frame = |symbol: &str, bid: &str| {
+            Message::Text(format!(r#"{{"stream":"x","data":{{"s":"{}","b":"{}","a":"2"}}}}"#, symbol, bid))
+        };
+        conn.handle_message(Ok(frame("BTCUSDT", "1")));
+        conn.handle_message(Ok(frame("BTCUSDT", "x")));
+        conn.handle_message(Ok(frame("ETHUSDT", "1")));
+        conn.handle_message(Ok(Message::Text("not json".to_string())));
+        // Request acks aren't feed frames
+        conn.handle_message(Ok(Message::Text(r#"{"result":null,"id":1}"#.to_string())));
+        let snap = stats.snapshot();
+        assert_eq!((snap.frames_received, snap.frames_parsed, snap.total_messages), (4, 3, 1));
+        assert_eq!((snap.price_errors, snap.filtered_messages, snap.parse_errors), (1, 1, 1));
+        assert_eq!(snap.unaccounted(), 0);
+
+        // A path that drops without counting shows up
+        conn.handle_message(Ok(frame("BTCUSDT", "silent")));
+        assert_eq!(stats.snapshot().unaccounted(), 1);
+    }
+
+    #[test]
+    fn test_all_market_mode() {
+        let seen: Arc<std::sync::Mutex<Vec<String>>> = Arc::default();
+        let sink = seen.clone();
+        let handler: MessageHandler = Arc::new(move |data: BookTickerData| sink.lock().unwrap().push(data.symbol));
+        let symbols: Vec<String> = (0..250).map(|i| format!("SYM{}", i)).collect();
+        let config = WsConfig { all_market: true, ..Default::default() };
+        let stats = Arc::new(PerfStats::new());
+
+        let manager = WsManager::new(symbols.clone(), handler, config.clone(), stats.clone());
+        assert_eq!(manager.connections.len(), WsManager::connection_count(&symbols, &config));
+        assert_eq!(manager.connections[0].target().unwrap().url, format!("{}/stream?streams=!bookTicker", WS_BASE));
+
+        for symbol in ["SYM7", "NEWLISTING", "SYM249"] {
+            manager.handler()(BookTickerData { symbol: symbol.to_string(), bid_price: "1".into(), ask_price: "2".into(), update_id: 0, event_time_ms: 0, received_us: 0, stream: String::new() });
+        }
+        assert_eq!(*seen.lock().unwrap(), vec!["SYM7", "SYM249"]);
+        assert_eq!(stats.snapshot().filtered_messages, 1);
+    }
+
+    #[test]
+    fn test_strict_schema() {
+        let full = r#"{"stream":"btcusdt@bookTicker","data":{"e":"bookTicker","u":400900217,"s":"BTCUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000","T":1568014460891,"E":1568014460893}}"#;
+        let data = decode_frame(full, true).unwrap();
+        assert_eq!((data.symbol.as_str(), data.bid_price.as_str(), data.ask_price.as_str(), data.update_id),
+                   ("BTCUSDT", "25.35190000", "25.36520000", 400900217));
+        assert_eq!(decode_frame(full, false).unwrap().update_id, 400900217);
+        assert_eq!(decode_frame(full, true).unwrap().event_time_ms, 1568014460893);
+        assert_eq!(decode_frame(full, false).unwrap().event_time_ms, 1568014460893);
+        assert_eq!(decode_frame(full, true).unwrap().stream, stream_name("BTCUSDT"));
+        assert_eq!(decode_frame(full, false).unwrap().stream, "btcusdt@bookTicker");
+
+        let stats = PerfStats::new();
+        for (broken, field) in [
+            (full.replace(r#","T":1568014460891"#, ""), "missing T"),
+            (full.replace(r#""B":"31.21000000""#, r#""B":31.21"#), "B is not a string"),
+            (full.replace(r#""a":"25.36520000""#, r#""a":"2.5e1""#), "a \"2.5e1\""),
+            (full.replace(r#""u":400900217"#, r#""u":"400900217""#), "u is not"),
+        ] {
+            // Lenient mode only needs s, b and a
+            assert!(decode_frame(&broken, false).is_ok());
+            let e = decode_frame(&broken, true).unwrap_err();
+            assert!(e.to_string().contains(field), "{} vs {}", e, field);
+            stats.record_frame_error(&e);
+        }
+        stats.record_frame_error(&decode_frame("{not json", true).unwrap_err());
+
+        let snap = stats.snapshot();
+        assert_eq!((snap.parse_errors, snap.schema_violations), (1, 4));
+    }
+
+    #[test]
+    fn test_batch_size_histogram() {
+        let stats = PerfStats::new();
+        for frames in [0, 1, 1, 3, 4, 200, MAX_BATCH] {
+            stats.record_batch(frames);
+        }
+        assert_eq!(stats.snapshot().batch_sizes, vec![(1, 2), (2, 1), (4, 1), (128, 1), (256, 1)]);
+    }
+
+    #[test]
+    fn test_conn_stats_interval() {
+        let stats = ConnStats::default();
+        stats.record(10);
+        stats.record(30);
+        stats.record_bytes(180);
+        stats.record_bytes(220);
+        assert_eq!(stats.take_interval(), (2, 40, 30, 400));
+        assert_eq!(stats.take_interval(), (0, 0, 0, 0));
+        assert_eq!(stats.traffic(), (2, 400));
+    }
+
+    #[test]
+    fn test_conn_outages() {
+        let stats = ConnStats::default();
+        // Failed initial attempts are not an outage
+        stats.mark_down(100);
+        stats.mark_up(500);
+        assert_eq!(stats.downtime(600), (0, 0, 0));
+
+        stats.mark_down(1_000);
+        stats.mark_down(1_200);
+        stats.mark_up(1_300);
+        stats.mark_down(2_000);
+        assert_eq!(stats.downtime(3_000), (2, 1_300, 1_000));
+    }
+
+    #[test]
+    fn test_conn_states() {
+        use ConnState::*;
+        let stats = ConnStats::default();
+        let t = crate::platform::monotonic_us() + 1_000_000;
+        assert_eq!(stats.state(), Backoff);
+        assert_eq!(stats.enter(Connecting, t).unwrap().0, Backoff);
+        assert_eq!(stats.enter(Subscribing, t + 300), Ok((Connecting, 300)));
+        // Invalid transitions change nothing
+        assert_eq!(stats.enter(Backoff, t + 400), Err(Subscribing));
+        assert_eq!(stats.enter(Streaming, t + 500), Ok((Subscribing, 200)));
+        assert_eq!(stats.enter(Draining, t + 10_500), Ok((Streaming, 10_000)));
+        assert_eq!(stats.enter(Backoff, t + 10_600), Ok((Draining, 100)));
+        assert_eq!(stats.enter(Connecting, t + 12_600), Ok((Backoff, 2_000)));
+
+        let ((state, in_state_us), times) = stats.state_times(t + 13_000);
+        assert_eq!((state, in_state_us), (Connecting, 400));
+        assert_eq!(times[Connecting.index()], (2, 700));
+        assert_eq!(&times[Subscribing.index()..=Draining.index()], &[(1, 200), (1, 10_000), (1, 100)]);
+        assert_eq!(times[Backoff.index()].0, 2);
+    }
+
+    #[test]
+    fn test_raw_handlers_need_unshared_connections() {
+        let manager = WsManager::new(vec!["BTCUSDT".to_string()], Arc::new(|_| {}), WsConfig::default(), Arc::new(PerfStats::new()));
+        let running = manager.connections[0].clone();
+        assert!(manager.with_raw_handlers(|_| Box::new(|_| {})).is_err());
+        drop(running);
     }
 }
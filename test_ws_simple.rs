@@ -7,7 +7,7 @@ use tokio_tungstenite::connect_async;
 #[tokio::main]
 async fn main() {
     // Test with just 2 symbols
-    let symbols = vec!["btcusdt", "ethusdt"];
+    let symbols = ["btcusdt", "ethusdt"];
 
     // Create URL with bookTicker (correct case from official example)
     let streams: Vec<String> = symbols
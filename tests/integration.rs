@@ -0,0 +1,92 @@
+// End-to-end SHM test: temp file from ShmManager::create, ShmManager::open + init_slot,
+// then concurrent writer/reader threads hammering Quote64 to check for torn reads.
+
+use binance_futures_writer::shm::ShmManager;
+use binance_futures_writer::writer::WriterToken;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+const N_SOURCES: u64 = 2;
+const N_SYMBOLS: u64 = 8;
+const SOURCE_ID: u64 = 1;
+const ITERATIONS: i64 = 2_000_000;
+
+/// Create a temp SHM file through the public API
+fn create_temp_shm(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("it_{}_{}.dat", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    ShmManager::create(path.to_str().unwrap(), N_SOURCES, N_SYMBOLS, 0o600, None).unwrap();
+    path
+}
+
+#[test]
+fn test_open_init_and_write() {
+    let path = create_temp_shm("open_init");
+    let shm = Arc::new(ShmManager::open(path.to_str().unwrap()).unwrap());
+
+    shm.init_slot(SOURCE_ID, 3).unwrap();
+    assert!(shm.init_slot(N_SOURCES, 0).is_err());
+    assert!(shm.init_slot(SOURCE_ID, N_SYMBOLS).is_err());
+
+    let token = WriterToken::claim(shm.clone(), "test", SOURCE_ID, &[3]).unwrap();
+    token.writer(3).unwrap().write(100, 200, 300);
+    assert!(token.writer(4).is_err());
+    assert_eq!(shm.view(SOURCE_ID, 3).unwrap().read(), Some((SOURCE_ID, 3, 100, 200, 300)));
+    drop(token);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_concurrent_writer_reader_no_torn_reads() {
+    let path = create_temp_shm("concurrent");
+    let shm = Arc::new(ShmManager::open(path.to_str().unwrap()).unwrap());
+    let ids: Vec<u64> = (0..N_SYMBOLS).collect();
+    for &symbol_id in &ids {
+        shm.init_slot(SOURCE_ID, symbol_id).unwrap();
+    }
+    let token = WriterToken::claim(shm.clone(), "test", SOURCE_ID, &ids).unwrap();
+
+    let done = AtomicBool::new(false);
+    let consistent_reads = AtomicU64::new(0);
+
+    std::thread::scope(|s| {
+        // Writer: every record satisfies ask == -bid and ts == 2 * bid
+        s.spawn(|| {
+            for i in 1..=ITERATIONS {
+                let slot = token.writer((i as u64) % N_SYMBOLS).unwrap();
+                slot.write(i, -i, i * 2);
+            }
+            done.store(true, Ordering::Release);
+        });
+
+        for _ in 0..2 {
+            s.spawn(|| {
+                let mut symbol_id = 0;
+                while !done.load(Ordering::Acquire) {
+                    let slot = shm.view(SOURCE_ID, symbol_id).unwrap();
+                    if let Some((sid, sym, bid, ask, ts)) = slot.read() {
+                        assert_eq!(sid, SOURCE_ID);
+                        assert_eq!(sym, symbol_id);
+                        assert_eq!(ask, -bid, "torn read: bid={} ask={}", bid, ask);
+                        assert_eq!(ts, bid * 2, "torn read: bid={} ts={}", bid, ts);
+                        consistent_reads.fetch_add(1, Ordering::Relaxed);
+                    }
+                    symbol_id = (symbol_id + 1) % N_SYMBOLS;
+                }
+            });
+        }
+    });
+
+    assert!(consistent_reads.load(Ordering::Relaxed) > 0);
+
+    // Final state: the last write to each slot is visible
+    for symbol_id in 0..N_SYMBOLS {
+        let (_, _, bid, ask, _) = shm.view(SOURCE_ID, symbol_id).unwrap().read().unwrap();
+        assert_eq!(ask, -bid);
+        assert!(bid > ITERATIONS - N_SYMBOLS as i64);
+    }
+
+    std::fs::remove_file(path).unwrap();
+}
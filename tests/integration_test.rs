@@ -6,10 +6,46 @@ mod tests {
     // Note: Most unit tests are in the individual module files
     // This file is for integration tests that require multiple modules
 
+    use binance_futures_writer::app::{App, Config};
+    use binance_futures_writer::flags::Flag;
+    use binance_futures_writer::hooks::Hooks;
+    use binance_futures_writer::shm::ShmManager;
+
     #[test]
     fn test_placeholder() {
         // Integration tests would go here
         // They require actual SHM file and symbol files to be present
-        assert!(true);
+    }
+
+    #[test]
+    fn test_embedded_app_init() {
+        let dir = std::env::temp_dir().join(format!("embedded_app_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (symbols_tsv, subscribe_file, shm_path) =
+            (dir.join("symbols.tsv"), dir.join("subscribe.txt"), dir.join("quotes.dat"));
+        std::fs::write(&symbols_tsv, "1\tBTCUSDT\t0.10\n2\tETHUSDT\n").unwrap();
+        std::fs::write(&subscribe_file, "BTCUSDT\nETHUSDT scale=1e4\n").unwrap();
+        // Only this test in this binary touches the environment
+        std::env::set_var("SHM_PATH", &shm_path);
+        std::env::set_var("SHM_CREATE", "1");
+
+        let config = Config {
+            subscribe_file: subscribe_file.to_str().unwrap().to_string(),
+            symbols_tsv: symbols_tsv.to_str().unwrap().to_string(),
+            source_id: 2,
+            standalone: false,
+            ..Config::default()
+        };
+        let app = App::new(config).unwrap().with_hooks(Hooks::new().pre_write(|quote| quote.bid > 0));
+        assert!(app.flags().enabled(Flag::Recorder));
+        assert_eq!(app.universe().book_ticker(), ["BTCUSDT", "ETHUSDT"]);
+        assert_eq!(app.symbol_ids().get("ETHUSDT"), Some(&2));
+        // The file's scale is folded into the settings snapshot
+        assert!(app.settings().price.scale_overrides.iter().any(|s| s.symbol == "ETHUSDT" && s.decimals == 4));
+
+        let shm = ShmManager::open(shm_path.to_str().unwrap()).unwrap();
+        assert_eq!(shm.view(2, 2).unwrap().price_scale(), 10_000);
+        assert_eq!(shm.source_state(2).unwrap().writer_info().unwrap().source_name, "binance_futures");
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,71 @@
+// Loom model of the seqlock protocol used by Quote64: one writer, one reader,
+// all interleavings. Run with:
+//   cargo test --release --features loom --test loom_seqlock
+#![cfg(feature = "loom")]
+
+use binance_futures_writer::seqlock::{self, AtomicU64};
+use loom::sync::atomic::{AtomicI64, Ordering};
+use loom::sync::Arc;
+use loom::thread;
+
+/// Quote64 stand-in whose data fields are loom atomics, so loom can
+/// observe reorderings of the payload relative to seq
+struct ModelQuote {
+    seq: AtomicU64,
+    bid: AtomicI64,
+    ask: AtomicI64,
+}
+
+impl ModelQuote {
+    fn new() -> Self {
+        Self {
+            seq: AtomicU64::new(0),
+            bid: AtomicI64::new(0),
+            ask: AtomicI64::new(0),
+        }
+    }
+
+    fn write(&self, bid: i64, ask: i64) {
+        let seq0 = seqlock::write_begin(&self.seq);
+        self.bid.store(bid, Ordering::Relaxed);
+        self.ask.store(ask, Ordering::Relaxed);
+        seqlock::write_end(&self.seq, seq0);
+    }
+
+    /// Single read attempt (loom needs bounded loops)
+    fn try_read(&self) -> Option<(i64, i64)> {
+        let s1 = seqlock::read_begin(&self.seq)?;
+        let bid = self.bid.load(Ordering::Relaxed);
+        let ask = self.ask.load(Ordering::Relaxed);
+        if seqlock::read_end(&self.seq, s1) {
+            Some((bid, ask))
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn loom_seqlock_no_torn_reads() {
+    loom::model(|| {
+        let quote = Arc::new(ModelQuote::new());
+
+        let writer = {
+            let quote = quote.clone();
+            thread::spawn(move || {
+                quote.write(1, -1);
+                quote.write(2, -2);
+            })
+        };
+
+        // Invariant maintained by every write: ask == -bid
+        for _ in 0..2 {
+            if let Some((bid, ask)) = quote.try_read() {
+                assert_eq!(ask, -bid, "torn read: bid={} ask={}", bid, ask);
+            }
+        }
+
+        writer.join().unwrap();
+        assert_eq!(quote.try_read(), Some((2, -2)));
+    });
+}
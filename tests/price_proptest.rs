@@ -0,0 +1,132 @@
+// Property-based tests: parse_price_i64_1e8 vs an arbitrary-precision reference
+// (BigDecimal, round half-up to 8 decimals).
+
+use bigdecimal::{BigDecimal, RoundingMode, ToPrimitive};
+use binance_futures_writer::price::{self, parse_price_i64_1e8, parse_price_with, PriceParseOptions};
+use proptest::prelude::*;
+use std::str::FromStr;
+
+/// Reference conversion: round half-up to 8 decimals, scale by 1e8, None on i64 overflow
+fn reference_1e8(s: &str) -> Option<i64> {
+    reference_1e8_mode(s, RoundingMode::HalfUp)
+}
+
+fn reference_1e8_mode(s: &str, mode: RoundingMode) -> Option<i64> {
+    let value = BigDecimal::from_str(s).ok()?;
+    let scaled = value.with_scale_round(8, mode) * BigDecimal::from(100_000_000u64);
+    scaled.with_scale(0).to_i64()
+}
+
+/// Whether `s` may be compared: an unchecked-arith build only debug-asserts
+/// overflow, so out-of-range inputs panic instead of erroring
+fn comparable(s: &str) -> bool {
+    cfg!(not(feature = "unchecked-arith")) || reference_1e8(s).is_some()
+}
+
+/// Decimal strings as the venue sends them: digits, optional '.', optional fraction
+fn decimal_string() -> impl Strategy<Value = String> {
+    (0u64..=92_233_720_368, proptest::option::of("[0-9]{0,18}"))
+        .prop_map(|(int, frac)| match frac {
+            Some(frac) => format!("{}.{}", int, frac),
+            None => int.to_string(),
+        })
+}
+
+/// Scientific notation: mantissa with optional fraction and a signed exponent
+fn scientific_string() -> impl Strategy<Value = String> {
+    (0u64..1_000_000, "[0-9]{0,10}", -20i32..=8, prop::bool::ANY)
+        .prop_map(|(int, frac, exp, upper)| {
+            let e = if upper { 'E' } else { 'e' };
+            if frac.is_empty() {
+                format!("{}{}{}", int, e, exp)
+            } else {
+                format!("{}.{}{}{}", int, frac, e, exp)
+            }
+        })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(5_000))]
+
+    #[test]
+    fn prop_matches_reference(s in decimal_string()) {
+        prop_assume!(comparable(&s));
+        prop_assert_eq!(parse_price_i64_1e8(&s).ok(), reference_1e8(&s), "input: {}", s);
+    }
+
+    #[test]
+    fn prop_scientific_matches_reference(s in scientific_string()) {
+        prop_assume!(comparable(&s));
+        prop_assert_eq!(parse_price_i64_1e8(&s).ok(), reference_1e8(&s), "input: {}", s);
+    }
+
+    #[test]
+    fn prop_small_prices_match_reference(int in 0u64..1000, frac in "[0-9]{9,18}") {
+        let s = format!("{}.{}", int, frac);
+        prop_assert_eq!(parse_price_i64_1e8(&s).ok(), reference_1e8(&s), "input: {}", s);
+    }
+
+    #[test]
+    fn prop_rounding_modes_match_reference(s in decimal_string()) {
+        prop_assume!(comparable(&s));
+        for (ours, theirs) in [
+            (price::RoundingMode::HalfUp, RoundingMode::HalfUp),
+            (price::RoundingMode::HalfEven, RoundingMode::HalfEven),
+            (price::RoundingMode::Truncate, RoundingMode::Down),
+        ] {
+            let opts = PriceParseOptions { rounding: ours, ..Default::default() };
+            prop_assert_eq!(parse_price_with(&s, &opts).ok(), reference_1e8_mode(&s, theirs), "{:?}: {}", ours, s);
+        }
+    }
+
+    #[test]
+    fn prop_carry_across_integer_boundary(int in 0u64..1_000_000, tail in "[5-9][0-9]{0,6}") {
+        // x.99999999<5..9>... always rounds up to (x + 1).00000000
+        let s = format!("{}.99999999{}", int, tail);
+        prop_assert_eq!(parse_price_i64_1e8(&s).unwrap(), (int as i64 + 1) * 100_000_000, "input: {}", s);
+        prop_assert_eq!(parse_price_i64_1e8(&s).ok(), reference_1e8(&s));
+    }
+
+    #[test]
+    fn prop_rejects_garbage_anywhere(s in decimal_string(), pos in any::<prop::sample::Index>(), ch in "[a-dA-Df-zF-Z,_ +-]") {
+        let mut chars: Vec<char> = s.chars().collect();
+        let idx = pos.index(chars.len() + 1);
+        chars.insert(idx, ch.chars().next().unwrap());
+        let bad: String = chars.into_iter().collect();
+        // Leading/trailing whitespace is trimmed by the parser (e/E excluded: exponent notation)
+        prop_assume!(bad.trim() == bad);
+        // A single leading '+' is accepted in lenient mode
+        prop_assume!(!(idx == 0 && ch == "+"));
+        prop_assert!(parse_price_i64_1e8(&bad).is_err(), "accepted: {:?}", bad);
+    }
+
+    #[test]
+    fn prop_format_matches_reference(v in 0i64..=i64::MAX, decimals in 0u32..=8) {
+        let s = price::format_price_scaled(v, decimals);
+        prop_assert_eq!(BigDecimal::from_str(&s).unwrap(), BigDecimal::new(v.into(), decimals as i64));
+        prop_assert!(!s.ends_with('0') || !s.contains('.'), "trailing zero: {}", s);
+        prop_assert_eq!(price::parse_price_scaled(&s, decimals, &PriceParseOptions::default()).unwrap(), v);
+    }
+}
+
+#[test]
+fn test_carry_edge_cases() {
+    for (input, expected) in [
+        ("0.999999995", 100_000_000),
+        ("0.999999994", 99_999_999),
+        ("1.999999995", 200_000_000),
+        ("9.9999999950", 1_000_000_000),
+        ("99999.999999999", 10_000_000_000_000),
+    ] {
+        assert_eq!(parse_price_i64_1e8(input).unwrap(), expected, "input: {}", input);
+        assert_eq!(reference_1e8(input), Some(expected), "reference: {}", input);
+    }
+}
+
+#[test]
+#[cfg(not(feature = "unchecked-arith"))]
+fn test_overflow_matches_reference() {
+    for input in ["92233720368.54775807", "92233720368.54775808", "92233720369", "99999999999999999999"] {
+        assert_eq!(parse_price_i64_1e8(input).ok(), reference_1e8(input), "input: {}", input);
+    }
+}
@@ -0,0 +1,80 @@
+// Replay determinism: the same capture through the full decode and write path
+// twice must leave identical SHM records and stats.
+//
+// The capture (tests/fixtures/replay_capture.jsonl) mixes the cases each
+// stage decides on: a subscription ack (not a feed frame), an unsubscribed symbol (all-market
+// filter), malformed JSON, unparseable and overflowing prices, crossed and
+// zero-price quotes. Everything a frame decides must come from the frame:
+// only the local timestamp, the slot owner token and time-based stats
+// (parse and processing µs) may differ between runs, and those are masked out.
+//
+// One test per binary: App::new reads its settings from the environment.
+
+use binance_futures_writer::app::{App, Config};
+use binance_futures_writer::ws::PerfSnapshot;
+use std::path::Path;
+
+const CAPTURE: &str = include_str!("fixtures/replay_capture.jsonl");
+const SOURCE_ID: u64 = 1;
+const HEADER_SIZE: usize = 4096;
+const RECORD_SIZE: usize = 64;
+/// Byte ranges of a record that hold the local clock and the writer's token
+const MASKED: [std::ops::Range<usize>; 2] = [40..48, 56..64];
+
+/// Replay the capture into a fresh SHM; returns (record bytes, stats)
+fn replay(dir: &Path, run: &str) -> (Vec<u8>, PerfSnapshot) {
+    let shm_path = dir.join(format!("quotes_{}.dat", run));
+    std::env::set_var("SHM_PATH", &shm_path);
+
+    let config = Config {
+        subscribe_file: dir.join("subscribe.txt").to_str().unwrap().to_string(),
+        symbols_tsv: dir.join("symbols.tsv").to_str().unwrap().to_string(),
+        source_id: SOURCE_ID,
+        standalone: false,
+        ..Config::default()
+    };
+    let app = App::new(config).unwrap();
+    let mut stats = app.replay(CAPTURE.lines());
+
+    // Timing, not decisions
+    stats.max_proc_us = 0;
+    stats.proc_us_total = 0;
+    stats.max_parse_us = 0;
+    stats.parse_us_total = 0;
+    stats.slow_buckets.clear();
+
+    let mut records = std::fs::read(&shm_path).unwrap().split_off(HEADER_SIZE);
+    for record in records.chunks_mut(RECORD_SIZE) {
+        for range in MASKED {
+            record[range].fill(0);
+        }
+    }
+    (records, stats)
+}
+
+#[test]
+fn test_replay_is_deterministic() {
+    let dir = std::env::temp_dir().join(format!("replay_determinism_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("symbols.tsv"), "0\tBTCUSDT\n1\tETHUSDT\n2\tSOLUSDT\n3\tDOGEUSDT\n4\tXRPUSDT\n").unwrap();
+    std::fs::write(dir.join("subscribe.txt"), "BTCUSDT\nETHUSDT\nSOLUSDT\nDOGEUSDT\n").unwrap();
+    std::env::set_var("SHM_CREATE", "1");
+    std::env::set_var("WS_ALL_MARKET", "1");
+
+    let (first_records, first_stats) = replay(&dir, "a");
+    let (second_records, second_stats) = replay(&dir, "b");
+
+    // The capture exercised every stage it is meant to
+    assert_eq!(first_stats.frames_received, 20);
+    assert_eq!(first_stats.total_messages, 16);
+    assert_eq!(first_stats.filtered_messages, 1);
+    assert_eq!(first_stats.parse_errors, 1);
+    assert_eq!(first_stats.price_errors, 2);
+    assert_eq!(first_stats.unaccounted(), 0);
+    assert!(first_records.iter().any(|&b| b != 0));
+
+    assert_eq!(first_stats, second_stats);
+    assert!(first_records == second_records, "SHM records differ between identical replays");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}